@@ -0,0 +1,58 @@
+//! Compile-time helpers for `image_previewer`'s macros
+//!
+//! `embed_icons!{"assets/icons2/*.svg"}` needs the files a glob matches while
+//! compiling, which `macro_rules!` can't list, so the expansion is done here.
+//! Use the `embed_icons!` macro rather than calling this crate directly.
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+/// `[(name, content), ...]` for the files matching a glob relative to the
+/// calling crate's root, each named by its file stem and embedded with
+/// `include_str!` so editing one rebuilds the caller
+#[proc_macro]
+pub fn icon_files(input: TokenStream) -> TokenStream {
+    match expand(input) {
+        Ok(tokens) => tokens,
+        Err(message) => format!("compile_error!({:?})", message).parse().unwrap(),
+    }
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream, String> {
+    let pattern = pattern_literal(input)?;
+    let root = std::env::var("CARGO_MANIFEST_DIR").map_err(|_| "CARGO_MANIFEST_DIR isn't set".to_string())?;
+    let full = format!("{}/{}", glob::Pattern::escape(&root), pattern);
+    let paths = glob::glob(&full).map_err(|e| format!("Invalid icon pattern {:?}: {}", pattern, e))?;
+    let mut icons = Vec::new();
+    for path in paths {
+        let path = path.map_err(|e| format!("Can't read {}: {}", e.path().display(), e.error()))?;
+        if !path.is_file() {
+            continue;
+        }
+        let name = path.file_stem().and_then(|stem| stem.to_str()).ok_or_else(|| format!("{} has no usable icon name", path.display()))?;
+        let path = path.to_str().ok_or_else(|| format!("{} isn't valid UTF-8", path.display()))?;
+        icons.push(format!("({:?}, include_str!({:?}))", name, path));
+    }
+    if icons.is_empty() {
+        return Err(format!("No icon files match {:?}", pattern));
+    }
+    Ok(format!("[{}]", icons.join(", ")).parse().unwrap())
+}
+
+/// The text of the single plain string literal in `input`
+fn pattern_literal(input: TokenStream) -> Result<String, String> {
+    let mut tokens: Vec<TokenTree> = input.into_iter().collect();
+    // A `$pattern:literal` passed on by `macro_rules!` arrives in an invisible group
+    if let [TokenTree::Group(group)] = tokens.as_slice()
+        && group.delimiter() == Delimiter::None {
+        tokens = group.stream().into_iter().collect();
+    }
+    let [TokenTree::Literal(literal)] = tokens.as_slice() else {
+        return Err("Expected one string literal, such as \"assets/icons/*.svg\"".to_string());
+    };
+    let text = literal.to_string();
+    match text.strip_prefix('"').and_then(|text| text.strip_suffix('"')) {
+        // Escapes would need unescaping; paths and globs don't need them
+        Some(pattern) if !pattern.contains('\\') => Ok(pattern.to_string()),
+        _ => Err(format!("Expected a plain string literal without escapes, got {}", text)),
+    }
+}
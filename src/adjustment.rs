@@ -0,0 +1,85 @@
+//! Brightness, contrast and saturation adjustments
+//!
+//! Like rotations and redactions, adjustments are edits in the undo history,
+//! replayed on top of the decoded image. Each amount is a percentage from
+//! -100 to 100, zero leaving the image as it is.
+
+use eframe::egui::{Color32, ColorImage};
+
+pub const MIN_AMOUNT: i32 = -100;
+pub const MAX_AMOUNT: i32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Adjustment {
+    pub brightness: i32,
+    pub contrast: i32,
+    pub saturation: i32,
+}
+
+impl Adjustment {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn label(&self) -> String {
+        let amounts: Vec<String> = [("brightness", self.brightness), ("contrast", self.contrast), ("saturation", self.saturation)]
+            .into_iter()
+            .filter(|(_, amount)| *amount != 0)
+            .map(|(name, amount)| format!("{} {:+}", name, amount))
+            .collect();
+        if amounts.is_empty() {
+            "Adjust".to_string()
+        } else {
+            format!("Adjust {}", amounts.join(", "))
+        }
+    }
+
+    pub fn apply(&self, image: &mut ColorImage) {
+        if self.is_identity() {
+            return;
+        }
+        let amount = |value: i32| value.clamp(MIN_AMOUNT, MAX_AMOUNT) as f32 / 100.0;
+        let offset = amount(self.brightness) * 255.0;
+        let contrast = 1.0 + amount(self.contrast);
+        let saturation = 1.0 + amount(self.saturation);
+        for pixel in &mut image.pixels {
+            let [r, g, b, a] = pixel.to_srgba_unmultiplied();
+            let [r, g, b] = [r, g, b].map(|channel| (channel as f32 - 128.0) * contrast + 128.0 + offset);
+            let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+            let [r, g, b] = [r, g, b].map(|channel| (luma + (channel - luma) * saturation).round().clamp(0.0, 255.0) as u8);
+            *pixel = Color32::from_rgba_unmultiplied(r, g, b, a);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adjustments() {
+        let gray = Color32::from_rgb(100, 100, 100);
+        let mut image = ColorImage::new([2, 1], gray);
+        image.pixels[1] = Color32::from_rgb(200, 50, 50);
+
+        let mut unchanged = image.clone();
+        Adjustment::default().apply(&mut unchanged);
+        assert_eq!(unchanged, image);
+
+        let mut brighter = image.clone();
+        Adjustment { brightness: 20, ..Default::default() }.apply(&mut brighter);
+        assert_eq!(brighter.pixels[0], Color32::from_rgb(151, 151, 151));
+
+        let mut desaturated = image.clone();
+        Adjustment { saturation: -100, ..Default::default() }.apply(&mut desaturated);
+        let [r, g, b, _] = desaturated.pixels[1].to_array();
+        assert!(r == g && g == b);
+        assert_eq!(desaturated.pixels[0], gray, "grays have no saturation to lose");
+
+        let mut flat = image.clone();
+        Adjustment { contrast: -100, ..Default::default() }.apply(&mut flat);
+        assert_eq!(flat.pixels, vec![Color32::from_rgb(128, 128, 128); 2]);
+
+        assert_eq!(Adjustment { brightness: 20, contrast: -5, saturation: 0 }.label(), "Adjust brightness +20, contrast -5");
+    }
+}
@@ -0,0 +1,75 @@
+//! Rectangles drawn on an image to point something out
+//!
+//! Annotations are edits in the undo history, drawn on top of the decoded
+//! image, so exports and prints include them. Like redactions, their regions
+//! and line widths are kept in the pixels of the original file.
+
+use eframe::egui::{Color32, ColorImage};
+
+use crate::color_stats::PixelRect;
+
+pub const DEFAULT_WIDTH: usize = 4;
+pub const MAX_WIDTH: usize = 64;
+pub const DEFAULT_COLOR: Color32 = Color32::from_rgb(255, 40, 40);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Annotation {
+    /// Outer edge of the rectangle
+    pub region: PixelRect,
+    pub color: Color32,
+    /// Line width
+    pub width: usize,
+}
+
+impl Annotation {
+    /// A rectangle around `selection`, in an image shown at `scale` times the
+    /// size of the original
+    pub fn of_selection(selection: PixelRect, color: Color32, width: usize, scale: f64) -> Self {
+        let region = selection.scaled(1.0 / scale, [usize::MAX, usize::MAX]).unwrap_or(selection);
+        let width = ((width as f64 / scale).round() as usize).max(1);
+        Self { region, color, width }
+    }
+
+    /// Draw the rectangle in `image`, which is shown at `scale` times the size
+    /// of the original; lines are at least one pixel wide
+    pub fn apply(&self, image: &mut ColorImage, scale: f64) {
+        let Some(region) = self.region.scaled(scale, image.size) else {
+            return;
+        };
+        let line = ((self.width.min(MAX_WIDTH) as f64 * scale).round() as usize).max(1);
+        let width = image.size[0];
+        for y in region.min_y..region.max_y {
+            let on_edge_row = y < region.min_y + line || y + line >= region.max_y;
+            for x in region.min_x..region.max_x {
+                if on_edge_row || x < region.min_x + line || x + line >= region.max_x {
+                    let pixel = &mut image.pixels[y * width + x];
+                    *pixel = pixel.blend(self.color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rectangles_outline_their_region() {
+        let mut image = ColorImage::new([8, 8], Color32::WHITE);
+        let region = PixelRect { min_x: 1, min_y: 1, max_x: 7, max_y: 7 };
+        Annotation { region, color: Color32::RED, width: 1 }.apply(&mut image, 1.0);
+        assert_eq!(image.pixels[8 + 1], Color32::RED);
+        assert_eq!(image.pixels[6 * 8 + 6], Color32::RED);
+        assert_eq!(image.pixels[3 * 8 + 3], Color32::WHITE, "the inside is left alone");
+        assert_eq!(image.pixels[0], Color32::WHITE, "so is the outside");
+
+        // Drawn on an image shown at half size, kept in the original's pixels
+        let annotation = Annotation::of_selection(PixelRect { min_x: 1, min_y: 1, max_x: 3, max_y: 3 }, Color32::BLUE, 1, 0.5);
+        assert_eq!(annotation.region, PixelRect { min_x: 2, min_y: 2, max_x: 6, max_y: 6 });
+        assert_eq!(annotation.width, 2);
+        let mut small = ColorImage::new([2, 2], Color32::WHITE);
+        annotation.apply(&mut small, 0.25);
+        assert_eq!(small.pixels[3], Color32::BLUE);
+    }
+}
@@ -1,57 +1,620 @@
 //! Main application UI and logic
 
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 use eframe::egui;
-use egui::TextureHandle;
+use egui::{ColorImage, TextureHandle};
 use glob::glob;
 
-use crate::settings::ImageLoadingSettings;
-use crate::benchmark::{PerformanceProfile, SystemPerformanceCategory, run_simple_cpu_benchmark};
-use crate::file_locality::FileInfo;
-use crate::image_processing::{should_skip_large_file, load_svg_image, load_raster_image, estimate_image_render_time};
-use crate::icons::IconRenderer;
+use crate::settings::{
+    ImageLoadingSettings, MAX_LARGE_IMAGE_DIMENSION, MAX_UI_SCALE, MIN_LARGE_IMAGE_DIMENSION, MIN_UI_SCALE, ResizeFilter, SvgColorMapping,
+};
+use crate::preferences::Preferences;
+use crate::benchmark::{HardwareFingerprint, PerformanceProfile, SystemPerformanceCategory, run_simple_cpu_benchmark};
+use crate::file_locality::{FileInfo, FileLocalityStatus, HydrationWatch, LocalityRefresh, LocalityUpdate};
+use crate::image_processing::{should_skip_large_file, decode_svg_image, read_svg_source, recolor_svg, render_svg_preview, render_svg_region, SVG_PREVIEW_SIZE, svg_intrinsic_size, export_svg_as_png, SvgExportOptions, MAX_SVG_EXPORT_SIZE, decode_raster_image, decode, cached_render_time, timed_out};
+use crate::egui_adapter::{SMOOTH_TEXTURE, from_rgba_image};
+use crate::color_profile::ColorProfile;
+use crate::color_stats::{PixelRect, RegionColorStats, compute_region_stats, css_hex, css_rgba, css_linear_gradient};
+use crate::metadata::{ImageMetadata, read_header, read_image_metadata};
+use crate::metadata_providers::{MetadataRegistry, MetadataSection, MetadataSource};
+use crate::print_size::{PrintTarget, PrintUnit, print_size, effective_dpi, required_pixels};
+use crate::icons::{HOT_RELOAD_INTERVAL, IconDirectory, IconRenderer, Icons};
+use crate::network_cost::{MeteredConnection, NetworkCostMonitor};
+use crate::power::{BackgroundWorkBudget, PowerMonitor, PowerSource};
+use crate::jobs::{JobEngine, JobEvent, JobId, JobPriority};
+use crate::idle::IdleDetector;
+use crate::keybindings::{Action, KeyBindings, MOUSE_SHORTCUTS, navigation_target, next_matching};
+use crate::share::{ShareRequest, share_targets};
+use crate::bandwidth::{BandwidthEstimator, format_estimate};
+use crate::view_state::ViewStates;
+use crate::mouse_gestures::{Gesture, GestureTracker, zoom_about};
+use crate::backdrop::{Backdrop, PreviewBackground, automatic_backdrop, border_color, checkerboard_shapes, resolve_backdrop};
+use crate::svg_source::{SvgSource, XmlHighlighter};
+use crate::sharp_svg::{Region, SETTLE_DELAY, SharpSvg, visible_region};
+use crate::download_queue::{DownloadQueue, DownloadEvent, summarize_on_demand};
+use crate::progress::{OperationKind, ProgressEvent, ProgressHub};
+use crate::cache::{CacheRegistry, SharedCache};
+use crate::favorites::Favorites;
+use crate::file_selection::{FileSelection, SelectionSummary};
+use crate::soft_proof::{OUTPUT_PROFILES, ProofTransform, SoftProofOptions};
+use crate::diagnostics::{DecodeError, diagnostics_dir, open_with_system, save_error_report};
+use crate::status_bar::{MemoryMonitor, StatusInfo, format_size};
+use crate::theme::{Theme, ThemePreset};
+use crate::slideshow::{ReadAhead, SkipRules, SlideFacts, SlideshowController};
+use crate::fit_mode::{FitMode, OpeningZoom, is_icon, next_zoom_step};
+use crate::toolbar::ToolbarButton;
+use crate::repaint::{RepaintReason, RepaintScheduler};
+use crate::file_rows::{FileRow, FileRowCache};
+use crate::frame_sequence::{FrameDecoded, FrameSequence, SequencePlayer, find_sequences};
+use crate::hot_folder::{HotFolder, POLL_INTERVAL};
+use crate::redaction::{DEFAULT_STRENGTH, MAX_STRENGTH, MIN_STRENGTH, Redaction, RedactionStyle};
+use crate::adjustment::{self, Adjustment};
+use crate::annotation::{self, Annotation};
+use crate::history::{Command, FileOperation, History, ImageEdit};
+use crate::edit_sidecar;
+use crate::external_editor::{self, EditWatcher, ExternalEditor};
+use crate::picture_frame::{self, PictureFrame};
+use crate::preview_store::{self, PreviewStore};
+use crate::shell_thumbnail;
+use crate::prompt_policy::{Prompt, PromptPolicies, Silence};
+use crate::toast::Toast;
+use crate::folder_pane::{self, FolderPane};
+use crate::culling::{CullingSession, Decision, REJECTS_FOLDER};
+use crate::stacks::{self, Stack, find_stacks};
+use crate::pixel_grid;
+use crate::navigator;
+use crate::sharpness::{SharpnessUpdate, measure_file};
+use crate::triage::{QUARANTINE_FOLDER, UnreadableImage, check_image};
+use crate::checksums::{ChecksumUpdate, FileChecksums, Verification, checksum_file, read_manifests};
+use crate::panorama::{MAX_SCROLL_SPEED, MIN_SCROLL_SPEED, PanoramaScroll};
+use crate::captions::{CaptionIndex, matches_query};
+use crate::crash;
+use crate::{archive, video};
+use crate::duplicates::{DEFAULT_MAX_DISTANCE, HashedImage, group_duplicates, groups_left_empty, hash_file};
+use crate::compare::{CompareMode, DiffStats, difference_image};
+use crate::search_index::{self, FolderRecords, IndexedImage, SearchIndex};
+use crate::xmp_sidecar::{ColorLabel, MAX_RATING, SidecarIndex, normalize_keyword, stars_text, write_keywords, write_rating};
+use crate::print::{PRINT_DPI, PageLayout, Paper, PrintScaling, list_printers, print_page, render_page};
+use crate::session::{SessionRecorder, SessionState};
+use crate::tuning::{self, TunedDefaults};
+use crate::view_export::{ViewSnapshot, export_view};
+use crate::deep_zoom::{DeepZoomImage, DeepZoomSession, DeepZoomView, TileId};
+#[cfg(feature = "graph")]
+use crate::onedrive::graph::{DeviceCodePrompt, DriveItem, GraphSession, GraphTaskResult, ThumbnailSize, drive_path_for_local_file};
+
+/// UI state for the OneDrive (Microsoft Graph) browser window
+#[cfg(feature = "graph")]
+#[derive(Default)]
+pub(crate) struct GraphBrowserState {
+    pub(crate) show_window: bool,
+    pub(crate) client_id: String,
+    pub(crate) session: GraphSession,
+    pub(crate) login_prompt: Option<DeviceCodePrompt>,
+    pub(crate) current_folder: String,
+    pub(crate) items: Vec<DriveItem>,
+    pub(crate) thumbnail: Option<TextureHandle>,
+    pub(crate) error: Option<String>,
+}
+
+/// What the view shows for a selected file that has to be downloaded first
+pub(crate) struct OnlinePlaceholder {
+    pub(crate) file: FileInfo,
+    /// What the file looks like, with where that came from
+    pub(crate) preview: Option<(TextureHandle, &'static str)>,
+}
+
+/// State of the "Export SVG as PNG" dialog
+pub(crate) struct SvgExportDialog {
+    pub(crate) source: PathBuf,
+    pub(crate) intrinsic_size: (f32, f32),
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) keep_aspect: bool,
+    pub(crate) transparent: bool,
+    pub(crate) background: egui::Color32,
+    pub(crate) output_path: String,
+}
+
+impl SvgExportDialog {
+    fn set_scale(&mut self, scale: f32) {
+        self.width = ((self.intrinsic_size.0 * scale).round() as u32).clamp(1, MAX_SVG_EXPORT_SIZE);
+        self.height = ((self.intrinsic_size.1 * scale).round() as u32).clamp(1, MAX_SVG_EXPORT_SIZE);
+    }
+
+    fn scale(&self) -> f32 {
+        self.width as f32 / self.intrinsic_size.0.max(1.0)
+    }
+}
+
+/// State of the "Export View" dialog
+pub(crate) struct ViewExportDialog {
+    pub(crate) view: ViewSnapshot,
+    pub(crate) image: Arc<ColorImage>,
+    /// Profile of the source file; the view is converted to sRGB with it
+    pub(crate) profile: Option<ColorProfile>,
+    /// Multiple of the screen's resolution
+    pub(crate) factor: f32,
+    pub(crate) output_path: String,
+}
+
+/// State of the "Print" dialog
+pub(crate) struct PrintDialog {
+    pub(crate) image: Arc<ColorImage>,
+    pub(crate) texture: TextureHandle,
+    pub(crate) layout: PageLayout,
+    pub(crate) printers: Vec<String>,
+    /// The installed printers, while they are still being listed
+    pub(crate) printer_list: Option<std::sync::mpsc::Receiver<Vec<String>>>,
+    /// None prints to the default printer
+    pub(crate) printer: Option<String>,
+    pub(crate) name: String,
+}
+
+/// A change to the star rating, color label or keywords of some images
+#[derive(Debug, Clone)]
+pub(crate) enum SidecarEdit {
+    Stars(i8),
+    Label(Option<ColorLabel>),
+    AddKeyword(String),
+    RemoveKeyword(String),
+}
+
+/// Two selected images shown against each other
+pub(crate) struct CompareView {
+    pub(crate) names: [String; 2],
+    pub(crate) images: [Arc<ColorImage>; 2],
+    pub(crate) textures: [TextureHandle; 2],
+    pub(crate) mode: CompareMode,
+    /// Position of the wipe divider across the image, 0-1
+    pub(crate) wipe: f32,
+    /// Smallest channel difference highlighted in the difference view
+    pub(crate) threshold: u8,
+    /// Recent heatmaps and statistics, with the threshold they were computed for, newest last
+    pub(crate) differences: Vec<(u8, TextureHandle, DiffStats)>,
+    /// Heatmap being computed on the job engine, for its threshold
+    pub(crate) computing: Option<(u8, std::sync::mpsc::Receiver<(ColorImage, DiffStats)>)>,
+}
+
+/// A decoded image, or why it couldn't be decoded
+type ImageLoad = Result<Arc<ColorImage>, DecodeError>;
+
+/// Original and recolored SVG preview thumbnails
+type SvgPreviewRender = Result<[ColorImage; 2], String>;
+
+/// Decoded images to compare, or why one couldn't be decoded
+type CompareLoad = Result<[(PathBuf, Arc<ColorImage>); 2], (PathBuf, String)>;
+
+/// Status and bytes on disk of each file checked in one hydration poll
+type HydrationPoll = Vec<(PathBuf, FileLocalityStatus, Option<u64>)>;
+
+/// State of the duplicate review window
+pub(crate) struct DuplicateReview {
+    pub(crate) images: Vec<HashedImage>,
+    pub(crate) max_distance: u32,
+    pub(crate) groups: Vec<Vec<HashedImage>>,
+    /// Copies chosen for deleting or moving
+    pub(crate) marked: HashSet<PathBuf>,
+    pub(crate) move_folder: String,
+    pub(crate) confirm_delete: bool,
+}
+
+impl DuplicateReview {
+    fn new(images: Vec<HashedImage>, folder: &std::path::Path) -> Self {
+        let mut review = Self {
+            images,
+            max_distance: DEFAULT_MAX_DISTANCE,
+            groups: Vec::new(),
+            marked: HashSet::new(),
+            move_folder: folder.join("duplicates").display().to_string(),
+            confirm_delete: false,
+        };
+        review.regroup();
+        review
+    }
+
+    fn regroup(&mut self) {
+        self.groups = group_duplicates(&self.images, self.max_distance);
+        let grouped: HashSet<&PathBuf> = self.groups.iter().flatten().map(|image| &image.path).collect();
+        self.marked.retain(|path| grouped.contains(path));
+    }
+
+    /// Mark every copy except the largest of each group
+    fn mark_all_but_largest(&mut self) {
+        self.marked = self.groups.iter().flat_map(|group| group.iter().skip(1)).map(|image| image.path.clone()).collect();
+    }
+}
+
+/// State of the unreadable image window
+#[derive(Default)]
+pub(crate) struct TriageReview {
+    /// Decode every pixel instead of only reading headers
+    pub(crate) full_decode: bool,
+    /// Number of files checked and the ones that failed, from a running scan
+    pub(crate) scan: Option<std::sync::mpsc::Receiver<(usize, Vec<UnreadableImage>)>>,
+    /// Files checked by the last scan; `None` before the first one
+    pub(crate) checked: Option<usize>,
+    pub(crate) unreadable: Vec<UnreadableImage>,
+    /// Files chosen for moving into quarantine
+    pub(crate) marked: HashSet<PathBuf>,
+    /// Folder the last scan checked, which the quarantine goes in
+    pub(crate) folder: PathBuf,
+}
 
 pub struct ImageViewerApp {
-    pub file_infos: Vec<FileInfo>,
-    pub selected_image_index: Option<usize>,
-    pub image_texture: Option<TextureHandle>,
-    pub status_text: String,
-    pub settings: ImageLoadingSettings,
-    pub show_settings: bool,
-    pub performance_profile: PerformanceProfile,
-    pub show_benchmark_window: bool,
-    pub benchmark_in_progress: bool,
-    pub benchmark_threshold_ms: f64,
-    pub run_benchmark_trigger: bool,
-    pub auto_benchmark_on_startup: bool,
+    /// Folder whose images are listed
+    pub(crate) current_dir: PathBuf,
+    pub(crate) file_infos: Vec<FileInfo>,
+    pub(crate) selected_image_index: Option<usize>,
+    /// Images selected for batch operations (Ctrl/Shift+click), including the displayed one
+    pub(crate) file_selection: FileSelection,
+    pub(crate) selection_summary: SelectionSummary,
+    pub(crate) image_texture: Option<TextureHandle>,
+    /// CPU-side pixels of the displayed image, kept for color sampling
+    pub(crate) current_image: Option<Arc<ColorImage>>,
+    /// Backdrop chosen for the displayed image in automatic background mode
+    pub(crate) auto_backdrop: Option<Backdrop>,
+    /// Solid color around the displayed image's edge, for the matte background
+    pub(crate) image_matte: Option<egui::Color32>,
+    // Region selection (image pixel coordinates)
+    pub(crate) selection: Option<PixelRect>,
+    /// Statistics for the selection and the image they were computed from,
+    /// kept until either changes
+    pub(crate) selection_stats: Option<(PixelRect, std::sync::Weak<ColorImage>, Option<RegionColorStats>)>,
+    /// Edits and file operations that can be undone
+    pub(crate) history: History,
+    /// The displayed image before the edits in the history
+    pub(crate) unedited_image: Option<Arc<ColorImage>>,
+    /// File the edits belong to and are saved beside; `None` for images
+    /// that can't have a sidecar, such as ones inside archives
+    pub(crate) edited_path: Option<PathBuf>,
+    pub(crate) show_history: bool,
+    /// Block size or blur radius for new redactions
+    pub(crate) redaction_strength: usize,
+    /// Color and line width for new rectangles
+    pub(crate) annotation_color: egui::Color32,
+    pub(crate) annotation_width: usize,
+    /// Adjustment being set up in the Adjust window, applied as one edit
+    pub(crate) adjustment: Option<Adjustment>,
+    /// Explorer registration being written or removed
+    #[cfg(windows)]
+    pub(crate) shell_registration: Option<std::sync::mpsc::Receiver<Result<String, String>>>,
+    /// Text being recognized in the displayed image
+    #[cfg(feature = "ocr")]
+    pub(crate) ocr_result: Option<std::sync::mpsc::Receiver<Result<String, String>>>,
+    /// Rhai scripts from the config directory, with their menu commands and hooks
+    #[cfg(feature = "scripting")]
+    pub(crate) scripts: Vec<crate::scripting::Script>,
+    #[cfg(feature = "scripting")]
+    pub(crate) script_updates: (std::sync::mpsc::Sender<crate::scripting::ScriptUpdate>, std::sync::mpsc::Receiver<crate::scripting::ScriptUpdate>),
+    #[cfg(feature = "scripting")]
+    pub(crate) script_log: Vec<String>,
+    #[cfg(feature = "scripting")]
+    pub(crate) show_script_log: bool,
+    pub(crate) selection_drag_start: Option<[f32; 2]>,
+    // Metadata panel
+    pub(crate) show_metadata_panel: bool,
+    pub(crate) current_metadata: Option<ImageMetadata>,
+    /// Sections the metadata providers found for the displayed image
+    pub(crate) metadata_sections: Vec<MetadataSection>,
+    pub(crate) metadata_providers: MetadataRegistry,
+    // SVG source panel
+    pub(crate) show_svg_source: bool,
+    pub(crate) svg_source: Option<SvgSource>,
+    pub(crate) svg_highlighter: XmlHighlighter,
+    pub(crate) svg_source_show_recolored: bool,
+    /// Screen-resolution render of the visible part of a zoomed-in SVG
+    pub(crate) sharp_svg: SharpSvg,
+    pub(crate) show_svg_export_dialog: bool,
+    pub(crate) svg_export: Option<SvgExportDialog>,
+    /// How the image was drawn in the last frame, for exporting the view
+    pub(crate) last_view: Option<ViewSnapshot>,
+    pub(crate) view_export: Option<ViewExportDialog>,
+    pub(crate) print_dialog: Option<PrintDialog>,
+    pub(crate) print_target: PrintTarget,
+    pub(crate) status_text: String,
+    pub(crate) settings: ImageLoadingSettings,
+    pub(crate) show_settings: bool,
+    pub(crate) performance_profile: PerformanceProfile,
+    pub(crate) show_benchmark_window: bool,
+    pub(crate) benchmark_in_progress: bool,
+    pub(crate) benchmark_threshold_ms: f64,
+    pub(crate) run_benchmark_trigger: bool,
+    pub(crate) auto_benchmark_on_startup: bool,
     // New fields for user confirmation dialog
-    pub show_slow_image_dialog: bool,
-    pub pending_slow_image_path: Option<PathBuf>,
-    pub pending_slow_image_estimated_time: f64,
+    pub(crate) show_slow_image_dialog: bool,
+    pub(crate) pending_slow_image_path: Option<PathBuf>,
+    pub(crate) pending_slow_image_estimated_time: f64,
     // File download-specific fields
-    pub show_download_dialog: bool,
-    pub pending_download_file: Option<FileInfo>,
+    pub(crate) show_download_dialog: bool,
+    pub(crate) pending_download_file: Option<FileInfo>,
+    /// When the download and slow-image warnings are skipped
+    pub(crate) prompt_policies: PromptPolicies,
+    /// What confirming the open warning silences
+    pub(crate) prompt_silence: Silence,
+    /// Note fading out over the view
+    pub(crate) toast: Option<Toast>,
+    /// Set while the window is a compact always-on-top frame around the image
+    pub(crate) picture_frame: Option<PictureFrame>,
+    /// Shown instead of the image while the selected file is online-only
+    pub(crate) online_placeholder: Option<OnlinePlaceholder>,
+    /// A video without a poster frame, and why it has none
+    pub(crate) video_placeholder: Option<(PathBuf, String)>,
+    /// Preview of the displayed synced file, saved once the thumbnail delay
+    /// has passed with the same image still shown
+    pub(crate) pending_preview: Option<(std::time::Instant, PathBuf, Arc<ColorImage>)>,
+    /// Previews saved for online-only files; None without a config directory
+    pub(crate) preview_store: Option<Arc<Mutex<PreviewStore>>>,
+    pub(crate) show_bulk_download_dialog: bool,
+    /// Progress of scans, downloads, benchmarks, jobs and exports
+    pub(crate) progress: ProgressHub,
+    pub(crate) download_queue: DownloadQueue,
+    #[cfg(feature = "graph")]
+    pub(crate) graph: GraphBrowserState,
+    pub(crate) power_monitor: PowerMonitor,
+    /// Whether the system reports the connection as metered
+    pub(crate) network_cost: NetworkCostMonitor,
+    /// When downloads paused by the machine sleeping resume
+    pub(crate) wake_recovery: Option<Instant>,
+    /// Frames requested by animations and background work
+    pub(crate) repaint: RepaintScheduler,
+    // Background jobs, with idle-only work gated by the idle detector
+    pub(crate) job_engine: JobEngine,
+    /// The queued or running integrity scan
+    pub(crate) integrity_scan: Option<JobId>,
+    pub(crate) idle_detector: IdleDetector,
+    pub(crate) locality_refresh: Option<LocalityRefresh>,
+    pub(crate) last_hydration_poll: Instant,
+    /// Statuses of the downloading files, checked in the background
+    pub(crate) hydration_poll: Option<std::sync::mpsc::Receiver<HydrationPoll>>,
+    pub(crate) hydration_watch: HydrationWatch,
+    pub(crate) keybindings: KeyBindings,
+    pub(crate) show_shortcut_help: bool,
+    /// Next click on the image samples the SVG recolor target color
+    pub(crate) eyedropper_active: bool,
+    /// Color picked for adding to the colors SVG recoloring keeps
+    pub(crate) preserved_color_input: [u8; 3],
+    /// Before/after thumbnails of the displayed SVG in the settings window,
+    /// keyed by the path and recolor settings they were rendered for
+    pub(crate) svg_preview: Option<(String, Option<[TextureHandle; 2]>)>,
+    /// The displayed SVG being rendered on the job engine, with its cache key
+    pub(crate) svg_load: Option<(PathBuf, String, std::sync::mpsc::Receiver<ImageLoad>)>,
+    /// Key the preview should be rendered for, since when it was wanted, and
+    /// the render once it has started on the job engine
+    pub(crate) svg_preview_request: Option<(String, Instant, Option<std::sync::mpsc::Receiver<SvgPreviewRender>>)>,
+    pub(crate) gesture_tracker: GestureTracker,
+    pub(crate) swipe_tracker: GestureTracker,
+    // Zoom relative to the fit/100% size, and offset of the image center from the view center
+    pub(crate) zoom_factor: f32,
+    pub(crate) pan_offset: egui::Vec2,
+    /// Fit used for the displayed image, after resolving `FitMode::Auto`
+    pub(crate) current_fit: Option<FitMode>,
+    /// Fit kept for every image while the lock is on
+    pub(crate) locked_fit: Option<FitMode>,
+    /// Start a newly shown image that overflows the view at its top/left edge
+    pub(crate) align_to_start: bool,
+    pub(crate) panorama_scroll: PanoramaScroll,
+    pub(crate) bandwidth: BandwidthEstimator,
+    pub(crate) view_states: ViewStates,
+    /// File list rows worked out so far
+    pub(crate) file_rows: FileRowCache,
     // Icon renderer
-    pub icon_renderer: IconRenderer,
+    pub(crate) icon_renderer: IconRenderer,
+    /// Every cache, listed in the Caches settings section
+    pub(crate) caches: CacheRegistry,
+    /// Decoded pixels and uploaded textures of recently viewed images
+    pub(crate) decoded_cache: SharedCache<String, Arc<ColorImage>>,
+    pub(crate) texture_cache: SharedCache<String, TextureHandle>,
+    /// Cloud thumbnails by drive item id
+    #[cfg(feature = "graph")]
+    pub(crate) thumbnail_cache: SharedCache<String, TextureHandle>,
+    /// Pinned folders and images shown above the image list
+    pub(crate) favorites: Favorites,
+    /// Whether each favorite is a folder, or None if it's gone, checked again
+    /// every `FAVORITES_RECHECK` or when the window regains focus
+    pub(crate) favorite_kinds: HashMap<PathBuf, Option<bool>>,
+    pub(crate) favorites_checked: Instant,
+    pub(crate) folder_path_input: String,
+    /// Image list filter, matched against file names and caption sidecars
+    pub(crate) filter_text: String,
+    pub(crate) caption_index: CaptionIndex,
+    /// Star ratings, color labels and keywords from XMP sidecars
+    pub(crate) sidecars: SidecarIndex,
+    /// Only list images tagged with all of these
+    pub(crate) tag_filter: Vec<String>,
+    /// Text of the tag editor's input field
+    pub(crate) tag_input: String,
+    /// Only list images rated at least this many stars
+    pub(crate) min_rating: i8,
+    /// Only list images with this color label
+    pub(crate) label_filter: Option<ColorLabel>,
+    /// List the highest rated images first
+    pub(crate) sort_by_rating: bool,
+    /// Only list images flagged as possibly blurry
+    pub(crate) only_blurry: bool,
+    /// Soft-proofed version of the displayed image and the options it was made with
+    pub(crate) proof_texture: Option<(SoftProofOptions, TextureHandle)>,
+    /// Soft proof being made on the job engine, and the options it's made with
+    pub(crate) proof_render: Option<(SoftProofOptions, std::sync::mpsc::Receiver<ColorImage>)>,
+    /// Diagnostics report saved for the last image that failed to decode
+    pub(crate) last_error_report: Option<PathBuf>,
+    // Bottom status bar
+    pub(crate) status_info: StatusInfo,
+    pub(crate) memory_monitor: MemoryMonitor,
+    /// Screen pixels per image pixel for the displayed image
+    pub(crate) display_scale: Option<f32>,
+    /// Theme last passed to egui, so visuals are only rebuilt when it changes
+    pub(crate) applied_theme: Option<Theme>,
+    /// Interface scale last passed to egui
+    pub(crate) applied_ui_scale: Option<f32>,
+    /// Animation setting last passed to egui
+    pub(crate) applied_animations: Option<bool>,
+    /// Settings tuned for this machine, offered once after the first benchmark
+    pub(crate) suggested_tuning: Option<TunedDefaults>,
+    /// The scale slider is held; the scale is applied once it is released
+    pub(crate) ui_scale_dragging: bool,
+    pub(crate) slideshow: SlideshowController,
+    pub(crate) show_slideshow_log: bool,
+    /// Numbered image sequences in the current folder
+    pub(crate) frame_sequences: Vec<FrameSequence>,
+    pub(crate) sequence_player: Option<SequencePlayer>,
+    /// Frames decoded for the sequence player, by index into the folder's files
+    pub(crate) sequence_decodes: (std::sync::mpsc::Sender<FrameDecoded>, std::sync::mpsc::Receiver<FrameDecoded>),
+    /// Folder whose new images are shown as they arrive
+    pub(crate) hot_folder: Option<HotFolder>,
+    /// Programs offered by "Open in Editor", saved in the preferences
+    pub(crate) external_editors: Vec<ExternalEditor>,
+    /// Files opened in an editor, reloaded when it saves them
+    pub(crate) edit_watcher: EditWatcher,
+    /// An on-demand file downloading before it opens in the editor at that index
+    pub(crate) pending_edit: Option<(usize, PathBuf)>,
+    /// The selected on-demand file, downloading on the queue before it opens
+    pub(crate) open_after_download: Option<PathBuf>,
+    /// Second folder of the two-pane layout, to move images into
+    pub(crate) folder_pane: Option<FolderPane>,
+    /// Fullscreen keep/reject decisions, while culling
+    pub(crate) culling: Option<CullingSession>,
+    /// RAW+JPEG pairs and bursts in the current folder
+    pub(crate) stacks: Vec<Stack>,
+    /// RAW files in the current folder, which aren't listed, by the image of the same name
+    pub(crate) raw_companions: HashMap<PathBuf, Vec<PathBuf>>,
+    /// First images of the stacks listed in full
+    pub(crate) expanded_stacks: HashSet<PathBuf>,
+    /// Downloads made ahead of the slideshow in the current session
+    pub(crate) read_ahead: ReadAhead,
+    /// Images that failed to decode, from integrity scans and from loading them
+    pub(crate) decode_failures: Arc<Mutex<HashSet<PathBuf>>>,
+    /// Open DeepZoom/IIIF image, shown instead of the selected image
+    pub(crate) deep_zoom: Option<DeepZoomSession>,
+    pub(crate) compare: Option<CompareView>,
+    /// Images for the comparison being decoded on the job engine
+    pub(crate) compare_load: Option<std::sync::mpsc::Receiver<CompareLoad>>,
+    /// Hashes from a running duplicate scan's jobs, the number of jobs still
+    /// running, and the images hashed so far
+    pub(crate) duplicate_scan: Option<(std::sync::mpsc::Receiver<Vec<HashedImage>>, usize, Vec<HashedImage>)>,
+    pub(crate) duplicates: Option<DuplicateReview>,
+    pub(crate) triage: Option<TriageReview>,
+    /// ZIP and CBZ archives in the current folder, which open like folders
+    pub(crate) archives: Vec<PathBuf>,
+    /// Metadata of the images in previously visited folders
+    pub(crate) search_index: SearchIndex,
+    /// Indexing job for the current folder, cancelled when another folder is opened
+    pub(crate) search_indexing: Option<JobId>,
+    /// When the search index first changed since it was last saved
+    pub(crate) search_index_changed: Option<Instant>,
+    /// Save of the search index running on the job engine
+    pub(crate) search_index_saving: Option<std::sync::mpsc::Receiver<Result<(), String>>>,
+    /// Fresh records of a folder from the indexing job
+    pub(crate) search_updates: (std::sync::mpsc::Sender<FolderRecords>, std::sync::mpsc::Receiver<FolderRecords>),
+    /// Sharpness scores of local images, measured while idle
+    pub(crate) sharpness: HashMap<PathBuf, f32>,
+    pub(crate) sharpness_updates: (std::sync::mpsc::Sender<SharpnessUpdate>, std::sync::mpsc::Receiver<SharpnessUpdate>),
+    /// The queued or running sharpness measurement
+    pub(crate) sharpness_scan: Option<JobId>,
+    /// Checksums computed so far, with how they compare to the folder's manifests
+    pub(crate) checksums: HashMap<PathBuf, (FileChecksums, Verification)>,
+    pub(crate) checksum_updates: (std::sync::mpsc::Sender<ChecksumUpdate>, std::sync::mpsc::Receiver<ChecksumUpdate>),
+    pub(crate) show_search: bool,
+    pub(crate) show_icon_cache_stats: bool,
+    /// Folder path field shown on the toolbar
+    pub(crate) show_toolbar_folder_input: bool,
+    /// Image waiting for the user to confirm deleting it
+    pub(crate) confirm_delete_image: Option<PathBuf>,
+    /// File being renamed and the name typed so far
+    pub(crate) rename_target: Option<(PathBuf, String)>,
+    pub(crate) search_query: String,
+    /// Descriptor being fetched for the "Open Deep Zoom Image" dialog
+    pub(crate) deep_zoom_opening: Option<std::sync::mpsc::Receiver<(String, Result<DeepZoomImage, String>)>>,
+    pub(crate) show_deep_zoom_dialog: bool,
+    pub(crate) deep_zoom_input: String,
+    /// Tile textures by tile path or URL
+    pub(crate) deep_zoom_tiles: SharedCache<String, TextureHandle>,
+    /// Report written when the previous session crashed, offered until dismissed
+    pub(crate) crash_report: Option<PathBuf>,
+    pub(crate) session_recorder: SessionRecorder,
+    /// Session left by an unclean exit, offered for restoring until dismissed
+    pub(crate) recovered_session: Option<SessionState>,
 }
 
 impl Default for ImageViewerApp {
     fn default() -> Self {
-        let mut file_infos = vec![];
         let settings = ImageLoadingSettings::default();
-        for ext in settings.supported_formats.iter() {
-            if let Ok(paths) = glob(&format!("*.{}", ext)) {
-                for entry in paths.flatten() {
-                    file_infos.push(FileInfo::new(entry));
-                }
-            }
+        let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let file_infos = scan_folder(&current_dir, &settings);
+        let caption_index = CaptionIndex::scan(&current_dir, &file_infos);
+        let sidecars = SidecarIndex::scan(&current_dir, &file_infos);
+        let frame_sequences = find_sequences(&file_infos);
+        let raw_companions = stacks::raw_companions(&file_infos, stacks::raw_files(&current_dir));
+        let stacks = find_stacks(&file_infos, &raw_companions);
+
+        let power_monitor = PowerMonitor::new();
+        // Enough workers for the full budget; the current one limits how many run
+        let decode_threads = BackgroundWorkBudget::full().with_thread_limit(settings.max_decode_threads).decode_threads;
+        let idle_detector = IdleDetector::new(std::time::Duration::from_secs(settings.idle_delay_secs as u64));
+        let progress = ProgressHub::new();
+
+        let mut caches = CacheRegistry::default();
+        let texture_cache = caches.create("Textures", TEXTURE_CACHE_BYTES);
+        #[cfg(feature = "graph")]
+        let thumbnail_cache = caches.create("Cloud thumbnails", THUMBNAIL_CACHE_BYTES);
+        let icon_renderer = IconRenderer::new();
+        caches.register(icon_renderer.cache());
+        let decoded_cache = caches.create("Decoded images", DECODED_IMAGE_CACHE_BYTES);
+        let deep_zoom_tiles = caches.create("Deep zoom tiles", DEEP_ZOOM_TILE_CACHE_BYTES);
+        let preview_store = preview_store::previews_dir().map(|dir| Arc::new(Mutex::new(PreviewStore::open(dir, PREVIEW_STORE_BYTES))));
+        if let Some(store) = &preview_store {
+            caches.register(Arc::clone(store));
         }
 
         Self {
             file_infos,
             selected_image_index: None,
+            file_selection: FileSelection::default(),
+            selection_summary: SelectionSummary::default(),
             image_texture: None,
+            current_image: None,
+            history: History::default(),
+            unedited_image: None,
+            edited_path: None,
+            show_history: false,
+            redaction_strength: DEFAULT_STRENGTH,
+            annotation_color: annotation::DEFAULT_COLOR,
+            annotation_width: annotation::DEFAULT_WIDTH,
+            adjustment: None,
+            #[cfg(windows)]
+            shell_registration: None,
+            #[cfg(feature = "ocr")]
+            ocr_result: None,
+            #[cfg(feature = "scripting")]
+            scripts: Vec::new(),
+            #[cfg(feature = "scripting")]
+            script_updates: std::sync::mpsc::channel(),
+            #[cfg(feature = "scripting")]
+            script_log: Vec::new(),
+            #[cfg(feature = "scripting")]
+            show_script_log: false,
+            auto_backdrop: None,
+            image_matte: None,
+            selection: None,
+            selection_stats: None,
+            selection_drag_start: None,
+            show_metadata_panel: false,
+            current_metadata: None,
+            metadata_sections: Vec::new(),
+            metadata_providers: MetadataRegistry::default(),
+            show_svg_source: false,
+            svg_source: None,
+            svg_highlighter: XmlHighlighter::default(),
+            sharp_svg: SharpSvg::default(),
+            svg_source_show_recolored: false,
+            show_svg_export_dialog: false,
+            svg_export: None,
+            last_view: None,
+            view_export: None,
+            print_dialog: None,
+            print_target: PrintTarget::default(),
             status_text: "Select an image".to_string(),
             settings,
             show_settings: false,
@@ -66,24 +629,257 @@ impl Default for ImageViewerApp {
             pending_slow_image_estimated_time: 0.0,
             show_download_dialog: false,
             pending_download_file: None,
-            icon_renderer: IconRenderer::new(),
+            prompt_policies: PromptPolicies::default(),
+            prompt_silence: Silence::None,
+            toast: None,
+            picture_frame: None,
+            online_placeholder: None,
+            video_placeholder: None,
+            pending_preview: None,
+            preview_store,
+            show_bulk_download_dialog: false,
+            download_queue: DownloadQueue::with_progress(progress.sink()),
+            #[cfg(feature = "graph")]
+            graph: GraphBrowserState::default(),
+            power_monitor,
+            network_cost: NetworkCostMonitor::new(),
+            wake_recovery: None,
+            repaint: RepaintScheduler::default(),
+            job_engine: JobEngine::with_progress(decode_threads, progress.sink()),
+            integrity_scan: None,
+            idle_detector,
+            locality_refresh: None,
+            last_hydration_poll: Instant::now(),
+            hydration_poll: None,
+            hydration_watch: HydrationWatch::default(),
+            keybindings: KeyBindings::default(),
+            show_shortcut_help: false,
+            eyedropper_active: false,
+            preserved_color_input: [255, 255, 255],
+            svg_preview: None,
+            svg_preview_request: None,
+            svg_load: None,
+            gesture_tracker: GestureTracker::right_drag(),
+            swipe_tracker: GestureTracker::touch_swipe(),
+            zoom_factor: 1.0,
+            pan_offset: egui::Vec2::ZERO,
+            current_fit: None,
+            locked_fit: None,
+            align_to_start: true,
+            panorama_scroll: PanoramaScroll::default(),
+            bandwidth: BandwidthEstimator::new(),
+            view_states: ViewStates::default(),
+            file_rows: FileRowCache::default(),
+            icon_renderer,
+            caches,
+            decoded_cache,
+            texture_cache,
+            #[cfg(feature = "graph")]
+            thumbnail_cache,
+            progress,
+            favorites: Favorites::load(),
+            favorite_kinds: HashMap::new(),
+            favorites_checked: Instant::now(),
+            folder_path_input: String::new(),
+            filter_text: String::new(),
+            caption_index,
+            sidecars,
+            min_rating: 0,
+            label_filter: None,
+            only_blurry: false,
+            sort_by_rating: false,
+            tag_filter: Vec::new(),
+            tag_input: String::new(),
+            last_error_report: None,
+            status_info: StatusInfo::default(),
+            memory_monitor: MemoryMonitor::new(),
+            display_scale: None,
+            applied_theme: None,
+            applied_ui_scale: None,
+            applied_animations: None,
+            suggested_tuning: None,
+            ui_scale_dragging: false,
+            slideshow: SlideshowController::default(),
+            show_slideshow_log: false,
+            frame_sequences,
+            sequence_player: None,
+            sequence_decodes: std::sync::mpsc::channel(),
+            hot_folder: None,
+            external_editors: external_editor::default_editors(),
+            edit_watcher: EditWatcher::default(),
+            pending_edit: None,
+            open_after_download: None,
+            folder_pane: None,
+            culling: None,
+            stacks,
+            raw_companions,
+            expanded_stacks: HashSet::new(),
+            read_ahead: ReadAhead::default(),
+            decode_failures: Arc::new(Mutex::new(HashSet::new())),
+            deep_zoom: None,
+            compare: None,
+            compare_load: None,
+            duplicate_scan: None,
+            duplicates: None,
+            triage: None,
+            archives: archive::archives_in(&current_dir),
+            search_index: SearchIndex::load(),
+            search_indexing: None,
+            search_index_changed: None,
+            search_index_saving: None,
+            search_updates: std::sync::mpsc::channel(),
+            sharpness: HashMap::new(),
+            sharpness_updates: std::sync::mpsc::channel(),
+            sharpness_scan: None,
+            checksums: HashMap::new(),
+            checksum_updates: std::sync::mpsc::channel(),
+            show_search: false,
+            show_icon_cache_stats: false,
+            show_toolbar_folder_input: false,
+            confirm_delete_image: None,
+            rename_target: None,
+            search_query: String::new(),
+            deep_zoom_opening: None,
+            show_deep_zoom_dialog: false,
+            deep_zoom_input: String::new(),
+            deep_zoom_tiles,
+            crash_report: None,
+            session_recorder: SessionRecorder::default(),
+            recovered_session: None,
+            proof_texture: None,
+            proof_render: None,
+            current_dir,
         }
     }
 }
 
 impl eframe::App for ImageViewerApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        self.render_top_menu(ctx);
+        self.apply_theme(ctx);
+        self.apply_ui_scale(ctx);
+        self.apply_animations(ctx);
+        self.icon_renderer.set_show_text_labels(self.settings.show_status_text);
+        self.icon_renderer.set_dark_mode(ctx.style().visuals.dark_mode);
+        self.icon_renderer.set_hot_reload(self.settings.icon_hot_reload);
+        if self.icon_renderer.poll_hot_reload() {
+            self.repaint.request(RepaintReason::Background);
+        }
+        if self.settings.icon_hot_reload && self.icon_renderer.icon_directory().is_some() {
+            self.repaint.request_after(RepaintReason::Background, HOT_RELOAD_INTERVAL);
+        }
+        self.power_monitor.poll();
+        self.job_engine.set_worker_limit(self.background_budget().decode_threads);
+        self.save_pending_preview();
+        self.network_cost.poll();
+        self.repaint.set_max_fps(self.settings.battery_max_fps.filter(|_| self.power_monitor.source() == PowerSource::Battery));
+        self.memory_monitor.poll();
+        self.update_status_info();
+        crash::snapshot(&self.settings, &self.performance_profile);
+        if self.culling.is_none() && self.picture_frame.is_none() {
+            self.render_top_menu(ctx);
+            self.render_toolbar(ctx);
+            self.render_status_bar(ctx);
+        }
         self.render_settings_window(ctx);
         self.render_benchmark_window(ctx);
-        self.render_main_panel(ctx);
+        if self.culling.is_some() {
+            self.render_culling(ctx);
+        } else if self.picture_frame.is_some() {
+            self.render_picture_frame(ctx);
+        } else {
+            self.render_main_panel(ctx);
+        }
+        self.render_selection_window(ctx);
+        self.render_shortcut_help(ctx);
+        self.render_slideshow_log(ctx);
+        self.render_sequence_player(ctx);
+        self.render_history_window(ctx);
+        self.render_adjust_window(ctx);
+        self.render_toast(ctx);
         self.handle_keyboard_nav(ctx);
+        self.handle_mouse_nav(ctx);
         self.handle_benchmark_trigger(ctx);
         self.handle_dialogs(ctx);
+        self.process_download_events(ctx);
+        self.process_background_jobs(ctx);
+        self.process_locality_refresh();
+        self.process_progress_events();
+        self.process_slideshow(ctx);
+        self.process_sequence(ctx);
+        self.process_compare_load(ctx);
+        self.process_svg_load(ctx);
+        self.process_hot_folder(ctx);
+        self.process_external_edits(ctx);
+        self.process_checksum_results();
+        self.process_sharpness_results();
+        self.poll_hydrating_files();
+        self.record_session();
+        #[cfg(feature = "graph")]
+        {
+            self.render_graph_browser_window(ctx);
+            self.process_graph_results(ctx);
+        }
+        #[cfg(windows)]
+        self.process_shell_registration();
+        #[cfg(feature = "ocr")]
+        self.process_ocr_result(ctx);
+        #[cfg(feature = "scripting")]
+        {
+            self.render_script_log_window(ctx);
+            self.process_script_results(ctx);
+        }
+        self.repaint.flush(ctx);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.session_recorder.finish();
+        let unsaved = self.search_index.take_unsaved();
+        if !unsaved.is_empty() && let Err(e) = search_index::save_folders(&unsaved) {
+            eprintln!("Warning: {}", e);
+        }
     }
 }
 
 impl ImageViewerApp {
+    /// Create the app, identifying the GPU adapter from the render state, and
+    /// open `launch_path` (a file or folder) if one was given
+    pub fn new(cc: &eframe::CreationContext<'_>, launch_path: Option<PathBuf>) -> Self {
+        use eframe::glow::HasContext;
+
+        let gpu_adapter = cc.gl.as_ref().map(|gl| unsafe { gl.get_parameter_string(eframe::glow::RENDERER) });
+        let mut app = Self { performance_profile: PerformanceProfile::load(), ..Self::default() };
+        if app.performance_profile.set_hardware(HardwareFingerprint::detect(gpu_adapter)) {
+            app.status_text = "The hardware changed since the last benchmark, so its results were discarded".to_string();
+            if let Err(e) = app.performance_profile.save() {
+                eprintln!("Warning: {}", e);
+            }
+        }
+        let preferences = Preferences::load();
+        if let Some(scale) = preferences.get::<f32>("ui_scale") {
+            app.settings.ui_scale = scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+        }
+        tuning::load_into(&preferences, &mut app.settings);
+        app.external_editors = external_editor::load(&preferences);
+        app.prompt_policies = PromptPolicies::load(&preferences);
+        app.settings.load_svg_colors(&preferences);
+        if let Some(limit) = app.settings.max_decode_threads {
+            let decode_threads = BackgroundWorkBudget::full().with_thread_limit(Some(limit)).decode_threads;
+            app.job_engine = JobEngine::with_progress(decode_threads, app.progress.sink());
+        }
+        app.job_engine.set_worker_limit(app.background_budget().decode_threads);
+        app.crash_report = crash::take_pending_report();
+        app.recovered_session = app.session_recorder.take_unclean();
+        #[cfg(feature = "scripting")]
+        app.reload_scripts();
+        if let Some(path) = launch_path {
+            app.open_favorite(&cc.egui_ctx, &path);
+        } else {
+            app.index_folder();
+            app.measure_sharpness();
+        }
+        app
+    }
+
     /// Update the locality status of a file after it has been accessed/downloaded
     fn update_file_locality_status(&mut self, file_path: &PathBuf) {
         if let Some(file_info) = self.file_infos.iter_mut().find(|f| f.path == *file_path) {
@@ -100,27 +896,205 @@ impl ImageViewerApp {
     }
 
     /// Refresh locality status for all files (useful if OneDrive has synced files in background)
-    pub fn refresh_all_file_locality_status(&mut self) {
-        for file_info in &mut self.file_infos {
-            let new_status = crate::file_locality::get_file_locality_status(&file_info.path);
-            if file_info.locality_status != new_status {
-                // Clear estimated download size if the file is now local
-                let is_now_local = matches!(new_status, crate::file_locality::FileLocalityStatus::Local);
-                let is_now_on_demand = matches!(new_status, crate::file_locality::FileLocalityStatus::OnDemand);
-                file_info.locality_status = new_status;
-                if is_now_local {
-                    file_info.estimated_download_size = None;
-                } else if is_now_on_demand {
-                    // Re-calculate download size for on-demand files
-                    file_info.estimated_download_size = std::fs::metadata(&file_info.path).ok().map(|m| m.len());
+    ///
+    /// Runs in the background; results are applied by `process_locality_refresh`.
+    pub(crate) fn refresh_all_file_locality_status(&mut self) {
+        let paths = self.file_infos.iter().map(|f| f.path.clone()).collect();
+        // Replacing an in-progress refresh cancels it
+        self.locality_refresh = Some(LocalityRefresh::start(paths, &self.progress.sink()));
+    }
+
+    /// Re-check files the cloud provider is currently downloading so their progress stays current
+    ///
+    /// Checking can block on the provider, so it runs on a thread, one round at a time.
+    fn poll_hydrating_files(&mut self) {
+        if let Some(receiver) = &self.hydration_poll {
+            match receiver.try_recv() {
+                Ok(statuses) => {
+                    self.hydration_poll = None;
+                    let now = Instant::now();
+                    for (path, status, allocated) in statuses {
+                        let status = self.hydration_watch.observe(&path, status, allocated, now);
+                        if let Some(file_info) = self.file_infos.iter_mut().find(|f| f.path == path) {
+                            if status == FileLocalityStatus::Local {
+                                file_info.estimated_download_size = None;
+                            }
+                            file_info.locality_status = status;
+                        }
+                    }
+                }
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => self.hydration_poll = None,
+                Err(std::sync::mpsc::TryRecvError::Empty) => {}
+            }
+        }
+        let mut hydrating: Vec<PathBuf> = self.file_infos
+            .iter()
+            .filter(|f| matches!(f.locality_status, FileLocalityStatus::Hydrating { .. }))
+            .map(|f| f.path.clone())
+            .collect();
+        if hydrating.is_empty() && self.hydration_watch.watched().next().is_none() {
+            return;
+        }
+        if self.hydration_poll.is_none() && self.last_hydration_poll.elapsed() >= std::time::Duration::from_secs(1) {
+            self.last_hydration_poll = Instant::now();
+            // Stalled downloads are shown as on demand but still checked, as
+            // long as they are listed
+            let listed: HashSet<&std::path::Path> = self.file_infos.iter().map(|f| f.path.as_path()).collect();
+            self.hydration_watch.retain(|path| listed.contains(path));
+            for path in self.hydration_watch.watched() {
+                if !hydrating.contains(path) {
+                    hydrating.push(path.clone());
+                }
+            }
+            let (sender, receiver) = std::sync::mpsc::channel();
+            let spawned = std::thread::Builder::new()
+                .name("hydration-poll".to_string())
+                .spawn(move || {
+                    let statuses = hydrating
+                        .into_iter()
+                        .map(|path| {
+                            let status = crate::file_locality::get_file_locality_status(&path);
+                            let allocated = crate::file_locality::allocated_bytes(&path);
+                            (path, status, allocated)
+                        })
+                        .collect();
+                    let _ = sender.send(statuses);
+                });
+            self.hydration_poll = spawned.is_ok().then_some(receiver);
+        }
+        self.repaint.request_after(RepaintReason::Download, std::time::Duration::from_secs(1));
+    }
+
+    /// Apply statuses streamed back from a background locality refresh
+    fn process_locality_refresh(&mut self) {
+        let Some(refresh) = self.locality_refresh.as_mut() else { return };
+        let updates = refresh.poll();
+        let (_, total) = refresh.progress();
+        let finished = refresh.is_finished();
+
+        if !updates.is_empty() {
+            let index: HashMap<&PathBuf, usize> = self.file_infos
+                .iter()
+                .enumerate()
+                .map(|(i, f)| (&f.path, i))
+                .collect();
+            let positions: Vec<(usize, LocalityUpdate)> = updates
+                .into_iter()
+                .filter_map(|update| index.get(&update.path).map(|&i| (i, update)))
+                .collect();
+            for (i, update) in positions {
+                let file_info = &mut self.file_infos[i];
+                if file_info.locality_status != update.status {
+                    file_info.locality_status = update.status;
+                    file_info.estimated_download_size = update.estimated_download_size;
                 }
             }
         }
+
+        if finished {
+            self.locality_refresh = None;
+            self.update_selection_summary();
+            self.status_text = format!("File status refreshed for {} files", total);
+        } else {
+            self.repaint.request_after(RepaintReason::Background, std::time::Duration::from_millis(100));
+        }
     }
 
     fn render_top_menu(&mut self, ctx: &egui::Context) {
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    let svg_path = self.displayed_svg_path();
+                    let response = ui.add_enabled(svg_path.is_some(), egui::Button::new("Export SVG as PNG…"))
+                        .on_disabled_hover_text("Open an SVG first");
+                    if response.clicked() && let Some(path) = svg_path {
+                        self.open_svg_export_dialog(path);
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(self.image_texture.is_some() && self.current_image.is_some(), egui::Button::new("Print…"))
+                        .on_disabled_hover_text("Open an image first")
+                        .clicked() {
+                        self.open_print_dialog();
+                        ui.close_menu();
+                    }
+                    let can_export_view = self.last_view.is_some() && self.current_image.is_some();
+                    if ui.add_enabled(can_export_view, egui::Button::new("Export View as PNG…"))
+                        .on_hover_text("Save what's on screen, at the current zoom and pan")
+                        .on_disabled_hover_text("Open an image first")
+                        .clicked() {
+                        self.open_view_export_dialog();
+                        ui.close_menu();
+                    }
+                    #[cfg(feature = "ocr")]
+                    if ui.add_enabled(self.current_image.is_some() && self.ocr_result.is_none(), egui::Button::new("Copy Text from Image"))
+                        .on_hover_text("Recognize the text in the image, or in the selected region, and copy it")
+                        .on_disabled_hover_text("Open an image first")
+                        .clicked() {
+                        self.copy_text_from_image(self.selection);
+                        ui.close_menu();
+                    }
+                    let selected_path = self.selected_image_index.and_then(|i| self.file_infos.get(i)).map(|f| f.path.clone());
+                    if let Some(editor) = editor_menu(ui, &self.external_editors, selected_path.as_deref())
+                        && let Some(path) = selected_path {
+                        self.open_in_editor(editor, path);
+                    }
+                    if ui.button("Open Deep Zoom Image…")
+                        .on_hover_text("A .dzi file or IIIF info.json, as a path or URL")
+                        .clicked() {
+                        self.show_deep_zoom_dialog = true;
+                        ui.close_menu();
+                    }
+                    if self.deep_zoom.is_some() && ui.button("Close Deep Zoom Image").clicked() {
+                        self.deep_zoom = None;
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("Edit", |ui| {
+                    let undo = self.history.done().last().map(|command| format!("Undo {}", command.label()));
+                    if ui.add_enabled(undo.is_some(), egui::Button::new(undo.unwrap_or_else(|| "Undo".to_string()))
+                        .shortcut_text(ctx.format_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z))))
+                        .clicked() {
+                        self.undo(ctx);
+                        ui.close_menu();
+                    }
+                    let redo = self.history.undone().last().map(|command| format!("Redo {}", command.label()));
+                    if ui.add_enabled(redo.is_some(), egui::Button::new(redo.unwrap_or_else(|| "Redo".to_string()))
+                        .shortcut_text(ctx.format_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y))))
+                        .clicked() {
+                        self.redo(ctx);
+                        ui.close_menu();
+                    }
+                    if ui.button("History…").clicked() {
+                        self.show_history = true;
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.add_enabled(self.current_image.is_some(), egui::Button::new("Adjust…"))
+                        .on_hover_text("Change the brightness, contrast and saturation")
+                        .clicked() {
+                        self.adjustment.get_or_insert_default();
+                        ui.close_menu();
+                    }
+                    let edited = self.history.has_image_edits();
+                    if ui.add_enabled(edited, egui::Button::new("Revert to Original"))
+                        .on_hover_text("Drop the edits saved for this image")
+                        .clicked() {
+                        self.revert_to_original(ctx);
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(edited && self.edited_path.is_some(), egui::Button::new("Export Flattened Copy"))
+                        .on_hover_text("Save the edited image as a new PNG next to the original")
+                        .clicked() {
+                        self.export_flattened();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    let selected = self.selected_image_index.and_then(|i| self.file_infos.get(i)).map(|f| f.path.clone());
+                    if ui.add_enabled(selected.is_some(), egui::Button::new("Rename…")).clicked() {
+                        self.start_rename(selected);
+                        ui.close_menu();
+                    }
+                });
                 ui.menu_button("Settings", |ui| {
                     if ui.button("Image Loading Settings").clicked() {
                         self.show_settings = !self.show_settings;
@@ -128,6 +1102,274 @@ impl ImageViewerApp {
                     if ui.button("Refresh File Status").clicked() {
                         self.refresh_all_file_locality_status();
                     }
+                    if ui.button("Download All On-Demand Images…").clicked() {
+                        self.show_bulk_download_dialog = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Verify Images When Idle").clicked() {
+                        self.queue_integrity_scan();
+                        ui.close_menu();
+                    }
+                    if ui.button("Scan Folder for Unreadable Images…")
+                        .on_hover_text("List images that fail to decode and move them into a quarantine subfolder")
+                        .clicked() {
+                        self.triage.get_or_insert_default();
+                        ui.close_menu();
+                    }
+                    if ui.button("Verify Folder Checksums")
+                        .on_hover_text("Compute SHA-256 and BLAKE3 of every local image and compare them with the folder's .sha256 manifests")
+                        .clicked() {
+                        self.compute_checksums((0..self.file_infos.len()).collect());
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(self.duplicate_scan.is_none(), egui::Button::new("Find Duplicates…"))
+                        .on_hover_text("Group visually similar local images so redundant copies can be removed")
+                        .clicked() {
+                        self.find_duplicates();
+                        ui.close_menu();
+                    }
+                    #[cfg(windows)]
+                    {
+                        ui.separator();
+                        if ui.button("Register as Image Viewer")
+                            .on_hover_text("Add to \"Open with\", Default Apps and the \"Send to\" context menu for this user")
+                            .clicked() {
+                            self.update_shell_registration(true);
+                            ui.close_menu();
+                        }
+                        if ui.button("Remove Registration").clicked() {
+                            self.update_shell_registration(false);
+                            ui.close_menu();
+                        }
+                    }
+                });
+                #[cfg(feature = "graph")]
+                ui.menu_button("OneDrive", |ui| {
+                    if ui.button("Browse OneDrive (Graph)…").clicked() {
+                        self.graph.show_window = !self.graph.show_window;
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("View", |ui| {
+                    let mut watching = self.hot_folder.is_some();
+                    if ui.checkbox(&mut watching, "Show New Images as They Arrive")
+                        .on_hover_text("Watch this folder and open each image saved into it, e.g. from a tethered camera")
+                        .changed() {
+                        self.toggle_hot_folder(ctx);
+                        ui.close_menu();
+                    }
+                    let cull_shortcut = self.keybindings.shortcuts_for(Action::ToggleCulling).next()
+                        .map(|binding| ctx.format_shortcut(&binding.shortcut)).unwrap_or_default();
+                    if ui.add_enabled(!self.file_infos.is_empty(), egui::Button::new("Cull Images").shortcut_text(cull_shortcut))
+                        .on_hover_text("Go through the images fullscreen, keeping (K), rejecting (X) or skipping (Space) each one")
+                        .clicked() {
+                        self.toggle_culling(ctx);
+                        ui.close_menu();
+                    }
+                    if ui.button("Picture Frame")
+                        .on_hover_text("Show just the image in a small window that stays on top of other programs; Esc leaves")
+                        .clicked() {
+                        self.toggle_picture_frame(ctx);
+                        ui.close_menu();
+                    }
+                    ui.checkbox(&mut self.settings.stack_images, "Stack RAW+JPEG Pairs and Bursts")
+                        .on_hover_text("List the files of one shot, and shots taken moments apart, as one row that expands");
+                    let mut two_panes = self.folder_pane.is_some();
+                    if ui.checkbox(&mut two_panes, "Two-Pane Layout")
+                        .on_hover_text("Show a second folder beside the file list and move images into it by dragging them or pressing M")
+                        .changed() {
+                        self.toggle_folder_pane();
+                    }
+                    ui.checkbox(&mut self.show_metadata_panel, "Image Info Panel");
+                    ui.checkbox(&mut self.show_svg_source, "SVG Source Panel");
+                    ui.checkbox(&mut self.show_search, "Search Library")
+                        .on_hover_text("Search the images of every folder you have opened");
+                    if ui.add_enabled(self.selection_summary.count == 2, egui::Button::new("Compare Selected Images"))
+                        .on_disabled_hover_text("Ctrl+click two images in the list to compare them")
+                        .clicked() {
+                        self.open_compare(ctx);
+                        ui.close_menu();
+                    }
+                    ui.menu_button("Soft Proof", |ui| {
+                        ui.checkbox(&mut self.settings.soft_proof_enabled, "Preview as Printed");
+                        ui.separator();
+                        for (index, profile) in OUTPUT_PROFILES.iter().enumerate() {
+                            ui.radio_value(&mut self.settings.soft_proof.profile, index, profile.name);
+                        }
+                        ui.separator();
+                        ui.checkbox(&mut self.settings.soft_proof.black_point_compensation, "Black Point Compensation")
+                            .on_hover_text("Scale shadows into the printable range instead of clipping them");
+                        ui.checkbox(&mut self.settings.soft_proof.simulate_paper_white, "Simulate Paper White")
+                            .on_hover_text("Show the paper's tint and brightness instead of mapping paper to display white");
+                    });
+                    ui.menu_button("Fit", |ui| {
+                        let current = self.image_texture.as_ref().map(|texture| self.opening_zoom(texture.size_vec2()));
+                        ui.add_enabled_ui(current.is_some(), |ui| {
+                            ui.label("This Image");
+                            for zoom in OpeningZoom::ALL {
+                                if ui.radio(current == Some(zoom), zoom.label()).clicked() {
+                                    self.set_image_zoom(zoom);
+                                }
+                            }
+                        });
+                        ui.separator();
+                        ui.menu_button("Photos Open At", |ui| {
+                            for zoom in OpeningZoom::ALL {
+                                if ui.radio_value(&mut self.settings.photo_zoom, zoom, zoom.label()).changed() {
+                                    self.reset_zoom();
+                                }
+                            }
+                        });
+                        ui.menu_button("Icons and SVGs Open At", |ui| {
+                            for zoom in OpeningZoom::ALL {
+                                if ui.radio_value(&mut self.settings.icon_zoom, zoom, zoom.label()).changed() {
+                                    self.reset_zoom();
+                                }
+                            }
+                        });
+                        ui.checkbox(&mut self.settings.snap_zoom, "Snap Zoom Steps")
+                            .on_hover_text("Zoom buttons step to 100%, whole multiples and common fractions");
+                        ui.separator();
+                        ui.add_enabled_ui(self.locked_fit.is_none(), |ui| {
+                            for mode in FitMode::ALL {
+                                if ui.radio_value(&mut self.settings.fit_mode, mode, mode.label()).changed() {
+                                    self.reset_zoom();
+                                }
+                            }
+                        });
+                        ui.separator();
+                        let label = if self.panorama_scroll.is_active() { "⏹ Stop Panorama Auto-Scroll" } else { "↔ Panorama Auto-Scroll" };
+                        if ui.add_enabled(self.image_texture.is_some(), egui::Button::new(label))
+                            .on_hover_text("Pan slowly across a wide image at fit-height zoom (P)")
+                            .clicked() {
+                            self.toggle_panorama_scroll();
+                            ui.close_menu();
+                        }
+                        ui.add(egui::Slider::new(&mut self.settings.panorama_scroll_speed, MIN_SCROLL_SPEED..=MAX_SCROLL_SPEED)
+                            .logarithmic(true)
+                            .text("pt/s"));
+                        ui.separator();
+                        let mut locked = self.locked_fit.is_some();
+                        let lock_label = match self.locked_fit.or(self.current_fit) {
+                            Some(fit) => format!("Lock Fit ({})", fit.label()),
+                            None => "Lock Fit".to_string(),
+                        };
+                        if ui.add_enabled(self.current_fit.is_some() || locked, egui::Checkbox::new(&mut locked, lock_label))
+                            .on_hover_text("Keep the current image's fit for every image instead of choosing one per aspect ratio")
+                            .changed() {
+                            self.locked_fit = if locked { self.current_fit } else { None };
+                        }
+                    });
+                    ui.menu_button("Slideshow", |ui| {
+                        let label = if self.slideshow.is_running() { "⏹ Stop Slideshow" } else { "▶ Start Slideshow" };
+                        if ui.button(label).clicked() {
+                            self.toggle_slideshow();
+                            ui.close_menu();
+                        }
+                        ui.add(egui::Slider::new(&mut self.settings.slideshow_interval_secs, 1..=60).text("seconds per image"));
+                        ui.separator();
+                        ui.label("Skip images that are:");
+                        let rules = &mut self.settings.slideshow_skip;
+                        ui.checkbox(&mut rules.skip_on_demand, "Not downloaded (on-demand)");
+                        ui.checkbox(&mut rules.skip_failed_integrity, "Failing to decode")
+                            .on_hover_text("Found by an integrity scan or when the image was shown");
+                        ui.horizontal(|ui| {
+                            let mut limit_enabled = rules.max_megapixels.is_some();
+                            if ui.checkbox(&mut limit_enabled, "Larger than").changed() {
+                                rules.max_megapixels = limit_enabled.then_some(24.0);
+                            }
+                            if let Some(limit) = &mut rules.max_megapixels {
+                                ui.add(egui::DragValue::new(limit).range(0.1..=1000.0).speed(0.5).suffix(" MP"));
+                            }
+                        });
+                        ui.horizontal_wrapped(|ui| {
+                            ui.label("Formats:");
+                            for format in &self.settings.supported_formats {
+                                let mut skipped = rules.skip_formats.contains(format);
+                                if ui.checkbox(&mut skipped, format.to_uppercase()).changed() {
+                                    if skipped {
+                                        rules.skip_formats.push(format.clone());
+                                    } else {
+                                        rules.skip_formats.retain(|f| f != format);
+                                    }
+                                }
+                            }
+                        });
+                        ui.separator();
+                        ui.add_enabled_ui(!rules.skip_on_demand, |ui| {
+                            ui.add(egui::Slider::new(&mut self.settings.slideshow_read_ahead, 1..=10).text("images downloaded ahead"))
+                                .on_hover_text("On-demand images coming up next are downloaded in the background so slides don't stall");
+                            ui.horizontal(|ui| {
+                                let mut limited = self.settings.slideshow_download_limit_mb.is_some();
+                                if ui.checkbox(&mut limited, "Limit downloads to").changed() {
+                                    self.settings.slideshow_download_limit_mb = limited.then_some(500);
+                                }
+                                if let Some(limit) = &mut self.settings.slideshow_download_limit_mb {
+                                    ui.add(egui::DragValue::new(limit).range(10..=100_000).speed(10).suffix(" MB"));
+                                    ui.label("per slideshow");
+                                }
+                            });
+                        });
+                        ui.separator();
+                        if ui.button(format!("Skipped Images ({})…", self.slideshow.skip_log().len())).clicked() {
+                            self.show_slideshow_log = true;
+                            ui.close_menu();
+                        }
+                    });
+                    ui.add_enabled_ui(!self.frame_sequences.is_empty(), |ui| {
+                        ui.menu_button("Play Sequence", |ui| {
+                            for sequence in &self.frame_sequences {
+                                if ui.button(format!("🎞 {} ({} frames)", sequence.name, sequence.frames.len())).clicked() {
+                                    self.sequence_player = SequencePlayer::local(sequence.clone(), &self.file_infos);
+                                    if self.sequence_player.is_none() {
+                                        self.status_text = format!("No frames of {} are on this computer", sequence.name);
+                                    }
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    }).response.on_disabled_hover_text("No numbered images like frame_0001.png in this folder");
+                    ui.menu_button("Theme", |ui| {
+                        for preset in ThemePreset::ALL {
+                            ui.radio_value(&mut self.settings.theme.preset, preset, preset.label());
+                        }
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            let mut accent = self.settings.theme.accent.unwrap_or_else(|| self.settings.theme.preset.default_accent());
+                            if ui.color_edit_button_srgb(&mut accent).changed() {
+                                self.settings.theme.accent = Some(accent);
+                            }
+                            ui.label("Accent");
+                            if self.settings.theme.accent.is_some() && ui.small_button("Reset").clicked() {
+                                self.settings.theme.accent = None;
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_srgb(&mut self.settings.theme.image_background);
+                            ui.label("Image background");
+                            if self.settings.theme.image_background != Theme::default().image_background && ui.small_button("Reset").clicked() {
+                                self.settings.theme.image_background = Theme::default().image_background;
+                            }
+                        });
+                    });
+                    ui.menu_button("Background", |ui| {
+                        for background in PreviewBackground::ALL {
+                            ui.radio_value(&mut self.settings.preview_background, background, background.label());
+                        }
+                        if let Some(Backdrop::Solid(color)) = self.auto_backdrop
+                            && color != self.settings.theme.image_background() {
+                            ui.separator();
+                            ui.colored_label(egui::Color32::GRAY, "Low contrast detected: using a contrasting backdrop");
+                        } else if self.auto_backdrop == Some(Backdrop::Checkerboard) {
+                            ui.separator();
+                            ui.colored_label(egui::Color32::GRAY, "Low contrast detected: using a checkerboard");
+                        }
+                        if self.settings.preview_background == PreviewBackground::Matte
+                            && self.image_texture.is_some() && self.image_matte.is_none() {
+                            ui.separator();
+                            ui.colored_label(egui::Color32::GRAY, "The image's border isn't one solid color: using the theme color");
+                        }
+                    });
                 });
                 ui.menu_button("Performance", |ui| {
                     if ui.button("Run Benchmark").clicked() {
@@ -137,31 +1379,367 @@ impl ImageViewerApp {
                         self.show_benchmark_window = !self.show_benchmark_window;
                     }
                 });
+                ui.menu_button("Share", |ui| {
+                    self.render_share_menu(ui, ctx);
+                });
+                #[cfg(feature = "scripting")]
+                ui.menu_button("Scripts", |ui| {
+                    self.render_scripts_menu(ui);
+                });
+                ui.menu_button("Help", |ui| {
+                    if ui.button("Keyboard Shortcuts").clicked() {
+                        self.show_shortcut_help = true;
+                        ui.close_menu();
+                    }
+                });
+            });
+        });
+    }
+
+    fn apply_theme(&mut self, ctx: &egui::Context) {
+        if self.applied_theme != Some(self.settings.theme) {
+            self.settings.theme.apply(ctx);
+            self.applied_theme = Some(self.settings.theme);
+        }
+    }
+
+    /// Keep egui's zoom in step with the UI scale setting, which it can also
+    /// change through its own Ctrl+Plus/Minus shortcuts
+    fn apply_ui_scale(&mut self, ctx: &egui::Context) {
+        if let Some(applied) = self.applied_ui_scale
+            && ctx.zoom_factor() != applied {
+            self.settings.ui_scale = ctx.zoom_factor().clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+        }
+        if self.ui_scale_dragging || self.applied_ui_scale == Some(self.settings.ui_scale) {
+            return;
+        }
+        ctx.set_zoom_factor(self.settings.ui_scale);
+        if self.applied_ui_scale.is_some() {
+            let mut preferences = Preferences::load();
+            preferences.set("ui_scale", self.settings.ui_scale);
+            if let Err(e) = preferences.save() {
+                self.status_text = e;
+            }
+        }
+        self.applied_ui_scale = Some(self.settings.ui_scale);
+    }
+
+    fn apply_animations(&mut self, ctx: &egui::Context) {
+        if self.applied_animations != Some(self.settings.animations) {
+            // egui's default animation time
+            let time = if self.settings.animations { 1.0 / 12.0 } else { 0.0 };
+            ctx.all_styles_mut(|style| style.animation_time = time);
+            self.applied_animations = Some(self.settings.animations);
+        }
+    }
+
+    /// Refresh what the status bar shows about the displayed image
+    fn update_status_info(&mut self) {
+        let file = self.selected_image_index.and_then(|i| self.file_infos.get(i));
+        let metadata = self.current_metadata.as_ref();
+        let texture_size = self.image_texture.as_ref().map(|t| t.size()).map(|[w, h]| (w as u32, h as u32));
+        self.status_info = StatusInfo {
+            dimensions: metadata.map(|m| (m.width, m.height)).or(texture_size),
+            zoom_percent: self.display_scale.filter(|_| self.image_texture.is_some()).map(|scale| scale * 100.0),
+            file_size: metadata.map(|m| m.file_size).or_else(|| file.and_then(|f| f.estimated_download_size)),
+            format: metadata.map(|m| m.format.clone()),
+            locality: file.map(|f| f.locality_status.clone()),
+            position: self.selected_image_index.map(|i| (i + 1, self.file_infos.len())),
+            memory_bytes: self.memory_monitor.memory_bytes(),
+        };
+        if let Some(session) = &self.deep_zoom {
+            self.status_info.dimensions = Some((session.image.width, session.image.height));
+            self.status_info.zoom_percent = session.view.map(|view| view.scale as f32 * 100.0);
+            self.status_info.file_size = None;
+            self.status_info.format = Some("Deep Zoom".to_string());
+        }
+    }
+
+    /// Limits for background work given the current power source and settings
+    pub(crate) fn background_budget(&self) -> BackgroundWorkBudget {
+        BackgroundWorkBudget::for_power_source(self.power_monitor.source(), self.settings.reduce_work_on_battery)
+            .with_thread_limit(self.settings.max_decode_threads)
+    }
+
+    fn render_toolbar(&mut self, ctx: &egui::Context) {
+        if !self.settings.show_toolbar {
+            return;
+        }
+        let has_image = self.current_image.is_some();
+        let selected = self.selected_image_index.and_then(|i| self.file_infos.get(i)).map(|f| f.path.clone());
+        let mut clicked = None;
+        let mut open = None;
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let color = ui.visuals().text_color();
+                for button in ToolbarButton::ALL {
+                    if !button.is_visible(&self.settings.hidden_toolbar_buttons) {
+                        continue;
+                    }
+                    let (enabled, disabled_hint) = match button {
+                        ToolbarButton::Rotate | ToolbarButton::ZoomIn | ToolbarButton::ZoomOut | ToolbarButton::Fit => (has_image, "Open an image first"),
+                        ToolbarButton::Delete => (selected.is_some() && !archive::is_archive(&self.current_dir), "Select an image in a folder first"),
+                        _ => (true, ""),
+                    };
+                    let (icon, label) = match button {
+                        ToolbarButton::Slideshow if self.slideshow.is_running() => (Icons::PLAYER_PAUSE, "Stop Slideshow"),
+                        _ => (button.icon(), button.label()),
+                    };
+                    let response = ui.add_enabled_ui(enabled, |ui| {
+                        self.icon_renderer.icon_button(ui, ctx, icon, TOOLBAR_ICON_SIZE, color, label)
+                    }).inner.on_disabled_hover_text(disabled_hint);
+                    if response.clicked() {
+                        clicked = Some(button);
+                    }
+                    if matches!(button, ToolbarButton::OpenFolder | ToolbarButton::Fit | ToolbarButton::Delete) {
+                        ui.separator();
+                    }
+                    if button == ToolbarButton::OpenFolder && self.show_toolbar_folder_input {
+                        let input = ui.add(egui::TextEdit::singleline(&mut self.folder_path_input)
+                            .hint_text("Folder or image path")
+                            .desired_width(220.0));
+                        let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                        if (ui.button("Open").clicked() || submitted) && !self.folder_path_input.trim().is_empty() {
+                            open = Some(PathBuf::from(self.folder_path_input.trim()));
+                        }
+                        ui.separator();
+                    }
+                }
+            });
+        });
+        if let Some(path) = open {
+            self.show_toolbar_folder_input = false;
+            self.open_favorite(ctx, &path);
+        }
+        let Some(button) = clicked else {
+            return;
+        };
+        match button {
+            ToolbarButton::OpenFolder => self.show_toolbar_folder_input = !self.show_toolbar_folder_input,
+            ToolbarButton::Refresh => self.open_folder(ctx, self.current_dir.clone(), selected.as_deref()),
+            ToolbarButton::Rotate => self.rotate_image(ctx),
+            ToolbarButton::ZoomIn => self.zoom_by(TOOLBAR_ZOOM_STEP),
+            ToolbarButton::ZoomOut => self.zoom_by(1.0 / TOOLBAR_ZOOM_STEP),
+            ToolbarButton::Fit => self.set_image_zoom(OpeningZoom::Fit),
+            ToolbarButton::Slideshow => self.toggle_slideshow(),
+            ToolbarButton::Delete => self.confirm_delete_image = selected,
+            ToolbarButton::Settings => self.show_settings = !self.show_settings,
+        }
+    }
+
+    /// Turn the displayed image a quarter turn clockwise
+    fn rotate_image(&mut self, ctx: &egui::Context) {
+        self.apply_edit(ctx, ImageEdit::RotateClockwise);
+    }
+
+    /// Zoom about the center of the view, to the next snap step if snapping is on
+    fn zoom_by(&mut self, factor: f32) {
+        let mut zoom = self.zoom_factor * factor;
+        if self.settings.snap_zoom
+            && let Some(scale) = self.display_scale {
+            zoom = self.zoom_factor * next_zoom_step(scale, factor > 1.0) / scale;
+        }
+        let zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.pan_offset *= zoom / self.zoom_factor;
+        self.zoom_factor = zoom;
+    }
+
+    fn render_status_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                // Errors in red, recoloring results in green
+                let text_color = if self.status_text.contains("Error") || self.status_text.contains("Skipped") {
+                    ui.visuals().error_fg_color
+                } else if self.status_text.contains("recolored") {
+                    if ui.visuals().dark_mode { egui::Color32::from_rgb(120, 255, 120) } else { egui::Color32::from_rgb(0, 128, 0) }
+                } else {
+                    ui.visuals().text_color()
+                };
+                ui.colored_label(text_color, &self.status_text);
+                if let Some(report) = self.last_error_report.clone() {
+                    if ui.link("📄 Open error report").on_hover_text(report.display().to_string()).clicked()
+                        && let Err(e) = open_with_system(&report) {
+                        self.status_text = e;
+                    }
+                    if let Some(dir) = report.parent()
+                        && ui.link("Show diagnostics folder").clicked()
+                        && let Err(e) = open_with_system(dir) {
+                        self.status_text = e;
+                    }
+                }
+                let summary = self.selection_summary;
+                if summary.count > 1 {
+                    let mut text = format!("{} selected · {:.1} MB", summary.count, summary.total_bytes as f64 / MB as f64);
+                    if summary.on_demand > 0 {
+                        text.push_str(&format!(
+                            " · {} on-demand, {:.1} MB to download{}",
+                            summary.on_demand,
+                            summary.download_bytes as f64 / MB as f64,
+                            download_time_suffix(&self.bandwidth, self.settings.link_speed_mbps, summary.download_bytes),
+                        ));
+                    }
+                    ui.separator();
+                    ui.label(text).on_hover_text("Ctrl+click toggles an image, Shift+click selects a range");
+                    if summary.count == 2 && ui.small_button("Compare A/B").clicked() {
+                        self.open_compare(ctx);
+                    }
+                }
+                let current_video = self.selected_image_index
+                    .and_then(|i| self.file_infos.get(i))
+                    .map(|f| f.path.clone())
+                    .filter(|path| video::is_video(path));
+                if let Some(path) = current_video {
+                    ui.separator();
+                    if ui.small_button("▶ Play").on_hover_text("Open in the system's video player").clicked() {
+                        self.play_video(&path);
+                    }
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if let Some(bytes) = self.status_info.memory_bytes {
+                        ui.colored_label(egui::Color32::GRAY, format!("🧠 {}", format_size(bytes)))
+                            .on_hover_text("Memory used by the app");
+                        ui.separator();
+                    }
+                    let segments = self.status_info.image_segments();
+                    if !segments.is_empty() {
+                        ui.label(segments.join(" · "));
+                        ui.separator();
+                    }
+                    let waiting_jobs = self.job_engine.queued_count(JobPriority::Idle);
+                    if waiting_jobs > 0 {
+                        ui.colored_label(egui::Color32::GRAY, format!("⏸ {} waiting for idle", waiting_jobs))
+                            .on_hover_text("Heavy background work runs once you stop using the app for a while");
+                    }
+                    let source = self.power_monitor.source();
+                    let saving = source == PowerSource::Battery && self.settings.reduce_work_on_battery;
+                    let (text, color) = if saving {
+                        (format!("{} Battery saver", source.icon()), egui::Color32::YELLOW)
+                    } else if self.settings.show_status_text {
+                        (format!("{} {}", source.icon(), source.description()), egui::Color32::GRAY)
+                    } else {
+                        (source.icon().to_string(), egui::Color32::GRAY)
+                    };
+                    let hover = if saving {
+                        format!("{}: no slideshow read-ahead, single decode thread, delayed previews", source.description())
+                    } else {
+                        source.description().to_string()
+                    };
+                    ui.colored_label(color, text).on_hover_text(hover);
+                    if self.slideshow.is_running() {
+                        let interval = std::time::Duration::from_secs(self.settings.slideshow_interval_secs.into());
+                        let mut text = if self.slideshow.is_waiting() {
+                            "▶ Slideshow: waiting for download".to_string()
+                        } else {
+                            format!("▶ Slideshow {} s", self.slideshow.time_remaining(interval).as_secs() + 1)
+                        };
+                        if self.read_ahead.used_bytes() > 0 {
+                            text.push_str(&format!(" · ⬇ {}", format_size(self.read_ahead.used_bytes())));
+                            if let Some(limit) = self.read_ahead.limit_bytes() {
+                                text.push_str(&format!(" of {}", format_size(limit)));
+                            }
+                        }
+                        ui.colored_label(egui::Color32::LIGHT_GREEN, text).on_hover_text("F5 stops the slideshow");
+                    }
+                    if let Some(hot) = &self.hot_folder {
+                        ui.colored_label(egui::Color32::LIGHT_GREEN, "👁 Watching")
+                            .on_hover_text(format!("New images in {} are shown as they arrive", hot.dir().display()));
+                    }
+                    if self.settings.soft_proof_enabled && self.image_texture.is_some() {
+                        ui.colored_label(egui::Color32::LIGHT_BLUE, format!("🖨 {}", self.settings.soft_proof.output_profile().name))
+                            .on_hover_text("Soft-proof: the preview simulates printing on this paper. Ctrl+Shift+Y turns it off.");
+                    }
+                    if let Some(path) = self.displayed_svg_path()
+                        && self.view_states.recolor_active(&path, self.settings.svg_recolor_enabled) {
+                        let [r, g, b] = self.settings.svg_target_color;
+                        ui.colored_label(egui::Color32::from_rgb(r, g, b), "🎨 Recoloring")
+                            .on_hover_text("SVG recoloring is applied to this image. Right-click it in the list to show original colors.");
+                    }
+                    for op in self.progress.active() {
+                        let text = match (op.total, op.bytes) {
+                            (Some(total), Some(bytes)) => format!("{} ({:.1}/{:.1} MB)", op.name, bytes as f64 / 1_048_576.0, total as f64 / 1_048_576.0),
+                            (Some(total), None) => format!("{} ({}/{})", op.name, op.done, total),
+                            (None, _) => op.name.clone(),
+                        };
+                        let bar = egui::ProgressBar::new(op.fraction().unwrap_or(0.0))
+                            .desired_width(160.0)
+                            .text(text)
+                            .animate(op.fraction().is_none());
+                        ui.add(bar);
+                    }
+                });
             });
         });
     }
 
     fn render_settings_window(&mut self, ctx: &egui::Context) {
         if self.show_settings {
+            if self.settings.svg_recolor_enabled {
+                self.update_svg_preview(ctx);
+            }
+            let mut apply_recolor = false;
+            let mut start_blur_detection = false;
+            let mut save_policies = false;
+            let mut save_svg_colors = false;
             egui::Window::new("Image Loading Settings")
                 .open(&mut self.show_settings)
                 .show(ctx, |ui| {
                     ui.checkbox(&mut self.settings.skip_large_images, "Skip very large images");
                     ui.checkbox(&mut self.settings.auto_scale_large_images, "Auto-scale large images");
-                    ui.checkbox(&mut self.settings.auto_scale_to_fit, "Scale images to fit display");
+                    for (label, salt, zoom) in [
+                        ("Photos open at", "photo_zoom", &mut self.settings.photo_zoom),
+                        ("Icons and SVGs open at", "icon_zoom", &mut self.settings.icon_zoom),
+                    ] {
+                        ui.horizontal(|ui| {
+                            ui.label(label);
+                            egui::ComboBox::from_id_salt(salt)
+                                .selected_text(zoom.label())
+                                .show_ui(ui, |ui| {
+                                    for option in OpeningZoom::ALL {
+                                        ui.selectable_value(zoom, option, option.label());
+                                    }
+                                });
+                        });
+                    }
+                    ui.checkbox(&mut self.settings.snap_zoom, "Snap zoom buttons to 100% and whole multiples");
                     
                     if self.settings.skip_large_images {
                         self.settings.auto_scale_large_images = false;
                     } else if self.settings.auto_scale_large_images {
                         self.settings.skip_large_images = false;
                     }
-
-                    ui.separator();
-                    
-                    ui.heading("File Size Limits");
-                    
-                    // Show current effective limit (whether manual or dynamic)
-                    let effective_limit = self.settings.get_effective_max_file_size_mb().unwrap_or(0);
+                    ui.horizontal(|ui| {
+                        ui.label("Large image limit:");
+                        ui.add(egui::Slider::new(&mut self.settings.large_image_max_dimension, MIN_LARGE_IMAGE_DIMENSION..=MAX_LARGE_IMAGE_DIMENSION).suffix(" px"));
+                        let megapixels = self.performance_profile.system_capabilities.max_successful_megapixels;
+                        if let Some(dimension) = tuning::dimension_for_megapixels(megapixels)
+                            && dimension != self.settings.large_image_max_dimension
+                            && ui.small_button("From benchmark")
+                                .on_hover_text(format!("{} px: the largest image the benchmark decoded was {:.1} MP", dimension, megapixels))
+                                .clicked() {
+                            self.settings.large_image_max_dimension = dimension;
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Scaling quality:");
+                        egui::ComboBox::from_id_salt("resize_filter")
+                            .selected_text(self.settings.resize_filter.label())
+                            .show_ui(ui, |ui| {
+                                for filter in ResizeFilter::ALL {
+                                    ui.selectable_value(&mut self.settings.resize_filter, filter, filter.label());
+                                }
+                            });
+                    });
+
+                    ui.separator();
+                    
+                    ui.horizontal(|ui| {
+                        ui.heading("File Size Limits");
+                        help_button(ui, "Files larger than the limit are skipped or scaled down, depending on the options above. \
+                            The dynamic limit follows available RAM; a manual value overrides it.");
+                    });
+                    
+                    // Show current effective limit (whether manual or dynamic)
+                    let effective_limit = self.settings.get_effective_max_file_size_mb().unwrap_or(0);
                     let dynamic_limit = crate::settings::ImageLoadingSettings::calculate_dynamic_max_file_size_mb();
                     
                     ui.horizontal(|ui| {
@@ -197,6 +1775,233 @@ impl ImageViewerApp {
                         ui.colored_label(egui::Color32::YELLOW, "⚠ Using manual override - consider using dynamic for better memory management");
                     }
 
+                    ui.separator();
+                    ui.heading("Interface");
+                    ui.checkbox(&mut self.settings.show_status_text, "Show text next to status icons")
+                        .on_hover_text("Label file status and speed icons with words instead of relying on icons, colors and tooltips");
+                    ui.checkbox(&mut self.settings.animations, "Animations");
+                    ui.horizontal(|ui| {
+                        ui.label("UI scale:");
+                        let mut percent = (self.settings.ui_scale * 100.0).round();
+                        let response = ui.add(egui::Slider::new(&mut percent, MIN_UI_SCALE * 100.0..=MAX_UI_SCALE * 100.0)
+                            .step_by(5.0)
+                            .suffix("%"));
+                        if response.changed() {
+                            self.settings.ui_scale = percent / 100.0;
+                        }
+                        // Rescaling mid-drag would move the slider out from under the pointer
+                        self.ui_scale_dragging = response.dragged();
+                        if self.settings.ui_scale != 1.0 && ui.small_button("Reset").clicked() {
+                            self.settings.ui_scale = 1.0;
+                        }
+                    }).response.on_hover_text("Ctrl+Plus / Ctrl+Minus also change the scale");
+
+                    ui.separator();
+                    ui.heading("Blur Detection");
+                    if ui.checkbox(&mut self.settings.detect_blur, "Flag possibly blurry images")
+                        .on_hover_text("Measure the sharpness of downloaded images while the computer is idle")
+                        .changed() {
+                        start_blur_detection = self.settings.detect_blur;
+                    }
+                    ui.add_enabled_ui(self.settings.detect_blur, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Sharpness threshold:");
+                            ui.add(egui::Slider::new(&mut self.settings.blur_threshold, 10.0..=500.0).logarithmic(true));
+                        }).response.on_hover_text("Images scoring below this are flagged; raise it to flag more");
+                    });
+
+                    ui.separator();
+                    ui.heading("Power");
+                    ui.checkbox(&mut self.settings.reduce_work_on_battery, "Reduce background work on battery")
+                        .on_hover_text("Stops the slideshow downloading ahead, runs one background job at a time and delays saving previews while on battery power");
+                    ui.horizontal(|ui| {
+                        let mut capped = self.settings.battery_max_fps.is_some();
+                        if ui.checkbox(&mut capped, "Limit the frame rate on battery to").changed() {
+                            self.settings.battery_max_fps = capped.then_some(30);
+                        }
+                        if let Some(fps) = &mut self.settings.battery_max_fps {
+                            ui.add(egui::DragValue::new(fps).range(5..=60).suffix(" fps"));
+                        }
+                    }).response.on_hover_text("Applies to animations, slideshows and background progress; input is always handled right away");
+                    ui.label(format!("{} {}", self.power_monitor.source().icon(), self.power_monitor.source().description()));
+                    ui.horizontal(|ui| {
+                        ui.label("Run idle-only background work after (s):");
+                        ui.add(egui::Slider::new(&mut self.settings.idle_delay_secs, 5..=600));
+                    });
+
+                    ui.separator();
+                    ui.heading("Navigation");
+                    ui.checkbox(&mut self.settings.wrap_navigation, "Wrap around at the ends of the list");
+                    ui.checkbox(&mut self.settings.skip_on_demand_navigation, "Previous/next image skips files that need downloading");
+                    ui.horizontal(|ui| {
+                        ui.label("Page Up/Down skips:");
+                        ui.add(egui::DragValue::new(&mut self.settings.page_jump_size).range(2..=100).suffix(" images"));
+                    });
+                    ui.checkbox(&mut self.settings.mouse_button_navigation, "Mouse back/forward buttons change image");
+                    ui.checkbox(&mut self.settings.gesture_navigation, "Right-drag left/right over the image changes image");
+                    ui.checkbox(&mut self.settings.double_click_zoom, "Double-click the image to toggle fit / 100%");
+                    ui.checkbox(&mut self.settings.touch_gestures, "Touch gestures (pinch to zoom, two-finger pan, swipe to change image)");
+                    ui.checkbox(&mut self.settings.show_navigator, "Minimap when the image is larger than the view");
+
+                    ui.separator();
+                    ui.heading("Pixel Inspection");
+                    ui.checkbox(&mut self.settings.pixel_grid, format!("Pixel grid when zoomed past {:.0}%", pixel_grid::GRID_MIN_SCALE * 100.0));
+                    ui.checkbox(&mut self.settings.pixel_rulers, "Rulers in image pixels");
+                    ui.checkbox(&mut self.settings.pixel_crosshair, "Crosshair with pixel coordinates");
+
+                    ui.separator();
+                    ui.heading("Network");
+                    ui.horizontal(|ui| {
+                        ui.label("Link speed (Mbps):");
+                        let mut mbps = self.settings.link_speed_mbps.unwrap_or(0.0);
+                        if ui.add(egui::DragValue::new(&mut mbps).range(0.0..=10_000.0).speed(1.0)).changed() {
+                            self.settings.link_speed_mbps = (mbps > 0.0).then_some(mbps);
+                        }
+                        help_button(ui, "Used to predict download times until real downloads have been measured. 0 means unknown.");
+                    });
+                    if let Some(bytes_per_sec) = self.bandwidth.measured_bytes_per_sec() {
+                        ui.label(format!("Measured: {:.1} Mbps", bytes_per_sec * 8.0 / 1_000_000.0));
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Metered connection:");
+                        egui::ComboBox::from_id_salt("metered_connection")
+                            .selected_text(self.settings.metered_connection.label())
+                            .show_ui(ui, |ui| {
+                                for option in MeteredConnection::ALL {
+                                    ui.selectable_value(&mut self.settings.metered_connection, option, option.label());
+                                }
+                            });
+                        help_button(ui, "On a metered connection every download of an online-only file asks first, even when its warning is turned off, and slideshows don't download ahead.");
+                    });
+                    ui.weak(match self.network_cost.detected() {
+                        Some(true) => "The system reports this connection as metered",
+                        Some(false) => "The system reports this connection as unmetered",
+                        None => "The system doesn't report whether this connection is metered",
+                    });
+
+                    ui.separator();
+                    ui.heading("Warnings");
+                    save_policies |= download_limit_editor(ui, &mut self.prompt_policies.download_under_mb);
+                    for prompt in Prompt::ALL {
+                        let rules = self.prompt_policies.rules_mut(prompt);
+                        ui.label(egui::RichText::new(prompt.label()).strong());
+                        ui.indent(prompt.label(), |ui| {
+                            save_policies |= ui.checkbox(&mut rules.never, "Never show").changed();
+                            if rules.session && ui.button("Show again this session").clicked() {
+                                rules.session = false;
+                            }
+                            let mut removed = None;
+                            for (index, folder) in rules.folders.iter().enumerate() {
+                                ui.horizontal(|ui| {
+                                    if ui.small_button("✖").on_hover_text("Warn for this folder again").clicked() {
+                                        removed = Some(index);
+                                    }
+                                    ui.label(format!("Not shown in {}", folder.display()));
+                                });
+                            }
+                            if let Some(index) = removed {
+                                rules.folders.remove(index);
+                                save_policies = true;
+                            }
+                        });
+                    }
+
+                    ui.separator();
+                    ui.heading("Sharing");
+                    ui.horizontal(|ui| {
+                        ui.label("Share folder:");
+                        ui.text_edit_singleline(&mut self.settings.share_folder);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Upload command:");
+                        ui.text_edit_singleline(&mut self.settings.share_command)
+                            .on_hover_text("Runs in a shell; {path} is replaced with the quoted image path");
+                    });
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.heading("External Editors");
+                        help_button(ui, "Offered by Open in Editor for the listed extensions, or for every format when none are listed. \
+                            Commands run in a shell; {path} is replaced with the quoted image path. \
+                            The preview reloads when the editor saves.");
+                    });
+                    // Text fields are saved when they lose focus, not on every keystroke
+                    let mut editors_changed = false;
+                    let mut remove_editor = None;
+                    egui::Grid::new("external_editors").num_columns(4).show(ui, |ui| {
+                        for (index, editor) in self.external_editors.iter_mut().enumerate() {
+                            editors_changed |= ui.add(egui::TextEdit::singleline(&mut editor.name).desired_width(90.0).hint_text("Name")).lost_focus();
+                            editors_changed |= ui.add(egui::TextEdit::singleline(&mut editor.command).desired_width(200.0).hint_text("editor {path}")).lost_focus();
+                            let mut extensions = editor.extensions.join(", ");
+                            let response = ui.add(egui::TextEdit::singleline(&mut extensions).desired_width(110.0).hint_text("All formats"));
+                            if response.changed() {
+                                editor.extensions = external_editor::parse_extensions(&extensions);
+                            }
+                            editors_changed |= response.lost_focus();
+                            if ui.small_button("🗑").on_hover_text("Remove").clicked() {
+                                remove_editor = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    if let Some(index) = remove_editor {
+                        self.external_editors.remove(index);
+                        editors_changed = true;
+                    }
+                    if ui.button("Add Editor").clicked() {
+                        self.external_editors.push(ExternalEditor::new("Editor", "", &[]));
+                        editors_changed = true;
+                    }
+                    if editors_changed {
+                        let mut preferences = Preferences::load();
+                        external_editor::save_to(&mut preferences, &self.external_editors);
+                        if let Err(e) = preferences.save() {
+                            self.status_text = e;
+                        }
+                    }
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.heading("Icons");
+                        help_button(ui, "A folder of SVG files replaces the built-in icons with the same names, e.g. cloud.svg. \
+                            cloud.dark.svg and cloud.light.svg are used instead with a dark or light theme.");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Icon folder:");
+                        ui.text_edit_singleline(&mut self.settings.icon_directory);
+                        if ui.button("Load").clicked() {
+                            match IconDirectory::load(self.settings.icon_directory.trim()) {
+                                Ok(directory) => {
+                                    self.status_text = format!("Loaded {} icons from {}", directory.len(), directory.dir().display());
+                                    self.icon_renderer.set_icon_directory(Some(directory));
+                                }
+                                Err(e) => self.status_text = e,
+                            }
+                        }
+                        if self.icon_renderer.icon_directory().is_some() && ui.button("Use built-in").clicked() {
+                            self.icon_renderer.set_icon_directory(None);
+                        }
+                    });
+                    ui.checkbox(&mut self.settings.icon_hot_reload, "Reload icons when the files change")
+                        .on_hover_text("Handy while designing an icon pack");
+
+                    ui.separator();
+                    ui.heading("Toolbar");
+                    ui.checkbox(&mut self.settings.show_toolbar, "Show toolbar");
+                    ui.add_enabled_ui(self.settings.show_toolbar, |ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            for button in ToolbarButton::ALL {
+                                let mut visible = button.is_visible(&self.settings.hidden_toolbar_buttons);
+                                if ui.checkbox(&mut visible, button.label()).changed() {
+                                    self.settings.hidden_toolbar_buttons.retain(|hidden| *hidden != button);
+                                    if !visible {
+                                        self.settings.hidden_toolbar_buttons.push(button);
+                                    }
+                                }
+                            }
+                        });
+                    });
+
                     ui.separator();
                     ui.heading("SVG Options");
                     ui.checkbox(&mut self.settings.svg_recolor_enabled, "Enable SVG recoloring");
@@ -213,12 +2018,149 @@ impl ImageViewerApp {
                                 let [r, g, b, _] = color.to_array();
                                 self.settings.svg_target_color = [r, g, b];
                             }
+                            let eyedropper = ui.add_enabled(self.current_image.is_some(), egui::Button::new("💧 Pick from image").selected(self.eyedropper_active))
+                                .on_hover_text("Click the displayed image to use the color under the cursor")
+                                .on_disabled_hover_text("Load an image first");
+                            if eyedropper.clicked() {
+                                self.eyedropper_active = !self.eyedropper_active;
+                            }
+                        });
+
+                        let mut picked = None;
+                        if !self.settings.recent_svg_colors.is_empty() {
+                            ui.horizontal(|ui| {
+                                ui.label("Recent:");
+                                for color in &self.settings.recent_svg_colors {
+                                    if color_swatch(ui, *color).clicked() {
+                                        picked = Some(*color);
+                                    }
+                                }
+                            });
+                        }
+                        ui.horizontal(|ui| {
+                            let selected_name = self.settings.svg_palettes
+                                .get(self.settings.selected_palette)
+                                .map(|p| p.name.clone())
+                                .unwrap_or_default();
+                            egui::ComboBox::from_id_salt("svg_palette")
+                                .selected_text(selected_name)
+                                .show_ui(ui, |ui| {
+                                    for (index, palette) in self.settings.svg_palettes.iter().enumerate() {
+                                        if ui.selectable_value(&mut self.settings.selected_palette, index, &palette.name).changed() {
+                                            save_svg_colors = true;
+                                        }
+                                    }
+                                });
+                            if let Some(palette) = self.settings.svg_palettes.get(self.settings.selected_palette) {
+                                for color in &palette.colors {
+                                    if color_swatch(ui, *color).clicked() {
+                                        picked = Some(*color);
+                                    }
+                                }
+                            }
+                        });
+                        if let Some(color) = picked {
+                            self.settings.set_svg_target_color(color);
+                            save_svg_colors = true;
+                        }
+
+                        ui.label("Color mappings:")
+                            .on_hover_text("Each source color gets its own target; other colors use the target color above");
+                        let mut removed = None;
+                        for (index, mapping) in self.settings.svg_color_mappings.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.color_edit_button_srgb(&mut mapping.from);
+                                ui.label("→");
+                                ui.color_edit_button_srgb(&mut mapping.to);
+                                if ui.small_button("✖").on_hover_text("Remove this mapping").clicked() {
+                                    removed = Some(index);
+                                }
+                            });
+                        }
+                        if let Some(index) = removed {
+                            self.settings.svg_color_mappings.remove(index);
+                        }
+                        if ui.button("➕ Add mapping").clicked() {
+                            self.settings.svg_color_mappings.push(SvgColorMapping { from: [0, 0, 0], to: self.settings.svg_target_color });
+                        }
+
+                        ui.checkbox(&mut self.settings.svg_recolor_current_color_only, "Only replace currentColor")
+                            .on_hover_text("Keep explicit colors and only replace currentColor, which icon sets use for their themeable parts");
+                        ui.add_enabled_ui(!self.settings.svg_recolor_current_color_only, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("Keep colors:");
+                                let mut removed = None;
+                                for (index, color) in self.settings.svg_preserved_colors.iter().enumerate() {
+                                    if color_swatch(ui, *color).on_hover_text("Click to stop keeping this color").clicked() {
+                                        removed = Some(index);
+                                    }
+                                }
+                                if let Some(index) = removed {
+                                    self.settings.svg_preserved_colors.remove(index);
+                                }
+                                ui.color_edit_button_srgb(&mut self.preserved_color_input);
+                                if ui.button("Add").on_hover_text("Leave this color unchanged when recoloring").clicked()
+                                    && !self.settings.svg_preserved_colors.contains(&self.preserved_color_input) {
+                                    self.settings.svg_preserved_colors.push(self.preserved_color_input);
+                                }
+                            });
                         });
+
+                        if let Some((_, Some([original, recolored]))) = &self.svg_preview {
+                            ui.horizontal(|ui| {
+                                ui.label("Preview:");
+                                ui.image((original.id(), original.size_vec2()));
+                                ui.label("→");
+                                ui.image((recolored.id(), recolored.size_vec2()));
+                                if ui.button("Apply to image").on_hover_text("Reload the displayed SVG with these colors").clicked() {
+                                    apply_recolor = true;
+                                }
+                            });
+                        }
                     }
                     
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.heading("Caches");
+                        help_button(ui, "Recently used images are kept in memory so going back to them is instant. \
+                            The least recently used entries are dropped once a cache reaches its limit.");
+                    });
+                    egui::Grid::new("cache_grid").striped(true).show(ui, |ui| {
+                        for (index, cache) in self.caches.summaries().into_iter().enumerate() {
+                            ui.label(&cache.name);
+                            ui.label(format!("{} items, {:.1} MB", cache.entries, cache.size_bytes as f64 / MB as f64));
+                            let mut limit_mb = (cache.limit_bytes / MB).max(1);
+                            if ui.add(egui::Slider::new(&mut limit_mb, 1..=4096).logarithmic(true).suffix(" MB")).changed() {
+                                self.caches.set_limit(index, limit_mb * MB);
+                            }
+                            if ui.add_enabled(cache.entries > 0, egui::Button::new("Clear")).clicked() {
+                                self.caches.clear(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Total: {:.1} MB", self.caches.total_bytes() as f64 / MB as f64));
+                        if ui.add_enabled(!self.caches.all_empty(), egui::Button::new("Clear all")).clicked() {
+                            self.caches.clear_all();
+                        }
+                    });
+
                     ui.separator();
                     ui.heading("Debug Options");
                     ui.checkbox(&mut self.settings.debug_file_locality_detection, "Debug file locality detection");
+                    ui.checkbox(&mut self.show_icon_cache_stats, "Show icon cache statistics");
+                    let reasons = self.repaint.last_reasons();
+                    ui.label(if reasons.is_empty() {
+                        "Repainting: only on input".to_string()
+                    } else {
+                        format!("Repainting for: {}", reasons.iter().map(RepaintReason::label).collect::<Vec<_>>().join(", "))
+                    });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.settings.capture_error_reports, "Save an error report when an image fails to open");
+                        help_button(ui, "The report contains a hex dump of the start of the file, its detected format, \
+                            a backtrace and your loading settings. Attach it to bug reports.");
+                    });
                     
                     ui.separator();
                     ui.heading("Filename Display");
@@ -273,7 +2215,93 @@ impl ImageViewerApp {
                         });
                     }
                 });
+            if apply_recolor {
+                self.load_selected_image(ctx);
+            }
+            if start_blur_detection {
+                self.measure_sharpness();
+            }
+            if save_policies {
+                self.save_prompt_policies();
+            }
+            if save_svg_colors {
+                self.save_svg_colors();
+            }
+        }
+    }
+
+    /// Recolor settings that change how an SVG renders
+    fn recolor_signature(&self) -> String {
+        format!(
+            "{:?}|{:?}|{:?}|{}",
+            self.settings.svg_target_color,
+            self.settings.svg_preserved_colors,
+            self.settings.svg_color_mappings,
+            self.settings.svg_recolor_current_color_only,
+        )
+    }
+
+    /// Re-render the settings window's before/after thumbnails of the displayed
+    /// SVG when it or the recolor settings changed
+    fn update_svg_preview(&mut self, ctx: &egui::Context) {
+        let (Some(path), Some(source)) = (self.displayed_svg_path(), self.svg_source.as_ref()) else {
+            self.svg_preview = None;
+            return;
+        };
+        let key = format!("{}|{}", path.display(), self.recolor_signature());
+        if self.svg_preview.as_ref().is_some_and(|(previous, _)| *previous == key) {
+            return;
+        }
+        if self.has_failed_to_decode(&path) {
+            self.svg_preview = Some((key, None));
+            return;
+        }
+        // The last preview stays up until the settings stop changing and the new one is rendered
+        let request = match &mut self.svg_preview_request {
+            Some(request) if request.0 == key => request,
+            request => request.insert((key, Instant::now(), None)),
+        };
+        let (key, changed, render) = request;
+        let Some(receiver) = render else {
+            let waited = changed.elapsed();
+            if waited < SVG_PREVIEW_DEBOUNCE {
+                self.repaint.request_after(RepaintReason::Decode, SVG_PREVIEW_DEBOUNCE - waited);
+                return;
+            }
+            let (sender, receiver) = std::sync::mpsc::channel();
+            *render = Some(receiver);
+            let (svg, settings) = (source.original.clone(), self.settings.clone());
+            self.job_engine.submit("Render SVG preview", JobPriority::Interactive, move |_| {
+                let render = |recolor: bool| {
+                    let settings = ImageLoadingSettings { svg_recolor_enabled: recolor, ..settings.clone() };
+                    render_svg_preview(&svg, &settings, SVG_PREVIEW_SIZE).map(|image| from_rgba_image(&image))
+                };
+                let images = render(false).and_then(|original| Ok([original, render(true)?]));
+                let summary = images.as_ref().map(|_| "Rendered".to_string()).map_err(Clone::clone);
+                let _ = sender.send(images);
+                summary
+            });
+            self.repaint.request_after(RepaintReason::Decode, std::time::Duration::from_millis(50));
+            return;
+        };
+        let images = match receiver.try_recv() {
+            Ok(images) => images,
+            Err(std::sync::mpsc::TryRecvError::Empty) => {
+                self.repaint.request_after(RepaintReason::Decode, std::time::Duration::from_millis(50));
+                return;
+            }
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => Err("The preview wasn't rendered".to_string()),
+        };
+        let key = key.clone();
+        self.svg_preview_request = None;
+        if let Err(e) = &images {
+            self.note_timeout(&path, e);
         }
+        let textures = images.ok().map(|[original, recolored]| [
+            ctx.load_texture("svg_preview_original", original, SMOOTH_TEXTURE),
+            ctx.load_texture("svg_preview_recolored", recolored, SMOOTH_TEXTURE),
+        ]);
+        self.svg_preview = Some((key, textures));
     }
 
     fn render_benchmark_window(&mut self, ctx: &egui::Context) {
@@ -288,7 +2316,11 @@ impl ImageViewerApp {
             .open(&mut show_window)
             .default_width(500.0)
             .show(ctx, |ui| {
-                ui.heading("Benchmark Configuration");
+                ui.horizontal(|ui| {
+                    ui.heading("Benchmark Configuration");
+                    help_button(ui, "The threshold is the longest acceptable estimated render time. \
+                        Images estimated to take longer are marked as slow and ask for confirmation before loading.");
+                });
                 
                 ui.horizontal(|ui| {
                     ui.label("Performance threshold (ms):");
@@ -307,7 +2339,12 @@ impl ImageViewerApp {
                 }
                 
                 ui.separator();
-                ui.heading("System Performance Profile");
+                ui.horizontal(|ui| {
+                    ui.heading("System Performance Profile");
+                    help_button(ui, "The score comes from a short storage, memory and arithmetic test; higher is faster. \
+                        ms/MP is the average time per megapixel: decode is reading and decompressing the file, \
+                        texture is uploading the pixels to the GPU. Render estimates multiply these by the image size.");
+                });
                 
                 // Show current system performance category
                 let cpu_score = run_simple_cpu_benchmark();
@@ -325,6 +2362,11 @@ impl ImageViewerApp {
                     ui.colored_label(category_color, format!("{} (Score: {})", performance_category.description(), cpu_score));
                 });
                 
+                if let Some(hardware) = &self.performance_profile.hardware {
+                    ui.label(format!("Hardware: {}", hardware.summary()))
+                        .on_hover_text(format!("Fingerprint: {}", hardware.id()));
+                }
+                
                 ui.separator();
                 
                 if !self.performance_profile.benchmark_results.is_empty() {
@@ -335,7 +2377,11 @@ impl ImageViewerApp {
                     ui.label(format!("Avg texture time: {:.2} ms/MP", caps.avg_texture_time_per_mp));
                     
                     ui.separator();
-                    ui.heading("Format Performance");
+                    ui.horizontal(|ui| {
+                        ui.heading("Format Performance");
+                        help_button(ui, "Average total load time per megapixel for each file format that was benchmarked. \
+                            Used in place of the overall average when estimating images of that format.");
+                    });
                     for (format, time_per_mp) in &caps.format_performance {
                         ui.label(format!("{}: {:.2} ms/MP", format, time_per_mp));
                     }
@@ -354,7 +2400,8 @@ impl ImageViewerApp {
                                 };
                                 
                                 ui.horizontal(|ui| {
-                                    self.icon_renderer.icon_label(ui, ctx, icon_name, 16.0, color);
+                                    let label = if result.success { "Succeeded" } else { "Failed" };
+                                    self.icon_renderer.labeled_icon(ui, ctx, icon_name, 16.0, color, label);
                                     ui.label(format!(
                                         "{} ({}x{}, {:.1}MP): {:.1}ms", 
                                         result.characteristics.format,
@@ -385,49 +2432,165 @@ impl ImageViewerApp {
     fn render_main_panel(&mut self, ctx: &egui::Context) {
         egui::CentralPanel::default().show(ctx, |ui| {
             self.render_file_list(ui, ctx);
+            if self.folder_pane.is_some() {
+                self.render_folder_pane(ui, ctx);
+            }
+            if self.show_metadata_panel {
+                self.render_metadata_panel(ui, ctx);
+            }
+            if self.show_svg_source {
+                self.render_svg_source_panel(ui);
+            }
             self.render_image_display(ui);
         });
     }
 
     fn render_file_list(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
-        egui::SidePanel::left("image_list_panel")
+        let panel = egui::SidePanel::left("image_list_panel")
             .resizable(true)
             .show_inside(ui, |ui| {
-                egui::ScrollArea::vertical().show(ui, |ui| {
-                    ui.heading("Images");
+                egui::CollapsingHeader::new("⭐ Favorites")
+                    .default_open(true)
+                    .show(ui, |ui| self.render_favorites(ui, ctx));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let hint = if self.caption_index.is_empty() { "Filter by name…" } else { "Filter by name, caption or tag…" };
+                    ui.add(egui::TextEdit::singleline(&mut self.filter_text).hint_text(hint).desired_width(160.0))
+                        .on_hover_text(format!("{} images here have captions or tags", self.caption_index.len()));
+                    if !self.filter_text.is_empty() && ui.small_button("✖").on_hover_text("Clear filter").clicked() {
+                        self.filter_text.clear();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("min_rating")
+                        .selected_text(if self.min_rating == 0 { "Any rating".to_string() } else { format!("{}+", "★".repeat(self.min_rating as usize)) })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.min_rating, 0, "Any rating");
+                            for stars in 1..=MAX_RATING {
+                                ui.selectable_value(&mut self.min_rating, stars, format!("{}+", "★".repeat(stars as usize)));
+                            }
+                        });
+                    egui::ComboBox::from_id_salt("label_filter")
+                        .selected_text(self.label_filter.map_or("Any label", |label| label.name()))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.label_filter, None, "Any label");
+                            for label in ColorLabel::ALL {
+                                ui.selectable_value(&mut self.label_filter, Some(label), egui::RichText::new(label.name()).color(label.color()));
+                            }
+                        });
+                    ui.checkbox(&mut self.sort_by_rating, "Sort by rating")
+                        .on_hover_text("Highest rated first; equal ratings keep name order");
+                    if self.settings.detect_blur || self.only_blurry {
+                        ui.checkbox(&mut self.only_blurry, "Possibly blurry")
+                            .on_hover_text("Only list images whose sharpness is below the threshold in the settings");
+                    }
+                });
+                let known_keywords = self.sidecars.known_keywords();
+                if !known_keywords.is_empty() || !self.tag_filter.is_empty() {
+                    ui.horizontal_wrapped(|ui| {
+                        egui::ComboBox::from_id_salt("tag_filter")
+                            .selected_text(if self.tag_filter.is_empty() { "Any tags" } else { "Tags" })
+                            .show_ui(ui, |ui| {
+                                for (keyword, count) in &known_keywords {
+                                    let active = self.tag_filter.iter().any(|tag| tag.eq_ignore_ascii_case(keyword));
+                                    if ui.selectable_label(active, format!("{} ({})", keyword, count)).clicked() {
+                                        if active {
+                                            self.tag_filter.retain(|tag| !tag.eq_ignore_ascii_case(keyword));
+                                        } else {
+                                            self.tag_filter.push(keyword.clone());
+                                        }
+                                    }
+                                }
+                            });
+                        let mut removed = None;
+                        for (index, tag) in self.tag_filter.iter().enumerate() {
+                            if ui.small_button(format!("{} ✖", tag)).on_hover_text("Stop filtering by this tag").clicked() {
+                                removed = Some(index);
+                            }
+                        }
+                        if let Some(index) = removed {
+                            self.tag_filter.remove(index);
+                        }
+                    });
+                }
+                let mut open_archive = None;
+                if archive::is_archive(&self.current_dir) && let Some(parent) = self.current_dir.parent() {
+                    if ui.button("⬆ Close Archive").on_hover_text(parent.display().to_string()).clicked() {
+                        open_archive = Some(parent.to_path_buf());
+                    }
+                } else if !self.archives.is_empty() {
+                    egui::CollapsingHeader::new(format!("Archives ({})", self.archives.len()))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            egui::ScrollArea::vertical().id_salt("archive_list").max_height(120.0).show(ui, |ui| {
+                                for path in &self.archives {
+                                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                                    if ui.selectable_label(false, format!("📦 {}", name)).on_hover_text("Browse the images inside").clicked() {
+                                        open_archive = Some(path.clone());
+                                    }
+                                }
+                            });
+                        });
+                }
+                ui.heading("Images");
+                let visible = self.visible_indices();
+                self.file_rows.validate(self.file_row_signature());
+                let has_benchmark_data = !self.performance_profile.benchmark_results.is_empty();
+                // Every row is one line high, so only the rows in view need laying out
+                let row_height = ui.spacing().interact_size.y;
+                egui::ScrollArea::vertical().show_rows(ui, row_height, visible.len(), |ui, row_range| {
                     let mut changed = false;
-                    for (index, file_info) in self.file_infos.iter().enumerate() {
+                    let mut favorites_changed = false;
+                    let mut selection_changed = false;
+                    let mut download_selected = false;
+                    let mut rating_edit = None;
+                    let mut play_video = None;
+                    let mut checksum_targets = None;
+                    let mut rename = None;
+                    let mut open_in_editor = None;
+                    let mut toggle_stack = None;
+                    let stack_heads: HashMap<usize, &Stack> = if self.settings.stack_images {
+                        self.stacks.iter().map(|stack| (stack.members[0], stack)).collect()
+                    } else {
+                        HashMap::new()
+                    };
+                    let stacked: HashSet<usize> = stack_heads.values().flat_map(|stack| stack.members[1..].iter().copied()).collect();
+                    for &index in &visible[row_range] {
+                        let path = self.file_infos[index].path.clone();
+                        // Estimates read the image header, so never for files that would be downloaded or read over the network
+                        let local = !self.file_infos[index].is_slow_to_read();
+                        let row = self.file_rows.row(&path, local, || {
+                            let filename = path.file_name()
+                                .map(|f| f.to_string_lossy().to_string())
+                                .unwrap_or_else(|| path.to_string_lossy().to_string());
+                            let estimate = has_benchmark_data.then(|| cached_render_time(&mut self.file_infos[index], &self.performance_profile)).flatten();
+                            FileRow::new(&path, self.settings.truncate_filename(&filename), self.settings.get_full_filename_tooltip(&path), local, estimate)
+                        }).clone();
+                        let file_info = &self.file_infos[index];
+                        let rating = self.sidecars.rating(&file_info.path);
                         let is_selected = self.selected_image_index == Some(index);
-                        
-                        // Pre-calculate performance info to avoid borrowing issues
-                        let has_benchmark_data = !self.performance_profile.benchmark_results.is_empty();
-                        let performance_info = if has_benchmark_data && !file_info.will_trigger_download() {
-                            // Only calculate performance for locally available files to avoid triggering downloads
-                            self.will_image_render_quickly(&file_info.path)
-                        } else {
-                            None
-                        };
-                        let estimated_time = if has_benchmark_data && !file_info.will_trigger_download() {
-                            // Only estimate time for locally available files to avoid triggering downloads
-                            estimate_image_render_time(&file_info.path, &self.performance_profile)
-                        } else {
-                            None
-                        };
-                        
+                        let in_selection = self.file_selection.contains(index);
+                        let performance_info = row.estimated_ms.map(|ms| ms <= self.benchmark_threshold_ms);
+                        let blur_score = self.possibly_blurry(&path);
+
                         ui.horizontal(|ui| {
                             // Show file locality status indicator
                             let locality_color = match file_info.locality_status {
                                 crate::file_locality::FileLocalityStatus::Local => egui::Color32::GREEN,
                                 crate::file_locality::FileLocalityStatus::OnDemand => egui::Color32::LIGHT_BLUE,
+                                crate::file_locality::FileLocalityStatus::Hydrating { .. } => egui::Color32::LIGHT_BLUE,
+                                crate::file_locality::FileLocalityStatus::Network => egui::Color32::LIGHT_YELLOW,
                                 crate::file_locality::FileLocalityStatus::Unknown => egui::Color32::GRAY,
                             };
-                            self.icon_renderer.icon_label(ui, ctx, file_info.locality_status.icon(), 16.0, locality_color)
+                            self.icon_renderer.labeled_icon(ui, ctx, file_info.locality_status.icon(), 16.0, locality_color, &file_info.locality_status.label())
                                 .on_hover_text(format!(
                                     "{}\n{}",
-                                    file_info.locality_status.description(),
-                                    if file_info.will_trigger_download() {
+                                    file_info.status_description(),
+                                    if self.download_queue.is_queued(&file_info.path) {
+                                        if self.download_queue.is_paused() { "Queued for download (paused while the computer wakes)" } else { "Queued for download" }.to_string()
+                                    } else if file_info.will_trigger_download() {
                                         if let Some(size) = file_info.estimated_download_size {
-                                            format!("Download size: {:.1} MB", size as f64 / (1024.0 * 1024.0))
+                                            format!("Download size: {:.1} MB{}", size as f64 / (1024.0 * 1024.0), visible_time_suffix(&self.bandwidth, self.settings.link_speed_mbps, &self.performance_profile, &file_info.path, size))
                                         } else {
                                             "Will trigger download".to_string()
                                         }
@@ -435,51 +2598,176 @@ impl ImageViewerApp {
                                         "Safe for immediate access".to_string()
                                     }
                                 ));
+                            if let crate::file_locality::FileLocalityStatus::Hydrating { percent } = file_info.locality_status {
+                                ui.add(egui::ProgressBar::new(percent as f32 / 100.0).desired_width(40.0))
+                                    .on_hover_text(format!("Downloading: {}%", percent));
+                            }
                             
                             // Show performance indicator if benchmark data is available
                             if has_benchmark_data {
                                 if file_info.will_trigger_download() {
                                     // Special indicator for files requiring download
-                                    self.icon_renderer.icon_label(ui, ctx, "cloud", 16.0, egui::Color32::LIGHT_BLUE).on_hover_text("Remote file - performance estimate unavailable until downloaded");
+                                    self.icon_renderer.labeled_icon(ui, ctx, "cloud", 16.0, egui::Color32::LIGHT_BLUE, "Remote")
+                                        .on_hover_text("Remote file - performance estimate unavailable until downloaded");
                                 } else if let Some(will_be_fast) = performance_info {
-                                    let (icon, color) = if will_be_fast { 
-                                        ("circle-check", egui::Color32::GREEN)
-                                    } else { 
-                                        ("clock", egui::Color32::YELLOW)
+                                    let (icon, color, label) = if will_be_fast {
+                                        ("circle-check", egui::Color32::GREEN, "Fast")
+                                    } else {
+                                        ("clock", egui::Color32::YELLOW, "Slow")
                                     };
                                     let tooltip = if will_be_fast { 
                                         "Expected to render quickly" 
                                     } else { 
                                         "May take longer to render" 
                                     };
-                                    self.icon_renderer.icon_label(ui, ctx, icon, 16.0, color).on_hover_text(tooltip);
+                                    self.icon_renderer.labeled_icon(ui, ctx, icon, 16.0, color, label).on_hover_text(tooltip);
                                 } else {
-                                    self.icon_renderer.icon_label(ui, ctx, "help", 16.0, egui::Color32::GRAY).on_hover_text("Performance unknown");
+                                    self.icon_renderer.labeled_icon(ui, ctx, "help", 16.0, egui::Color32::GRAY, "Speed unknown")
+                                        .on_hover_text("Performance unknown");
                                 }
                             }
                             
-                            let filename = file_info.path.file_name()
-                                .map(|f| f.to_string_lossy().to_string())
-                                .unwrap_or_else(|| file_info.path.to_string_lossy().to_string());
-                            
-                            let display_filename = self.settings.truncate_filename(&filename);
-                            let label = ui.selectable_label(is_selected, display_filename);
+                            if let Some(label) = rating.label {
+                                let (rect, _) = ui.allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
+                                ui.painter().circle_filled(rect.center(), 4.5, label.color());
+                            }
+                            if rating.stars > 0 {
+                                ui.label(egui::RichText::new("★".repeat(rating.stars as usize)).small().color(egui::Color32::GOLD))
+                                    .on_hover_text(stars_text(rating.stars));
+                            }
+                            let is_video = row.is_video;
+                            if is_video {
+                                ui.label("🎬").on_hover_text("Video: shows its first keyframe; right-click to play it");
+                            }
+                            if row.has_edits {
+                                ui.weak("✏").on_hover_text("Edited: edits are saved beside the image and the original is unchanged");
+                            }
+                            if let Some(score) = blur_score {
+                                ui.weak("🌫").on_hover_text(format!("Possibly blurry (sharpness {:.0})", score));
+                            }
+                            if let Some((_, verification @ Verification::Mismatch { .. })) = self.checksums.get(&file_info.path) {
+                                ui.colored_label(egui::Color32::RED, "⚠").on_hover_text(verification.description());
+                            }
+                            if let Some(stack) = stack_heads.get(&index).filter(|stack| stack.members.len() == 1) {
+                                // Only RAW files are stacked under it, which aren't listed
+                                ui.weak(format!("+{}", stack.raw.len())).on_hover_text(stack.description());
+                            } else if let Some(stack) = stack_heads.get(&index) {
+                                let expanded = self.expanded_stacks.contains(&path);
+                                let badge = format!("{} {}", if expanded { "⏷" } else { "⏵" }, stack.file_count());
+                                if ui.small_button(badge)
+                                    .on_hover_text(format!("{}\nClick to {} the stack", stack.description(), if expanded { "collapse" } else { "expand" }))
+                                    .clicked() {
+                                    toggle_stack = Some(path.clone());
+                                }
+                            } else if stacked.contains(&index) {
+                                ui.weak("↳");
+                            }
+                            let label = ui.selectable_label(is_selected || in_selection, &row.display_name);
+                            // Dragged onto the second folder pane to move them there
+                            let label = if self.folder_pane.is_some() { label.interact(egui::Sense::drag()) } else { label };
+                            if label.drag_started() {
+                                let dragged = if in_selection { self.file_selection.indices().map(|i| self.file_infos[i].path.clone()).collect() } else { vec![path.clone()] };
+                                label.dnd_set_drag_payload(FileDrag(dragged));
+                            }
                             
                             if label.clicked() {
-                                self.selected_image_index = Some(index);
-                                changed = true;
+                                let (toggle, range) = ui.input(|i| (i.modifiers.command, i.modifiers.shift));
+                                self.file_selection.click(index, toggle, range);
+                                selection_changed = true;
+                                if !toggle && !range {
+                                    self.selected_image_index = Some(index);
+                                    changed = true;
+                                }
                             }
+
+                            let is_svg = row.is_svg;
+                            label.context_menu(|ui| {
+                                let summary = self.selection_summary;
+                                if in_selection && summary.count > 1 {
+                                    if ui.add_enabled(summary.on_demand > 0, egui::Button::new(format!("Download {} Selected On-Demand Images", summary.on_demand)))
+                                        .on_disabled_hover_text("All selected images are available locally")
+                                        .clicked() {
+                                        download_selected = true;
+                                        ui.close_menu();
+                                    }
+                                    ui.separator();
+                                }
+                                if is_svg {
+                                    let state = self.view_states.get_mut(&file_info.path);
+                                    if ui.checkbox(&mut state.disable_recolor, "Show original colors")
+                                        .on_hover_text("Skip SVG recoloring for this file only")
+                                        .changed() {
+                                        // Reload so the preview reflects the new setting
+                                        changed |= is_selected;
+                                        ui.close_menu();
+                                    }
+                                }
+                                if is_video && ui.button("Open in External Player").clicked() {
+                                    play_video = Some(file_info.path.clone());
+                                    ui.close_menu();
+                                }
+                                if let Some(editor) = editor_menu(ui, &self.external_editors, Some(&file_info.path)) {
+                                    open_in_editor = Some((editor, file_info.path.clone()));
+                                }
+                                let targets: Vec<usize> = if in_selection && summary.count > 1 { self.file_selection.indices().collect() } else { vec![index] };
+                                if ui.button("Rename…").clicked() {
+                                    rename = Some(file_info.path.clone());
+                                    ui.close_menu();
+                                }
+                                if ui.button(if targets.len() > 1 { "Compute Checksums of Selected" } else { "Compute Checksums" }).clicked() {
+                                    checksum_targets = Some(targets.clone());
+                                    ui.close_menu();
+                                }
+                                ui.menu_button("Rating", |ui| {
+                                    for stars in 0..=MAX_RATING {
+                                        let text = if stars == 0 { "No rating".to_string() } else { stars_text(stars) };
+                                        if ui.selectable_label(rating.stars == stars, text).clicked() {
+                                            rating_edit = Some((targets.clone(), SidecarEdit::Stars(stars)));
+                                            ui.close_menu();
+                                        }
+                                    }
+                                });
+                                ui.menu_button("Color Label", |ui| {
+                                    if ui.selectable_label(rating.label.is_none(), "None").clicked() {
+                                        rating_edit = Some((targets.clone(), SidecarEdit::Label(None)));
+                                        ui.close_menu();
+                                    }
+                                    for label in ColorLabel::ALL {
+                                        let text = egui::RichText::new(format!("● {}", label.name())).color(label.color());
+                                        if ui.selectable_label(rating.label == Some(label), text).clicked() {
+                                            rating_edit = Some((targets.clone(), SidecarEdit::Label(Some(label))));
+                                            ui.close_menu();
+                                        }
+                                    }
+                                });
+                                ui.separator();
+                                if self.favorites.contains(&file_info.path) {
+                                    if ui.button("Remove from Favorites").clicked() {
+                                        self.favorites.remove(&file_info.path);
+                                        favorites_changed = true;
+                                        ui.close_menu();
+                                    }
+                                } else if ui.button("Add to Favorites").clicked() {
+                                    favorites_changed = self.favorites.add(file_info.path.clone());
+                                    ui.close_menu();
+                                }
+                            });
                             
                             // Combine tooltips for full filename and render time
                             let mut tooltip_parts = Vec::new();
                             
-                            if let Some(filename_tooltip) = self.settings.get_full_filename_tooltip(&file_info.path) {
+                            if let Some(filename_tooltip) = row.name_tooltip {
                                 tooltip_parts.push(filename_tooltip);
                             }
                             
-                            if let Some(time) = estimated_time {
+                            if let Some(time) = row.estimated_ms {
                                 tooltip_parts.push(format!("Estimated render time: {:.0}ms", time));
                             }
+
+                            let keywords = self.sidecars.keywords(&file_info.path);
+                            if !keywords.is_empty() {
+                                tooltip_parts.push(format!("Tags: {}", keywords.join(", ")));
+                            }
                             
                             if !tooltip_parts.is_empty() {
                                 label.on_hover_text(tooltip_parts.join("\n"));
@@ -489,324 +2777,5196 @@ impl ImageViewerApp {
                     if changed {
                         self.load_selected_image(ctx);
                     }
-                });
-            });
-    }
-
-    fn render_image_display(&mut self, ui: &mut egui::Ui) {
-        egui::CentralPanel::default().show_inside(ui, |ui| {
-            // Set a neutral grey background for the image preview area
-            ui.style_mut().visuals.extreme_bg_color = egui::Color32::from_gray(128);
+                    if favorites_changed {
+                        self.save_favorites();
+                    }
+                    if selection_changed {
+                        self.update_selection_summary();
+                    }
+                    if download_selected {
+                        self.download_selected_files();
+                    }
+                    if let Some((targets, edit)) = rating_edit {
+                        self.edit_sidecars(&targets, edit);
+                    }
+                    if let Some(path) = play_video {
+                        self.play_video(&path);
+                    }
+                    if let Some((editor, path)) = open_in_editor {
+                        self.open_in_editor(editor, path);
+                    }
+                    if let Some(path) = toggle_stack
+                        && !self.expanded_stacks.remove(&path) {
+                        self.expanded_stacks.insert(path);
+                    }
+                    if let Some(targets) = checksum_targets {
+                        self.compute_checksums(targets);
+                    }
+                    if rename.is_some() {
+                        self.start_rename(rename);
+                    }
+                });
+                if let Some(path) = open_archive {
+                    self.open_favorite(ctx, &path);
+                }
+            });
+        if let Some(dragged) = panel.response.dnd_release_payload::<PaneDrag>() {
+            self.move_to_folder(ctx, std::slice::from_ref(&dragged.0), self.current_dir.clone());
+        }
+    }
+
+    /// Favorites list: click to switch, drag the handle to reorder, right-click to remove
+    fn render_favorites(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let selected_path = self.selected_image_index.and_then(|i| self.file_infos.get(i)).map(|f| f.path.clone());
+        let mut open = None;
+        let mut remove = None;
+        let mut reorder = None;
+
+        if self.favorites.items().is_empty() {
+            ui.colored_label(egui::Color32::GRAY, "Pin folders or images (right-click an image) to switch to them quickly");
+        }
+        let focused = ctx.input(|i| i.events.iter().any(|event| matches!(event, egui::Event::WindowFocused(true))));
+        if focused || self.favorites_checked.elapsed() >= FAVORITES_RECHECK {
+            self.favorite_kinds.clear();
+            self.favorites_checked = Instant::now();
+        }
+        for item in self.favorites.items() {
+            self.favorite_kinds.entry(item.clone()).or_insert_with(|| std::fs::metadata(item).ok().map(|metadata| metadata.is_dir()));
+        }
+        for (index, item) in self.favorites.items().iter().enumerate() {
+            let kind = self.favorite_kinds.get(item).copied().flatten();
+            let is_folder = kind == Some(true);
+            let name = item.file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or_else(|| item.display().to_string());
+            let current = *item == self.current_dir || selected_path.as_ref() == Some(item);
+
+            let row = ui.horizontal(|ui| {
+                ui.dnd_drag_source(egui::Id::new(("favorite", index)), FavoriteDrag(index), |ui| {
+                    ui.label("☰");
+                })
+                .response
+                .on_hover_text("Drag to reorder");
+                let icon = if is_folder { "📁" } else { "🖼" };
+                let mut text = egui::RichText::new(format!("{} {}", icon, self.settings.truncate_filename(&name)));
+                let mut hover = item.display().to_string();
+                if kind.is_none() {
+                    // Kept enabled so it can still be removed from the context menu
+                    text = text.weak().strikethrough();
+                    hover.push_str(" (not found)");
+                }
+                let label = ui.selectable_label(current, text).on_hover_text(hover);
+                if label.clicked() {
+                    open = Some(item.clone());
+                }
+                label.context_menu(|ui| {
+                    if ui.button("Remove from Favorites").clicked() {
+                        remove = Some(item.clone());
+                        ui.close_menu();
+                    }
+                });
+            }).response;
+
+            if row.dnd_hover_payload::<FavoriteDrag>().is_some() {
+                // Show where the dragged favorite will land
+                let y = row.rect.top();
+                ui.painter().hline(row.rect.x_range(), y, ui.visuals().selection.stroke);
+            }
+            if let Some(dragged) = row.dnd_release_payload::<FavoriteDrag>() {
+                reorder = Some((dragged.0, index));
+            }
+        }
+
+        ui.horizontal(|ui| {
+            let pinned = self.favorites.contains(&self.current_dir);
+            if ui.add_enabled(!pinned, egui::Button::new("＋ Pin this folder"))
+                .on_hover_text(self.current_dir.display().to_string())
+                .clicked() {
+                self.favorites.add(self.current_dir.clone());
+                self.save_favorites();
+            }
+        });
+        ui.horizontal(|ui| {
+            let input = ui.add(egui::TextEdit::singleline(&mut self.folder_path_input)
+                .hint_text("Open folder…")
+                .desired_width(140.0));
+            let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (ui.button("Open").clicked() || submitted) && !self.folder_path_input.trim().is_empty() {
+                open = Some(PathBuf::from(self.folder_path_input.trim()));
+            }
+        });
+
+        if let Some((from, to)) = reorder {
+            self.favorites.move_item(from, to);
+            self.save_favorites();
+        }
+        if let Some(path) = remove {
+            self.favorites.remove(&path);
+            self.save_favorites();
+        }
+        if let Some(path) = open {
+            self.open_favorite(ctx, &path);
+        }
+    }
+
+    fn update_selection_summary(&mut self) {
+        self.selection_summary = SelectionSummary::of(&self.file_infos, &self.file_selection);
+    }
+
+    /// Queue the on-demand files among the selected images for download
+    fn download_selected_files(&mut self) {
+        let paths: Vec<PathBuf> = self.file_selection
+            .indices()
+            .filter_map(|i| self.file_infos.get(i))
+            .filter(|f| f.will_trigger_download())
+            .map(|f| f.path.clone())
+            .collect();
+        self.status_text = format!("Queued {} selected images for download", paths.len());
+        self.download_queue.enqueue_all(paths);
+    }
+
+    fn save_favorites(&mut self) {
+        if let Err(e) = self.favorites.save() {
+            self.status_text = format!("Error: {}", e);
+        }
+    }
+
+    /// Switch to a folder, or to an image's folder with the image selected
+    pub(crate) fn open_favorite(&mut self, ctx: &egui::Context, path: &std::path::Path) {
+        if path.is_dir() || archive::is_archive(path) && path.is_file() {
+            self.open_folder(ctx, path.to_path_buf(), None);
+        } else if let Some((archive, _)) = archive::split_path(path) {
+            self.open_folder(ctx, archive.to_path_buf(), Some(path));
+        } else if path.is_file() && let Some(parent) = path.parent() {
+            self.open_folder(ctx, parent.to_path_buf(), Some(path));
+        } else {
+            self.status_text = format!("Not found: {}", path.display());
+        }
+    }
+
+    /// List the images in `dir`, optionally selecting and loading one of them
+    pub(crate) fn open_folder(&mut self, ctx: &egui::Context, dir: PathBuf, select: Option<&std::path::Path>) {
+        crash::record_action(format!("Open folder {}", dir.display()));
+        self.download_queue.cancel_pending();
+        self.locality_refresh = None;
+        self.file_infos = scan_folder(&dir, &self.settings);
+        self.file_rows.clear();
+        if dir != self.current_dir {
+            self.checksums.clear();
+            self.expanded_stacks.clear();
+        }
+        self.frame_sequences = find_sequences(&self.file_infos);
+        self.raw_companions = stacks::raw_companions(&self.file_infos, stacks::raw_files(&dir));
+        self.stacks = find_stacks(&self.file_infos, &self.raw_companions);
+        self.close_sequence_player();
+        if self.hot_folder.as_ref().is_some_and(|hot| hot.dir() != dir) {
+            self.hot_folder = None;
+        }
+        self.caption_index = CaptionIndex::scan(&dir, &self.file_infos);
+        self.sidecars = SidecarIndex::scan(&dir, &self.file_infos);
+        self.archives = archive::archives_in(&dir);
+        if let Some(pane) = &mut self.folder_pane {
+            // Moves and their undos change both folders
+            pane.refresh(&self.settings.supported_formats);
+        }
+        self.selected_image_index = select.and_then(|path| self.file_infos.iter().position(|f| f.path == path));
+        self.file_selection.clear();
+        if let Some(index) = self.selected_image_index {
+            self.file_selection.select_only(index);
+        }
+        self.update_selection_summary();
+        self.image_texture = None;
+        self.current_image = None;
+        self.online_placeholder = None;
+        self.video_placeholder = None;
+        self.current_metadata = None;
+        self.metadata_sections.clear();
+        self.svg_source = None;
+        self.selection = None;
+        self.last_error_report = None;
+        self.deep_zoom = None;
+        self.compare = None;
+        self.compare_load = None;
+        self.status_text = format!("{}: {} images", dir.display(), self.file_infos.len());
+        self.current_dir = dir;
+        self.index_folder();
+        self.measure_sharpness();
+        #[cfg(feature = "scripting")]
+        self.run_script_hook(crate::scripting::FOLDER_OPENED_HOOK, &self.current_dir.clone());
+        if self.selected_image_index.is_some() {
+            self.load_selected_image(ctx);
+        }
+    }
+
+    /// Ask the metadata providers about the displayed image
+    fn update_metadata_sections(&mut self, path: &std::path::Path) {
+        let file = self.file_infos.iter().find(|f| f.path == path).cloned().unwrap_or_else(|| FileInfo::new(path.to_path_buf()));
+        // Never read an on-demand file's contents just for its metadata
+        let header = if file.will_trigger_download() { Vec::new() } else { read_header(path) };
+        self.metadata_sections = self.metadata_providers.sections(&MetadataSource { path, file: &file, header: &header });
+        // Another tool may have rated the image since the folder was scanned
+        self.sidecars.refresh(path);
+    }
+
+    fn render_metadata_panel(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        egui::SidePanel::right("metadata_panel")
+            .resizable(true)
+            .default_width(240.0)
+            .show_inside(ui, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.heading("Image Info");
+
+                    let Some(metadata) = self.current_metadata.clone() else {
+                        ui.label("No image loaded");
+                        return;
+                    };
+
+                    egui::Grid::new("metadata_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Dimensions:");
+                        ui.label(format!("{} x {}", metadata.width, metadata.height));
+                        ui.end_row();
+                        ui.label("Format:");
+                        ui.label(metadata.format.to_uppercase());
+                        ui.end_row();
+                        ui.label("File size:");
+                        ui.label(format!("{:.2} MB", metadata.file_size as f64 / (1024.0 * 1024.0)));
+                        ui.end_row();
+                        ui.label("Resolution:");
+                        match metadata.dpi {
+                            Some((x, y)) if (x - y).abs() < 0.5 => ui.label(format!("{:.0} DPI", x)),
+                            Some((x, y)) => ui.label(format!("{:.0} x {:.0} DPI", x, y)),
+                            None => ui.colored_label(egui::Color32::GRAY, "Not recorded"),
+                        };
+                        ui.end_row();
+                        if let Some((x, y)) = metadata.dpi {
+                            let unit = self.print_target.unit;
+                            let (width, _) = print_size(metadata.width, metadata.height, x, unit);
+                            let (_, height) = print_size(metadata.width, metadata.height, y, unit);
+                            ui.label("Native print size:");
+                            ui.label(format!("{:.1} x {:.1} {}", width, height, unit.label()));
+                            ui.end_row();
+                        }
+                    });
+
+                    if let Some(index) = self.selected_image_index.filter(|&i| i < self.file_infos.len()) {
+                        let rating = self.sidecars.rating(&self.file_infos[index].path);
+                        let mut edit = None;
+                        ui.horizontal(|ui| {
+                            ui.label("Rating:");
+                            for stars in 1..=MAX_RATING {
+                                let star = if stars <= rating.stars { "★" } else { "☆" };
+                                // Clicking the current rating clears it
+                                if ui.add(egui::Button::new(egui::RichText::new(star).color(egui::Color32::GOLD)).frame(false))
+                                    .on_hover_text(format!("{} (key {})", stars_text(stars), stars))
+                                    .clicked() {
+                                    edit = Some(SidecarEdit::Stars(if rating.stars == stars { 0 } else { stars }));
+                                }
+                            }
+                            let mut label = rating.label;
+                            egui::ComboBox::from_id_salt("metadata_color_label")
+                                .selected_text(label.map_or("No label", |label| label.name()))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut label, None, "No label");
+                                    for option in ColorLabel::ALL {
+                                        ui.selectable_value(&mut label, Some(option), egui::RichText::new(option.name()).color(option.color()));
+                                    }
+                                });
+                            if label != rating.label {
+                                edit = Some(SidecarEdit::Label(label));
+                            }
+                        });
+                        if let Some(edit) = edit {
+                            self.edit_sidecars(&[index], edit);
+                        }
+                        self.render_tag_editor(ui, index);
+                        self.render_checksums(ui, index);
+                    }
+
+                    for (index, section) in self.metadata_sections.iter().enumerate() {
+                        egui::CollapsingHeader::new(&section.title).id_salt(("metadata_section", index)).default_open(true).show(ui, |ui| {
+                            egui::Grid::new(("metadata_section_grid", index)).num_columns(2).show(ui, |ui| {
+                                for (key, value) in &section.entries {
+                                    ui.label(format!("{}:", key));
+                                    ui.add(egui::Label::new(value).wrap());
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                    }
+
+                    ui.separator();
+                    ui.heading("Print Calculator");
+
+                    ui.horizontal(|ui| {
+                        ui.label("Units:");
+                        let mut unit = self.print_target.unit;
+                        ui.selectable_value(&mut unit, PrintUnit::Inches, "Inches");
+                        ui.selectable_value(&mut unit, PrintUnit::Centimeters, "Centimeters");
+                        if unit != self.print_target.unit {
+                            self.print_target.set_unit(unit);
+                        }
+                    });
+
+                    let unit_label = self.print_target.unit.label();
+                    ui.horizontal(|ui| {
+                        ui.label("Print size:");
+                        ui.add(egui::DragValue::new(&mut self.print_target.width).range(0.1..=1000.0).speed(0.1).suffix(unit_label));
+                        ui.label("x");
+                        ui.add(egui::DragValue::new(&mut self.print_target.height).range(0.1..=1000.0).speed(0.1).suffix(unit_label));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Target resolution:");
+                        ui.add(egui::DragValue::new(&mut self.print_target.dpi).range(30.0..=2400.0).speed(1.0).suffix(" DPI"));
+                    });
+
+                    if let Some((x, y)) = metadata.dpi
+                        && ui.button("Use native size").clicked() {
+                        let unit = self.print_target.unit;
+                        self.print_target.width = print_size(metadata.width, metadata.height, x, unit).0;
+                        self.print_target.height = print_size(metadata.width, metadata.height, y, unit).1;
+                    }
+
+                    ui.separator();
+
+                    let target = &self.print_target;
+                    let achieved_dpi = effective_dpi(metadata.width, metadata.height, target.width, target.height, target.unit);
+                    let (needed_width, needed_height) = required_pixels(target.width, target.height, target.dpi, target.unit);
+
+                    ui.label(format!("Resolution at this size: {:.0} DPI", achieved_dpi));
+                    ui.label(format!("Needed for {:.0} DPI: {} x {} px", target.dpi, needed_width, needed_height));
+
+                    ui.horizontal(|ui| {
+                        if achieved_dpi + 0.5 < target.dpi {
+                            self.icon_renderer.icon_label(ui, ctx, "alert-triangle", 16.0, egui::Color32::YELLOW);
+                            ui.colored_label(egui::Color32::YELLOW, "Insufficient resolution for this print size");
+                        } else {
+                            self.icon_renderer.icon_label(ui, ctx, "circle-check", 16.0, egui::Color32::GREEN);
+                            ui.colored_label(egui::Color32::GREEN, "Sufficient resolution");
+                        }
+                    });
+                });
+            });
+    }
+
+    fn render_svg_source_panel(&mut self, ui: &mut egui::Ui) {
+        let Some(source) = &self.svg_source else {
+            return;
+        };
+        egui::SidePanel::right("svg_source_panel")
+            .resizable(true)
+            .default_width(360.0)
+            .show_inside(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("SVG Source");
+                    ui.add_enabled_ui(source.recolored.is_some(), |ui| {
+                        ui.selectable_value(&mut self.svg_source_show_recolored, false, "Original");
+                        ui.selectable_value(&mut self.svg_source_show_recolored, true, "Recolored");
+                    }).response.on_disabled_hover_text("Recoloring is not applied to this file");
+                });
+
+                let mut text = match &source.recolored {
+                    Some(recolored) if self.svg_source_show_recolored => recolored.as_str(),
+                    _ => source.original.as_str(),
+                };
+                if ui.small_button("Copy").clicked() {
+                    ui.ctx().copy_text(text.to_string());
+                }
+                ui.separator();
+
+                let dark_mode = ui.visuals().dark_mode;
+                let highlighter = &mut self.svg_highlighter;
+                let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                    let mut job = highlighter.layout_job(text, egui::TextStyle::Monospace.resolve(ui.style()), dark_mode);
+                    job.wrap.max_width = wrap_width;
+                    ui.fonts(|fonts| fonts.layout_job(job))
+                };
+                egui::ScrollArea::both().show(ui, |ui| {
+                    ui.add(egui::TextEdit::multiline(&mut text)
+                        .code_editor()
+                        .desired_width(f32::INFINITY)
+                        .layouter(&mut layouter));
+                });
+            });
+    }
+
+    fn render_image_display(&mut self, ui: &mut egui::Ui) {
+        egui::CentralPanel::default().show_inside(ui, |ui| {
+            // Theme background unless the image needs a contrasting backdrop or a matte
+            let theme_color = self.settings.theme.image_background();
+            let loaded = self.image_texture.is_some();
+            let backdrop = resolve_backdrop(
+                self.settings.preview_background,
+                self.auto_backdrop.filter(|_| loaded),
+                self.image_matte.filter(|_| loaded),
+                theme_color,
+            );
+            let fill = match backdrop {
+                Backdrop::Solid(color) => color,
+                Backdrop::Checkerboard => theme_color,
+            };
+            ui.style_mut().visuals.extreme_bg_color = fill;
             let frame = egui::Frame::default()
-                .fill(egui::Color32::from_gray(128))
+                .fill(fill)
                 .inner_margin(egui::Margin::same(10));
             
             frame.show(ui, |ui| {
                 ui.vertical_centered(|ui| {
-                    if let Some(texture) = &self.image_texture {
-                        if self.settings.auto_scale_to_fit {
-                            // Calculate available space for the image
-                            let available_size = ui.available_size();
-                            let texture_size = texture.size_vec2();
-                            
-                            // Calculate scale factor to fit image within available space
-                            let scale_x = available_size.x / texture_size.x;
-                            let scale_y = available_size.y / texture_size.y;
-                            let scale = scale_x.min(scale_y).min(1.0); // Don't scale up, only down
-                            
-                            let scaled_size = texture_size * scale;
-                            ui.image((texture.id(), scaled_size));
-                        } else {
-                            ui.image(texture);
-                        }
+                    if self.deep_zoom.is_some() {
+                        self.last_view = None;
+                        self.render_deep_zoom(ui);
+                        return;
+                    }
+                    if self.compare.is_some() {
+                        self.last_view = None;
+                        self.render_compare(ui);
+                        return;
+                    }
+                    if self.online_placeholder.is_some() {
+                        self.display_scale = None;
+                        self.last_view = None;
+                        self.render_online_placeholder(ui);
+                        return;
+                    }
+                    if self.video_placeholder.is_some() {
+                        self.display_scale = None;
+                        self.last_view = None;
+                        self.render_video_placeholder(ui);
+                        return;
+                    }
+                    let texture = if self.settings.soft_proof_enabled {
+                        self.proofed_texture(ui.ctx())
                     } else {
-                        // Customize status text color with good contrast against grey background
-                        let text_color = if self.status_text.contains("Error") || self.status_text.contains("Skipped") {
-                            egui::Color32::from_rgb(255, 120, 120) // Light red for errors - good contrast on grey
-                        } else if self.status_text.contains("recolored") {
-                            egui::Color32::from_rgb(120, 255, 120) // Light green for successful operations
+                        self.image_texture.clone()
+                    };
+                    if let Some(texture) = texture {
+                        // Reserved so the checkerboard can be painted under the image once its rect is known
+                        let checkerboard_slot = ui.painter().add(egui::Shape::Noop);
+                        let area = ui.available_rect_before_wrap();
+                        ui.allocate_rect(area, egui::Sense::hover());
+                        self.handle_touch_zoom(ui, area);
+
+                        let texture_size = texture.size_vec2();
+                        let scrolling = self.panorama_scroll.is_active();
+                        let fit = if scrolling {
+                            FitMode::Height
                         } else {
-                            egui::Color32::from_rgb(240, 240, 240) // Very light gray/white for normal status
+                            self.locked_fit.unwrap_or(self.settings.fit_mode).resolve(texture_size, area.size())
                         };
-                        
-                        ui.colored_label(text_color, &self.status_text);
+                        let opening = if scrolling { OpeningZoom::Fit } else { self.opening_zoom(texture_size) };
+                        self.current_fit = (opening == OpeningZoom::Fit).then_some(fit);
+                        let base_scale = opening.scale(fit, texture_size, area.size());
+                        let scaled_size = texture_size * base_scale * self.zoom_factor;
+                        self.display_scale = Some(base_scale * self.zoom_factor);
+                        self.scroll_overflowing_image(ui, area, scaled_size);
+                        let image_rect = egui::Rect::from_center_size(area.center() + self.pan_offset, scaled_size);
+                        let sharp_region = self.update_sharp_svg(ui.ctx(), &texture, image_rect, area);
+                        let overlay = self.sharp_svg.overlay(image_rect, sharp_region.as_ref());
+                        // A render covering the whole view hides the blurry texture, edges and all
+                        let tint = if overlay.is_some_and(|(_, _, covers)| covers) { egui::Color32::TRANSPARENT } else { egui::Color32::WHITE };
+                        let response = ui.put(image_rect, egui::Image::new((texture.id(), scaled_size)).tint(tint).sense(egui::Sense::click_and_drag()));
+                        if let Some((sharp, rect, _)) = overlay {
+                            let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+                            ui.painter_at(area).image(sharp.id(), rect, uv, egui::Color32::WHITE);
+                        }
+                        self.paint_pixel_guides(ui, area, image_rect, base_scale * self.zoom_factor);
+
+                        // Dragging pans an image larger than the view; otherwise a finger swipe changes image
+                        let overflowing = scaled_size.x > area.width() + 0.5 || scaled_size.y > area.height() + 0.5;
+                        if overflowing && self.settings.show_navigator {
+                            self.render_navigator(ui, area, image_rect, texture.id());
+                        }
+                        let panning = (self.zoom_factor > 1.0 || overflowing) && !self.eyedropper_active
+                            && ui.input(|i| !i.modifiers.shift && i.multi_touch().is_none());
+                        if panning && response.dragged_by(egui::PointerButton::Primary) {
+                            self.pan_offset += response.drag_delta();
+                            self.align_to_start = false;
+                        }
+                        // Without decorations, the picture frame's window moves by dragging the image
+                        if self.picture_frame.is_some() && !panning && response.drag_started_by(egui::PointerButton::Primary)
+                            && !ui.input(|i| i.pointer.press_origin())
+                                .is_some_and(|origin| picture_frame::resize_direction(ui.ctx().screen_rect(), origin).is_some()) {
+                            ui.ctx().send_viewport_cmd(egui::ViewportCommand::StartDrag);
+                        }
+                        if self.settings.touch_gestures && !panning {
+                            self.swipe_tracker.update(ui.ctx(), response.hovered());
+                        } else {
+                            self.swipe_tracker.cancel();
+                        }
+                        if self.eyedropper_active {
+                            self.handle_eyedropper(&response);
+                        } else if self.settings.double_click_zoom && response.double_clicked() {
+                            self.set_image_zoom(if opening == OpeningZoom::Fit { OpeningZoom::Actual } else { OpeningZoom::Fit });
+                        }
+                        if self.settings.gesture_navigation {
+                            self.gesture_tracker.update(ui.ctx(), response.hovered());
+                        }
+                        if backdrop == Backdrop::Checkerboard {
+                            ui.painter().set(checkerboard_slot, egui::Shape::Vec(checkerboard_shapes(response.rect.intersect(area), 8.0)));
+                        }
+                        self.last_view = Some(ViewSnapshot {
+                            area,
+                            image_rect,
+                            background: fill,
+                            checkerboard: backdrop == Backdrop::Checkerboard,
+                            selection: self.selection,
+                            pixels_per_point: ui.ctx().pixels_per_point(),
+                        });
+                        self.handle_selection_drag(ui, &response);
+                        if self.panorama_scroll.is_active() {
+                            self.render_panorama_controls(ui, area);
+                        }
+                    } else {
+                        self.display_scale = None;
+                        self.last_view = None;
                     }
                 });
             });
         });
     }
 
-    fn handle_keyboard_nav(&mut self, ctx: &egui::Context) {
-        let mut changed = false;
-        if ctx.input(|i| i.key_pressed(egui::Key::ArrowUp)) {
-            if let Some(selected_index) = self.selected_image_index {
-                if selected_index > 0 {
-                    self.selected_image_index = Some(selected_index - 1);
-                    changed = true;
+    /// Name, size and a download button for an online-only file, over its
+    /// saved preview if there is one
+    fn render_online_placeholder(&mut self, ui: &mut egui::Ui) {
+        let Some(placeholder) = &self.online_placeholder else {
+            return;
+        };
+        let ctx = ui.ctx().clone();
+        let file = &placeholder.file;
+        ui.add_space(ui.available_height() * 0.1);
+        if let Some((preview, source)) = &placeholder.preview {
+            let size = preview.size_vec2();
+            let scale = (ui.available_width() * 0.6 / size.x).min(ui.available_height() * 0.5 / size.y).min(2.0);
+            ui.add(egui::Image::new((preview.id(), size * scale)).tint(egui::Color32::from_white_alpha(170)))
+                .on_hover_text(*source);
+        }
+        self.icon_renderer.icon_label(ui, &ctx, "cloud", 48.0, egui::Color32::LIGHT_BLUE);
+        let filename = file.path.file_name().unwrap_or_default().to_string_lossy();
+        ui.heading(self.settings.truncate_filename(&filename));
+        let provider = file.provider.as_ref().map(|provider| format!("{} · ", provider.name())).unwrap_or_default();
+        ui.label(format!("{}{}", provider, file.locality_status.description()));
+        ui.add_space(8.0);
+        if self.open_after_download.as_ref() == Some(&file.path) {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Downloading…");
+            });
+            return;
+        }
+        let download = match file.estimated_download_size {
+            Some(size) => {
+                let clicked = ui.button(format!("Download ({:.1} MB)", size as f64 / (1024.0 * 1024.0))).clicked();
+                let estimate = visible_time_suffix(&self.bandwidth, self.settings.link_speed_mbps, &self.performance_profile, &file.path, size);
+                if !estimate.is_empty() {
+                    ui.weak(estimate.trim().trim_matches(['(', ')']));
+                }
+                clicked
+            }
+            None => ui.button("Download").clicked(),
+        };
+        if download {
+            let file = file.clone();
+            self.dismiss_prompts();
+            self.download_then_open(&ctx, file);
+        }
+    }
+
+    /// Name, the reason there's no poster frame and a play button for a video
+    fn render_video_placeholder(&mut self, ui: &mut egui::Ui) {
+        let Some((path, reason)) = self.video_placeholder.clone() else {
+            return;
+        };
+        let ctx = ui.ctx().clone();
+        ui.add_space(ui.available_height() * 0.1);
+        self.icon_renderer.icon_label(ui, &ctx, "player-play", 48.0, egui::Color32::GRAY);
+        let filename = path.file_name().unwrap_or_default().to_string_lossy();
+        ui.heading(self.settings.truncate_filename(&filename));
+        ui.weak(reason);
+        ui.add_space(8.0);
+        if ui.button("▶ Play").on_hover_text("Open in the system's video player").clicked() {
+            self.play_video(&path);
+        }
+    }
+
+    /// Download the selected on-demand `file` on the queue, ahead of other
+    /// downloads, and open it once it's local
+    fn download_then_open(&mut self, ctx: &egui::Context, file: FileInfo) {
+        let filename = file.path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        self.status_text = format!("Downloading {}…", self.settings.truncate_filename(&filename));
+        self.download_queue.enqueue_next(file.path.clone());
+        self.open_after_download = Some(file.path.clone());
+        self.show_online_placeholder(ctx, file);
+    }
+
+    /// Show the placeholder for `file` until it's downloaded, with the
+    /// system's cached thumbnail or else the preview saved by the viewer
+    fn show_online_placeholder(&mut self, ctx: &egui::Context, file: FileInfo) {
+        let preview = shell_thumbnail::cached_thumbnail(&file.path, preview_store::PREVIEW_SIZE)
+            .map(|image| (image, "Thumbnail from the system's cache"))
+            .or_else(|| {
+                let image = self.preview_store.as_ref()?.lock().unwrap().load(&file.path)?;
+                Some((image, "Saved when this file was last viewed"))
+            })
+            .map(|(image, source)| (ctx.load_texture("online_placeholder_preview", image, SMOOTH_TEXTURE), source));
+        self.online_placeholder = Some(OnlinePlaceholder { file, preview });
+    }
+
+    /// Save the pending preview once the thumbnail delay has passed, so
+    /// flicking through images on battery doesn't write one for each
+    fn save_pending_preview(&mut self) {
+        let Some((shown_at, ..)) = &self.pending_preview else {
+            return;
+        };
+        let waited = shown_at.elapsed();
+        let delay = self.background_budget().thumbnail_delay;
+        if waited < delay {
+            self.repaint.request_after(RepaintReason::Background, delay - waited);
+            return;
+        }
+        let Some((_, path, image)) = self.pending_preview.take() else {
+            return;
+        };
+        let Some(store) = self.preview_store.clone() else {
+            return;
+        };
+        std::thread::spawn(move || {
+            // Shrunk before locking, so showing a placeholder meanwhile isn't held up
+            let saved = preview_store::shrink(&image).and_then(|preview| store.lock().unwrap().save(&path, &preview));
+            if let Err(e) = saved {
+                eprintln!("Failed to save preview: {}", e);
+            }
+        });
+    }
+
+    /// Grid, rulers and crosshair over an image drawn `scale` points per pixel
+    fn paint_pixel_guides(&self, ui: &egui::Ui, area: egui::Rect, image_rect: egui::Rect, scale: f32) {
+        let painter = ui.painter_at(area);
+        if self.settings.pixel_grid && scale * ui.ctx().pixels_per_point() >= pixel_grid::GRID_MIN_SCALE {
+            let stroke = egui::Stroke::new(1.0_f32, egui::Color32::from_rgba_unmultiplied(128, 128, 128, 110));
+            painter.extend(pixel_grid::grid_shapes(image_rect, scale, area, stroke));
+        }
+        if self.settings.pixel_crosshair
+            && let Some(pointer) = ui.input(|i| i.pointer.hover_pos()).filter(|pos| area.contains(*pos)) {
+            pixel_grid::paint_crosshair(&painter, area, pointer, image_rect, scale);
+        }
+        if self.settings.pixel_rulers {
+            pixel_grid::paint_rulers(&painter, area, image_rect, scale, ui.visuals());
+        }
+    }
+
+    /// Minimap of an image larger than the view; dragging or clicking it pans
+    fn render_navigator(&mut self, ui: &egui::Ui, area: egui::Rect, image_rect: egui::Rect, texture: egui::TextureId) {
+        let rect = navigator::navigator_rect(area, image_rect.size());
+        let viewport = navigator::viewport_rect(rect, image_rect, area);
+        let response = ui.interact(rect, ui.id().with("navigator"), egui::Sense::click_and_drag())
+            .on_hover_cursor(egui::CursorIcon::Grab);
+        let jump = response.clicked() || (response.drag_started() && !response.interact_pointer_pos().is_some_and(|pos| viewport.contains(pos)));
+        if jump && let Some(pointer) = response.interact_pointer_pos() {
+            self.pan_offset += navigator::pan_to(rect, image_rect, area, pointer);
+        } else if response.dragged() {
+            self.pan_offset += navigator::pan_for_drag(rect, image_rect, response.drag_delta());
+        }
+        if response.clicked() || response.dragged() {
+            self.align_to_start = false;
+            self.panorama_scroll.stop();
+        }
+        navigator::paint(&ui.painter_at(area), texture, rect, viewport, ui.visuals());
+    }
+
+    /// Re-render the visible part of a magnified SVG at screen resolution once
+    /// the view settles; returns the visible region when it's magnified
+    fn update_sharp_svg(&mut self, ctx: &egui::Context, texture: &TextureHandle, image_rect: egui::Rect, area: egui::Rect) -> Option<Region> {
+        let Some(path) = self.displayed_svg_path()
+            .filter(|path| !self.settings.soft_proof_enabled && !self.history.has_image_edits() && !self.has_failed_to_decode(path)) else {
+            self.sharp_svg = SharpSvg::default();
+            return None;
+        };
+        self.sharp_svg.track(texture.id());
+        if let Err(e) = self.sharp_svg.receive(ctx) {
+            self.note_timeout(&path, &e);
+            self.status_text = format!("Couldn't render the SVG sharper: {}", e);
+        }
+        let region = visible_region(image_rect, area, texture.size_vec2(), ctx.pixels_per_point());
+        if let Some(wanted) = self.sharp_svg.request(region, Instant::now())
+            && let Some(source) = &self.svg_source {
+            let (sender, receiver) = std::sync::mpsc::channel();
+            let svg = source.original.clone();
+            let settings = self.svg_settings_for(&path);
+            let ctx = ctx.clone();
+            std::thread::spawn(move || {
+                let region = render_svg_region(&svg, &settings, wanted.uv, wanted.size).map(|image| from_rgba_image(&image));
+                let _ = sender.send((wanted, region));
+                ctx.request_repaint();
+            });
+            self.sharp_svg.start(receiver);
+        }
+        if self.sharp_svg.is_waiting() {
+            self.repaint.request_after(RepaintReason::Animation, SETTLE_DELAY);
+        }
+        region
+    }
+
+    /// Pinch (or Ctrl+scroll) zooms about the touch point; two-finger drag pans
+    fn handle_touch_zoom(&mut self, ui: &egui::Ui, area: egui::Rect) {
+        if !self.settings.touch_gestures || !ui.rect_contains_pointer(area) {
+            return;
+        }
+        let (zoom_delta, touch, pointer) = ui.input(|i| (i.zoom_delta(), i.multi_touch(), i.pointer.hover_pos()));
+        if zoom_delta != 1.0 {
+            let new_zoom = (self.zoom_factor * zoom_delta).clamp(MIN_ZOOM, MAX_ZOOM);
+            let pivot = touch.map(|t| t.center_pos).or(pointer).unwrap_or(area.center());
+            self.pan_offset = zoom_about(self.pan_offset, area.center(), pivot, new_zoom / self.zoom_factor);
+            self.zoom_factor = new_zoom;
+        }
+        if let Some(touch) = touch {
+            self.pan_offset += touch.translation_delta;
+        }
+    }
+
+    /// Keep an unzoomed image that is fitted along one side within the view,
+    /// letting the mouse wheel scroll along its long side
+    fn scroll_overflowing_image(&mut self, ui: &egui::Ui, area: egui::Rect, scaled_size: egui::Vec2) {
+        let overflow = ((scaled_size - area.size()) / 2.0).max(egui::Vec2::ZERO);
+        if self.zoom_factor > 1.0 {
+            // Zoomed in, the view pans freely, but auto-scroll still covers the zoomed width
+            self.advance_panorama_scroll(ui, overflow);
+            return;
+        }
+        if self.align_to_start {
+            self.pan_offset = overflow;
+            self.align_to_start = false;
+        }
+        if overflow != egui::Vec2::ZERO && ui.rect_contains_pointer(area) {
+            let mut delta = ui.input(|i| if i.modifiers.command { egui::Vec2::ZERO } else { i.smooth_scroll_delta });
+            if overflow.y == 0.0 {
+                // A panorama scrolls sideways with an ordinary wheel
+                delta = egui::vec2(delta.x + delta.y, 0.0);
+            }
+            self.pan_offset += delta;
+        }
+        self.pan_offset = self.pan_offset.clamp(-overflow, overflow);
+        self.advance_panorama_scroll(ui, overflow);
+    }
+
+    /// Move a scrolling panorama along, between the ends of the current `overflow`
+    fn advance_panorama_scroll(&mut self, ui: &egui::Ui, overflow: egui::Vec2) {
+        if self.panorama_scroll.is_active() {
+            if overflow.x <= 0.0 {
+                self.panorama_scroll.stop();
+                self.status_text = "Auto-scroll stopped: the image fits the window's width".to_string();
+                return;
+            }
+            let dt = ui.input(|i| i.stable_dt).min(0.1);
+            self.pan_offset.x = self.panorama_scroll.step(self.pan_offset.x, overflow.x, self.settings.panorama_scroll_speed, dt);
+            self.repaint.request(RepaintReason::Animation);
+        }
+    }
+
+    fn toggle_panorama_scroll(&mut self) {
+        if self.panorama_scroll.is_active() {
+            self.panorama_scroll.stop();
+        } else if self.image_texture.is_some() {
+            self.panorama_scroll.start();
+            self.reset_zoom();
+        }
+    }
+
+    /// Stop and speed buttons shown over a scrolling panorama
+    fn render_panorama_controls(&mut self, ui: &mut egui::Ui, area: egui::Rect) {
+        let rect = egui::Rect::from_center_size(egui::pos2(area.center().x, area.bottom() - 24.0), egui::vec2(260.0, 32.0));
+        ui.scope_builder(egui::UiBuilder::new().max_rect(rect), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("⏹").on_hover_text("Stop auto-scroll (P)").clicked() {
+                        self.panorama_scroll.stop();
+                    }
+                    let speed = &mut self.settings.panorama_scroll_speed;
+                    if ui.button("➖").on_hover_text("Slower").clicked() {
+                        *speed = (*speed / 1.5).max(MIN_SCROLL_SPEED);
+                    }
+                    ui.add(egui::DragValue::new(speed).range(MIN_SCROLL_SPEED..=MAX_SCROLL_SPEED).speed(1.0).suffix(" pt/s"));
+                    if ui.button("➕").on_hover_text("Faster").clicked() {
+                        *speed = (*speed * 1.5).min(MAX_SCROLL_SPEED);
+                    }
+                });
+            });
+        });
+    }
+
+    /// Fetch a tiled image's descriptor in the background
+    fn open_deep_zoom(&mut self, ctx: &egui::Context, location: String) {
+        crash::record_action(format!("Open deep zoom image {}", location));
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let result = crate::deep_zoom::open(&location);
+            let _ = sender.send((location, result));
+            ctx.request_repaint();
+        });
+        self.deep_zoom_opening = Some(receiver);
+        self.status_text = "Opening deep zoom image…".to_string();
+    }
+
+    fn handle_deep_zoom_dialog(&mut self, ctx: &egui::Context) {
+        if let Some(receiver) = &self.deep_zoom_opening
+            && let Ok((location, result)) = receiver.try_recv() {
+            self.deep_zoom_opening = None;
+            match result {
+                Ok(image) => {
+                    self.status_text = format!("{}: {}×{} in {} levels", location, image.width, image.height, image.max_level() + 1);
+                    self.deep_zoom = Some(DeepZoomSession::new(image));
+                    self.show_deep_zoom_dialog = false;
+                    self.panorama_scroll.stop();
+                }
+                Err(e) => self.status_text = format!("Error: {}", e),
+            }
+        }
+        if !self.show_deep_zoom_dialog {
+            return;
+        }
+
+        let mut open = None;
+        egui::Window::new("Open Deep Zoom Image")
+            .open(&mut self.show_deep_zoom_dialog)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Path or URL of a .dzi descriptor or a IIIF info.json:");
+                let input = ui.add(egui::TextEdit::singleline(&mut self.deep_zoom_input)
+                    .hint_text("https://example.org/iiif/scan/info.json")
+                    .desired_width(360.0));
+                let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                ui.horizontal(|ui| {
+                    let opening = self.deep_zoom_opening.is_some();
+                    let ready = !opening && !self.deep_zoom_input.trim().is_empty();
+                    if ui.add_enabled(ready, egui::Button::new("Open")).clicked() || (submitted && ready) {
+                        open = Some(self.deep_zoom_input.trim().to_string());
+                    }
+                    if opening {
+                        ui.spinner();
+                    }
+                });
+                if !cfg!(feature = "http") {
+                    ui.colored_label(egui::Color32::GRAY, "URLs need a build with the `http` feature; local files work in any build");
+                }
+            });
+        if let Some(location) = open {
+            self.open_deep_zoom(ctx, location);
+        }
+    }
+
+    /// Draw the visible tiles of the open deep zoom image, fetching missing ones
+    fn render_deep_zoom(&mut self, ui: &mut egui::Ui) {
+        let area = ui.available_rect_before_wrap();
+        let response = ui.allocate_rect(area, egui::Sense::click_and_drag());
+        let pixels_per_point = ui.ctx().pixels_per_point() as f64;
+        let Some(session) = self.deep_zoom.as_mut() else {
+            return;
+        };
+        let size = [area.width() as f64, area.height() as f64];
+        let fit = DeepZoomView::fit(&session.image, size);
+        let view = session.view.get_or_insert(fit);
+
+        // Drag pans, the wheel or a pinch zooms about the pointer, double-click fits
+        if response.dragged_by(egui::PointerButton::Primary) {
+            view.pan([response.drag_delta().x as f64, response.drag_delta().y as f64]);
+        }
+        if response.double_clicked() {
+            *view = fit;
+        }
+        if ui.rect_contains_pointer(area) {
+            let (zoom_delta, scroll, pointer) = ui.input(|i| (i.zoom_delta(), i.smooth_scroll_delta.y, i.pointer.hover_pos()));
+            let factor = zoom_delta as f64 * (scroll as f64 / 200.0).exp();
+            if factor != 1.0 {
+                let pivot = pointer.unwrap_or(area.center()) - area.center();
+                view.zoom_about(factor, [pivot.x as f64, pivot.y as f64], fit.scale / 2.0, MAX_ZOOM as f64 / pixels_per_point);
+            }
+        }
+        let view = *view;
+
+        // Tiles with at least one pixel per screen pixel, over coarser levels while they load
+        let image = &session.image;
+        let target = image.level_for_scale(view.scale * pixels_per_point);
+        let visible = view.visible_rect(size);
+        let painter = ui.painter_at(area);
+        let mut missing = Vec::new();
+        {
+            let mut tiles = self.deep_zoom_tiles.lock().unwrap();
+            for level in target.saturating_sub(DEEP_ZOOM_FALLBACK_LEVELS)..=target {
+                for tile in image.visible_tiles(level, visible) {
+                    let location = image.tile_location(tile);
+                    if let Some(texture) = tiles.get(&location) {
+                        let [x0, y0, x1, y1] = image.tile_image_rect(tile);
+                        let [left, top] = view.to_screen([x0, y0]);
+                        let [right, bottom] = view.to_screen([x1, y1]);
+                        let rect = egui::Rect::from_min_max(
+                            area.center() + egui::vec2(left as f32, top as f32),
+                            area.center() + egui::vec2(right as f32, bottom as f32),
+                        );
+                        painter.image(texture.id(), rect, egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)), egui::Color32::WHITE);
+                    } else if !session.failed.contains(&tile) {
+                        missing.push((tile, location));
+                    }
+                }
+            }
+        }
+        // The loader takes the latest request first, so the few coarse tiles
+        // are asked for last and arrive first as placeholders
+        let wanted: HashSet<TileId> = missing.iter().map(|(tile, _)| *tile).collect();
+        for (tile, location) in missing.into_iter().rev() {
+            session.loader.request(tile, location);
+        }
+        // Panning away drops tiles that haven't started loading
+        session.loader.retain(|tile| wanted.contains(tile));
+
+        let mut first_error = None;
+        for (tile, result) in session.loader.poll() {
+            match result {
+                Ok(pixels) => {
+                    let location = image.tile_location(tile);
+                    let bytes = pixels.pixels.len() * 4;
+                    let texture = ui.ctx().load_texture(format!("deep_zoom_{}", location), pixels, Default::default());
+                    self.deep_zoom_tiles.lock().unwrap().insert(location, texture, bytes);
+                    self.repaint.request(RepaintReason::Decode);
+                }
+                Err(e) => {
+                    session.failed.insert(tile);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        if let Some(e) = first_error {
+            self.status_text = format!("Error: {}", e);
+        }
+        if session.loader.is_busy() {
+            self.repaint.request_after(RepaintReason::Download, std::time::Duration::from_millis(50));
+        }
+        self.display_scale = Some(view.scale as f32);
+    }
+
+    /// Whether decoding `path` failed or took too long before
+    fn has_failed_to_decode(&self, path: &std::path::Path) -> bool {
+        self.decode_failures.lock().is_ok_and(|failures| failures.contains(path))
+    }
+
+    /// Remember `path` as unreadable if decoding it gave up with `error` for
+    /// taking too long, so it isn't tried again in the background
+    fn note_timeout(&self, path: &std::path::Path, error: &str) {
+        if timed_out(error) && let Ok(mut failures) = self.decode_failures.lock() {
+            failures.insert(path.to_path_buf());
+        }
+    }
+
+    /// Compare the two selected images, A being the first in the list. They
+    /// are decoded on the job engine and shown by `process_compare_load`.
+    fn open_compare(&mut self, ctx: &egui::Context) {
+        let paths: Vec<PathBuf> = self.file_selection.indices()
+            .filter_map(|i| self.file_infos.get(i))
+            .map(|f| f.path.clone())
+            .collect();
+        let [a, b] = paths.as_slice() else {
+            return;
+        };
+        crash::record_action(format!("Compare {} with {}", a.display(), b.display()));
+        let settings = [self.svg_settings_for(a), self.svg_settings_for(b)];
+        let paths = [a.clone(), b.clone()];
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.compare_load = Some(receiver);
+        self.job_engine.submit("Decode images to compare", JobPriority::Interactive, move |_| {
+            let decode = |path: &PathBuf, settings: &ImageLoadingSettings| {
+                if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")) {
+                    decode_svg_image(path, settings, false)
+                } else {
+                    decode_raster_image(path, settings, false)
+                }
+                .map(|image| (path.clone(), Arc::new(image)))
+                .map_err(|e| (path.clone(), e))
+            };
+            let loaded = decode(&paths[0], &settings[0]).and_then(|a| Ok([a, decode(&paths[1], &settings[1])?]));
+            let summary = loaded.as_ref().map(|_| "Decoded".to_string()).map_err(|(_, e)| e.clone());
+            let _ = sender.send(loaded);
+            summary
+        });
+        self.status_text = "Decoding images to compare…".to_string();
+        ctx.request_repaint();
+    }
+
+    /// Show the comparison once both images are decoded
+    fn process_compare_load(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.compare_load else {
+            return;
+        };
+        let [(a, image_a), (b, image_b)] = match receiver.try_recv() {
+            Ok(Ok(images)) => images,
+            Ok(Err((path, e))) => {
+                self.compare_load = None;
+                self.note_timeout(&path, &e);
+                self.status_text = format!("Can't compare {}: {}", path.file_name().unwrap_or_default().to_string_lossy(), e);
+                return;
+            }
+            Err(std::sync::mpsc::TryRecvError::Empty) => return,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                self.compare_load = None;
+                return;
+            }
+        };
+        self.compare_load = None;
+        let name = |path: &PathBuf| path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let texture = |index: usize, image: &Arc<ColorImage>| ctx.load_texture(format!("compare_{}", index), (**image).clone(), SMOOTH_TEXTURE);
+        self.deep_zoom = None;
+        self.status_text = format!("Comparing {} with {}", name(&a), name(&b));
+        self.compare = Some(CompareView {
+            names: [name(&a), name(&b)],
+            textures: [texture(0, &image_a), texture(1, &image_b)],
+            images: [image_a, image_b],
+            mode: CompareMode::Wipe,
+            wipe: 0.5,
+            threshold: 8,
+            differences: Vec::new(),
+            computing: None,
+        });
+    }
+
+    fn render_compare(&mut self, ui: &mut egui::Ui) {
+        let Some(compare) = self.compare.as_mut() else {
+            return;
+        };
+        let mut close = false;
+        let mut settled = true;
+        ui.horizontal(|ui| {
+            ui.strong(format!("A: {}", compare.names[0]));
+            ui.separator();
+            ui.radio_value(&mut compare.mode, CompareMode::Wipe, "Wipe");
+            ui.radio_value(&mut compare.mode, CompareMode::Difference, "Difference");
+            if compare.mode == CompareMode::Difference {
+                // Recomputed once the slider is let go
+                settled = !ui.add(egui::Slider::new(&mut compare.threshold, 1..=255).text("threshold"))
+                    .on_hover_text("Smallest channel difference that is highlighted")
+                    .dragged();
+                if let Some((threshold, _, stats)) = compare.differences.last() {
+                    let stale = if *threshold == compare.threshold { String::new() } else { format!(" (at {})", threshold) };
+                    ui.label(format!("{:.2}% differ · max {} · mean {:.1}{}", stats.differing_percent(), stats.max, stats.mean, stale));
+                }
+            }
+            ui.separator();
+            if ui.button("⇄ Swap").clicked() {
+                compare.names.swap(0, 1);
+                compare.images.swap(0, 1);
+                compare.textures.swap(0, 1);
+                compare.differences.clear();
+                compare.computing = None;
+            }
+            if ui.button("✖ Close").clicked() {
+                close = true;
+            }
+            ui.separator();
+            ui.strong(format!("B: {}", compare.names[1]));
+        });
+
+        let area = ui.available_rect_before_wrap();
+        let response = ui.allocate_rect(area, egui::Sense::click_and_drag());
+        let size = compare.textures[0].size_vec2();
+        let rect = egui::Rect::from_center_size(area.center(), size * FitMode::Window.scale(size, area.size()));
+        let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        let painter = ui.painter_at(area);
+        match compare.mode {
+            CompareMode::Wipe => {
+                if (response.dragged() || response.clicked())
+                    && let Some(pointer) = response.interact_pointer_pos() {
+                    compare.wipe = ((pointer.x - rect.min.x) / rect.width()).clamp(0.0, 1.0);
+                }
+                let divider = rect.min.x + rect.width() * compare.wipe;
+                painter.image(compare.textures[1].id(), rect, uv, egui::Color32::WHITE);
+                let left = egui::Rect::from_min_max(rect.min, egui::pos2(divider, rect.max.y));
+                painter.with_clip_rect(left.intersect(area)).image(compare.textures[0].id(), rect, uv, egui::Color32::WHITE);
+                painter.vline(divider, rect.y_range(), egui::Stroke::new(2.0_f32, egui::Color32::WHITE));
+                painter.circle(egui::pos2(divider, rect.center().y), 8.0, egui::Color32::from_black_alpha(160), egui::Stroke::new(2.0_f32, egui::Color32::WHITE));
+                response.on_hover_cursor(egui::CursorIcon::ResizeHorizontal);
+            }
+            CompareMode::Difference => {
+                if let Some((threshold, receiver)) = &compare.computing {
+                    match receiver.try_recv() {
+                        Ok((heatmap, stats)) => {
+                            let texture = ui.ctx().load_texture(format!("compare_difference_{}", threshold), heatmap, Default::default());
+                            compare.differences.push((*threshold, texture, stats));
+                            let excess = compare.differences.len().saturating_sub(HEATMAP_CACHE);
+                            compare.differences.drain(..excess);
+                            compare.computing = None;
+                        }
+                        Err(std::sync::mpsc::TryRecvError::Empty) => ui.ctx().request_repaint(),
+                        Err(std::sync::mpsc::TryRecvError::Disconnected) => compare.computing = None,
+                    }
+                }
+                let threshold = compare.threshold;
+                // A cached heatmap becomes the newest again
+                if let Some(index) = compare.differences.iter().position(|(cached, _, _)| *cached == threshold) {
+                    let cached = compare.differences.remove(index);
+                    compare.differences.push(cached);
+                } else if settled && compare.computing.as_ref().is_none_or(|(computing, _)| *computing != threshold) {
+                    let images = compare.images.clone();
+                    let (sender, receiver) = std::sync::mpsc::channel();
+                    compare.computing = Some((threshold, receiver));
+                    self.job_engine.submit("Compute difference", JobPriority::Interactive, move |_| {
+                        let _ = sender.send(difference_image(&images[0], &images[1], threshold));
+                        Ok("Computed".to_string())
+                    });
+                }
+                // The last heatmap stays up while the next one is computed
+                if let Some((_, texture, _)) = compare.differences.last() {
+                    painter.image(texture.id(), rect, uv, egui::Color32::WHITE);
+                }
+            }
+        }
+        self.display_scale = Some(rect.width() / size.x.max(1.0));
+        if close {
+            self.compare = None;
+            self.compare_load = None;
+        }
+    }
+
+    /// The displayed image as it would print. The proof is made on the job
+    /// engine when the options change; until it's ready the image is shown unproofed.
+    fn proofed_texture(&mut self, ctx: &egui::Context) -> Option<TextureHandle> {
+        let options = self.settings.soft_proof;
+        if let Some((proofed_with, texture)) = &self.proof_texture
+            && *proofed_with == options {
+            return Some(texture.clone());
+        }
+        // Without CPU-side pixels (e.g. a cloud thumbnail) there's nothing to proof
+        let Some(image) = self.current_image.clone() else {
+            return self.image_texture.clone();
+        };
+        match &self.proof_render {
+            Some((proofing_with, receiver)) if *proofing_with == options => match receiver.try_recv() {
+                Ok(proofed) => {
+                    let texture = ctx.load_texture("soft_proof", proofed, SMOOTH_TEXTURE);
+                    self.proof_texture = Some((options, texture.clone()));
+                    self.proof_render = None;
+                    return Some(texture);
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => {
+                    self.repaint.request_after(RepaintReason::Decode, std::time::Duration::from_millis(50));
+                }
+                // The job was cancelled; keep showing the unproofed image
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {}
+            },
+            _ => {
+                let (sender, receiver) = std::sync::mpsc::channel();
+                self.proof_render = Some((options, receiver));
+                self.job_engine.submit("Soft proof", JobPriority::Interactive, move |_| {
+                    let _ = sender.send(ProofTransform::new(&options).apply(&image));
+                    Ok("Proofed".to_string())
+                });
+                self.repaint.request_after(RepaintReason::Decode, std::time::Duration::from_millis(50));
+            }
+        }
+        self.image_texture.clone()
+    }
+
+    fn reset_zoom(&mut self) {
+        self.zoom_factor = 1.0;
+        self.pan_offset = egui::Vec2::ZERO;
+        self.align_to_start = true;
+    }
+
+    /// Size the displayed image opens at: the one chosen for it, or the default for its type
+    fn opening_zoom(&self, texture_size: egui::Vec2) -> OpeningZoom {
+        let Some(path) = self.selected_image_index.and_then(|index| self.file_infos.get(index)).map(|file| &file.path) else {
+            return self.settings.photo_zoom;
+        };
+        self.view_states.get(path).zoom.unwrap_or(if is_icon(path, texture_size) {
+            self.settings.icon_zoom
+        } else {
+            self.settings.photo_zoom
+        })
+    }
+
+    /// Show the displayed image at `zoom`, remembering it for the image
+    fn set_image_zoom(&mut self, zoom: OpeningZoom) {
+        if let Some(path) = self.selected_image_index.and_then(|index| self.file_infos.get(index)).map(|file| file.path.clone()) {
+            self.view_states.get_mut(&path).zoom = Some(zoom);
+        }
+        self.reset_zoom();
+    }
+
+    /// Sample the clicked pixel as the SVG recolor target
+    fn handle_eyedropper(&mut self, response: &egui::Response) {
+        let response = response.clone().on_hover_cursor(egui::CursorIcon::Crosshair);
+        if !response.clicked() {
+            return;
+        }
+        let (Some(image), Some(pos)) = (self.current_image.clone(), response.interact_pointer_pos()) else {
+            return;
+        };
+        let [width, height] = image.size;
+        let rect = response.rect;
+        let x = (pos.x - rect.min.x) / rect.width() * width as f32;
+        let y = (pos.y - rect.min.y) / rect.height() * height as f32;
+        let stats = PixelRect::from_corners([x, y], [x + 1.0, y + 1.0], width, height)
+            .and_then(|pixel| compute_region_stats(&image, &pixel));
+        if let Some(stats) = stats {
+            // Ignore the pixel's transparency; only its color matters as a recolor target
+            let [r, g, b, _] = stats.average.to_srgba_unmultiplied();
+            self.settings.set_svg_target_color([r, g, b]);
+            self.status_text = format!("Recolor target set to {}", css_hex(egui::Color32::from_rgb(r, g, b)));
+        }
+        self.eyedropper_active = false;
+    }
+
+    /// Shift+drag on the displayed image selects a region for color statistics
+    fn handle_selection_drag(&mut self, ui: &egui::Ui, response: &egui::Response) {
+        let Some(image) = self.current_image.clone() else { return };
+        let [image_width, image_height] = image.size;
+        let rect = response.rect;
+
+        let to_image_coords = |pos: egui::Pos2| -> [f32; 2] {
+            [
+                (pos.x - rect.min.x) / rect.width() * image_width as f32,
+                (pos.y - rect.min.y) / rect.height() * image_height as f32,
+            ]
+        };
+        let to_screen = |x: usize, y: usize| -> egui::Pos2 {
+            egui::pos2(
+                rect.min.x + x as f32 / image_width as f32 * rect.width(),
+                rect.min.y + y as f32 / image_height as f32 * rect.height(),
+            )
+        };
+
+        if response.drag_started() && ui.input(|i| i.modifiers.shift)
+            && let Some(pos) = response.interact_pointer_pos() {
+            self.selection_drag_start = Some(to_image_coords(pos));
+        }
+
+        if let Some(start) = self.selection_drag_start
+            && let Some(pos) = response.interact_pointer_pos() {
+            self.selection = PixelRect::from_corners(start, to_image_coords(pos), image_width, image_height);
+        }
+
+        if response.drag_stopped() {
+            self.selection_drag_start = None;
+        }
+
+        if let Some(selection) = self.selection {
+            let screen_rect = egui::Rect::from_min_max(
+                to_screen(selection.min_x, selection.min_y),
+                to_screen(selection.max_x, selection.max_y),
+            );
+            ui.painter().rect_stroke(screen_rect, 0.0, egui::Stroke::new(1.5_f32, egui::Color32::YELLOW), egui::StrokeKind::Outside);
+        }
+    }
+
+    fn render_selection_window(&mut self, ctx: &egui::Context) {
+        let (Some(selection), Some(image)) = (self.selection, self.current_image.clone()) else {
+            return;
+        };
+        let computed_for = |(rect, source, _): &(PixelRect, std::sync::Weak<ColorImage>, _)| {
+            *rect == selection && std::ptr::eq(source.as_ptr(), Arc::as_ptr(&image))
+        };
+        if !self.selection_stats.as_ref().is_some_and(computed_for) {
+            self.selection_stats = Some((selection, Arc::downgrade(&image), compute_region_stats(&image, &selection)));
+        }
+        let Some(stats) = self.selection_stats.as_ref().and_then(|(_, _, stats)| stats.clone()) else {
+            return;
+        };
+
+        let mut open = true;
+        let mut redact = None;
+        let mut crop = false;
+        let mut annotate = false;
+        #[cfg(feature = "ocr")]
+        let mut copy_text = false;
+        egui::Window::new("Selection")
+            .open(&mut open)
+            .resizable(false)
+            .default_width(260.0)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Region: {}x{} at ({}, {}) - {} pixels",
+                    selection.width(), selection.height(), selection.min_x, selection.min_y, stats.pixel_count
+                ));
+                ui.separator();
+
+                let swatch = |ui: &mut egui::Ui, label: &str, color: egui::Color32| {
+                    ui.horizontal(|ui| {
+                        let (rect, _) = ui.allocate_exact_size(egui::vec2(32.0, 16.0), egui::Sense::hover());
+                        ui.painter().rect_filled(rect, 2.0, color);
+                        ui.label(format!("{}: {}", label, css_hex(color)));
+                        if ui.small_button("Copy hex").clicked() {
+                            ui.ctx().copy_text(css_hex(color));
+                        }
+                        if ui.small_button("Copy rgba").clicked() {
+                            ui.ctx().copy_text(css_rgba(color));
+                        }
+                    });
+                };
+
+                swatch(ui, "Average", stats.average);
+                swatch(ui, "Gradient start", stats.gradient_start);
+                swatch(ui, "Gradient end", stats.gradient_end);
+
+                ui.separator();
+                let gradient_css = css_linear_gradient(&stats);
+                ui.code(&gradient_css);
+                ui.horizontal(|ui| {
+                    if ui.button("Copy as CSS").clicked() {
+                        ui.ctx().copy_text(format!("background: {};", gradient_css));
+                    }
+                    if ui.button("Copy average as CSS").clicked() {
+                        ui.ctx().copy_text(format!("background-color: {};", css_rgba(stats.average)));
+                    }
+                });
+                #[cfg(feature = "ocr")]
+                if ui.add_enabled(self.ocr_result.is_none(), egui::Button::new("Copy Text in Selection")).clicked() {
+                    copy_text = true;
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Crop to Selection").clicked() {
+                        crop = true;
+                    }
+                });
+                ui.separator();
+                ui.strong("Annotate");
+                ui.horizontal(|ui| {
+                    ui.color_edit_button_srgba(&mut self.annotation_color);
+                    ui.add(egui::Slider::new(&mut self.annotation_width, 1..=annotation::MAX_WIDTH).suffix(" px"))
+                        .on_hover_text("Line width");
+                    if ui.button("Draw Rectangle").on_hover_text("Outline the selected region; exports and prints include it").clicked() {
+                        annotate = true;
+                    }
+                });
+                ui.separator();
+                ui.strong("Redact");
+                ui.horizontal(|ui| {
+                    ui.label("Strength:");
+                    ui.add(egui::Slider::new(&mut self.redaction_strength, MIN_STRENGTH..=MAX_STRENGTH).suffix(" px"));
+                }).response.on_hover_text("Block size when pixelating, radius when blurring");
+                ui.horizontal(|ui| {
+                    for style in [RedactionStyle::Pixelate, RedactionStyle::Blur] {
+                        if ui.button(style.label()).on_hover_text("Hide the selected region; exports and prints include it").clicked() {
+                            redact = Some(style);
+                        }
+                    }
+                });
+            });
+        if let Some(style) = redact {
+            let redaction = Redaction::of_selection(selection, style, self.redaction_strength, self.edit_scale());
+            self.apply_edit(ctx, ImageEdit::Redact(redaction));
+        }
+        if annotate {
+            let annotation = Annotation::of_selection(selection, self.annotation_color, self.annotation_width, self.edit_scale());
+            self.apply_edit(ctx, ImageEdit::Annotate(annotation));
+        }
+        if crop {
+            let scale = self.edit_scale();
+            let region = selection.scaled(1.0 / scale, [usize::MAX, usize::MAX]).unwrap_or(selection);
+            self.apply_edit(ctx, ImageEdit::Crop(region));
+        }
+        #[cfg(feature = "ocr")]
+        if copy_text {
+            self.copy_text_from_image(Some(selection));
+        }
+
+        if !open {
+            self.selection = None;
+        }
+    }
+
+    /// Edit the displayed image and record the edit for undoing
+    fn apply_edit(&mut self, ctx: &egui::Context, edit: ImageEdit) {
+        let Some(image) = self.current_image.clone() else {
+            return;
+        };
+        crash::record_action(edit.label());
+        self.unedited_image.get_or_insert_with(|| image.clone());
+        let mut edited = (*image).clone();
+        edit.apply(&mut edited, self.edit_scale());
+        self.history.record(Command::Edit(edit));
+        self.show_edited_pixels(ctx, edited);
+        self.save_edits();
+    }
+
+    /// Size of the displayed image relative to the original file, whose
+    /// pixels redactions are kept in
+    fn edit_scale(&self) -> f64 {
+        let decoded = self.unedited_image.as_ref().or(self.current_image.as_ref()).map(|image| image.size[0].max(image.size[1]));
+        match (decoded, &self.current_metadata) {
+            (Some(decoded), Some(metadata)) if metadata.width.max(metadata.height) > 0 => {
+                decoded as f64 / metadata.width.max(metadata.height) as f64
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Start keeping edits for the image just loaded from `path`, applying
+    /// the ones saved beside it
+    fn restore_edits(&mut self, ctx: &egui::Context, path: &std::path::Path) {
+        if archive::split_path(path).is_some() {
+            return;
+        }
+        self.edited_path = Some(path.to_path_buf());
+        let edits = edit_sidecar::load(path);
+        if edits.is_empty() {
+            return;
+        }
+        self.unedited_image = self.current_image.clone();
+        self.history.replay(edits);
+        self.redraw_edits(ctx);
+    }
+
+    /// Write the displayed image's edits to its sidecar
+    fn save_edits(&mut self) {
+        let Some(path) = &self.edited_path else {
+            return;
+        };
+        let edits: Vec<ImageEdit> = self.history.image_edits().copied().collect();
+        if let Err(e) = edit_sidecar::save(path, &edits) {
+            self.status_text = e;
+        }
+        self.file_rows.clear();
+    }
+
+    /// Drop every edit of the displayed image and its sidecar
+    fn revert_to_original(&mut self, ctx: &egui::Context) {
+        crash::record_action("Revert to original");
+        self.history.forget_image_edits();
+        self.redraw_edits(ctx);
+        self.save_edits();
+        self.status_text = "Reverted to the original image".to_string();
+    }
+
+    fn export_flattened(&mut self) {
+        let (Some(path), Some(image)) = (self.edited_path.clone(), self.current_image.clone()) else {
+            return;
+        };
+        crash::record_action("Export flattened image");
+        self.status_text = match edit_sidecar::export_flattened(&image, &path) {
+            Ok(output) => format!("Exported the edited image to {}", output.display()),
+            Err(e) => format!("Error exporting: {}", e),
+        };
+    }
+
+    /// Show the unedited image with the edits left in the history and the
+    /// adjustment being previewed
+    fn redraw_edits(&mut self, ctx: &egui::Context) {
+        let Some(original) = self.unedited_image.clone() else {
+            return;
+        };
+        let mut image = (*original).clone();
+        let scale = self.edit_scale();
+        for edit in self.history.image_edits() {
+            edit.apply(&mut image, scale);
+        }
+        if let Some(adjustment) = self.adjustment {
+            adjustment.apply(&mut image);
+        }
+        self.show_edited_pixels(ctx, image);
+    }
+
+    /// Replace the displayed pixels, keeping the zoom and selection unless
+    /// the size changed
+    fn show_edited_pixels(&mut self, ctx: &egui::Context, image: ColorImage) {
+        let resized = self.current_image.as_ref().is_none_or(|current| current.size != image.size);
+        let image = Arc::new(image);
+        self.image_texture = Some(ctx.load_texture("edited_image", image.clone(), SMOOTH_TEXTURE));
+        self.proof_texture = None;
+        self.proof_render = None;
+        self.current_image = Some(image);
+        if resized {
+            self.selection = None;
+            self.reset_zoom();
+        }
+    }
+
+    fn undo(&mut self, ctx: &egui::Context) {
+        let Some(result) = self.history.undo(|command| match command {
+            Command::Edit(_) => Ok(()),
+            Command::File(operation) => operation.undo(),
+        }) else {
+            return;
+        };
+        match result {
+            Ok(Command::Edit(edit)) => {
+                crash::record_action(format!("Undo {}", edit.label()));
+                self.redraw_edits(ctx);
+                self.save_edits();
+            }
+            Ok(Command::File(operation)) => {
+                crash::record_action(format!("Undo {}", operation.label()));
+                self.open_folder(ctx, self.current_dir.clone(), Some(operation.original()));
+                self.status_text = format!("Undone: {}", operation.label());
+            }
+            Err(e) => self.status_text = format!("Error undoing: {}", e),
+        }
+    }
+
+    fn redo(&mut self, ctx: &egui::Context) {
+        let Some(result) = self.history.redo(|command| match command {
+            Command::Edit(_) => Ok(()),
+            Command::File(operation) => operation.redo(),
+        }) else {
+            return;
+        };
+        match result {
+            Ok(Command::Edit(edit)) => {
+                crash::record_action(format!("Redo {}", edit.label()));
+                self.redraw_edits(ctx);
+                self.save_edits();
+            }
+            Ok(Command::File(operation)) => {
+                crash::record_action(format!("Redo {}", operation.label()));
+                let selected = self.selected_image_index.and_then(|i| self.file_infos.get(i)).map(|f| f.path.clone())
+                    .filter(|path| path.exists());
+                let select = operation.result().map(|path| path.to_path_buf()).or(selected);
+                self.open_folder(ctx, self.current_dir.clone(), select.as_deref());
+                self.status_text = format!("Redone: {}", operation.label());
+            }
+            Err(e) => self.status_text = format!("Error redoing: {}", e),
+        }
+    }
+
+    /// Sliders for an adjustment, previewed on the displayed image until it
+    /// is applied as one edit or cancelled
+    fn render_adjust_window(&mut self, ctx: &egui::Context) {
+        let Some(mut adjustment) = self.adjustment else {
+            return;
+        };
+        if self.current_image.is_none() {
+            self.adjustment = None;
+            return;
+        }
+        let mut open = true;
+        let mut apply = false;
+        let mut changed = false;
+        egui::Window::new("Adjust")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                for (label, amount) in [
+                    ("Brightness", &mut adjustment.brightness),
+                    ("Contrast", &mut adjustment.contrast),
+                    ("Saturation", &mut adjustment.saturation),
+                ] {
+                    ui.horizontal(|ui| {
+                        ui.label(label);
+                        changed |= ui.add(egui::Slider::new(amount, adjustment::MIN_AMOUNT..=adjustment::MAX_AMOUNT)).changed();
+                    });
+                }
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!adjustment.is_identity(), egui::Button::new("Apply")).clicked() {
+                        apply = true;
+                    }
+                    if ui.button("Reset").clicked() {
+                        changed = adjustment != Adjustment::default();
+                        adjustment = Adjustment::default();
+                    }
+                });
+            });
+        self.adjustment = Some(adjustment);
+        if apply {
+            self.adjustment = None;
+            self.redraw_edits(ctx);
+            self.apply_edit(ctx, ImageEdit::Adjust(adjustment));
+        } else if !open {
+            self.adjustment = None;
+            self.redraw_edits(ctx);
+        } else if changed {
+            if let Some(image) = self.current_image.clone() {
+                self.unedited_image.get_or_insert(image);
+            }
+            self.redraw_edits(ctx);
+        }
+    }
+
+    fn render_history_window(&mut self, ctx: &egui::Context) {
+        if !self.show_history {
+            return;
+        }
+        let mut open = true;
+        let mut step = None;
+        egui::Window::new("History")
+            .open(&mut open)
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                if self.history.done().is_empty() && self.history.undone().is_empty() {
+                    ui.weak("Nothing to undo yet");
+                    return;
+                }
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    for command in self.history.done() {
+                        ui.label(command.label());
+                    }
+                    // Undone commands, next to redo first
+                    for command in self.history.undone().iter().rev() {
+                        ui.weak(command.label());
+                    }
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!self.history.done().is_empty(), egui::Button::new("Undo")).clicked() {
+                        step = Some(false);
+                    }
+                    if ui.add_enabled(!self.history.undone().is_empty(), egui::Button::new("Redo")).clicked() {
+                        step = Some(true);
+                    }
+                });
+                ui.weak("Edits are forgotten when another image is shown. Deleted files go to the Recycle Bin or Trash.");
+            });
+        self.show_history = open;
+        match step {
+            Some(false) => self.undo(ctx),
+            Some(true) => self.redo(ctx),
+            None => {}
+        }
+    }
+
+    fn handle_keyboard_nav(&mut self, ctx: &egui::Context) {
+        let mut changed = false;
+        for action in self.keybindings.triggered(ctx) {
+            crash::record_action(format!("Shortcut {:?}", action));
+            match action {
+                Action::PreviousImage | Action::NextImage | Action::FirstImage | Action::LastImage
+                | Action::PageBackward | Action::PageForward => changed |= self.navigate(action),
+                Action::ToggleInfoPanel => self.show_metadata_panel = !self.show_metadata_panel,
+                Action::ToggleSvgSource => self.show_svg_source = !self.show_svg_source,
+                Action::ToggleSoftProof => self.settings.soft_proof_enabled = !self.settings.soft_proof_enabled,
+                Action::ToggleSlideshow => self.toggle_slideshow(),
+                Action::TogglePanoramaScroll => self.toggle_panorama_scroll(),
+                Action::ToggleShortcutHelp => self.show_shortcut_help = !self.show_shortcut_help,
+                Action::ToggleSearch => self.show_search = !self.show_search,
+                Action::Undo => self.undo(ctx),
+                Action::Redo => self.redo(ctx),
+                Action::MoveToFolderPane => self.move_selection_to_folder_pane(ctx),
+                Action::ToggleCulling => self.toggle_culling(ctx),
+                Action::TogglePictureFrame => self.toggle_picture_frame(ctx),
+                Action::ExitPictureFrame => if self.picture_frame.is_some() {
+                    self.toggle_picture_frame(ctx);
+                },
+                Action::FinishCulling => if let Some(session) = &mut self.culling {
+                    session.finish();
+                },
+                Action::CullKeep => self.cull(ctx, Decision::Keep),
+                Action::CullReject => self.cull(ctx, Decision::Reject),
+                Action::CullSkip => self.cull(ctx, Decision::Skip),
+                Action::SetRating(stars) => {
+                    let targets: Vec<usize> = if self.file_selection.is_empty() {
+                        self.selected_image_index.into_iter().collect()
+                    } else {
+                        self.file_selection.indices().collect()
+                    };
+                    self.edit_sidecars(&targets, SidecarEdit::Stars(stars));
+                }
+            }
+        }
+
+        if changed {
+            self.load_selected_image(ctx);
+        }
+    }
+
+    /// Whether an image is listed under the current filter
+    fn matches_filter(&self, file: &FileInfo) -> bool {
+        let rating = self.sidecars.rating(&file.path);
+        if (self.min_rating > 0 && rating.stars < self.min_rating)
+            || self.label_filter.is_some_and(|label| rating.label != Some(label))
+            || !self.sidecars.has_keywords(&file.path, &self.tag_filter)
+            || (self.only_blurry && self.possibly_blurry(&file.path).is_none()) {
+            return false;
+        }
+        self.filter_text.trim().is_empty() || {
+            let name = file.path.file_name().unwrap_or_default().to_string_lossy();
+            matches_query(&name, self.caption_index.get(&file.path), &self.filter_text)
+        }
+    }
+
+    /// Indices of the listed images, in list order
+    fn visible_indices(&self) -> Vec<usize> {
+        let collapsed = self.collapsed_stack_members();
+        let mut visible: Vec<usize> = (0..self.file_infos.len())
+            .filter(|&i| !collapsed.contains(&i) && self.matches_filter(&self.file_infos[i]))
+            .collect();
+        if self.sort_by_rating {
+            visible.sort_by_key(|&i| std::cmp::Reverse(self.sidecars.rating(&self.file_infos[i].path).stars));
+        }
+        visible
+    }
+
+    /// Images hidden under the first image of their collapsed stack
+    fn collapsed_stack_members(&self) -> HashSet<usize> {
+        if !self.settings.stack_images {
+            return HashSet::new();
+        }
+        self.stacks.iter()
+            .filter(|stack| !self.expanded_stacks.contains(&self.file_infos[stack.members[0]].path))
+            .flat_map(|stack| stack.members[1..].iter().copied())
+            .collect()
+    }
+
+    /// `paths` and, for the first image of a collapsed stack, the rest of it,
+    /// each with its RAW files, so a RAW file is moved or deleted along with its JPEG
+    fn with_collapsed_stacks(&self, paths: &[PathBuf]) -> Vec<PathBuf> {
+        let collapsed = self.collapsed_stack_members();
+        let mut all = paths.to_vec();
+        for stack in &self.stacks {
+            if paths.contains(&self.file_infos[stack.members[0]].path) {
+                all.extend(stack.members[1..].iter().filter(|i| collapsed.contains(i)).map(|&i| self.file_infos[i].path.clone()));
+            }
+        }
+        let raw: Vec<PathBuf> = all.iter().flat_map(|path| self.raw_companions.get(path)).flatten().cloned().collect();
+        all.extend(raw);
+        all.sort();
+        all.dedup();
+        all
+    }
+
+    /// Change the rating, label or keywords of the images at `targets` and save their sidecars
+    fn edit_sidecars(&mut self, targets: &[usize], edit: SidecarEdit) {
+        let mut saved = 0;
+        for path in targets.iter().filter_map(|&i| self.file_infos.get(i)).map(|f| f.path.clone()).collect::<Vec<_>>() {
+            let mut rating = self.sidecars.rating(&path);
+            let mut keywords = self.sidecars.keywords(&path).to_vec();
+            let result = match &edit {
+                SidecarEdit::Stars(stars) => {
+                    rating.stars = (*stars).min(MAX_RATING);
+                    write_rating(&path, &rating)
+                }
+                SidecarEdit::Label(label) => {
+                    rating.label = *label;
+                    write_rating(&path, &rating)
+                }
+                SidecarEdit::AddKeyword(keyword) => {
+                    if keywords.iter().any(|known| known.eq_ignore_ascii_case(keyword)) {
+                        continue;
+                    }
+                    keywords.push(keyword.clone());
+                    write_keywords(&path, &keywords)
+                }
+                SidecarEdit::RemoveKeyword(keyword) => {
+                    let count = keywords.len();
+                    keywords.retain(|known| !known.eq_ignore_ascii_case(keyword));
+                    if keywords.len() == count {
+                        continue;
+                    }
+                    write_keywords(&path, &keywords)
+                }
+            };
+            if let Err(e) = result {
+                self.status_text = e;
+                return;
+            }
+            self.sidecars.set_rating(&path, rating);
+            self.sidecars.set_keywords(&path, keywords);
+            saved += 1;
+        }
+        if saved == 0 {
+            return;
+        }
+        crash::record_action(format!("Edit {} sidecars: {:?}", saved, edit));
+        self.status_text = match edit {
+            SidecarEdit::Stars(0) => format!("Cleared the rating of {} image(s)", saved),
+            SidecarEdit::Stars(stars) => format!("Rated {} image(s) {}", saved, stars_text(stars)),
+            SidecarEdit::Label(Some(label)) => format!("Labelled {} image(s) {}", saved, label.name()),
+            SidecarEdit::Label(None) => format!("Removed the label of {} image(s)", saved),
+            SidecarEdit::AddKeyword(keyword) => format!("Tagged {} image(s) \"{}\"", saved, keyword),
+            SidecarEdit::RemoveKeyword(keyword) => format!("Removed the tag \"{}\" from {} image(s)", keyword, saved),
+        };
+    }
+
+    /// Keywords of the displayed image, or of every selected image when several are
+    fn render_tag_editor(&mut self, ui: &mut egui::Ui, index: usize) {
+        let targets = if self.file_selection.len() > 1 && self.file_selection.contains(index) {
+            self.file_selection.indices().collect()
+        } else {
+            vec![index]
+        };
+        let keywords = self.sidecars.keywords(&self.file_infos[index].path).to_vec();
+        let mut edit = None;
+
+        ui.label(if targets.len() > 1 { format!("Tags (editing {} selected images):", targets.len()) } else { "Tags:".to_string() });
+        ui.horizontal_wrapped(|ui| {
+            if keywords.is_empty() {
+                ui.colored_label(egui::Color32::GRAY, "None");
+            }
+            for keyword in &keywords {
+                let chip = ui.small_button(format!("{} ✖", keyword)).on_hover_text("Remove this tag; right-click to filter by it");
+                if chip.clicked() {
+                    edit = Some(SidecarEdit::RemoveKeyword(keyword.clone()));
+                }
+                if chip.secondary_clicked() && !self.tag_filter.iter().any(|tag| tag.eq_ignore_ascii_case(keyword)) {
+                    self.tag_filter.push(keyword.clone());
+                }
+            }
+        });
+        let input = ui.add(egui::TextEdit::singleline(&mut self.tag_input).hint_text("Add tag…").desired_width(f32::INFINITY));
+        if input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            if let Some(keyword) = normalize_keyword(&self.tag_input) {
+                edit = Some(SidecarEdit::AddKeyword(keyword));
+            }
+            input.request_focus();
+        }
+        // Autocomplete from the folder's tags, most used first
+        let suggestions = self.sidecars.suggest(&self.tag_input, &keywords, 8);
+        if !suggestions.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                for suggestion in suggestions {
+                    if ui.small_button(format!("+ {}", suggestion)).clicked() {
+                        edit = Some(SidecarEdit::AddKeyword(suggestion));
+                    }
+                }
+            });
+        }
+        if let Some(edit) = edit {
+            if matches!(edit, SidecarEdit::AddKeyword(_)) {
+                self.tag_input.clear();
+            }
+            self.edit_sidecars(&targets, edit);
+        }
+    }
+
+    /// Navigate among the images the filter shows, in list order; returns
+    /// whether the selection changed
+    fn navigate(&mut self, action: Action) -> bool {
+        let visible = self.visible_indices();
+        let current = self.selected_image_index.and_then(|selected| visible.iter().position(|&i| i == selected));
+        let mut target = navigation_target(
+            action,
+            current,
+            visible.len(),
+            self.settings.page_jump_size,
+            self.settings.wrap_navigation,
+        );
+        let on_demand = |position: usize| self.file_infos[visible[position]].will_trigger_download();
+        if self.settings.skip_on_demand_navigation
+            && matches!(action, Action::PreviousImage | Action::NextImage)
+            && let Some(position) = target
+            && on_demand(position) {
+            let forward = action == Action::NextImage;
+            // Step over on-demand files from where the plain step landed
+            let local = next_matching(position, visible.len(), forward, self.settings.wrap_navigation, |p| !on_demand(p))
+                .filter(|&p| Some(p) != current);
+            let message = match local {
+                Some(local) => {
+                    let skipped = if forward { (local + visible.len() - position) % visible.len() } else { (position + visible.len() - local) % visible.len() };
+                    format!("Skipped {} on-demand file{}", skipped, if skipped == 1 { "" } else { "s" })
+                }
+                None => format!("No local images {}; the rest need downloading", if forward { "after this one" } else { "before this one" }),
+            };
+            self.toast = Some(Toast::new(message, Instant::now()));
+            target = local;
+        }
+        let target = target.map(|position| visible[position]);
+        if let Some(index) = target {
+            self.selected_image_index = Some(index);
+            self.file_selection.select_only(index);
+            self.update_selection_summary();
+        }
+        target.is_some()
+    }
+
+    /// Previous/next image from the mouse back/forward buttons and swipe gestures
+    fn handle_mouse_nav(&mut self, ctx: &egui::Context) {
+        let mut actions = Vec::new();
+        if self.settings.mouse_button_navigation {
+            ctx.input(|i| {
+                if i.pointer.button_pressed(egui::PointerButton::Extra1) {
+                    actions.push(Action::PreviousImage);
+                }
+                if i.pointer.button_pressed(egui::PointerButton::Extra2) {
+                    actions.push(Action::NextImage);
+                }
+            });
+        }
+        for gesture in [self.gesture_tracker.take(), self.swipe_tracker.take()].into_iter().flatten() {
+            actions.push(match gesture {
+                Gesture::SwipeLeft => Action::NextImage,
+                Gesture::SwipeRight => Action::PreviousImage,
+            });
+        }
+
+        let mut changed = false;
+        for action in actions {
+            changed |= self.navigate(action);
+        }
+        if changed {
+            self.load_selected_image(ctx);
+        }
+    }
+
+    fn render_share_menu(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let Some(path) = self.selected_image_index
+            .and_then(|i| self.file_infos.get(i))
+            .map(|f| f.path.clone())
+        else {
+            ui.label("Select an image to share");
+            return;
+        };
+        let request = ShareRequest {
+            path: &path,
+            image: self.current_image.as_deref(),
+            edited: self.history.has_image_edits() && self.edited_path.as_ref() == Some(&path),
+        };
+
+        let mut result = None;
+        for target in share_targets(&self.settings) {
+            let unavailable = target.unavailable_reason(&request);
+            let response = ui.add_enabled(unavailable.is_none(), egui::Button::new(target.name()));
+            if let Some(reason) = unavailable {
+                response.on_disabled_hover_text(reason);
+            } else if response.clicked() {
+                result = Some(target.share(&request, ctx));
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+        if ui.button("Configure Share Targets…").clicked() {
+            self.show_settings = true;
+            ui.close_menu();
+        }
+
+        if let Some(result) = result {
+            self.status_text = result.unwrap_or_else(|e| format!("Error sharing image: {}", e));
+        }
+    }
+
+    /// Shortcut cheat sheet, built from the keybinding registry
+    fn render_shortcut_help(&mut self, ctx: &egui::Context) {
+        if !self.show_shortcut_help {
+            return;
+        }
+
+        egui::Window::new("Keyboard Shortcuts")
+            .open(&mut self.show_shortcut_help)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                for (category, bindings) in self.keybindings.grouped() {
+                    ui.heading(category);
+                    egui::Grid::new(format!("shortcuts_{}", category))
+                        .num_columns(2)
+                        .spacing([24.0, 4.0])
+                        .show(ui, |ui| {
+                            for binding in bindings {
+                                ui.monospace(binding.label());
+                                ui.label(binding.action.description());
+                                ui.end_row();
+                            }
+                        });
+                    ui.add_space(6.0);
+                }
+
+                ui.heading("Mouse");
+                egui::Grid::new("shortcuts_mouse")
+                    .num_columns(2)
+                    .spacing([24.0, 4.0])
+                    .show(ui, |ui| {
+                        for (gesture, description) in MOUSE_SHORTCUTS {
+                            ui.monospace(*gesture);
+                            ui.label(*description);
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    fn toggle_slideshow(&mut self) {
+        if self.slideshow.is_running() {
+            self.slideshow.stop();
+            self.status_text = "Slideshow stopped".to_string();
+        } else if self.file_infos.is_empty() {
+            self.status_text = "No images for a slideshow".to_string();
+        } else {
+            self.slideshow.start();
+            self.read_ahead = ReadAhead::new(self.settings.slideshow_download_limit_mb.map(|mb| mb as u64 * MB as u64));
+            self.queue_read_ahead();
+            self.status_text = format!("Slideshow: {} s per image", self.settings.slideshow_interval_secs);
+        }
+    }
+
+    /// Download the next few on-demand slides so playback doesn't stall on them
+    fn queue_read_ahead(&mut self) {
+        let rules = &self.settings.slideshow_skip;
+        if rules.skip_on_demand || !self.background_budget().prefetch_enabled {
+            return;
+        }
+        let upcoming = SlideshowController::upcoming(
+            self.selected_image_index,
+            self.file_infos.len(),
+            self.settings.slideshow_read_ahead,
+            rules,
+            |index| self.slide_facts(index),
+        );
+        let metered = self.on_metered_connection();
+        for index in upcoming {
+            let file = &self.file_infos[index];
+            if file.will_trigger_download()
+                && !metered
+                && self.read_ahead.admit(&file.path, file.estimated_download_size.unwrap_or(0)) {
+                self.download_queue.enqueue(file.path.clone());
+            }
+        }
+    }
+
+    /// What the slideshow skip rules need to know about an image in the list
+    fn slide_facts(&self, index: usize) -> SlideFacts {
+        let file = &self.file_infos[index];
+        let failed_integrity = self.decode_failures.lock().is_ok_and(|failures| failures.contains(&file.path));
+        let on_demand = file.will_trigger_download();
+        SlideFacts {
+            path: file.path.clone(),
+            on_demand,
+            // Metadata is never read for on-demand files, so this can't trigger a download
+            megapixels: read_image_metadata(&file.path).map(|m| m.width as f64 * m.height as f64 / 1_000_000.0),
+            failed_integrity,
+            over_download_limit: on_demand && !self.read_ahead.can_fit(&file.path, file.estimated_download_size.unwrap_or(0)),
+        }
+    }
+
+    /// Show the next slide that passes the skip rules once the interval has passed
+    fn process_slideshow(&mut self, ctx: &egui::Context) {
+        let interval = std::time::Duration::from_secs(self.settings.slideshow_interval_secs.into());
+        if !self.slideshow.is_running() {
+            return;
+        }
+        if self.slideshow.is_due(interval) {
+            let mut rules = self.settings.slideshow_skip.clone();
+            let len = self.file_infos.len();
+            let current = self.selected_image_index;
+            // Hold the current slide rather than showing one that is still downloading
+            let next = SlideshowController::upcoming(current, len, 1, &rules, |index| self.slide_facts(index));
+            let downloading = next.first().is_some_and(|&index| self.download_queue.is_queued(&self.file_infos[index].path));
+            if downloading {
+                // ...until the queue is paused or the download takes too long; then
+                // move on to the next slide that is already local, if there is one
+                let local = SkipRules { skip_on_demand: true, ..rules.clone() };
+                let give_up = self.download_queue.is_paused() || self.slideshow.waited() >= SLIDESHOW_DOWNLOAD_WAIT;
+                if give_up && !SlideshowController::upcoming(current, len, 1, &local, |index| self.slide_facts(index)).is_empty() {
+                    rules = local;
+                } else {
+                    self.slideshow.set_waiting(true);
+                    self.repaint.request_after(RepaintReason::Slideshow, std::time::Duration::from_millis(200));
+                    return;
+                }
+            }
+            let mut slideshow = std::mem::take(&mut self.slideshow);
+            let next = slideshow.advance(current, len, &rules, |index| self.slide_facts(index));
+            self.slideshow = slideshow;
+            match next {
+                Some(index) => {
+                    let file = &self.file_infos[index];
+                    if file.will_trigger_download() {
+                        // Shown without being read ahead; it still counts against the budget
+                        self.read_ahead.admit(&file.path, file.estimated_download_size.unwrap_or(0));
+                    }
+                    self.selected_image_index = Some(index);
+                    self.file_selection.select_only(index);
+                    self.update_selection_summary();
+                    self.load_selected_image(ctx);
+                    self.queue_read_ahead();
+                }
+                None => {
+                    self.slideshow.stop();
+                    self.status_text = "Slideshow stopped: every image matches a skip rule".to_string();
+                }
+            }
+        }
+        // Once a second, for the countdown in the status bar
+        self.repaint.request_after(RepaintReason::Slideshow, self.slideshow.time_remaining(interval).min(std::time::Duration::from_secs(1)));
+    }
+
+    fn toggle_hot_folder(&mut self, ctx: &egui::Context) {
+        if self.hot_folder.take().is_some() {
+            self.status_text = "Stopped watching for new images".to_string();
+        } else if archive::is_archive(&self.current_dir) {
+            self.status_text = "Archives can't be watched for new images".to_string();
+        } else {
+            crash::record_action(format!("Watch folder {}", self.current_dir.display()));
+            self.hot_folder = Some(HotFolder::watch(self.current_dir.clone(), self.settings.get_supported_extensions().to_vec(), ctx.clone()));
+            self.status_text = format!("Watching {} for new images", self.current_dir.display());
+        }
+    }
+
+    /// Open the newest image that finished arriving in the watched folder
+    fn process_hot_folder(&mut self, ctx: &egui::Context) {
+        let Some(hot_folder) = &mut self.hot_folder else {
+            return;
+        };
+        let (arrived_raw, arrived): (Vec<PathBuf>, Vec<PathBuf>) = hot_folder.poll().into_iter().partition(|path| stacks::is_raw(path));
+        if arrived.is_empty() && arrived_raw.is_empty() {
+            return;
+        }
+        // Added in place rather than rescanning the folder, keeping the list sorted by path
+        for path in &arrived {
+            let Err(position) = self.file_infos.binary_search_by(|file| file.path.cmp(path)) else {
+                continue;
+            };
+            self.caption_index.refresh(path);
+            self.sidecars.refresh(path);
+            self.file_infos.insert(position, FileInfo::new(path.clone()));
+        }
+        // Pair the RAW files already known with the images that arrived, and the arrived RAW files with all images
+        let raw: Vec<PathBuf> = self.raw_companions.values().flatten().cloned()
+            .chain(std::mem::take(&mut hot_folder.unpaired_raw))
+            .chain(arrived_raw)
+            .collect();
+        let (companions, unpaired) = stacks::pair_raw_files(&self.file_infos, raw);
+        hot_folder.unpaired_raw = unpaired;
+        self.raw_companions = companions;
+        let Some(newest) = arrived.last().cloned() else {
+            self.stacks = find_stacks(&self.file_infos, &self.raw_companions);
+            return;
+        };
+        // Indices into the list shifted, so the sequence player's frames are stale
+        self.close_sequence_player();
+        self.frame_sequences = find_sequences(&self.file_infos);
+        self.stacks = find_stacks(&self.file_infos, &self.raw_companions);
+        self.selected_image_index = self.file_infos.iter().position(|file| file.path == newest);
+        self.file_selection.clear();
+        if let Some(index) = self.selected_image_index {
+            self.file_selection.select_only(index);
+        }
+        self.update_selection_summary();
+        self.index_folder();
+        self.measure_sharpness();
+        self.load_selected_image(ctx);
+        let name = newest.file_name().unwrap_or_default().to_string_lossy().to_string();
+        self.status_text = format!("New image: {}", self.settings.truncate_filename(&name));
+    }
+
+    /// Stop playing the sequence, dropping frames still being decoded for it
+    fn close_sequence_player(&mut self) {
+        self.sequence_player = None;
+        self.sequence_decodes = std::sync::mpsc::channel();
+    }
+
+    /// Play controls and a scrubber for the numbered sequence being played
+    fn render_sequence_player(&mut self, ctx: &egui::Context) {
+        let Some(player) = &mut self.sequence_player else {
+            return;
+        };
+        let mut open = true;
+        let mut seek = None;
+        let now = Instant::now();
+        egui::Window::new(format!("Sequence: {}", player.sequence.name))
+            .id(egui::Id::new("sequence_player"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button(if player.playing { "⏸ Pause" } else { "▶ Play" }).clicked() {
+                        player.toggle(now);
+                        seek = Some(player.position);
+                    }
+                    if ui.button("⏮").on_hover_text("Previous frame").clicked() {
+                        seek = Some(player.position.saturating_sub(1));
+                    }
+                    if ui.button("⏭").on_hover_text("Next frame").clicked() {
+                        seek = Some(player.position + 1);
+                    }
+                    ui.add(egui::DragValue::new(&mut self.settings.sequence_fps).range(1..=120).suffix(" fps"));
+                    ui.checkbox(&mut player.looping, "Loop");
+                });
+                let mut frame = player.position + 1;
+                let slider = egui::Slider::new(&mut frame, 1..=player.frame_count()).text(format!("of {}", player.frame_count()));
+                if ui.add(slider).changed() {
+                    seek = Some(frame - 1);
+                }
+            });
+        if !open {
+            self.sequence_player = None;
+            // The frame it stopped on gets its metadata and hooks like any other image
+            self.load_selected_image(ctx);
+            return;
+        }
+        if let Some(position) = seek {
+            player.seek(position, now);
+            self.show_sequence_frame(ctx);
+        }
+    }
+
+    /// Advance a playing sequence when its next frame is due
+    fn process_sequence(&mut self, ctx: &egui::Context) {
+        let Some(player) = &mut self.sequence_player else {
+            return;
+        };
+        let now = Instant::now();
+        player.tick(self.settings.sequence_fps, now);
+        // Also picks up a frame that finished decoding since the last tick
+        self.show_sequence_frame(ctx);
+        if let Some(player) = &self.sequence_player {
+            if player.displayed != Some(player.current()) {
+                self.repaint.request_after(RepaintReason::Animation, std::time::Duration::from_millis(15));
+            } else if player.playing {
+                self.repaint.request_after(RepaintReason::Animation, player.time_to_next_frame(self.settings.sequence_fps, now));
+            }
+        }
+    }
+
+    /// Show the player's current frame once it is decoded, keeping the zoom and
+    /// pan so details can be compared. The frame on screen stays until then.
+    fn show_sequence_frame(&mut self, ctx: &egui::Context) {
+        self.decode_sequence_frames();
+        let Some(player) = &self.sequence_player else {
+            return;
+        };
+        let (index, position, count) = (player.current(), player.position, player.frame_count());
+        if player.displayed == Some(index) {
+            return;
+        }
+        let Some(path) = self.file_infos.get(index).map(|file| file.path.clone()) else {
+            return;
+        };
+        let cache_key = self.image_cache_key(&path);
+        let Some(color_image) = self.decoded_cache.lock().unwrap().get(&cache_key).cloned() else {
+            return;
+        };
+        let (zoom, pan) = (self.zoom_factor, self.pan_offset);
+        self.selected_image_index = Some(index);
+        self.file_selection.select_only(index);
+        self.update_selection_summary();
+        self.deep_zoom = None;
+        self.compare = None;
+        self.compare_load = None;
+        self.svg_source = None;
+        // Metadata is read when playback stops, not for every frame
+        self.current_metadata = None;
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        self.set_displayed_image(ctx, format!("image_{}", name), color_image, Some(&cache_key));
+        self.status_text = format!("Frame {} of {}: {}", position + 1, count, self.settings.truncate_filename(&name));
+        self.zoom_factor = zoom;
+        self.pan_offset = pan;
+        self.align_to_start = false;
+        if let Some(player) = &mut self.sequence_player {
+            player.displayed = Some(index);
+        }
+    }
+
+    /// Decode the player's next frames on the job engine into the decoded image cache
+    fn decode_sequence_frames(&mut self) {
+        while let Ok((index, result)) = self.sequence_decodes.1.try_recv() {
+            match result {
+                // Failed frames stay requested so they aren't tried again
+                Ok(()) => if let Some(player) = &mut self.sequence_player {
+                    player.requested.remove(&index);
+                },
+                Err(e) => self.status_text = format!("Error loading frame: {}", e),
+            }
+        }
+        let Some(upcoming) = self.sequence_player.as_ref().map(|player| player.upcoming(SEQUENCE_READ_AHEAD)) else {
+            return;
+        };
+        for index in upcoming {
+            let Some(path) = self.file_infos.get(index).map(|file| file.path.clone()) else {
+                continue;
+            };
+            let cache_key = self.image_cache_key(&path);
+            let requested = self.sequence_player.as_ref().is_some_and(|player| player.requested.contains(&index));
+            if requested || self.decoded_cache.lock().unwrap().contains_key(&cache_key) {
+                continue;
+            }
+            if let Some(player) = &mut self.sequence_player {
+                player.requested.insert(index);
+            }
+            let svg = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+            let settings = self.svg_settings_for(&path);
+            let cache = Arc::clone(&self.decoded_cache);
+            let sender = self.sequence_decodes.0.clone();
+            let name = format!("Decode frame {}", path.file_name().unwrap_or_default().to_string_lossy());
+            self.job_engine.submit(name, JobPriority::Interactive, move |_| {
+                let decoded = if svg {
+                    decode_svg_image(&path, &settings, true)
+                } else {
+                    decode_raster_image(&path, &settings, true)
+                };
+                let result = decoded.map(|color_image| {
+                    let bytes = color_image.pixels.len() * 4;
+                    cache.lock().unwrap().insert(cache_key, Arc::new(color_image), bytes);
+                });
+                let _ = sender.send((index, result.clone()));
+                result.map(|()| "Decoded".to_string())
+            });
+        }
+    }
+
+    fn render_slideshow_log(&mut self, ctx: &egui::Context) {
+        if !self.show_slideshow_log {
+            return;
+        }
+
+        let mut clear = false;
+        egui::Window::new("Skipped Slideshow Images")
+            .open(&mut self.show_slideshow_log)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                let log = self.slideshow.skip_log();
+                if log.is_empty() {
+                    ui.colored_label(egui::Color32::GRAY, "No images have been skipped");
+                    return;
+                }
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    egui::Grid::new("slideshow_skip_log").num_columns(2).striped(true).show(ui, |ui| {
+                        for skipped in log.iter().rev() {
+                            ui.label(skipped.path.file_name().unwrap_or_default().to_string_lossy())
+                                .on_hover_text(skipped.path.display().to_string());
+                            ui.label(skipped.reason.description());
+                            ui.end_row();
+                        }
+                    });
+                });
+                clear = ui.button("Clear").clicked();
+            });
+        if clear {
+            self.slideshow.clear_skip_log();
+        }
+    }
+
+    fn handle_benchmark_trigger(&mut self, ctx: &egui::Context) {
+        // Handle benchmark trigger
+        if self.run_benchmark_trigger && !self.benchmark_in_progress {
+            self.run_benchmark_trigger = false;
+            self.run_benchmark(ctx);
+        }
+        
+        // Auto-benchmark on startup if enabled
+        if self.auto_benchmark_on_startup && !self.benchmark_in_progress && self.performance_profile.benchmark_results.is_empty() {
+            self.auto_benchmark_on_startup = false;
+            self.run_benchmark(ctx);
+        }
+    }
+
+    fn handle_dialogs(&mut self, ctx: &egui::Context) {
+        self.handle_slow_image_dialog(ctx);
+        self.handle_download_dialog(ctx);
+        self.handle_bulk_download_dialog(ctx);
+        self.handle_svg_export_dialog(ctx);
+        self.handle_view_export_dialog(ctx);
+        self.handle_print_dialog(ctx);
+        self.handle_deep_zoom_dialog(ctx);
+        self.handle_crash_report_dialog(ctx);
+        self.handle_session_recovery_dialog(ctx);
+        self.handle_tuning_dialog(ctx);
+        self.handle_duplicates_window(ctx);
+        self.handle_triage_window(ctx);
+        self.handle_search_window(ctx);
+        self.handle_icon_cache_window(ctx);
+        self.handle_delete_image_dialog(ctx);
+        self.handle_rename_dialog(ctx);
+    }
+
+    fn handle_delete_image_dialog(&mut self, ctx: &egui::Context) {
+        let Some(path) = self.confirm_delete_image.clone() else {
+            return;
+        };
+        let mut answer = None;
+        egui::Window::new("Delete Image")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!("Delete {}?", path.display()));
+                ui.weak("It can be restored with Undo until the viewer is closed.");
+                ui.horizontal(|ui| {
+                    if ui.button("Delete").clicked() {
+                        answer = Some(true);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        answer = Some(false);
+                    }
+                });
+            });
+        let Some(delete) = answer else {
+            return;
+        };
+        self.confirm_delete_image = None;
+        if !delete {
+            return;
+        }
+        let operation = match FileOperation::delete(&path) {
+            Ok(operation) => operation,
+            Err(e) => {
+                self.status_text = format!("Failed to delete {}: {}", path.display(), e);
+                return;
+            }
+        };
+        crash::record_action(format!("Delete {}", path.display()));
+        self.history.record(Command::File(operation));
+        // Move on to the next image, or the previous one at the end of the list
+        let index = self.file_infos.iter().position(|f| f.path == path);
+        let next = index.and_then(|i| self.file_infos.get(i + 1).or_else(|| i.checked_sub(1).and_then(|i| self.file_infos.get(i))))
+            .map(|f| f.path.clone());
+        self.open_folder(ctx, self.current_dir.clone(), next.as_deref());
+        self.status_text = format!("Deleted {}", path.display());
+    }
+
+    fn start_rename(&mut self, path: Option<PathBuf>) {
+        self.rename_target = path.map(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            (path, name)
+        });
+    }
+
+    fn handle_rename_dialog(&mut self, ctx: &egui::Context) {
+        let Some((_, name)) = self.rename_target.as_mut() else {
+            return;
+        };
+        let mut answer = None;
+        egui::Window::new("Rename")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                let response = ui.add(egui::TextEdit::singleline(name).desired_width(280.0));
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    answer = Some(true);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Rename").clicked() {
+                        answer = Some(true);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        answer = Some(false);
+                    }
+                });
+            });
+        let Some(rename) = answer else {
+            return;
+        };
+        let Some((path, name)) = self.rename_target.take().filter(|_| rename) else {
+            return;
+        };
+        let name = name.trim();
+        if name.is_empty() || name.contains(['/', '\\']) {
+            self.status_text = format!("Error: \"{}\" isn't a valid file name", name);
+            return;
+        }
+        let target = path.with_file_name(name);
+        if target == path {
+            return;
+        }
+        match FileOperation::rename(&path, &target) {
+            Ok(operation) => {
+                crash::record_action(operation.label());
+                self.history.record(Command::File(operation));
+                self.open_folder(ctx, self.current_dir.clone(), Some(&target));
+                self.status_text = format!("Renamed to {}", name);
+            }
+            Err(e) => self.status_text = format!("Error renaming: {}", e),
+        }
+    }
+
+    fn handle_icon_cache_window(&mut self, ctx: &egui::Context) {
+        if !self.show_icon_cache_stats {
+            return;
+        }
+        let stats = self.icon_renderer.stats();
+        let mut clear = false;
+        egui::Window::new("Icon Cache")
+            .open(&mut self.show_icon_cache_stats)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("icon_cache_stats").num_columns(2).show(ui, |ui| {
+                    ui.label("Textures:");
+                    ui.label(stats.entries.to_string());
+                    ui.end_row();
+                    ui.label("Memory:");
+                    ui.label(format!("{:.1} of {:.1} MB", stats.size_bytes as f64 / MB as f64, stats.limit_bytes as f64 / MB as f64));
+                    ui.end_row();
+                    let lookups = stats.counters.hits + stats.counters.misses;
+                    ui.label("Hits:");
+                    ui.label(match lookups {
+                        0 => "0".to_string(),
+                        _ => format!("{} ({:.0}%)", stats.counters.hits, stats.counters.hits as f64 * 100.0 / lookups as f64),
+                    });
+                    ui.end_row();
+                    ui.label("Misses:");
+                    ui.label(stats.counters.misses.to_string());
+                    ui.end_row();
+                    ui.label("Evictions:");
+                    ui.label(stats.counters.evictions.to_string());
+                    ui.end_row();
+                });
+                if !stats.failed.is_empty() {
+                    ui.separator();
+                    ui.label("Failed to render:");
+                    for icon in &stats.failed {
+                        ui.monospace(icon);
+                    }
+                }
+                ui.separator();
+                clear = ui.button("Clear and retry").on_hover_text("Drop every icon texture and retry icons that failed").clicked();
+            });
+        if clear {
+            self.icon_renderer.clear_cache();
+        }
+    }
+
+    fn play_video(&mut self, path: &std::path::Path) {
+        crash::record_action(format!("Play video {}", path.display()));
+        if let Err(e) = open_with_system(path) {
+            self.status_text = e;
+        }
+    }
+
+    /// Start culling the listed images fullscreen, or show the summary to finish
+    fn toggle_culling(&mut self, ctx: &egui::Context) {
+        if let Some(session) = &mut self.culling {
+            session.finish();
+            return;
+        }
+        if self.picture_frame.is_some() {
+            self.status_text = "Leave the picture frame before culling".to_string();
+            return;
+        }
+        let visible = self.visible_indices();
+        let Some(&first) = visible.first() else {
+            self.status_text = "There are no images to cull".to_string();
+            return;
+        };
+        crash::record_action(format!("Start culling {}", self.current_dir.display()));
+        self.culling = Some(CullingSession::default());
+        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(true));
+        if !self.selected_image_index.is_some_and(|selected| visible.contains(&selected)) {
+            self.show_index(ctx, first);
+        }
+    }
+
+    /// Strip the window down to the image and keep it on top, or restore it
+    fn toggle_picture_frame(&mut self, ctx: &egui::Context) {
+        if let Some(frame) = self.picture_frame.take() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Decorations(true));
+            ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::viewport::WindowLevel::Normal));
+            if let Some(size) = frame.restore_size {
+                ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(size));
+            }
+            return;
+        }
+        if self.culling.is_some() {
+            self.status_text = "Finish culling before switching to the picture frame".to_string();
+            return;
+        }
+        crash::record_action("Start picture frame");
+        let restore_size = ctx.input(|i| i.viewport().inner_rect).map(|rect| rect.size());
+        self.picture_frame = Some(PictureFrame { restore_size });
+        ctx.send_viewport_cmd(egui::ViewportCommand::Decorations(false));
+        ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::viewport::WindowLevel::AlwaysOnTop));
+        if let Some(texture) = &self.image_texture {
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(picture_frame::frame_size(texture.size_vec2())));
+        }
+    }
+
+    /// Just the image, with slideshow and exit buttons while the pointer is over the window
+    fn render_picture_frame(&mut self, ctx: &egui::Context) {
+        let on_edge = picture_frame::handle_edge_resize(ctx);
+        egui::CentralPanel::default()
+            .frame(egui::Frame::NONE)
+            .show(ctx, |ui| self.render_image_display(ui));
+        if on_edge || !ctx.input(|i| i.pointer.has_pointer()) {
+            return;
+        }
+        let (mut slideshow, mut exit) = (false, false);
+        egui::Area::new(egui::Id::new("picture_frame_controls"))
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 8.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let running = self.slideshow.is_running();
+                        slideshow = ui.small_button(if running { "⏸" } else { "▶" })
+                            .on_hover_text(if running { "Stop slideshow" } else { "Start slideshow" })
+                            .clicked();
+                        exit = ui.small_button("✖").on_hover_text("Leave picture frame (Esc)").clicked();
+                    });
+                });
+            });
+        if slideshow {
+            self.toggle_slideshow();
+        }
+        if exit {
+            self.toggle_picture_frame(ctx);
+        }
+    }
+
+    fn end_culling(&mut self, ctx: &egui::Context) {
+        self.culling = None;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(false));
+    }
+
+    fn show_index(&mut self, ctx: &egui::Context, index: usize) {
+        self.selected_image_index = Some(index);
+        self.file_selection.select_only(index);
+        self.update_selection_summary();
+        self.load_selected_image(ctx);
+    }
+
+    /// Record the decision about the displayed image and show the next one,
+    /// or the summary after the last
+    fn cull(&mut self, ctx: &egui::Context, decision: Decision) {
+        let visible = self.visible_indices();
+        let Some(session) = self.culling.as_mut().filter(|session| !session.finished) else {
+            return;
+        };
+        let Some(index) = self.selected_image_index else {
+            return;
+        };
+        session.decide(&self.file_infos[index].path, decision);
+        match visible.iter().position(|&i| i == index).and_then(|position| visible.get(position + 1)) {
+            Some(&next) => self.show_index(ctx, next),
+            None => session.finish(),
+        }
+    }
+
+    /// The displayed image with the decision and running tally over it, and
+    /// the summary once culling is finished
+    fn render_culling(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default()
+            .frame(egui::Frame::NONE.fill(egui::Color32::BLACK))
+            .show(ctx, |ui| self.render_image_display(ui));
+        let Some(session) = &self.culling else {
+            return;
+        };
+        let visible = self.visible_indices();
+        let current = self.selected_image_index.and_then(|index| self.file_infos.get(index));
+        let position = self.selected_image_index.and_then(|index| visible.iter().position(|&i| i == index));
+        let tally = session.tally();
+        egui::Area::new(egui::Id::new("culling_overlay"))
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 12.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if let Some(file) = current {
+                            let name = file.path.file_name().unwrap_or_default().to_string_lossy();
+                            ui.strong(self.settings.truncate_filename(&name));
+                            if let Some(position) = position {
+                                ui.label(format!("{} of {}", position + 1, visible.len()));
+                            }
+                            if let Some(decision) = session.decision(&file.path) {
+                                let color = match decision {
+                                    Decision::Keep => egui::Color32::GREEN,
+                                    Decision::Reject => egui::Color32::RED,
+                                    Decision::Skip => egui::Color32::GRAY,
+                                };
+                                ui.colored_label(color, format!("{} {}", decision.symbol(), decision.label()));
+                            }
+                        }
+                        ui.separator();
+                        ui.colored_label(egui::Color32::GREEN, format!("{} {}", Decision::Keep.symbol(), tally.keep));
+                        ui.colored_label(egui::Color32::RED, format!("{} {}", Decision::Reject.symbol(), tally.reject));
+                        ui.colored_label(egui::Color32::GRAY, format!("{} {}", Decision::Skip.symbol(), tally.skip));
+                    });
+                    ui.weak("K keep · X reject · Space skip · ←/→ previous/next · Esc finish");
+                });
+            });
+        if session.finished {
+            self.render_culling_summary(ctx);
+        }
+    }
+
+    fn render_culling_summary(&mut self, ctx: &egui::Context) {
+        let Some(session) = &self.culling else {
+            return;
+        };
+        let tally = session.tally();
+        let rejected = session.rejected();
+        let visible: Vec<PathBuf> = self.visible_indices().iter().map(|&i| self.file_infos[i].path.clone()).collect();
+        let undecided = visible.iter().filter(|path| matches!(session.decision(path), None | Some(Decision::Skip))).count();
+        let first_undecided = session.first_undecided(&visible);
+        let mut answer = None;
+        egui::Window::new("Culling Finished")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!("{} kept, {} rejected, {} not decided", tally.keep, tally.reject, undecided));
+                ui.separator();
+                ui.add_enabled_ui(!rejected.is_empty(), |ui| {
+                    if ui.button(format!("Move {} Rejected to \"{}\" Subfolder", rejected.len(), REJECTS_FOLDER)).clicked() {
+                        answer = Some(CullingEnd::MoveRejects);
+                    }
+                    if ui.button(format!("Delete {} Rejected", rejected.len()))
+                        .on_hover_text("They can be restored with Undo until the viewer is closed")
+                        .clicked() {
+                        answer = Some(CullingEnd::DeleteRejects);
+                    }
+                });
+                if first_undecided.is_some() && ui.button(format!("Review {} Not Decided", undecided)).clicked() {
+                    answer = Some(CullingEnd::Review);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Back to Culling").clicked() {
+                        answer = Some(CullingEnd::Resume);
+                    }
+                    if ui.button("Leave Files as They Are").clicked() {
+                        answer = Some(CullingEnd::Leave);
+                    }
+                });
+            });
+        match answer {
+            None => {}
+            Some(CullingEnd::Resume) => if let Some(session) = &mut self.culling {
+                session.finished = false;
+            },
+            Some(CullingEnd::Review) => {
+                if let Some(session) = &mut self.culling {
+                    session.finished = false;
+                }
+                let visible = self.visible_indices();
+                if let Some(&index) = first_undecided.and_then(|position| visible.get(position)) {
+                    self.show_index(ctx, index);
+                }
+            }
+            Some(CullingEnd::Leave) => self.end_culling(ctx),
+            Some(CullingEnd::MoveRejects) => {
+                self.end_culling(ctx);
+                self.move_to_folder(ctx, &rejected, self.current_dir.join(REJECTS_FOLDER));
+            }
+            Some(CullingEnd::DeleteRejects) => {
+                self.end_culling(ctx);
+                self.delete_files(ctx, &rejected);
+            }
+        }
+    }
+
+    /// Send `paths` to the Recycle Bin or Trash, recording each for undo
+    fn delete_files(&mut self, ctx: &egui::Context, paths: &[PathBuf]) {
+        let paths = &self.with_collapsed_stacks(paths);
+        crash::record_action(format!("Delete {} images", paths.len()));
+        let mut deleted = 0;
+        let mut error = None;
+        for path in paths {
+            match FileOperation::delete(path) {
+                Ok(operation) => {
+                    self.history.record(Command::File(operation));
+                    deleted += 1;
+                }
+                Err(e) => error = error.or(Some(e)),
+            }
+        }
+        let selected = self.selected_image_index.and_then(|i| self.file_infos.get(i)).map(|f| f.path.clone())
+            .filter(|path| path.exists());
+        self.open_folder(ctx, self.current_dir.clone(), selected.as_deref());
+        self.status_text = match error {
+            Some(error) => format!("Deleted {} of {} images: {}", deleted, paths.len(), error),
+            None => format!("Deleted {} images; Undo restores them until the viewer is closed", deleted),
+        };
+    }
+
+    fn toggle_folder_pane(&mut self) {
+        if self.folder_pane.take().is_none() {
+            // Starts beside the current folder, where a "keep" folder usually goes
+            let dir = self.current_dir.parent().unwrap_or(&self.current_dir).to_path_buf();
+            self.folder_pane = Some(FolderPane::open(dir, &self.settings.supported_formats));
+        }
+    }
+
+    /// Second folder beside the file list: navigate its subfolders, drop images
+    /// from the file list onto it, or drag its images back onto the list
+    fn render_folder_pane(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let Some(pane) = &mut self.folder_pane else {
+            return;
+        };
+        let mut navigate = None;
+        let mut swap = false;
+        let panel = egui::SidePanel::left("folder_pane_panel")
+            .resizable(true)
+            .default_width(200.0)
+            .show_inside(ui, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.small_button("⬆").on_hover_text("Parent folder").clicked() {
+                        navigate = pane.dir.parent().map(|dir| dir.to_path_buf());
+                    }
+                    if ui.small_button("⇄").on_hover_text("Swap with the current folder").clicked() {
+                        swap = true;
+                    }
+                    let input = ui.add(egui::TextEdit::singleline(&mut pane.path_input).desired_width(f32::INFINITY));
+                    if input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        navigate = Some(PathBuf::from(pane.path_input.trim()));
+                    }
+                });
+                ui.weak(format!("{} images; drop images here to move them", pane.images.len()));
+                ui.separator();
+                egui::ScrollArea::vertical().id_salt("folder_pane_files").show(ui, |ui| {
+                    for folder in &pane.folders {
+                        let name = folder.file_name().unwrap_or_default().to_string_lossy();
+                        if ui.selectable_label(false, format!("📁 {}", name)).clicked() {
+                            navigate = Some(folder.clone());
+                        }
+                    }
+                    for (index, image) in pane.images.iter().enumerate() {
+                        let name = image.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                        ui.dnd_drag_source(egui::Id::new(("folder_pane_image", index)), PaneDrag(image.clone()), |ui| {
+                            ui.label(self.settings.truncate_filename(&name));
+                        }).response.on_hover_text("Drag onto the file list to move it to the current folder");
+                    }
+                });
+            });
+
+        let pane_dir = pane.dir.clone();
+        if panel.response.dnd_hover_payload::<FileDrag>().is_some() {
+            ui.painter().rect_stroke(panel.response.rect.shrink(1.0), 2.0, ui.visuals().selection.stroke, egui::StrokeKind::Inside);
+        }
+        if let Some(dragged) = panel.response.dnd_release_payload::<FileDrag>() {
+            self.move_to_folder(ctx, &dragged.0, pane_dir.clone());
+        }
+        if swap {
+            navigate = Some(self.current_dir.clone());
+            self.open_folder(ctx, pane_dir, None);
+        }
+        if let Some(dir) = navigate {
+            if dir.is_dir() {
+                self.folder_pane = Some(FolderPane::open(dir, &self.settings.supported_formats));
+            } else {
+                self.status_text = format!("{} is not a folder", dir.display());
+            }
+        }
+    }
+
+    fn move_selection_to_folder_pane(&mut self, ctx: &egui::Context) {
+        let Some(folder) = self.folder_pane.as_ref().map(|pane| pane.dir.clone()) else {
+            self.status_text = "Turn on View > Two-Pane Layout to choose where images are moved".to_string();
+            return;
+        };
+        let targets: Vec<usize> = if self.file_selection.is_empty() {
+            self.selected_image_index.into_iter().collect()
+        } else {
+            self.file_selection.indices().collect()
+        };
+        let paths: Vec<PathBuf> = targets.iter().filter_map(|&i| self.file_infos.get(i)).map(|f| f.path.clone()).collect();
+        self.move_to_folder(ctx, &paths, folder);
+    }
+
+    /// Move `paths` into `folder`, recording each move for undo
+    fn move_to_folder(&mut self, ctx: &egui::Context, paths: &[PathBuf], folder: PathBuf) {
+        if paths.is_empty() {
+            return;
+        }
+        if archive::is_archive(&self.current_dir) {
+            self.status_text = "Images in archives can't be moved; extract them first".to_string();
+            return;
+        }
+        let paths = &self.with_collapsed_stacks(paths);
+        crash::record_action(format!("Move {} images to {}", paths.len(), folder.display()));
+        let (moved, errors) = folder_pane::move_all(paths, &folder);
+        // Carry on from the first image after those moved out of the list
+        let last_moved = self.file_infos.iter().rposition(|f| moved.iter().any(|operation| operation.original() == f.path));
+        let next = last_moved.and_then(|last| {
+            self.file_infos[last + 1..].iter().chain(self.file_infos[..last].iter().rev())
+                .find(|f| !moved.iter().any(|operation| operation.original() == f.path))
+        }).map(|f| f.path.clone());
+        let selected = self.selected_image_index.and_then(|i| self.file_infos.get(i)).map(|f| f.path.clone())
+            .filter(|path| path.exists());
+        let count = moved.len();
+        for operation in moved {
+            self.history.record(Command::File(operation));
+        }
+        self.open_folder(ctx, self.current_dir.clone(), selected.or(next).as_deref());
+        self.status_text = match errors.first() {
+            Some(error) => format!("Moved {} of {} images: {}", count, paths.len(), error),
+            None => format!("Moved {} images to {}", count, folder.display()),
+        };
+    }
+
+    /// Open `path` in the editor at `editor`, downloading on-demand files first
+    fn open_in_editor(&mut self, index: usize, path: PathBuf) {
+        let Some(editor) = self.external_editors.get(index) else {
+            return;
+        };
+        if archive::is_archive(&self.current_dir) {
+            self.status_text = "Images in archives can't be edited; extract them first".to_string();
+            return;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let on_demand = self.file_infos.iter().any(|f| f.path == path && f.will_trigger_download());
+        if on_demand {
+            // Editors can save over a placeholder, so the file is downloaded first
+            if !self.download_queue.is_queued(&path) {
+                self.download_queue.enqueue(path.clone());
+            }
+            self.status_text = format!("Downloading {} before opening it in {}", self.settings.truncate_filename(&name), editor.name);
+            self.pending_edit = Some((index, path));
+            return;
+        }
+        crash::record_action(format!("Open {} in {}", path.display(), editor.name));
+        match editor.launch(&path) {
+            Ok(()) => {
+                self.edit_watcher.watch(&path);
+                self.status_text = format!("Opened {} in {}", self.settings.truncate_filename(&name), editor.name);
+            }
+            Err(e) => self.status_text = e,
+        }
+    }
+
+    /// Reload images that were saved in an editor
+    fn process_external_edits(&mut self, ctx: &egui::Context) {
+        if !self.edit_watcher.is_watching() {
+            return;
+        }
+        let saved = self.edit_watcher.poll(Instant::now());
+        if !saved.is_empty() {
+            // Dimensions and thumbnails in the list are stale
+            self.file_rows.clear();
+            let displayed = self.selected_image_index.and_then(|i| self.file_infos.get(i)).map(|f| f.path.clone());
+            if let Some(path) = displayed.filter(|path| saved.contains(path)) {
+                self.load_selected_image(ctx);
+                let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                self.status_text = format!("Reloaded {} after it was edited", self.settings.truncate_filename(&name));
+            }
+        }
+        self.repaint.request_after(RepaintReason::Background, POLL_INTERVAL);
+    }
+
+    /// Refresh the search index's records of the current folder in the background
+    fn index_folder(&mut self) {
+        let dir = self.current_dir.clone();
+        let paths: Vec<PathBuf> = self.file_infos.iter().map(|f| f.path.clone()).collect();
+        let previous = self.search_index.folder(&dir);
+        let sender = self.search_updates.0.clone();
+        if let Some(superseded) = self.search_indexing.take() {
+            self.job_engine.cancel(superseded);
+        }
+        self.search_indexing = Some(self.job_engine.submit("Search indexing", JobPriority::Idle, move |job| {
+            let mut images = Vec::with_capacity(paths.len());
+            for (done, path) in paths.iter().enumerate() {
+                job.checkpoint()?;
+                job.report_progress(done as u64, Some(paths.len() as u64));
+                images.extend(IndexedImage::read(path, previous.get(path)));
+            }
+            let count = images.len();
+            let _ = sender.send((dir, images));
+            Ok(format!("indexed {} images", count))
+        }));
+    }
+
+    /// Save the folders indexed since the last save on the job engine, one
+    /// save at a time and at most every [`SEARCH_INDEX_SAVE_INTERVAL`], so
+    /// indexing several folders writes them in one transaction
+    fn save_search_index(&mut self) {
+        if let Some(saving) = &self.search_index_saving {
+            match saving.try_recv() {
+                Ok(Err(e)) => self.status_text = e,
+                Ok(Ok(())) | Err(std::sync::mpsc::TryRecvError::Disconnected) => {}
+                Err(std::sync::mpsc::TryRecvError::Empty) => return,
+            }
+            self.search_index_saving = None;
+        }
+        let Some(changed) = self.search_index_changed else {
+            return;
+        };
+        let waited = changed.elapsed();
+        if waited < SEARCH_INDEX_SAVE_INTERVAL {
+            self.repaint.request_after(RepaintReason::Background, SEARCH_INDEX_SAVE_INTERVAL - waited);
+            return;
+        }
+        self.search_index_changed = None;
+        let folders = self.search_index.take_unsaved();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.search_index_saving = Some(receiver);
+        self.job_engine.submit("Save search index", JobPriority::Interactive, move |_| {
+            let saved = search_index::save_folders(&folders);
+            let _ = sender.send(saved.clone());
+            saved.map(|()| "saved".to_string())
+        });
+    }
+
+    /// Measure the sharpness of the folder's local images not measured yet,
+    /// while the user is idle
+    fn measure_sharpness(&mut self) {
+        if let Some(previous) = self.sharpness_scan.take() {
+            self.job_engine.cancel(previous);
+        }
+        if !self.settings.detect_blur {
+            return;
+        }
+        let paths: Vec<PathBuf> = self.file_infos.iter()
+            .filter(|f| !f.will_trigger_download() && !video::is_video(&f.path))
+            .filter(|f| !f.path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")))
+            .filter(|f| !self.sharpness.contains_key(&f.path))
+            .map(|f| f.path.clone())
+            .collect();
+        if paths.is_empty() {
+            return;
+        }
+        let sender = self.sharpness_updates.0.clone();
+        self.sharpness_scan = Some(self.job_engine.submit("Blur detection", JobPriority::Idle, move |job| {
+            let mut measured = 0;
+            for (done, path) in paths.iter().enumerate() {
+                job.checkpoint()?;
+                job.report_progress(done as u64, Some(paths.len() as u64));
+                // Files that don't decode are left to the integrity scan
+                if let Ok(score) = measure_file(path) {
+                    measured += 1;
+                    let _ = sender.send((path.clone(), score));
+                }
+            }
+            Ok(format!("measured the sharpness of {} images", measured))
+        }));
+    }
+
+    fn process_sharpness_results(&mut self) {
+        self.sharpness.extend(self.sharpness_updates.1.try_iter());
+    }
+
+    /// The sharpness score of an image flagged as possibly blurry
+    fn possibly_blurry(&self, path: &std::path::Path) -> Option<f32> {
+        self.sharpness.get(path).copied().filter(|&score| self.settings.detect_blur && score < self.settings.blur_threshold)
+    }
+
+    /// Hash the local files at `targets` on the job engine, checking them
+    /// against the folder's manifests. The files are split into a job per
+    /// processor so the engine's workers hash them side by side.
+    fn compute_checksums(&mut self, targets: Vec<usize>) {
+        let paths: Vec<PathBuf> = targets.iter()
+            .filter_map(|&i| self.file_infos.get(i))
+            .filter(|f| !f.will_trigger_download())
+            .map(|f| f.path.clone())
+            .collect();
+        if paths.is_empty() {
+            self.status_text = "Only downloaded files can be checksummed".to_string();
+            return;
+        }
+        crash::record_action(format!("Checksum {} files", paths.len()));
+        let manifests = Arc::new(read_manifests(&self.current_dir));
+        let jobs = std::thread::available_parallelism().map_or(1, usize::from).min(paths.len());
+        for paths in paths.chunks(paths.len().div_ceil(jobs)) {
+            let paths = paths.to_vec();
+            let manifests = Arc::clone(&manifests);
+            let sender = self.checksum_updates.0.clone();
+            self.job_engine.submit("Checksums", JobPriority::Interactive, move |job| {
+                let mut mismatches = 0;
+                for (done, path) in paths.iter().enumerate() {
+                    job.checkpoint()?;
+                    job.report_progress(done as u64, Some(paths.len() as u64));
+                    let result = checksum_file(path).map(|checksums| {
+                        let verification = manifests.verify(path, &checksums);
+                        (checksums, verification)
+                    });
+                    if matches!(result, Ok((_, Verification::Mismatch { .. }))) {
+                        mismatches += 1;
+                    }
+                    let _ = sender.send((path.clone(), result));
+                }
+                Ok(if mismatches > 0 {
+                    format!("{} of {} files don't match their manifest", mismatches, paths.len())
+                } else {
+                    format!("checksummed {} files", paths.len())
+                })
+            });
+        }
+    }
+
+    fn process_checksum_results(&mut self) {
+        while let Ok((path, result)) = self.checksum_updates.1.try_recv() {
+            match result {
+                Ok(entry) => {
+                    self.checksums.insert(path, entry);
+                }
+                Err(e) => self.status_text = e,
+            }
+        }
+    }
+
+    fn render_checksums(&mut self, ui: &mut egui::Ui, index: usize) {
+        let file_info = &self.file_infos[index];
+        let Some((checksums, verification)) = self.checksums.get(&file_info.path) else {
+            if ui.add_enabled(!file_info.will_trigger_download(), egui::Button::new("Compute Checksums"))
+                .on_disabled_hover_text("Download the file first")
+                .clicked() {
+                self.compute_checksums(vec![index]);
+            }
+            return;
+        };
+        egui::CollapsingHeader::new("Checksums").default_open(true).show(ui, |ui| {
+            egui::Grid::new("checksums_grid").num_columns(2).show(ui, |ui| {
+                for (name, hash) in [("SHA-256", &checksums.sha256), ("BLAKE3", &checksums.blake3)] {
+                    ui.label(format!("{}:", name));
+                    if ui.add(egui::Label::new(egui::RichText::new(hash).monospace()).wrap().sense(egui::Sense::click()))
+                        .on_hover_text("Click to copy")
+                        .clicked() {
+                        ui.ctx().copy_text(hash.clone());
+                    }
+                    ui.end_row();
+                }
+            });
+            let color = match verification {
+                Verification::Verified => egui::Color32::GREEN,
+                Verification::Mismatch { .. } => egui::Color32::RED,
+                Verification::Unlisted => egui::Color32::GRAY,
+                Verification::Conflict { .. } => egui::Color32::YELLOW,
+            };
+            ui.add(egui::Label::new(egui::RichText::new(verification.description()).color(color)).wrap());
+        });
+    }
+
+    fn handle_search_window(&mut self, ctx: &egui::Context) {
+        while let Ok((dir, images)) = self.search_updates.1.try_recv() {
+            self.search_index.replace_folder(&dir, images);
+            self.search_index_changed.get_or_insert_with(Instant::now);
+        }
+        self.save_search_index();
+        if !self.show_search {
+            return;
+        }
+
+        let mut open = true;
+        let mut selected = None;
+        egui::Window::new("Search Library")
+            .open(&mut open)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.search_query)
+                    .hint_text("e.g. iso>1600 2023 .nef tag:harbour rating>=4")
+                    .desired_width(f32::INFINITY));
+                let results = self.search_index.search(&self.search_query);
+                ui.label(format!("{} of {} indexed images", results.len(), self.search_index.len()))
+                    .on_hover_text("Fields: iso, width, height, mp, rating, size (MB), year, f, focal, tag, camera, model, format, name");
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    egui::Grid::new("search_results").num_columns(3).striped(true).show(ui, |ui| {
+                        for image in results.iter().take(500) {
+                            let name = image.path.file_name().unwrap_or_default().to_string_lossy();
+                            if ui.selectable_label(false, name).on_hover_text(image.path.display().to_string()).clicked() {
+                                selected = Some(image.path.clone());
+                            }
+                            ui.label(if image.width > 0 { format!("{} x {}", image.width, image.height) } else { String::new() });
+                            ui.label(if image.rating > 0 { stars_text(image.rating) } else { String::new() });
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        if let Some(path) = selected {
+            crash::record_action(format!("Open search result {}", path.display()));
+            self.open_favorite(ctx, &path);
+        }
+        if !open {
+            self.show_search = false;
+        }
+    }
+
+    /// Hash the folder's local raster images on the job engine, split into a
+    /// job per processor like the checksums
+    fn find_duplicates(&mut self) {
+        let paths: Vec<PathBuf> = self.file_infos.iter()
+            .filter(|f| !f.will_trigger_download())
+            .filter(|f| !f.path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")))
+            .map(|f| f.path.clone())
+            .collect();
+        crash::record_action(format!("Find duplicates among {} images", paths.len()));
+        if paths.is_empty() {
+            self.duplicates = Some(DuplicateReview::new(Vec::new(), &self.current_dir));
+            return;
+        }
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let jobs = std::thread::available_parallelism().map_or(1, usize::from).min(paths.len());
+        let chunks: Vec<Vec<PathBuf>> = paths.chunks(paths.len().div_ceil(jobs)).map(<[PathBuf]>::to_vec).collect();
+        self.duplicate_scan = Some((receiver, chunks.len(), Vec::with_capacity(paths.len())));
+        for paths in chunks {
+            let sender = sender.clone();
+            self.job_engine.submit("Duplicate scan", JobPriority::Interactive, move |job| {
+                let mut hashed = Vec::with_capacity(paths.len());
+                for (done, path) in paths.iter().enumerate() {
+                    job.checkpoint()?;
+                    job.report_progress(done as u64, Some(paths.len() as u64));
+                    // Files that don't decode are left to the integrity scan
+                    if let Ok(image) = hash_file(path) {
+                        hashed.push(image);
+                    }
+                }
+                let count = hashed.len();
+                let _ = sender.send(hashed);
+                Ok(format!("hashed {} images", count))
+            });
+        }
+    }
+
+    fn handle_duplicates_window(&mut self, ctx: &egui::Context) {
+        if let Some((receiver, running, hashed)) = &mut self.duplicate_scan {
+            let mut cancelled = false;
+            loop {
+                match receiver.try_recv() {
+                    Ok(images) => {
+                        hashed.extend(images);
+                        *running -= 1;
+                    }
+                    Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                    // Every job has ended; one that was cancelled sent nothing
+                    Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                        cancelled = *running > 0;
+                        break;
+                    }
+                }
+            }
+            if *running == 0 {
+                let images = std::mem::take(hashed);
+                self.duplicate_scan = None;
+                self.duplicates = Some(DuplicateReview::new(images, &self.current_dir));
+            } else if cancelled {
+                self.duplicate_scan = None;
+            } else {
+                self.repaint.request_after(RepaintReason::Decode, std::time::Duration::from_millis(200));
+            }
+        }
+        let Some(review) = self.duplicates.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut action = None;
+        egui::Window::new("Duplicates")
+            .open(&mut open)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Similarity:");
+                    if ui.add(egui::Slider::new(&mut review.max_distance, 0..=16).text("bits apart"))
+                        .on_hover_text("0 finds only identical-looking images; higher values also group edited copies")
+                        .changed() {
+                        review.regroup();
+                    }
+                });
+                let copies: usize = review.groups.iter().map(|group| group.len() - 1).sum();
+                ui.label(format!("{} groups, {} redundant copies among {} images", review.groups.len(), copies, review.images.len()));
+                if review.groups.is_empty() {
+                    return;
+                }
+                if ui.button("Mark All but the Largest").clicked() {
+                    review.mark_all_but_largest();
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                    for (index, group) in review.groups.iter().enumerate() {
+                        ui.strong(format!("Group {}", index + 1));
+                        for image in group {
+                            let mut marked = review.marked.contains(&image.path);
+                            let name = image.path.file_name().unwrap_or_default().to_string_lossy();
+                            let label = format!("{} · {}×{} · {}", name, image.width, image.height, format_size(image.file_size));
+                            if ui.checkbox(&mut marked, label).on_hover_text(image.path.display().to_string()).changed() {
+                                if marked {
+                                    review.marked.insert(image.path.clone());
+                                } else {
+                                    review.marked.remove(&image.path);
+                                }
+                            }
+                        }
+                        ui.add_space(4.0);
+                    }
+                });
+                ui.separator();
+                let left_empty = groups_left_empty(&review.groups, &review.marked);
+                if left_empty > 0 {
+                    ui.colored_label(egui::Color32::YELLOW, format!(
+                        "{} groups have every copy marked; unmark one in each to keep it",
+                        left_empty,
+                    ));
+                }
+                let any_marked = !review.marked.is_empty() && left_empty == 0;
+                ui.horizontal(|ui| {
+                    ui.label("Move to:");
+                    ui.add(egui::TextEdit::singleline(&mut review.move_folder).desired_width(260.0));
+                    if ui.add_enabled(any_marked && !review.move_folder.trim().is_empty(), egui::Button::new("Move Marked")).clicked() {
+                        action = Some(false);
+                    }
+                });
+                if review.confirm_delete {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(egui::Color32::YELLOW, format!("Delete {} files?", review.marked.len()));
+                        if ui.button("Delete").clicked() {
+                            action = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            review.confirm_delete = false;
+                        }
+                    });
+                } else if ui.add_enabled(any_marked, egui::Button::new("🗑 Delete Marked…")).clicked() {
+                    review.confirm_delete = true;
+                }
+            });
+        if !open {
+            self.duplicates = None;
+            return;
+        }
+        let Some(delete) = action else {
+            return;
+        };
+        if groups_left_empty(&review.groups, &review.marked) > 0 {
+            review.confirm_delete = false;
+            self.status_text = "Every group needs one unmarked copy to keep".to_string();
+            return;
+        }
+
+        let marked: Vec<PathBuf> = review.marked.drain().collect();
+        let folder = PathBuf::from(review.move_folder.trim());
+        review.confirm_delete = false;
+        let mut removed = Vec::new();
+        let mut operations = Vec::new();
+        let mut first_error = None;
+        for path in marked {
+            let result = if delete {
+                FileOperation::delete(&path)
+            } else {
+                FileOperation::move_into(&path, &folder)
+            };
+            match result {
+                Ok(operation) => {
+                    operations.push(operation);
+                    removed.push(path);
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        review.images.retain(|image| !removed.contains(&image.path));
+        review.regroup();
+        for operation in operations {
+            self.history.record(Command::File(operation));
+        }
+        crash::record_action(format!("{} {} duplicates", if delete { "Delete" } else { "Move" }, removed.len()));
+
+        let selected = self.selected_image_index.and_then(|i| self.file_infos.get(i)).map(|f| f.path.clone())
+            .filter(|path| !removed.contains(path));
+        self.open_folder(ctx, self.current_dir.clone(), selected.as_deref());
+        self.status_text = first_error.unwrap_or_else(|| format!(
+            "{} {} duplicate images; Undo restores them",
+            if delete { "Deleted" } else { "Moved" },
+            removed.len(),
+        ));
+    }
+
+    /// Check the folder's local images on the job engine, listing those that fail
+    fn scan_for_unreadable(&mut self) {
+        let Some(review) = self.triage.as_mut() else {
+            return;
+        };
+        let paths: Vec<PathBuf> = self.file_infos.iter()
+            .filter(|f| !f.will_trigger_download() && !video::is_video(&f.path))
+            .map(|f| f.path.clone())
+            .collect();
+        let full_decode = review.full_decode;
+        review.folder = self.current_dir.clone();
+        crash::record_action(format!("Scan {} images for unreadable files", paths.len()));
+        let (sender, receiver) = std::sync::mpsc::channel();
+        review.scan = Some(receiver);
+        let settings = self.settings.clone();
+        let decode_failures = Arc::clone(&self.decode_failures);
+        self.job_engine.submit("Unreadable image scan", JobPriority::Interactive, move |job| {
+            let mut unreadable = Vec::new();
+            for (done, path) in paths.iter().enumerate() {
+                job.checkpoint()?;
+                job.report_progress(done as u64, Some(paths.len() as u64));
+                if let Err(error) = check_image(path, full_decode, &settings) {
+                    if let Ok(mut known) = decode_failures.lock() {
+                        known.insert(path.clone());
+                    }
+                    unreadable.push(UnreadableImage { path: path.clone(), error });
+                }
+            }
+            let summary = format!("{} of {} images can't be read", unreadable.len(), paths.len());
+            let _ = sender.send((paths.len(), unreadable));
+            Ok(summary)
+        });
+    }
+
+    fn handle_triage_window(&mut self, ctx: &egui::Context) {
+        let Some(review) = self.triage.as_mut() else {
+            return;
+        };
+        if let Some(receiver) = &review.scan {
+            match receiver.try_recv() {
+                Ok((checked, unreadable)) => {
+                    review.scan = None;
+                    review.checked = Some(checked);
+                    review.marked = unreadable.iter().map(|image| image.path.clone()).collect();
+                    review.unreadable = unreadable;
+                }
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => review.scan = None,
+                Err(std::sync::mpsc::TryRecvError::Empty) => self.repaint.request_after(RepaintReason::Decode, std::time::Duration::from_millis(200)),
+            }
+        }
+
+        let mut open = true;
+        let mut scan = false;
+        let mut quarantine = false;
+        egui::Window::new("Unreadable Images")
+            .open(&mut open)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(review.scan.is_none(), |ui| {
+                        ui.checkbox(&mut review.full_decode, "Decode every pixel")
+                            .on_hover_text("Also finds truncated and damaged image data, but reads every file in full");
+                        if ui.button("Scan").clicked() {
+                            scan = true;
+                        }
+                    });
+                    if review.scan.is_some() {
+                        ui.spinner();
+                        ui.label("Scanning…");
+                    }
+                });
+                let Some(checked) = review.checked else {
+                    ui.label("Only downloaded images are checked.");
+                    return;
+                };
+                if review.unreadable.is_empty() {
+                    ui.label(format!("All {} images could be read", checked));
+                    return;
+                }
+                ui.label(format!("{} of {} images can't be read", review.unreadable.len(), checked));
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                    for image in &review.unreadable {
+                        let mut marked = review.marked.contains(&image.path);
+                        let name = image.path.file_name().unwrap_or_default().to_string_lossy();
+                        if ui.checkbox(&mut marked, name).on_hover_text(image.path.display().to_string()).changed() {
+                            if marked {
+                                review.marked.insert(image.path.clone());
+                            } else {
+                                review.marked.remove(&image.path);
+                            }
+                        }
+                        ui.indent(&image.path, |ui| ui.weak(&image.error));
+                    }
+                });
+                ui.separator();
+                if ui.add_enabled(!review.marked.is_empty(), egui::Button::new(format!("Move Marked to {}/", QUARANTINE_FOLDER)))
+                    .on_hover_text(format!("Move the marked files into a subfolder of {}", review.folder.display()))
+                    .clicked() {
+                    quarantine = true;
+                }
+            });
+        if !open {
+            self.triage = None;
+            return;
+        }
+        if scan {
+            self.scan_for_unreadable();
+            return;
+        }
+        if !quarantine {
+            return;
+        }
+
+        let folder = review.folder.join(QUARANTINE_FOLDER);
+        let mut moved = Vec::new();
+        let mut operations = Vec::new();
+        let mut first_error = None;
+        for path in review.marked.drain() {
+            match FileOperation::move_into(&path, &folder) {
+                Ok(operation) => {
+                    operations.push(operation);
+                    moved.push(path);
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        review.unreadable.retain(|image| !moved.contains(&image.path));
+        for operation in operations {
+            self.history.record(Command::File(operation));
+        }
+        crash::record_action(format!("Quarantine {} unreadable images", moved.len()));
+
+        let selected = self.selected_image_index.and_then(|i| self.file_infos.get(i)).map(|f| f.path.clone())
+            .filter(|path| !moved.contains(path));
+        self.open_folder(ctx, self.current_dir.clone(), selected.as_deref());
+        self.status_text = first_error.unwrap_or_else(|| format!("Moved {} unreadable images to {}", moved.len(), folder.display()));
+    }
+
+    /// Confirm the settings suggested by the benchmark before applying them
+    fn handle_tuning_dialog(&mut self, ctx: &egui::Context) {
+        let Some(tuned) = self.suggested_tuning.clone() else {
+            return;
+        };
+        let changes = tuned.changes(&self.settings);
+
+        let mut answer = None;
+        egui::Window::new("Tune Settings for This Computer")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("Based on the benchmark, these settings suit this computer better:");
+                egui::Grid::new("tuning_grid").num_columns(3).striped(true).show(ui, |ui| {
+                    ui.strong("Setting");
+                    ui.strong("Current");
+                    ui.strong("Suggested");
+                    ui.end_row();
+                    for change in &changes {
+                        ui.label(change.setting);
+                        ui.label(&change.current);
+                        ui.label(&change.suggested);
+                        ui.end_row();
+                    }
+                });
+                if changes.iter().any(|change| change.setting == "Decode threads") {
+                    ui.small("The number of decode threads changes after a restart.");
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Apply").clicked() {
+                        answer = Some(true);
+                    }
+                    if ui.button("Keep Current").clicked() {
+                        answer = Some(false);
+                    }
+                });
+            });
+        let Some(apply) = answer else {
+            return;
+        };
+        self.suggested_tuning = None;
+        let mut preferences = Preferences::load();
+        tuning::mark_offered(&mut preferences);
+        if apply {
+            tuned.apply(&mut self.settings);
+            tuned.save_to(&mut preferences);
+            self.status_text = format!("Applied {} tuned settings", changes.len());
+        }
+        if let Err(e) = preferences.save() {
+            self.status_text = e;
+        }
+    }
+
+    /// Save what's open so it can be restored after a crash
+    fn record_session(&mut self) {
+        // Nothing is saved until the offer to restore the last session is answered
+        if self.recovered_session.is_some() || !self.session_recorder.is_due() {
+            return;
+        }
+        let state = SessionState {
+            folder: self.current_dir.clone(),
+            displayed: self.selected_image_index.and_then(|i| self.file_infos.get(i)).map(|f| f.path.clone()),
+            selection: self.file_selection.indices().filter_map(|i| self.file_infos.get(i)).map(|f| f.path.clone()).collect(),
+            filter: self.filter_text.clone(),
+        };
+        if let Err(e) = self.session_recorder.record(state) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+
+    fn restore_session(&mut self, ctx: &egui::Context, state: SessionState) {
+        if !state.folder.is_dir() {
+            self.status_text = format!("Can't restore the session: {} no longer exists", state.folder.display());
+            return;
+        }
+        self.open_folder(ctx, state.folder.clone(), state.displayed.as_deref());
+        for path in &state.selection {
+            if let Some(index) = self.file_infos.iter().position(|f| f.path == *path)
+                && !self.file_selection.contains(index) {
+                self.file_selection.click(index, true, false);
+            }
+        }
+        self.update_selection_summary();
+        self.filter_text = state.filter;
+        self.status_text = format!("Restored the previous session in {}", state.folder.display());
+    }
+
+    /// Offer to reopen the folder and images of a session that ended in a crash
+    fn handle_session_recovery_dialog(&mut self, ctx: &egui::Context) {
+        let Some(state) = self.recovered_session.clone() else {
+            return;
+        };
+
+        let mut answer = None;
+        egui::Window::new("Restore Previous Session?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("The previous session didn't close normally. Pick up where it left off?");
+                egui::Grid::new("recovered_session_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("Folder:");
+                    ui.label(state.folder.display().to_string());
+                    ui.end_row();
+                    if let Some(displayed) = &state.displayed {
+                        ui.label("Image:");
+                        ui.label(displayed.file_name().unwrap_or_default().to_string_lossy());
+                        ui.end_row();
+                    }
+                    if state.selection.len() > 1 {
+                        ui.label("Selected:");
+                        ui.label(format!("{} images", state.selection.len()));
+                        ui.end_row();
+                    }
+                    if !state.filter.is_empty() {
+                        ui.label("Filter:");
+                        ui.label(&state.filter);
+                        ui.end_row();
+                    }
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Restore").clicked() {
+                        answer = Some(true);
+                    }
+                    if ui.button("Start Fresh").clicked() {
+                        answer = Some(false);
+                    }
+                });
+            });
+        if let Some(restore) = answer {
+            self.recovered_session = None;
+            if restore {
+                self.restore_session(ctx, state);
+            }
+        }
+    }
+
+    /// Offer the report left by a crash in the previous session
+    fn handle_crash_report_dialog(&mut self, ctx: &egui::Context) {
+        let Some(report) = self.crash_report.clone() else {
+            return;
+        };
+
+        let mut dismiss = false;
+        egui::Window::new("Image PreViewer Closed Unexpectedly")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("The previous session crashed. A report with the error and your last actions was saved:");
+                ui.monospace(report.display().to_string());
+                ui.label("Attaching it to a bug report helps get the crash fixed.");
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("📄 Open Report").clicked() {
+                        if let Err(e) = open_with_system(&report) {
+                            self.status_text = e;
+                        }
+                        dismiss = true;
+                    }
+                    if let Some(dir) = report.parent()
+                        && ui.button("Show Folder").clicked() {
+                        if let Err(e) = open_with_system(dir) {
+                            self.status_text = e;
+                        }
+                        dismiss = true;
+                    }
+                    if ui.button("Dismiss").clicked() {
+                        dismiss = true;
+                    }
+                });
+            });
+        if dismiss {
+            self.crash_report = None;
+        }
+    }
+
+    fn open_svg_export_dialog(&mut self, path: PathBuf) {
+        let settings = self.svg_settings_for(&path);
+        match svg_intrinsic_size(&path, &settings) {
+            Ok(intrinsic_size) => {
+                let output_path = path.with_extension("png").to_string_lossy().to_string();
+                let mut dialog = SvgExportDialog {
+                    source: path,
+                    intrinsic_size,
+                    width: 0,
+                    height: 0,
+                    keep_aspect: true,
+                    transparent: true,
+                    background: egui::Color32::WHITE,
+                    output_path,
+                };
+                dialog.set_scale(1.0);
+                self.svg_export = Some(dialog);
+                self.show_svg_export_dialog = true;
+            }
+            Err(e) => self.status_text = format!("Error exporting SVG: {}", e),
+        }
+    }
+
+    fn handle_svg_export_dialog(&mut self, ctx: &egui::Context) {
+        if !self.show_svg_export_dialog {
+            return;
+        }
+        let Some(dialog) = self.svg_export.as_mut() else {
+            self.show_svg_export_dialog = false;
+            return;
+        };
+
+        let mut export = false;
+        let mut close = false;
+        egui::Window::new("Export SVG as PNG")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                let (intrinsic_width, intrinsic_height) = dialog.intrinsic_size;
+                let aspect = intrinsic_height / intrinsic_width.max(1.0);
+                egui::Grid::new("svg_export_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("Intrinsic size:");
+                    ui.label(format!("{:.0} x {:.0}", intrinsic_width, intrinsic_height));
+                    ui.end_row();
+
+                    ui.label("Width:");
+                    if ui.add(egui::DragValue::new(&mut dialog.width).range(1..=MAX_SVG_EXPORT_SIZE).suffix(" px")).changed()
+                        && dialog.keep_aspect {
+                        dialog.height = ((dialog.width as f32 * aspect).round() as u32).clamp(1, MAX_SVG_EXPORT_SIZE);
+                    }
+                    ui.end_row();
+
+                    ui.label("Height:");
+                    if ui.add(egui::DragValue::new(&mut dialog.height).range(1..=MAX_SVG_EXPORT_SIZE).suffix(" px")).changed()
+                        && dialog.keep_aspect && aspect > 0.0 {
+                        dialog.width = ((dialog.height as f32 / aspect).round() as u32).clamp(1, MAX_SVG_EXPORT_SIZE);
+                    }
+                    ui.end_row();
+
+                    ui.label("Scale:");
+                    let mut scale = dialog.scale();
+                    if ui.add(egui::DragValue::new(&mut scale).range(0.01..=64.0).speed(0.05).suffix("×")).changed() {
+                        dialog.set_scale(scale);
+                    }
+                    ui.end_row();
+
+                    ui.label("");
+                    ui.checkbox(&mut dialog.keep_aspect, "Keep aspect ratio");
+                    ui.end_row();
+
+                    ui.label("Background:");
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut dialog.transparent, true, "Transparent");
+                        ui.radio_value(&mut dialog.transparent, false, "Color");
+                        ui.add_enabled_ui(!dialog.transparent, |ui| {
+                            ui.color_edit_button_srgba(&mut dialog.background);
+                        });
+                    });
+                    ui.end_row();
+
+                    ui.label("Save as:");
+                    ui.add(egui::TextEdit::singleline(&mut dialog.output_path).desired_width(280.0));
+                    ui.end_row();
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!dialog.output_path.trim().is_empty(), egui::Button::new("Export")).clicked() {
+                        export = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if export {
+            let options = SvgExportOptions {
+                width: dialog.width,
+                height: dialog.height,
+                background: (!dialog.transparent).then_some(dialog.background.to_array()),
+            };
+            let source = dialog.source.clone();
+            let output = PathBuf::from(dialog.output_path.trim());
+            let settings = self.svg_settings_for(&source);
+            let name = format!("Export {}", output.file_name().unwrap_or_default().to_string_lossy());
+            let sink = self.progress.sink();
+            // Large exports take a while, so render off the UI thread
+            std::thread::spawn(move || {
+                let reporter = sink.start(OperationKind::Export, name);
+                match export_svg_as_png(&source, &settings, &options, &output) {
+                    Ok(output) => reporter.finish(format!("Exported {}x{} PNG to {}", options.width, options.height, output.display())),
+                    Err(e) => reporter.fail(format!("Error exporting SVG: {}", e)),
+                }
+            });
+            close = true;
+        }
+        if close {
+            self.show_svg_export_dialog = false;
+            self.svg_export = None;
+        }
+    }
+
+    /// Write or remove the Explorer registration on the job engine; it is a
+    /// few hundred registry entries
+    #[cfg(windows)]
+    fn update_shell_registration(&mut self, register: bool) {
+        let extensions = self.settings.get_supported_extensions().to_vec();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.shell_registration = Some(receiver);
+        let name = if register { "Register with Explorer" } else { "Remove Explorer registration" };
+        self.job_engine.submit(name, JobPriority::Interactive, move |_| {
+            let result = if register {
+                crate::shell_integration::register(&extensions)
+                    .map(|count| format!("Registered for {} image formats; choose it under Default Apps to make it the default", count))
+            } else {
+                crate::shell_integration::unregister(&extensions).map(|()| "Removed the Explorer registration".to_string())
+            };
+            let _ = sender.send(result.clone());
+            result
+        });
+    }
+
+    #[cfg(windows)]
+    fn process_shell_registration(&mut self) {
+        let Some(receiver) = &self.shell_registration else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(result) => {
+                self.status_text = result.unwrap_or_else(|e| format!("Error: {}", e));
+                self.shell_registration = None;
+            }
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => self.shell_registration = None,
+            Err(std::sync::mpsc::TryRecvError::Empty) => self.repaint.request_after(RepaintReason::Background, std::time::Duration::from_millis(200)),
+        }
+    }
+
+    /// Recognize the text in the displayed image, or in `region` of it, on the
+    /// job engine; it is copied once found
+    #[cfg(feature = "ocr")]
+    fn copy_text_from_image(&mut self, region: Option<PixelRect>) {
+        let Some(image) = self.current_image.clone() else {
+            return;
+        };
+        crash::record_action("Copy text from image");
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.ocr_result = Some(receiver);
+        self.job_engine.submit("Text recognition", JobPriority::Interactive, move |job| {
+            job.checkpoint()?;
+            let result = crate::ocr::recognize_text(&image, region);
+            let summary = match &result {
+                Ok(text) => format!("found {} characters", text.chars().count()),
+                Err(e) => e.clone(),
+            };
+            let _ = sender.send(result);
+            Ok(summary)
+        });
+    }
+
+    #[cfg(feature = "ocr")]
+    fn process_ocr_result(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.ocr_result else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(Ok(text)) => {
+                self.status_text = format!("Copied {} characters of text from the image", text.chars().count());
+                ctx.copy_text(text);
+                self.ocr_result = None;
+            }
+            Ok(Err(e)) => {
+                self.status_text = e;
+                self.ocr_result = None;
+            }
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => self.ocr_result = None,
+            Err(std::sync::mpsc::TryRecvError::Empty) => self.repaint.request_after(RepaintReason::Background, std::time::Duration::from_millis(200)),
+        }
+    }
+
+    /// Load the scripts again, so edits take effect without restarting
+    #[cfg(feature = "scripting")]
+    fn reload_scripts(&mut self) {
+        let Some(dir) = crate::settings::config_dir().map(|dir| dir.join(crate::scripting::SCRIPTS_FOLDER)) else {
+            return;
+        };
+        let (scripts, errors) = crate::scripting::load_scripts(&dir);
+        self.scripts = scripts;
+        if let Some(first) = errors.first() {
+            self.status_text = format!("Failed to load script {}", first);
+        }
+        self.script_log.extend(errors);
+    }
+
+    /// What scripts see of the folder and the displayed image
+    #[cfg(feature = "scripting")]
+    fn script_context(&self) -> crate::scripting::ScriptContext {
+        let files: Vec<PathBuf> = self.file_infos.iter().map(|f| f.path.clone()).collect();
+        let ratings = files.iter()
+            .map(|path| (path.clone(), self.sidecars.rating(path).stars))
+            .filter(|(_, stars)| *stars > 0)
+            .collect();
+        let keywords = files.iter()
+            .filter(|path| !self.sidecars.keywords(path).is_empty())
+            .map(|path| (path.clone(), self.sidecars.keywords(path).to_vec()))
+            .collect();
+        crate::scripting::ScriptContext {
+            folder: self.current_dir.clone(),
+            current_image: self.selected_image_index.and_then(|i| self.file_infos.get(i)).map(|f| f.path.clone()),
+            files,
+            ratings,
+            keywords,
+        }
+    }
+
+    /// Call `function` of the script at `index` on the job engine
+    #[cfg(feature = "scripting")]
+    fn run_script(&mut self, index: usize, function: &str, args: Vec<String>) {
+        let Some(script) = self.scripts.get(index).cloned() else {
+            return;
+        };
+        let context = self.script_context();
+        let sender = self.script_updates.0.clone();
+        let function = function.to_string();
+        let name = format!("{} ({})", script.name, function);
+        self.job_engine.submit(format!("Script {}", name), JobPriority::Interactive, move |job| {
+            job.checkpoint()?;
+            let outcome = script.run(&function, args, &context, job.cancellation());
+            let summary = outcome.error.clone().unwrap_or_else(|| "finished".to_string());
+            let _ = sender.send((name, outcome));
+            Ok(summary)
+        });
+    }
+
+    /// Call `hook` in every script that defines it
+    #[cfg(feature = "scripting")]
+    fn run_script_hook(&mut self, hook: &str, path: &std::path::Path) {
+        let hooked: Vec<usize> = (0..self.scripts.len()).filter(|&i| self.scripts[i].has_hook(hook)).collect();
+        for index in hooked {
+            self.run_script(index, hook, vec![path.to_string_lossy().into_owned()]);
+        }
+    }
+
+    /// Show script output and do what the scripts asked for, in order
+    #[cfg(feature = "scripting")]
+    fn process_script_results(&mut self, ctx: &egui::Context) {
+        use crate::scripting::ScriptAction;
+
+        let updates: Vec<_> = self.script_updates.1.try_iter().collect();
+        for (name, outcome) in updates {
+            self.script_log.extend(outcome.log.iter().map(|line| format!("{}: {}", name, line)));
+            if let Some(e) = outcome.error {
+                self.status_text = format!("Script failed: {}", e);
+                self.script_log.push(e);
+            } else if let Some(line) = outcome.log.last() {
+                self.status_text = line.clone();
+            }
+            for action in outcome.actions {
+                match action {
+                    ScriptAction::OpenFolder(dir) => self.open_folder(ctx, dir, None),
+                    ScriptAction::Show(path) => match self.file_infos.iter().position(|f| f.path == path) {
+                        Some(index) => {
+                            self.selected_image_index = Some(index);
+                            self.file_selection.select_only(index);
+                            self.update_selection_summary();
+                            self.load_selected_image(ctx);
+                        }
+                        None => match path.parent() {
+                            Some(dir) if path.is_file() => self.open_folder(ctx, dir.to_path_buf(), Some(&path)),
+                            _ => self.status_text = format!("Script failed: {} not found", path.display()),
+                        },
+                    },
+                    ScriptAction::Next | ScriptAction::Previous => {
+                        let step = if action == ScriptAction::Next { Action::NextImage } else { Action::PreviousImage };
+                        if self.navigate(step) {
+                            self.load_selected_image(ctx);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "scripting")]
+    fn render_scripts_menu(&mut self, ui: &mut egui::Ui) {
+        let mut run = None;
+        for (index, script) in self.scripts.iter().enumerate() {
+            for command in &script.commands {
+                if ui.button(&command.label).on_hover_text(&script.name).clicked() {
+                    run = Some((index, command.function.clone()));
+                    ui.close_menu();
+                }
+            }
+        }
+        if self.scripts.iter().all(|script| script.commands.is_empty()) {
+            ui.weak("No script commands");
+        }
+        if let Some((index, function)) = run {
+            crash::record_action(format!("Run script command {}", function));
+            self.run_script(index, &function, Vec::new());
+        }
+        ui.separator();
+        if ui.button("Reload Scripts").clicked() {
+            self.reload_scripts();
+            self.status_text = format!("Loaded {} script(s)", self.scripts.len());
+            ui.close_menu();
+        }
+        if let Some(dir) = crate::settings::config_dir().map(|dir| dir.join(crate::scripting::SCRIPTS_FOLDER))
+            && ui.button("Open Scripts Folder").on_hover_text(dir.display().to_string()).clicked() {
+            if let Err(e) = std::fs::create_dir_all(&dir).map_err(|e| e.to_string()).and_then(|()| open_with_system(&dir)) {
+                self.status_text = e;
+            }
+            ui.close_menu();
+        }
+        if ui.button("Script Log…").clicked() {
+            self.show_script_log = true;
+            ui.close_menu();
+        }
+    }
+
+    #[cfg(feature = "scripting")]
+    fn render_script_log_window(&mut self, ctx: &egui::Context) {
+        if !self.show_script_log {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Script Log")
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                if self.script_log.is_empty() {
+                    ui.weak("Scripts haven't logged anything yet");
+                    return;
+                }
+                egui::ScrollArea::vertical().max_height(320.0).stick_to_bottom(true).show(ui, |ui| {
+                    for line in &self.script_log {
+                        ui.monospace(line);
+                    }
+                });
+                if ui.button("Clear").clicked() {
+                    self.script_log.clear();
+                }
+            });
+        self.show_script_log = open;
+    }
+
+    fn open_view_export_dialog(&mut self) {
+        let (Some(view), Some(image)) = (self.last_view.clone(), self.current_image.clone()) else {
+            return;
+        };
+        let source = self.selected_image_index.and_then(|i| self.file_infos.get(i)).map(|f| f.path.clone());
+        let output = source.as_ref()
+            .map(|path| path.with_file_name(format!("{}-view.png", path.file_stem().unwrap_or_default().to_string_lossy())))
+            .unwrap_or_else(|| self.current_dir.join("view.png"));
+        let profile = source.as_deref().and_then(ColorProfile::read);
+        self.view_export = Some(ViewExportDialog { view, image, profile, factor: 1.0, output_path: output.display().to_string() });
+    }
+
+    fn handle_view_export_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = self.view_export.as_mut() else {
+            return;
+        };
+
+        let mut export = false;
+        let mut close = false;
+        egui::Window::new("Export View")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                egui::Grid::new("view_export_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("Resolution:");
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut dialog.factor, 1.0, "Screen");
+                        ui.radio_value(&mut dialog.factor, 2.0, "2×");
+                    });
+                    ui.end_row();
+
+                    ui.label("Size:");
+                    let (width, height) = dialog.view.output_size(dialog.factor);
+                    ui.label(format!("{} x {} px", width, height));
+                    ui.end_row();
+
+                    ui.label("Save as:");
+                    ui.add(egui::TextEdit::singleline(&mut dialog.output_path).desired_width(280.0));
+                    ui.end_row();
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!dialog.output_path.trim().is_empty(), egui::Button::new("Export")).clicked() {
+                        export = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if export && let Some(dialog) = self.view_export.take() {
+            let output = PathBuf::from(dialog.output_path.trim());
+            // Export what's on screen, including the soft proof when it is shown
+            let proof = self.settings.soft_proof_enabled.then_some(self.settings.soft_proof);
+            let name = format!("Export {}", output.file_name().unwrap_or_default().to_string_lossy());
+            let sink = self.progress.sink();
+            std::thread::spawn(move || {
+                let reporter = sink.start(OperationKind::Export, name);
+                // Backdrops are sRGB, so the image is converted to match before compositing
+                let converted = dialog.profile.as_ref().and_then(|profile| profile.to_srgb(&dialog.image));
+                let image = converted.as_ref().unwrap_or(&dialog.image);
+                let proofed = proof.map(|options| ProofTransform::new(&options).apply(image));
+                let image = proofed.as_ref().unwrap_or(image);
+                match export_view(image, &dialog.view, dialog.factor, &output) {
+                    Ok((output, (width, height))) => reporter.finish(format!("Exported {}x{} view to {}", width, height, output.display())),
+                    Err(e) => reporter.fail(format!("Error exporting view: {}", e)),
+                }
+            });
+        }
+        if close {
+            self.view_export = None;
+        }
+    }
+
+    fn open_print_dialog(&mut self) {
+        let (Some(image), Some(texture)) = (self.current_image.clone(), self.image_texture.clone()) else {
+            return;
+        };
+        let dpi = self.current_metadata.as_ref().and_then(|metadata| metadata.dpi).map(|(x, _)| x as f32);
+        let name = self.selected_image_index.and_then(|i| self.file_infos.get(i))
+            .map(|f| f.path.file_name().unwrap_or_default().to_string_lossy().into_owned())
+            .unwrap_or_else(|| "image".to_string());
+        // Listing printers runs a program, which can take seconds
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.job_engine.submit("List printers", JobPriority::Interactive, move |_| {
+            let printers = list_printers();
+            let summary = format!("found {}", printers.len());
+            let _ = sender.send(printers);
+            Ok(summary)
+        });
+        self.print_dialog = Some(PrintDialog {
+            layout: PageLayout::for_image(image.size, dpi),
+            image,
+            texture,
+            printers: Vec::new(),
+            printer_list: Some(receiver),
+            printer: None,
+            name,
+        });
+    }
+
+    fn handle_print_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = self.print_dialog.as_mut() else {
+            return;
+        };
+        if let Some(receiver) = &dialog.printer_list {
+            match receiver.try_recv() {
+                Ok(printers) => {
+                    dialog.printers = printers;
+                    dialog.printer_list = None;
+                }
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => dialog.printer_list = None,
+                Err(std::sync::mpsc::TryRecvError::Empty) => self.repaint.request_after(RepaintReason::Background, std::time::Duration::from_millis(200)),
+            }
+        }
+
+        let mut print = false;
+        let mut close = false;
+        egui::Window::new("Print")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.horizontal_top(|ui| {
+                    ui.vertical(|ui| {
+                        egui::Grid::new("print_grid").num_columns(2).show(ui, |ui| {
+                            ui.label("Printer:");
+                            egui::ComboBox::from_id_salt("print_printer")
+                                .selected_text(dialog.printer.as_deref().unwrap_or("Default printer"))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut dialog.printer, None, "Default printer");
+                                    for printer in &dialog.printers {
+                                        ui.selectable_value(&mut dialog.printer, Some(printer.clone()), printer);
+                                    }
+                                    if dialog.printer_list.is_some() {
+                                        ui.weak("Looking for printers…");
+                                    }
+                                });
+                            ui.end_row();
+
+                            ui.label("Paper:");
+                            egui::ComboBox::from_id_salt("print_paper")
+                                .selected_text(dialog.layout.paper.label())
+                                .show_ui(ui, |ui| {
+                                    for paper in Paper::ALL {
+                                        ui.selectable_value(&mut dialog.layout.paper, paper, paper.label());
+                                    }
+                                });
+                            ui.end_row();
+
+                            ui.label("Orientation:");
+                            ui.horizontal(|ui| {
+                                ui.radio_value(&mut dialog.layout.landscape, false, "Portrait");
+                                ui.radio_value(&mut dialog.layout.landscape, true, "Landscape");
+                            });
+                            ui.end_row();
+
+                            ui.label("Size:");
+                            ui.vertical(|ui| {
+                                ui.radio_value(&mut dialog.layout.scaling, PrintScaling::FitToPage, "Fit to page");
+                                ui.horizontal(|ui| {
+                                    ui.radio_value(&mut dialog.layout.scaling, PrintScaling::ActualSize, "Actual size at");
+                                    ui.add(egui::DragValue::new(&mut dialog.layout.image_dpi).range(10.0..=2400.0).suffix(" DPI"));
+                                });
+                            });
+                            ui.end_row();
+
+                            ui.label("Margins:");
+                            ui.add(egui::DragValue::new(&mut dialog.layout.margin).range(0.0..=3.0).speed(0.05).suffix(" in"));
+                            ui.end_row();
+                        });
+                        if dialog.layout.is_cropped(dialog.image.size) {
+                            ui.colored_label(egui::Color32::YELLOW, "⚠ The image is larger than the page and will be cropped");
+                        }
+                    });
+
+                    // Preview of the page, with the margins outlined
+                    let (width, height) = dialog.layout.page_size();
+                    let scale = 220.0 / width.max(height);
+                    let (response, painter) = ui.allocate_painter(egui::vec2(width, height) * scale, egui::Sense::hover());
+                    let to_screen = |rect: egui::Rect| egui::Rect::from_min_max(
+                        response.rect.min + rect.min.to_vec2() * scale,
+                        response.rect.min + rect.max.to_vec2() * scale,
+                    );
+                    painter.rect_filled(response.rect, 0.0, egui::Color32::WHITE);
+                    let printable = to_screen(dialog.layout.printable_rect());
+                    let image_rect = to_screen(dialog.layout.image_rect(dialog.image.size));
+                    painter.with_clip_rect(printable).image(
+                        dialog.texture.id(),
+                        image_rect,
+                        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                        egui::Color32::WHITE,
+                    );
+                    painter.rect_stroke(printable, 0.0, egui::Stroke::new(1.0_f32, egui::Color32::LIGHT_GRAY), egui::StrokeKind::Outside);
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("🖨 Print").clicked() {
+                        print = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if print && let Some(dialog) = self.print_dialog.take() {
+            crash::record_action("Print image");
+            let sink = self.progress.sink();
+            std::thread::spawn(move || {
+                let reporter = sink.start(OperationKind::Export, format!("Print {}", dialog.name));
+                let page = render_page(&dialog.image, &dialog.layout, PRINT_DPI);
+                match print_page(&page, PRINT_DPI, &dialog.layout, dialog.printer.as_deref()) {
+                    Ok(()) => reporter.finish(format!(
+                        "Sent {} to {}",
+                        dialog.name,
+                        dialog.printer.as_deref().unwrap_or("the default printer")
+                    )),
+                    Err(e) => reporter.fail(format!("Error printing: {}", e)),
+                }
+            });
+        }
+        if close {
+            self.print_dialog = None;
+        }
+    }
+
+    fn handle_slow_image_dialog(&mut self, ctx: &egui::Context) {
+        if !self.show_slow_image_dialog {
+            return;
+        }
+
+        let mut load_anyway = false;
+        let mut cancel = false;
+        let mut skip = false;
+        
+        egui::Window::new("Slow Image Warning")
+            .open(&mut self.show_slow_image_dialog)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.horizontal(|ui| {
+                        self.icon_renderer.icon_label(ui, ctx, "alert-triangle", 16.0, egui::Color32::YELLOW);
+                        ui.label("Performance Warning");
+                    });
+                    ui.separator();
+                    
+                    if let Some(ref path) = self.pending_slow_image_path {
+                        let filename = path.file_name()
+                            .map(|f| f.to_string_lossy().to_string())
+                            .unwrap_or_else(|| path.to_string_lossy().to_string());
+                        let display_filename = self.settings.truncate_filename(&filename);
+                        ui.label(format!("Image: {}", display_filename));
+                    }
+                    
+                    ui.label(format!(
+                        "Estimated load time: {:.1} seconds", 
+                        self.pending_slow_image_estimated_time / 1000.0
+                    ));
+                    ui.label(format!(
+                        "Threshold: {:.1} seconds", 
+                        self.benchmark_threshold_ms / 1000.0
+                    ));
+                    
+                    ui.separator();
+                    ui.label("This image may take longer to load than expected.");
+                    ui.label("Do you want to continue?");
+                    
+                    ui.separator();
+                    silence_choice(ui, &mut self.prompt_silence);
+                    ui.separator();
+                    
+                    ui.horizontal(|ui| {
+                        if ui.button("Load Anyway").clicked() {
+                            load_anyway = true;
+                        }
+                        if ui.button("Skip and Continue").on_hover_text("Open the next local file instead").clicked() {
+                            skip = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            cancel = true;
+                        }
+                    });
+                });
+            });
+        
+        if skip && let Some(path) = self.pending_slow_image_path.clone() {
+            self.skip_to_next_local(ctx, &path);
+            return;
+        }
+        if cancel {
+            self.show_slow_image_dialog = false;
+        }
+        if !self.show_slow_image_dialog {
+            self.pending_slow_image_path = None;
+            self.pending_slow_image_estimated_time = 0.0;
+            self.prompt_silence = Silence::None;
+        } else if load_anyway {
+            self.show_slow_image_dialog = false;
+            if let Some(path) = self.pending_slow_image_path.take() {
+                self.confirm_prompt(Prompt::SlowImage, &path);
+                // Find the index and load the image
+                if let Some(index) = self.file_infos.iter().position(|f| f.path == path) {
+                    self.selected_image_index = Some(index);
+                    self.force_load_selected_image(ctx);
+                }
+            }
+            self.pending_slow_image_estimated_time = 0.0;
+        }
+    }
+
+    fn handle_download_dialog(&mut self, ctx: &egui::Context) {
+        if !self.show_download_dialog {
+            return;
+        }
+
+        let mut download_anyway = false;
+        let mut cancel = false;
+        let mut skip = false;
+        let mut save_policies = false;
+        
+        egui::Window::new("File Download Warning")
+            .open(&mut self.show_download_dialog)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.horizontal(|ui| {
+                        self.icon_renderer.icon_label(ui, ctx, "cloud", 16.0, egui::Color32::LIGHT_BLUE);
+                        self.icon_renderer.icon_label(ui, ctx, "download", 16.0, egui::Color32::LIGHT_BLUE);
+                        ui.label("File Download Required");
+                    });
+                    ui.separator();
+                    
+                    if let Some(ref file_info) = self.pending_download_file {
+                        let filename = file_info.path.file_name()
+                            .map(|f| f.to_string_lossy().to_string())
+                            .unwrap_or_else(|| file_info.path.to_string_lossy().to_string());
+                        let display_filename = self.settings.truncate_filename(&filename);
+                        ui.label(format!("File: {}", display_filename));
+                        ui.label(format!("Status: {}", file_info.locality_status.description()));
+                        if let Some(provider) = &file_info.provider {
+                            ui.label(format!("Provider: {} {}", provider.icon(), provider.name()));
+                        }
+                        
+                        if let Some(size) = file_info.estimated_download_size {
+                            ui.label(format!("Download size: {:.1} MB{}", size as f64 / (1024.0 * 1024.0), visible_time_suffix(&self.bandwidth, self.settings.link_speed_mbps, &self.performance_profile, &file_info.path, size)));
+                        }
+                    }
+                    
+                    ui.separator();
+                    ui.label("This file is stored remotely and needs to be downloaded");
+                    ui.label("before it can be viewed. This may take some time depending");
+                    ui.label("on your internet connection.");
+                    if self.settings.metered_connection.is_metered(self.network_cost.detected()) {
+                        ui.colored_label(egui::Color32::YELLOW, "You're on a metered connection, so downloads always ask first.");
+                    }
+                    
+                    ui.separator();
+                    silence_choice(ui, &mut self.prompt_silence);
+                    save_policies = download_limit_editor(ui, &mut self.prompt_policies.download_under_mb);
+                    ui.separator();
+                    
+                    ui.vertical_centered(|ui| {
+                        ui.horizontal(|ui| {
+                            if ui.button("Download and Open").clicked() {
+                                download_anyway = true;
+                            }
+                            if ui.button("Skip and Continue").on_hover_text("Open the next local file instead").clicked() {
+                                skip = true;
+                            }
+                            if ui.button("Cancel").clicked() {
+                                cancel = true;
+                            }
+                        });
+                        #[cfg(feature = "graph")]
+                        if self.graph.session.is_signed_in()
+                            && let Some(ref file_info) = self.pending_download_file
+                            && let Some(drive_path) = drive_path_for_local_file(&file_info.path)
+                            && ui.button("Show Cloud Thumbnail (no download)").clicked() {
+                            self.graph.session.fetch_local_thumbnail(&file_info.path, &drive_path, ThumbnailSize::for_pixels(self.settings.thumbnail_size));
+                            self.status_text = "Fetching cloud thumbnail...".to_string();
+                        }
+                    });
+                });
+            });
+        
+        if save_policies {
+            self.save_prompt_policies();
+        }
+        if skip && let Some(path) = self.pending_download_file.as_ref().map(|f| f.path.clone()) {
+            self.skip_to_next_local(ctx, &path);
+            return;
+        }
+        if cancel {
+            self.show_download_dialog = false;
+        }
+        if !self.show_download_dialog {
+            self.pending_download_file = None;
+            self.prompt_silence = Silence::None;
+        } else if download_anyway {
+            self.show_download_dialog = false;
+            if let Some(file_info) = self.pending_download_file.take() {
+                self.confirm_prompt(Prompt::Download, &file_info.path);
+                if let Some(index) = self.file_infos.iter().position(|f| f.path == file_info.path) {
+                    self.selected_image_index = Some(index);
+                    self.download_then_open(ctx, file_info);
+                }
+            }
+        }
+    }
+
+    fn handle_bulk_download_dialog(&mut self, ctx: &egui::Context) {
+        if !self.show_bulk_download_dialog {
+            return;
+        }
+
+        let (on_demand_count, total_bytes) = summarize_on_demand(&self.file_infos);
+        let mut start_download = false;
+
+        egui::Window::new("Download On-Demand Images")
+            .open(&mut self.show_bulk_download_dialog)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.horizontal(|ui| {
+                        self.icon_renderer.icon_label(ui, ctx, "cloud", 16.0, egui::Color32::LIGHT_BLUE);
+                        self.icon_renderer.icon_label(ui, ctx, "download", 16.0, egui::Color32::LIGHT_BLUE);
+                        ui.label("Bulk Download");
+                    });
+                    ui.separator();
+
+                    if on_demand_count == 0 {
+                        ui.label("All images in this folder are already available locally.");
+                        return;
+                    }
+
+                    ui.label(format!("On-demand images: {}", on_demand_count));
+                    ui.label(format!("Total download size: {:.1} MB{}", total_bytes as f64 / (1024.0 * 1024.0), download_time_suffix(&self.bandwidth, self.settings.link_speed_mbps, total_bytes)));
+
+                    ui.separator();
+                    ui.label("Files are downloaded one at a time in the background.");
+                    ui.label("You can keep browsing while the queue runs.");
+
+                    ui.separator();
+
+                    ui.vertical_centered(|ui| {
+                        if ui.button("Download All").clicked() {
+                            start_download = true;
+                        }
+                    });
+                });
+            });
+
+        if start_download {
+            self.show_bulk_download_dialog = false;
+            let paths: Vec<PathBuf> = self.file_infos.iter()
+                .filter(|f| f.will_trigger_download())
+                .map(|f| f.path.clone())
+                .collect();
+            self.download_queue.enqueue_all(paths);
+            self.status_text = format!("Queued {} on-demand images for download", on_demand_count);
+        }
+    }
+
+    /// Apply results from the background download queue
+    fn process_download_events(&mut self, ctx: &egui::Context) {
+        self.recover_downloads_after_sleep();
+        for event in self.download_queue.poll_events() {
+            match event {
+                DownloadEvent::Started(path) => {
+                    let filename = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                    let (completed, total) = self.download_queue.progress();
+                    self.status_text = format!(
+                        "Downloading {} ({} of {}, {} waiting)",
+                        self.settings.truncate_filename(&filename),
+                        completed + 1,
+                        total,
+                        self.download_queue.pending_count(),
+                    );
+                }
+                DownloadEvent::Completed { path, bytes, elapsed } => {
+                    self.bandwidth.record(bytes, elapsed);
+                    self.update_file_locality_status(&path);
+                    if self.selection_summary.on_demand > 0 {
+                        self.update_selection_summary();
+                    }
+                    if self.open_after_download.take_if(|pending| *pending == path).is_some() {
+                        self.force_load_selected_image(ctx);
+                    }
+                    if let Some((editor, _)) = self.pending_edit.take_if(|(_, pending)| *pending == path) {
+                        self.open_in_editor(editor, path);
+                    }
+                }
+                DownloadEvent::Woke { slept } if self.download_queue.is_busy() || self.wake_recovery.is_some() => {
+                    self.download_queue.pause();
+                    self.wake_recovery = Some(Instant::now() + WAKE_GRACE);
+                    self.status_text = format!("Downloads paused after the computer slept for {}; resuming shortly", format_estimate(slept));
+                    self.repaint.request_after(RepaintReason::Download, WAKE_GRACE);
+                }
+                DownloadEvent::Woke { .. } => {}
+                // Most likely the network dropping while the machine slept
+                DownloadEvent::Failed { path, .. } if self.wake_recovery.is_some() => self.download_queue.retry(path),
+                DownloadEvent::Failed { path, error } => {
+                    let filename = path.file_name()
+                        .map(|f| f.to_string_lossy().to_string())
+                        .unwrap_or_else(|| path.to_string_lossy().to_string());
+                    self.status_text = format!("Error downloading {}: {}", self.settings.truncate_filename(&filename), error);
+                    self.pending_edit.take_if(|(_, pending)| *pending == path);
+                    self.open_after_download.take_if(|pending| *pending == path);
+                }
+            }
+        }
+
+        if self.download_queue.is_busy() {
+            // Keep polling while the worker is running
+            self.repaint.request_after(RepaintReason::Download, std::time::Duration::from_millis(200));
+        }
+    }
+
+    /// Once the grace period after the machine woke has passed, recheck
+    /// which files are local and resume the downloads paused on waking
+    fn recover_downloads_after_sleep(&mut self) {
+        match self.wake_recovery {
+            Some(resume_at) if Instant::now() >= resume_at => {
+                self.wake_recovery = None;
+                self.refresh_all_file_locality_status();
+                self.download_queue.resume();
+                self.status_text = "Downloads resumed after the computer woke".to_string();
+            }
+            Some(resume_at) => self.repaint.request_after(RepaintReason::Download, resume_at - Instant::now()),
+            None => {}
+        }
+    }
+
+    /// Gate idle-only jobs on user activity and report finished jobs
+    fn process_background_jobs(&mut self, ctx: &egui::Context) {
+        self.idle_detector.set_idle_after(std::time::Duration::from_secs(self.settings.idle_delay_secs as u64));
+        let idle = self.idle_detector.observe(ctx);
+        self.job_engine.set_user_idle(idle);
+
+        for event in self.job_engine.poll_events() {
+            match event {
+                // Scans that wait for the user to go idle start without taking over the status bar
+                JobEvent::Started { id, name } => {
+                    if self.integrity_scan != Some(id) && self.sharpness_scan != Some(id) {
+                        self.status_text = format!("{}…", name);
+                    }
+                }
+                JobEvent::Finished { id, name, result } => {
+                    if self.integrity_scan == Some(id) {
+                        self.integrity_scan = None;
+                    }
+                    if self.sharpness_scan == Some(id) {
+                        self.sharpness_scan = None;
+                    }
+                    self.status_text = match result {
+                        Ok(summary) => format!("{}: {}", name, summary),
+                        Err(error) => format!("Error in {}: {}", name, error),
+                    };
+                }
+            }
+        }
+
+        if self.job_engine.is_busy() {
+            // egui only repaints on input, so wake up when the idle period ends
+            let wait = self.idle_detector.time_until_idle(std::time::Instant::now());
+            self.repaint.request_after(RepaintReason::Background, wait.max(std::time::Duration::from_millis(250)));
+        }
+    }
+
+    /// Track running operations and report finished exports
+    fn process_progress_events(&mut self) {
+        let mut exports: Vec<u64> = self.progress.active()
+            .iter()
+            .filter(|op| op.kind == OperationKind::Export)
+            .map(|op| op.id)
+            .collect();
+        for event in self.progress.poll() {
+            match event {
+                ProgressEvent::Started { id, kind: OperationKind::Export, .. } => exports.push(id),
+                ProgressEvent::Finished { id, summary } if exports.contains(&id) => self.status_text = summary,
+                ProgressEvent::Failed { id, error } if exports.contains(&id) => self.status_text = error,
+                _ => {}
+            }
+        }
+
+        if self.progress.is_busy() {
+            self.repaint.request_after(RepaintReason::Background, std::time::Duration::from_millis(100));
+        }
+    }
+
+    /// Decode every local image in the background to find corrupt files
+    pub(crate) fn queue_integrity_scan(&mut self) {
+        let paths: Vec<PathBuf> = self.file_infos
+            .iter()
+            .filter(|f| !f.will_trigger_download() && !video::is_video(&f.path))
+            .map(|f| f.path.clone())
+            .collect();
+        let settings = self.settings.clone();
+        let decode_failures = Arc::clone(&self.decode_failures);
+
+        // A new scan replaces one that hasn't finished
+        if let Some(previous) = self.integrity_scan.take() {
+            self.job_engine.cancel(previous);
+        }
+        self.integrity_scan = Some(self.job_engine.submit("Integrity scan", JobPriority::Idle, move |job| {
+            let mut failures = Vec::new();
+            for (done, path) in paths.iter().enumerate() {
+                job.checkpoint()?;
+                job.report_progress(done as u64, Some(paths.len() as u64));
+                if decode(path, &settings, false).is_err() {
+                    failures.push(path.file_name().unwrap_or_default().to_string_lossy().to_string());
+                    if let Ok(mut known) = decode_failures.lock() {
+                        known.insert(path.clone());
+                    }
+                }
+            }
+            Ok(if failures.is_empty() {
+                format!("all {} images decoded successfully", paths.len())
+            } else {
+                format!("{} of {} images failed to decode ({})", failures.len(), paths.len(), failures.join(", "))
+            })
+        }));
+        self.status_text = format!("Integrity scan will run after {} s without input", self.settings.idle_delay_secs);
+    }
+
+    #[cfg(feature = "graph")]
+    fn render_graph_browser_window(&mut self, ctx: &egui::Context) {
+        if !self.graph.show_window {
+            return;
+        }
+
+        let mut show_window = true;
+        let mut navigate_to: Option<String> = None;
+
+        egui::Window::new("OneDrive Browser")
+            .open(&mut show_window)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                if !self.graph.session.is_signed_in() {
+                    ui.label("Sign in with an Azure app registration that allows public client flows.");
+                    ui.horizontal(|ui| {
+                        ui.label("Client ID:");
+                        ui.text_edit_singleline(&mut self.graph.client_id);
+                    });
+                    let can_sign_in = !self.graph.client_id.trim().is_empty() && !self.graph.session.is_busy();
+                    if ui.add_enabled(can_sign_in, egui::Button::new("Sign In")).clicked() {
+                        self.graph.error = None;
+                        self.graph.session.sign_in(self.graph.client_id.trim());
+                    }
+                    if let Some(ref prompt) = self.graph.login_prompt {
+                        ui.separator();
+                        ui.label(&prompt.message);
+                        ui.horizontal(|ui| {
+                            ui.label("Code:");
+                            ui.code(&prompt.user_code);
+                        });
+                        ui.hyperlink(&prompt.verification_uri);
+                        ui.spinner();
+                    }
+                } else {
+                    ui.horizontal(|ui| {
+                        if ui.button("⬆ Up").clicked() {
+                            let parent = self.graph.current_folder
+                                .rsplit_once('/')
+                                .map(|(parent, _)| parent.to_string())
+                                .unwrap_or_default();
+                            navigate_to = Some(parent);
+                        }
+                        ui.label("Folder:");
+                        let response = ui.text_edit_singleline(&mut self.graph.current_folder);
+                        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            navigate_to = Some(self.graph.current_folder.clone());
+                        }
+                        if ui.button("Go").clicked() {
+                            navigate_to = Some(self.graph.current_folder.clone());
+                        }
+                        if ui.button("Sign Out").clicked() {
+                            self.graph.session.sign_out();
+                            self.graph.items.clear();
+                            self.graph.thumbnail = None;
+                        }
+                    });
+                    if self.graph.session.is_busy() {
+                        ui.spinner();
+                    }
+                    ui.separator();
+
+                    egui::ScrollArea::vertical()
+                        .max_height(240.0)
+                        .show(ui, |ui| {
+                            for item in &self.graph.items {
+                                if item.is_folder {
+                                    if ui.selectable_label(false, format!("📁 {}", item.name)).clicked() {
+                                        let trimmed = self.graph.current_folder.trim_matches('/');
+                                        navigate_to = Some(if trimmed.is_empty() {
+                                            item.name.clone()
+                                        } else {
+                                            format!("{}/{}", trimmed, item.name)
+                                        });
+                                    }
+                                } else if item.is_image() {
+                                    let label = format!("{} ({:.1} MB)", item.name, item.size as f64 / (1024.0 * 1024.0));
+                                    if ui.selectable_label(false, label).clicked() {
+                                        let cached = self.thumbnail_cache.lock().unwrap().get(&item.id).cloned();
+                                        match cached {
+                                            Some(texture) => self.graph.thumbnail = Some(texture),
+                                            None => self.graph.session.fetch_thumbnail_by_id(&item.id, &item.id, ThumbnailSize::for_pixels(self.settings.thumbnail_size)),
+                                        }
+                                    }
+                                }
+                            }
+                        });
+
+                    if let Some(ref texture) = self.graph.thumbnail {
+                        ui.separator();
+                        let size = texture.size_vec2();
+                        let scale = (380.0 / size.x).min(1.0);
+                        ui.image((texture.id(), size * scale));
+                    }
+                }
+
+                if let Some(ref error) = self.graph.error {
+                    ui.colored_label(egui::Color32::from_rgb(255, 120, 120), error);
                 }
-            } else if !self.file_infos.is_empty() {
-                self.selected_image_index = Some(self.file_infos.len() - 1);
-                changed = true;
-            }
+            });
+
+        self.graph.show_window = show_window;
+
+        if let Some(folder) = navigate_to {
+            self.graph.current_folder = folder;
+            self.graph.session.list_folder(&self.graph.current_folder);
         }
+    }
 
-        if ctx.input(|i| i.key_pressed(egui::Key::ArrowDown)) {
-            if let Some(selected_index) = self.selected_image_index {
-                if selected_index < self.file_infos.len() - 1 {
-                    self.selected_image_index = Some(selected_index + 1);
-                    changed = true;
+    /// Apply results from background Graph requests
+    #[cfg(feature = "graph")]
+    fn process_graph_results(&mut self, ctx: &egui::Context) {
+        for result in self.graph.session.poll() {
+            match result {
+                GraphTaskResult::LoginPrompt(prompt) => self.graph.login_prompt = Some(prompt),
+                GraphTaskResult::LoginFinished(result) => {
+                    self.graph.login_prompt = None;
+                    match result {
+                        Ok(()) => self.graph.session.list_folder(&self.graph.current_folder),
+                        Err(e) => self.graph.error = Some(format!("Sign-in failed: {}", e)),
+                    }
+                }
+                GraphTaskResult::Listing { folder, result } => match result {
+                    Ok(items) if folder == self.graph.current_folder => {
+                        self.graph.items = items;
+                        self.graph.error = None;
+                    }
+                    Ok(_) => {} // Stale listing for a folder we already left
+                    Err(e) => self.graph.error = Some(e),
+                },
+                GraphTaskResult::Thumbnail { key, result } => {
+                    match result.and_then(|bytes| graph_thumbnail_texture(ctx, &key, &bytes)) {
+                        Ok(texture) => {
+                            let [width, height] = texture.size();
+                            self.thumbnail_cache.lock().unwrap().insert(key, texture.clone(), width * height * 4);
+                            self.graph.thumbnail = Some(texture);
+                        }
+                        Err(e) => self.graph.error = Some(e),
+                    }
+                }
+                GraphTaskResult::LocalThumbnail { path, result } => {
+                    // Only while the download prompt is still about that file
+                    if self.pending_download_file.as_ref().is_none_or(|file| file.path != path) {
+                        continue;
+                    }
+                    let key = path.to_string_lossy();
+                    match result.and_then(|bytes| graph_thumbnail_texture(ctx, &key, &bytes)) {
+                        Ok(texture) => {
+                            self.image_texture = Some(texture);
+                            self.online_placeholder = None;
+                            self.current_image = None;
+                            self.proof_texture = None;
+                            self.proof_render = None;
+                            self.selection = None;
+                            self.show_download_dialog = false;
+                            self.status_text = "Showing cloud thumbnail (file not downloaded)".to_string();
+                        }
+                        Err(e) => self.status_text = format!("Error fetching cloud thumbnail: {}", e),
+                    }
                 }
-            } else if !self.file_infos.is_empty() {
-                self.selected_image_index = Some(0);
-                changed = true;
             }
         }
 
-        if changed {
-            self.load_selected_image(ctx);
+        if self.graph.session.is_busy() {
+            self.repaint.request_after(RepaintReason::Download, std::time::Duration::from_millis(250));
         }
     }
 
-    fn handle_benchmark_trigger(&mut self, ctx: &egui::Context) {
-        // Handle benchmark trigger
-        if self.run_benchmark_trigger && !self.benchmark_in_progress {
-            self.run_benchmark_trigger = false;
-            self.run_benchmark(ctx);
-        }
-        
-        // Auto-benchmark on startup if enabled
-        if self.auto_benchmark_on_startup && !self.benchmark_in_progress && self.performance_profile.benchmark_results.is_empty() {
-            self.auto_benchmark_on_startup = false;
-            self.run_benchmark(ctx);
-        }
+    fn on_metered_connection(&self) -> bool {
+        self.settings.metered_connection.is_metered(self.network_cost.detected())
     }
 
-    fn handle_dialogs(&mut self, ctx: &egui::Context) {
-        self.handle_slow_image_dialog(ctx);
-        self.handle_download_dialog(ctx);
+    /// Keep the warning policies for later sessions
+    fn save_prompt_policies(&mut self) {
+        let mut preferences = Preferences::load();
+        self.prompt_policies.save_to(&mut preferences);
+        if let Err(e) = preferences.save() {
+            self.status_text = e;
+        }
     }
 
-    fn handle_slow_image_dialog(&mut self, ctx: &egui::Context) {
-        if !self.show_slow_image_dialog {
-            return;
+    /// Keep the recent recolor targets and palettes for later sessions
+    fn save_svg_colors(&mut self) {
+        let mut preferences = Preferences::load();
+        self.settings.save_svg_colors(&mut preferences);
+        if let Err(e) = preferences.save() {
+            self.status_text = e;
         }
+    }
 
-        let mut load_anyway = false;
-        
-        egui::Window::new("Slow Image Warning")
-            .open(&mut self.show_slow_image_dialog)
-            .collapsible(false)
-            .resizable(false)
-            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
-            .show(ctx, |ui| {
-                ui.vertical_centered(|ui| {
-                    ui.horizontal(|ui| {
-                        self.icon_renderer.icon_label(ui, ctx, "alert-triangle", 16.0, egui::Color32::YELLOW);
-                        ui.label("Performance Warning");
-                    });
-                    ui.separator();
-                    
-                    if let Some(ref path) = self.pending_slow_image_path {
-                        let filename = path.file_name()
-                            .map(|f| f.to_string_lossy().to_string())
-                            .unwrap_or_else(|| path.to_string_lossy().to_string());
-                        let display_filename = self.settings.truncate_filename(&filename);
-                        ui.label(format!("Image: {}", display_filename));
-                    }
-                    
-                    ui.label(format!(
-                        "Estimated load time: {:.1} seconds", 
-                        self.pending_slow_image_estimated_time / 1000.0
-                    ));
-                    ui.label(format!(
-                        "Threshold: {:.1} seconds", 
-                        self.benchmark_threshold_ms / 1000.0
-                    ));
-                    
-                    ui.separator();
-                    ui.label("This image may take longer to load than expected.");
-                    ui.label("Do you want to continue?");
-                    
-                    ui.separator();
-                    
-                    ui.vertical_centered(|ui| {
-                        if ui.button("Load Anyway").clicked() {
-                            load_anyway = true;
-                        }
-                    });
-                });
-            });
-        
-        if !self.show_slow_image_dialog {
-            self.pending_slow_image_path = None;
-            self.pending_slow_image_estimated_time = 0.0;
-        } else if load_anyway {
-            self.show_slow_image_dialog = false;
-            if let Some(path) = self.pending_slow_image_path.take() {
-                // Find the index and load the image
-                if let Some(index) = self.file_infos.iter().position(|f| f.path == path) {
-                    self.selected_image_index = Some(index);
-                    self.force_load_selected_image(ctx);
-                }
-            }
-            self.pending_slow_image_estimated_time = 0.0;
+    /// Silence `prompt` as chosen in its dialog, which was confirmed for `path`
+    fn confirm_prompt(&mut self, prompt: Prompt, path: &std::path::Path) {
+        if self.prompt_policies.silence(prompt, std::mem::take(&mut self.prompt_silence), path) {
+            self.save_prompt_policies();
         }
     }
 
-    fn handle_download_dialog(&mut self, ctx: &egui::Context) {
-        if !self.show_download_dialog {
+    fn render_toast(&mut self, ctx: &egui::Context) {
+        let Some(toast) = &self.toast else {
             return;
+        };
+        match toast.opacity(Instant::now()) {
+            Some(opacity) => {
+                toast.show(ctx, opacity);
+                self.repaint.request_after(RepaintReason::Animation, std::time::Duration::from_millis(if opacity < 1.0 { 16 } else { 250 }));
+            }
+            None => self.toast = None,
         }
+    }
 
-        let mut download_anyway = false;
-        
-        egui::Window::new("File Download Warning")
-            .open(&mut self.show_download_dialog)
-            .collapsible(false)
-            .resizable(false)
-            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
-            .show(ctx, |ui| {
-                ui.vertical_centered(|ui| {
-                    ui.horizontal(|ui| {
-                        self.icon_renderer.icon_label(ui, ctx, "cloud", 16.0, egui::Color32::LIGHT_BLUE);
-                        self.icon_renderer.icon_label(ui, ctx, "download", 16.0, egui::Color32::LIGHT_BLUE);
-                        ui.label("File Download Required");
-                    });
-                    ui.separator();
-                    
-                    if let Some(ref file_info) = self.pending_download_file {
-                        let filename = file_info.path.file_name()
-                            .map(|f| f.to_string_lossy().to_string())
-                            .unwrap_or_else(|| file_info.path.to_string_lossy().to_string());
-                        let display_filename = self.settings.truncate_filename(&filename);
-                        ui.label(format!("File: {}", display_filename));
-                        ui.label(format!("Status: {}", file_info.locality_status.description()));
-                        
-                        if let Some(size) = file_info.estimated_download_size {
-                            ui.label(format!("Download size: {:.1} MB", size as f64 / (1024.0 * 1024.0)));
-                        }
-                    }
-                    
-                    ui.separator();
-                    ui.label("This file is stored remotely and needs to be downloaded");
-                    ui.label("before it can be viewed. This may take some time depending");
-                    ui.label("on your internet connection.");
-                    
-                    ui.separator();
-                    
-                    ui.vertical_centered(|ui| {
-                        if ui.button("Download and Open").clicked() {
-                            download_anyway = true;
-                        }
-                    });
-                });
-            });
-        
-        if !self.show_download_dialog {
-            self.pending_download_file = None;
-        } else if download_anyway {
-            self.show_download_dialog = false;
-            if let Some(file_info) = self.pending_download_file.take() {
-                // Find the index and load the image (this will trigger download)
-                if let Some(index) = self.file_infos.iter().position(|f| f.path == file_info.path) {
-                    self.selected_image_index = Some(index);
-                    self.force_load_selected_image(ctx);
-                }
+    /// Close the open warning without loading its file
+    fn dismiss_prompts(&mut self) {
+        self.show_download_dialog = false;
+        self.pending_download_file = None;
+        self.show_slow_image_dialog = false;
+        self.pending_slow_image_path = None;
+        self.pending_slow_image_estimated_time = 0.0;
+        self.prompt_silence = Silence::None;
+    }
+
+    /// Select and open the first file after `path` that needs no download
+    fn skip_to_next_local(&mut self, ctx: &egui::Context, path: &std::path::Path) {
+        self.dismiss_prompts();
+        let visible = self.visible_indices();
+        let Some(position) = visible.iter().position(|&i| self.file_infos[i].path == path) else {
+            return;
+        };
+        let next = next_matching(position, visible.len(), true, self.settings.wrap_navigation, |p| !self.file_infos[visible[p]].will_trigger_download());
+        match next {
+            Some(next) => {
+                let index = visible[next];
+                self.selected_image_index = Some(index);
+                self.file_selection.select_only(index);
+                self.update_selection_summary();
+                self.load_selected_image(ctx);
             }
+            None => self.status_text = "No more local files in this folder".to_string(),
         }
     }
 
-    pub fn load_selected_image(&mut self, ctx: &egui::Context) {
-        if let Some(index) = self.selected_image_index {
-            if let Some(file_info) = self.file_infos.get(index) {
-                // Check if this is a file that will trigger download
-                if file_info.will_trigger_download() {
+    pub(crate) fn load_selected_image(&mut self, ctx: &egui::Context) {
+        // A new selection replaces whatever the open warning was asking about
+        self.dismiss_prompts();
+        self.online_placeholder = None;
+        self.video_placeholder = None;
+        self.open_after_download = None;
+        self.pending_preview = None;
+        let estimated_time = match self.selected_image_index.and_then(|index| self.file_infos.get_mut(index)) {
+            Some(file_info) if !self.performance_profile.benchmark_results.is_empty() => cached_render_time(file_info, &self.performance_profile),
+            _ => None,
+        };
+        if let Some(index) = self.selected_image_index
+            && let Some(file_info) = self.file_infos.get(index) {
+            // Check if this is a file that will trigger download
+            if file_info.will_trigger_download() {
+                if self.on_metered_connection()
+                    || self.prompt_policies.should_ask(Prompt::Download, &file_info.path, file_info.estimated_download_size) {
                     // Show download warning dialog
+                    let file_info = file_info.clone();
                     self.pending_download_file = Some(file_info.clone());
                     self.show_download_dialog = true;
+                    self.show_online_placeholder(ctx, file_info);
                     return; // Don't load immediately, wait for user confirmation
                 }
-                
-                // Check if we should prompt user for slow images (only if benchmark data is available)
-                if !self.performance_profile.benchmark_results.is_empty() {
-                    if let Some(estimated_time) = estimate_image_render_time(&file_info.path, &self.performance_profile) {
-                        if estimated_time > self.benchmark_threshold_ms {
-                            // Show slow image warning dialog
-                            self.pending_slow_image_path = Some(file_info.path.clone());
-                            self.pending_slow_image_estimated_time = estimated_time;
-                            self.show_slow_image_dialog = true;
-                            return; // Don't load immediately, wait for user confirmation
-                        }
-                    }
-                }
-                
-                // If we get here, either no OneDrive/benchmark issues, or user confirmed
-                self.force_load_selected_image(ctx);
+                let file_info = file_info.clone();
+                self.download_then_open(ctx, file_info);
+                return;
+            } else if let Some(estimated_time) = estimated_time
+                && estimated_time > self.benchmark_threshold_ms
+                && self.prompt_policies.should_ask(Prompt::SlowImage, &file_info.path, None) {
+                // Prompt for slow images (only if benchmark data is available)
+                // Show slow image warning dialog
+                self.pending_slow_image_path = Some(file_info.path.clone());
+                self.pending_slow_image_estimated_time = estimated_time;
+                self.show_slow_image_dialog = true;
+                return; // Don't load immediately, wait for user confirmation
             }
+            
+            // If we get here, either no OneDrive/benchmark issues, or user confirmed
+            self.force_load_selected_image(ctx);
         }
     }
 
-    pub fn force_load_selected_image(&mut self, ctx: &egui::Context) {
-        if let Some(index) = self.selected_image_index {
-            if let Some(file_info) = self.file_infos.get(index) {
-                let path = file_info.path.clone(); // Clone the path to avoid borrowing issues
-                
-                // Check file size first (but allow on-demand files when forcing)
-                if let Some(skip_message) = should_skip_large_file(&path, &self.settings, true) {
-                    self.status_text = skip_message;
+    pub(crate) fn force_load_selected_image(&mut self, ctx: &egui::Context) {
+        self.svg_load = None;
+        if let Some(index) = self.selected_image_index
+            && let Some(file_info) = self.file_infos.get(index) {
+            let path = file_info.path.clone(); // Clone the path to avoid borrowing issues
+            self.deep_zoom = None;
+            self.compare = None;
+            self.compare_load = None;
+            crash::record_action(format!("Load image {}", path.display()));
+            
+            // Check file size first (but allow on-demand files when forcing)
+            if let Some(skip_message) = should_skip_large_file(&path, &self.settings, true) {
+                self.status_text = skip_message;
+                self.last_error_report = None;
+                self.image_texture = None;
+                self.current_image = None;
+                self.svg_source = None;
+                self.selection = None;
+                return;
+            }
+
+            let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+            let recolor_active = self.view_states.recolor_active(&path, self.settings.svg_recolor_enabled);
+            
+            let settings = self.svg_settings_for(&path);
+            if extension == "svg" {
+                // Kept even if decoding fails, since the source helps diagnose why
+                self.svg_source = read_svg_source(&path).ok().map(|original| SvgSource {
+                    recolored: recolor_active.then(|| recolor_svg(&original, &settings)),
+                    original,
+                });
+            } else {
+                self.svg_source = None;
+            }
+
+            let cache_key = self.image_cache_key(&path);
+            let cached = self.decoded_cache.lock().unwrap().get(&cache_key).cloned();
+            let result = match cached {
+                Some(color_image) => Ok(color_image),
+                // Parsing can take seconds before it times out, so it runs on the job engine
+                None if extension == "svg" => {
                     self.image_texture = None;
+                    self.current_image = None;
+                    self.selection = None;
+                    let cache = Arc::clone(&self.decoded_cache);
+                    let (sender, receiver) = std::sync::mpsc::channel();
+                    self.svg_load = Some((path.clone(), cache_key.clone(), receiver));
+                    let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                    let capture_backtrace = self.settings.capture_error_reports;
+                    self.job_engine.submit(format!("Render {}", name), JobPriority::Interactive, move |_| {
+                        let decoded = decode_svg_image(&path, &settings, true)
+                            .map_err(|message| DecodeError::new(message, capture_backtrace))
+                            .map(|color_image| {
+                                let color_image = Arc::new(color_image);
+                                let bytes = color_image.pixels.len() * 4;
+                                cache.lock().unwrap().insert(cache_key, Arc::clone(&color_image), bytes);
+                                color_image
+                            });
+                        let summary = decoded.as_ref().map(|_| "Rendered".to_string()).map_err(|e| e.message.clone());
+                        let _ = sender.send(decoded);
+                        summary
+                    });
+                    self.status_text = format!("Rendering {}…", self.settings.truncate_filename(&name));
                     return;
                 }
+                None => decode_raster_image(&path, &self.settings, true)
+                    .map_err(|message| DecodeError::new(message, self.settings.capture_error_reports))
+                    .map(|color_image| {
+                        let color_image = Arc::new(color_image);
+                        let bytes = color_image.pixels.len() * 4;
+                        self.decoded_cache.lock().unwrap().insert(cache_key.clone(), Arc::clone(&color_image), bytes);
+                        color_image
+                    }),
+            };
+            self.show_loaded_image(ctx, path, &cache_key, result);
+        }
+    }
 
-                let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-                
-                let result = if extension == "svg" {
-                    load_svg_image(&path, &self.settings, ctx, true)
-                } else {
-                    load_raster_image(&path, &self.settings, ctx, true)
-                };
+    /// Show an SVG once the job engine has rendered it, if it is still selected
+    fn process_svg_load(&mut self, ctx: &egui::Context) {
+        let Some((_, _, receiver)) = &self.svg_load else {
+            return;
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(std::sync::mpsc::TryRecvError::Empty) => return,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => Err(DecodeError::new("Rendering was cancelled".to_string(), false)),
+        };
+        let Some((path, cache_key, _)) = self.svg_load.take() else {
+            return;
+        };
+        let selected = self.selected_image_index.and_then(|index| self.file_infos.get(index)).is_some_and(|file| file.path == path);
+        if selected {
+            self.show_loaded_image(ctx, path, &cache_key, result);
+        }
+    }
 
-                match result {
-                    Ok(texture) => {
-                        self.image_texture = Some(texture);
-                        let recolor_suffix = if extension == "svg" && self.settings.svg_recolor_enabled {
-                            " (recolored)"
-                        } else {
-                            ""
-                        };
-                        let filename = path.file_name()
-                            .map(|f| f.to_string_lossy().to_string())
-                            .unwrap_or_else(|| path.to_string_lossy().to_string());
-                        let display_filename = self.settings.truncate_filename(&filename);
-                        self.status_text = format!("Loaded: {}{}", display_filename, recolor_suffix);
-                        
-                        // Update file locality status after successful load (in case it was downloaded)
-                        self.update_file_locality_status(&path);
+    /// Show a decoded image with its metadata and saved edits, or why it couldn't be decoded
+    fn show_loaded_image(&mut self, ctx: &egui::Context, path: PathBuf, cache_key: &str, result: ImageLoad) {
+        let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+        let recolor_active = self.view_states.recolor_active(&path, self.settings.svg_recolor_enabled);
+        let synced = self.file_infos.iter().any(|file| file.path == path && file.provider.is_some());
+        let settings = self.svg_settings_for(&path);
+        match result {
+            Ok(color_image) => {
+                self.last_error_report = None;
+                let texture_name = format!("image_{}", path.file_name().unwrap_or_default().to_string_lossy());
+                let [decoded_width, decoded_height] = color_image.size;
+                // Kept for when the file is back to online-only
+                if synced
+                    && let Some(store) = &self.preview_store
+                    && store.lock().unwrap().needs_preview(&path) {
+                    self.pending_preview = Some((std::time::Instant::now(), path.clone(), Arc::clone(&color_image)));
+                }
+                self.set_displayed_image(ctx, texture_name, color_image, Some(cache_key));
+                self.current_metadata = read_image_metadata(&path).map(|mut metadata| {
+                    // SVGs have no raster dimensions of their own
+                    if metadata.width == 0 || metadata.height == 0 {
+                        metadata.width = decoded_width as u32;
+                        metadata.height = decoded_height as u32;
                     }
-                    Err(e) => {
-                        self.image_texture = None;
-                        let filename = path.file_name()
-                            .map(|f| f.to_string_lossy().to_string())
-                            .unwrap_or_else(|| path.to_string_lossy().to_string());
-                        let display_filename = self.settings.truncate_filename(&filename);
-                        self.status_text = format!("Error loading {}: {}", display_filename, e);
+                    metadata
+                });
+                // Needs the file's own size to place saved redactions
+                self.restore_edits(ctx, &path);
+                #[cfg(feature = "scripting")]
+                self.run_script_hook(crate::scripting::IMAGE_LOADED_HOOK, &path);
+                self.update_metadata_sections(&path);
+                if extension == "svg" && recolor_active && self.settings.recent_svg_colors.first() != Some(&self.settings.svg_target_color) {
+                    self.settings.remember_svg_color(self.settings.svg_target_color);
+                    self.save_svg_colors();
+                }
+                let recolor_suffix = if extension == "svg" && recolor_active {
+                    " (recolored)"
+                } else {
+                    ""
+                };
+                let filename = path.file_name()
+                    .map(|f| f.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.to_string_lossy().to_string());
+                let display_filename = self.settings.truncate_filename(&filename);
+                self.status_text = format!("Loaded: {}{}", display_filename, recolor_suffix);
+                    
+                // Update file locality status after successful load (in case it was downloaded)
+                self.update_file_locality_status(&path);
+            }
+            Err(e) => {
+                self.image_texture = None;
+                self.current_image = None;
+                self.selection = None;
+                let filename = path.file_name()
+                    .map(|f| f.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.to_string_lossy().to_string());
+                let display_filename = self.settings.truncate_filename(&filename);
+                self.status_text = format!("Error loading {}: {}", display_filename, e);
+                // A video without a poster frame isn't a broken file
+                if video::is_video(&path) {
+                    self.video_placeholder = Some((path.clone(), e.to_string()));
+                    return;
+                }
+                if let Ok(mut failures) = self.decode_failures.lock() {
+                    failures.insert(path.clone());
+                }
+                if self.settings.capture_error_reports && let Some(dir) = diagnostics_dir() {
+                    match save_error_report(&path, &e, &settings, &dir) {
+                        Ok(report) => self.last_error_report = Some(report),
+                        Err(report_error) => self.status_text.push_str(&format!(" ({})", report_error)),
                     }
                 }
             }
         }
     }
 
-    pub fn run_benchmark(&mut self, ctx: &egui::Context) {
+    /// Key for the image caches; changes when the file or the settings that shape decoding do
+    fn image_cache_key(&self, path: &std::path::Path) -> String {
+        let modified = std::fs::metadata(archive::backing_file(path)).and_then(|m| m.modified()).ok();
+        format!(
+            "{}|{:?}|{}|{}|{}|{}|{}|{:?}",
+            path.display(),
+            modified,
+            self.view_states.recolor_active(path, self.settings.svg_recolor_enabled),
+            self.recolor_signature(),
+            self.settings.auto_scale_large_images,
+            self.settings.skip_large_images,
+            self.settings.large_image_max_dimension,
+            self.settings.resize_filter,
+        )
+    }
+
+    /// Show decoded pixels, reusing the cached texture for `cache_key` if there is one
+    fn set_displayed_image(&mut self, ctx: &egui::Context, texture_name: String, color_image: Arc<ColorImage>, cache_key: Option<&str>) {
+        self.auto_backdrop = Some(automatic_backdrop(&color_image, self.settings.theme.image_background()));
+        self.image_matte = border_color(&color_image);
+        self.proof_texture = None;
+        self.proof_render = None;
+        self.online_placeholder = None;
+        let cached = cache_key.and_then(|key| self.texture_cache.lock().unwrap().get(&key.to_string()).cloned());
+        let texture = cached.unwrap_or_else(|| {
+            let texture = ctx.load_texture(texture_name, color_image.clone(), SMOOTH_TEXTURE);
+            if let Some(key) = cache_key {
+                self.texture_cache.lock().unwrap().insert(key.to_string(), texture.clone(), color_image.pixels.len() * 4);
+            }
+            texture
+        });
+        self.image_texture = Some(texture);
+        self.current_image = Some(color_image);
+        self.unedited_image = None;
+        self.edited_path = None;
+        self.history.forget_image_edits();
+        self.selection = None;
+        self.selection_drag_start = None;
+        self.reset_zoom();
+    }
+
+    pub(crate) fn run_benchmark(&mut self, ctx: &egui::Context) {
+        crash::record_action("Run benchmark");
         if self.benchmark_in_progress {
             return;
         }
         
         self.benchmark_in_progress = true;
+        if self.performance_profile.hardware.is_none() {
+            self.performance_profile.set_hardware(HardwareFingerprint::detect(None));
+        }
         self.performance_profile.benchmark_results.clear();
         self.performance_profile.last_benchmark_time = Some(Instant::now());
         
         // Run safe benchmarks using existing images
-        let results = self.performance_profile.benchmark_safe_images(ctx);
+        let results = self.performance_profile.benchmark_safe_images(ctx, &self.progress.sink());
         
         self.benchmark_in_progress = false;
         
@@ -818,13 +7978,218 @@ impl ImageViewerApp {
             "Benchmark completed: {}/{} images processed successfully", 
             successful_count, total_count
         );
+        if let Err(e) = self.performance_profile.save() {
+            self.status_text = e;
+        }
+
+        if !tuning::was_offered(&Preferences::load()) {
+            self.offer_tuned_settings();
+        }
     }
 
-    fn will_image_render_quickly(&self, path: &PathBuf) -> Option<bool> {
-        if let Some(estimated_time) = estimate_image_render_time(path, &self.performance_profile) {
-            Some(estimated_time <= self.benchmark_threshold_ms)
+    /// Suggest settings for this machine's performance category
+    fn offer_tuned_settings(&mut self) {
+        let tuned = TunedDefaults::for_benchmark(
+            SystemPerformanceCategory::from_score(run_simple_cpu_benchmark()),
+            self.performance_profile.system_capabilities.max_successful_megapixels,
+        );
+        if tuned.changes(&self.settings).is_empty() {
+            self.status_text.push_str("; settings already suit this computer");
         } else {
-            None
+            self.suggested_tuning = Some(tuned);
+        }
+    }
+
+    /// Loading settings for an SVG, honoring its per-file recolor override
+    fn svg_settings_for(&self, path: &std::path::Path) -> ImageLoadingSettings {
+        ImageLoadingSettings {
+            svg_recolor_enabled: self.view_states.recolor_active(path, self.settings.svg_recolor_enabled),
+            ..self.settings.clone()
         }
     }
+
+    /// Path of the selected image if it is a loaded SVG
+    fn displayed_svg_path(&self) -> Option<PathBuf> {
+        self.image_texture.as_ref()?;
+        let path = &self.file_infos.get(self.selected_image_index?)?.path;
+        path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")).then(|| path.clone())
+    }
+
+    /// What the file list's cached rows depend on besides the files themselves
+    fn file_row_signature(&self) -> String {
+        let settings = &self.settings;
+        format!(
+            "{}|{}|{:?}|{}|{:?}",
+            settings.truncate_long_filenames,
+            settings.max_filename_length,
+            settings.truncation_style,
+            settings.ellipsis_char,
+            self.performance_profile.last_benchmark_time,
+        )
+    }
+}
+
+/// Supported images directly inside `dir`, sorted by name; or in reading
+/// order inside an archive
+fn scan_folder(dir: &std::path::Path, settings: &ImageLoadingSettings) -> Vec<FileInfo> {
+    if archive::is_archive(dir) && dir.is_file() {
+        // Entries share the archive's locality, so a placeholder archive isn't downloaded to list it
+        let locality = FileInfo::new(dir.to_path_buf());
+        let entries = archive::list_images(dir, &settings.supported_formats).unwrap_or_else(|e| {
+            eprintln!("Warning: {}", e);
+            Vec::new()
+        });
+        return entries.into_iter().map(|path| FileInfo { path, ..locality.clone() }).collect();
+    }
+    let escaped = glob::Pattern::escape(&dir.to_string_lossy());
+    let mut paths: Vec<PathBuf> = settings.supported_formats
+        .iter()
+        .map(String::as_str)
+        .chain(video::VIDEO_EXTENSIONS)
+        .filter_map(|ext| glob(&format!("{}/*.{}", escaped, ext)).ok())
+        .flat_map(|paths| paths.flatten())
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths.into_iter().map(FileInfo::new).collect()
+}
+
+/// Drag payload when reordering favorites
+struct FavoriteDrag(usize);
+
+/// What to do when culling is finished
+#[derive(Clone, Copy)]
+enum CullingEnd {
+    Resume,
+    Review,
+    Leave,
+    MoveRejects,
+    DeleteRejects,
+}
+
+/// Drag payload of images dragged from the file list onto the second folder pane
+struct FileDrag(Vec<PathBuf>);
+
+/// Drag payload of an image dragged from the second folder pane onto the file list
+struct PaneDrag(PathBuf);
+
+const MB: usize = 1024 * 1024;
+const TEXTURE_CACHE_BYTES: usize = 256 * MB;
+const DECODED_IMAGE_CACHE_BYTES: usize = 512 * MB;
+const DEEP_ZOOM_TILE_CACHE_BYTES: usize = 256 * MB;
+const PREVIEW_STORE_BYTES: usize = 64 * MB;
+/// Shortest time between saves of the search index
+const SEARCH_INDEX_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+/// How long the recolor settings must stay unchanged before the preview is rendered
+const SVG_PREVIEW_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(250);
+/// Difference heatmaps kept while comparing, for moving the threshold back and forth
+const HEATMAP_CACHE: usize = 4;
+/// Frames of a playing sequence decoded ahead of the one on screen
+const SEQUENCE_READ_AHEAD: usize = 8;
+/// Coarser pyramid levels drawn under the current one while its tiles load
+const DEEP_ZOOM_FALLBACK_LEVELS: u32 = 3;
+#[cfg(feature = "graph")]
+const THUMBNAIL_CACHE_BYTES: usize = 64 * MB;
+
+/// How often pinned favorites are checked for having been moved or deleted
+const FAVORITES_RECHECK: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long a slideshow holds for the next slide's download before moving on to a local one
+const SLIDESHOW_DOWNLOAD_WAIT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Time for the network to come back after the machine wakes, before downloads resume
+const WAKE_GRACE: std::time::Duration = std::time::Duration::from_secs(10);
+
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 32.0;
+/// Zoom change per click of the toolbar's zoom buttons
+const TOOLBAR_ZOOM_STEP: f32 = 1.25;
+const TOOLBAR_ICON_SIZE: f32 = 18.0;
+
+/// Texture of a thumbnail Graph sent as encoded image `bytes`
+#[cfg(feature = "graph")]
+fn graph_thumbnail_texture(ctx: &egui::Context, key: &str, bytes: &[u8]) -> Result<egui::TextureHandle, String> {
+    let img = image::load_from_memory(bytes)
+        .map_err(|e| format!("Failed to decode thumbnail: {}", e))?;
+    let rgba = img.to_rgba8();
+    let color_image = egui::ColorImage::from_rgba_unmultiplied(
+        [rgba.width() as usize, rgba.height() as usize],
+        rgba.as_flat_samples().as_slice(),
+    );
+    Ok(ctx.load_texture(format!("graph_thumbnail_{}", key), color_image, SMOOTH_TEXTURE))
+}
+
+/// Clickable color square showing its hex value on hover
+fn color_swatch(ui: &mut egui::Ui, [r, g, b]: [u8; 3]) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(16.0, 16.0), egui::Sense::click());
+    let stroke = if response.hovered() {
+        ui.visuals().widgets.hovered.fg_stroke
+    } else {
+        ui.visuals().widgets.noninteractive.bg_stroke
+    };
+    ui.painter().rect(rect, 2.0, egui::Color32::from_rgb(r, g, b), stroke, egui::StrokeKind::Inside);
+    response.on_hover_text(format!("#{:02x}{:02x}{:02x}", r, g, b))
+}
+
+/// "Open in Editor" submenu of the editors handling `path`; the index of the one clicked
+fn editor_menu(ui: &mut egui::Ui, editors: &[ExternalEditor], path: Option<&std::path::Path>) -> Option<usize> {
+    let handling: Vec<usize> = path.map(|path| (0..editors.len()).filter(|&i| !editors[i].command.trim().is_empty() && editors[i].handles(path)).collect()).unwrap_or_default();
+    let mut clicked = None;
+    ui.add_enabled_ui(!handling.is_empty(), |ui| {
+        ui.menu_button("Open in Editor", |ui| {
+            for index in handling {
+                if ui.button(&editors[index].name).clicked() {
+                    clicked = Some(index);
+                    ui.close_menu();
+                }
+            }
+        });
+    }).response.on_disabled_hover_text("No editor is set up for this format; add one in Settings");
+    clicked
+}
+
+/// Choice of how long confirming a warning silences it
+fn silence_choice(ui: &mut egui::Ui, silence: &mut Silence) {
+    for option in Silence::ALL {
+        ui.radio_value(silence, option, option.label());
+    }
+}
+
+/// "Always download files under N MB"; returns whether a change is finished
+/// and worth saving
+fn download_limit_editor(ui: &mut egui::Ui, limit: &mut Option<u32>) -> bool {
+    ui.horizontal(|ui| {
+        let mut enabled = limit.is_some();
+        let mut mb = limit.unwrap_or(10);
+        let mut changed = ui.checkbox(&mut enabled, "Always download files under").changed();
+        // Dragging changes the value every frame, so only report it on release
+        let response = ui.add_enabled(enabled, egui::DragValue::new(&mut mb).range(1..=10_000).suffix(" MB"));
+        changed |= response.drag_stopped() || (response.changed() && !response.dragged());
+        *limit = enabled.then_some(mb);
+        changed
+    }).inner
+}
+
+/// Small "?" button that explains the adjacent section on hover
+fn help_button(ui: &mut egui::Ui, explanation: &str) {
+    ui.small_button("?").on_hover_text(explanation);
+}
+
+/// " (≈ 12 s on your connection)" when a download time can be estimated
+fn download_time_suffix(bandwidth: &BandwidthEstimator, link_speed_mbps: Option<f64>, bytes: u64) -> String {
+    match bandwidth.estimate(bytes, link_speed_mbps) {
+        Some(duration) => format!(" ({} on your connection)", format_estimate(duration)),
+        None => String::new(),
+    }
+}
+
+/// " (≈ 14 s until visible on your connection)" for an on-demand file of
+/// `bytes`: the download plus the render time, once the benchmark has run
+fn visible_time_suffix(bandwidth: &BandwidthEstimator, link_speed_mbps: Option<f64>, profile: &PerformanceProfile, path: &std::path::Path, bytes: u64) -> String {
+    let format = path.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let render_ms = profile.estimate_render_time_from_size(bytes as f64 / MB as f64, &format);
+    match bandwidth.time_until_visible(bytes, link_speed_mbps, render_ms) {
+        Some(duration) if render_ms.is_some() => format!(" ({} until visible on your connection)", format_estimate(duration)),
+        _ => download_time_suffix(bandwidth, link_speed_mbps, bytes),
+    }
 }
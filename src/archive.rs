@@ -0,0 +1,163 @@
+//! ZIP and CBZ archives browsed as folders
+//!
+//! An image inside an archive is addressed by a virtual path: the archive's
+//! path followed by the entry name, e.g. `comics/issue1.cbz/page01.jpg`.
+//! Entries are decompressed into memory when shown; nothing is extracted to
+//! disk.
+
+#[cfg(feature = "gui")]
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+#[cfg(feature = "gui")]
+use std::path::PathBuf;
+
+use zip::ZipArchive;
+
+/// Extensions of archives that open as folders
+pub const ARCHIVE_EXTENSIONS: [&str; 2] = ["zip", "cbz"];
+
+/// Entries larger than this aren't decompressed
+const MAX_ENTRY_BYTES: u64 = 512 * 1024 * 1024;
+
+pub fn is_archive(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ARCHIVE_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+}
+
+/// The archive and entry name of a virtual path, or `None` for an ordinary file
+pub fn split_path(path: &Path) -> Option<(&Path, String)> {
+    let archive = path.ancestors().skip(1).find(|ancestor| is_archive(ancestor) && ancestor.is_file())?;
+    let entry = path.strip_prefix(archive).ok()?;
+    let entry: Vec<String> = entry.components().map(|part| part.as_os_str().to_string_lossy().into_owned()).collect();
+    Some((archive, entry.join("/")))
+}
+
+/// The file on disk that holds `path`: its archive, or the path itself
+pub fn backing_file(path: &Path) -> &Path {
+    split_path(path).map_or(path, |(archive, _)| archive)
+}
+
+/// Archives directly inside `dir`, sorted by name
+#[cfg(feature = "gui")]
+pub fn archives_in(dir: &Path) -> Vec<PathBuf> {
+    let mut archives: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|entry| entry.path()).filter(|path| is_archive(path) && path.is_file()).collect())
+        .unwrap_or_default();
+    archives.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+    archives
+}
+
+fn open(archive: &Path) -> Result<ZipArchive<BufReader<File>>, String> {
+    let file = File::open(archive).map_err(|e| format!("Failed to open {}: {}", archive.display(), e))?;
+    ZipArchive::new(BufReader::new(file)).map_err(|e| format!("Failed to read {}: {}", archive.display(), e))
+}
+
+/// Virtual paths of the images in an archive with one of `extensions`, in
+/// reading order ("page2" before "page10")
+#[cfg(feature = "gui")]
+pub fn list_images(archive: &Path, extensions: &[String]) -> Result<Vec<PathBuf>, String> {
+    let zip = open(archive)?;
+    let mut names: Vec<&str> = zip
+        .file_names()
+        // Resource forks added by macOS archivers aren't images
+        .filter(|name| !name.ends_with('/') && !name.starts_with("__MACOSX/"))
+        .filter(|name| {
+            let extension = Path::new(name).extension().unwrap_or_default();
+            extensions.iter().any(|known| extension.eq_ignore_ascii_case(known.as_str()))
+        })
+        .collect();
+    names.sort_by(|a, b| natural_cmp(a, b));
+    Ok(names.into_iter().map(|name| archive.join(name)).collect())
+}
+
+/// Decompressed bytes of an entry, at most `limit` of them
+pub fn read_entry(archive: &Path, entry: &str, limit: Option<u64>) -> Result<Vec<u8>, String> {
+    let mut zip = open(archive)?;
+    let file = zip.by_name(entry).map_err(|e| format!("{} in {}: {}", entry, archive.display(), e))?;
+    if limit.is_none() && file.size() > MAX_ENTRY_BYTES {
+        return Err(format!("{} is too large to decompress ({} MB)", entry, file.size() / (1024 * 1024)));
+    }
+    let mut bytes = Vec::with_capacity(file.size().min(limit.unwrap_or(MAX_ENTRY_BYTES)) as usize);
+    file.take(limit.unwrap_or(MAX_ENTRY_BYTES))
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to decompress {}: {}", entry, e))?;
+    Ok(bytes)
+}
+
+/// Compare names ignoring case, with runs of digits sorted by value
+#[cfg(feature = "gui")]
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    folded_cmp(a, b).then_with(|| a.cmp(b))
+}
+
+#[cfg(feature = "gui")]
+fn folded_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+                        digits.push(c);
+                        chars.next();
+                    }
+                    digits
+                };
+                let (x, y) = (number(&mut a), number(&mut b));
+                let (x_value, y_value) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ordering = x_value.len().cmp(&y_value.len()).then_with(|| x_value.cmp(y_value)).then_with(|| x.len().cmp(&y.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "gui"))]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::io::Write;
+
+    #[test]
+    fn test_natural_order() {
+        let mut names = vec!["page10.jpg", "Page2.jpg", "page1.jpg", "page01.jpg"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["page1.jpg", "page01.jpg", "Page2.jpg", "page10.jpg"]);
+    }
+
+    #[test]
+    fn test_list_and_read_entries() {
+        let dir = TempDir::new("archive");
+        let archive = dir.join("issue.cbz");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for name in ["pages/p10.png", "pages/p2.png", "notes.txt", "__MACOSX/pages/._p2.png"] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(name.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let images = list_images(&archive, &["png".to_string()]).unwrap();
+        assert_eq!(images, [archive.join("pages/p2.png"), archive.join("pages/p10.png")]);
+        let (found, entry) = split_path(&images[1]).unwrap();
+        assert_eq!((found, entry.as_str()), (archive.as_path(), "pages/p10.png"));
+        assert_eq!(read_entry(found, &entry, None).unwrap(), b"pages/p10.png");
+        assert_eq!(backing_file(&archive), archive);
+        assert!(split_path(&dir.join("photo.png")).is_none());
+    }
+}
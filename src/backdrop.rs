@@ -0,0 +1,271 @@
+//! Preview backgrounds, including automatic contrast for transparent images
+//!
+//! Light icons on a transparent background disappear against the default
+//! preview background. In `Auto` mode the backdrop is chosen from the
+//! alpha-weighted luminance of the image so its content stays visible.
+//!
+//! Logos and product shots often sit on a solid color. In `Matte` mode that
+//! color, sampled from the image's border, fills the view around the image so
+//! the two blend seamlessly.
+
+#[cfg(feature = "gui")]
+use std::collections::HashMap;
+
+use ecolor::Color32;
+#[cfg(feature = "gui")]
+use eframe::egui::{self, ColorImage};
+
+/// Neutral gray behind previews unless the theme sets another color
+pub const DEFAULT_BACKDROP: Color32 = Color32::from_gray(128);
+#[cfg(feature = "gui")]
+const LIGHT_BACKDROP: Color32 = Color32::from_gray(235);
+#[cfg(feature = "gui")]
+const DARK_BACKDROP: Color32 = Color32::from_gray(30);
+
+/// Below this WCAG contrast ratio (the AA level for text) content is considered hard to see
+#[cfg(feature = "gui")]
+const MIN_CONTRAST_RATIO: f32 = 4.5;
+/// Images whose mean alpha is above this are opaque enough to cover the backdrop
+#[cfg(feature = "gui")]
+const OPAQUE_COVERAGE: f32 = 0.9;
+
+/// Border pixels differing by at most this much per channel count as the same color
+#[cfg(feature = "gui")]
+const MATTE_TOLERANCE: u8 = 12;
+/// Share of the border that must be one color for it to be used as the matte
+#[cfg(feature = "gui")]
+const MATTE_AGREEMENT: f32 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewBackground {
+    /// Theme background, switching to a contrasting color for low-contrast transparent images
+    Auto,
+    /// The background color set in the theme
+    Theme,
+    Light,
+    Dark,
+    Checkerboard,
+    /// The solid color around the image's edge, or the theme color if there is none
+    Matte,
+}
+
+impl PreviewBackground {
+    pub const ALL: [PreviewBackground; 6] = [
+        PreviewBackground::Auto,
+        PreviewBackground::Theme,
+        PreviewBackground::Light,
+        PreviewBackground::Dark,
+        PreviewBackground::Checkerboard,
+        PreviewBackground::Matte,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PreviewBackground::Auto => "Automatic",
+            PreviewBackground::Theme => "Theme Color",
+            PreviewBackground::Light => "Light",
+            PreviewBackground::Dark => "Dark",
+            PreviewBackground::Checkerboard => "Checkerboard",
+            PreviewBackground::Matte => "Match Image Border",
+        }
+    }
+}
+
+/// What to draw behind the image
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backdrop {
+    Solid(Color32),
+    Checkerboard,
+}
+
+/// Relative luminance (0..1) of an sRGB color, per WCAG
+#[cfg(feature = "gui")]
+pub fn relative_luminance(color: Color32) -> f32 {
+    let linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    };
+    0.2126 * linear(color.r()) + 0.7152 * linear(color.g()) + 0.0722 * linear(color.b())
+}
+
+#[cfg(feature = "gui")]
+pub fn contrast_ratio(a: f32, b: f32) -> f32 {
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Pixels sampled at most when measuring an image, so large photos stay cheap
+#[cfg(feature = "gui")]
+const MAX_SAMPLES: usize = 65_536;
+
+/// Mean alpha (0..1) and alpha-weighted mean luminance of the visible pixels
+#[cfg(feature = "gui")]
+pub fn alpha_weighted_luminance(image: &ColorImage) -> (f32, Option<f32>) {
+    let step = (image.pixels.len() / MAX_SAMPLES).max(1);
+    let mut samples = 0usize;
+    let mut alpha_sum = 0.0_f64;
+    let mut luminance_sum = 0.0_f64;
+    for pixel in image.pixels.iter().step_by(step) {
+        samples += 1;
+        let alpha = pixel.a() as f64 / 255.0;
+        if alpha > 0.0 {
+            // Pixels are premultiplied; undo that before measuring the color
+            let [r, g, b, _] = pixel.to_srgba_unmultiplied();
+            alpha_sum += alpha;
+            luminance_sum += alpha * relative_luminance(Color32::from_rgb(r, g, b)) as f64;
+        }
+    }
+    let coverage = if samples == 0 { 0.0 } else { (alpha_sum / samples as f64) as f32 };
+    let luminance = (alpha_sum > 0.0).then(|| (luminance_sum / alpha_sum) as f32);
+    (coverage, luminance)
+}
+
+/// Backdrop that keeps a mostly-transparent image readable against `default`
+#[cfg(feature = "gui")]
+pub fn automatic_backdrop(image: &ColorImage, default: Color32) -> Backdrop {
+    let (coverage, luminance) = alpha_weighted_luminance(image);
+    let Some(luminance) = luminance else {
+        return Backdrop::Solid(default);
+    };
+    if coverage >= OPAQUE_COVERAGE || contrast_ratio(luminance, relative_luminance(default)) >= MIN_CONTRAST_RATIO {
+        return Backdrop::Solid(default);
+    }
+    let light = contrast_ratio(luminance, relative_luminance(LIGHT_BACKDROP));
+    let dark = contrast_ratio(luminance, relative_luminance(DARK_BACKDROP));
+    if light.max(dark) < MIN_CONTRAST_RATIO {
+        // Mid-tone content has no good solid backdrop
+        Backdrop::Checkerboard
+    } else if light > dark {
+        Backdrop::Solid(LIGHT_BACKDROP)
+    } else {
+        Backdrop::Solid(DARK_BACKDROP)
+    }
+}
+
+/// The color of the image's outermost pixels, if they're opaque and nearly all alike
+#[cfg(feature = "gui")]
+pub fn border_color(image: &ColorImage) -> Option<Color32> {
+    let [width, height] = image.size;
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let step = ((width + height) * 2 / MAX_SAMPLES).max(1);
+    let top = (0..width).map(|x| (x, 0));
+    let bottom = (0..width).map(|x| (x, height - 1));
+    let left = (0..height).map(|y| (0, y));
+    let right = (0..height).map(|y| (width - 1, y));
+    let border: Vec<Color32> = top.chain(bottom).chain(left).chain(right)
+        .step_by(step)
+        .map(|(x, y)| image.pixels[y * width + x])
+        .collect();
+    if border.iter().any(|pixel| pixel.a() < u8::MAX) {
+        // The backdrop already shows through a transparent edge
+        return None;
+    }
+    let mean = |pixels: &[Color32]| {
+        let sum = pixels.iter().fold([0u64; 3], |sum, p| [sum[0] + p.r() as u64, sum[1] + p.g() as u64, sum[2] + p.b() as u64]);
+        let n = pixels.len().max(1) as u64;
+        Color32::from_rgb((sum[0] / n) as u8, (sum[1] / n) as u8, (sum[2] / n) as u8)
+    };
+    // The most common color, so a logo touching the edge doesn't pull it off
+    let bucket = |p: &Color32| [p.r() / MATTE_TOLERANCE, p.g() / MATTE_TOLERANCE, p.b() / MATTE_TOLERANCE];
+    let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+    for pixel in &border {
+        *counts.entry(bucket(pixel)).or_default() += 1;
+    }
+    let modal = counts.into_iter().max_by_key(|&(_, count)| count).map(|(key, _)| key)?;
+    let modal: Vec<Color32> = border.iter().copied().filter(|pixel| bucket(pixel) == modal).collect();
+    let average = mean(&modal);
+    let close = |p: &Color32| p.r().abs_diff(average.r()).max(p.g().abs_diff(average.g())).max(p.b().abs_diff(average.b())) <= MATTE_TOLERANCE;
+    let matching: Vec<Color32> = border.iter().copied().filter(close).collect();
+    (matching.len() as f32 >= border.len() as f32 * MATTE_AGREEMENT).then(|| mean(&matching))
+}
+
+/// Resolve the configured background, given the automatic choice and border
+/// color for the loaded image
+#[cfg(feature = "gui")]
+pub fn resolve_backdrop(setting: PreviewBackground, auto: Option<Backdrop>, matte: Option<Color32>, theme_color: Color32) -> Backdrop {
+    match setting {
+        PreviewBackground::Auto => auto.unwrap_or(Backdrop::Solid(theme_color)),
+        PreviewBackground::Theme => Backdrop::Solid(theme_color),
+        PreviewBackground::Light => Backdrop::Solid(LIGHT_BACKDROP),
+        PreviewBackground::Dark => Backdrop::Solid(DARK_BACKDROP),
+        PreviewBackground::Checkerboard => Backdrop::Checkerboard,
+        PreviewBackground::Matte => Backdrop::Solid(matte.unwrap_or(theme_color)),
+    }
+}
+
+/// Checkerboard squares covering `rect`
+#[cfg(feature = "gui")]
+pub fn checkerboard_shapes(rect: egui::Rect, square: f32) -> Vec<egui::Shape> {
+    let mut shapes = vec![egui::Shape::rect_filled(rect, 0.0, Color32::from_gray(204))];
+    let columns = (rect.width() / square).ceil() as usize;
+    let rows = (rect.height() / square).ceil() as usize;
+    for row in 0..rows {
+        for column in (row % 2..columns).step_by(2) {
+            let min = rect.min + egui::vec2(column as f32 * square, row as f32 * square);
+            let cell = egui::Rect::from_min_size(min, egui::Vec2::splat(square)).intersect(rect);
+            shapes.push(egui::Shape::rect_filled(cell, 0.0, Color32::from_gray(153)));
+        }
+    }
+    shapes
+}
+
+#[cfg(all(test, feature = "gui"))]
+mod tests {
+    use super::*;
+
+    fn icon(color: Color32, coverage: usize) -> ColorImage {
+        // `coverage` of 10 pixels are opaque, the rest transparent
+        let mut image = ColorImage::new([10, 1], Color32::TRANSPARENT);
+        for pixel in image.pixels.iter_mut().take(coverage) {
+            *pixel = color;
+        }
+        image
+    }
+
+    #[test]
+    fn test_light_icon_gets_dark_backdrop() {
+        // White on the default gray is under 4:1
+        assert_eq!(automatic_backdrop(&icon(Color32::WHITE, 2), DEFAULT_BACKDROP), Backdrop::Solid(DARK_BACKDROP));
+        assert_eq!(automatic_backdrop(&icon(Color32::from_gray(200), 2), DEFAULT_BACKDROP), Backdrop::Solid(DARK_BACKDROP));
+        // Mid-tones have no readable solid backdrop
+        assert_eq!(automatic_backdrop(&icon(Color32::from_gray(118), 2), LIGHT_BACKDROP), Backdrop::Checkerboard);
+        // Dark icons are already readable on gray
+        assert_eq!(automatic_backdrop(&icon(Color32::BLACK, 2), DEFAULT_BACKDROP), Backdrop::Solid(DEFAULT_BACKDROP));
+    }
+
+    #[test]
+    fn test_opaque_and_empty_images_keep_default() {
+        let pale = Color32::from_gray(200);
+        assert_eq!(automatic_backdrop(&icon(pale, 10), DEFAULT_BACKDROP), Backdrop::Solid(DEFAULT_BACKDROP));
+        assert_eq!(automatic_backdrop(&icon(pale, 0), DEFAULT_BACKDROP), Backdrop::Solid(DEFAULT_BACKDROP));
+        assert_eq!(
+            resolve_backdrop(PreviewBackground::Checkerboard, Some(Backdrop::Solid(DARK_BACKDROP)), None, DEFAULT_BACKDROP),
+            Backdrop::Checkerboard
+        );
+        let navy = Color32::from_rgb(20, 30, 60);
+        assert_eq!(resolve_backdrop(PreviewBackground::Auto, None, None, navy), Backdrop::Solid(navy));
+    }
+
+    #[test]
+    fn test_matte_needs_a_solid_opaque_border() {
+        let white = Color32::from_gray(250);
+        let mut logo = ColorImage::new([20, 10], white);
+        logo.pixels[5 * 20 + 10] = Color32::RED;
+        // A stray dark pixel on the edge doesn't change the matte
+        logo.pixels[3] = Color32::BLACK;
+        assert_eq!(border_color(&logo), Some(white));
+        assert_eq!(resolve_backdrop(PreviewBackground::Matte, None, border_color(&logo), DEFAULT_BACKDROP), Backdrop::Solid(white));
+
+        // A dark logo running off the top edge covers 18% of the border
+        let mut cropped = ColorImage::new([50, 50], white);
+        cropped.pixels[5..41].iter_mut().for_each(|pixel| *pixel = Color32::from_gray(10));
+        assert_eq!(border_color(&cropped), Some(white));
+
+        let mut split = ColorImage::new([20, 10], white);
+        split.pixels.iter_mut().skip(100).for_each(|pixel| *pixel = Color32::BLACK);
+        assert_eq!(border_color(&split), None);
+        assert_eq!(border_color(&icon(Color32::WHITE, 9)), None);
+    }
+}
@@ -0,0 +1,99 @@
+//! Download bandwidth estimation for download-time predictions
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Number of recent downloads averaged for the estimate
+const MAX_SAMPLES: usize = 10;
+/// Downloads smaller or faster than this are dominated by latency, not bandwidth
+const MIN_SAMPLE_BYTES: u64 = 256 * 1024;
+const MIN_SAMPLE_DURATION: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Default)]
+pub struct BandwidthEstimator {
+    samples: VecDeque<(u64, Duration)>,
+}
+
+impl BandwidthEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a completed download. Samples too small to measure bandwidth are ignored.
+    pub fn record(&mut self, bytes: u64, elapsed: Duration) {
+        if bytes < MIN_SAMPLE_BYTES || elapsed < MIN_SAMPLE_DURATION {
+            return;
+        }
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((bytes, elapsed));
+    }
+
+    /// Measured throughput over the recent downloads, in bytes per second
+    pub fn measured_bytes_per_sec(&self) -> Option<f64> {
+        let (bytes, secs) = self.samples
+            .iter()
+            .fold((0u64, 0f64), |(bytes, secs), (b, d)| (bytes + b, secs + d.as_secs_f64()));
+        (secs > 0.0).then(|| bytes as f64 / secs)
+    }
+
+    /// Expected download time, preferring measurements over the configured link speed
+    pub fn estimate(&self, bytes: u64, configured_mbps: Option<f64>) -> Option<Duration> {
+        let bytes_per_sec = self.measured_bytes_per_sec()
+            .or_else(|| configured_mbps.filter(|mbps| *mbps > 0.0).map(|mbps| mbps * 1_000_000.0 / 8.0))?;
+        Some(Duration::from_secs_f64(bytes as f64 / bytes_per_sec))
+    }
+
+    /// Expected time until a downloaded file is on screen: its download plus
+    /// `render_ms` to decode and show it, when that's known
+    pub fn time_until_visible(&self, bytes: u64, configured_mbps: Option<f64>, render_ms: Option<f64>) -> Option<Duration> {
+        let download = self.estimate(bytes, configured_mbps)?;
+        Some(download + Duration::from_secs_f64(render_ms.unwrap_or(0.0).max(0.0) / 1000.0))
+    }
+}
+
+/// Rough human-readable duration, e.g. "≈ 12 s" or "≈ 3 min"
+pub fn format_estimate(duration: Duration) -> String {
+    let secs = duration.as_secs_f64();
+    if secs < 1.0 {
+        "< 1 s".to_string()
+    } else if secs < 60.0 {
+        format!("≈ {:.0} s", secs)
+    } else if secs < 3600.0 {
+        format!("≈ {:.0} min", secs / 60.0)
+    } else {
+        let minutes = (secs / 60.0).round() as u64;
+        format!("≈ {} h {} min", minutes / 60, minutes % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_prefers_measurements() {
+        let mut estimator = BandwidthEstimator::new();
+        // 10 Mbps configured = 1.25 MB/s
+        assert_eq!(estimator.estimate(2_500_000, Some(10.0)), Some(Duration::from_secs(2)));
+        assert_eq!(estimator.estimate(2_500_000, None), None);
+
+        estimator.record(1_000, Duration::from_secs(1)); // too small, ignored
+        assert_eq!(estimator.measured_bytes_per_sec(), None);
+
+        estimator.record(4_000_000, Duration::from_secs(2));
+        estimator.record(2_000_000, Duration::from_secs(1));
+        assert_eq!(estimator.measured_bytes_per_sec(), Some(2_000_000.0));
+        assert_eq!(estimator.estimate(10_000_000, Some(10.0)), Some(Duration::from_secs(5)));
+        assert_eq!(estimator.time_until_visible(10_000_000, None, Some(1500.0)), Some(Duration::from_millis(6500)));
+    }
+
+    #[test]
+    fn test_format_estimate() {
+        assert_eq!(format_estimate(Duration::from_millis(300)), "< 1 s");
+        assert_eq!(format_estimate(Duration::from_secs(12)), "≈ 12 s");
+        assert_eq!(format_estimate(Duration::from_secs(180)), "≈ 3 min");
+        assert_eq!(format_estimate(Duration::from_secs(3900)), "≈ 1 h 5 min");
+    }
+}
@@ -1,15 +1,30 @@
 //! Performance benchmarking functionality
+//!
+//! Benchmark results are kept between sessions in the config directory, together
+//! with the fingerprint of the machine that measured them, so a profile
+//! carried over to different hardware is recognized and discarded.
 
 use std::time::Instant;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use eframe::egui;
-use egui::{ColorImage, TextureHandle};
+#[cfg(feature = "gui")]
+use std::path::Path;
+#[cfg(feature = "gui")]
+use eframe::egui::{self, ColorImage, TextureHandle};
 use glob::glob;
 use image::ImageReader;
+use sysinfo::System;
 
 use crate::file_locality::FileInfo;
 use crate::settings::DEFAULT_SUPPORTED_FORMATS;
+#[cfg(feature = "gui")]
+use crate::settings::config_dir;
+#[cfg(feature = "gui")]
+use crate::progress::{OperationKind, ProgressSink};
+
+/// File in the config directory the performance profile is kept in
+#[cfg(feature = "gui")]
+const PROFILE_FILE: &str = "performance_profile.txt";
 
 // Performance categories based on simple CPU benchmark
 #[derive(Debug, Clone, PartialEq)]
@@ -118,11 +133,15 @@ pub struct BenchmarkResult {
     pub total_time_ms: f64,
     pub success: bool,
     pub error_message: Option<String>,
+    /// `HardwareFingerprint::id` of the machine that produced this result
+    pub hardware_id: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct PerformanceProfile {
     pub benchmark_results: Vec<BenchmarkResult>,
+    /// Machine the benchmark results were measured on
+    pub hardware: Option<HardwareFingerprint>,
     pub system_capabilities: SystemCapabilities,
     pub last_benchmark_time: Option<Instant>,
     pub reference_comparison: Option<PerformanceComparison>,
@@ -142,10 +161,103 @@ pub struct PerformanceComparison {
     pub confidence_level: f64,  // 0.0 to 1.0, how confident we are in the estimate
 }
 
+/// Hardware description used to tell whether benchmark results still apply to this machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct HardwareFingerprint {
+    pub cpu_model: String,
+    pub cpu_cores: usize,
+    pub total_memory_mb: u64,
+    pub gpu_adapter: Option<String>,
+    pub os_version: String,
+}
+
+impl HardwareFingerprint {
+    /// Detect the current machine. The GPU adapter name comes from the render
+    /// state, which is only available once the app window has been created.
+    pub fn detect(gpu_adapter: Option<String>) -> Self {
+        let mut system = System::new();
+        system.refresh_cpu_all();
+        system.refresh_memory();
+
+        let cpu_model = system
+            .cpus()
+            .first()
+            .map(|cpu| cpu.brand().trim().to_string())
+            .unwrap_or_else(|| "Unknown CPU".to_string());
+
+        Self {
+            cpu_model,
+            cpu_cores: system.cpus().len(),
+            total_memory_mb: system.total_memory() / (1024 * 1024),
+            gpu_adapter,
+            os_version: System::long_os_version().unwrap_or_else(|| "Unknown OS".to_string()),
+        }
+    }
+
+    /// Stable identifier for this hardware, identical across runs on the same machine
+    ///
+    /// Memory is rounded to the nearest GB since the reported total varies slightly
+    /// between boots. The OS version is not included so that OS updates don't
+    /// invalidate otherwise valid results.
+    pub fn id(&self) -> String {
+        let key = format!(
+            "{}|{}|{}|{}",
+            self.cpu_model,
+            self.cpu_cores,
+            (self.total_memory_mb + 512) / 1024,
+            self.gpu_adapter.as_deref().unwrap_or("")
+        );
+        // FNV-1a, since std's hasher output isn't guaranteed to be stable between releases
+        let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        format!("{:016x}", hash)
+    }
+
+    /// Tab-separated fields, as kept in the profile file
+    #[cfg(feature = "gui")]
+    fn to_line(&self) -> String {
+        format!(
+            "hardware\t{}\t{}\t{}\t{}\t{}",
+            self.cpu_model,
+            self.cpu_cores,
+            self.total_memory_mb,
+            self.gpu_adapter.as_deref().unwrap_or(""),
+            self.os_version
+        )
+    }
+
+    #[cfg(feature = "gui")]
+    fn from_fields(fields: &[&str]) -> Option<Self> {
+        let [cpu_model, cpu_cores, total_memory_mb, gpu_adapter, os_version] = fields else {
+            return None;
+        };
+        Some(Self {
+            cpu_model: cpu_model.to_string(),
+            cpu_cores: cpu_cores.parse().ok()?,
+            total_memory_mb: total_memory_mb.parse().ok()?,
+            gpu_adapter: (!gpu_adapter.is_empty()).then(|| gpu_adapter.to_string()),
+            os_version: os_version.to_string(),
+        })
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} ({} cores), {:.1} GB RAM, {}, {}",
+            self.cpu_model,
+            self.cpu_cores,
+            self.total_memory_mb as f64 / 1024.0,
+            self.gpu_adapter.as_deref().unwrap_or("Unknown GPU"),
+            self.os_version
+        )
+    }
+}
+
 impl Default for PerformanceProfile {
     fn default() -> Self {
         Self {
             benchmark_results: Vec::new(),
+            hardware: None,
             system_capabilities: SystemCapabilities {
                 max_successful_megapixels: 0.0,
                 avg_decode_time_per_mp: 0.0,
@@ -159,11 +271,93 @@ impl Default for PerformanceProfile {
 }
 
 impl PerformanceProfile {
-    pub fn add_benchmark_result(&mut self, result: BenchmarkResult) {
+    pub fn add_benchmark_result(&mut self, mut result: BenchmarkResult) {
+        if result.hardware_id.is_none() {
+            result.hardware_id = self.hardware.as_ref().map(HardwareFingerprint::id);
+        }
         self.benchmark_results.push(result);
         self.update_system_capabilities();
     }
+
+    /// True if every result in this profile was measured on the given hardware
+    pub fn matches_hardware(&self, hardware: &HardwareFingerprint) -> bool {
+        let id = hardware.id();
+        self.benchmark_results
+            .iter()
+            .all(|r| r.hardware_id.as_deref().is_none_or(|result_id| result_id == id))
+    }
+
+    /// Associate the profile with the current machine, discarding results measured
+    /// on different hardware. Returns true if stale results were discarded.
+    pub fn set_hardware(&mut self, hardware: HardwareFingerprint) -> bool {
+        let stale = !self.matches_hardware(&hardware);
+        if stale {
+            *self = Self::default();
+        }
+        self.hardware = Some(hardware);
+        stale
+    }
     
+    /// The profile as text: the machine it was measured on, then a line per result
+    #[cfg(feature = "gui")]
+    pub(crate) fn to_text(&self) -> String {
+        let mut text = String::from("# Benchmark results and the machine that measured them\n");
+        if let Some(hardware) = &self.hardware {
+            text.push_str(&hardware.to_line());
+            text.push('\n');
+        }
+        for result in &self.benchmark_results {
+            let characteristics = &result.characteristics;
+            text.push_str(&format!(
+                "result\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                result.hardware_id.as_deref().unwrap_or(""),
+                characteristics.format,
+                characteristics.width,
+                characteristics.height,
+                characteristics.file_size_mb,
+                result.decode_time_ms,
+                result.texture_creation_time_ms,
+                result.total_time_ms,
+                result.success,
+                result.error_message.as_deref().unwrap_or("").replace(['\t', '\r', '\n'], " "),
+            ));
+        }
+        text
+    }
+
+    /// A profile written by [`Self::to_text`]; unreadable lines are skipped
+    #[cfg(feature = "gui")]
+    pub(crate) fn parse(text: &str) -> Self {
+        let mut profile = Self::default();
+        for line in text.lines().filter(|line| !line.starts_with('#')) {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.split_first() {
+                Some((&"hardware", fields)) => profile.hardware = HardwareFingerprint::from_fields(fields),
+                Some((&"result", fields)) => profile.benchmark_results.extend(parse_result(fields)),
+                _ => {}
+            }
+        }
+        profile.update_system_capabilities();
+        profile
+    }
+
+    /// The profile saved by an earlier session, if any
+    #[cfg(feature = "gui")]
+    pub(crate) fn load() -> Self {
+        config_dir()
+            .and_then(|dir| std::fs::read_to_string(dir.join(PROFILE_FILE)).ok())
+            .map(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "gui")]
+    pub(crate) fn save(&self) -> Result<(), String> {
+        let dir = config_dir().ok_or("No config directory available")?;
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create config directory: {}", e))?;
+        std::fs::write(dir.join(PROFILE_FILE), self.to_text())
+            .map_err(|e| format!("Failed to save the performance profile: {}", e))
+    }
+
     pub fn update_system_capabilities(&mut self) {
         if self.benchmark_results.is_empty() {
             return;
@@ -242,9 +436,24 @@ impl PerformanceProfile {
         
         time_per_mp * characteristics.megapixels
     }
+
+    /// Render time for a file whose dimensions can't be read yet, such as an
+    /// on-demand file, from the benchmark's time per megabyte of files in the
+    /// same format (or of all files if none were)
+    pub fn estimate_render_time_from_size(&self, file_size_mb: f64, format: &str) -> Option<f64> {
+        let per_mb = |format: Option<&str>| {
+            let (time, size) = self.benchmark_results.iter()
+                .filter(|r| r.success && format.is_none_or(|format| r.characteristics.format.eq_ignore_ascii_case(format)))
+                .fold((0.0, 0.0), |(time, size), r| (time + r.total_time_ms, size + r.characteristics.file_size_mb));
+            (size > 0.0).then(|| time / size)
+        };
+        per_mb(Some(format)).or_else(|| per_mb(None)).map(|ms_per_mb| ms_per_mb * file_size_mb)
+    }
     
-    pub fn benchmark_safe_images(&mut self, ctx: &egui::Context) -> Vec<BenchmarkResult> {
+    #[cfg(feature = "gui")]
+    pub(crate) fn benchmark_safe_images(&mut self, ctx: &egui::Context, progress: &ProgressSink) -> Vec<BenchmarkResult> {
         let mut results = Vec::new();
+        let reporter = progress.start(OperationKind::Benchmark, "Benchmark");
         
         // Get system performance to determine safe limits
         let cpu_score = run_simple_cpu_benchmark(); 
@@ -253,17 +462,46 @@ impl PerformanceProfile {
         
         // Find safe images to benchmark
         let safe_images = find_safe_benchmark_images(&limits);
+        let total = safe_images.len() as u64;
         
-        for path in safe_images {
-            let result = benchmark_image(&path, ctx);
+        for (i, path) in safe_images.iter().enumerate() {
+            let mut result = benchmark_image(path, ctx);
+            result.hardware_id = self.hardware.as_ref().map(HardwareFingerprint::id);
             results.push(result.clone());
             self.add_benchmark_result(result);
+            reporter.progress(i as u64 + 1, Some(total));
         }
         
+        reporter.finish(format!("{} images benchmarked", results.len()));
         results
     }
 }
 
+/// A result line's fields after `result`
+#[cfg(feature = "gui")]
+fn parse_result(fields: &[&str]) -> Option<BenchmarkResult> {
+    let [hardware_id, format, width, height, file_size_mb, decode, texture, total, success, error] = fields else {
+        return None;
+    };
+    let (width, height): (u32, u32) = (width.parse().ok()?, height.parse().ok()?);
+    Some(BenchmarkResult {
+        characteristics: ImageCharacteristics {
+            file_size_mb: file_size_mb.parse().ok()?,
+            width,
+            height,
+            megapixels: (width as f64 * height as f64) / 1_000_000.0,
+            format: format.to_string(),
+            bit_depth: None,
+        },
+        decode_time_ms: decode.parse().ok()?,
+        texture_creation_time_ms: texture.parse().ok()?,
+        total_time_ms: total.parse().ok()?,
+        success: success.parse().ok()?,
+        error_message: (!error.is_empty()).then(|| error.to_string()),
+        hardware_id: (!hardware_id.is_empty()).then(|| hardware_id.to_string()),
+    })
+}
+
 // Simple benchmark that tests both CPU and storage performance for image viewing
 // Focuses on the actual operations: file I/O, memory allocation, and basic arithmetic
 pub fn run_simple_cpu_benchmark() -> u32 {
@@ -311,18 +549,18 @@ pub fn run_simple_cpu_benchmark() -> u32 {
     // Test 2: Memory allocation and copying (simulates image loading into RAM)
     for _ in 0..5 {
         let mut buffer = vec![0u8; 200_000]; // ~200KB buffer (typical small image)
-        for i in 0..buffer.len() {
-            buffer[i] = (i % 256) as u8;
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            *byte = (i % 256) as u8;
         }
         // Simulate format conversion (like JPEG -> RGBA)
         let mut output = vec![0u32; buffer.len() / 4];
-        for i in 0..output.len() {
+        for (i, pixel) in output.iter_mut().enumerate() {
             let base = i * 4;
             if base + 3 < buffer.len() {
-                output[i] = ((buffer[base] as u32) << 24) |
-                           ((buffer[base + 1] as u32) << 16) |
-                           ((buffer[base + 2] as u32) << 8) |
-                           (buffer[base + 3] as u32);
+                *pixel = ((buffer[base] as u32) << 24) |
+                         ((buffer[base + 1] as u32) << 16) |
+                         ((buffer[base + 2] as u32) << 8) |
+                         (buffer[base + 3] as u32);
             }
         }
         score += (output.iter().map(|&x| x as u64).sum::<u64>() / 10_000_000) as u32;
@@ -353,7 +591,7 @@ pub fn run_simple_cpu_benchmark() -> u32 {
     let final_score = (score as f64 * time_factor) as u32;
     
     // Clamp score to reasonable range
-    final_score.min(15_000).max(50)
+    final_score.clamp(50, 15_000)
 }
 
 // Function to get performance baseline based on current system performance
@@ -369,12 +607,10 @@ pub fn find_safe_benchmark_images(limits: &BenchmarkLimits) -> Vec<PathBuf> {
     // Check assets folder first
     for ext in DEFAULT_SUPPORTED_FORMATS.iter() {
         if let Ok(paths) = glob(&format!("assets/*.{}", ext)) {
-            for entry in paths {
-                if let Ok(path) = entry {
-                    let file_info = FileInfo::new(path.clone());
-                    if !file_info.will_trigger_download() {
-                        candidates.push(path);
-                    }
+            for path in paths.flatten() {
+                let file_info = FileInfo::new(path.clone());
+                if !file_info.will_trigger_download() {
+                    candidates.push(path);
                 }
             }
         }
@@ -384,12 +620,10 @@ pub fn find_safe_benchmark_images(limits: &BenchmarkLimits) -> Vec<PathBuf> {
     if candidates.is_empty() {
         for ext in DEFAULT_SUPPORTED_FORMATS.iter() {
             if let Ok(paths) = glob(&format!("*.{}", ext)) {
-                for entry in paths {
-                    if let Ok(path) = entry {
-                        let file_info = FileInfo::new(path.clone());
-                        if !file_info.will_trigger_download() {
-                            candidates.push(path);
-                        }
+                for path in paths.flatten() {
+                    let file_info = FileInfo::new(path.clone());
+                    if !file_info.will_trigger_download() {
+                        candidates.push(path);
                     }
                 }
             }
@@ -420,14 +654,13 @@ pub fn find_safe_benchmark_images(limits: &BenchmarkLimits) -> Vec<PathBuf> {
                     
                     // Try to get basic image info without fully loading
                     // Even opening the file might trigger downloads for some on-demand configurations
-                    if let Ok(reader) = ImageReader::open(&path) {
-                        if let Ok((width, height)) = reader.into_dimensions() {
-                            let megapixels = (width as f64 * height as f64) / 1_000_000.0;
-                            
-                            // Only include images within safe pixel limits
-                            if megapixels <= limits.max_megapixels {
-                                return Some((path, file_size_mb));
-                            }
+                    if let Ok(reader) = ImageReader::open(&path)
+                        && let Ok((width, height)) = reader.into_dimensions() {
+                        let megapixels = (width as f64 * height as f64) / 1_000_000.0;
+                        
+                        // Only include images within safe pixel limits
+                        if megapixels <= limits.max_megapixels {
+                            return Some((path, file_size_mb));
                         }
                     }
                 }
@@ -447,6 +680,7 @@ pub fn find_safe_benchmark_images(limits: &BenchmarkLimits) -> Vec<PathBuf> {
         .collect()
 }
 
+#[cfg(feature = "gui")]
 pub fn benchmark_image(path: &PathBuf, ctx: &egui::Context) -> BenchmarkResult {
     // Skip on-demand files during benchmarking to avoid triggering downloads
     let file_info = FileInfo::new(path.clone());
@@ -475,6 +709,7 @@ pub fn benchmark_image(path: &PathBuf, ctx: &egui::Context) -> BenchmarkResult {
             total_time_ms: 0.0,
             success: false,
             error_message: Some("Skipped on-demand file to avoid triggering download during benchmark".to_string()),
+            hardware_id: None,
         };
     }
     
@@ -512,6 +747,7 @@ pub fn benchmark_image(path: &PathBuf, ctx: &egui::Context) -> BenchmarkResult {
                     total_time_ms: total_time.as_secs_f64() * 1000.0,
                     success: true,
                     error_message: None,
+                    hardware_id: None,
                 },
                 Err(e) => BenchmarkResult {
                     characteristics,
@@ -520,6 +756,7 @@ pub fn benchmark_image(path: &PathBuf, ctx: &egui::Context) -> BenchmarkResult {
                     total_time_ms: total_time.as_secs_f64() * 1000.0,
                     success: false,
                     error_message: Some(format!("Texture creation failed: {}", e)),
+                    hardware_id: None,
                 }
             }
         }
@@ -545,12 +782,14 @@ pub fn benchmark_image(path: &PathBuf, ctx: &egui::Context) -> BenchmarkResult {
                 total_time_ms: total_time.as_secs_f64() * 1000.0,
                 success: false,
                 error_message: Some(e),
+                hardware_id: None,
             }
         }
     }
 }
 
-fn try_create_texture(img: &image::DynamicImage, ctx: &egui::Context, path: &PathBuf) -> Result<TextureHandle, String> {
+#[cfg(feature = "gui")]
+fn try_create_texture(img: &image::DynamicImage, ctx: &egui::Context, path: &Path) -> Result<TextureHandle, String> {
     let size = [img.width() as _, img.height() as _];
     let rgba = img.to_rgba8();
     let pixels = rgba.as_flat_samples();
@@ -564,3 +803,99 @@ fn try_create_texture(img: &image::DynamicImage, ctx: &egui::Context, path: &Pat
         Default::default(),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint(gpu: &str) -> HardwareFingerprint {
+        HardwareFingerprint {
+            cpu_model: "Test CPU".to_string(),
+            cpu_cores: 8,
+            total_memory_mb: 16_000,
+            gpu_adapter: Some(gpu.to_string()),
+            os_version: "Test OS 1.0".to_string(),
+        }
+    }
+
+    fn result() -> BenchmarkResult {
+        BenchmarkResult {
+            characteristics: ImageCharacteristics {
+                file_size_mb: 1.0,
+                width: 1000,
+                height: 1000,
+                megapixels: 1.0,
+                format: "png".to_string(),
+                bit_depth: None,
+            },
+            decode_time_ms: 10.0,
+            texture_creation_time_ms: 5.0,
+            total_time_ms: 15.0,
+            success: true,
+            error_message: None,
+            hardware_id: None,
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_id_is_stable() {
+        let a = fingerprint("GPU A");
+        let mut updated_os = a.clone();
+        updated_os.os_version = "Test OS 2.0".to_string();
+
+        assert_eq!(a.id(), fingerprint("GPU A").id());
+        assert_eq!(a.id(), updated_os.id());
+        assert_ne!(a.id(), fingerprint("GPU B").id());
+    }
+
+    #[test]
+    fn test_profile_discards_results_from_other_hardware() {
+        let mut profile = PerformanceProfile::default();
+        assert!(!profile.set_hardware(fingerprint("GPU A")));
+        profile.add_benchmark_result(result());
+        assert_eq!(profile.benchmark_results[0].hardware_id, Some(fingerprint("GPU A").id()));
+
+        assert!(!profile.set_hardware(fingerprint("GPU A")));
+        assert_eq!(profile.benchmark_results.len(), 1);
+
+        assert!(profile.set_hardware(fingerprint("GPU B")));
+        assert!(profile.benchmark_results.is_empty());
+        assert_eq!(profile.hardware, Some(fingerprint("GPU B")));
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_saved_profile_keeps_its_hardware() {
+        let mut profile = PerformanceProfile::default();
+        profile.set_hardware(fingerprint("GPU A"));
+        let mut failed = result();
+        failed.success = false;
+        failed.error_message = Some("Out of\tmemory".to_string());
+        profile.add_benchmark_result(result());
+        profile.add_benchmark_result(failed);
+
+        let mut loaded = PerformanceProfile::parse(&profile.to_text());
+        assert_eq!(loaded.hardware, Some(fingerprint("GPU A")));
+        assert_eq!(loaded.benchmark_results.len(), 2);
+        assert_eq!(loaded.benchmark_results[1].error_message.as_deref(), Some("Out of memory"));
+        assert_eq!(loaded.system_capabilities.avg_decode_time_per_mp, 10.0);
+        assert!(!loaded.set_hardware(fingerprint("GPU A")));
+        assert!(loaded.set_hardware(fingerprint("GPU B")));
+    }
+
+    #[test]
+    fn test_render_time_from_file_size() {
+        let mut profile = PerformanceProfile::default();
+        assert_eq!(profile.estimate_render_time_from_size(4.0, "png"), None);
+        profile.add_benchmark_result(result());
+        let mut jpeg = result();
+        jpeg.characteristics.format = "jpg".to_string();
+        jpeg.characteristics.file_size_mb = 0.5;
+        profile.add_benchmark_result(jpeg);
+
+        assert_eq!(profile.estimate_render_time_from_size(4.0, "PNG"), Some(60.0));
+        assert_eq!(profile.estimate_render_time_from_size(2.0, "jpg"), Some(60.0));
+        // Formats the benchmark didn't decode use every result
+        assert_eq!(profile.estimate_render_time_from_size(3.0, "webp"), Some(60.0));
+    }
+}
@@ -0,0 +1,277 @@
+//! Size-limited caches and a registry to inspect and clear them
+//!
+//! Caches are [`LruCache`]s shared through [`SharedCache`]. Registering one
+//! with the [`CacheRegistry`] makes it appear in the Caches settings section
+//! with its current size, a size limit and a Clear button.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+/// What the registry needs to show and manage a cache
+pub trait ManagedCache: Send {
+    fn name(&self) -> &str;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn size_bytes(&self) -> usize;
+    fn limit_bytes(&self) -> usize;
+    /// Change the limit, evicting entries until the cache fits
+    fn set_limit_bytes(&mut self, limit: usize);
+    fn clear(&mut self);
+}
+
+struct CacheEntry<V> {
+    value: V,
+    size: usize,
+    last_used: u64,
+}
+
+/// Lookups and evictions since a cache was created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheCounters {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped to stay within the limit
+    pub evictions: u64,
+}
+
+/// Cache that evicts the least recently used entries once over its byte limit
+pub struct LruCache<K, V> {
+    name: String,
+    entries: HashMap<K, CacheEntry<V>>,
+    limit_bytes: usize,
+    size_bytes: usize,
+    tick: u64,
+    counters: CacheCounters,
+}
+
+pub type SharedCache<K, V> = Arc<Mutex<LruCache<K, V>>>;
+
+impl<K: Eq + Hash + Clone, V> LruCache<K, V> {
+    pub fn new(name: impl Into<String>, limit_bytes: usize) -> Self {
+        Self {
+            name: name.into(),
+            entries: HashMap::new(),
+            limit_bytes,
+            size_bytes: 0,
+            tick: 0,
+            counters: CacheCounters::default(),
+        }
+    }
+
+    pub fn shared(name: impl Into<String>, limit_bytes: usize) -> SharedCache<K, V> {
+        Arc::new(Mutex::new(Self::new(name, limit_bytes)))
+    }
+
+    /// Look up an entry, marking it as recently used
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.tick += 1;
+        let tick = self.tick;
+        let entry = self.entries.get_mut(key);
+        match entry {
+            Some(_) => self.counters.hits += 1,
+            None => self.counters.misses += 1,
+        }
+        entry.map(|entry| {
+            entry.last_used = tick;
+            &entry.value
+        })
+    }
+
+    pub fn counters(&self) -> CacheCounters {
+        self.counters
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Store an entry of `size` bytes. Entries bigger than the whole limit aren't kept.
+    pub fn insert(&mut self, key: K, value: V, size: usize) {
+        self.remove(&key);
+        if size > self.limit_bytes {
+            return;
+        }
+        self.tick += 1;
+        self.entries.insert(key, CacheEntry { value, size, last_used: self.tick });
+        self.size_bytes += size;
+        self.evict_to(self.limit_bytes);
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let entry = self.entries.remove(key)?;
+        self.size_bytes -= entry.size;
+        Some(entry.value)
+    }
+
+    fn evict_to(&mut self, limit: usize) {
+        while self.size_bytes > limit {
+            let Some(oldest) = self.entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+            self.counters.evictions += 1;
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone + Send, V: Send> ManagedCache for LruCache<K, V> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn size_bytes(&self) -> usize {
+        self.size_bytes
+    }
+
+    fn limit_bytes(&self) -> usize {
+        self.limit_bytes
+    }
+
+    fn set_limit_bytes(&mut self, limit: usize) {
+        self.limit_bytes = limit;
+        self.evict_to(limit);
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.size_bytes = 0;
+    }
+}
+
+/// Snapshot of one registered cache
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheSummary {
+    pub name: String,
+    pub entries: usize,
+    pub size_bytes: usize,
+    pub limit_bytes: usize,
+}
+
+/// Every cache in the app, in registration order
+#[derive(Default, Clone)]
+pub struct CacheRegistry {
+    caches: Vec<Arc<Mutex<dyn ManagedCache>>>,
+}
+
+impl CacheRegistry {
+    pub fn register<C: ManagedCache + 'static>(&mut self, cache: Arc<Mutex<C>>) {
+        self.caches.push(cache);
+    }
+
+    /// Create a cache and register it in one step
+    pub fn create<K, V>(&mut self, name: impl Into<String>, limit_bytes: usize) -> SharedCache<K, V>
+    where
+        K: Eq + Hash + Clone + Send + 'static,
+        V: Send + 'static,
+    {
+        let cache = LruCache::shared(name, limit_bytes);
+        self.register(Arc::clone(&cache));
+        cache
+    }
+
+    pub fn summaries(&self) -> Vec<CacheSummary> {
+        self.caches
+            .iter()
+            .map(|cache| {
+                let cache = cache.lock().unwrap();
+                CacheSummary {
+                    name: cache.name().to_string(),
+                    entries: cache.len(),
+                    size_bytes: cache.size_bytes(),
+                    limit_bytes: cache.limit_bytes(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.caches.iter().map(|cache| cache.lock().unwrap().size_bytes()).sum()
+    }
+
+    /// Set the limit of the cache at `index` (as listed by `summaries`)
+    pub fn set_limit(&self, index: usize, limit_bytes: usize) {
+        if let Some(cache) = self.caches.get(index) {
+            cache.lock().unwrap().set_limit_bytes(limit_bytes);
+        }
+    }
+
+    pub fn clear(&self, index: usize) {
+        if let Some(cache) = self.caches.get(index) {
+            cache.lock().unwrap().clear();
+        }
+    }
+
+    /// Whether every cache is empty, leaving nothing to clear
+    pub fn all_empty(&self) -> bool {
+        self.caches.iter().all(|cache| cache.lock().unwrap().is_empty())
+    }
+
+    pub fn clear_all(&self) {
+        for cache in &self.caches {
+            cache.lock().unwrap().clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_evicts_least_recently_used() {
+        let mut cache = LruCache::new("test", 30);
+        cache.insert("a", 1, 10);
+        cache.insert("b", 2, 10);
+        cache.insert("c", 3, 10);
+        // Touching "a" makes "b" the oldest
+        assert_eq!(cache.get(&"a"), Some(&1));
+        cache.insert("d", 4, 10);
+
+        assert!(!cache.contains_key(&"b"));
+        assert!(cache.contains_key(&"a") && cache.contains_key(&"c") && cache.contains_key(&"d"));
+        assert_eq!(cache.size_bytes(), 30);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.counters(), CacheCounters { hits: 1, misses: 1, evictions: 1 });
+
+        // Too big to ever fit
+        cache.insert("huge", 5, 31);
+        assert!(!cache.contains_key(&"huge"));
+
+        cache.set_limit_bytes(10);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains_key(&"d"));
+    }
+
+    #[test]
+    fn test_registry_lists_and_clears_caches() {
+        let mut registry = CacheRegistry::default();
+        let textures = registry.create::<String, Vec<u8>>("Textures", 1000);
+        let icons = LruCache::<u32, ()>::shared("Icons", 100);
+        registry.register(Arc::clone(&icons));
+
+        textures.lock().unwrap().insert("a".to_string(), vec![0; 400], 400);
+        icons.lock().unwrap().insert(1, (), 20);
+
+        let summaries = registry.summaries();
+        assert_eq!(summaries.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), ["Textures", "Icons"]);
+        assert_eq!(summaries[0].size_bytes, 400);
+        assert_eq!(registry.total_bytes(), 420);
+
+        registry.clear(0);
+        assert_eq!(textures.lock().unwrap().len(), 0);
+        registry.set_limit(1, 10);
+        assert_eq!(registry.total_bytes(), 0);
+        assert_eq!(registry.summaries()[1].limit_bytes, 10);
+    }
+}
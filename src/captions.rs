@@ -0,0 +1,244 @@
+//! Caption and keyword sidecars written by tagging tools
+//!
+//! Tools that caption or tag images usually write a file next to each image:
+//! `photo.txt` or `photo.caption` with a caption or comma-separated tags, or
+//! `photo.json` / `photo.jpg.json` with caption and tag fields. They are only
+//! read here; nothing is generated.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::file_locality::FileInfo;
+use crate::metadata_providers::{MetadataProvider, MetadataSection, MetadataSource};
+use crate::sidecars;
+
+/// Sidecars larger than this aren't captions
+const MAX_SIDECAR_BYTES: u64 = 256 * 1024;
+
+/// JSON fields holding the caption, in order of preference
+const CAPTION_FIELDS: [&str; 4] = ["caption", "description", "text", "title"];
+/// JSON fields holding keywords
+const KEYWORD_FIELDS: [&str; 3] = ["tags", "keywords", "labels"];
+/// Sidecar extensions, in order of preference
+const SIDECAR_EXTENSIONS: [&str; 3] = ["json", "txt", "caption"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Caption {
+    pub text: Option<String>,
+    pub keywords: Vec<String>,
+}
+
+impl Caption {
+    fn is_empty(&self) -> bool {
+        self.text.is_none() && self.keywords.is_empty()
+    }
+
+    /// Read a caption from a JSON sidecar
+    pub fn from_json(json: &str) -> Self {
+        let text = CAPTION_FIELDS.iter().find_map(|field| json_string(json, field)).filter(|text| !text.is_empty());
+        let keywords = KEYWORD_FIELDS
+            .iter()
+            .find_map(|field| json_string_list(json, field))
+            .unwrap_or_default();
+        Self { text, keywords }
+    }
+
+    /// Read a caption from a text sidecar: a line of short comma-separated
+    /// items is taken as tags, anything else as the caption
+    pub fn from_text(text: &str) -> Self {
+        let mut caption = Self::default();
+        let mut lines = Vec::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let lower = line.to_lowercase();
+            if let Some(tags) = ["tags:", "keywords:"].iter().find_map(|prefix| lower.starts_with(prefix).then(|| &line[prefix.len()..])) {
+                caption.keywords.extend(split_tags(tags));
+            } else if looks_like_tags(line) {
+                caption.keywords.extend(split_tags(line));
+            } else {
+                lines.push(line);
+            }
+        }
+        if !lines.is_empty() {
+            caption.text = Some(lines.join("\n"));
+        }
+        caption
+    }
+
+    /// Whether the caption or a keyword contains `term`, which must be lowercase
+    pub fn contains(&self, term: &str) -> bool {
+        self.text.as_ref().is_some_and(|text| text.to_lowercase().contains(term))
+            || self.keywords.iter().any(|keyword| keyword.to_lowercase().contains(term))
+    }
+}
+
+fn looks_like_tags(line: &str) -> bool {
+    let parts: Vec<&str> = line.split(',').map(str::trim).collect();
+    parts.len() >= 2 && parts.iter().all(|part| part.split_whitespace().count() <= 4) && !line.trim_end().ends_with('.')
+}
+
+fn split_tags(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(',').map(str::trim).filter(|tag| !tag.is_empty()).map(str::to_string)
+}
+
+fn unescape_json(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('u') => {
+                let code: String = chars.by_ref().take(4).collect();
+                out.extend(u32::from_str_radix(&code, 16).ok().and_then(char::from_u32));
+            }
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+const JSON_STRING: &str = r#""((?:[^"\\]|\\.)*)""#;
+
+fn json_string(json: &str, field: &str) -> Option<String> {
+    let pattern = format!(r#""{}"\s*:\s*{}"#, regex::escape(field), JSON_STRING);
+    Regex::new(&pattern).ok()?.captures(json).map(|captures| unescape_json(&captures[1]))
+}
+
+/// A list of strings, or one comma-separated string
+fn json_string_list(json: &str, field: &str) -> Option<Vec<String>> {
+    if let Some(text) = json_string(json, field) {
+        return Some(split_tags(&text).collect());
+    }
+    let pattern = format!(r#""{}"\s*:\s*\[([^\]]*)\]"#, regex::escape(field));
+    let items = Regex::new(&pattern).ok()?.captures(json)?.get(1)?.as_str().to_string();
+    let string = Regex::new(JSON_STRING).ok()?;
+    Some(string.captures_iter(&items).map(|captures| unescape_json(&captures[1])).collect())
+}
+
+fn read_sidecar(path: &Path) -> Option<Caption> {
+    let metadata = std::fs::metadata(path).ok()?;
+    // Like the images themselves, cloud placeholders are never downloaded just to be read
+    if metadata.len() > MAX_SIDECAR_BYTES || FileInfo::new(path.to_path_buf()).will_trigger_download() {
+        return None;
+    }
+    let text = std::fs::read_to_string(path).ok()?;
+    let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let caption = if is_json { Caption::from_json(&text) } else { Caption::from_text(&text) };
+    (!caption.is_empty()).then_some(caption)
+}
+
+/// Caption from the first sidecar next to `image` that has one
+pub fn load_caption(image: &Path) -> Option<Caption> {
+    let dir = image.parent()?;
+    SIDECAR_EXTENSIONS
+        .iter()
+        .flat_map(|extension| sidecars::sidecar_names(image, extension))
+        .find_map(|name| read_sidecar(&dir.join(name)))
+}
+
+/// Captions of a folder's images, for searching the image list
+#[derive(Debug, Clone, Default)]
+pub struct CaptionIndex {
+    captions: HashMap<PathBuf, Caption>,
+}
+
+impl CaptionIndex {
+    /// Captions of `images` from their sidecars in `dir`
+    pub fn scan(dir: &Path, images: &[FileInfo]) -> Self {
+        let captions = sidecars::scan(dir, images, &SIDECAR_EXTENSIONS, read_sidecar).into_iter().collect();
+        Self { captions }
+    }
+
+    /// Read the sidecar of one image, such as a file added to the folder
+    pub fn refresh(&mut self, image: &Path) {
+        match load_caption(image) {
+            Some(caption) => self.captions.insert(image.to_path_buf(), caption),
+            None => self.captions.remove(image),
+        };
+    }
+
+    pub fn get(&self, image: &Path) -> Option<&Caption> {
+        self.captions.get(image)
+    }
+
+    pub fn len(&self) -> usize {
+        self.captions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.captions.is_empty()
+    }
+}
+
+/// Whether every word of `query` is in the file name, caption or keywords
+pub fn matches_query(file_name: &str, caption: Option<&Caption>, query: &str) -> bool {
+    let file_name = file_name.to_lowercase();
+    query
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .all(|term| file_name.contains(&term) || caption.is_some_and(|caption| caption.contains(&term)))
+}
+
+/// Shows caption sidecars in the metadata panel
+pub struct CaptionProvider;
+
+impl MetadataProvider for CaptionProvider {
+    fn sections(&self, source: &MetadataSource) -> Vec<MetadataSection> {
+        let Some(caption) = load_caption(source.path) else {
+            return Vec::new();
+        };
+        let mut section = MetadataSection::new("Caption");
+        if let Some(text) = caption.text {
+            section.push("Caption", text);
+        }
+        if !caption.keywords.is_empty() {
+            section.push("Keywords", caption.keywords.join(", "));
+        }
+        vec![section]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_parse_sidecars() {
+        let caption = Caption::from_json(r#"{"caption": "A boat at \"dusk\"", "tags": ["harbour", "boat"], "score": 0.9}"#);
+        assert_eq!(caption.text.as_deref(), Some("A boat at \"dusk\""));
+        assert_eq!(caption.keywords, ["harbour", "boat"]);
+        assert_eq!(Caption::from_json(r#"{"description": "x", "keywords": "a, b"}"#).keywords, ["a", "b"]);
+
+        assert_eq!(Caption::from_text("1girl, red hair, outdoors\n").keywords, ["1girl", "red hair", "outdoors"]);
+        let caption = Caption::from_text("A lighthouse on a rocky coast, seen from the sea.\nTags: coast, lighthouse");
+        assert_eq!(caption.text.as_deref(), Some("A lighthouse on a rocky coast, seen from the sea."));
+        assert_eq!(caption.keywords, ["coast", "lighthouse"]);
+    }
+
+    #[test]
+    fn test_index_and_query() {
+        let dir = TempDir::new("captions");
+        std::fs::write(dir.join("a.jpg"), b"").unwrap();
+        std::fs::write(dir.join("b.png"), b"").unwrap();
+        std::fs::write(dir.join("a.txt"), "harbour, boat").unwrap();
+        std::fs::write(dir.join("b.png.json"), r#"{"caption": "Mountain lake"}"#).unwrap();
+
+        let images: Vec<FileInfo> = ["a.jpg", "b.png"].iter().map(|name| FileInfo::new(dir.join(name))).collect();
+        let index = CaptionIndex::scan(&dir, &images);
+        assert_eq!(index.len(), 2);
+        let a = index.get(&dir.join("a.jpg"));
+        assert!(matches_query("a.jpg", a, "Boat"));
+        assert!(matches_query("a.jpg", a, "a.jpg harbour"));
+        assert!(!matches_query("a.jpg", a, "lake"));
+        assert!(matches_query("b.png", index.get(&dir.join("b.png")), "lake"));
+        assert_eq!(load_caption(&dir.join("b.png")).unwrap().text.as_deref(), Some("Mountain lake"));
+    }
+}
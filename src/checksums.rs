@@ -0,0 +1,209 @@
+//! File checksums and verification against `.sha256` manifests
+//!
+//! Both SHA-256 and BLAKE3 are computed in one pass over the file. SHA-256 is
+//! what `sha256sum` writes, so it is the one checked against manifests: any
+//! `*.sha256` file in the folder, either in `sha256sum` format (`<hash>
+//! <name>` per line) or holding just the hash of the file it is named after
+//! (`photo.jpg.sha256`). Names in a manifest are relative to its folder, and
+//! files that two manifests disagree about are reported as conflicts.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChecksums {
+    /// Lowercase hex
+    pub sha256: String,
+    pub blake3: String,
+}
+
+/// A file's checksums and their verification, or why it couldn't be read
+pub type ChecksumUpdate = (PathBuf, Result<(FileChecksums, Verification), String>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verification {
+    Verified,
+    /// The manifest lists a different hash
+    Mismatch { expected: String },
+    /// No manifest in the folder mentions the file
+    Unlisted,
+    /// The folder's manifests list different hashes for the file
+    Conflict { manifests: Vec<String> },
+}
+
+impl Verification {
+    /// The outcome for `checksums` given the manifest's hash for the file
+    pub fn check(checksums: &FileChecksums, expected: Option<&str>) -> Self {
+        match expected {
+            None => Verification::Unlisted,
+            Some(expected) if expected.eq_ignore_ascii_case(&checksums.sha256) => Verification::Verified,
+            Some(expected) => Verification::Mismatch { expected: expected.to_lowercase() },
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Verification::Verified => "Matches the folder's .sha256 manifest".to_string(),
+            Verification::Mismatch { expected } => format!("Doesn't match the folder's .sha256 manifest, which lists {}", expected),
+            Verification::Unlisted => "Not listed in a .sha256 manifest".to_string(),
+            Verification::Conflict { manifests } => format!("The folder's manifests disagree about this file: {}", manifests.join(", ")),
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// SHA-256 and BLAKE3 of a file's contents
+pub fn checksum_file(path: &Path) -> Result<FileChecksums, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut sha256 = Sha256::new();
+    let mut blake3 = blake3::Hasher::new();
+    let mut buffer = vec![0; 256 * 1024];
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        sha256.update(&buffer[..read]);
+        blake3.update(&buffer[..read]);
+    }
+    Ok(FileChecksums {
+        sha256: hex(&sha256.finalize()),
+        blake3: blake3.finalize().to_hex().to_string(),
+    })
+}
+
+/// Hashes listed in one manifest by file, resolved against `dir`, the
+/// manifest's folder. A line without a name applies to `own_name`, the file
+/// the manifest is named after.
+fn parse_manifest(text: &str, dir: &Path, own_name: Option<&str>) -> HashMap<PathBuf, String> {
+    let mut hashes = HashMap::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let (hash, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            continue;
+        }
+        // `*` marks binary mode in sha256sum output
+        let name = name.trim_start().trim_start_matches('*');
+        let name = if name.is_empty() { own_name } else { Some(name) };
+        if let Some(name) = name {
+            // `./photo.jpg` is the same file as `photo.jpg`
+            let relative: PathBuf = Path::new(name).components().filter(|part| *part != Component::CurDir).collect();
+            hashes.insert(dir.join(relative), hash.to_lowercase());
+        }
+    }
+    hashes
+}
+
+/// SHA-256 hashes from the `.sha256` manifests of a folder
+#[derive(Debug, Default)]
+pub struct Manifests {
+    hashes: HashMap<PathBuf, String>,
+    /// Files the manifests disagree about, with the manifests' names
+    conflicts: HashMap<PathBuf, Vec<String>>,
+}
+
+impl Manifests {
+    /// How `checksums` of the file at `path` compare to the manifests
+    pub fn verify(&self, path: &Path, checksums: &FileChecksums) -> Verification {
+        match self.conflicts.get(path) {
+            Some(manifests) => Verification::Conflict { manifests: manifests.clone() },
+            None => Verification::check(checksums, self.hashes.get(path).map(String::as_str)),
+        }
+    }
+}
+
+/// SHA-256 hashes from every `.sha256` manifest in `dir`
+pub fn read_manifests(dir: &Path) -> Manifests {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Manifests::default();
+    };
+    let mut manifests = Manifests::default();
+    // Which manifest listed each hash first, to name both when another disagrees
+    let mut listed_by: HashMap<PathBuf, String> = HashMap::new();
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+    for path in paths {
+        if !path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("sha256")) {
+            continue;
+        }
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let manifest = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let own_name = path.file_stem().map(|stem| stem.to_string_lossy().to_string());
+        for (file, hash) in parse_manifest(&text, dir, own_name.as_deref()) {
+            match manifests.hashes.get(&file) {
+                Some(listed) if *listed != hash => {
+                    let conflict = manifests.conflicts.entry(file.clone())
+                        .or_insert_with(|| vec![listed_by[&file].clone()]);
+                    conflict.push(manifest.clone());
+                }
+                Some(_) => {}
+                None => {
+                    listed_by.insert(file.clone(), manifest.clone());
+                    manifests.hashes.insert(file, hash);
+                }
+            }
+        }
+    }
+    manifests
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn test_checksum_file() {
+        let dir = TempDir::new("checksums");
+        let path = dir.join("abc.txt");
+        std::fs::write(&path, "abc").unwrap();
+        let checksums = checksum_file(&path).unwrap();
+        assert_eq!(checksums.sha256, ABC_SHA256);
+        assert_eq!(checksums.blake3, "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85");
+        assert_eq!(Verification::check(&checksums, Some(&ABC_SHA256.to_uppercase())), Verification::Verified);
+        assert_eq!(Verification::check(&checksums, None), Verification::Unlisted);
+        assert!(matches!(Verification::check(&checksums, Some(&"0".repeat(64))), Verification::Mismatch { .. }));
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let dir = Path::new("shoot");
+        let text = format!("# made by sha256sum\n{0}  photo.jpg\n{0} *raw/IMG_1.CR2\n{0}  ./edit.png\nnot a hash  x.jpg\n", ABC_SHA256);
+        let hashes = parse_manifest(&text, dir, Some("SHA256SUMS"));
+        assert_eq!(hashes.len(), 3);
+        assert_eq!(hashes[&dir.join("photo.jpg")], ABC_SHA256);
+        assert_eq!(hashes[&dir.join("edit.png")], ABC_SHA256);
+        // Only the file in raw/ is meant, not one of the same name in the folder
+        assert_eq!(hashes[&dir.join("raw").join("IMG_1.CR2")], ABC_SHA256);
+        assert!(!hashes.contains_key(&dir.join("IMG_1.CR2")));
+
+        // photo.jpg.sha256 holding only the hash
+        let hashes = parse_manifest(&format!("{}\n", ABC_SHA256.to_uppercase()), dir, Some("photo.jpg"));
+        assert_eq!(hashes[&dir.join("photo.jpg")], ABC_SHA256);
+    }
+
+    #[test]
+    fn test_conflicting_manifests() {
+        let dir = TempDir::new("checksum_manifests");
+        let other = "0".repeat(64);
+        std::fs::write(dir.join("SHA256SUMS.sha256"), format!("{}  a.txt\n{}  b.txt\n", ABC_SHA256, ABC_SHA256)).unwrap();
+        std::fs::write(dir.join("a.txt.sha256"), format!("{}\n", other)).unwrap();
+        let manifests = read_manifests(&dir);
+        let checksums = FileChecksums { sha256: ABC_SHA256.to_string(), blake3: String::new() };
+        assert_eq!(manifests.verify(&dir.join("a.txt"), &checksums), Verification::Conflict {
+            manifests: vec!["SHA256SUMS.sha256".to_string(), "a.txt.sha256".to_string()],
+        });
+        assert_eq!(manifests.verify(&dir.join("b.txt"), &checksums), Verification::Verified);
+        assert_eq!(manifests.verify(&dir.join("c.txt"), &checksums), Verification::Unlisted);
+    }
+}
@@ -0,0 +1,611 @@
+//! Command-line subcommands that run without opening a window
+//!
+//! `image_previewer recolor --color #336699 --out dir <glob>...` writes
+//! recolored copies of SVGs using the same rules as the viewer.
+//! `image_previewer register` / `unregister` add or remove the Explorer
+//! integration. `bench`, `convert` and `locality` run the benchmark, the
+//! decoders and the cloud file checks for scripts and CI. Any other argument
+//! is a file or folder to open in the window.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "gui")]
+use eframe::egui;
+use glob::glob;
+
+#[cfg(feature = "gui")]
+use crate::benchmark;
+use crate::benchmark::{BenchmarkResult, SystemPerformanceCategory};
+use crate::file_locality::{FileInfo, FileLocalityStatus};
+use crate::image_processing::{convert_image, recolor_svg};
+use crate::settings::ImageLoadingSettings;
+use crate::shell_integration;
+
+pub const RECOLOR_USAGE: &str = "\
+Usage: image_previewer recolor --color <#rrggbb> --out <dir> <file or glob>...
+
+Writes a recolored copy of each SVG into <dir>, keeping the file name.
+Options:
+  -c, --color <#rrggbb>  Target color (#rgb also accepted)
+  -o, --out <dir>        Output directory (created if missing)
+  -k, --keep <#rrggbb>   Leave this color unchanged (repeatable)
+  --current-color-only   Only replace currentColor, keeping explicit colors
+  -h, --help             Show this help";
+
+pub const BENCH_USAGE: &str = "\
+Usage: image_previewer bench [--json <file>] [<image>...]
+
+Scores the machine and times decoding each image. Without images, the
+bundled assets (or images in the current folder) within the safe limits for
+the machine are used.
+Options:
+  --json <file>  Also write the results as JSON
+  -h, --help     Show this help";
+
+pub const CONVERT_USAGE: &str = "\
+Usage: image_previewer convert <input> <output> [--max-size <pixels>]
+
+Decodes <input> as the viewer does, SVG recoloring aside, and writes it in the
+format named by the extension of <output>.
+Options:
+  --max-size <pixels>  Scale down so neither side exceeds this
+  -h, --help           Show this help";
+
+pub const LOCALITY_USAGE: &str = "\
+Usage: image_previewer locality <folder>
+
+Lists the images in <folder> and whether opening them would download them.";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecolorArgs {
+    pub color: [u8; 3],
+    /// Colors left unchanged
+    pub keep: Vec<[u8; 3]>,
+    pub current_color_only: bool,
+    pub out_dir: PathBuf,
+    pub inputs: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct RecolorSummary {
+    pub written: Vec<PathBuf>,
+    /// Inputs that couldn't be processed, with the reason
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+/// Parse `#rrggbb` or `#rgb` (the `#` is optional)
+pub fn parse_hex_color(value: &str) -> Result<[u8; 3], String> {
+    let hex = value.trim().trim_start_matches('#');
+    let expanded: String = match hex.len() {
+        _ if !hex.is_ascii() => return Err(format!("Invalid color '{}': expected #rrggbb or #rgb", value)),
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 => hex.to_string(),
+        _ => return Err(format!("Invalid color '{}': expected #rrggbb or #rgb", value)),
+    };
+    let channel = |i: usize| u8::from_str_radix(&expanded[i..i + 2], 16)
+        .map_err(|_| format!("Invalid color '{}': expected #rrggbb or #rgb", value));
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Parse the arguments following `recolor`. `Ok(None)` means help was requested.
+pub fn parse_recolor_args(args: &[String]) -> Result<Option<RecolorArgs>, String> {
+    let mut color = None;
+    let mut keep = Vec::new();
+    let mut current_color_only = false;
+    let mut out_dir = None;
+    let mut inputs = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-c" | "--color" => {
+                let value = args.next().ok_or("--color needs a value")?;
+                color = Some(parse_hex_color(value)?);
+            }
+            "-k" | "--keep" => {
+                let value = args.next().ok_or("--keep needs a value")?;
+                keep.push(parse_hex_color(value)?);
+            }
+            "--current-color-only" => current_color_only = true,
+            "-o" | "--out" => {
+                let value = args.next().ok_or("--out needs a value")?;
+                out_dir = Some(PathBuf::from(value));
+            }
+            other if other.starts_with('-') && other.len() > 1 => {
+                return Err(format!("Unknown option '{}'", other));
+            }
+            input => inputs.push(input.to_string()),
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err("No input files given".to_string());
+    }
+    Ok(Some(RecolorArgs {
+        color: color.ok_or("--color is required")?,
+        keep,
+        current_color_only,
+        out_dir: out_dir.ok_or("--out is required")?,
+        inputs,
+    }))
+}
+
+/// Expand file names and glob patterns into SVG paths. Patterns that match
+/// nothing are kept as-is so the missing file gets reported.
+fn expand_inputs(inputs: &[String]) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        let matches: Vec<PathBuf> = glob(input)
+            .map(|paths| paths.flatten().collect())
+            .unwrap_or_default();
+        if matches.is_empty() {
+            paths.push(PathBuf::from(input));
+        } else {
+            paths.extend(matches.into_iter().filter(|p| p.is_file()));
+        }
+    }
+    paths.dedup();
+    paths
+}
+
+/// Where each input is written, or why it can't be: an input may not be
+/// overwritten by its own output, and no two inputs may share an output name
+fn plan_destinations(paths: &[PathBuf], out_dir: &Path) -> Vec<Result<PathBuf, String>> {
+    let out_dir = out_dir.canonicalize().unwrap_or_else(|_| out_dir.to_path_buf());
+    let mut claimed: std::collections::HashMap<String, &Path> = std::collections::HashMap::new();
+    paths.iter().map(|path| {
+        let name = path.file_name().ok_or("Path has no file name")?;
+        let destination = out_dir.join(name);
+        if path.canonicalize().is_ok_and(|input| input == destination) {
+            return Err("Output would overwrite the input; choose another --out directory".to_string());
+        }
+        // Compared ignoring case, as on Windows and macOS file systems
+        let key = name.to_string_lossy().to_lowercase();
+        if let Some(first) = claimed.get(&key) {
+            return Err(format!("{} is also written to {}", first.display(), destination.display()));
+        }
+        claimed.insert(key, path);
+        Ok(destination)
+    }).collect()
+}
+
+fn recolor_file(path: &Path, destination: &Path, settings: &ImageLoadingSettings) -> Result<(), String> {
+    if !path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")) {
+        return Err("Not an SVG file".to_string());
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read SVG file: {}", e))?;
+    std::fs::write(destination, recolor_svg(&content, settings))
+        .map_err(|e| format!("Failed to write {}: {}", destination.display(), e))
+}
+
+/// Recolor every input SVG into the output directory
+pub fn run_recolor(args: &RecolorArgs) -> Result<RecolorSummary, String> {
+    std::fs::create_dir_all(&args.out_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+
+    let settings = ImageLoadingSettings {
+        svg_recolor_enabled: true,
+        svg_target_color: args.color,
+        svg_preserved_colors: args.keep.clone(),
+        svg_recolor_current_color_only: args.current_color_only,
+        ..Default::default()
+    };
+
+    let mut summary = RecolorSummary::default();
+    let paths = expand_inputs(&args.inputs);
+    let destinations = plan_destinations(&paths, &args.out_dir);
+    for (path, destination) in paths.into_iter().zip(destinations) {
+        match destination.and_then(|destination| recolor_file(&path, &destination, &settings).map(|_| destination)) {
+            Ok(destination) => summary.written.push(destination),
+            Err(e) => summary.failed.push((path, e)),
+        }
+    }
+    Ok(summary)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchArgs {
+    pub json: Option<PathBuf>,
+    /// Images to time; the safe defaults when empty
+    pub images: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertArgs {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub max_size: Option<u32>,
+}
+
+/// Parse the arguments following `bench`. `Ok(None)` means help was requested.
+pub fn parse_bench_args(args: &[String]) -> Result<Option<BenchArgs>, String> {
+    let mut json = None;
+    let mut images = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--json" => json = Some(PathBuf::from(args.next().ok_or("--json needs a value")?)),
+            other if other.starts_with('-') && other.len() > 1 => {
+                return Err(format!("Unknown option '{}'", other));
+            }
+            image => images.push(image.to_string()),
+        }
+    }
+    Ok(Some(BenchArgs { json, images }))
+}
+
+/// Parse the arguments following `convert`. `Ok(None)` means help was requested.
+pub fn parse_convert_args(args: &[String]) -> Result<Option<ConvertArgs>, String> {
+    let mut max_size = None;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--max-size" => {
+                let value = args.next().ok_or("--max-size needs a value")?;
+                let size = value.parse().ok().filter(|&size| size > 0)
+                    .ok_or_else(|| format!("Invalid size '{}': expected a number of pixels", value))?;
+                max_size = Some(size);
+            }
+            other if other.starts_with('-') && other.len() > 1 => {
+                return Err(format!("Unknown option '{}'", other));
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+    let [input, output] = <[PathBuf; 2]>::try_from(paths)
+        .map_err(|_| "Expected an input and an output file".to_string())?;
+    Ok(Some(ConvertArgs { input, output, max_size }))
+}
+
+/// Quote `value` as a JSON string
+fn json_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Benchmark results as a JSON document
+pub fn bench_json(cpu_score: u32, results: &[(PathBuf, BenchmarkResult)]) -> String {
+    let category = SystemPerformanceCategory::from_score(cpu_score);
+    let images: Vec<String> = results.iter().map(|(path, result)| {
+        let c = &result.characteristics;
+        format!(
+            "    {{\"path\": {}, \"format\": {}, \"width\": {}, \"height\": {}, \"file_size_mb\": {:.3}, \"decode_ms\": {:.3}, \"texture_ms\": {:.3}, \"total_ms\": {:.3}, \"success\": {}, \"error\": {}}}",
+            json_string(&path.to_string_lossy()), json_string(&c.format), c.width, c.height, c.file_size_mb,
+            result.decode_time_ms, result.texture_creation_time_ms, result.total_time_ms, result.success,
+            result.error_message.as_deref().map_or("null".to_string(), json_string),
+        )
+    }).collect();
+    format!(
+        "{{\n  \"cpu_score\": {},\n  \"category\": {},\n  \"images\": [\n{}\n  ]\n}}\n",
+        cpu_score, json_string(&format!("{:?}", category)), images.join(",\n")
+    )
+}
+
+/// Score the machine and time each image, printing a line per image
+#[cfg(feature = "gui")]
+pub fn run_bench(args: &BenchArgs) -> Result<Vec<(PathBuf, BenchmarkResult)>, String> {
+    let cpu_score = benchmark::run_simple_cpu_benchmark();
+    let category = SystemPerformanceCategory::from_score(cpu_score);
+    println!("CPU score {}: {}", cpu_score, category.description());
+
+    let images = if args.images.is_empty() {
+        benchmark::find_safe_benchmark_images(&category.safe_benchmark_limits())
+    } else {
+        expand_inputs(&args.images)
+    };
+    // Textures are created without a window and never reach a GPU, so no side limit applies
+    let ctx = egui::Context::default();
+    ctx.input_mut(|input| input.max_texture_side = usize::MAX);
+    let results: Vec<(PathBuf, BenchmarkResult)> = images.into_iter().map(|path| {
+        let result = benchmark::benchmark_image(&path, &ctx);
+        match &result.error_message {
+            None => println!(
+                "{}: {}x{} decoded in {:.1} ms, {:.1} ms total",
+                path.display(), result.characteristics.width, result.characteristics.height,
+                result.decode_time_ms, result.total_time_ms
+            ),
+            Some(e) => println!("{}: {}", path.display(), e),
+        }
+        (path, result)
+    }).collect();
+
+    if let Some(json) = &args.json {
+        std::fs::write(json, bench_json(cpu_score, &results))
+            .map_err(|e| format!("Failed to write {}: {}", json.display(), e))?;
+    }
+    Ok(results)
+}
+
+/// Decode the input, scale it down if asked, and write it as the output's format
+pub fn run_convert(args: &ConvertArgs) -> Result<(u32, u32), String> {
+    convert_image(&args.input, &args.output, args.max_size)
+}
+
+/// Locality of each supported image directly inside `folder`, sorted by name
+pub fn run_locality(folder: &Path) -> Result<Vec<FileInfo>, String> {
+    if !folder.is_dir() {
+        return Err(format!("{} is not a folder", folder.display()));
+    }
+    let settings = ImageLoadingSettings::default();
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(folder)
+        .map_err(|e| format!("Failed to read {}: {}", folder.display(), e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| {
+            settings.get_supported_extensions().iter().any(|supported| ext.eq_ignore_ascii_case(supported.as_str()))
+        }))
+        .collect();
+    paths.sort();
+    Ok(paths.into_iter().map(FileInfo::new).collect())
+}
+
+/// Run a subcommand if the arguments name one, returning the process exit
+/// code. `None` means the GUI should start instead.
+pub fn run(args: &[String]) -> Option<i32> {
+    let (command, rest) = args.split_first()?;
+    match command.as_str() {
+        "recolor" => Some(match parse_recolor_args(rest) {
+            Ok(None) => {
+                println!("{}", RECOLOR_USAGE);
+                0
+            }
+            Ok(Some(args)) => match run_recolor(&args) {
+                Ok(summary) => {
+                    for (path, reason) in &summary.failed {
+                        eprintln!("Skipped {}: {}", path.display(), reason);
+                    }
+                    println!("Recolored {} file(s) into {}", summary.written.len(), args.out_dir.display());
+                    if summary.failed.is_empty() { 0 } else { 1 }
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    1
+                }
+            },
+            Err(e) => {
+                eprintln!("Error: {}\n\n{}", e, RECOLOR_USAGE);
+                2
+            }
+        }),
+        #[cfg(feature = "gui")]
+        "bench" => Some(match parse_bench_args(rest) {
+            Ok(None) => {
+                println!("{}", BENCH_USAGE);
+                0
+            }
+            Ok(Some(args)) => match run_bench(&args) {
+                Ok(results) => {
+                    if let Some(json) = &args.json {
+                        println!("Wrote {}", json.display());
+                    }
+                    if results.iter().all(|(_, result)| result.success) { 0 } else { 1 }
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    1
+                }
+            },
+            Err(e) => {
+                eprintln!("Error: {}\n\n{}", e, BENCH_USAGE);
+                2
+            }
+        }),
+        "convert" => Some(match parse_convert_args(rest) {
+            Ok(None) => {
+                println!("{}", CONVERT_USAGE);
+                0
+            }
+            Ok(Some(args)) => match run_convert(&args) {
+                Ok((width, height)) => {
+                    println!("Wrote {} ({}x{})", args.output.display(), width, height);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    1
+                }
+            },
+            Err(e) => {
+                eprintln!("Error: {}\n\n{}", e, CONVERT_USAGE);
+                2
+            }
+        }),
+        "locality" => Some(match rest {
+            [folder] if folder != "-h" && folder != "--help" => match run_locality(Path::new(folder)) {
+                Ok(files) => {
+                    for file in &files {
+                        let name = file.path.file_name().unwrap_or_default().to_string_lossy();
+                        match &file.provider {
+                            Some(provider) => println!("{}\t{}\t{}", file.locality_status.label(), provider.name(), name),
+                            None => println!("{}\t\t{}", file.locality_status.label(), name),
+                        }
+                    }
+                    let local = files.iter().filter(|file| file.locality_status == FileLocalityStatus::Local).count();
+                    println!("{} of {} image(s) are local", local, files.len());
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    1
+                }
+            },
+            [_] => {
+                println!("{}", LOCALITY_USAGE);
+                0
+            }
+            _ => {
+                eprintln!("Error: Expected one folder\n\n{}", LOCALITY_USAGE);
+                2
+            }
+        }),
+        "register" => Some(match shell_integration::register(ImageLoadingSettings::default().get_supported_extensions()) {
+            Ok(count) => {
+                println!("Registered {} for {} image formats", shell_integration::APP_NAME, count);
+                0
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                1
+            }
+        }),
+        "unregister" => Some(match shell_integration::unregister(ImageLoadingSettings::default().get_supported_extensions()) {
+            Ok(()) => {
+                println!("Removed the {} registration", shell_integration::APP_NAME);
+                0
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                1
+            }
+        }),
+        _ => None,
+    }
+}
+
+/// Names handled by [`run`] rather than opened in the window
+const SUBCOMMANDS: [&str; 6] = ["recolor", "bench", "convert", "locality", "register", "unregister"];
+
+/// File or folder to open in the window, as passed by Explorer's "Open with"
+/// and "Send to" entries
+pub fn launch_path(args: &[String]) -> Option<PathBuf> {
+    if args.first().is_some_and(|command| SUBCOMMANDS.contains(&command.as_str())) {
+        return None;
+    }
+    args.iter()
+        .filter(|arg| !arg.starts_with('-'))
+        .map(PathBuf::from)
+        .find(|path| path.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("#336699"), Ok([0x33, 0x66, 0x99]));
+        assert_eq!(parse_hex_color("f80"), Ok([0xff, 0x88, 0x00]));
+        assert!(parse_hex_color("#12345").is_err());
+        assert!(parse_hex_color("#gggggg").is_err());
+    }
+
+    #[test]
+    fn test_parse_recolor_args() {
+        let parsed = parse_recolor_args(&strings(&["--color", "#336699", "-o", "out", "-k", "#fff", "a.svg", "icons/*.svg"])).unwrap();
+        assert_eq!(parsed, Some(RecolorArgs {
+            color: [0x33, 0x66, 0x99],
+            keep: vec![[0xff, 0xff, 0xff]],
+            current_color_only: false,
+            out_dir: PathBuf::from("out"),
+            inputs: strings(&["a.svg", "icons/*.svg"]),
+        }));
+        assert_eq!(parse_recolor_args(&strings(&["--help"])), Ok(None));
+        assert!(parse_recolor_args(&strings(&["--out", "out", "a.svg"])).is_err());
+        assert!(parse_recolor_args(&strings(&["--color", "#000", "--out", "out"])).is_err());
+        assert!(run(&strings(&["view"])).is_none());
+    }
+
+    #[test]
+    fn test_launch_path() {
+        let dir = TempDir::new("cli_launch_path");
+        assert_eq!(launch_path(&strings(&["--flag", "missing.png", &dir.to_string_lossy()])), Some(dir.to_path_buf()));
+        assert_eq!(launch_path(&strings(&["locality", &dir.to_string_lossy()])), None);
+        for command in SUBCOMMANDS {
+            assert_eq!(launch_path(&strings(&[command, "--out", &dir.to_string_lossy(), &dir.to_string_lossy()])), None);
+        }
+    }
+
+    #[test]
+    fn test_parse_bench_and_convert_args() {
+        assert_eq!(parse_bench_args(&strings(&["--json", "out.json"])), Ok(Some(BenchArgs {
+            json: Some(PathBuf::from("out.json")),
+            images: Vec::new(),
+        })));
+        assert_eq!(parse_convert_args(&strings(&["in.png", "out.webp", "--max-size", "2048"])), Ok(Some(ConvertArgs {
+            input: PathBuf::from("in.png"),
+            output: PathBuf::from("out.webp"),
+            max_size: Some(2048),
+        })));
+        assert!(parse_convert_args(&strings(&["in.png"])).is_err());
+        assert!(parse_convert_args(&strings(&["in.png", "out.png", "--max-size", "0"])).is_err());
+        assert_eq!(json_string("a \"b\"\\\n"), r#""a \"b\"\\\u000a""#);
+    }
+
+    #[test]
+    fn test_run_convert_scales_to_max_size() {
+        let root = TempDir::new("cli_convert");
+        let input = root.join("in.png");
+        image::RgbaImage::from_pixel(400, 200, image::Rgba([10, 20, 30, 255])).save(&input).unwrap();
+
+        let output = root.join("out.jpg");
+        let size = run_convert(&ConvertArgs { input, output: output.clone(), max_size: Some(100) }).unwrap();
+        assert_eq!(size, (100, 50));
+        assert_eq!(image::image_dimensions(&output).unwrap(), (100, 50));
+    }
+
+    #[test]
+    fn test_run_recolor_writes_svgs() {
+        let root = TempDir::new("cli_recolor");
+        std::fs::write(root.join("a.svg"), r##"<svg><path fill="#000000"/></svg>"##).unwrap();
+        std::fs::write(root.join("notes.txt"), "not an svg").unwrap();
+        let out_dir = root.join("out");
+
+        let summary = run_recolor(&RecolorArgs {
+            color: [0x33, 0x66, 0x99],
+            keep: Vec::new(),
+            current_color_only: false,
+            out_dir: out_dir.clone(),
+            inputs: vec![root.join("*.svg").to_string_lossy().to_string(), root.join("notes.txt").to_string_lossy().to_string()],
+        }).unwrap();
+
+        assert_eq!(summary.written, vec![out_dir.join("a.svg")]);
+        assert_eq!(summary.failed.len(), 1);
+        let recolored = std::fs::read_to_string(out_dir.join("a.svg")).unwrap();
+        assert!(recolored.contains(r##"fill="#336699""##));
+    }
+
+    #[test]
+    fn test_run_recolor_refuses_to_overwrite() {
+        let root = TempDir::new("cli_recolor_clash");
+        std::fs::create_dir_all(root.join("more")).unwrap();
+        let original = r##"<svg><path fill="#000000"/></svg>"##;
+        std::fs::write(root.join("a.svg"), original).unwrap();
+        std::fs::write(root.join("more").join("A.svg"), original).unwrap();
+        let recolor = |out_dir: PathBuf, inputs: &[PathBuf]| run_recolor(&RecolorArgs {
+            color: [0x33, 0x66, 0x99],
+            keep: Vec::new(),
+            current_color_only: false,
+            out_dir,
+            inputs: inputs.iter().map(|path| path.to_string_lossy().to_string()).collect(),
+        }).unwrap();
+
+        let in_place = recolor(root.join("more").join(".."), &[root.join("a.svg")]);
+        let clash = recolor(root.join("out"), &[root.join("a.svg"), root.join("more").join("A.svg")]);
+        let untouched = std::fs::read_to_string(root.join("a.svg")).unwrap();
+
+        assert!(in_place.written.is_empty());
+        assert!(in_place.failed[0].1.contains("overwrite the input"));
+        assert_eq!(untouched, original);
+        assert_eq!(clash.written.len(), 1);
+        assert_eq!(clash.failed.len(), 1);
+        assert_eq!(clash.failed[0].0, root.join("more").join("A.svg"));
+    }
+}
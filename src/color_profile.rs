@@ -0,0 +1,221 @@
+//! Embedded ICC profiles, for copying and exporting colors faithfully
+//!
+//! Decoded pixels keep the values stored in the file, which are only sRGB if
+//! the file's profile is. Exports of those pixels embed the source profile so
+//! other programs interpret them the same way. The clipboard and composited
+//! views can't carry a profile, so their pixels are converted to sRGB first;
+//! that needs a matrix/TRC profile (Adobe RGB, Display P3, ProPhoto and most
+//! camera and monitor profiles). Other profiles are passed through as is.
+
+use std::io::BufWriter;
+use std::path::Path;
+
+use eframe::egui::{self, Color32, ColorImage};
+use image::{ImageDecoder, ImageEncoder};
+
+/// XYZ (D50, the ICC connection space) to linear sRGB, Bradford-adapted
+const XYZ_D50_TO_SRGB: [[f32; 3]; 3] = [
+    [3.133856, -1.616867, -0.490615],
+    [-0.978768, 1.916142, 0.033454],
+    [0.071945, -0.228991, 1.405243],
+];
+
+/// Entries in the table encoding linear values to sRGB
+const ENCODE_STEPS: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorProfile {
+    /// The profile as embedded in the file
+    pub data: Vec<u8>,
+    /// How to reach sRGB, when the profile is simple enough to convert from
+    transform: Option<MatrixTransform>,
+}
+
+/// Tone curves and primaries of a matrix/TRC RGB profile
+#[derive(Debug, Clone, PartialEq)]
+struct MatrixTransform {
+    /// Linear value of each 8-bit channel value, per channel
+    curves: [Vec<f32>; 3],
+    /// Linear profile RGB to linear sRGB
+    matrix: [[f32; 3]; 3],
+}
+
+impl ColorProfile {
+    /// The profile embedded in the image at `path`, if any
+    pub fn read(path: &Path) -> Option<Self> {
+        let mut decoder = image::ImageReader::open(path).ok()?.with_guessed_format().ok()?.into_decoder().ok()?;
+        decoder.icc_profile().ok().flatten().filter(|data| !data.is_empty()).map(Self::from_data)
+    }
+
+    pub fn from_data(data: Vec<u8>) -> Self {
+        let transform = MatrixTransform::parse(&data);
+        Self { data, transform }
+    }
+
+    /// `image` converted from this profile to sRGB, if the profile allows it
+    pub fn to_srgb(&self, image: &ColorImage) -> Option<ColorImage> {
+        let transform = self.transform.as_ref()?;
+        let encode: Vec<u8> = (0..ENCODE_STEPS)
+            .map(|step| egui::ecolor::gamma_u8_from_linear_f32(step as f32 / (ENCODE_STEPS - 1) as f32))
+            .collect();
+        let encode = |linear: f32| encode[(linear.clamp(0.0, 1.0) * (ENCODE_STEPS - 1) as f32).round() as usize];
+        let pixels = image.pixels.iter().map(|pixel| {
+            let [r, g, b, a] = pixel.to_srgba_unmultiplied();
+            let linear = [transform.curves[0][r as usize], transform.curves[1][g as usize], transform.curves[2][b as usize]];
+            let [r, g, b] = transform.matrix.map(|row| encode(row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2]));
+            Color32::from_rgba_unmultiplied(r, g, b, a)
+        });
+        Some(ColorImage { size: image.size, pixels: pixels.collect() })
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+/// An ICC s15Fixed16Number
+fn read_fixed(data: &[u8], offset: usize) -> Option<f32> {
+    Some(read_u32(data, offset)? as i32 as f32 / 65536.0)
+}
+
+/// The data of the tag with `signature`
+fn find_tag<'a>(data: &'a [u8], signature: &[u8; 4]) -> Option<&'a [u8]> {
+    let count = read_u32(data, 128)? as usize;
+    (0..count.min(1024)).find_map(|index| {
+        let entry = 132 + index * 12;
+        if data.get(entry..entry + 4)? != signature {
+            return None;
+        }
+        let (offset, size) = (read_u32(data, entry + 4)? as usize, read_u32(data, entry + 8)? as usize);
+        data.get(offset..offset.checked_add(size)?)
+    })
+}
+
+fn read_xyz(tag: &[u8]) -> Option<[f32; 3]> {
+    (tag.get(0..4)? == b"XYZ ").then_some(())?;
+    Some([read_fixed(tag, 8)?, read_fixed(tag, 12)?, read_fixed(tag, 16)?])
+}
+
+/// Linear values of the 256 channel values under a `curv` or `para` tone curve
+fn read_curve(tag: &[u8]) -> Option<Vec<f32>> {
+    let inputs = (0..=255u8).map(|value| value as f32 / 255.0);
+    match tag.get(0..4)? {
+        b"curv" => {
+            let count = read_u32(tag, 8)? as usize;
+            match count {
+                0 => Some(inputs.collect()),
+                1 => {
+                    let gamma = read_u16(tag, 12)? as f32 / 256.0;
+                    Some(inputs.map(|x| x.powf(gamma)).collect())
+                }
+                _ => {
+                    let table: Vec<f32> = (0..count).map(|i| read_u16(tag, 12 + i * 2).map(|v| v as f32 / 65535.0)).collect::<Option<_>>()?;
+                    Some(inputs.map(|x| {
+                        let position = x * (count - 1) as f32;
+                        let (low, high) = (position.floor() as usize, position.ceil() as usize);
+                        table[low] + (table[high] - table[low]) * position.fract()
+                    }).collect())
+                }
+            }
+        }
+        b"para" => {
+            let kind = read_u16(tag, 8)?;
+            let parameters = [1, 3, 4, 5, 7].get(kind as usize)?;
+            let p: Vec<f32> = (0..*parameters).map(|i| read_fixed(tag, 12 + i * 4)).collect::<Option<_>>()?;
+            let power = |x: f32| if x > 0.0 { x.powf(p[0]) } else { 0.0 };
+            Some(inputs.map(|x| match kind {
+                0 => power(x),
+                1 => if x >= -p[2] / p[1] { power(p[1] * x + p[2]) } else { 0.0 },
+                2 => if x >= -p[2] / p[1] { power(p[1] * x + p[2]) + p[3] } else { p[3] },
+                3 => if x >= p[4] { power(p[1] * x + p[2]) } else { p[3] * x },
+                _ => if x >= p[4] { power(p[1] * x + p[2]) + p[5] } else { p[3] * x + p[6] },
+            }).collect())
+        }
+        _ => None,
+    }
+}
+
+impl MatrixTransform {
+    fn parse(data: &[u8]) -> Option<Self> {
+        (data.get(16..20)? == b"RGB ").then_some(())?;
+        let [red, green, blue] = [b"rXYZ", b"gXYZ", b"bXYZ"].map(|signature| find_tag(data, signature).and_then(read_xyz));
+        let (red, green, blue) = (red?, green?, blue?);
+        let [r, g, b] = [b"rTRC", b"gTRC", b"bTRC"].map(|signature| find_tag(data, signature).and_then(read_curve));
+        // Columns are the primaries in XYZ
+        let matrix = XYZ_D50_TO_SRGB.map(|row| {
+            let dot = |xyz: [f32; 3]| row[0] * xyz[0] + row[1] * xyz[1] + row[2] * xyz[2];
+            [dot(red), dot(green), dot(blue)]
+        });
+        Some(Self { curves: [r?, g?, b?], matrix })
+    }
+}
+
+/// Save `image` as a PNG at `output`, embedding `profile` if given
+pub fn save_png(image: &image::RgbaImage, profile: Option<&ColorProfile>, output: &Path) -> Result<(), String> {
+    let file = std::fs::File::create(output).map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+    let mut encoder = image::codecs::png::PngEncoder::new(BufWriter::new(file));
+    if let Some(profile) = profile {
+        encoder.set_icc_profile(profile.data.clone()).map_err(|e| e.to_string())?;
+    }
+    encoder.write_image(image.as_raw(), image.width(), image.height(), image::ExtendedColorType::Rgba8)
+        .map_err(|e| format!("Failed to write {}: {}", output.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    /// A matrix/TRC profile with the sRGB primaries and a gamma curve
+    fn profile(gamma: f32) -> Vec<u8> {
+        let fixed = |value: f32| ((value * 65536.0).round() as i32).to_be_bytes();
+        let mut data = vec![0u8; 128];
+        data[16..20].copy_from_slice(b"RGB ");
+        let primaries = [(b"rXYZ", [0.4361, 0.2225, 0.0139]), (b"gXYZ", [0.3851, 0.7169, 0.0971]), (b"bXYZ", [0.1431, 0.0606, 0.7141])];
+        let mut tags: Vec<(&[u8; 4], Vec<u8>)> = primaries.iter().map(|(signature, xyz)| {
+            let mut tag = b"XYZ \0\0\0\0".to_vec();
+            xyz.iter().for_each(|value| tag.extend(fixed(*value)));
+            (*signature, tag)
+        }).collect();
+        for signature in [b"rTRC", b"gTRC", b"bTRC"] {
+            let mut tag = b"curv\0\0\0\0".to_vec();
+            tag.extend(1u32.to_be_bytes());
+            tag.extend(((gamma * 256.0) as u16).to_be_bytes());
+            tags.push((signature, tag));
+        }
+        data.extend((tags.len() as u32).to_be_bytes());
+        let mut offset = 132 + tags.len() * 12;
+        for (signature, tag) in &tags {
+            data.extend(signature.iter());
+            data.extend((offset as u32).to_be_bytes());
+            data.extend((tag.len() as u32).to_be_bytes());
+            offset += tag.len();
+        }
+        tags.iter().for_each(|(_, tag)| data.extend(tag));
+        data
+    }
+
+    #[test]
+    fn test_matrix_profile_converts_to_srgb() {
+        let image = ColorImage { size: [3, 1], pixels: vec![Color32::WHITE, Color32::from_gray(128), Color32::from_rgb(200, 40, 40)] };
+        // Linear data with sRGB primaries: mid gray brightens, white and primaries' hue stay
+        let linear = ColorProfile::from_data(profile(1.0));
+        let converted = linear.to_srgb(&image).unwrap();
+        assert!(converted.pixels[0].r() >= 254 && converted.pixels[0].b() >= 254);
+        assert!((186..=189).contains(&converted.pixels[1].g()));
+        assert!(converted.pixels[2].r() > converted.pixels[2].g() * 2);
+
+        let unsupported = ColorProfile::from_data(vec![1, 2, 3]);
+        assert_eq!(unsupported.to_srgb(&image), None);
+
+        let dir = TempDir::new("color_profile");
+        let output = dir.join("tagged.png");
+        save_png(&image::RgbaImage::new(2, 2), Some(&linear), &output).unwrap();
+        let read = ColorProfile::read(&output);
+        assert_eq!(read, Some(linear));
+    }
+}
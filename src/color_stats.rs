@@ -0,0 +1,189 @@
+//! Color statistics for a selected image region and CSS formatting helpers
+
+use eframe::egui;
+use egui::{Color32, ColorImage};
+
+/// A rectangle in image pixel coordinates (max is exclusive)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelRect {
+    pub min_x: usize,
+    pub min_y: usize,
+    pub max_x: usize,
+    pub max_y: usize,
+}
+
+impl PixelRect {
+    /// Build a rectangle from two corner points given in (possibly fractional) pixel
+    /// coordinates, clamped to the image bounds. Returns None for empty selections.
+    pub fn from_corners(a: [f32; 2], b: [f32; 2], image_width: usize, image_height: usize) -> Option<Self> {
+        let clamp_x = |v: f32| (v.max(0.0) as usize).min(image_width);
+        let clamp_y = |v: f32| (v.max(0.0) as usize).min(image_height);
+
+        let rect = Self {
+            min_x: clamp_x(a[0].min(b[0])),
+            min_y: clamp_y(a[1].min(b[1])),
+            max_x: clamp_x(a[0].max(b[0]).ceil()),
+            max_y: clamp_y(a[1].max(b[1]).ceil()),
+        };
+
+        if rect.width() == 0 || rect.height() == 0 {
+            None
+        } else {
+            Some(rect)
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.max_x.saturating_sub(self.min_x)
+    }
+
+    pub fn height(&self) -> usize {
+        self.max_y.saturating_sub(self.min_y)
+    }
+
+    /// The rectangle scaled by `scale`, growing to whole pixels and clamped
+    /// to an image of `size`; `None` if nothing of it is left
+    pub fn scaled(&self, scale: f64, size: [usize; 2]) -> Option<Self> {
+        let corner = |x: usize, y: usize| [(x as f64 * scale) as f32, (y as f64 * scale) as f32];
+        Self::from_corners(corner(self.min_x, self.min_y), corner(self.max_x, self.max_y), size[0], size[1])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionColorStats {
+    pub average: Color32,
+    /// Average color at the start edge of the region's longer axis
+    pub gradient_start: Color32,
+    /// Average color at the end edge of the region's longer axis
+    pub gradient_end: Color32,
+    /// CSS gradient angle: 90 = left to right, 180 = top to bottom
+    pub gradient_angle_deg: u32,
+    pub pixel_count: usize,
+}
+
+/// Compute average color and gradient endpoints for a region of an image
+pub fn compute_region_stats(image: &ColorImage, rect: &PixelRect) -> Option<RegionColorStats> {
+    let [width, height] = image.size;
+    if rect.max_x > width || rect.max_y > height || rect.width() == 0 || rect.height() == 0 {
+        return None;
+    }
+
+    let horizontal = rect.width() >= rect.height();
+    let (length, edge) = if horizontal {
+        (rect.width(), (rect.width() / 10).max(1))
+    } else {
+        (rect.height(), (rect.height() / 10).max(1))
+    };
+
+    let average = average_color(image, rect);
+    let (start_rect, end_rect) = if horizontal {
+        (
+            PixelRect { max_x: rect.min_x + edge, ..*rect },
+            PixelRect { min_x: rect.min_x + length - edge, ..*rect },
+        )
+    } else {
+        (
+            PixelRect { max_y: rect.min_y + edge, ..*rect },
+            PixelRect { min_y: rect.min_y + length - edge, ..*rect },
+        )
+    };
+
+    Some(RegionColorStats {
+        average,
+        gradient_start: average_color(image, &start_rect),
+        gradient_end: average_color(image, &end_rect),
+        gradient_angle_deg: if horizontal { 90 } else { 180 },
+        pixel_count: rect.width() * rect.height(),
+    })
+}
+
+/// Alpha-weighted average, computed in premultiplied space
+fn average_color(image: &ColorImage, rect: &PixelRect) -> Color32 {
+    let [width, _] = image.size;
+    let mut sums = [0u64; 4];
+    for y in rect.min_y..rect.max_y {
+        for pixel in &image.pixels[y * width + rect.min_x..y * width + rect.max_x] {
+            let channels = pixel.to_array();
+            for (sum, channel) in sums.iter_mut().zip(channels) {
+                *sum += channel as u64;
+            }
+        }
+    }
+    let count = (rect.width() * rect.height()).max(1) as u64;
+    let avg = |i: usize| ((sums[i] + count / 2) / count) as u8;
+    Color32::from_rgba_premultiplied(avg(0), avg(1), avg(2), avg(3))
+}
+
+/// Format as `#rrggbb`, or `#rrggbbaa` when the color is translucent
+pub fn css_hex(color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    if a == 255 {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+}
+
+/// Format as `rgba(r, g, b, a)` with alpha in 0..1
+pub fn css_rgba(color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    format!("rgba({}, {}, {}, {})", r, g, b, format_alpha(a))
+}
+
+pub fn css_linear_gradient(stats: &RegionColorStats) -> String {
+    format!(
+        "linear-gradient({}deg, {}, {})",
+        stats.gradient_angle_deg,
+        css_hex(stats.gradient_start),
+        css_hex(stats.gradient_end)
+    )
+}
+
+fn format_alpha(alpha: u8) -> String {
+    let value = format!("{:.2}", alpha as f32 / 255.0);
+    value.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient_image() -> ColorImage {
+        // 10x2 image: left half red, right half blue
+        let mut pixels = Vec::new();
+        for _ in 0..2 {
+            for x in 0..10 {
+                pixels.push(if x < 5 { Color32::RED } else { Color32::BLUE });
+            }
+        }
+        ColorImage { size: [10, 2], pixels }
+    }
+
+    #[test]
+    fn test_pixel_rect_from_corners_clamps_and_orders() {
+        let rect = PixelRect::from_corners([8.5, 5.0], [-3.0, 0.2], 6, 4).unwrap();
+        assert_eq!(rect, PixelRect { min_x: 0, min_y: 0, max_x: 6, max_y: 4 });
+        assert!(PixelRect::from_corners([2.0, 2.0], [2.0, 3.0], 6, 4).is_none());
+    }
+
+    #[test]
+    fn test_region_stats_average_and_gradient() {
+        let image = gradient_image();
+        let rect = PixelRect { min_x: 0, min_y: 0, max_x: 10, max_y: 2 };
+        let stats = compute_region_stats(&image, &rect).unwrap();
+
+        assert_eq!(stats.pixel_count, 20);
+        assert_eq!(stats.gradient_angle_deg, 90);
+        assert_eq!(stats.gradient_start, Color32::RED);
+        assert_eq!(stats.gradient_end, Color32::BLUE);
+        assert_eq!(css_hex(stats.average), "#800080");
+        assert_eq!(css_linear_gradient(&stats), "linear-gradient(90deg, #ff0000, #0000ff)");
+    }
+
+    #[test]
+    fn test_css_formatting() {
+        assert_eq!(css_hex(Color32::from_rgb(0x33, 0x66, 0x99)), "#336699");
+        assert_eq!(css_rgba(Color32::from_rgb(1, 2, 3)), "rgba(1, 2, 3, 1)");
+        assert_eq!(css_rgba(Color32::TRANSPARENT), "rgba(0, 0, 0, 0)");
+    }
+}
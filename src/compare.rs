@@ -0,0 +1,116 @@
+//! A/B comparison of two versions of an image
+//!
+//! The wipe view shows image A left of a draggable divider and image B right
+//! of it. The difference view colors pixels whose largest channel difference
+//! reaches a threshold on a heat scale, over a dimmed copy of A, which makes
+//! compression artifacts and retouching easy to spot.
+
+use eframe::egui::{Color32, ColorImage};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareMode {
+    Wipe,
+    Difference,
+}
+
+/// How much two images differ
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DiffStats {
+    /// Pixels whose difference reaches the threshold
+    pub differing: usize,
+    pub total: usize,
+    /// Largest channel difference found, 0-255
+    pub max: u8,
+    /// Mean of each pixel's largest channel difference
+    pub mean: f32,
+}
+
+impl DiffStats {
+    pub fn differing_percent(&self) -> f32 {
+        if self.total == 0 { 0.0 } else { self.differing as f32 * 100.0 / self.total as f32 }
+    }
+}
+
+/// Largest per-channel difference between two pixels
+fn pixel_difference(a: Color32, b: Color32) -> u8 {
+    let (a, b) = (a.to_array(), b.to_array());
+    (0..4).map(|i| a[i].abs_diff(b[i])).max().unwrap_or(0)
+}
+
+/// Blue through yellow to red as `value` goes from 0 to 255
+pub fn heat_color(value: u8) -> Color32 {
+    let t = value as f32 / 255.0;
+    let mix = |from: [f32; 3], to: [f32; 3], t: f32| {
+        let channel = |i: usize| (from[i] + (to[i] - from[i]) * t).round() as u8;
+        Color32::from_rgb(channel(0), channel(1), channel(2))
+    };
+    if t < 0.5 {
+        mix([0.0, 64.0, 255.0], [255.0, 255.0, 0.0], t * 2.0)
+    } else {
+        mix([255.0, 255.0, 0.0], [255.0, 0.0, 0.0], (t - 0.5) * 2.0)
+    }
+}
+
+/// Heatmap of where `b` differs from `a` by at least `threshold`
+///
+/// `b` is sampled at `a`'s size when the two differ, so a resized copy can
+/// still be compared.
+pub fn difference_image(a: &ColorImage, b: &ColorImage, threshold: u8) -> (ColorImage, DiffStats) {
+    let [width, height] = a.size;
+    let [b_width, b_height] = b.size;
+    let mut stats = DiffStats { total: width * height, ..Default::default() };
+    let mut sum = 0u64;
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let by = (y * b_height / height.max(1)).min(b_height.saturating_sub(1));
+        for x in 0..width {
+            let bx = (x * b_width / width.max(1)).min(b_width.saturating_sub(1));
+            let pixel = a.pixels[y * width + x];
+            let difference = b.pixels.get(by * b_width + bx).map_or(255, |&other| pixel_difference(pixel, other));
+            sum += difference as u64;
+            stats.max = stats.max.max(difference);
+            if difference >= threshold.max(1) {
+                stats.differing += 1;
+                pixels.push(heat_color(difference));
+            } else {
+                // Dimmed luminance of A, so the differences stand out in context
+                let [r, g, b, _] = pixel.to_array();
+                let luma = (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) * 0.3;
+                pixels.push(Color32::from_gray(luma as u8));
+            }
+        }
+    }
+    stats.mean = if stats.total == 0 { 0.0 } else { sum as f32 / stats.total as f32 };
+    (ColorImage { size: a.size, pixels }, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_difference_image() {
+        let a = ColorImage::new([2, 2], Color32::from_rgb(100, 100, 100));
+        let mut b = a.clone();
+        b.pixels[1] = Color32::from_rgb(100, 104, 100);
+        b.pixels[3] = Color32::from_rgb(0, 100, 100);
+
+        let (heatmap, stats) = difference_image(&a, &b, 10);
+        assert_eq!(stats.differing, 1);
+        assert_eq!(stats.max, 100);
+        assert_eq!(stats.mean, 26.0);
+        assert_eq!(heatmap.pixels[3], heat_color(100));
+        // Below the threshold: dimmed A
+        assert_eq!(heatmap.pixels[1], Color32::from_gray(30));
+
+        // A lower threshold also reveals the subtle change
+        assert_eq!(difference_image(&a, &b, 4).1.differing, 2);
+        assert_eq!(difference_image(&a, &a, 1).1.differing_percent(), 0.0);
+    }
+
+    #[test]
+    fn test_heat_scale() {
+        assert_eq!(heat_color(0), Color32::from_rgb(0, 64, 255));
+        assert_eq!(heat_color(255), Color32::from_rgb(255, 0, 0));
+    }
+}
@@ -0,0 +1,187 @@
+//! Crash reports for panics
+//!
+//! Release builds on Windows have no console, so a panic used to close the
+//! window without a trace. The panic hook writes a report with the backtrace,
+//! the user's last actions and a snapshot of the settings and benchmark
+//! profile, and remembers it so the next launch can offer to open it. Only
+//! panics on the main thread bring the window down; a panicking worker
+//! thread just ends its job, so it leaves no report.
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::thread::ThreadId;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::benchmark::PerformanceProfile;
+use crate::diagnostics::diagnostics_dir;
+use crate::preferences::Preferences;
+use crate::settings::ImageLoadingSettings;
+
+/// Actions kept for the report, oldest dropped first
+pub const MAX_RECENT_ACTIONS: usize = 50;
+
+/// Preference holding the report left by a crash, until it is shown
+const PENDING_REPORT_KEY: &str = "pending_crash_report";
+
+/// Thread that installed the panic hook, whose panics end the process
+static MAIN_THREAD: OnceLock<ThreadId> = OnceLock::new();
+
+/// How often the settings snapshot is refreshed
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+struct CrashContext {
+    started: Option<Instant>,
+    actions: VecDeque<String>,
+    settings: Option<ImageLoadingSettings>,
+    profile: Option<PerformanceProfile>,
+    snapshot_taken: Option<Instant>,
+}
+
+static CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext {
+    started: None,
+    actions: VecDeque::new(),
+    settings: None,
+    profile: None,
+    snapshot_taken: None,
+});
+
+fn context() -> MutexGuard<'static, CrashContext> {
+    // A panic while recording must not stop later reports
+    CONTEXT.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Note something the user did, for the "last actions" part of a report
+pub fn record_action(action: impl Into<String>) {
+    let mut context = context();
+    let elapsed = context.started.get_or_insert_with(Instant::now).elapsed();
+    if context.actions.len() >= MAX_RECENT_ACTIONS {
+        context.actions.pop_front();
+    }
+    context.actions.push_back(format!("[{:>8.1}s] {}", elapsed.as_secs_f64(), action.into()));
+}
+
+/// Keep the settings and profile for a report, at most every couple of seconds
+pub fn snapshot(settings: &ImageLoadingSettings, profile: &PerformanceProfile) {
+    let mut context = context();
+    if context.snapshot_taken.is_some_and(|taken| taken.elapsed() < SNAPSHOT_INTERVAL) {
+        return;
+    }
+    context.settings = Some(settings.clone());
+    context.profile = Some(profile.clone());
+    context.snapshot_taken = Some(Instant::now());
+}
+
+/// Build the text of a crash report
+pub fn crash_report(message: &str, location: Option<&str>, backtrace: &str) -> String {
+    let mut report = String::new();
+    let _ = writeln!(report, "Crash report");
+    let _ = writeln!(report, "App version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "OS: {} {}", std::env::consts::OS, std::env::consts::ARCH);
+    let _ = writeln!(report, "Thread: {}", std::thread::current().name().unwrap_or("unnamed"));
+    let _ = writeln!(report, "Panic: {}", message);
+    let _ = writeln!(report, "Location: {}", location.unwrap_or("unknown"));
+
+    // Don't wait on a lock the panicking code may hold
+    let context = match CONTEXT.try_lock() {
+        Ok(context) => Some(context),
+        Err(std::sync::TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+        Err(std::sync::TryLockError::WouldBlock) => None,
+    };
+    let _ = writeln!(report, "\n== Last actions ==");
+    match &context {
+        Some(context) if !context.actions.is_empty() => {
+            for action in &context.actions {
+                let _ = writeln!(report, "{}", action);
+            }
+        }
+        Some(_) => { let _ = writeln!(report, "(none)"); }
+        None => { let _ = writeln!(report, "(unavailable)"); }
+    }
+    let settings = context.as_ref().and_then(|c| c.settings.as_ref());
+    let _ = writeln!(report, "\n== Settings ==\n{}", settings.map_or("(unavailable)".to_string(), |s| format!("{:#?}", s)));
+    let profile = context.as_ref().and_then(|c| c.profile.as_ref());
+    let _ = writeln!(report, "\n== Benchmark profile ==\n{}", profile.map_or("(unavailable)".to_string(), |p| format!("{:#?}", p)));
+    let _ = writeln!(report, "\n== Backtrace ==\n{}", backtrace);
+    report
+}
+
+fn write_report(dir: &Path, report: &str) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create diagnostics directory: {}", e))?;
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let path = dir.join(format!("crash-{}.txt", stamp));
+    std::fs::write(&path, report).map_err(|e| format!("Failed to write crash report: {}", e))?;
+    Ok(path)
+}
+
+/// Write a report for panics on the calling thread, then run the default
+/// hook. Call it from the main thread.
+pub fn install_panic_hook() {
+    record_action("Started");
+    let _ = MAIN_THREAD.set(std::thread::current().id());
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if MAIN_THREAD.get() != Some(&std::thread::current().id()) {
+            default_hook(info);
+            return;
+        }
+        let payload = info.payload();
+        let message = payload.downcast_ref::<&str>().copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("(no message)");
+        let location = info.location().map(|l| l.to_string());
+        let backtrace = std::backtrace::Backtrace::force_capture().to_string();
+        let report = crash_report(message, location.as_deref(), &backtrace);
+        if let Some(dir) = diagnostics_dir() {
+            match write_report(&dir, &report) {
+                Ok(path) => {
+                    eprintln!("Crash report written to {}", path.display());
+                    let mut preferences = Preferences::load();
+                    preferences.set(PENDING_REPORT_KEY, path.display());
+                    let _ = preferences.save();
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        default_hook(info);
+    }));
+}
+
+/// Report left by a crash in an earlier session, forgotten once returned
+pub fn take_pending_report() -> Option<PathBuf> {
+    let mut preferences = Preferences::load();
+    let path: PathBuf = preferences.get::<String>(PENDING_REPORT_KEY)?.into();
+    preferences.remove(PENDING_REPORT_KEY);
+    if let Err(e) = preferences.save() {
+        eprintln!("Warning: {}", e);
+    }
+    path.exists().then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_report_includes_recent_actions() {
+        for i in 0..MAX_RECENT_ACTIONS + 5 {
+            record_action(format!("action {}", i));
+        }
+        snapshot(&ImageLoadingSettings::default(), &PerformanceProfile::default());
+
+        let report = crash_report("index out of bounds", Some("src/app.rs:10:5"), "<backtrace>");
+        assert!(report.contains("Panic: index out of bounds"));
+        assert!(report.contains("Location: src/app.rs:10:5"));
+        assert!(report.contains(&format!("action {}", MAX_RECENT_ACTIONS + 4)));
+        assert!(!report.contains("] action 4\n"));
+        assert!(report.contains("== Settings ==\nImageLoadingSettings"));
+        assert!(report.contains("== Benchmark profile ==\nPerformanceProfile"));
+
+        let dir = TempDir::new("crash");
+        let path = write_report(&dir, &report).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), report);
+    }
+}
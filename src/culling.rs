@@ -0,0 +1,116 @@
+//! Culling: deciding quickly which images to keep
+//!
+//! Culling mode shows the listed images one at a time, fullscreen, and a
+//! single key keeps, rejects or skips each one before moving on. Decisions
+//! last for the session; when it ends, the rejected images can be moved into
+//! a [`REJECTS_FOLDER`] or sent to the trash in one go.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Subfolder rejected images are moved into
+pub const REJECTS_FOLDER: &str = "rejects";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Keep,
+    Reject,
+    /// Left for later; counts as undecided when reviewing
+    Skip,
+}
+
+impl Decision {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Decision::Keep => "Keep",
+            Decision::Reject => "Reject",
+            Decision::Skip => "Skipped",
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Decision::Keep => "✔",
+            Decision::Reject => "✖",
+            Decision::Skip => "➜",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Tally {
+    pub keep: usize,
+    pub reject: usize,
+    pub skip: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct CullingSession {
+    decisions: HashMap<PathBuf, Decision>,
+    /// The summary is showing, with what to do about the rejects
+    pub finished: bool,
+}
+
+impl CullingSession {
+    pub fn decide(&mut self, path: &Path, decision: Decision) {
+        self.decisions.insert(path.to_path_buf(), decision);
+    }
+
+    pub fn decision(&self, path: &Path) -> Option<Decision> {
+        self.decisions.get(path).copied()
+    }
+
+    pub fn tally(&self) -> Tally {
+        let mut tally = Tally::default();
+        for decision in self.decisions.values() {
+            match decision {
+                Decision::Keep => tally.keep += 1,
+                Decision::Reject => tally.reject += 1,
+                Decision::Skip => tally.skip += 1,
+            }
+        }
+        tally
+    }
+
+    /// Rejected images, sorted by path
+    pub fn rejected(&self) -> Vec<PathBuf> {
+        let mut rejected: Vec<PathBuf> = self.decisions.iter()
+            .filter(|(_, decision)| **decision == Decision::Reject)
+            .map(|(path, _)| path.clone())
+            .collect();
+        rejected.sort();
+        rejected
+    }
+
+    /// Position in `files` of the first image skipped or not decided yet
+    pub fn first_undecided(&self, files: &[PathBuf]) -> Option<usize> {
+        files.iter().position(|path| matches!(self.decision(path), None | Some(Decision::Skip)))
+    }
+
+    /// Show the summary, forgetting images moved or deleted while culling
+    pub fn finish(&mut self) {
+        self.decisions.retain(|path, _| path.exists());
+        self.finished = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tally_and_rejects_follow_the_latest_decision() {
+        let files: Vec<PathBuf> = ["a.jpg", "b.jpg", "c.jpg"].iter().map(PathBuf::from).collect();
+        let mut session = CullingSession::default();
+        session.decide(&files[0], Decision::Reject);
+        session.decide(&files[1], Decision::Skip);
+        assert_eq!(session.first_undecided(&files), Some(1));
+
+        session.decide(&files[1], Decision::Reject);
+        session.decide(&files[2], Decision::Keep);
+        session.decide(&files[0], Decision::Keep);
+        assert_eq!(session.tally(), Tally { keep: 2, reject: 1, skip: 0 });
+        assert_eq!(session.rejected(), vec![PathBuf::from("b.jpg")]);
+        assert_eq!(session.first_undecided(&files), None);
+    }
+}
@@ -0,0 +1,52 @@
+//! Decoded pixels without a GUI attached
+//!
+//! The decoders in [`image_processing`](crate::image_processing) produce a
+//! [`DecodedImage`], so programs that embed the pipeline don't need an egui
+//! context. The viewer turns them into textures through
+//! [`egui_adapter`](crate::egui_adapter).
+
+/// An RGBA image with 8 bits per channel; alpha is not premultiplied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    /// Rows top to bottom, four bytes per pixel
+    pub pixels: Vec<u8>,
+}
+
+impl DecodedImage {
+    pub fn from_rgba(image: image::RgbaImage) -> Self {
+        let (width, height) = image.dimensions();
+        Self { width, height, pixels: image.into_raw() }
+    }
+
+    pub fn into_rgba(self) -> image::RgbaImage {
+        image::RgbaImage::from_raw(self.width, self.height, self.pixels)
+            .expect("pixel buffer matches the dimensions")
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The RGBA channels of the pixel at `x`, `y`
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let start = (y as usize * self.width as usize + x as usize) * 4;
+        self.pixels[start..start + 4].try_into().expect("four channels per pixel")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgba_round_trip() {
+        let mut rgba = image::RgbaImage::new(3, 2);
+        rgba.put_pixel(2, 1, image::Rgba([1, 2, 3, 4]));
+        let decoded = DecodedImage::from_rgba(rgba.clone());
+        assert_eq!(decoded.dimensions(), (3, 2));
+        assert_eq!(decoded.pixel(2, 1), [1, 2, 3, 4]);
+        assert_eq!(decoded.into_rgba(), rgba);
+    }
+}
@@ -0,0 +1,461 @@
+//! Deep Zoom (.dzi) and IIIF tiled image sources
+//!
+//! Museum-scale scans are published as tile pyramids: each level halves the
+//! resolution of the one above it, and each level is cut into fixed-size
+//! tiles. Only the tiles covering the view, at the level matching the zoom,
+//! are fetched, so an image can be inspected without downloading all of it.
+//!
+//! Sources can be local files or, with the `http` feature (on by default),
+//! `http(s)` URLs.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+
+use eframe::egui::ColorImage;
+use regex::Regex;
+
+/// Largest descriptor or tile that will be downloaded
+#[cfg(feature = "http")]
+const MAX_RESOURCE_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Tiles fetched at once
+const TILE_WORKERS: usize = 4;
+
+/// IIIF tile size when the server doesn't advertise one
+const DEFAULT_IIIF_TILE_SIZE: u32 = 512;
+
+#[derive(Debug, Clone, PartialEq)]
+enum TileScheme {
+    /// `<name>_files/<level>/<col>_<row>.<format>`
+    Dzi { files_base: String, format: String },
+    /// `<id>/<x>,<y>,<w>,<h>/<width>,/0/default.jpg`
+    Iiif { id: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileId {
+    pub level: u32,
+    pub col: u32,
+    pub row: u32,
+}
+
+/// A tiled image: its full-resolution size and how to find its tiles
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeepZoomImage {
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: u32,
+    /// Pixels each DZI tile repeats from its neighbours
+    pub overlap: u32,
+    scheme: TileScheme,
+}
+
+/// Full-resolution image coordinates as [left, top, right, bottom]
+pub type ImageRect = [f64; 4];
+
+impl DeepZoomImage {
+    /// Parse a DZI descriptor found at `location` (a path or URL)
+    pub fn parse_dzi(xml: &str, location: &str) -> Result<Self, String> {
+        let attribute = |name: &str| -> Option<String> {
+            Regex::new(&format!(r#"\b{}\s*=\s*"([^"]*)""#, name))
+                .ok()?
+                .captures(xml)
+                .map(|captures| captures[1].to_string())
+        };
+        let number = |name: &str| -> Result<u32, String> {
+            attribute(name)
+                .ok_or_else(|| format!("DZI descriptor has no {}", name))?
+                .parse()
+                .map_err(|_| format!("DZI descriptor has an invalid {}", name))
+        };
+        let stem = location.rsplit_once('.').map_or(location, |(stem, _)| stem);
+        Self::new(number("Width")?, number("Height")?, number("TileSize")?, attribute("Overlap").and_then(|o| o.parse().ok()).unwrap_or(0), TileScheme::Dzi {
+            files_base: format!("{}_files", stem),
+            format: attribute("Format").unwrap_or_else(|| "jpg".to_string()),
+        })
+    }
+
+    /// Parse a IIIF Image API `info.json` found at `location`
+    pub fn parse_iiif(json: &str, location: &str) -> Result<Self, String> {
+        // Tile and size lists repeat "width"/"height"; cut them out before reading the image's own
+        let (tiles, rest) = take_array(json, "tiles");
+        let (_, rest) = take_array(&rest, "sizes");
+        let number = |text: &str, name: &str| -> Option<u32> {
+            Regex::new(&format!(r#""{}"\s*:\s*(\d+)"#, name)).ok()?.captures(text)?[1].parse().ok()
+        };
+        let width = number(&rest, "width").ok_or("IIIF info has no width")?;
+        let height = number(&rest, "height").ok_or("IIIF info has no height")?;
+        let tile_size = tiles.as_deref().and_then(|tiles| number(tiles, "width")).unwrap_or(DEFAULT_IIIF_TILE_SIZE);
+        let id = Regex::new(r#""@?id"\s*:\s*"([^"]+)""#)
+            .ok()
+            .and_then(|re| re.captures(&rest).map(|captures| captures[1].to_string()))
+            .unwrap_or_else(|| location.trim_end_matches("/info.json").to_string());
+        Self::new(width, height, tile_size, 0, TileScheme::Iiif { id: id.trim_end_matches('/').to_string() })
+    }
+
+    fn new(width: u32, height: u32, tile_size: u32, overlap: u32, scheme: TileScheme) -> Result<Self, String> {
+        if width == 0 || height == 0 || tile_size == 0 {
+            return Err(format!("Invalid tiled image: {}×{} with {} px tiles", width, height, tile_size));
+        }
+        Ok(Self { width, height, tile_size, overlap, scheme })
+    }
+
+    /// Level holding the full-resolution image; level 0 is a single pixel
+    pub fn max_level(&self) -> u32 {
+        u32::BITS - (self.width.max(self.height) - 1).leading_zeros()
+    }
+
+    /// Size of a level relative to full resolution
+    pub fn level_scale(&self, level: u32) -> f64 {
+        0.5_f64.powi((self.max_level() - level.min(self.max_level())) as i32)
+    }
+
+    pub fn level_size(&self, level: u32) -> (u32, u32) {
+        let scale = self.level_scale(level);
+        ((self.width as f64 * scale).ceil() as u32, (self.height as f64 * scale).ceil() as u32)
+    }
+
+    /// Lowest level with at least `display_scale` pixels per image pixel
+    pub fn level_for_scale(&self, display_scale: f64) -> u32 {
+        let levels_below = (-display_scale.log2()).floor().max(0.0) as u32;
+        self.max_level().saturating_sub(levels_below)
+    }
+
+    pub fn tile_count(&self, level: u32) -> (u32, u32) {
+        let (width, height) = self.level_size(level);
+        (width.div_ceil(self.tile_size), height.div_ceil(self.tile_size))
+    }
+
+    /// Tiles of `level` that intersect `rect`, in row order
+    pub fn visible_tiles(&self, level: u32, rect: ImageRect) -> Vec<TileId> {
+        let scale = self.level_scale(level) / self.tile_size as f64;
+        let (cols, rows) = self.tile_count(level);
+        let range = |min: f64, max: f64, count: u32| {
+            let first = (min * scale).floor().max(0.0) as u32;
+            let end = ((max * scale).ceil().max(0.0) as u32).min(count);
+            first..end
+        };
+        let columns = range(rect[0], rect[2], cols);
+        range(rect[1], rect[3], rows)
+            .flat_map(|row| columns.clone().map(move |col| TileId { level, col, row }))
+            .collect()
+    }
+
+    /// Area of the full-resolution image a tile's pixels cover, including any overlap
+    pub fn tile_image_rect(&self, tile: TileId) -> ImageRect {
+        let (width, height) = self.level_size(tile.level);
+        let (size, overlap) = (self.tile_size, self.overlap);
+        let x0 = (tile.col * size).saturating_sub(overlap);
+        let y0 = (tile.row * size).saturating_sub(overlap);
+        let x1 = ((tile.col + 1) * size + overlap).min(width);
+        let y1 = ((tile.row + 1) * size + overlap).min(height);
+        let scale = self.level_scale(tile.level);
+        [x0 as f64 / scale, y0 as f64 / scale, x1 as f64 / scale, y1 as f64 / scale]
+    }
+
+    /// Path or URL of a tile
+    pub fn tile_location(&self, tile: TileId) -> String {
+        match &self.scheme {
+            TileScheme::Dzi { files_base, format } => format!("{}/{}/{}_{}.{}", files_base, tile.level, tile.col, tile.row, format),
+            TileScheme::Iiif { id } => {
+                let [x0, y0, x1, y1] = self.tile_image_rect(tile).map(|v| v.round() as u64);
+                let (x1, y1) = (x1.min(self.width as u64), y1.min(self.height as u64));
+                let scaled_width = ((x1 - x0) as f64 * self.level_scale(tile.level)).ceil().max(1.0) as u64;
+                format!("{}/{},{},{},{}/{},/0/default.jpg", id, x0, y0, x1 - x0, y1 - y0, scaled_width)
+            }
+        }
+    }
+}
+
+/// Split the JSON array stored under `key` out of `json`
+fn take_array(json: &str, key: &str) -> (Option<String>, String) {
+    let Some(key_start) = json.find(&format!("\"{}\"", key)) else {
+        return (None, json.to_string());
+    };
+    let Some(open) = json[key_start..].find('[').map(|i| key_start + i) else {
+        return (None, json.to_string());
+    };
+    let mut depth = 0;
+    for (i, c) in json[open..].char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    let close = open + i + 1;
+                    return (Some(json[open..close].to_string()), format!("{}{}", &json[..key_start], &json[close..]));
+                }
+            }
+            _ => {}
+        }
+    }
+    (None, json.to_string())
+}
+
+fn is_url(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+/// Read a descriptor or tile from disk or over HTTP
+pub fn read_resource(location: &str) -> Result<Vec<u8>, String> {
+    if is_url(location) {
+        http_get(location)
+    } else {
+        std::fs::read(Path::new(location)).map_err(|e| format!("Failed to read {}: {}", location, e))
+    }
+}
+
+#[cfg(feature = "http")]
+fn http_get(url: &str) -> Result<Vec<u8>, String> {
+    ureq::get(url)
+        .call()
+        .map_err(|e| format!("Request for {} failed: {}", url, e))?
+        .body_mut()
+        .with_config()
+        .limit(MAX_RESOURCE_BYTES)
+        .read_to_vec()
+        .map_err(|e| format!("Failed to read {}: {}", url, e))
+}
+
+#[cfg(not(feature = "http"))]
+fn http_get(url: &str) -> Result<Vec<u8>, String> {
+    Err(format!("Can't fetch {}: this build has no HTTP support (enable the `http` feature)", url))
+}
+
+/// Load the descriptor of a tiled image: a `.dzi` file or a IIIF `info.json`
+pub fn open(location: &str) -> Result<DeepZoomImage, String> {
+    let location = location.trim();
+    let bytes = read_resource(location)?;
+    let text = String::from_utf8_lossy(&bytes);
+    if text.trim_start().starts_with('{') {
+        DeepZoomImage::parse_iiif(&text, location)
+    } else {
+        DeepZoomImage::parse_dzi(&text, location)
+    }
+}
+
+/// Visible part of a tiled image: the image point at the view's center and the zoom
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeepZoomView {
+    pub center: [f64; 2],
+    /// Screen points per full-resolution image pixel
+    pub scale: f64,
+}
+
+impl DeepZoomView {
+    /// Whole image in a view of `area` size
+    pub fn fit(image: &DeepZoomImage, area: [f64; 2]) -> Self {
+        let scale = (area[0] / image.width as f64).min(area[1] / image.height as f64);
+        Self { center: [image.width as f64 / 2.0, image.height as f64 / 2.0], scale }
+    }
+
+    pub fn visible_rect(&self, area: [f64; 2]) -> ImageRect {
+        let half = [area[0] / 2.0 / self.scale, area[1] / 2.0 / self.scale];
+        [self.center[0] - half[0], self.center[1] - half[1], self.center[0] + half[0], self.center[1] + half[1]]
+    }
+
+    /// Screen offset from the view's center of an image point
+    pub fn to_screen(self, point: [f64; 2]) -> [f64; 2] {
+        [(point[0] - self.center[0]) * self.scale, (point[1] - self.center[1]) * self.scale]
+    }
+
+    pub fn pan(&mut self, delta: [f64; 2]) {
+        self.center[0] -= delta[0] / self.scale;
+        self.center[1] -= delta[1] / self.scale;
+    }
+
+    /// Zoom keeping the image point at `pivot` (offset from the view's center) in place
+    pub fn zoom_about(&mut self, factor: f64, pivot: [f64; 2], min_scale: f64, max_scale: f64) {
+        let scale = (self.scale * factor).clamp(min_scale, max_scale);
+        let point = [self.center[0] + pivot[0] / self.scale, self.center[1] + pivot[1] / self.scale];
+        self.center = [point[0] - pivot[0] / scale, point[1] - pivot[1] / scale];
+        self.scale = scale;
+    }
+}
+
+/// An open tiled image and its tile fetching
+pub struct DeepZoomSession {
+    pub image: DeepZoomImage,
+    /// None until the first frame sizes the view to fit
+    pub view: Option<DeepZoomView>,
+    pub loader: TileLoader,
+    /// Tiles that couldn't be fetched or decoded; not retried this session
+    pub failed: HashSet<TileId>,
+}
+
+impl DeepZoomSession {
+    pub fn new(image: DeepZoomImage) -> Self {
+        Self { image, view: None, loader: TileLoader::new(TILE_WORKERS), failed: HashSet::new() }
+    }
+}
+
+struct LoaderShared {
+    queue: Mutex<Vec<(TileId, String)>>,
+    wake: Condvar,
+    shutdown: AtomicBool,
+}
+
+/// Fetches and decodes tiles on worker threads, most recently requested first
+pub struct TileLoader {
+    shared: Arc<LoaderShared>,
+    results: Receiver<(TileId, Result<ColorImage, String>)>,
+    requested: HashSet<TileId>,
+}
+
+impl TileLoader {
+    pub fn new(workers: usize) -> Self {
+        let shared = Arc::new(LoaderShared {
+            queue: Mutex::new(Vec::new()),
+            wake: Condvar::new(),
+            shutdown: AtomicBool::new(false),
+        });
+        let (sender, results) = mpsc::channel();
+        for index in 0..workers.max(1) {
+            let shared = Arc::clone(&shared);
+            let sender = sender.clone();
+            let _ = std::thread::Builder::new()
+                .name(format!("deep-zoom-tiles-{}", index))
+                .spawn(move || tile_worker(shared, sender));
+        }
+        Self { shared, results, requested: HashSet::new() }
+    }
+
+    /// Queue a tile unless it is already on its way
+    pub fn request(&mut self, tile: TileId, location: String) {
+        if self.requested.insert(tile) {
+            self.shared.queue.lock().unwrap().push((tile, location));
+            self.shared.wake.notify_one();
+        }
+    }
+
+    /// Drop queued tiles that are no longer wanted, e.g. after panning away
+    pub fn retain(&mut self, keep: impl Fn(&TileId) -> bool) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.retain(|(tile, _)| {
+            let wanted = keep(tile);
+            if !wanted {
+                self.requested.remove(tile);
+            }
+            wanted
+        });
+    }
+
+    pub fn is_busy(&self) -> bool {
+        !self.requested.is_empty()
+    }
+
+    /// Tiles finished since the last call
+    pub fn poll(&mut self) -> Vec<(TileId, Result<ColorImage, String>)> {
+        let results: Vec<_> = self.results.try_iter().collect();
+        for (tile, _) in &results {
+            self.requested.remove(tile);
+        }
+        results
+    }
+}
+
+impl Drop for TileLoader {
+    fn drop(&mut self) {
+        // Workers may be blocked on the network, so they are left to exit on their own
+        self.shared.shutdown.store(true, Ordering::Relaxed);
+        self.shared.wake.notify_all();
+    }
+}
+
+fn tile_worker(shared: Arc<LoaderShared>, sender: Sender<(TileId, Result<ColorImage, String>)>) {
+    loop {
+        let (tile, location) = {
+            let mut queue = shared.queue.lock().unwrap();
+            loop {
+                if shared.shutdown.load(Ordering::Relaxed) {
+                    return;
+                }
+                if let Some(job) = queue.pop() {
+                    break job;
+                }
+                queue = shared.wake.wait(queue).unwrap();
+            }
+        };
+        let result = read_resource(&location).and_then(|bytes| {
+            let image = image::load_from_memory(&bytes).map_err(|e| format!("Failed to decode tile {}: {}", location, e))?;
+            let rgba = image.to_rgba8();
+            Ok(ColorImage::from_rgba_unmultiplied([rgba.width() as usize, rgba.height() as usize], rgba.as_raw()))
+        });
+        if sender.send((tile, result)).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DZI: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Image xmlns="http://schemas.microsoft.com/deepzoom/2008" TileSize="254" Overlap="1" Format="jpg">
+  <Size Width="1000" Height="600"/>
+</Image>"#;
+
+    #[test]
+    fn test_dzi_pyramid() {
+        let image = DeepZoomImage::parse_dzi(DZI, "/scans/map.dzi").unwrap();
+        assert_eq!((image.width, image.height, image.tile_size, image.overlap), (1000, 600, 254, 1));
+        assert_eq!(image.max_level(), 10);
+        assert_eq!(image.level_size(10), (1000, 600));
+        assert_eq!(image.level_size(9), (500, 300));
+        assert_eq!(image.level_size(0), (1, 1));
+        assert_eq!(image.tile_count(10), (4, 3));
+
+        assert_eq!(image.level_for_scale(1.0), 10);
+        assert_eq!(image.level_for_scale(0.3), 9);
+        assert_eq!(image.level_for_scale(0.5), 9);
+        assert_eq!(image.level_for_scale(4.0), 10);
+
+        let tile = TileId { level: 10, col: 1, row: 0 };
+        assert_eq!(image.tile_location(tile), "/scans/map_files/10/1_0.jpg");
+        assert_eq!(image.tile_image_rect(tile), [253.0, 0.0, 509.0, 255.0]);
+
+        let visible = image.visible_tiles(10, [300.0, 100.0, 600.0, 200.0]);
+        assert_eq!(visible, [TileId { level: 10, col: 1, row: 0 }, TileId { level: 10, col: 2, row: 0 }]);
+        assert!(image.visible_tiles(10, [2000.0, 0.0, 3000.0, 100.0]).is_empty());
+    }
+
+    #[test]
+    fn test_iiif_info() {
+        let json = r#"{
+            "@context": "http://iiif.io/api/image/2/context.json",
+            "@id": "https://example.org/iiif/scan1",
+            "sizes": [{"width": 150, "height": 100}],
+            "tiles": [{"width": 256, "scaleFactors": [1, 2, 4, 8]}],
+            "width": 6000,
+            "height": 4000
+        }"#;
+        let image = DeepZoomImage::parse_iiif(json, "https://example.org/iiif/scan1/info.json").unwrap();
+        assert_eq!((image.width, image.height, image.tile_size), (6000, 4000, 256));
+        let top = image.max_level();
+        assert_eq!(image.tile_location(TileId { level: top, col: 1, row: 0 }), "https://example.org/iiif/scan1/256,0,256,256/256,/0/default.jpg");
+        assert_eq!(image.tile_location(TileId { level: top - 1, col: 0, row: 0 }), "https://example.org/iiif/scan1/0,0,512,512/256,/0/default.jpg");
+
+        assert!(DeepZoomImage::parse_iiif("{}", "x").is_err());
+    }
+
+    #[test]
+    fn test_view_zoom_keeps_pivot() {
+        let image = DeepZoomImage::parse_dzi(DZI, "map.dzi").unwrap();
+        let mut view = DeepZoomView::fit(&image, [500.0, 500.0]);
+        assert_eq!(view.scale, 0.5);
+        assert_eq!(view.visible_rect([500.0, 500.0]), [0.0, -200.0, 1000.0, 800.0]);
+
+        let pivot = [100.0, 50.0];
+        let before = [view.center[0] + pivot[0] / view.scale, view.center[1] + pivot[1] / view.scale];
+        view.zoom_about(4.0, pivot, 0.1, 8.0);
+        assert_eq!(view.scale, 2.0);
+        assert_eq!(view.to_screen(before), pivot);
+
+        view.pan([20.0, 0.0]);
+        assert_eq!(view.center[0], before[0] - 50.0 - 10.0);
+    }
+}
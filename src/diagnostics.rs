@@ -0,0 +1,207 @@
+//! Error reports for images that fail to decode
+//!
+//! A report holds a hex dump of the start of the file, the format its magic
+//! bytes suggest, a backtrace of where decoding failed and the loading
+//! settings, so a "this file won't open" bug report can be acted on without
+//! the original file.
+
+#[cfg(feature = "gui")]
+use std::backtrace::Backtrace;
+#[cfg(feature = "gui")]
+use std::fmt::Write as _;
+#[cfg(feature = "gui")]
+use std::io::Read;
+use std::path::PathBuf;
+#[cfg(feature = "gui")]
+use std::path::Path;
+#[cfg(feature = "gui")]
+use std::sync::Arc;
+#[cfg(feature = "gui")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::settings::config_dir;
+#[cfg(feature = "gui")]
+use crate::settings::ImageLoadingSettings;
+
+/// Bytes from the start of the file included in the hex dump
+#[cfg(feature = "gui")]
+pub const HEX_DUMP_BYTES: usize = 4 * 1024;
+
+/// An image that failed to decode, with a backtrace of where the failure was noticed
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone)]
+pub struct DecodeError {
+    pub message: String,
+    /// Only captured when error reports are on, since capturing is slow
+    pub backtrace: Option<Arc<Backtrace>>,
+}
+
+#[cfg(feature = "gui")]
+impl DecodeError {
+    /// Wrap `message`, capturing the caller's backtrace if `capture`
+    pub fn new(message: String, capture: bool) -> Self {
+        Self {
+            message,
+            backtrace: capture.then(|| Arc::new(Backtrace::force_capture())),
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Where error reports are written
+pub fn diagnostics_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("diagnostics"))
+}
+
+/// Format suggested by the file's leading bytes, regardless of its extension
+#[cfg(feature = "gui")]
+pub fn detect_magic(bytes: &[u8]) -> Option<String> {
+    if let Ok(format) = image::guess_format(bytes) {
+        return Some(format!("{:?}", format));
+    }
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(512)]);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if text.starts_with("<svg") || (text.starts_with("<?xml") && text.contains("<svg")) {
+        Some("SVG".to_string())
+    } else if text.starts_with('<') {
+        Some("XML/HTML (not an image)".to_string())
+    } else if !bytes.is_empty() && bytes.iter().all(|&b| b == 0) {
+        // Typical of a cloud placeholder whose contents were never downloaded
+        Some("All zero bytes".to_string())
+    } else {
+        None
+    }
+}
+
+/// Classic hex dump: offset, 16 bytes in hex, then printable ASCII
+#[cfg(feature = "gui")]
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (line, chunk) in bytes.chunks(16).enumerate() {
+        let _ = write!(out, "{:08x}  ", line * 16);
+        for i in 0..16 {
+            match chunk.get(i) {
+                Some(byte) => { let _ = write!(out, "{:02x} ", byte); }
+                None => out.push_str("   "),
+            }
+            if i == 7 {
+                out.push(' ');
+            }
+        }
+        out.push(' ');
+        out.extend(chunk.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }));
+        out.push('\n');
+    }
+    out
+}
+
+/// Build the text of an error report for `path`
+#[cfg(feature = "gui")]
+pub fn error_report(path: &Path, error: &DecodeError, settings: &ImageLoadingSettings) -> String {
+    let mut head = Vec::new();
+    let read = std::fs::File::open(path)
+        .and_then(|file| file.take(HEX_DUMP_BYTES as u64).read_to_end(&mut head));
+    let size = std::fs::metadata(path).map(|m| m.len());
+
+    let mut report = String::new();
+    let _ = writeln!(report, "Image decode error report");
+    let _ = writeln!(report, "App version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "File: {}", path.display());
+    let _ = writeln!(report, "Error: {}", error);
+    match size {
+        Ok(size) => { let _ = writeln!(report, "Size: {} bytes", size); }
+        Err(e) => { let _ = writeln!(report, "Size: unknown ({})", e); }
+    }
+    let _ = writeln!(report, "Detected format: {}", detect_magic(&head).unwrap_or_else(|| "unknown".to_string()));
+
+    let _ = writeln!(report, "\n== First {} bytes ==", head.len());
+    match read {
+        Ok(_) => report.push_str(&hex_dump(&head)),
+        Err(e) => { let _ = writeln!(report, "Failed to read file: {}", e); }
+    }
+    let _ = writeln!(report, "\n== Settings ==\n{:#?}", settings);
+    if let Some(backtrace) = &error.backtrace {
+        let _ = writeln!(report, "\n== Backtrace ==\n{}", backtrace);
+    }
+    report
+}
+
+/// Write an error report into `dir`, returning the report's path
+#[cfg(feature = "gui")]
+pub fn save_error_report(path: &Path, error: &DecodeError, settings: &ImageLoadingSettings, dir: &Path) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create diagnostics directory: {}", e))?;
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let report_path = dir.join(format!("decode-error-{}-{}.txt", stem, stamp));
+    std::fs::write(&report_path, error_report(path, error, settings))
+        .map_err(|e| format!("Failed to write error report: {}", e))?;
+    Ok(report_path)
+}
+
+/// Open a file or folder with the system's default application
+#[cfg(feature = "gui")]
+pub fn open_with_system(path: &Path) -> Result<(), String> {
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = std::process::Command::new("explorer");
+        command.arg(path);
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = std::process::Command::new("open");
+        command.arg(path);
+        command
+    };
+    #[cfg(not(any(windows, target_os = "macos")))]
+    let mut command = {
+        let mut command = std::process::Command::new("xdg-open");
+        command.arg(path);
+        command
+    };
+    command.spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+}
+
+#[cfg(all(test, feature = "gui"))]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_detect_magic() {
+        assert_eq!(detect_magic(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").as_deref(), Some("Png"));
+        assert_eq!(detect_magic(b"<?xml version=\"1.0\"?>\n<svg></svg>").as_deref(), Some("SVG"));
+        assert_eq!(detect_magic(b"<!DOCTYPE html>").as_deref(), Some("XML/HTML (not an image)"));
+        assert_eq!(detect_magic(&[0; 32]).as_deref(), Some("All zero bytes"));
+        assert_eq!(detect_magic(b"hello"), None);
+    }
+
+    #[test]
+    fn test_hex_dump_and_report() {
+        let dump = hex_dump(b"GIF89a\x01\x02");
+        assert!(dump.starts_with("00000000  47 49 46 38 39 61 01 02"));
+        assert!(dump.trim_end().ends_with("GIF89a.."));
+
+        let dir = TempDir::new("diagnostics");
+        let image = dir.join("broken.png");
+        std::fs::write(&image, b"\x89PNG\r\n\x1a\ntruncated").unwrap();
+
+        let error = DecodeError::new("unexpected end of file".to_string(), true);
+        let report = save_error_report(&image, &error, &ImageLoadingSettings::default(), &dir).unwrap();
+        let text = std::fs::read_to_string(&report).unwrap();
+        assert!(text.contains("Error: unexpected end of file"));
+        assert!(text.contains("Detected format: Png"));
+        assert!(text.contains("== Settings =="));
+        // Taken where the error was created, so it runs through this test
+        let backtrace = text.split_once("== Backtrace ==").unwrap().1;
+        assert!(backtrace.contains("test_hex_dump_and_report"));
+    }
+}
@@ -0,0 +1,377 @@
+//! Background download queue for on-demand (cloud placeholder) files
+//!
+//! Files are hydrated by reading their full contents on a worker thread, which
+//! makes the cloud provider download them without blocking the UI.
+
+use std::collections::VecDeque;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::file_locality::FileInfo;
+use crate::power::{ResumeNotifications, SleepDetector};
+use crate::progress::{OperationKind, ProgressReporter, ProgressSink};
+
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Events reported by the download worker
+#[derive(Debug, Clone)]
+pub enum DownloadEvent {
+    Started(PathBuf),
+    Completed {
+        path: PathBuf,
+        bytes: u64,
+        elapsed: Duration,
+    },
+    Failed {
+        path: PathBuf,
+        error: String,
+    },
+    /// The machine woke from sleep, so downloads in flight may fail
+    Woke {
+        slept: Duration,
+    },
+}
+
+#[derive(Default)]
+struct QueueState {
+    pending: VecDeque<PathBuf>,
+    active: Option<PathBuf>,
+}
+
+struct QueueShared {
+    state: Mutex<QueueState>,
+    wake: Condvar,
+    shutdown: AtomicBool,
+    /// No new downloads start while set
+    paused: AtomicBool,
+}
+
+pub struct DownloadQueue {
+    shared: Arc<QueueShared>,
+    events: Receiver<DownloadEvent>,
+    worker: Option<JoinHandle<()>>,
+    completed_count: usize,
+    total_count: usize,
+    /// Reports wake-ups where the system can; otherwise the worker watches the clock
+    _resume_notifications: Option<ResumeNotifications>,
+}
+
+impl Default for DownloadQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DownloadQueue {
+    pub fn new() -> Self {
+        Self::with_progress(ProgressSink::disconnected())
+    }
+
+    /// Create a queue that reports each download's progress to `progress`
+    pub fn with_progress(progress: ProgressSink) -> Self {
+        let shared = Arc::new(QueueShared {
+            state: Mutex::new(QueueState::default()),
+            wake: Condvar::new(),
+            shutdown: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+        });
+        let (sender, events) = mpsc::channel();
+
+        let resume_sender = sender.clone();
+        let resume_notifications = ResumeNotifications::subscribe(move |slept| {
+            let _ = resume_sender.send(DownloadEvent::Woke { slept });
+        });
+        let sleep_detector = resume_notifications.is_none().then(SleepDetector::default);
+
+        let worker_shared = Arc::clone(&shared);
+        let worker = std::thread::Builder::new()
+            .name("download-queue".to_string())
+            .spawn(move || download_worker(worker_shared, sender, progress, sleep_detector))
+            .ok();
+
+        Self {
+            shared,
+            events,
+            worker,
+            completed_count: 0,
+            total_count: 0,
+            _resume_notifications: resume_notifications,
+        }
+    }
+
+    /// Queue a file for download. Files already queued are ignored.
+    pub fn enqueue(&mut self, path: PathBuf) {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.pending.contains(&path) || state.active.as_ref() == Some(&path) {
+            return;
+        }
+        state.pending.push_back(path);
+        drop(state);
+        self.count_queued();
+        self.shared.wake.notify_one();
+    }
+
+    /// Count a newly queued file, starting a fresh batch once every file of
+    /// the last one has been reported
+    fn count_queued(&mut self) {
+        if self.completed_count >= self.total_count {
+            self.completed_count = 0;
+            self.total_count = 0;
+        }
+        self.total_count += 1;
+    }
+
+    pub fn enqueue_all(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        for path in paths {
+            self.enqueue(path);
+        }
+    }
+
+    /// Queue a file that failed to download again, ahead of the others
+    pub fn retry(&mut self, path: PathBuf) {
+        self.enqueue_next(path);
+    }
+
+    /// Queue a file ahead of the others, as when it's wanted right away
+    pub fn enqueue_next(&mut self, path: PathBuf) {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.pending.contains(&path) || state.active.as_ref() == Some(&path) {
+            return;
+        }
+        state.pending.push_front(path);
+        drop(state);
+        self.count_queued();
+        self.shared.wake.notify_one();
+    }
+
+    /// Let the current download finish but start no others until [`resume`](Self::resume)
+    pub fn pause(&mut self) {
+        self.shared.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&mut self) {
+        self.shared.paused.store(false, Ordering::SeqCst);
+        self.shared.wake.notify_all();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.shared.paused.load(Ordering::SeqCst)
+    }
+
+    /// Drop all files that have not started downloading yet
+    pub fn cancel_pending(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        self.total_count -= state.pending.len();
+        state.pending.clear();
+    }
+
+    pub fn is_queued(&self, path: &Path) -> bool {
+        let state = self.shared.state.lock().unwrap();
+        state.active.as_deref() == Some(path) || state.pending.iter().any(|p| p == path)
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.shared.state.lock().unwrap().pending.len()
+    }
+
+    pub fn is_busy(&self) -> bool {
+        let state = self.shared.state.lock().unwrap();
+        state.active.is_some() || !state.pending.is_empty()
+    }
+
+    /// Progress of the current batch as (completed, total)
+    pub fn progress(&self) -> (usize, usize) {
+        (self.completed_count, self.total_count)
+    }
+
+    pub fn active_download(&self) -> Option<PathBuf> {
+        self.shared.state.lock().unwrap().active.clone()
+    }
+
+    /// Drain events produced by the worker since the last call
+    pub fn poll_events(&mut self) -> Vec<DownloadEvent> {
+        let events: Vec<DownloadEvent> = self.events.try_iter().collect();
+        for event in &events {
+            if matches!(event, DownloadEvent::Completed { .. } | DownloadEvent::Failed { .. }) {
+                self.completed_count += 1;
+            }
+        }
+        events
+    }
+}
+
+impl Drop for DownloadQueue {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+        self.shared.wake.notify_all();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// `sleep_detector` watches for the machine sleeping mid-download where the
+/// system doesn't report it
+fn download_worker(
+    shared: Arc<QueueShared>,
+    sender: Sender<DownloadEvent>,
+    progress: ProgressSink,
+    mut sleep_detector: Option<SleepDetector>,
+) {
+    loop {
+        let next = {
+            let mut state = shared.state.lock().unwrap();
+            loop {
+                if shared.shutdown.load(Ordering::SeqCst) {
+                    return;
+                }
+                if !shared.paused.load(Ordering::SeqCst)
+                    && let Some(path) = state.pending.pop_front() {
+                    state.active = Some(path.clone());
+                    break path;
+                }
+                state = shared.wake.wait(state).unwrap();
+            }
+        };
+
+        let _ = sender.send(DownloadEvent::Started(next.clone()));
+        let name = next.file_name().unwrap_or(next.as_os_str()).to_string_lossy().to_string();
+        let reporter = progress.start(OperationKind::Hydration, format!("Downloading {}", name));
+        let start = Instant::now();
+        // Time spent waiting for work isn't sleep
+        if let Some(detector) = &mut sleep_detector {
+            detector.reset();
+        }
+        let mut watch_for_sleep = || {
+            if let Some(slept) = sleep_detector.as_mut().and_then(|detector| detector.observe(std::time::SystemTime::now())) {
+                let _ = sender.send(DownloadEvent::Woke { slept });
+            }
+        };
+        let result = hydrate_file(&next, &shared.shutdown, &reporter, &mut watch_for_sleep);
+        // A read that fails on waking reports the sleep before the failure
+        watch_for_sleep();
+        let event = match result {
+            Ok(bytes) => {
+                reporter.finish(format!("{} bytes", bytes));
+                DownloadEvent::Completed {
+                    path: next,
+                    bytes,
+                    elapsed: start.elapsed(),
+                }
+            }
+            Err(error) => {
+                reporter.fail(error.clone());
+                DownloadEvent::Failed { path: next, error }
+            }
+        };
+        // Report before clearing `active` so the queue never looks idle
+        // while a completion event is still in flight
+        let disconnected = sender.send(event).is_err();
+        shared.state.lock().unwrap().active = None;
+        if disconnected {
+            return;
+        }
+    }
+}
+
+/// Read a file end-to-end so the cloud provider downloads its contents,
+/// calling `after_read` after each chunk
+pub fn hydrate_file(path: &Path, cancel: &AtomicBool, reporter: &ProgressReporter, after_read: &mut dyn FnMut()) -> Result<u64, String> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+    let size = file.metadata().ok().map(|m| m.len());
+    let mut buffer = vec![0u8; READ_CHUNK_SIZE];
+    let mut total = 0u64;
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            return Err("Download cancelled".to_string());
+        }
+        let read = file.read(&mut buffer)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if read == 0 {
+            return Ok(total);
+        }
+        total += read as u64;
+        reporter.progress_bytes(total, size);
+        after_read();
+    }
+}
+
+/// Count on-demand files and their combined download size in bytes
+pub fn summarize_on_demand(files: &[FileInfo]) -> (usize, u64) {
+    files
+        .iter()
+        .filter(|f| f.will_trigger_download())
+        .fold((0, 0), |(count, bytes), f| {
+            (count + 1, bytes + f.estimated_download_size.unwrap_or(0))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use crate::file_locality::FileLocalityStatus;
+
+    #[test]
+    fn test_summarize_on_demand() {
+        let files = vec![
+            FileInfo {
+                path: PathBuf::from("a.jpg"),
+                locality_status: FileLocalityStatus::OnDemand,
+                estimated_download_size: Some(1000),
+                provider: None,
+                render_estimate: None,
+            },
+            FileInfo {
+                path: PathBuf::from("b.jpg"),
+                locality_status: FileLocalityStatus::Local,
+                estimated_download_size: None,
+                provider: None,
+                render_estimate: None,
+            },
+            FileInfo {
+                path: PathBuf::from("c.jpg"),
+                locality_status: FileLocalityStatus::OnDemand,
+                estimated_download_size: Some(500),
+                provider: None,
+                render_estimate: None,
+            },
+        ];
+        assert_eq!(summarize_on_demand(&files), (2, 1500));
+    }
+
+    #[test]
+    fn test_queue_hydrates_local_file() {
+        let dir = TempDir::new("download_queue");
+        let path = dir.join("file.bin");
+        std::fs::write(&path, vec![7u8; 200_000]).unwrap();
+
+        let mut queue = DownloadQueue::new();
+        queue.enqueue(path.clone());
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut completed_bytes = None;
+        while completed_bytes.is_none() && Instant::now() < deadline {
+            for event in queue.poll_events() {
+                if let DownloadEvent::Completed { bytes, .. } = event {
+                    completed_bytes = Some(bytes);
+                }
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(completed_bytes, Some(200_000));
+        assert_eq!(queue.progress(), (1, 1), "the finished batch stays counted until the next one");
+        queue.enqueue(path.clone());
+        assert_eq!(queue.progress(), (0, 1));
+        while queue.is_busy() {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
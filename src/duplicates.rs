@@ -0,0 +1,166 @@
+//! Near-duplicate detection with perceptual hashes
+//!
+//! Each image is reduced to a 64-bit difference hash (dHash): shrunk to 9x8
+//! grayscale pixels, with one bit per horizontally adjacent pair saying which
+//! is brighter. Resized, recompressed or slightly edited copies get hashes a
+//! few bits apart, so images within a small Hamming distance are grouped.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "gui")]
+use image::DynamicImage;
+
+/// Default largest Hamming distance between hashes of duplicates
+#[cfg(feature = "gui")]
+pub const DEFAULT_MAX_DISTANCE: u32 = 5;
+
+/// A hashed image
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashedImage {
+    pub path: PathBuf,
+    pub hash: u64,
+    pub width: u32,
+    pub height: u32,
+    pub file_size: u64,
+}
+
+#[cfg(feature = "gui")]
+impl HashedImage {
+    pub fn pixels(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+}
+
+#[cfg(feature = "gui")]
+pub fn dhash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, image::imageops::FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y).0[0] < small.get_pixel(x + 1, y).0[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+#[cfg(feature = "gui")]
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+#[cfg(feature = "gui")]
+pub fn hash_file(path: &Path) -> Result<HashedImage, String> {
+    let image = image::open(path).map_err(|e| format!("Failed to decode {}: {}", path.display(), e))?;
+    Ok(HashedImage {
+        path: path.to_path_buf(),
+        hash: dhash(&image),
+        width: image.width(),
+        height: image.height(),
+        file_size: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+    })
+}
+
+/// Groups of two or more images whose hashes are within `max_distance` of
+/// another member, largest image first
+#[cfg(feature = "gui")]
+pub fn group_duplicates(images: &[HashedImage], max_distance: u32) -> Vec<Vec<HashedImage>> {
+    // Union-find over every close pair
+    let mut parent: Vec<usize> = (0..images.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..images.len() {
+        for j in i + 1..images.len() {
+            if hamming_distance(images[i].hash, images[j].hash) <= max_distance {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<HashedImage>> = Vec::new();
+    let mut group_of_root = std::collections::HashMap::new();
+    for (i, image) in images.iter().enumerate() {
+        let group = *group_of_root.entry(root(&mut parent, i)).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(image.clone());
+    }
+    groups.retain(|group| group.len() > 1);
+    for group in &mut groups {
+        group.sort_by(|a, b| b.pixels().cmp(&a.pixels()).then(b.file_size.cmp(&a.file_size)));
+    }
+    groups
+}
+
+/// Number of `groups` with every member in `marked`, which would leave no copy
+/// of that image behind
+#[cfg(feature = "gui")]
+pub fn groups_left_empty(groups: &[Vec<HashedImage>], marked: &std::collections::HashSet<PathBuf>) -> usize {
+    groups.iter().filter(|group| group.iter().all(|image| marked.contains(&image.path))).count()
+}
+
+/// Where `path` goes in `folder`: its own name, or a numbered one while
+/// `taken` says the name is in use. Creates `folder`.
+pub fn target_in(path: &Path, folder: &Path, taken: impl Fn(&Path) -> bool) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(folder).map_err(|e| format!("Failed to create {}: {}", folder.display(), e))?;
+    let name = path.file_name().ok_or_else(|| format!("{} has no file name", path.display()))?;
+    let mut target = folder.join(name);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let extension = path.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+    let mut copy = 1;
+    while taken(&target) {
+        copy += 1;
+        target = folder.join(format!("{} ({}){}", stem, copy, extension));
+    }
+    Ok(target)
+}
+
+#[cfg(all(test, feature = "gui"))]
+mod tests {
+    use super::*;
+
+    fn gradient(width: u32, height: u32, flip: bool) -> DynamicImage {
+        DynamicImage::ImageLuma8(image::GrayImage::from_fn(width, height, |x, _| {
+            let value = (x * 255 / (width - 1)) as u8;
+            image::Luma([if flip { 255 - value } else { value }])
+        }))
+    }
+
+    #[test]
+    fn test_resized_copies_group_together() {
+        let hashed = |name: &str, image: &DynamicImage| HashedImage {
+            path: PathBuf::from(name),
+            hash: dhash(image),
+            width: image.width(),
+            height: image.height(),
+            file_size: 0,
+        };
+        let images = [
+            hashed("small.png", &gradient(64, 48, false)),
+            hashed("flipped.png", &gradient(64, 48, true)),
+            hashed("large.png", &gradient(640, 480, false)),
+        ];
+        assert_eq!(hamming_distance(images[0].hash, images[2].hash), 0);
+        assert_eq!(hamming_distance(images[0].hash, images[1].hash), 64);
+
+        let groups = group_duplicates(&images, DEFAULT_MAX_DISTANCE);
+        assert_eq!(groups.len(), 1);
+        let names: Vec<_> = groups[0].iter().map(|image| image.path.to_str().unwrap()).collect();
+        assert_eq!(names, ["large.png", "small.png"]);
+
+        let mut marked = std::collections::HashSet::from([PathBuf::from("small.png")]);
+        assert_eq!(groups_left_empty(&groups, &marked), 0);
+        marked.insert(PathBuf::from("large.png"));
+        assert_eq!(groups_left_empty(&groups, &marked), 1);
+    }
+}
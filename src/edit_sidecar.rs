@@ -0,0 +1,180 @@
+//! Edits kept beside the image instead of in it
+//!
+//! Rotations, crops, adjustments, annotations and redactions of an image are
+//! saved to `photo.jpg.edits` and replayed when it is shown again, so the
+//! original file is never modified. Each line is one edit, oldest first:
+//!
+//! ```text
+//! rotate
+//! crop <min x> <min y> <max x> <max y>
+//! adjust <brightness> <contrast> <saturation>
+//! rectangle <min x> <min y> <max x> <max y> <line width> <#rrggbb[aa]>
+//! pixelate <min x> <min y> <max x> <max y> <strength>
+//! blur <min x> <min y> <max x> <max y> <strength>
+//! ```
+//!
+//! Regions and line widths are in the pixels of the original file, after the
+//! rotations and crops before them. Adjustments are percentages from -100
+//! to 100.
+//!
+//! Exporting a flattened copy writes the edited pixels to a new PNG, tagged
+//! with the original's color profile.
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{Color32, ColorImage};
+
+use crate::color_profile::{self, ColorProfile};
+use crate::adjustment::Adjustment;
+use crate::annotation::Annotation;
+use crate::color_stats::{PixelRect, css_hex};
+use crate::egui_adapter;
+use crate::history::ImageEdit;
+use crate::redaction::{Redaction, RedactionStyle};
+
+const EXTENSION: &str = "edits";
+
+pub fn sidecar_path(image: &Path) -> PathBuf {
+    let mut name = image.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(EXTENSION);
+    image.with_file_name(name)
+}
+
+fn edit_to_line(edit: &ImageEdit) -> String {
+    match edit {
+        ImageEdit::RotateClockwise => "rotate".to_string(),
+        ImageEdit::Crop(region) => format!("crop {} {} {} {}", region.min_x, region.min_y, region.max_x, region.max_y),
+        ImageEdit::Adjust(Adjustment { brightness, contrast, saturation }) => format!("adjust {} {} {}", brightness, contrast, saturation),
+        ImageEdit::Annotate(Annotation { region, color, width }) => format!(
+            "rectangle {} {} {} {} {} {}",
+            region.min_x, region.min_y, region.max_x, region.max_y, width, css_hex(*color)
+        ),
+        ImageEdit::Redact(Redaction { region, style, strength }) => format!(
+            "{} {} {} {} {} {}",
+            style.label().to_lowercase(), region.min_x, region.min_y, region.max_x, region.max_y, strength
+        ),
+    }
+}
+
+/// A color written by [`css_hex`]
+fn parse_hex_color(word: &str) -> Option<Color32> {
+    let hex = word.strip_prefix('#')?;
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return None;
+    }
+    let channels: Vec<u8> = (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok()).collect::<Option<_>>()?;
+    let alpha = channels.get(3).copied().unwrap_or(255);
+    Some(Color32::from_rgba_unmultiplied(channels[0], channels[1], channels[2], alpha))
+}
+
+fn edit_from_line(line: &str) -> Option<ImageEdit> {
+    let mut words = line.split_whitespace();
+    let kind = words.next()?;
+    let words: Vec<&str> = words.collect();
+    let region = |corners: &[&str]| -> Option<PixelRect> {
+        let [min_x, min_y, max_x, max_y] = corners else {
+            return None;
+        };
+        Some(PixelRect { min_x: min_x.parse().ok()?, min_y: min_y.parse().ok()?, max_x: max_x.parse().ok()?, max_y: max_y.parse().ok()? })
+    };
+    match (kind, &words[..]) {
+        ("rotate", []) => Some(ImageEdit::RotateClockwise),
+        ("crop", corners) => Some(ImageEdit::Crop(region(corners)?)),
+        ("adjust", [brightness, contrast, saturation]) => Some(ImageEdit::Adjust(Adjustment {
+            brightness: brightness.parse().ok()?,
+            contrast: contrast.parse().ok()?,
+            saturation: saturation.parse().ok()?,
+        })),
+        ("rectangle", [corners @ .., width, color]) => Some(ImageEdit::Annotate(Annotation {
+            region: region(corners)?,
+            color: parse_hex_color(color)?,
+            width: width.parse().ok()?,
+        })),
+        ("pixelate" | "blur", [corners @ .., strength]) => Some(ImageEdit::Redact(Redaction {
+            region: region(corners)?,
+            style: if kind == "blur" { RedactionStyle::Blur } else { RedactionStyle::Pixelate },
+            strength: strength.parse().ok()?,
+        })),
+        _ => None,
+    }
+}
+
+pub fn to_text(edits: &[ImageEdit]) -> String {
+    edits.iter().map(|edit| edit_to_line(edit) + "\n").collect()
+}
+
+/// Edits in a sidecar; lines that aren't understood are skipped
+pub fn parse(text: &str) -> Vec<ImageEdit> {
+    text.lines().map(str::trim).filter(|line| !line.starts_with('#')).filter_map(edit_from_line).collect()
+}
+
+/// Edits saved for `image`; none if there is no sidecar
+pub fn load(image: &Path) -> Vec<ImageEdit> {
+    std::fs::read_to_string(sidecar_path(image)).map(|text| parse(&text)).unwrap_or_default()
+}
+
+/// Save the edits of `image`, removing the sidecar when there are none
+pub fn save(image: &Path, edits: &[ImageEdit]) -> Result<(), String> {
+    let path = sidecar_path(image);
+    if edits.is_empty() {
+        return match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to remove {}: {}", path.display(), e)),
+            _ => Ok(()),
+        };
+    }
+    std::fs::write(&path, to_text(edits)).map_err(|e| format!("Failed to save edits to {}: {}", path.display(), e))
+}
+
+/// Write the edited pixels of `image` next to it as `<name>_edited.png`,
+/// numbered if that exists; returns where
+pub fn export_flattened(pixels: &ColorImage, image: &Path) -> Result<PathBuf, String> {
+    let stem = image.file_stem().unwrap_or_default().to_string_lossy();
+    let mut output = image.with_file_name(format!("{}_edited.png", stem));
+    let mut copy = 1;
+    while output.exists() {
+        copy += 1;
+        output = image.with_file_name(format!("{}_edited ({}).png", stem, copy));
+    }
+    let pixels = egui_adapter::from_color_image(pixels).into_rgba();
+    color_profile::save_png(&pixels, ColorProfile::read(image).as_ref(), &output)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edits_round_trip() {
+        let edits = vec![
+            ImageEdit::RotateClockwise,
+            ImageEdit::Redact(Redaction {
+                region: PixelRect { min_x: 10, min_y: 20, max_x: 110, max_y: 60 },
+                style: RedactionStyle::Pixelate,
+                strength: 12,
+            }),
+            ImageEdit::Crop(PixelRect { min_x: 0, min_y: 5, max_x: 300, max_y: 200 }),
+            ImageEdit::Adjust(Adjustment { brightness: 15, contrast: -20, saturation: 0 }),
+            ImageEdit::Annotate(Annotation {
+                region: PixelRect { min_x: 40, min_y: 40, max_x: 90, max_y: 70 },
+                color: Color32::from_rgb(255, 40, 40),
+                width: 4,
+            }),
+            ImageEdit::Annotate(Annotation {
+                region: PixelRect { min_x: 1, min_y: 2, max_x: 3, max_y: 4 },
+                color: Color32::from_rgba_unmultiplied(0, 0, 255, 128),
+                width: 1,
+            }),
+        ];
+        let text = to_text(&edits);
+        assert_eq!(
+            text,
+            "rotate\npixelate 10 20 110 60 12\ncrop 0 5 300 200\nadjust 15 -20 0\n\
+             rectangle 40 40 90 70 4 #ff2828\nrectangle 1 2 3 4 1 #0000ff80\n"
+        );
+        let broken = "blur 1 2 3\nsharpen 5\ncrop 1 2 3\nadjust 1 2 x\nrectangle 1 2 3 4 5\nrectangle 1 2 3 4 5 #12345\nrotate 90\n";
+        assert_eq!(parse(&format!("# edits\n{}{}", text, broken)), edits);
+        assert_eq!(sidecar_path(Path::new("shots/a.jpg")), Path::new("shots/a.jpg.edits"));
+    }
+}
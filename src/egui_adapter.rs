@@ -0,0 +1,49 @@
+//! Turning decoded images into egui textures
+//!
+//! The only place decoding meets egui: everything before it works on
+//! [`DecodedImage`] buffers.
+
+use eframe::egui::{self, ColorImage, TextureHandle};
+
+use crate::decoded_image::DecodedImage;
+
+/// Sampling for displayed images: linear, with mipmaps so that fitted photos
+/// and thumbnails shown far below their size don't shimmer
+pub const SMOOTH_TEXTURE: egui::TextureOptions = egui::TextureOptions::LINEAR.with_mipmap_mode(Some(egui::TextureFilter::Linear));
+
+pub fn to_color_image(image: &DecodedImage) -> ColorImage {
+    ColorImage::from_rgba_unmultiplied([image.width as usize, image.height as usize], &image.pixels)
+}
+
+/// Pixels rendered by the core, such as SVG previews, ready for a texture
+pub fn from_rgba_image(image: &image::RgbaImage) -> ColorImage {
+    ColorImage::from_rgba_unmultiplied([image.width() as usize, image.height() as usize], image.as_raw())
+}
+
+/// Pixels shown in the viewer, for saving or handing to other programs
+pub fn from_color_image(image: &ColorImage) -> DecodedImage {
+    let [width, height] = image.size;
+    DecodedImage {
+        width: width as u32,
+        height: height as u32,
+        pixels: image.pixels.iter().flat_map(|pixel| pixel.to_srgba_unmultiplied()).collect(),
+    }
+}
+
+/// Upload `image` as a texture called `name`
+pub fn load_texture(ctx: &egui::Context, name: impl Into<String>, image: &DecodedImage) -> TextureHandle {
+    ctx.load_texture(name, to_color_image(image), SMOOTH_TEXTURE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_image_round_trip() {
+        let image = DecodedImage { width: 2, height: 1, pixels: vec![255, 0, 0, 255, 0, 0, 255, 255] };
+        let color_image = to_color_image(&image);
+        assert_eq!(color_image.pixels[1], egui::Color32::BLUE);
+        assert_eq!(from_color_image(&color_image), image);
+    }
+}
@@ -0,0 +1,219 @@
+//! Opening images in other programs for editing
+//!
+//! Editors are shell commands with `{path}` standing for the image, each
+//! offered for a list of formats (or all of them). Files opened in an editor
+//! are watched, and the preview reloads once a save has settled. On-demand
+//! files are downloaded before the editor is started, since editors that
+//! open a placeholder can save over the cloud copy with an empty file.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use crate::hot_folder::{POLL_INTERVAL, SETTLE_TIME};
+use crate::preferences::Preferences;
+use crate::share::{expand_command_template, spawn_shell};
+
+/// Preference keys are numbered from 1: `external_editor.1`, ...
+const PREFERENCE_PREFIX: &str = "external_editor.";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalEditor {
+    pub name: String,
+    /// Runs in a shell; `{path}` is replaced with the quoted image path
+    pub command: String,
+    /// Lowercase extensions it is offered for; empty offers it for all
+    pub extensions: Vec<String>,
+}
+
+impl ExternalEditor {
+    pub fn new(name: &str, command: &str, extensions: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            command: command.to_string(),
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+        }
+    }
+
+    pub fn handles(&self, path: &Path) -> bool {
+        self.extensions.is_empty()
+            || path.extension().is_some_and(|ext| self.extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+    }
+
+    /// Start the editor on `path` without waiting for it
+    pub fn launch(&self, path: &Path) -> Result<(), String> {
+        let command_line = if self.command.contains("{path}") {
+            expand_command_template(&self.command, path)
+        } else {
+            expand_command_template(&format!("{} {{path}}", self.command), path)
+        };
+        spawn_shell(&command_line)
+            .map_err(|e| format!("Failed to start {}: {}", self.name, e))
+    }
+
+    /// `name|ext,ext|command`; the command comes last as it may contain `|`
+    fn to_preference(&self) -> String {
+        format!("{}|{}|{}", self.name, self.extensions.join(","), self.command)
+    }
+
+    fn from_preference(value: &str) -> Option<Self> {
+        let mut parts = value.splitn(3, '|');
+        let name = parts.next()?.trim();
+        let extensions = parts.next()?;
+        let command = parts.next()?.trim();
+        if name.is_empty() || command.is_empty() {
+            return None;
+        }
+        Some(Self {
+            name: name.to_string(),
+            command: command.to_string(),
+            extensions: parse_extensions(extensions),
+        })
+    }
+}
+
+/// Extensions from a comma separated list like `png, .JPG`
+pub fn parse_extensions(text: &str) -> Vec<String> {
+    text.split(',').map(|ext| ext.trim().trim_start_matches('.').to_lowercase()).filter(|ext| !ext.is_empty()).collect()
+}
+
+/// Editors offered before any are configured
+pub fn default_editors() -> Vec<ExternalEditor> {
+    #[cfg(windows)]
+    return vec![ExternalEditor::new("Paint", "mspaint {path}", &["png", "jpg", "jpeg", "bmp", "gif"])];
+    #[cfg(target_os = "macos")]
+    return vec![ExternalEditor::new("Preview", "open -a Preview {path}", &[])];
+    #[cfg(not(any(windows, target_os = "macos")))]
+    return vec![
+        ExternalEditor::new("GIMP", "gimp {path}", &["png", "jpg", "jpeg", "bmp", "gif"]),
+        ExternalEditor::new("Inkscape", "inkscape {path}", &["svg"]),
+    ];
+}
+
+/// Editors saved in the preferences, or the defaults if none were
+pub fn load(preferences: &Preferences) -> Vec<ExternalEditor> {
+    let saved: Vec<ExternalEditor> = (1..)
+        .map_while(|number| preferences.get::<String>(&format!("{}{}", PREFERENCE_PREFIX, number)))
+        .filter_map(|value| ExternalEditor::from_preference(&value))
+        .collect();
+    if saved.is_empty() && preferences.get::<String>(&format!("{}count", PREFERENCE_PREFIX)).is_none() {
+        default_editors()
+    } else {
+        saved
+    }
+}
+
+pub fn save_to(preferences: &mut Preferences, editors: &[ExternalEditor]) {
+    let mut number = 1;
+    while preferences.get::<String>(&format!("{}{}", PREFERENCE_PREFIX, number)).is_some() {
+        preferences.remove(&format!("{}{}", PREFERENCE_PREFIX, number));
+        number += 1;
+    }
+    for (number, editor) in (1..).zip(editors) {
+        preferences.set(&format!("{}{}", PREFERENCE_PREFIX, number), editor.to_preference());
+    }
+    // Remembers that the list was configured, even when it was emptied
+    preferences.set(&format!("{}count", PREFERENCE_PREFIX), editors.len());
+}
+
+/// Size and modification time of a file
+type Stamp = (u64, Option<SystemTime>);
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()))
+}
+
+/// Notices when files opened in an editor are saved
+#[derive(Debug, Default)]
+pub struct EditWatcher {
+    /// Each file as it was when opened or last reported
+    watched: HashMap<PathBuf, Option<Stamp>>,
+    /// Changes still being written, and since when they have looked like this
+    changing: HashMap<PathBuf, (Option<Stamp>, Instant)>,
+    last_poll: Option<Instant>,
+}
+
+impl EditWatcher {
+    pub fn watch(&mut self, path: &Path) {
+        self.watched.insert(path.to_path_buf(), stamp(path));
+        self.changing.remove(path);
+    }
+
+    pub fn is_watching(&self) -> bool {
+        !self.watched.is_empty()
+    }
+
+    /// Files saved since the last call, once their saves have settled.
+    /// Looks at the files at most every [`POLL_INTERVAL`].
+    pub fn poll(&mut self, now: Instant) -> Vec<PathBuf> {
+        if self.last_poll.is_some_and(|last| now.duration_since(last) < POLL_INTERVAL) {
+            return Vec::new();
+        }
+        self.last_poll = Some(now);
+        let stamps: Vec<(PathBuf, Option<Stamp>)> = self.watched.keys().map(|path| (path.clone(), stamp(path))).collect();
+        stamps.into_iter().filter(|(path, current)| self.update(path, *current, now)).map(|(path, _)| path).collect()
+    }
+
+    /// Whether `path`, now at `current`, has been saved and stopped changing
+    fn update(&mut self, path: &Path, current: Option<Stamp>, now: Instant) -> bool {
+        // Editors that save through a temporary file briefly remove the original
+        if current.is_none() || self.watched.get(path) == Some(&current) {
+            self.changing.remove(path);
+            return false;
+        }
+        match self.changing.get(path) {
+            Some((seen, since)) if *seen == current => {
+                if now.duration_since(*since) < SETTLE_TIME {
+                    return false;
+                }
+                self.changing.remove(path);
+                self.watched.insert(path.to_path_buf(), current);
+                true
+            }
+            _ => {
+                self.changing.insert(path.to_path_buf(), (current, now));
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editors_round_trip_through_preferences() {
+        let editors = vec![
+            ExternalEditor::new("Krita", "krita {path} | tee log", &["png", "jpg"]),
+            ExternalEditor::new("Any", "edit", &[]),
+        ];
+        let mut preferences = Preferences::default();
+        assert_eq!(load(&preferences), default_editors());
+        save_to(&mut preferences, &editors);
+        assert_eq!(load(&preferences), editors);
+        save_to(&mut preferences, &editors[1..]);
+        assert_eq!(load(&preferences), editors[1..]);
+        save_to(&mut preferences, &[]);
+        assert!(load(&preferences).is_empty());
+
+        assert!(editors[0].handles(Path::new("a.PNG")) && !editors[0].handles(Path::new("a.svg")));
+        assert!(editors[1].handles(Path::new("a.svg")));
+    }
+
+    #[test]
+    fn test_saves_are_reported_once_settled() {
+        let start = Instant::now();
+        let path = Path::new("photo.jpg");
+        let mut watcher = EditWatcher::default();
+        watcher.watched.insert(path.to_path_buf(), Some((10, None)));
+
+        assert!(!watcher.update(path, Some((10, None)), start));
+        assert!(!watcher.update(path, None, start), "replaced through a temporary file");
+        assert!(!watcher.update(path, Some((20, None)), start));
+        assert!(!watcher.update(path, Some((30, None)), start + SETTLE_TIME), "still growing");
+        assert!(watcher.update(path, Some((30, None)), start + SETTLE_TIME * 2));
+        assert!(!watcher.update(path, Some((30, None)), start + SETTLE_TIME * 3), "reported once");
+    }
+}
@@ -0,0 +1,141 @@
+//! Pinned folders and images, kept between sessions
+//!
+//! Favorites are stored one path per line in `favorites.txt` inside the
+//! config directory, in the order shown in the side panel.
+
+use std::path::{Path, PathBuf};
+
+use crate::settings::config_dir;
+
+const FAVORITES_FILE: &str = "favorites.txt";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Favorites {
+    items: Vec<PathBuf>,
+}
+
+impl Favorites {
+    pub fn from_paths(items: Vec<PathBuf>) -> Self {
+        let mut favorites = Self::default();
+        for item in items {
+            favorites.add(item);
+        }
+        favorites
+    }
+
+    pub fn items(&self) -> &[PathBuf] {
+        &self.items
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.items.iter().any(|item| item == path)
+    }
+
+    /// Pin a path at the end of the list. Returns false if it was already pinned.
+    pub fn add(&mut self, path: PathBuf) -> bool {
+        if self.contains(&path) {
+            return false;
+        }
+        self.items.push(path);
+        true
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.items.retain(|item| item != path);
+    }
+
+    /// Move the favorite at `from` in front of the one at `before`, or to the
+    /// end if `before` is past it, as when dropping it on that row
+    pub fn move_item(&mut self, from: usize, before: usize) {
+        if from >= self.items.len() || from == before {
+            return;
+        }
+        let item = self.items.remove(from);
+        // Everything after `from` moved up by one
+        let to = if before > from { before - 1 } else { before };
+        self.items.insert(to.min(self.items.len()), item);
+    }
+
+    pub fn parse(text: &str) -> Self {
+        Self::from_paths(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(PathBuf::from)
+                .collect(),
+        )
+    }
+
+    pub fn to_text(&self) -> String {
+        self.items.iter().map(|item| format!("{}\n", item.display())).collect()
+    }
+
+    pub fn load_from(file: &Path) -> Result<Self, String> {
+        match std::fs::read_to_string(file) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read favorites: {}", e)),
+        }
+    }
+
+    pub fn save_to(&self, file: &Path) -> Result<(), String> {
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        std::fs::write(file, self.to_text()).map_err(|e| format!("Failed to save favorites: {}", e))
+    }
+
+    /// Favorites saved by the last session, if any
+    pub fn load() -> Self {
+        default_file()
+            .and_then(|file| Self::load_from(&file).map_err(|e| eprintln!("Warning: {}", e)).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = default_file().ok_or("No config directory available")?;
+        self.save_to(&file)
+    }
+}
+
+fn default_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(FAVORITES_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_add_and_reorder() {
+        let mut favorites = Favorites::default();
+        assert!(favorites.add(PathBuf::from("photos")));
+        assert!(favorites.add(PathBuf::from("icons")));
+        assert!(favorites.add(PathBuf::from("icons/logo.svg")));
+        assert!(!favorites.add(PathBuf::from("photos")));
+
+        favorites.move_item(2, 0);
+        assert_eq!(favorites.items(), [PathBuf::from("icons/logo.svg"), PathBuf::from("photos"), PathBuf::from("icons")]);
+        favorites.move_item(0, 10);
+        assert_eq!(favorites.items().last(), Some(&PathBuf::from("icons/logo.svg")));
+        // Dropped on the last row, so it goes in front of it
+        favorites.move_item(0, 2);
+        assert_eq!(favorites.items(), [PathBuf::from("icons"), PathBuf::from("photos"), PathBuf::from("icons/logo.svg")]);
+
+        favorites.remove(Path::new("photos"));
+        assert_eq!(favorites.items().len(), 2);
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let dir = TempDir::new("favorites");
+        let file = dir.join(FAVORITES_FILE);
+        let favorites = Favorites::from_paths(vec![PathBuf::from("/photos/2024"), PathBuf::from("/icons/a b.svg")]);
+        favorites.save_to(&file).unwrap();
+        assert_eq!(Favorites::load_from(&file), Ok(favorites));
+
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(Favorites::load_from(&file), Ok(Favorites::default()));
+    }
+}
@@ -1,6 +1,24 @@
 //! File locality detection and availability status
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+use crate::image_processing::RenderEstimate;
+use crate::progress::{OperationKind, ProgressSink};
+
+/// Number of statuses sent to the UI at a time during a background refresh
+const REFRESH_BATCH_SIZE: usize = 64;
+
+/// How long the parsed mount table is reused; mounts change rarely
+#[cfg(target_os = "linux")]
+const MOUNTS_MAX_AGE: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// How long a download's progress may stand still before the file counts as
+/// only partly present rather than downloading
+#[cfg(feature = "gui")]
+const HYDRATION_STALL: std::time::Duration = std::time::Duration::from_secs(10);
 
 // File locality status tracking
 #[derive(Debug, Clone, PartialEq)]
@@ -9,6 +27,10 @@ pub enum FileLocalityStatus {
     Local,
     /// File is on-demand and will trigger download when accessed
     OnDemand,
+    /// File is being downloaded by the cloud provider right now
+    Hydrating { percent: u8 },
+    /// File is on a network share, so every read waits on the network
+    Network,
     /// Cannot determine status
     Unknown,
 }
@@ -18,30 +40,135 @@ impl FileLocalityStatus {
         match self {
             FileLocalityStatus::Local => "💾",
             FileLocalityStatus::OnDemand => "☁️",
+            FileLocalityStatus::Hydrating { .. } => "⬇️",
+            FileLocalityStatus::Network => "🌐",
             FileLocalityStatus::Unknown => "❓",
         }
     }
     
+    /// Short text shown next to or instead of the icon
+    pub fn label(&self) -> String {
+        match self {
+            FileLocalityStatus::Local => "Local".to_string(),
+            FileLocalityStatus::OnDemand => "On-demand".to_string(),
+            FileLocalityStatus::Hydrating { percent } => format!("Downloading {}%", percent),
+            FileLocalityStatus::Network => "Network".to_string(),
+            FileLocalityStatus::Unknown => "Unknown".to_string(),
+        }
+    }
+
     pub fn description(&self) -> &'static str {
         match self {
             FileLocalityStatus::Local => "Local file (immediately available)",
             FileLocalityStatus::OnDemand => "On-demand file (will download when accessed)",
+            FileLocalityStatus::Hydrating { .. } => "Downloading in the background",
+            FileLocalityStatus::Network => "Network share (read over the network when opened)",
             FileLocalityStatus::Unknown => "Unknown availability status",
         }
     }
 }
 
+/// Cloud storage provider that owns a synced file
+#[derive(Debug, Clone, PartialEq)]
+pub enum CloudProvider {
+    OneDrive,
+    Dropbox,
+    GoogleDrive,
+    ICloud,
+    /// Any other provider registered as a sync root, by its reported name
+    Other(String),
+}
+
+impl CloudProvider {
+    pub fn name(&self) -> &str {
+        match self {
+            CloudProvider::OneDrive => "OneDrive",
+            CloudProvider::Dropbox => "Dropbox",
+            CloudProvider::GoogleDrive => "Google Drive",
+            CloudProvider::ICloud => "iCloud Drive",
+            CloudProvider::Other(name) => name,
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            CloudProvider::OneDrive => "☁️",
+            CloudProvider::Dropbox => "📦",
+            CloudProvider::GoogleDrive => "🔺",
+            CloudProvider::ICloud => "🍏",
+            CloudProvider::Other(_) => "🌐",
+        }
+    }
+
+    /// Map a provider name reported by the OS (e.g. a Windows sync root) to a known provider
+    pub fn from_provider_name(name: &str) -> Self {
+        let lower = name.to_lowercase();
+        if lower.contains("onedrive") || lower.contains("sharepoint") {
+            CloudProvider::OneDrive
+        } else if lower.contains("dropbox") {
+            CloudProvider::Dropbox
+        } else if lower.contains("google") {
+            CloudProvider::GoogleDrive
+        } else if lower.contains("icloud") {
+            CloudProvider::ICloud
+        } else {
+            CloudProvider::Other(name.to_string())
+        }
+    }
+
+    /// The provider whose client mounted a filesystem of type `fs_type`
+    /// (from the mount table), if it's a remote FUSE mount
+    pub fn from_mount(fs_type: &str) -> Option<Self> {
+        if !is_remote_fuse_filesystem(fs_type) {
+            return None;
+        }
+        Some(match fs_type {
+            "fuse.onedriver" => CloudProvider::OneDrive,
+            "fuse.dbxfs" => CloudProvider::Dropbox,
+            "fuse.google-drive-ocamlfuse" => CloudProvider::GoogleDrive,
+            // rclone and the like, named after the client
+            _ => CloudProvider::Other(fs_type.trim_start_matches("fuse.").to_string()),
+        })
+    }
+}
+
+/// Determine which cloud provider, if any, syncs the given file: from the
+/// sync root it's registered under, or on Linux the FUSE mount it's on
+pub fn detect_cloud_provider(path: &Path) -> Option<CloudProvider> {
+    crate::onedrive::sync_root_provider_name(path)
+        .map(|name| CloudProvider::from_provider_name(&name))
+        .or_else(|| mounted_provider(path))
+}
+
+#[cfg(target_os = "linux")]
+fn mounted_provider(path: &Path) -> Option<CloudProvider> {
+    if !is_on_fuse(path) {
+        return None;
+    }
+    let absolute = std::fs::canonicalize(path).ok()?;
+    filesystem_type_for_path(&cached_mounts(), &absolute).and_then(CloudProvider::from_mount)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn mounted_provider(_path: &Path) -> Option<CloudProvider> {
+    None
+}
+
 #[derive(Debug, Clone)]
 pub struct FileInfo {
     pub path: PathBuf,
     pub locality_status: FileLocalityStatus,
     pub estimated_download_size: Option<u64>, // Size in bytes if it needs to be downloaded
+    pub provider: Option<CloudProvider>,
+    /// Cached by [`cached_render_time`](crate::image_processing::cached_render_time)
+    pub render_estimate: Option<RenderEstimate>,
 }
 
 impl FileInfo {
     pub fn new(path: PathBuf) -> Self {
         let locality_status = get_file_locality_status(&path);
-        let estimated_download_size = if matches!(locality_status, FileLocalityStatus::OnDemand) {
+        let provider = detect_cloud_provider(&path);
+        let estimated_download_size = if matches!(locality_status, FileLocalityStatus::OnDemand | FileLocalityStatus::Hydrating { .. }) {
             // Get the reported file size (which is the full file size for on-demand files)
             std::fs::metadata(&path).ok().map(|m| m.len())
         } else {
@@ -52,11 +179,128 @@ impl FileInfo {
             path,
             locality_status,
             estimated_download_size,
+            provider,
+            render_estimate: None,
         }
     }
     
     pub fn will_trigger_download(&self) -> bool {
-        matches!(self.locality_status, FileLocalityStatus::OnDemand)
+        matches!(self.locality_status, FileLocalityStatus::OnDemand | FileLocalityStatus::Hydrating { .. })
+    }
+
+    /// Whether reading the file waits on a download or the network; browsing
+    /// never reads such files, only opening them does
+    pub fn is_slow_to_read(&self) -> bool {
+        self.will_trigger_download() || self.locality_status == FileLocalityStatus::Network
+    }
+
+    /// Locality description including the owning cloud provider, for tooltips
+    pub fn status_description(&self) -> String {
+        let status = match self.locality_status {
+            FileLocalityStatus::Hydrating { percent } => format!("{} ({}%)", self.locality_status.description(), percent),
+            _ => self.locality_status.description().to_string(),
+        };
+        match &self.provider {
+            Some(provider) => format!("{}\nProvider: {} {}", status, provider.icon(), provider.name()),
+            None => status,
+        }
+    }
+}
+
+/// New locality status for a file, produced by a background refresh
+#[derive(Debug, Clone)]
+pub struct LocalityUpdate {
+    pub path: PathBuf,
+    pub status: FileLocalityStatus,
+    pub estimated_download_size: Option<u64>,
+}
+
+/// Re-checks the locality of many files on a background thread, streaming
+/// results back in batches. Dropping it cancels the refresh.
+pub struct LocalityRefresh {
+    receiver: Receiver<Vec<LocalityUpdate>>,
+    cancel: Arc<AtomicBool>,
+    processed: usize,
+    total: usize,
+    finished: bool,
+}
+
+impl LocalityRefresh {
+    pub fn start(paths: Vec<PathBuf>, progress: &ProgressSink) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let total = paths.len();
+        let reporter = progress.start(OperationKind::Scan, "Refreshing file status");
+
+        let worker_cancel = Arc::clone(&cancel);
+        let _ = std::thread::Builder::new()
+            .name("locality-refresh".to_string())
+            .spawn(move || {
+                let mut checked = 0;
+                for chunk in paths.chunks(REFRESH_BATCH_SIZE) {
+                    if worker_cancel.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    let batch = chunk
+                        .iter()
+                        .map(|path| {
+                            let status = get_file_locality_status(path);
+                            let estimated_download_size = if matches!(status, FileLocalityStatus::OnDemand | FileLocalityStatus::Hydrating { .. }) {
+                                std::fs::metadata(path).ok().map(|m| m.len())
+                            } else {
+                                None
+                            };
+                            LocalityUpdate { path: path.clone(), status, estimated_download_size }
+                        })
+                        .collect();
+                    if sender.send(batch).is_err() {
+                        return;
+                    }
+                    checked += chunk.len();
+                    reporter.progress(checked as u64, Some(total as u64));
+                }
+                reporter.finish(format!("{} files checked", total));
+            });
+
+        Self {
+            receiver,
+            cancel,
+            processed: 0,
+            total,
+            finished: false,
+        }
+    }
+
+    /// Drain all batches received since the last call
+    pub fn poll(&mut self) -> Vec<LocalityUpdate> {
+        let mut updates = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(batch) => updates.extend(batch),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+        self.processed += updates.len();
+        updates
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Progress as (files checked, total files)
+    pub fn progress(&self) -> (usize, usize) {
+        (self.processed, self.total)
+    }
+}
+
+impl Drop for LocalityRefresh {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::SeqCst);
     }
 }
 
@@ -69,37 +313,34 @@ pub fn get_file_locality_status(path: &std::path::Path) -> FileLocalityStatus {
     if let Ok(metadata) = std::fs::metadata(path) {
         let attributes = metadata.file_attributes();
         
-        // Key Windows file attributes for determining locality
+        // Key Windows file attributes for determining locality. Every provider built on
+        // the Cloud Files API (OneDrive, Dropbox, iCloud for Windows) uses the recall
+        // attributes; Google Drive's virtual drive marks uncached files as offline.
+        const FILE_ATTRIBUTE_OFFLINE: u32 = 0x00001000;
+        const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x00040000;
         const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x00400000;
-        const FILE_ATTRIBUTE_UNPINNED: u32 = 0x00100000;
         
         // Debug output for troubleshooting
         #[cfg(debug_assertions)]
         println!("File locality check: {} - attributes: 0x{:08X}", path.display(), attributes);
         
-        // Based on the provided data patterns:
-        // On-demand files have both UNPINNED and RECALL_ON_DATA_ACCESS attributes
-        let is_unpinned = (attributes & FILE_ATTRIBUTE_UNPINNED) != 0;
-        let has_recall_on_data_access = (attributes & FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS) != 0;
-        
-        if is_unpinned && has_recall_on_data_access {
+        // Any recall attribute means reading the file contents will fetch them from the
+        // provider, regardless of whether the file is pinned
+        if attributes & (FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS | FILE_ATTRIBUTE_RECALL_ON_OPEN | FILE_ATTRIBUTE_OFFLINE) != 0 {
+            // A placeholder that already has some data on disk is being hydrated
+            if let Some(percent) = allocated_bytes(path).and_then(|allocated| hydration_percent(allocated, metadata.len())) {
+                #[cfg(debug_assertions)]
+                println!("  -> Hydrating ({}%)", percent);
+                return FileLocalityStatus::Hydrating { percent };
+            }
             #[cfg(debug_assertions)]
-            println!("  -> OnDemand (unpinned + recall on data access)");
+            println!("  -> OnDemand (recall or offline attribute set)");
             return FileLocalityStatus::OnDemand;
         }
         
-        // Local files have neither UNPINNED nor RECALL_ON_DATA_ACCESS
-        if !is_unpinned && !has_recall_on_data_access {
-            #[cfg(debug_assertions)]
-            println!("  -> Local (not unpinned, no recall on data access)");
-            return FileLocalityStatus::Local;
-        }
-        
-        // Handle edge cases
         #[cfg(debug_assertions)]
-        println!("  -> Unknown (unusual attribute combination: unpinned={}, recall_on_data_access={})", 
-                 is_unpinned, has_recall_on_data_access);
-        return FileLocalityStatus::Unknown;
+        println!("  -> Local (no recall attributes)");
+        return FileLocalityStatus::Local;
     }
     
     // Default to unknown if we can't determine status
@@ -108,12 +349,295 @@ pub fn get_file_locality_status(path: &std::path::Path) -> FileLocalityStatus {
     FileLocalityStatus::Unknown
 }
 
-#[cfg(not(windows))]
+/// Bytes actually stored on disk for a (possibly sparse) placeholder file
+#[cfg(windows)]
+pub(crate) fn allocated_bytes(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{SetLastError, WIN32_ERROR};
+    use windows::Win32::Storage::FileSystem::{GetCompressedFileSizeW, INVALID_FILE_SIZE};
+
+    let wide_path: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut high = 0u32;
+    // A size whose low half happens to be INVALID_FILE_SIZE is only an error
+    // if the call set one, so clear whatever an earlier call left behind
+    let low = unsafe {
+        SetLastError(WIN32_ERROR(0));
+        GetCompressedFileSizeW(PCWSTR::from_raw(wide_path.as_ptr()), Some(&mut high as *mut u32))
+    };
+    if low == INVALID_FILE_SIZE && std::io::Error::last_os_error().raw_os_error().unwrap_or(0) != 0 {
+        return None;
+    }
+    Some(((high as u64) << 32) | low as u64)
+}
+
+/// Bytes actually stored on disk for a (possibly sparse) placeholder file
+#[cfg(all(feature = "gui", unix))]
+pub(crate) fn allocated_bytes(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    std::fs::metadata(path).ok().map(|metadata| metadata.blocks() * 512)
+}
+
+#[cfg(all(feature = "gui", not(any(windows, unix))))]
+pub(crate) fn allocated_bytes(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Download progress of a partially present file, if it is neither empty nor complete
+pub fn hydration_percent(allocated: u64, logical: u64) -> Option<u8> {
+    if allocated == 0 || logical == 0 || allocated >= logical {
+        return None;
+    }
+    Some(((allocated * 100) / logical).min(99) as u8)
+}
+
+/// Tells files being downloaded from placeholders that only hold part of
+/// their data, such as after an interrupted download, by whether the
+/// bytes on disk of each keep growing
+#[cfg(feature = "gui")]
+#[derive(Debug, Default)]
+pub(crate) struct HydrationWatch {
+    /// Bytes on disk when each download last moved, and when that was
+    progress: std::collections::HashMap<PathBuf, (u64, std::time::Instant)>,
+}
+
+#[cfg(feature = "gui")]
+impl HydrationWatch {
+    /// The status to show for `path`, checked as `status` with `allocated`
+    /// bytes on disk at `now`: a download whose bytes haven't grown for a
+    /// while is on demand
+    pub(crate) fn observe(
+        &mut self,
+        path: &Path,
+        status: FileLocalityStatus,
+        allocated: Option<u64>,
+        now: std::time::Instant,
+    ) -> FileLocalityStatus {
+        let FileLocalityStatus::Hydrating { percent } = status else {
+            self.progress.remove(path);
+            return status;
+        };
+        // Whole percents are all there is to go on where the size on disk can't be read
+        let allocated = allocated.unwrap_or(percent as u64);
+        let (last, since) = self.progress.entry(path.to_path_buf()).or_insert((allocated, now));
+        if *last != allocated {
+            *last = allocated;
+            *since = now;
+        }
+        if now.duration_since(*since) >= HYDRATION_STALL {
+            FileLocalityStatus::OnDemand
+        } else {
+            status
+        }
+    }
+
+    /// Files still being watched, including stalled ones shown as on demand,
+    /// which keep being checked in case their download picks up again
+    pub(crate) fn watched(&self) -> impl Iterator<Item = &PathBuf> {
+        self.progress.keys()
+    }
+
+    /// Stop watching files for which `listed` is false
+    pub(crate) fn retain(&mut self, mut listed: impl FnMut(&Path) -> bool) {
+        self.progress.retain(|path, _| listed(path));
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn get_file_locality_status(path: &std::path::Path) -> FileLocalityStatus {
+    use std::os::macos::fs::MetadataExt;
+    
+    // File Provider based clients (iCloud Drive, Dropbox, Google Drive, OneDrive)
+    // mark evicted files as dataless
+    const SF_DATALESS: u32 = 0x40000000;
+    
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.st_flags() & SF_DATALESS != 0 => {
+            // Blocks are allocated as the provider streams the file in
+            match hydration_percent(metadata.st_blocks() * 512, metadata.len()) {
+                Some(percent) => FileLocalityStatus::Hydrating { percent },
+                None => FileLocalityStatus::OnDemand,
+            }
+        }
+        Ok(_) => FileLocalityStatus::Local,
+        // Legacy iCloud eviction replaces the file with a hidden ".name.icloud" stub
+        Err(_) if icloud_stub_path(path).is_some_and(|stub| stub.exists()) => FileLocalityStatus::OnDemand,
+        Err(_) => FileLocalityStatus::Unknown,
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn get_file_locality_status(path: &std::path::Path) -> FileLocalityStatus {
+    use std::os::unix::fs::MetadataExt;
+
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return FileLocalityStatus::Unknown;
+    };
+    // Kernel NFS and SMB clients are told apart by the filesystem itself;
+    // FUSE mounts by the client that mounted them
+    match filesystem_magic(path) {
+        Some(magic) if is_network_magic(magic) => return FileLocalityStatus::Network,
+        Some(FUSE_SUPER_MAGIC) => {}
+        _ => return FileLocalityStatus::Local,
+    }
+    let Ok(absolute) = std::fs::canonicalize(path) else {
+        return FileLocalityStatus::Unknown;
+    };
+    let mounts = cached_mounts();
+    let fs_type = filesystem_type_for_path(&mounts, &absolute);
+    if fs_type.is_some_and(is_network_filesystem) {
+        return FileLocalityStatus::Network;
+    }
+    if !fs_type.is_some_and(is_remote_fuse_filesystem) {
+        return FileLocalityStatus::Local;
+    }
+    // These clients count the blocks they have cached, so a file without any
+    // hasn't been downloaded
+    let allocated = metadata.blocks() * 512;
+    match hydration_percent(allocated, metadata.len()) {
+        Some(percent) => FileLocalityStatus::Hydrating { percent },
+        None if allocated == 0 && metadata.len() > 0 => FileLocalityStatus::OnDemand,
+        None => FileLocalityStatus::Local,
+    }
+}
+
+#[cfg(target_os = "linux")]
+const FUSE_SUPER_MAGIC: u32 = 0x6573_5546;
+
+/// The type of the filesystem `path` is on, as its magic number from
+/// statfs; asked of the filesystem itself so bind mounts and namespaces
+/// can't mislead it
+#[cfg(target_os = "linux")]
+fn filesystem_magic(path: &Path) -> Option<u32> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stats = std::mem::MaybeUninit::<libc::statfs>::uninit();
+    if unsafe { libc::statfs(c_path.as_ptr(), stats.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    Some(unsafe { stats.assume_init() }.f_type as u32)
+}
+
+#[cfg(target_os = "linux")]
+fn is_on_fuse(path: &Path) -> bool {
+    filesystem_magic(path) == Some(FUSE_SUPER_MAGIC)
+}
+
+/// Magic numbers of the kernel's network filesystem clients
+#[cfg(target_os = "linux")]
+fn is_network_magic(magic: u32) -> bool {
+    const NFS: u32 = 0x6969;
+    const SMB: u32 = 0x517b;
+    const CIFS: u32 = 0xff53_4d42;
+    const SMB2: u32 = 0xfe53_4d42;
+    const V9FS: u32 = 0x0102_1997;
+    const AFS: u32 = 0x6b41_4653;
+    const CEPH: u32 = 0x00c3_6400;
+    const CODA: u32 = 0x7375_7245;
+    [NFS, SMB, CIFS, SMB2, V9FS, AFS, CEPH, CODA].contains(&magic)
+}
+
+/// The mount table, read again once it is [`MOUNTS_MAX_AGE`] old rather than
+/// for every file
+#[cfg(target_os = "linux")]
+fn cached_mounts() -> Arc<Vec<(PathBuf, String)>> {
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    type Mounts = Arc<Vec<(PathBuf, String)>>;
+    static MOUNTS: Mutex<Option<(Instant, Mounts)>> = Mutex::new(None);
+    let mut cached = MOUNTS.lock().unwrap();
+    match &*cached {
+        Some((read, mounts)) if read.elapsed() < MOUNTS_MAX_AGE => Arc::clone(mounts),
+        _ => {
+            let text = std::fs::read_to_string("/proc/self/mountinfo").unwrap_or_default();
+            let mounts = Arc::new(parse_mountinfo(&text));
+            *cached = Some((Instant::now(), Arc::clone(&mounts)));
+            mounts
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
 pub fn get_file_locality_status(_path: &std::path::Path) -> FileLocalityStatus {
-    // On non-Windows platforms, assume all files are local
+    // No placeholder files on other platforms, assume all files are local
     FileLocalityStatus::Local
 }
 
+/// Parse `/proc/self/mountinfo` into (mount point, filesystem type) pairs
+pub fn parse_mountinfo(text: &str) -> Vec<(PathBuf, String)> {
+    text.lines()
+        .filter_map(|line| {
+            // "36 35 98:0 /root /mnt/point rw,noatime shared:1 - ext4 /dev/sda1 rw"
+            let (fields, rest) = line.split_once(" - ")?;
+            let mount_point = fields.split(' ').nth(4)?;
+            let fs_type = rest.split(' ').next()?;
+            Some((PathBuf::from(unescape_mount_path(mount_point)), fs_type.to_string()))
+        })
+        .collect()
+}
+
+/// Mount paths escape spaces, tabs, newlines and backslashes as octal (e.g. `\040`)
+fn unescape_mount_path(path: &str) -> String {
+    let mut result = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            let octal: String = chars.by_ref().take(3).collect();
+            match u8::from_str_radix(&octal, 8) {
+                Ok(byte) => result.push(byte as char),
+                Err(_) => {
+                    result.push(c);
+                    result.push_str(&octal);
+                }
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Filesystem type of the most specific mount containing `path`
+pub fn filesystem_type_for_path<'a>(mounts: &'a [(PathBuf, String)], path: &Path) -> Option<&'a str> {
+    mounts
+        .iter()
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+        .map(|(_, fs_type)| fs_type.as_str())
+}
+
+/// Filesystems, by their type in the mount table, that read another
+/// machine's files over the network on every access rather than keeping a
+/// local copy
+pub fn is_network_filesystem(fs_type: &str) -> bool {
+    const NETWORK_FILESYSTEMS: &[&str] = &[
+        "nfs", "nfs4", "cifs", "smb3", "smbfs", "9p", "afs", "ceph", "coda", "davfs",
+        "fuse.sshfs", "fuse.curlftpfs", "fuse.glusterfs", "fuse.s3fs", "fuse.smbnetfs",
+    ];
+
+    NETWORK_FILESYSTEMS.contains(&fs_type)
+}
+
+/// FUSE filesystems of cloud clients, which fetch files remotely and so can
+/// hold files that aren't downloaded yet
+pub fn is_remote_fuse_filesystem(fs_type: &str) -> bool {
+    // FUSE mounts that only expose local data
+    const LOCAL_FUSE_FILESYSTEMS: &[&str] = &[
+        "fuse.portal", "fuse.lxcfs", "fuse.gocryptfs", "fuse.encfs", "fuse.bindfs", "fuse.mergerfs",
+    ];
+
+    fs_type.starts_with("fuse.") && !LOCAL_FUSE_FILESYSTEMS.contains(&fs_type) && !is_network_filesystem(fs_type)
+}
+
+/// Path of the stub iCloud leaves behind when it evicts a file: `dir/.name.icloud`
+pub fn icloud_stub_path(path: &Path) -> Option<PathBuf> {
+    let file_name = path.file_name()?.to_string_lossy();
+    Some(path.with_file_name(format!(".{}.icloud", file_name)))
+}
+
 /// Check if a file is immediately available without triggering a download
 pub fn is_file_immediately_available(path: &std::path::Path) -> bool {
     matches!(get_file_locality_status(path), FileLocalityStatus::Local)
@@ -133,7 +657,6 @@ pub fn get_file_status_string(path: &std::path::Path) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::Path;
 
     #[test]
     fn test_file_locality_status_display() {
@@ -158,4 +681,134 @@ mod tests {
         // Status will depend on actual file attributes, so we just check it's set
         assert!(matches!(info.locality_status, FileLocalityStatus::Local | FileLocalityStatus::OnDemand | FileLocalityStatus::Unknown));
     }
+
+    #[test]
+    fn test_cloud_provider_from_mount() {
+        let cases = [
+            ("fuse.onedriver", Some(CloudProvider::OneDrive)),
+            ("fuse.google-drive-ocamlfuse", Some(CloudProvider::GoogleDrive)),
+            ("fuse.rclone", Some(CloudProvider::Other("rclone".to_string()))),
+            ("fuse.portal", None),
+            ("fuse.sshfs", None),
+            ("nfs4", None),
+            ("ext4", None),
+        ];
+        for (fs_type, expected) in cases {
+            assert_eq!(CloudProvider::from_mount(fs_type), expected, "{}", fs_type);
+        }
+    }
+
+    #[test]
+    fn test_cloud_provider_from_provider_name() {
+        assert_eq!(CloudProvider::from_provider_name("OneDrive - Contoso"), CloudProvider::OneDrive);
+        assert_eq!(CloudProvider::from_provider_name("Dropbox"), CloudProvider::Dropbox);
+        assert_eq!(CloudProvider::from_provider_name("Box"), CloudProvider::Other("Box".to_string()));
+    }
+
+    #[test]
+    fn test_hydration_percent() {
+        assert_eq!(hydration_percent(0, 1000), None);
+        assert_eq!(hydration_percent(250, 1000), Some(25));
+        assert_eq!(hydration_percent(999, 1000), Some(99));
+        assert_eq!(hydration_percent(4096, 1000), None);
+        let file = |locality_status| FileInfo {
+            path: PathBuf::from("a.jpg"),
+            locality_status,
+            estimated_download_size: None,
+            provider: None,
+            render_estimate: None,
+        };
+        assert!(file(FileLocalityStatus::Hydrating { percent: 40 }).will_trigger_download());
+        // Network shares are read in place, but not while browsing
+        assert!(!file(FileLocalityStatus::Network).will_trigger_download());
+        assert!(file(FileLocalityStatus::Network).is_slow_to_read());
+        assert!(!file(FileLocalityStatus::Local).is_slow_to_read());
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_stalled_hydration_is_on_demand() {
+        let mut watch = HydrationWatch::default();
+        let path = Path::new("a.jpg");
+        let start = std::time::Instant::now();
+        let at = |seconds| start + std::time::Duration::from_secs(seconds);
+        let hydrating = |percent| FileLocalityStatus::Hydrating { percent };
+
+        assert_eq!(watch.observe(path, hydrating(20), Some(2000), at(0)), hydrating(20));
+        assert_eq!(watch.observe(path, hydrating(20), Some(2000), at(9)), hydrating(20));
+        // A slow download moves by bytes well within one percent
+        assert_eq!(watch.observe(path, hydrating(20), Some(2100), at(15)), hydrating(20));
+        assert_eq!(watch.observe(path, hydrating(20), Some(2100), at(25)), FileLocalityStatus::OnDemand);
+        // Stalled files stay watched and come back once they move again
+        assert_eq!(watch.watched().collect::<Vec<_>>(), [path]);
+        assert_eq!(watch.observe(path, hydrating(30), Some(3000), at(26)), hydrating(30));
+        assert_eq!(watch.observe(path, FileLocalityStatus::Local, None, at(27)), FileLocalityStatus::Local);
+        assert_eq!(watch.watched().count(), 0);
+
+        assert_eq!(watch.observe(path, hydrating(30), None, at(28)), hydrating(30));
+        watch.retain(|listed| listed != path);
+        assert_eq!(watch.watched().count(), 0);
+    }
+
+    #[test]
+    fn test_locality_refresh_streams_all_files() {
+        let paths: Vec<PathBuf> = (0..150).map(|i| PathBuf::from(format!("missing_{}.png", i))).collect();
+        let mut hub = crate::progress::ProgressHub::new();
+        let mut refresh = LocalityRefresh::start(paths, &hub.sink());
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let mut received = 0;
+        while !refresh.is_finished() && std::time::Instant::now() < deadline {
+            received += refresh.poll().len();
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert_eq!(received, 150);
+        assert_eq!(refresh.progress(), (150, 150));
+        let events = hub.poll();
+        assert!(matches!(events.last(), Some(crate::progress::ProgressEvent::Finished { .. })));
+        assert!(!hub.is_busy());
+    }
+
+    #[test]
+    fn test_mountinfo_network_detection() {
+        let mountinfo = "\
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+40 22 0:35 / /mnt/nas rw,relatime shared:20 - nfs4 nas:/export rw
+41 22 0:36 / /home/me/Google\\040Drive rw,nosuid shared:21 - fuse.rclone gdrive: rw
+42 22 0:37 / /run/user/1000/doc rw,nosuid shared:22 - fuse.portal portal rw
+";
+        let mounts = parse_mountinfo(mountinfo);
+        assert_eq!(mounts.len(), 4);
+        assert_eq!(mounts[2].0, PathBuf::from("/home/me/Google Drive"));
+
+        let fs = |p: &str| filesystem_type_for_path(&mounts, Path::new(p)).unwrap();
+        assert_eq!(fs("/home/me/Pictures/a.png"), "ext4");
+        assert_eq!(fs("/mnt/nas/photos/a.png"), "nfs4");
+        assert_eq!(fs("/home/me/Google Drive/a.png"), "fuse.rclone");
+
+        for network in ["nfs4", "cifs", "smb3", "fuse.sshfs"] {
+            assert!(is_network_filesystem(network), "{}", network);
+            assert!(!is_remote_fuse_filesystem(network), "{}", network);
+        }
+        assert!(!is_network_filesystem("ext4"));
+        assert!(!is_network_filesystem("fuse.rclone"));
+        assert!(is_remote_fuse_filesystem("fuse.rclone"));
+        assert!(!is_remote_fuse_filesystem("nfs4"));
+        assert!(!is_remote_fuse_filesystem("fuse.portal"));
+        assert!(!is_remote_fuse_filesystem("ext4"));
+        assert!(!is_remote_fuse_filesystem("fuseblk"));
+    }
+
+    #[test]
+    fn test_status_description_includes_provider() {
+        let info = FileInfo {
+            path: PathBuf::from("a.jpg"),
+            locality_status: FileLocalityStatus::OnDemand,
+            estimated_download_size: Some(10),
+            provider: Some(CloudProvider::Dropbox),
+            render_estimate: None,
+        };
+        assert_eq!(info.status_description(), "On-demand file (will download when accessed)\nProvider: 📦 Dropbox");
+        assert_eq!(icloud_stub_path(Path::new("dir/a.jpg")), Some(PathBuf::from("dir/.a.jpg.icloud")));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,108 @@
+//! Per-row data for the file list
+//!
+//! A row's display name and render estimate take string formatting and, for
+//! the estimate, reading the image's header. That is too slow to repeat for
+//! every row of a large folder on every frame, so rows are worked out when
+//! they first scroll into view and kept until something they depend on
+//! changes.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::edit_sidecar;
+use crate::video;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileRow {
+    pub display_name: String,
+    /// Shown on hover when the display name is truncated
+    pub name_tooltip: Option<String>,
+    pub is_svg: bool,
+    pub is_video: bool,
+    /// Edits are saved beside the image
+    pub has_edits: bool,
+    /// Expected render time in milliseconds; only known for local files
+    pub estimated_ms: Option<f64>,
+    /// Whether the file was local when the row was made
+    local: bool,
+}
+
+impl FileRow {
+    pub fn new(path: &Path, display_name: String, name_tooltip: Option<String>, local: bool, estimated_ms: Option<f64>) -> Self {
+        Self {
+            display_name,
+            name_tooltip,
+            is_svg: path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")),
+            is_video: video::is_video(path),
+            has_edits: edit_sidecar::sidecar_path(path).exists(),
+            estimated_ms: estimated_ms.filter(|_| local),
+            local,
+        }
+    }
+}
+
+/// Rows made so far, dropped together when the inputs they were made from change
+#[derive(Debug, Default)]
+pub struct FileRowCache {
+    rows: HashMap<PathBuf, FileRow>,
+    /// Settings and benchmark state the rows were made with
+    signature: String,
+}
+
+impl FileRowCache {
+    /// Forget every row if `signature` differs from the one they were made with
+    pub fn validate(&mut self, signature: String) {
+        if self.signature != signature {
+            self.rows.clear();
+            self.signature = signature;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    /// The row for `path`, made with `make` if there is none yet or the file
+    /// has since been downloaded or evicted
+    pub fn row(&mut self, path: &Path, local: bool, make: impl FnOnce() -> FileRow) -> &FileRow {
+        let stale = self.rows.get(path).is_none_or(|row| row.local != local);
+        if stale {
+            self.rows.insert(path.to_path_buf(), make());
+        }
+        &self.rows[path]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_are_reused_until_their_inputs_change() {
+        let path = Path::new("photos/cat.svg");
+        let mut cache = FileRowCache::default();
+        let made = std::cell::Cell::new(0);
+        let row = |cache: &mut FileRowCache, local: bool| {
+            cache.row(path, local, || {
+                made.set(made.get() + 1);
+                FileRow::new(path, "cat.svg".to_string(), None, local, Some(12.0))
+            }).clone()
+        };
+
+        let first = row(&mut cache, false);
+        assert!(first.is_svg && !first.is_video);
+        assert_eq!(first.estimated_ms, None, "no estimate before the file is local");
+        row(&mut cache, false);
+        assert_eq!(made.get(), 1);
+
+        // Downloaded since
+        assert_eq!(row(&mut cache, true).estimated_ms, Some(12.0));
+        assert_eq!(made.get(), 2);
+
+        cache.validate("truncated".to_string());
+        assert_eq!(cache.rows.len(), 0);
+        row(&mut cache, true);
+        cache.validate("truncated".to_string());
+        assert_eq!(cache.rows.len(), 1);
+    }
+}
@@ -0,0 +1,144 @@
+//! Multi-selection in the image list and the aggregate shown for it
+//!
+//! Click selects one image, Ctrl/Cmd+click toggles, Shift+click selects a
+//! range from the last clicked image. Batch operations work on
+//! [`FileSelection::indices`].
+
+use std::collections::BTreeSet;
+
+use crate::file_locality::FileInfo;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileSelection {
+    selected: BTreeSet<usize>,
+    /// Where a Shift+click range starts
+    anchor: Option<usize>,
+}
+
+impl FileSelection {
+    /// Apply a click on the image at `index`
+    pub fn click(&mut self, index: usize, toggle: bool, range: bool) {
+        match (range, self.anchor) {
+            (true, Some(anchor)) => {
+                if !toggle {
+                    self.selected.clear();
+                }
+                self.selected.extend(anchor.min(index)..=anchor.max(index));
+            }
+            _ if toggle => {
+                if !self.selected.remove(&index) {
+                    self.selected.insert(index);
+                }
+                self.anchor = Some(index);
+            }
+            _ => self.select_only(index),
+        }
+    }
+
+    pub fn select_only(&mut self, index: usize) {
+        self.selected.clear();
+        self.selected.insert(index);
+        self.anchor = Some(index);
+    }
+
+    pub fn clear(&mut self) {
+        self.selected.clear();
+        self.anchor = None;
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.selected.contains(&index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.selected.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.selected.is_empty()
+    }
+
+    /// Selected indices in list order
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.selected.iter().copied()
+    }
+}
+
+/// Totals for the selected files
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SelectionSummary {
+    pub count: usize,
+    pub total_bytes: u64,
+    pub on_demand: usize,
+    pub download_bytes: u64,
+}
+
+impl SelectionSummary {
+    /// Sizes of local files come from their metadata, which doesn't trigger downloads
+    pub fn of(files: &[FileInfo], selection: &FileSelection) -> Self {
+        let mut summary = Self::default();
+        for file in selection.indices().filter_map(|i| files.get(i)) {
+            summary.count += 1;
+            let size = file.estimated_download_size
+                .or_else(|| std::fs::metadata(&file.path).ok().map(|m| m.len()))
+                .unwrap_or(0);
+            summary.total_bytes += size;
+            if file.will_trigger_download() {
+                summary.on_demand += 1;
+                summary.download_bytes += size;
+            }
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_locality::FileLocalityStatus;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_click_modifiers() {
+        let mut selection = FileSelection::default();
+        selection.click(2, false, false);
+        selection.click(5, false, true);
+        assert_eq!(selection.indices().collect::<Vec<_>>(), [2, 3, 4, 5]);
+
+        selection.click(3, true, false);
+        assert_eq!(selection.indices().collect::<Vec<_>>(), [2, 4, 5]);
+        // The toggled item becomes the new range anchor; Ctrl+Shift extends
+        selection.click(0, true, true);
+        assert_eq!(selection.indices().collect::<Vec<_>>(), [0, 1, 2, 3, 4, 5]);
+
+        selection.click(1, false, false);
+        assert_eq!(selection.len(), 1);
+        assert!(selection.contains(1));
+    }
+
+    #[test]
+    fn test_summary_counts_on_demand_files() {
+        let file = |name: &str, status, size| FileInfo {
+            path: PathBuf::from(name),
+            locality_status: status,
+            estimated_download_size: size,
+            provider: None,
+            render_estimate: None,
+        };
+        let files = vec![
+            file("a.jpg", FileLocalityStatus::OnDemand, Some(1000)),
+            file("b.jpg", FileLocalityStatus::Local, Some(300)),
+            file("c.jpg", FileLocalityStatus::OnDemand, Some(500)),
+        ];
+        let mut selection = FileSelection::default();
+        selection.click(0, false, false);
+        selection.click(1, true, false);
+
+        assert_eq!(SelectionSummary::of(&files, &selection), SelectionSummary {
+            count: 2,
+            total_bytes: 1300,
+            on_demand: 1,
+            download_bytes: 1000,
+        });
+    }
+}
@@ -0,0 +1,178 @@
+//! How images are scaled to the display area
+//!
+//! Fitting a tall screenshot or a stitched panorama entirely into the window
+//! leaves it tiny. `Auto` fits such images to the window's width or height
+//! instead, so they can be scrolled along their long side.
+//!
+//! Icons and SVGs open at 100% or a whole multiple of it by default, where
+//! pixel art stays crisp, while photos open fitted. Zoom buttons step through
+//! [`ZOOM_STEPS`] so they always land on those sizes.
+
+#[cfg(feature = "gui")]
+use std::path::Path;
+
+use emath::Vec2;
+
+/// Images this many times taller or wider than the display area are fitted
+/// along their short side in `Auto` mode
+const ELONGATED_RATIO: f32 = 2.0;
+
+/// Raster images no larger than this on either side are treated as icons
+#[cfg(feature = "gui")]
+pub const ICON_MAX_SIZE: f32 = 256.0;
+
+/// Largest whole multiple an image opens at
+const MAX_MULTIPLE: f32 = 32.0;
+
+/// Display scales the zoom buttons step through when snapping is on
+#[cfg(feature = "gui")]
+pub const ZOOM_STEPS: [f32; 17] = [
+    0.1, 0.125, 0.25, 1.0 / 3.0, 0.5, 2.0 / 3.0, 1.0, 1.5, 2.0, 3.0, 4.0, 6.0, 8.0, 12.0, 16.0, 24.0, 32.0,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FitMode {
+    /// Whole image visible
+    Window,
+    Width,
+    Height,
+    /// Width for tall images, height for panoramas, otherwise the whole image
+    Auto,
+}
+
+impl FitMode {
+    pub const ALL: [FitMode; 4] = [FitMode::Auto, FitMode::Window, FitMode::Width, FitMode::Height];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FitMode::Window => "Fit to Window",
+            FitMode::Width => "Fit Width",
+            FitMode::Height => "Fit Height",
+            FitMode::Auto => "Fit by Aspect Ratio",
+        }
+    }
+
+    /// The concrete mode for an image of `image` size shown in `area`
+    pub fn resolve(self, image: Vec2, area: Vec2) -> FitMode {
+        if self != FitMode::Auto {
+            return self;
+        }
+        if image.x <= 0.0 || image.y <= 0.0 || area.x <= 0.0 || area.y <= 0.0 {
+            return FitMode::Window;
+        }
+        let relative = (image.x / image.y) / (area.x / area.y);
+        if relative >= ELONGATED_RATIO {
+            FitMode::Height
+        } else if relative <= 1.0 / ELONGATED_RATIO {
+            FitMode::Width
+        } else {
+            FitMode::Window
+        }
+    }
+
+    /// Scale for showing `image` in `area`; images are only scaled down, never up
+    pub fn scale(self, image: Vec2, area: Vec2) -> f32 {
+        let (width, height) = (area.x / image.x, area.y / image.y);
+        let scale = match self.resolve(image, area) {
+            FitMode::Width => width,
+            FitMode::Height => height,
+            FitMode::Window | FitMode::Auto => width.min(height),
+        };
+        scale.min(1.0)
+    }
+}
+
+/// The size an image opens at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpeningZoom {
+    /// Scaled down by the fit mode
+    Fit,
+    /// One image pixel per point
+    Actual,
+    /// The largest whole multiple of 100% that fits, at least 100%
+    IntegerMultiple,
+}
+
+impl OpeningZoom {
+    pub const ALL: [OpeningZoom; 3] = [OpeningZoom::Fit, OpeningZoom::Actual, OpeningZoom::IntegerMultiple];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OpeningZoom::Fit => "Fit",
+            OpeningZoom::Actual => "100%",
+            OpeningZoom::IntegerMultiple => "Whole Multiple of 100%",
+        }
+    }
+
+    /// Scale for opening `image` in `area`, fitting it with `fit`
+    pub fn scale(self, fit: FitMode, image: Vec2, area: Vec2) -> f32 {
+        match self {
+            OpeningZoom::Fit => fit.scale(image, area),
+            OpeningZoom::Actual => 1.0,
+            OpeningZoom::IntegerMultiple => {
+                let multiple = (area.x / image.x).min(area.y / image.y).floor();
+                if multiple.is_finite() { multiple.clamp(1.0, MAX_MULTIPLE) } else { 1.0 }
+            }
+        }
+    }
+}
+
+/// Whether `path`, decoded to `size`, opens with the icon or the photo zoom
+#[cfg(feature = "gui")]
+pub fn is_icon(path: &Path, size: Vec2) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+        || (size.x <= ICON_MAX_SIZE && size.y <= ICON_MAX_SIZE)
+}
+
+/// The next of [`ZOOM_STEPS`] above (or below) `scale`
+#[cfg(feature = "gui")]
+pub fn next_zoom_step(scale: f32, zoom_in: bool) -> f32 {
+    // Scales this close to a step count as being on it
+    let tolerance = 1.001;
+    let next = if zoom_in {
+        ZOOM_STEPS.iter().find(|&&step| step > scale * tolerance)
+    } else {
+        ZOOM_STEPS.iter().rev().find(|&&step| step * tolerance < scale)
+    };
+    next.copied().unwrap_or(scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use emath::vec2;
+
+    #[test]
+    fn test_auto_resolves_by_aspect_ratio() {
+        let area = vec2(1600.0, 900.0);
+        assert_eq!(FitMode::Auto.resolve(vec2(4000.0, 3000.0), area), FitMode::Window);
+        assert_eq!(FitMode::Auto.resolve(vec2(12000.0, 1500.0), area), FitMode::Height);
+        assert_eq!(FitMode::Auto.resolve(vec2(1080.0, 6000.0), area), FitMode::Width);
+        assert_eq!(FitMode::Width.resolve(vec2(12000.0, 1500.0), area), FitMode::Width);
+    }
+
+    #[test]
+    fn test_scale() {
+        let area = vec2(1600.0, 900.0);
+        assert_eq!(FitMode::Auto.scale(vec2(12000.0, 1800.0), area), 0.5);
+        assert_eq!(FitMode::Window.scale(vec2(12000.0, 1800.0), area), 1600.0 / 12000.0);
+        // Small images stay at 100%
+        assert_eq!(FitMode::Auto.scale(vec2(100.0, 1000.0), area), 1.0);
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_opening_zoom_and_steps() {
+        let area = vec2(1600.0, 900.0);
+        assert_eq!(OpeningZoom::IntegerMultiple.scale(FitMode::Auto, vec2(64.0, 64.0), area), 14.0);
+        assert_eq!(OpeningZoom::IntegerMultiple.scale(FitMode::Auto, vec2(4000.0, 3000.0), area), 1.0);
+        assert_eq!(OpeningZoom::Fit.scale(FitMode::Window, vec2(3200.0, 900.0), area), 0.5);
+        assert!(is_icon(Path::new("logo.SVG"), vec2(2048.0, 2048.0)));
+        assert!(!is_icon(Path::new("photo.jpg"), vec2(300.0, 200.0)));
+
+        assert_eq!(next_zoom_step(1.0, true), 1.5);
+        assert_eq!(next_zoom_step(1.2, false), 1.0);
+        assert_eq!(next_zoom_step(0.1, false), 0.1);
+        assert_eq!(next_zoom_step(14.0, true), 16.0);
+    }
+}
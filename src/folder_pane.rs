@@ -0,0 +1,93 @@
+//! A second folder shown beside the file list
+//!
+//! In the two-pane layout, images are moved from the current folder into the
+//! pane's folder by dragging them onto the pane or with a shortcut, and back by
+//! dragging them from the pane onto the file list. Every move can be undone.
+
+use std::path::{Path, PathBuf};
+
+use crate::history::FileOperation;
+
+#[derive(Debug, Clone, Default)]
+pub struct FolderPane {
+    pub dir: PathBuf,
+    /// Subfolders to navigate into, sorted by name; hidden ones are left out
+    pub folders: Vec<PathBuf>,
+    /// Images with one of the supported extensions, sorted by name
+    pub images: Vec<PathBuf>,
+    /// What is typed in the pane's path field
+    pub path_input: String,
+}
+
+impl FolderPane {
+    pub fn open(dir: PathBuf, extensions: &[String]) -> Self {
+        let mut pane = Self { path_input: dir.display().to_string(), dir, ..Self::default() };
+        pane.refresh(extensions);
+        pane
+    }
+
+    /// List the folder again after files were moved in or out
+    pub fn refresh(&mut self, extensions: &[String]) {
+        self.folders.clear();
+        self.images.clear();
+        for path in std::fs::read_dir(&self.dir).into_iter().flatten().flatten().map(|entry| entry.path()) {
+            let hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if path.is_dir() {
+                if !hidden {
+                    self.folders.push(path);
+                }
+            } else if path.extension().is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e))) {
+                self.images.push(path);
+            }
+        }
+        self.folders.sort();
+        self.images.sort();
+    }
+}
+
+/// Move each of `paths` into `folder`, skipping those already there. Returns
+/// the moves made, to record for undo, and errors for those that failed.
+pub fn move_all(paths: &[PathBuf], folder: &Path) -> (Vec<FileOperation>, Vec<String>) {
+    let mut moved = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths.iter().filter(|path| path.parent() != Some(folder)) {
+        match FileOperation::move_into(path, folder) {
+            Ok(operation) => moved.push(operation),
+            Err(e) => errors.push(e),
+        }
+    }
+    (moved, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_moved_images_leave_one_listing_for_the_other() {
+        let dir = TempDir::new("folder_pane");
+        let (source, keep) = (dir.join("shoot"), dir.join("keep"));
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::create_dir_all(keep.join(".hidden")).unwrap();
+        std::fs::create_dir_all(keep.join("best")).unwrap();
+        for name in ["b.JPG", "a.jpg", "notes.txt"] {
+            std::fs::write(source.join(name), name).unwrap();
+        }
+        std::fs::write(keep.join("a.jpg"), "taken").unwrap();
+        let extensions = vec!["jpg".to_string()];
+
+        let mut pane = FolderPane::open(keep.clone(), &extensions);
+        assert_eq!(pane.folders, vec![keep.join("best")]);
+        let (moved, errors) = move_all(&[source.join("a.jpg"), source.join("b.JPG"), keep.join("a.jpg")], &keep);
+        pane.refresh(&extensions);
+        let images = pane.images.clone();
+        let undone: Vec<bool> = moved.iter().map(|operation| operation.undo().is_ok()).collect();
+        let restored = source.join("a.jpg").exists();
+
+        assert!(errors.is_empty());
+        assert_eq!(images, vec![keep.join("a (2).jpg"), keep.join("a.jpg"), keep.join("b.JPG")]);
+        assert_eq!(undone, vec![true, true]);
+        assert!(restored);
+    }
+}
@@ -0,0 +1,196 @@
+//! Decoders for formats the viewer doesn't know
+//!
+//! Crates that embed the viewer can [`register`] a [`FormatDecoder`] for
+//! niche formats such as DDS, KTX2 or PCX before creating the app. Registered
+//! decoders are tried before the built-in ones by file extension; files with
+//! an extension the viewer doesn't decode itself are also matched by the
+//! magic bytes at the start of the file. Registered extensions are listed in
+//! folders like any other image.
+//!
+//! ```no_run
+//! use image_previewer::prelude::*;
+//!
+//! struct Pcx;
+//!
+//! impl FormatDecoder for Pcx {
+//!     fn name(&self) -> &str { "PCX" }
+//!     fn extensions(&self) -> &[&str] { &["pcx"] }
+//!     fn matches_magic(&self, header: &[u8]) -> bool { header.first() == Some(&0x0a) }
+//!     fn decode(&self, bytes: &[u8]) -> Result<DecodedImage, String> {
+//!         Err(format!("{} bytes of PCX aren't supported yet", bytes.len()))
+//!     }
+//! }
+//!
+//! register_decoder(Pcx);
+//! ```
+
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+
+use crate::decoded_image::DecodedImage;
+
+/// Bytes read from the start of a file to check magic numbers against
+pub const MAGIC_LENGTH: usize = 64;
+
+pub trait FormatDecoder: Send + Sync {
+    /// Shown in errors
+    fn name(&self) -> &str;
+
+    /// Lowercase file extensions without the dot
+    fn extensions(&self) -> &[&str];
+
+    /// Whether a file starting with `header` (at most [`MAGIC_LENGTH`]
+    /// bytes) is in this format, for files with an extension that neither a
+    /// registered decoder nor the viewer itself handles
+    fn matches_magic(&self, _header: &[u8]) -> bool {
+        false
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<DecodedImage, String>;
+}
+
+/// Decoders in the order they're tried, newest first
+struct Registry(RwLock<Vec<Arc<dyn FormatDecoder>>>);
+
+static DECODERS: Registry = Registry::new();
+
+/// Whether the viewer decodes files with `extension` (lowercase) itself
+fn is_built_in(extension: &str) -> bool {
+    extension == "svg" || image::ImageFormat::from_extension(extension).is_some()
+}
+
+impl Registry {
+    const fn new() -> Self {
+        Self(RwLock::new(Vec::new()))
+    }
+
+    fn decoders(&self) -> RwLockReadGuard<'_, Vec<Arc<dyn FormatDecoder>>> {
+        // A decoder panicking mid-registration leaves the list usable
+        self.0.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn register(&self, decoder: Arc<dyn FormatDecoder>) {
+        let mut decoders = self.0.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        decoders.insert(0, decoder);
+    }
+
+    fn extensions(&self) -> Vec<String> {
+        let mut extensions: Vec<String> = self.decoders()
+            .iter()
+            .flat_map(|decoder| decoder.extensions().iter().map(|ext| ext.to_lowercase()))
+            .collect();
+        extensions.sort();
+        extensions.dedup();
+        extensions
+    }
+
+    /// The decoder for a file called `name` starting with `header`
+    fn find(&self, name: &Path, header: &[u8]) -> Option<Arc<dyn FormatDecoder>> {
+        let decoders = self.decoders();
+        let extension = name.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+        if let Some(ext) = extension.as_deref()
+            && let Some(decoder) = decoders.iter().find(|decoder| decoder.extensions().contains(&ext)) {
+            return Some(decoder.clone());
+        }
+        // A file named like a format the viewer knows is left to the viewer
+        if extension.as_deref().is_some_and(is_built_in) {
+            return None;
+        }
+        decoders.iter()
+            .find(|decoder| decoder.matches_magic(&header[..header.len().min(MAGIC_LENGTH)]))
+            .cloned()
+    }
+
+    fn decode_bytes(&self, name: &Path, bytes: &[u8]) -> Option<Result<DecodedImage, String>> {
+        let decoder = self.find(name, bytes)?;
+        Some(decoder.decode(bytes).map_err(|e| format!("{} decoder failed: {}", decoder.name(), e)))
+    }
+
+    fn decode_file(&self, path: &Path) -> Option<Result<DecodedImage, String>> {
+        if self.decoders().is_empty() {
+            return None;
+        }
+        let mut header = Vec::with_capacity(MAGIC_LENGTH);
+        if let Ok(file) = std::fs::File::open(path) {
+            let _ = file.take(MAGIC_LENGTH as u64).read_to_end(&mut header);
+        }
+        let decoder = self.find(path, &header)?;
+        Some(std::fs::read(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+            .and_then(|bytes| decoder.decode(&bytes).map_err(|e| format!("{} decoder failed: {}", decoder.name(), e))))
+    }
+}
+
+/// Add a decoder; later registrations take precedence for the same extension
+pub fn register_decoder(decoder: impl FormatDecoder + 'static) {
+    DECODERS.register(Arc::new(decoder));
+}
+
+/// Extensions of all registered decoders
+pub fn registered_extensions() -> Vec<String> {
+    DECODERS.extensions()
+}
+
+/// Decode `bytes` of a file called `name` with a registered decoder, or
+/// `None` when none of them handles it
+pub fn decode_bytes(name: &Path, bytes: &[u8]) -> Option<Result<DecodedImage, String>> {
+    DECODERS.decode_bytes(name, bytes)
+}
+
+/// Decode the file at `path` with a registered decoder, or `None` when none
+/// of them handles it. Nothing is read unless a decoder is registered.
+pub fn decode_file(path: &Path) -> Option<Result<DecodedImage, String>> {
+    DECODERS.decode_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One gray pixel per file, whatever the bytes
+    struct Gray;
+
+    impl FormatDecoder for Gray {
+        fn name(&self) -> &str {
+            "Gray"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["gray"]
+        }
+
+        fn matches_magic(&self, header: &[u8]) -> bool {
+            header.starts_with(b"GRAY")
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<DecodedImage, String> {
+            let value = *bytes.last().ok_or("empty file")?;
+            Ok(DecodedImage { width: 1, height: 1, pixels: vec![value, value, value, 255] })
+        }
+    }
+
+    #[test]
+    fn test_registered_decoder_is_found_by_extension_and_magic() {
+        let registry = Registry::new();
+        registry.register(Arc::new(Gray));
+        assert_eq!(registry.extensions(), ["gray"]);
+
+        let by_extension = registry.decode_bytes(Path::new("a.GRAY"), &[7]).unwrap().unwrap();
+        assert_eq!(by_extension.pixel(0, 0), [7, 7, 7, 255]);
+        let by_magic = registry.decode_bytes(Path::new("a.bin"), b"GRAY\x09").unwrap().unwrap();
+        assert_eq!(by_magic.pixel(0, 0), [9, 9, 9, 255]);
+        let no_extension = registry.decode_bytes(Path::new("a"), b"GRAY\x05").unwrap().unwrap();
+        assert_eq!(no_extension.pixel(0, 0), [5, 5, 5, 255]);
+        assert!(registry.decode_bytes(Path::new("a.png"), b"\x89PNG").is_none());
+        assert!(registry.decode_bytes(Path::new("a.gray"), &[]).unwrap().unwrap_err().starts_with("Gray decoder failed"));
+    }
+
+    #[test]
+    fn test_magic_is_not_checked_for_built_in_extensions() {
+        let registry = Registry::new();
+        registry.register(Arc::new(Gray));
+        assert!(registry.decode_bytes(Path::new("a.png"), b"GRAY\x09").is_none());
+        assert!(registry.decode_bytes(Path::new("a.svg"), b"GRAY\x09").is_none());
+    }
+}
@@ -0,0 +1,217 @@
+//! Numbered image sequences played back as an animation
+//!
+//! Render output usually arrives as `frame_0001.png`, `frame_0002.png`, …
+//! Files whose names differ only in that number, with the same extension,
+//! form a [`FrameSequence`] that the [`SequencePlayer`] steps through at a
+//! fixed frame rate. Frames are decoded ahead of playback, and only frames
+//! already on this computer are played.
+
+use std::collections::{BTreeMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::file_locality::FileInfo;
+
+/// Fewer numbered files than this are not treated as a sequence
+pub const MIN_FRAMES: usize = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameSequence {
+    /// Name pattern with the number replaced by `#`, e.g. `frame_####.png`
+    pub name: String,
+    /// Indices into the folder's files, in frame number order
+    pub frames: Vec<usize>,
+}
+
+/// Name split around its last run of digits: (before, number, after)
+fn split_frame_number(name: &str) -> Option<(&str, u64, &str)> {
+    let end = name.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = name[..end].rfind(|c: char| !c.is_ascii_digit()).map_or(0, |i| i + 1);
+    let number = name[start..end].parse().ok()?;
+    Some((&name[..start], number, &name[end..]))
+}
+
+/// The numbered sequences among `files`, largest first
+pub fn find_sequences(files: &[FileInfo]) -> Vec<FrameSequence> {
+    let mut groups: BTreeMap<(String, String, usize), Vec<(u64, usize)>> = BTreeMap::new();
+    for (index, file) in files.iter().enumerate() {
+        let name = file.path.file_name().unwrap_or_default().to_string_lossy();
+        if let Some((prefix, number, suffix)) = split_frame_number(&name)
+            && suffix.starts_with('.') {
+            let digits = name.len() - prefix.len() - suffix.len();
+            groups.entry((prefix.to_string(), suffix.to_lowercase(), digits)).or_default().push((number, index));
+        }
+    }
+    let mut sequences: Vec<FrameSequence> = groups
+        .into_iter()
+        .filter(|(_, frames)| frames.len() >= MIN_FRAMES)
+        .map(|((prefix, suffix, digits), mut frames)| {
+            frames.sort();
+            FrameSequence {
+                name: format!("{}{}{}", prefix, "#".repeat(digits), suffix),
+                frames: frames.into_iter().map(|(_, index)| index).collect(),
+            }
+        })
+        .collect();
+    sequences.sort_by_key(|sequence| std::cmp::Reverse(sequence.frames.len()));
+    sequences
+}
+
+/// A frame finished decoding, by index into the folder's files
+pub type FrameDecoded = (usize, Result<(), String>);
+
+/// Playback position in a sequence
+#[derive(Debug, Clone)]
+pub struct SequencePlayer {
+    pub sequence: FrameSequence,
+    pub position: usize,
+    pub playing: bool,
+    pub looping: bool,
+    /// When the current frame was shown
+    shown_at: Instant,
+    /// Index into the folder's files of the frame on screen
+    pub displayed: Option<usize>,
+    /// Frames being decoded, or that failed to decode
+    pub requested: HashSet<usize>,
+}
+
+impl SequencePlayer {
+    pub fn new(sequence: FrameSequence) -> Self {
+        Self {
+            sequence,
+            position: 0,
+            playing: false,
+            looping: true,
+            shown_at: Instant::now(),
+            displayed: None,
+            requested: HashSet::new(),
+        }
+    }
+
+    /// Player of the frames of `sequence` that are on this computer, if any are
+    pub fn local(mut sequence: FrameSequence, files: &[FileInfo]) -> Option<Self> {
+        sequence.frames.retain(|&index| files.get(index).is_some_and(|file| !file.will_trigger_download()));
+        (!sequence.frames.is_empty()).then(|| Self::new(sequence))
+    }
+
+    /// Index into the folder's files of the current frame
+    pub fn current(&self) -> usize {
+        self.sequence.frames[self.position]
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.sequence.frames.len()
+    }
+
+    pub fn seek(&mut self, position: usize, now: Instant) {
+        self.position = position.min(self.frame_count() - 1);
+        self.shown_at = now;
+    }
+
+    pub fn toggle(&mut self, now: Instant) {
+        // Playing from the last frame of a non-looping sequence starts over
+        if !self.playing && !self.looping && self.position + 1 == self.frame_count() {
+            self.position = 0;
+        }
+        self.playing = !self.playing;
+        self.shown_at = now;
+    }
+
+    /// Move on if the current frame has been shown for a frame's time at
+    /// `fps`; returns whether the frame changed. Frames are dropped rather
+    /// than playback slowing down when decoding can't keep up.
+    pub fn tick(&mut self, fps: u32, now: Instant) -> bool {
+        if !self.playing {
+            return false;
+        }
+        let frame_time = Duration::from_secs(1) / fps.max(1);
+        let elapsed = now.duration_since(self.shown_at);
+        if elapsed < frame_time {
+            return false;
+        }
+        let steps = (elapsed.as_nanos() / frame_time.as_nanos()) as usize;
+        let next = self.position + steps;
+        if next >= self.frame_count() && !self.looping {
+            self.position = self.frame_count() - 1;
+            self.playing = false;
+        } else {
+            self.position = next % self.frame_count();
+        }
+        self.shown_at = now;
+        true
+    }
+
+    /// The current frame and up to `count - 1` after it, as indices into the folder's files
+    pub fn upcoming(&self, count: usize) -> Vec<usize> {
+        let frames = &self.sequence.frames;
+        let ahead = if self.looping { frames.len() } else { frames.len() - self.position };
+        (0..count.min(ahead)).map(|step| frames[(self.position + step) % frames.len()]).collect()
+    }
+
+    /// Time until the next frame is due
+    pub fn time_to_next_frame(&self, fps: u32, now: Instant) -> Duration {
+        (Duration::from_secs(1) / fps.max(1)).saturating_sub(now.duration_since(self.shown_at))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn files(names: &[&str]) -> Vec<FileInfo> {
+        names.iter().map(|name| FileInfo {
+            path: PathBuf::from(name),
+            locality_status: crate::file_locality::FileLocalityStatus::Local,
+            estimated_download_size: None,
+            provider: None,
+            render_estimate: None,
+        }).collect()
+    }
+
+    #[test]
+    fn test_find_sequences() {
+        let files = files(&[
+            "frame_0010.png", "frame_0002.png", "frame_0001.png", "frame_0003.PNG",
+            "shot1.jpg", "shot2.jpg", "cover.jpg", "frame_0004.exr",
+        ]);
+        let sequences = find_sequences(&files);
+        assert_eq!(sequences, vec![FrameSequence { name: "frame_####.png".to_string(), frames: vec![2, 1, 3, 0] }]);
+        assert_eq!(split_frame_number("v2_shot_010.tif"), Some(("v2_shot_", 10, ".tif")));
+        assert_eq!(split_frame_number("cover.jpg"), None);
+    }
+
+    #[test]
+    fn test_player_ticks_at_the_frame_rate() {
+        let start = Instant::now();
+        let mut player = SequencePlayer::new(FrameSequence { name: "f#.png".to_string(), frames: vec![5, 6, 7] });
+        player.seek(0, start);
+        assert!(!player.tick(10, start + Duration::from_secs(1)), "paused");
+        player.toggle(start);
+        assert!(!player.tick(10, start + Duration::from_millis(50)));
+        assert!(player.tick(10, start + Duration::from_millis(100)));
+        assert_eq!(player.current(), 6);
+        // Decoding fell behind by two frames
+        assert!(player.tick(10, start + Duration::from_millis(300)));
+        assert_eq!(player.current(), 5);
+
+        player.looping = false;
+        player.seek(1, start);
+        assert!(player.tick(10, start + Duration::from_millis(500)));
+        assert_eq!(player.current(), 7);
+        assert!(!player.playing);
+    }
+
+    #[test]
+    fn test_frames_are_read_ahead_locally() {
+        let mut files = files(&["f1.png", "f2.png", "f3.png", "f4.png"]);
+        files[2].locality_status = crate::file_locality::FileLocalityStatus::OnDemand;
+        let sequence = FrameSequence { name: "f#.png".to_string(), frames: vec![0, 1, 2, 3] };
+        let mut player = SequencePlayer::local(sequence, &files).unwrap();
+        assert_eq!(player.sequence.frames, [0, 1, 3]);
+
+        player.seek(1, Instant::now());
+        assert_eq!(player.upcoming(5), [1, 3, 0]);
+        player.looping = false;
+        assert_eq!(player.upcoming(5), [1, 3]);
+    }
+}
@@ -0,0 +1,431 @@
+//! Undo and redo
+//!
+//! Everything that can be undone is recorded as a [`Command`]. Edits of the
+//! displayed image are replayed on top of its decoded pixels, so undoing one
+//! redraws the others; they only apply to that image and are dropped when
+//! another one is shown. File operations are undone on disk and stay undoable
+//! for the rest of the session. Deleted files go to the Recycle Bin or Trash,
+//! where they stay recoverable after the viewer closes, and undoing a delete
+//! restores them from there. A file's XMP and edit sidecars move along with it.
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui::ColorImage;
+
+use crate::adjustment::Adjustment;
+use crate::annotation::Annotation;
+use crate::color_stats::PixelRect;
+use crate::duplicates::target_in;
+use crate::image_processing::{crop, rotate_clockwise};
+use crate::redaction::Redaction;
+use crate::{edit_sidecar, xmp_sidecar};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageEdit {
+    RotateClockwise,
+    /// Keep only this region, in the pixels of the original file
+    Crop(PixelRect),
+    Adjust(Adjustment),
+    Annotate(Annotation),
+    Redact(Redaction),
+}
+
+impl ImageEdit {
+    /// Edit `image`, shown at `scale` times the size of the original file
+    pub fn apply(&self, image: &mut ColorImage, scale: f64) {
+        match self {
+            ImageEdit::RotateClockwise => *image = rotate_clockwise(image),
+            ImageEdit::Crop(region) => {
+                if let Some(region) = region.scaled(scale, image.size) {
+                    *image = crop(image, &region);
+                }
+            }
+            ImageEdit::Adjust(adjustment) => adjustment.apply(image),
+            ImageEdit::Annotate(annotation) => annotation.apply(image, scale),
+            ImageEdit::Redact(redaction) => redaction.apply(image, scale),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            ImageEdit::RotateClockwise => "Rotate".to_string(),
+            ImageEdit::Crop(region) => format!("Crop to {}x{}", region.width(), region.height()),
+            ImageEdit::Adjust(adjustment) => adjustment.label(),
+            ImageEdit::Annotate(_) => "Draw rectangle".to_string(),
+            ImageEdit::Redact(redaction) => format!("{} region", redaction.style.label()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileOperation {
+    Rename { from: PathBuf, to: PathBuf },
+    /// `original` was sent to the trash along with its sidecars, `trashed`
+    /// holding the full paths of all of them
+    Delete { original: PathBuf, trashed: Vec<PathBuf> },
+    /// `from` was moved into another folder as `to`
+    Move { from: PathBuf, to: PathBuf },
+}
+
+/// Every name a sidecar of `image` can have, whether or not it exists
+fn sidecar_paths(image: &Path) -> impl Iterator<Item = PathBuf> {
+    let xmp = xmp_sidecar::sidecar_names(image).map(|name| image.with_file_name(name));
+    xmp.into_iter().chain([edit_sidecar::sidecar_path(image)])
+}
+
+/// The sidecars `from` has, paired with where they go when it becomes `to`
+fn sidecar_moves(from: &Path, to: &Path) -> Vec<(PathBuf, PathBuf)> {
+    sidecar_paths(from).zip(sidecar_paths(to)).filter(|(old, _)| old.is_file()).collect()
+}
+
+/// Whether `to` or one of the sidecars `from` would bring along already exists
+fn is_taken(from: &Path, to: &Path) -> bool {
+    to.exists() || sidecar_moves(from, to).iter().any(|(_, sidecar)| sidecar.exists())
+}
+
+fn rename_or_copy(from: &Path, to: &Path) -> Result<(), String> {
+    // Renaming fails across drives; fall back to copying
+    if std::fs::rename(from, to).is_err() {
+        std::fs::copy(from, to).map_err(|e| format!("Failed to move {}: {}", from.display(), e))?;
+        std::fs::remove_file(from).map_err(|e| format!("Failed to remove {}: {}", from.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Move a file and its sidecars to exactly `to`, which must not exist yet
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if is_taken(from, to) {
+        return Err(format!("{} already exists", to.display()));
+    }
+    let sidecars = sidecar_moves(from, to);
+    rename_or_copy(from, to)?;
+    for (old, new) in sidecars {
+        rename_or_copy(&old, &new)?;
+    }
+    Ok(())
+}
+
+/// Move `path` and its sidecars into `folder`, numbering it if the name is taken there
+fn move_into(path: &Path, folder: &Path) -> Result<PathBuf, String> {
+    let to = target_in(path, folder, |target| is_taken(path, target))?;
+    move_file(path, &to)?;
+    Ok(to)
+}
+
+fn send_to_trash(paths: &[PathBuf]) -> Result<(), String> {
+    trash::delete_all(paths).map_err(|e| format!("Failed to move {} to the trash: {}", paths[0].display(), e))
+}
+
+/// Put the most recently trashed item of each of `paths` back where it was
+#[cfg(any(windows, all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))))]
+fn restore_from_trash(paths: &[PathBuf]) -> Result<(), String> {
+    let mut items = trash::os_limited::list().map_err(|e| format!("Failed to read the trash: {}", e))?;
+    items.sort_by_key(|item| std::cmp::Reverse(item.time_deleted));
+    let mut restore = Vec::new();
+    for path in paths {
+        let Some(index) = items.iter().position(|item| item.original_path() == *path) else {
+            return Err(format!("{} is no longer in the trash", path.display()));
+        };
+        restore.push(items.remove(index));
+    }
+    trash::os_limited::restore_all(restore).map_err(|e| format!("Failed to restore {}: {}", paths[0].display(), e))
+}
+
+/// The Finder's trash can't be read, so files are put back from there by hand
+#[cfg(not(any(windows, all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))))]
+fn restore_from_trash(paths: &[PathBuf]) -> Result<(), String> {
+    Err(format!("Use Put Back in the Trash to restore {}", paths[0].display()))
+}
+
+impl FileOperation {
+    pub fn rename(from: &Path, to: &Path) -> Result<Self, String> {
+        move_file(from, to)?;
+        Ok(FileOperation::Rename { from: from.to_path_buf(), to: to.to_path_buf() })
+    }
+
+    /// Send `path` and its sidecars to the Recycle Bin or Trash
+    pub fn delete(path: &Path) -> Result<Self, String> {
+        // The trash records where items came from by their full path
+        let full = |path: PathBuf| std::fs::canonicalize(&path).unwrap_or(path);
+        let trashed: Vec<PathBuf> = std::iter::once(path.to_path_buf())
+            .chain(sidecar_paths(path).filter(|sidecar| sidecar.is_file()))
+            .map(full)
+            .collect();
+        send_to_trash(&trashed)?;
+        Ok(FileOperation::Delete { original: path.to_path_buf(), trashed })
+    }
+
+    /// Move `path` into `folder`, numbering it if the name is taken there
+    pub fn move_into(path: &Path, folder: &Path) -> Result<Self, String> {
+        let to = move_into(path, folder)?;
+        Ok(FileOperation::Move { from: path.to_path_buf(), to })
+    }
+
+    pub fn label(&self) -> String {
+        let name = |path: &Path| path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        match self {
+            FileOperation::Rename { from, to } => format!("Rename {} to {}", name(from), name(to)),
+            FileOperation::Delete { original, .. } => format!("Delete {}", name(original)),
+            FileOperation::Move { from, to } => format!("Move {} to {}", name(from), to.parent().map(name).unwrap_or_default()),
+        }
+    }
+
+    /// The file the operation leaves in the folder, if any
+    pub fn result(&self) -> Option<&Path> {
+        match self {
+            FileOperation::Rename { to, .. } => Some(to),
+            FileOperation::Delete { .. } | FileOperation::Move { .. } => None,
+        }
+    }
+
+    /// Where the file was before the operation
+    pub fn original(&self) -> &Path {
+        match self {
+            FileOperation::Rename { from, .. } | FileOperation::Move { from, .. } => from,
+            FileOperation::Delete { original, .. } => original,
+        }
+    }
+
+    pub fn undo(&self) -> Result<(), String> {
+        match self {
+            FileOperation::Rename { from, to } | FileOperation::Move { from, to } => move_file(to, from),
+            FileOperation::Delete { trashed, .. } => restore_from_trash(trashed),
+        }
+    }
+
+    pub fn redo(&self) -> Result<(), String> {
+        match self {
+            FileOperation::Rename { from, to } | FileOperation::Move { from, to } => move_file(from, to),
+            FileOperation::Delete { trashed, .. } => send_to_trash(trashed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Edit(ImageEdit),
+    File(FileOperation),
+}
+
+impl Command {
+    pub fn label(&self) -> String {
+        match self {
+            Command::Edit(edit) => edit.label(),
+            Command::File(operation) => operation.label(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    /// Oldest first
+    done: Vec<Command>,
+    /// Most recently undone last
+    undone: Vec<Command>,
+}
+
+impl History {
+    /// Record a command that was just carried out
+    pub fn record(&mut self, command: Command) {
+        self.done.push(command);
+        self.undone.clear();
+    }
+
+    pub fn done(&self) -> &[Command] {
+        &self.done
+    }
+
+    pub fn undone(&self) -> &[Command] {
+        &self.undone
+    }
+
+    /// Undo the last command with `run`; it stays done if `run` fails
+    pub fn undo(&mut self, run: impl FnOnce(&Command) -> Result<(), String>) -> Option<Result<Command, String>> {
+        let command = self.done.pop()?;
+        Some(match run(&command) {
+            Ok(()) => {
+                self.undone.push(command.clone());
+                Ok(command)
+            }
+            Err(e) => {
+                self.done.push(command);
+                Err(e)
+            }
+        })
+    }
+
+    /// Redo the last undone command with `run`; it stays undone if `run` fails
+    pub fn redo(&mut self, run: impl FnOnce(&Command) -> Result<(), String>) -> Option<Result<Command, String>> {
+        let command = self.undone.pop()?;
+        Some(match run(&command) {
+            Ok(()) => {
+                self.done.push(command.clone());
+                Ok(command)
+            }
+            Err(e) => {
+                self.undone.push(command);
+                Err(e)
+            }
+        })
+    }
+
+    /// Edits of the displayed image, in the order they were made
+    pub fn image_edits(&self) -> impl Iterator<Item = &ImageEdit> {
+        self.done.iter().filter_map(|command| match command {
+            Command::Edit(edit) => Some(edit),
+            Command::File(_) => None,
+        })
+    }
+
+    pub fn has_image_edits(&self) -> bool {
+        self.image_edits().next().is_some()
+    }
+
+    /// Add edits saved earlier, as if they had just been made, without
+    /// touching what can be redone
+    pub fn replay(&mut self, edits: impl IntoIterator<Item = ImageEdit>) {
+        self.done.extend(edits.into_iter().map(Command::Edit));
+    }
+
+    /// Drop the edits when another image is shown
+    pub fn forget_image_edits(&mut self) {
+        self.done.retain(|command| matches!(command, Command::File(_)));
+        self.undone.retain(|command| matches!(command, Command::File(_)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    /// Empty what a test sent to the trash
+    #[cfg(any(windows, target_os = "linux"))]
+    fn purge(operation: &FileOperation) {
+        let FileOperation::Delete { trashed, .. } = operation else {
+            return;
+        };
+        if let Ok(items) = trash::os_limited::list() {
+            let _ = trash::os_limited::purge_all(items.into_iter().filter(|item| trashed.contains(&item.original_path())));
+        }
+    }
+
+    #[test]
+    #[cfg(any(windows, target_os = "linux"))]
+    fn test_delete_and_rename_are_undone_on_disk() {
+        let dir = TempDir::new("history");
+        let photo = dir.join("photo.jpg");
+        let renamed = dir.join("holiday.jpg");
+        std::fs::write(&photo, "jpeg").unwrap();
+        let mut history = History::default();
+
+        history.record(Command::File(FileOperation::rename(&photo, &renamed).unwrap()));
+        let deleted = FileOperation::delete(&renamed).unwrap();
+        history.record(Command::File(deleted.clone()));
+        history.record(Command::Edit(ImageEdit::RotateClockwise));
+        assert!(!renamed.exists());
+
+        let run = |command: &Command| match command {
+            Command::File(operation) => operation.undo(),
+            Command::Edit(_) => Ok(()),
+        };
+        assert!(history.undo(run).unwrap().is_ok());
+        assert!(!history.has_image_edits());
+        assert!(history.undo(run).unwrap().is_ok());
+        let restored = std::fs::read_to_string(&renamed);
+        assert!(history.redo(|command| match command {
+            Command::File(operation) => operation.redo(),
+            Command::Edit(_) => Ok(()),
+        }).unwrap().is_ok());
+        let trashed_again = !renamed.exists();
+        purge(&deleted);
+
+        assert_eq!(restored.unwrap(), "jpeg");
+        assert!(trashed_again);
+        history.forget_image_edits();
+        assert_eq!(history.undone().len(), 0, "the rotation was dropped");
+    }
+
+    #[test]
+    fn test_move_into_renames_clashes_and_brings_sidecars() {
+        let dir = TempDir::new("history_move");
+        let target = dir.join("quarantine");
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::write(dir.join("a.jpg"), "copy").unwrap();
+        std::fs::write(dir.join("a.jpg.edits"), "rotate\n").unwrap();
+        std::fs::write(target.join("a.jpg"), "original").unwrap();
+
+        let moved = FileOperation::move_into(&dir.join("a.jpg"), &target).unwrap();
+        assert_eq!(moved, FileOperation::Move { from: dir.join("a.jpg"), to: target.join("a (2).jpg") });
+        assert!(!dir.join("a.jpg").exists());
+        assert!(!dir.join("a.jpg.edits").exists());
+        assert!(target.join("a (2).jpg.edits").exists());
+
+        moved.undo().unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("a.jpg")).unwrap(), "copy");
+        assert!(dir.join("a.jpg.edits").exists());
+        assert_eq!(std::fs::read_to_string(target.join("a.jpg")).unwrap(), "original");
+    }
+
+    #[test]
+    fn test_undoing_an_edit_redraws_the_rest() {
+        use eframe::egui::Color32;
+
+        let annotate = ImageEdit::Annotate(Annotation {
+            region: PixelRect { min_x: 0, min_y: 0, max_x: 4, max_y: 4 },
+            color: Color32::RED,
+            width: 1,
+        });
+        let mut history = History::default();
+        for edit in [
+            ImageEdit::RotateClockwise,
+            ImageEdit::Crop(PixelRect { min_x: 0, min_y: 0, max_x: 4, max_y: 6 }),
+            ImageEdit::Adjust(Adjustment { brightness: -100, ..Default::default() }),
+            annotate,
+        ] {
+            history.record(Command::Edit(edit));
+        }
+        // Shown at half the size of the original
+        let redraw = |history: &History| {
+            let mut image = ColorImage::new([8, 4], Color32::WHITE);
+            history.image_edits().for_each(|edit| edit.apply(&mut image, 0.5));
+            image
+        };
+
+        let edited = redraw(&history);
+        assert_eq!(edited.size, [2, 3]);
+        assert_eq!(edited.pixels[0], Color32::RED);
+        assert_eq!(edited.pixels[5], Color32::BLACK);
+
+        assert_eq!(history.undo(|_| Ok(())), Some(Ok(Command::Edit(annotate))));
+        assert_eq!(redraw(&history).pixels, vec![Color32::BLACK; 6]);
+        history.undo(|_| Ok(())).unwrap().unwrap();
+        history.undo(|_| Ok(())).unwrap().unwrap();
+        assert_eq!(redraw(&history).size, [4, 8]);
+        history.redo(|_| Ok(())).unwrap().unwrap();
+        assert_eq!(redraw(&history).size, [2, 3]);
+    }
+
+    #[test]
+    #[cfg(any(windows, target_os = "linux"))]
+    fn test_sidecars_go_to_the_trash_with_their_image() {
+        let dir = TempDir::new("history_sidecar");
+        let names = ["photo.jpg", "photo.xmp", "photo.jpg.edits"];
+        for name in names {
+            std::fs::write(dir.join(name), name).unwrap();
+        }
+
+        let deleted = FileOperation::delete(&dir.join("photo.jpg")).unwrap();
+        let left: Vec<bool> = names.iter().map(|name| dir.join(name).exists()).collect();
+        let undone = deleted.undo();
+        let restored = names.iter().all(|name| std::fs::read_to_string(dir.join(name)).is_ok_and(|text| text == *name));
+        deleted.redo().unwrap();
+        purge(&deleted);
+
+        let FileOperation::Delete { trashed, .. } = &deleted else { unreachable!() };
+        assert_eq!(trashed.len(), 3);
+        assert_eq!(left, [false; 3]);
+        assert_eq!(undone, Ok(()));
+        assert!(restored);
+    }
+}
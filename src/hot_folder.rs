@@ -0,0 +1,165 @@
+//! Showing images as they appear in a folder
+//!
+//! A camera tether or screenshot tool writes a new file into a folder; the
+//! [`HotFolder`] notices it and hands it over once it has finished writing.
+//! RAW files are reported too, so a RAW+JPEG pair can be stacked without
+//! listing the folder again.
+//! The folder is polled rather than watched through the OS, so this also
+//! works on network shares. A file counts as finished once its size and
+//! modification time have stopped changing for [`SETTLE_TIME`].
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant, SystemTime};
+
+use eframe::egui;
+
+use crate::stacks::RAW_EXTENSIONS;
+
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long a new file must stay unchanged before it is shown
+pub const SETTLE_TIME: Duration = Duration::from_secs(1);
+
+/// A file in a listing, with its size and modification time
+pub type Listed = (PathBuf, u64, Option<SystemTime>);
+
+/// Spots files that weren't in the folder before and reports each one once
+/// it has stopped changing
+#[derive(Debug, Default)]
+pub struct NewFileTracker {
+    known: HashSet<PathBuf>,
+    /// New files still being written: their last size and time, and since when
+    pending: HashMap<PathBuf, (u64, Option<SystemTime>, Instant)>,
+}
+
+impl NewFileTracker {
+    /// Start with the files already in the folder, which are never reported
+    pub fn new(existing: impl IntoIterator<Item = PathBuf>) -> Self {
+        Self { known: existing.into_iter().collect(), pending: HashMap::new() }
+    }
+
+    /// Compare a fresh listing with the previous ones; returns the new files
+    /// that have finished writing, oldest first
+    pub fn update(&mut self, listing: Vec<Listed>, now: Instant) -> Vec<PathBuf> {
+        let present: HashSet<&PathBuf> = listing.iter().map(|(path, _, _)| path).collect();
+        self.pending.retain(|path, _| present.contains(path));
+        let mut settled = Vec::new();
+        for (path, size, modified) in listing {
+            if self.known.contains(&path) {
+                continue;
+            }
+            match self.pending.get_mut(&path) {
+                Some(entry) if (entry.0, entry.1) == (size, modified) => {
+                    // An empty file is most likely still being created
+                    if size > 0 && now.duration_since(entry.2) >= SETTLE_TIME {
+                        settled.push((modified, path.clone()));
+                    }
+                }
+                Some(entry) => *entry = (size, modified, now),
+                None => {
+                    self.pending.insert(path, (size, modified, now));
+                }
+            }
+        }
+        settled.sort();
+        settled.into_iter().map(|(_, path)| {
+            self.pending.remove(&path);
+            self.known.insert(path.clone());
+            path
+        }).collect()
+    }
+}
+
+/// Files directly in `dir` with one of `extensions`
+fn list(dir: &Path, extensions: &[String]) -> Vec<Listed> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            entry.path().extension().is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+            Some((entry.path(), metadata.len(), metadata.modified().ok()))
+        })
+        .collect()
+}
+
+/// A folder polled on a background thread for new images
+pub struct HotFolder {
+    dir: PathBuf,
+    arrived: Receiver<PathBuf>,
+    stop: Arc<AtomicBool>,
+    /// RAW files that arrived without an image of the same name yet
+    pub unpaired_raw: Vec<PathBuf>,
+}
+
+impl HotFolder {
+    /// Watch `dir` for images with `extensions` and for RAW files
+    pub fn watch(dir: PathBuf, mut extensions: Vec<String>, ctx: egui::Context) -> Self {
+        extensions.extend(RAW_EXTENSIONS.map(String::from));
+        let (sender, arrived) = std::sync::mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread_dir = dir.clone();
+        std::thread::spawn(move || {
+            let existing = list(&thread_dir, &extensions).into_iter().map(|(path, _, _)| path);
+            let mut tracker = NewFileTracker::new(existing);
+            while !thread_stop.load(Ordering::Relaxed) {
+                std::thread::sleep(POLL_INTERVAL);
+                for path in tracker.update(list(&thread_dir, &extensions), Instant::now()) {
+                    if sender.send(path).is_err() {
+                        return;
+                    }
+                    ctx.request_repaint();
+                }
+            }
+        });
+        Self { dir, arrived, stop, unpaired_raw: Vec::new() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// New images and RAW files that finished writing since the last call
+    pub fn poll(&self) -> Vec<PathBuf> {
+        self.arrived.try_iter().collect()
+    }
+}
+
+impl Drop for HotFolder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_files_are_reported_once_settled() {
+        let start = Instant::now();
+        let old = PathBuf::from("old.jpg");
+        let new = PathBuf::from("new.jpg");
+        let mut tracker = NewFileTracker::new([old.clone()]);
+        let listing = |size: u64| vec![(old.clone(), 10, None), (new.clone(), size, None)];
+
+        assert!(tracker.update(listing(0), start).is_empty());
+        // Still empty after the settle time: not written yet
+        assert!(tracker.update(listing(0), start + SETTLE_TIME).is_empty());
+        // Growing restarts the clock
+        assert!(tracker.update(listing(500), start + SETTLE_TIME * 2).is_empty());
+        assert!(tracker.update(listing(500), start + SETTLE_TIME * 2 + POLL_INTERVAL).is_empty());
+        assert_eq!(tracker.update(listing(500), start + SETTLE_TIME * 3), vec![new.clone()]);
+        // Reported once
+        assert!(tracker.update(listing(500), start + SETTLE_TIME * 4).is_empty());
+    }
+}
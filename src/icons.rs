@@ -1,9 +1,56 @@
-//! Icon support for the application
+//! SVG icon rendering for egui
+//!
+//! This module doesn't depend on application state: icons are looked up in an
+//! [`IconSet`], rendered with resvg in the requested color and cached as egui
+//! textures by an [`IconRenderer`]. The one other module it needs is the
+//! general-purpose `cache` module, whose LRU holds the textures, so
+//! reusing it elsewhere means taking `cache.rs` along.
+//!
+//! The built-in icons live in `assets/icons`. Additional sets can be embedded
+//! with [`embed_icons!`](crate::embed_icons) and combined with the built-ins
+//! through [`IconSet::builder`]; icons registered later replace earlier ones
+//! with the same name.
+//!
+//! At runtime an [`IconDirectory`] of SVG files can override any of them, and
+//! an icon can have `name.dark` / `name.light` variants that are preferred
+//! over `name` with a dark or light theme.
+//!
+//! ```no_run
+//! use eframe::egui;
+//! use image_previewer::embed_icons;
+//! use image_previewer::icons::{IconRenderer, IconSet, Icons};
+//!
+//! let extra = embed_icons!{"assets/icons/*.svg"};
+//! let icons = IconSet::builder()
+//!     .with_builtin_icons()
+//!     .with_set(&extra)
+//!     .fallback("star", "★")
+//!     .build();
+//! let mut renderer = IconRenderer::with_icon_set(icons);
+//!
+//! # let ctx = egui::Context::default();
+//! # egui::CentralPanel::default().show(&ctx, |ui| {
+//! renderer.icon_label(ui, &ctx, Icons::CHECK, 16.0, egui::Color32::GREEN);
+//! renderer.icon_button(ui, &ctx, "star", 16.0, egui::Color32::GOLD, "Favourite");
+//! # });
+//! ```
 
 use eframe::egui;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use resvg;
+
+use crate::cache::{CacheCounters, LruCache, ManagedCache, SharedCache};
+
+#[doc(hidden)]
+pub use include_dir;
+#[doc(hidden)]
+pub use image_previewer_macros::icon_files;
+
 /// Pre-validated SVG icon data embedded at compile time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct EmbeddedIcon {
     pub name: &'static str,
     pub content: &'static str,
@@ -18,21 +65,157 @@ pub static EMBEDDED_ICONS: &[EmbeddedIcon] = &[
     EmbeddedIcon { name: "cloud", content: include_str!("../assets/icons/cloud.svg") },
     EmbeddedIcon { name: "device-floppy", content: include_str!("../assets/icons/device-floppy.svg") },
     EmbeddedIcon { name: "download", content: include_str!("../assets/icons/download.svg") },
+    EmbeddedIcon { name: "folder", content: include_str!("../assets/icons/folder.svg") },
     EmbeddedIcon { name: "help", content: include_str!("../assets/icons/help.svg") },
+    EmbeddedIcon { name: "maximize", content: include_str!("../assets/icons/maximize.svg") },
+    EmbeddedIcon { name: "player-pause", content: include_str!("../assets/icons/player-pause.svg") },
+    EmbeddedIcon { name: "player-play", content: include_str!("../assets/icons/player-play.svg") },
+    EmbeddedIcon { name: "refresh", content: include_str!("../assets/icons/refresh.svg") },
+    EmbeddedIcon { name: "rotate-clockwise", content: include_str!("../assets/icons/rotate-clockwise.svg") },
+    EmbeddedIcon { name: "settings", content: include_str!("../assets/icons/settings.svg") },
+    EmbeddedIcon { name: "trash", content: include_str!("../assets/icons/trash.svg") },
     EmbeddedIcon { name: "x", content: include_str!("../assets/icons/x.svg") },
+    EmbeddedIcon { name: "zoom-in", content: include_str!("../assets/icons/zoom-in.svg") },
+    EmbeddedIcon { name: "zoom-out", content: include_str!("../assets/icons/zoom-out.svg") },
 ];
 
-/// SVG icon loader and renderer with embedded validation
-pub struct SvgIcons;
+/// Text shown in place of the built-in icons when they can't be rendered
+const BUILTIN_FALLBACKS: &[(&str, &str)] = &[
+    ("device-floppy", "💾"),
+    ("cloud", "☁"),
+    ("download", "⬇"),
+    ("check", "✓"),
+    ("x", "✗"),
+    ("alert-triangle", "⚠"),
+    ("help", "?"),
+    ("circle-check", "✅"),
+    ("clock", "🕐"),
+    ("folder", "📁"),
+    ("refresh", "⟳"),
+    ("rotate-clockwise", "↻"),
+    ("zoom-in", "+"),
+    ("zoom-out", "-"),
+    ("maximize", "⛶"),
+    ("player-play", "▶"),
+    ("player-pause", "⏸"),
+    ("trash", "🗑"),
+    ("settings", "⚙"),
+];
 
-impl SvgIcons {
-    /// Validate all embedded SVG icons at compile time
-    pub fn validate_all_icons() -> Result<(), String> {
-        for icon in EMBEDDED_ICONS {
+/// Embed SVG icons at compile time, producing an [`IconSet`](crate::icons::IconSet).
+///
+/// Icons can be listed by name, with paths relative to the crate root:
+///
+/// ```ignore
+/// let set = embed_icons!{
+///     "star" => "assets/icons2/star.svg",
+///     "heart" => "assets/icons2/heart.svg",
+/// };
+/// ```
+///
+/// or picked by a glob, also relative to the crate root, in which case each
+/// matching file becomes an icon named after its file stem:
+///
+/// ```ignore
+/// let set = embed_icons!{"assets/icons2/*.svg"};
+/// ```
+///
+/// The glob is expanded when compiling, so a file added later is only picked
+/// up once the calling crate is rebuilt. A whole directory can also be
+/// embedded, taking every `*.svg` file in it:
+///
+/// ```ignore
+/// let set = embed_icons!(dir "$CARGO_MANIFEST_DIR/assets/icons2");
+/// ```
+#[macro_export]
+macro_rules! embed_icons {
+    (dir $path:tt) => {{
+        use $crate::icons::include_dir;
+        static DIR: include_dir::Dir<'static> = include_dir::include_dir!($path);
+        $crate::icons::IconSet::from_dir(&DIR)
+    }};
+    ($pattern:literal) => {{
+        static FILES: &[(&str, &str)] = &$crate::icons::icon_files!($pattern);
+        $crate::icons::IconSet::from_files(FILES)
+    }};
+    ($($name:literal => $path:literal),* $(,)?) => {{
+        static ICONS: &[$crate::icons::EmbeddedIcon] = &[
+            $($crate::icons::EmbeddedIcon {
+                name: $name,
+                content: include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path)),
+            }),*
+        ];
+        $crate::icons::IconSet::from_icons(ICONS)
+    }};
+}
+
+/// A named collection of SVG icons plus the text shown when one can't be rendered
+#[derive(Debug, Clone, Default)]
+pub struct IconSet {
+    icons: Vec<EmbeddedIcon>,
+    fallbacks: HashMap<String, String>,
+}
+
+impl IconSet {
+    /// The icons shipped with the application
+    pub fn builtin() -> Self {
+        Self::builder().with_builtin_icons().build()
+    }
+
+    pub fn builder() -> IconSetBuilder {
+        IconSetBuilder::default()
+    }
+
+    pub fn from_icons(icons: &[EmbeddedIcon]) -> Self {
+        Self::builder().with_icons(icons).build()
+    }
+
+    /// Every `*.svg` file directly inside `dir`, named by file stem
+    pub fn from_dir(dir: &'static include_dir::Dir<'static>) -> Self {
+        let icons: Vec<EmbeddedIcon> = dir.files()
+            .filter(|file| file.path().extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")))
+            .filter_map(|file| {
+                let name = file.path().file_stem()?.to_str()?;
+                Some(EmbeddedIcon { name, content: file.contents_utf8()? })
+            })
+            .collect();
+        Self::from_icons(&icons)
+    }
+
+    /// Icons from `(name, content)` pairs, as the glob form of
+    /// [`embed_icons!`](crate::embed_icons) lists them
+    pub fn from_files(files: &[(&'static str, &'static str)]) -> Self {
+        let icons: Vec<EmbeddedIcon> = files.iter().map(|&(name, content)| EmbeddedIcon { name, content }).collect();
+        Self::from_icons(&icons)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&'static str> {
+        self.icons.iter().find(|icon| icon.name == name).map(|icon| icon.content)
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.icons.iter().map(|icon| icon.name).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.icons.is_empty()
+    }
+
+    /// Text to show when `name` can't be rendered: the registered fallback, or
+    /// the bracketed first letter of the name
+    pub fn fallback_text(&self, name: &str) -> String {
+        self.fallbacks.get(name).cloned().unwrap_or_else(|| {
+            format!("[{}]", name.chars().next().unwrap_or('?').to_uppercase())
+        })
+    }
+
+    /// Check that every icon contains SVG markup
+    pub fn validate(&self) -> Result<(), String> {
+        for icon in &self.icons {
             if icon.content.is_empty() {
                 return Err(format!("Icon '{}' has empty content", icon.name));
             }
-            
+
             // Basic SVG validation - check for required elements
             if !icon.content.contains("<svg") {
                 return Err(format!("Icon '{}' does not contain valid SVG markup", icon.name));
@@ -40,42 +223,154 @@ impl SvgIcons {
         }
         Ok(())
     }
-    
+}
+
+/// Builder for an [`IconSet`]; later icons replace earlier ones with the same name
+#[derive(Debug, Default)]
+pub struct IconSetBuilder {
+    set: IconSet,
+}
+
+impl IconSetBuilder {
+    pub fn with_builtin_icons(mut self) -> Self {
+        self = self.with_icons(EMBEDDED_ICONS);
+        for (name, text) in BUILTIN_FALLBACKS {
+            self = self.fallback(name, text);
+        }
+        self
+    }
+
+    pub fn with_icons(mut self, icons: &[EmbeddedIcon]) -> Self {
+        for icon in icons {
+            self.set.icons.retain(|existing| existing.name != icon.name);
+            self.set.icons.push(*icon);
+        }
+        self
+    }
+
+    /// Add every icon and fallback from another set
+    pub fn with_set(mut self, other: &IconSet) -> Self {
+        self = self.with_icons(&other.icons);
+        self.set.fallbacks.extend(other.fallbacks.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self
+    }
+
+    pub fn fallback(mut self, name: &str, text: &str) -> Self {
+        self.set.fallbacks.insert(name.to_string(), text.to_string());
+        self
+    }
+
+    pub fn build(self) -> IconSet {
+        self.set
+    }
+}
+
+/// Icons read from a directory at runtime, each `*.svg` file named after its
+/// file stem (`check.svg`, `check.dark.svg`)
+#[derive(Debug, Clone, Default)]
+pub struct IconDirectory {
+    dir: PathBuf,
+    icons: HashMap<String, String>,
+    /// Newest modification time and number of files when loaded
+    stamp: (Option<SystemTime>, usize),
+}
+
+impl IconDirectory {
+    pub fn load(dir: impl Into<PathBuf>) -> Result<Self, String> {
+        let dir = dir.into();
+        let entries = std::fs::read_dir(&dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+        let icons = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")))
+            .filter_map(|path| {
+                let name = path.file_stem()?.to_str()?.to_string();
+                let content = std::fs::read_to_string(&path).ok().filter(|content| content.contains("<svg"))?;
+                Some((name, content))
+            })
+            .collect();
+        let stamp = Self::stamp(&dir);
+        Ok(Self { dir, icons, stamp })
+    }
+
+    fn stamp(dir: &Path) -> (Option<SystemTime>, usize) {
+        let files: Vec<_> = std::fs::read_dir(dir).map(|entries| entries.flatten().collect()).unwrap_or_default();
+        let newest = files.iter().filter_map(|entry| entry.metadata().and_then(|m| m.modified()).ok()).max();
+        (newest, files.len())
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.icons.get(name).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.icons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.icons.is_empty()
+    }
+
+    /// Reload if a file was added, removed or edited since loading; returns
+    /// whether anything changed
+    pub fn reload_if_changed(&mut self) -> Result<bool, String> {
+        if Self::stamp(&self.dir) == self.stamp {
+            return Ok(false);
+        }
+        *self = Self::load(self.dir.clone())?;
+        Ok(true)
+    }
+}
+
+/// SVG icon loader and renderer for the built-in icons
+pub struct SvgIcons;
+
+impl SvgIcons {
+    /// Validate all embedded SVG icons at compile time
+    pub fn validate_all_icons() -> Result<(), String> {
+        IconSet::builtin().validate()
+    }
+
     /// Get embedded SVG content by name
     fn get_embedded_svg(icon_name: &str) -> Option<&'static str> {
         EMBEDDED_ICONS.iter()
             .find(|icon| icon.name == icon_name)
             .map(|icon| icon.content)
     }
-    
+
     /// Get list of all available icon names
     pub fn get_available_icons() -> Vec<&'static str> {
         EMBEDDED_ICONS.iter().map(|icon| icon.name).collect()
     }
-    
+
     /// Load and render an SVG icon as an egui texture using embedded content
     pub fn load_icon(ctx: &egui::Context, icon_name: &str, size: f32, color: egui::Color32) -> Option<egui::TextureHandle> {
         let svg_content = Self::get_embedded_svg(icon_name)?;
         Self::render_svg_to_texture(ctx, svg_content, size, color, icon_name)
     }
-    
-    fn render_svg_to_texture(ctx: &egui::Context, svg_content: &str, size: f32, color: egui::Color32, icon_name: &str) -> Option<egui::TextureHandle> {
+
+    /// Render arbitrary SVG markup, replacing `currentColor` with `color`
+    pub fn render_svg_to_texture(ctx: &egui::Context, svg_content: &str, size: f32, color: egui::Color32, icon_name: &str) -> Option<egui::TextureHandle> {
         use resvg::usvg;
-        
+
         // Validate size parameter to prevent errors
         if size <= 0.0 || size > 1024.0 {
             eprintln!("Warning: Invalid icon size {} for icon '{}', using default 16.0", size, icon_name);
             return Self::render_svg_to_texture(ctx, svg_content, 16.0, color, icon_name);
         }
-        
+
         let colored_svg = svg_content.replace(
-            "currentColor", 
+            "currentColor",
             &format!("rgb({},{},{})", color.r(), color.g(), color.b())
         );
-        
+
         // Parse SVG with error handling
         let opt = usvg::Options::default();
-        
+
         let tree = match usvg::Tree::from_str(&colored_svg, &opt) {
             Ok(tree) => tree,
             Err(e) => {
@@ -83,7 +378,7 @@ impl SvgIcons {
                 return None;
             }
         };
-        
+
         // Render to pixmap with error handling
         let size_u32 = size as u32;
         let mut pixmap = match resvg::tiny_skia::Pixmap::new(size_u32, size_u32) {
@@ -93,15 +388,17 @@ impl SvgIcons {
                 return None;
             }
         };
-        
-        resvg::render(&tree, resvg::tiny_skia::Transform::default(), &mut pixmap.as_mut());
-        
+
+        // Scale the drawing to fill the texture rather than crop it
+        let transform = resvg::tiny_skia::Transform::from_scale(size / tree.size().width(), size / tree.size().height());
+        resvg::render(&tree, transform, &mut pixmap.as_mut());
+
         // Convert to egui texture
-        let image = egui::ColorImage::from_rgba_unmultiplied(
+        let image = egui::ColorImage::from_rgba_premultiplied(
             [size_u32 as usize, size_u32 as usize],
             pixmap.data(),
         );
-        
+
         Some(ctx.load_texture(
             format!("icon_{}_{}", icon_name, size as u32),
             image,
@@ -123,100 +420,231 @@ impl Icons {
     pub const HELP: &'static str = "help";
     pub const CIRCLE_CHECK: &'static str = "circle-check";
     pub const CLOCK: &'static str = "clock";
+    pub const FOLDER: &'static str = "folder";
+    pub const REFRESH: &'static str = "refresh";
+    pub const ROTATE_CLOCKWISE: &'static str = "rotate-clockwise";
+    pub const ZOOM_IN: &'static str = "zoom-in";
+    pub const ZOOM_OUT: &'static str = "zoom-out";
+    pub const MAXIMIZE: &'static str = "maximize";
+    pub const PLAYER_PLAY: &'static str = "player-play";
+    pub const PLAYER_PAUSE: &'static str = "player-pause";
+    pub const TRASH: &'static str = "trash";
+    pub const SETTINGS: &'static str = "settings";
 }
 
-/// Better icon representation that's guaranteed to work
+/// Size limit of an icon renderer's texture cache
+pub const ICON_CACHE_BYTES: usize = 16 * 1024 * 1024;
+
+/// What an [`IconRenderer`]'s texture cache holds and how well it's working
+#[derive(Debug, Clone, PartialEq)]
+pub struct IconCacheStats {
+    pub entries: usize,
+    pub size_bytes: usize,
+    pub limit_bytes: usize,
+    pub counters: CacheCounters,
+    /// Icons that couldn't be rendered, sorted
+    pub failed: Vec<String>,
+}
+
+/// How often a hot-reloaded icon directory is checked for changes
+pub const HOT_RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Renders icons from an [`IconSet`], caching textures per name, size and color
 pub struct IconRenderer {
-    cache: HashMap<String, egui::TextureHandle>,
+    icons: IconSet,
+    /// Overrides `icons` by name
+    directory: Option<IconDirectory>,
+    /// Reload `directory` when its files change, checked at most every `HOT_RELOAD_INTERVAL`
+    hot_reload: bool,
+    last_reload_check: Instant,
+    /// Prefer `.dark` variants over `.light` ones
+    dark_mode: bool,
+    cache: SharedCache<String, egui::TextureHandle>,
+    /// Icons that failed to render, so they aren't retried every frame and
+    /// the warning is only printed once
+    failed: HashSet<String>,
+    /// Show each labeled icon's text next to it
+    show_text_labels: bool,
 }
 
 impl Default for IconRenderer {
     fn default() -> Self {
-        Self {
-            cache: HashMap::new(),
-        }
+        Self::new()
     }
 }
 
 impl IconRenderer {
-    /// Create a new IconRenderer and validate icons
+    /// Create a renderer for the built-in icons
     pub fn new() -> Self {
-        // Validate all icons at startup
-        if let Err(e) = SvgIcons::validate_all_icons() {
+        Self::with_icon_set(IconSet::builtin())
+    }
+
+    /// Create a renderer for a custom icon set, validating it up front
+    pub fn with_icon_set(icons: IconSet) -> Self {
+        if let Err(e) = icons.validate() {
             eprintln!("Warning: Icon validation failed: {}", e);
         }
-        
+
         Self {
-            cache: HashMap::new(),
+            icons,
+            directory: None,
+            hot_reload: false,
+            last_reload_check: Instant::now(),
+            dark_mode: true,
+            cache: LruCache::shared("Icons", ICON_CACHE_BYTES),
+            failed: HashSet::new(),
+            show_text_labels: false,
+        }
+    }
+
+    /// Whether [`Self::labeled_icon`] also shows its label as text, for low-vision users
+    pub fn set_show_text_labels(&mut self, show: bool) {
+        self.show_text_labels = show;
+    }
+
+    pub fn icon_set(&self) -> &IconSet {
+        &self.icons
+    }
+
+    /// Override icons with those in `directory`, or go back to the icon set alone
+    pub fn set_icon_directory(&mut self, directory: Option<IconDirectory>) {
+        self.directory = directory;
+        self.clear_cache();
+    }
+
+    pub fn icon_directory(&self) -> Option<&IconDirectory> {
+        self.directory.as_ref()
+    }
+
+    pub fn set_hot_reload(&mut self, hot_reload: bool) {
+        self.hot_reload = hot_reload;
+    }
+
+    /// Pick the `.dark` or `.light` variant of icons that have them
+    pub fn set_dark_mode(&mut self, dark: bool) {
+        self.dark_mode = dark;
+    }
+
+    /// With hot reload on, reload the icon directory if its files changed;
+    /// returns whether the icons changed
+    pub fn poll_hot_reload(&mut self) -> bool {
+        if !self.hot_reload || self.last_reload_check.elapsed() < HOT_RELOAD_INTERVAL {
+            return false;
+        }
+        self.last_reload_check = Instant::now();
+        let Some(directory) = self.directory.as_mut() else {
+            return false;
+        };
+        match directory.reload_if_changed() {
+            Ok(false) => false,
+            Ok(true) => {
+                self.clear_cache();
+                true
+            }
+            Err(e) => {
+                eprintln!("Warning: Failed to reload icons: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Drop every rendered texture (freeing it once nothing else holds it)
+    /// and retry icons that failed
+    pub fn clear_cache(&mut self) {
+        self.cache.lock().unwrap().clear();
+        self.failed.clear();
+    }
+
+    pub fn stats(&self) -> IconCacheStats {
+        let cache = self.cache.lock().unwrap();
+        let mut failed: Vec<String> = self.failed.iter().cloned().collect();
+        failed.sort();
+        IconCacheStats {
+            entries: cache.len(),
+            size_bytes: cache.size_bytes(),
+            limit_bytes: cache.limit_bytes(),
+            counters: cache.counters(),
+            failed,
         }
     }
-    
+
+    /// Name and markup of the icon to draw for `icon`: the themed variant
+    /// before the plain icon, and the icon directory before the icon set
+    fn resolve(&self, icon: &str) -> Option<(String, &str)> {
+        let variant = format!("{}.{}", icon, if self.dark_mode { "dark" } else { "light" });
+        let directory = self.directory.as_ref();
+        [variant.as_str(), icon]
+            .into_iter()
+            .find_map(|name| directory.and_then(|directory| directory.get(name)).map(|svg| (name.to_string(), svg)))
+            .or_else(|| [variant.as_str(), icon].into_iter().find_map(|name| self.icons.get(name).map(|svg| (name.to_string(), svg))))
+    }
+
+    /// The texture cache, for registering with a [`crate::cache::CacheRegistry`]
+    pub fn cache(&self) -> SharedCache<String, egui::TextureHandle> {
+        Arc::clone(&self.cache)
+    }
+
     /// Get or create an icon texture with better error handling
-    pub fn get_icon(&mut self, ctx: &egui::Context, icon: &str, size: f32, color: egui::Color32) -> Option<&egui::TextureHandle> {
-        let cache_key = format!("{}_{}_{}_{}", icon, size as u32, color.r(), color.g());
-        
-        if !self.cache.contains_key(&cache_key) {
-            match SvgIcons::load_icon(ctx, icon, size, color) {
-                Some(texture) => {
-                    self.cache.insert(cache_key.clone(), texture);
-                }
-                None => {
-                    // Log the failure but don't spam the console
-                    if !self.cache.contains_key(&format!("failed_{}", icon)) {
-                        eprintln!("Warning: Failed to load icon '{}'. Available icons: {:?}", 
-                                icon, SvgIcons::get_available_icons());
-                        // Mark this icon as failed to avoid repeated warnings
-                        self.cache.insert(format!("failed_{}", icon), 
-                            ctx.load_texture("placeholder", egui::ColorImage::new([1, 1], egui::Color32::TRANSPARENT), egui::TextureOptions::default()));
-                    }
+    pub fn get_icon(&mut self, ctx: &egui::Context, icon: &str, size: f32, color: egui::Color32) -> Option<egui::TextureHandle> {
+        // Checked first, so only render attempts count as cache misses
+        if self.failed.contains(icon) {
+            return None;
+        }
+        let resolved = self.resolve(icon);
+        let name = resolved.as_ref().map_or(icon, |(name, _)| name.as_str());
+        let cache_key = format!("{}_{}_{:?}", name, size as u32, color.to_array());
+        if let Some(texture) = self.cache.lock().unwrap().get(&cache_key) {
+            return Some(texture.clone());
+        }
+
+        let texture = resolved.and_then(|(name, svg)| SvgIcons::render_svg_to_texture(ctx, svg, size, color, &name));
+        match &texture {
+            Some(texture) => {
+                let [width, height] = texture.size();
+                self.cache.lock().unwrap().insert(cache_key, texture.clone(), width * height * 4);
+            }
+            None => {
+                // Log the failure but don't spam the console
+                if self.failed.insert(icon.to_string()) {
+                    eprintln!("Warning: Failed to load icon '{}'. Available icons: {:?}",
+                            icon, self.icons.names());
                 }
             }
         }
-        
-        self.cache.get(&cache_key)
+        texture
     }
-    
-    /// Render an icon in the UI with improved fallback
+
+    /// Clickable icon, falling back to text if it can't be rendered; `tooltip`
+    /// is shown on hover and next to the icon when text labels are turned on
     pub fn icon_button(&mut self, ui: &mut egui::Ui, ctx: &egui::Context, icon: &str, size: f32, color: egui::Color32, tooltip: &str) -> egui::Response {
-        if let Some(texture) = self.get_icon(ctx, icon, size, color) {
-            ui.image((texture.id(), egui::Vec2::splat(size))).on_hover_text(tooltip)
+        let button = match self.get_icon(ctx, icon, size, color) {
+            Some(texture) if self.show_text_labels => egui::Button::image_and_text((texture.id(), egui::Vec2::splat(size)), tooltip),
+            Some(texture) => egui::Button::image((texture.id(), egui::Vec2::splat(size))),
+            None => egui::Button::new(egui::RichText::new(self.icons.fallback_text(icon)).color(color)),
+        };
+        let response = ui.add(button);
+        response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Button, ui.is_enabled(), tooltip));
+        response.on_hover_text(tooltip)
+    }
+
+    /// Icon that conveys information: `label` is what screen readers announce,
+    /// and is shown next to the icon when text labels are turned on
+    pub fn labeled_icon(&mut self, ui: &mut egui::Ui, ctx: &egui::Context, icon: &str, size: f32, color: egui::Color32, label: &str) -> egui::Response {
+        let response = self.icon_label(ui, ctx, icon, size, color);
+        response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Image, true, label));
+        if self.show_text_labels {
+            response | ui.label(label)
         } else {
-            // Improved fallback with better visual representation
-            let fallback_text = match icon {
-                "device-floppy" => "💾",
-                "cloud" => "☁",
-                "download" => "⬇",
-                "check" => "✓",
-                "x" => "✗",
-                "alert-triangle" => "⚠",
-                "help" => "?",
-                "circle-check" => "✅",
-                "clock" => "🕐",
-                _ => &format!("[{}]", icon.chars().next().unwrap_or('?').to_uppercase()),
-            };
-            ui.colored_label(color, fallback_text).on_hover_text(tooltip)
+            response
         }
     }
-    
-    /// Simple icon label with improved fallback
+
+    /// Simple icon label, falling back to text if it can't be rendered
     pub fn icon_label(&mut self, ui: &mut egui::Ui, ctx: &egui::Context, icon: &str, size: f32, color: egui::Color32) -> egui::Response {
         if let Some(texture) = self.get_icon(ctx, icon, size, color) {
             ui.image((texture.id(), egui::Vec2::splat(size)))
         } else {
-            // Improved fallback with better visual representation
-            let fallback_text = match icon {
-                "device-floppy" => "💾",
-                "cloud" => "☁",
-                "download" => "⬇",
-                "check" => "✓",
-                "x" => "✗",
-                "alert-triangle" => "⚠",
-                "help" => "?",
-                "circle-check" => "✅",
-                "clock" => "🕐",
-                _ => &format!("[{}]", icon.chars().next().unwrap_or('?').to_uppercase()),
-            };
-            ui.colored_label(color, fallback_text)
+            ui.colored_label(color, self.icons.fallback_text(icon))
         }
     }
 }
@@ -224,30 +652,33 @@ impl IconRenderer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::TempDir;
 
     #[test]
     fn test_embedded_icons_available() {
         // Verify all expected icons are embedded
         let expected_icons = vec![
-            "alert-triangle", "check", "circle-check", "clock", 
-            "cloud", "device-floppy", "download", "help", "x"
+            "alert-triangle", "check", "circle-check", "clock",
+            "cloud", "device-floppy", "download", "help", "x",
+            "folder", "refresh", "rotate-clockwise", "zoom-in", "zoom-out",
+            "maximize", "player-play", "player-pause", "trash", "settings"
         ];
-        
+
         let available_icons = SvgIcons::get_available_icons();
-        
+
         for expected in &expected_icons {
-            assert!(available_icons.contains(expected), 
+            assert!(available_icons.contains(expected),
                 "Expected icon '{}' not found in embedded icons", expected);
         }
-        
-        assert_eq!(available_icons.len(), expected_icons.len(), 
+
+        assert_eq!(available_icons.len(), expected_icons.len(),
             "Number of available icons doesn't match expected");
     }
 
     #[test]
     fn test_icon_validation() {
         // Test that all embedded icons pass validation
-        assert!(SvgIcons::validate_all_icons().is_ok(), 
+        assert!(SvgIcons::validate_all_icons().is_ok(),
             "Icon validation failed");
     }
 
@@ -257,7 +688,7 @@ mod tests {
         for icon in EMBEDDED_ICONS {
             let content = SvgIcons::get_embedded_svg(icon.name);
             assert!(content.is_some(), "Failed to get content for icon '{}'", icon.name);
-            
+
             let svg_content = content.unwrap();
             assert!(!svg_content.is_empty(), "Icon '{}' has empty content", icon.name);
             assert!(svg_content.contains("<svg"), "Icon '{}' does not contain SVG markup", icon.name);
@@ -270,4 +701,84 @@ mod tests {
         let content = SvgIcons::get_embedded_svg("nonexistent-icon");
         assert!(content.is_none(), "Should return None for nonexistent icon");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_icon_set_builder_overrides_and_fallbacks() {
+        let custom = EmbeddedIcon { name: "check", content: "<svg xmlns=\"http://www.w3.org/2000/svg\"/>" };
+        let set = IconSet::builder()
+            .with_builtin_icons()
+            .with_icons(&[custom])
+            .fallback("star", "★")
+            .build();
+
+        assert_eq!(set.names().len(), EMBEDDED_ICONS.len());
+        assert_eq!(set.get("check"), Some(custom.content));
+        assert_eq!(set.fallback_text("check"), "✓");
+        assert_eq!(set.fallback_text("star"), "★");
+        assert_eq!(set.fallback_text("moon"), "[M]");
+        assert!(IconSet::default().is_empty());
+    }
+
+    #[test]
+    fn test_embed_icons_macro() {
+        let listed = crate::embed_icons!{ "tick" => "assets/check.svg" };
+        assert_eq!(listed.names(), vec!["tick"]);
+
+        let dir = crate::embed_icons!(dir "$CARGO_MANIFEST_DIR/assets/icons");
+        let mut names = dir.names();
+        names.sort();
+        assert_eq!(names, SvgIcons::get_available_icons());
+        assert!(dir.validate().is_ok());
+
+        let mut players = crate::embed_icons!{"assets/icons/player-*.svg"}.names();
+        players.sort();
+        assert_eq!(players, ["player-pause", "player-play"]);
+        let mut all = crate::embed_icons!{"assets/icons/*.svg"}.names();
+        all.sort();
+        assert_eq!(all, SvgIcons::get_available_icons());
+    }
+
+    #[test]
+    fn test_icon_cache_stats() {
+        let ctx = egui::Context::default();
+        let mut renderer = IconRenderer::new();
+        assert!(renderer.get_icon(&ctx, Icons::CHECK, 16.0, egui::Color32::WHITE).is_some());
+        assert!(renderer.get_icon(&ctx, Icons::CHECK, 16.0, egui::Color32::WHITE).is_some());
+        assert!(renderer.get_icon(&ctx, "missing", 16.0, egui::Color32::WHITE).is_none());
+
+        let stats = renderer.stats();
+        assert_eq!((stats.entries, stats.size_bytes), (1, 16 * 16 * 4));
+        assert_eq!((stats.counters.hits, stats.counters.misses), (1, 2));
+        assert_eq!(stats.failed, ["missing"]);
+
+        renderer.clear_cache();
+        let stats = renderer.stats();
+        assert_eq!(stats.entries, 0);
+        assert!(stats.failed.is_empty());
+    }
+
+    #[test]
+    fn test_icon_directory_overrides_and_variants() {
+        let dir = TempDir::new("icon_pack");
+        let svg = |id: &str| format!("<svg xmlns=\"http://www.w3.org/2000/svg\" id=\"{}\"/>", id);
+        std::fs::write(dir.join("check.svg"), svg("pack-check")).unwrap();
+        std::fs::write(dir.join("help.light.svg"), svg("pack-help-light")).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not an icon").unwrap();
+
+        let mut renderer = IconRenderer::new();
+        renderer.set_icon_directory(Some(IconDirectory::load(dir.to_path_buf()).unwrap()));
+        assert_eq!(renderer.icon_directory().map(IconDirectory::len), Some(2));
+        assert_eq!(renderer.resolve("check").unwrap().1, svg("pack-check"));
+        // The light variant only applies with a light theme
+        assert_eq!(renderer.resolve("help").unwrap().0, "help");
+        renderer.set_dark_mode(false);
+        assert_eq!(renderer.resolve("help").unwrap(), ("help.light".to_string(), svg("pack-help-light").as_str()));
+        assert!(renderer.resolve("missing").is_none());
+
+        let mut directory = IconDirectory::load(dir.to_path_buf()).unwrap();
+        assert_eq!(directory.reload_if_changed(), Ok(false));
+        std::fs::write(dir.join("star.svg"), svg("star")).unwrap();
+        assert_eq!(directory.reload_if_changed(), Ok(true));
+        assert!(directory.get("star").is_some());
+    }
+}
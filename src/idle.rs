@@ -0,0 +1,70 @@
+//! User idle detection for scheduling background work
+
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+
+#[derive(Debug, Clone)]
+pub struct IdleDetector {
+    last_input: Instant,
+    idle_after: Duration,
+}
+
+impl IdleDetector {
+    pub fn new(idle_after: Duration) -> Self {
+        Self {
+            last_input: Instant::now(),
+            idle_after,
+        }
+    }
+
+    pub fn set_idle_after(&mut self, idle_after: Duration) {
+        self.idle_after = idle_after;
+    }
+
+    pub fn record_input(&mut self, now: Instant) {
+        self.last_input = now;
+    }
+
+    pub fn is_idle_at(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.last_input) >= self.idle_after
+    }
+
+    /// Time remaining until the user counts as idle (zero if already idle)
+    pub fn time_until_idle(&self, now: Instant) -> Duration {
+        self.idle_after.saturating_sub(now.saturating_duration_since(self.last_input))
+    }
+
+    /// Record any keyboard, pointer or scroll input from this frame and return
+    /// whether the user is currently idle
+    pub fn observe(&mut self, ctx: &egui::Context) -> bool {
+        let now = Instant::now();
+        let had_input = ctx.input(|i| {
+            !i.events.is_empty() || i.pointer.is_moving() || i.pointer.any_down() || i.raw_scroll_delta != egui::Vec2::ZERO
+        });
+        if had_input {
+            self.record_input(now);
+        }
+        self.is_idle_at(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_after_configured_period() {
+        let start = Instant::now();
+        let mut detector = IdleDetector::new(Duration::from_secs(30));
+        detector.record_input(start);
+
+        assert!(!detector.is_idle_at(start + Duration::from_secs(10)));
+        assert_eq!(detector.time_until_idle(start + Duration::from_secs(10)), Duration::from_secs(20));
+        assert!(detector.is_idle_at(start + Duration::from_secs(30)));
+
+        // New input resets the timer immediately
+        detector.record_input(start + Duration::from_secs(31));
+        assert!(!detector.is_idle_at(start + Duration::from_secs(32)));
+    }
+}
@@ -1,12 +1,17 @@
 //! Image loading and processing functionality
 
-use std::path::PathBuf;
-use eframe::egui;
-use egui::{ColorImage, TextureHandle};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "gui")]
+use eframe::egui::{self, ColorImage, TextureHandle};
 use image::ImageReader;
 use resvg;
 use regex;
 
+#[cfg(feature = "gui")]
+use crate::egui_adapter;
+use crate::{archive, format_decoders, video};
+use crate::decoded_image::DecodedImage;
+use crate::duplicates::target_in;
 use crate::settings::ImageLoadingSettings;
 use crate::file_locality::FileInfo;
 use crate::benchmark::ImageCharacteristics;
@@ -23,20 +28,19 @@ pub fn should_skip_large_file(path: &PathBuf, settings: &ImageLoadingSettings, f
         }
     }
     
-    if let Some(max_mb) = settings.get_effective_max_file_size_mb() {
-        if let Ok(metadata) = std::fs::metadata(path) {
-            let size_mb = metadata.len() / (1024 * 1024);
-            if size_mb > max_mb as u64 {
-                let limit_source = if settings.max_file_size_mb.is_some() {
-                    "manual"
-                } else {
-                    "dynamic"
-                };
-                return Some(format!(
-                    "Skipped large file ({} MB > {} MB {} limit): {}",
-                    size_mb, max_mb, limit_source, path.to_string_lossy()
-                ));
-            }
+    if let Some(max_mb) = settings.get_effective_max_file_size_mb()
+        && let Ok(metadata) = std::fs::metadata(path) {
+        let size_mb = metadata.len() / (1024 * 1024);
+        if size_mb > max_mb as u64 {
+            let limit_source = if settings.max_file_size_mb.is_some() {
+                "manual"
+            } else {
+                "dynamic"
+            };
+            return Some(format!(
+                "Skipped large file ({} MB > {} MB {} limit): {}",
+                size_mb, max_mb, limit_source, path.to_string_lossy()
+            ));
         }
     }
     None
@@ -45,120 +49,354 @@ pub fn should_skip_large_file(path: &PathBuf, settings: &ImageLoadingSettings, f
 pub fn scale_image_if_needed(img: image::DynamicImage, settings: &ImageLoadingSettings) -> Result<image::DynamicImage, String> {
     // Only scale if auto_scale_large_images is enabled and the image is considered "large"
     let (width, height) = (img.width(), img.height());
-    
-    const LARGE_IMAGE_THRESHOLD: u32 = 8192; // Arbitrary threshold for large images
-    
-    if width <= LARGE_IMAGE_THRESHOLD && height <= LARGE_IMAGE_THRESHOLD {
+    let threshold = settings.large_image_max_dimension;
+
+    if width <= threshold && height <= threshold {
         return Ok(img);
     }
 
     if settings.skip_large_images {
         return Err(format!(
             "Image too large ({}x{} > {}x{} threshold)", 
-            width, height, LARGE_IMAGE_THRESHOLD, LARGE_IMAGE_THRESHOLD
+            width, height, threshold, threshold
         ));
     }
 
     if settings.auto_scale_large_images {
         // Calculate scale factor to fit within threshold
-        let scale_factor = (LARGE_IMAGE_THRESHOLD as f32 / width.max(height) as f32).min(1.0);
+        let scale_factor = (threshold as f32 / width.max(height) as f32).min(1.0);
         let new_width = (width as f32 * scale_factor) as u32;
         let new_height = (height as f32 * scale_factor) as u32;
 
-        Ok(img.resize(new_width, new_height, image::imageops::FilterType::Lanczos3))
+        Ok(img.resize(new_width, new_height, settings.resize_filter.filter_type()))
     } else {
         Err(format!(
             "Image too large ({}x{} > {}x{} threshold) and auto-scaling disabled", 
-            width, height, LARGE_IMAGE_THRESHOLD, LARGE_IMAGE_THRESHOLD
+            width, height, threshold, threshold
         ))
     }
 }
 
-pub fn recolor_svg_simple(svg_content: &str, settings: &ImageLoadingSettings) -> String {
+/// Properties whose values are colors
+const COLOR_PROPERTIES: [&str; 6] = ["fill", "stroke", "stop-color", "flood-color", "lighting-color", "color"];
+
+/// The recolored value of one color property, or `None` to leave it alone
+///
+/// Colors with a mapping get the mapping's target; the rest get the global
+/// target unless they're preserved. `none`, `url(#...)` references and fully
+/// transparent colors are never touched.
+fn recolor_value(value: &str, settings: &ImageLoadingSettings) -> Option<String> {
+    let hex = |[r, g, b]: [u8; 3], alpha: u8| match alpha {
+        255 => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        alpha => format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, alpha),
+    };
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("currentColor") {
+        return Some(hex(settings.svg_target_color, 255));
+    }
+    let color: svgtypes::Color = value.parse().ok()?;
+    let rgb = [color.red, color.green, color.blue];
+    if color.alpha == 0 {
+        return None;
+    }
+    if let Some(mapping) = settings.svg_color_mappings.iter().find(|mapping| mapping.from == rgb) {
+        return Some(hex(mapping.to, color.alpha));
+    }
+    if settings.svg_recolor_current_color_only || settings.svg_preserved_colors.contains(&rgb) {
+        return None;
+    }
+    Some(hex(settings.svg_target_color, color.alpha))
+}
+
+/// Recolor the color declarations in CSS text: a `style` attribute or the
+/// contents of a `<style>` element
+fn recolor_declarations(css: &str, settings: &ImageLoadingSettings) -> Option<String> {
+    let declaration = regex::Regex::new(r"(?i)(^|[;{\s])(fill|stroke|stop-color|flood-color|lighting-color|color)(\s*:\s*)([^;}!]*)").unwrap();
+    let mut changed = false;
+    let result = declaration.replace_all(css, |caps: &regex::Captures| match recolor_value(&caps[4], settings) {
+        Some(color) => {
+            changed = true;
+            // Keep the whitespace before `;` or `}`
+            let trailing = &caps[4][caps[4].trim_end().len()..];
+            format!("{}{}{}{}{}", &caps[1], &caps[2], &caps[3], color, trailing)
+        }
+        None => caps[0].to_string(),
+    });
+    changed.then(|| result.into_owned())
+}
+
+/// Replace the colors of an SVG with the target color
+///
+/// The markup is parsed with the same XML parser usvg uses and every fill,
+/// stroke, gradient stop, filter color and `color` property is visited:
+/// presentation attributes, inline `style` attributes and `<style>` sheets.
+/// Only the color values themselves are rewritten, so everything else
+/// (comments, URLs, formatting) is kept byte for byte. Markup that doesn't
+/// parse is returned unchanged for usvg to report.
+pub fn recolor_svg(svg_content: &str, settings: &ImageLoadingSettings) -> String {
     if !settings.svg_recolor_enabled {
         return svg_content.to_string();
     }
+    let options = resvg::usvg::roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() };
+    let Ok(document) = resvg::usvg::roxmltree::Document::parse_with_options(svg_content, options) else {
+        return svg_content.to_string();
+    };
 
-    let target_hex = format!(
-        "#{:02x}{:02x}{:02x}",
-        settings.svg_target_color[0],
-        settings.svg_target_color[1],
-        settings.svg_target_color[2]
-    );
-
-    println!("SVG Recoloring enabled! Target color: {}", target_hex);
-    println!("Original SVG preview: {}", &svg_content[..std::cmp::min(200, svg_content.len())]);
+    let mut edits: Vec<(std::ops::Range<usize>, String)> = Vec::new();
+    for node in document.descendants().filter(|node| node.is_element()) {
+        for attribute in node.attributes().filter(|attribute| attribute.namespace().is_none()) {
+            let range = attribute.range_value();
+            let replacement = if COLOR_PROPERTIES.contains(&attribute.name()) {
+                recolor_value(&svg_content[range.clone()], settings)
+            } else if attribute.name() == "style" {
+                recolor_declarations(&svg_content[range.clone()], settings)
+            } else {
+                None
+            };
+            if let Some(replacement) = replacement {
+                edits.push((range, replacement));
+            }
+        }
+        if node.tag_name().name() == "style" {
+            for text in node.children().filter(|child| child.is_text()) {
+                let range = text.range();
+                if let Some(replacement) = recolor_declarations(&svg_content[range.clone()], settings) {
+                    edits.push((range, replacement));
+                }
+            }
+        }
+    }
 
     let mut result = svg_content.to_string();
-    let mut changes_made = 0;
-    
-    if result.contains("currentColor") {
-        result = result.replace("currentColor", &target_hex);
-        changes_made += result.matches(&target_hex).count();
-        println!("Replaced currentColor with {}, {} instances", target_hex, changes_made);
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    for (range, replacement) in edits {
+        result.replace_range(range, &replacement);
     }
+    result
+}
+
+#[cfg(feature = "gui")]
+pub fn load_svg_image(path: &Path, settings: &ImageLoadingSettings, ctx: &egui::Context, force_load: bool) -> Result<TextureHandle, String> {
+    let image = decode_svg(path, settings, force_load)?;
     
-    // Match case insensitive fill colors, allowing for hex codes, named colors, and "none"
-    let fill_regex = regex::Regex::new(r#"(?i)fill=(["'])(#[0-9a-f]{6}|#[0-9a-f]{3}|black|white|red|green|blue|yellow|cyan|magenta|purple|orange|brown|pink|gray|grey)\1"#).unwrap();
-    let before_count = result.len();
-    result = fill_regex.replace_all(&result, &format!(r#"fill="{}""#, target_hex)).to_string();
-    if result.len() != before_count {
-        changes_made += 1;
-        println!("Replaced fill colors");
-    }
-        
-    // Match case insensitive stroke colors, allowing for hex codes, named colors, and "none"
-    let stroke_regex = regex::Regex::new(r#"(?i)stroke=(["'])(#[0-9a-f]{6}|#[0-9a-f]{3}|black|white|red|green|blue|yellow|cyan|magenta|purple|orange|brown|pink|gray|grey)\1"#).unwrap();
-    let before_count = result.len();
-    result = stroke_regex.replace_all(&result, &format!(r#"stroke="{}""#, target_hex)).to_string();
-    if result.len() != before_count {
-        changes_made += 1;
-        println!("Replaced stroke colors");
-    }
-
-    // Match case insensitive style attributes that contain fill or stroke colors 
-    let style_regex = regex::Regex::new(r#"(?i)style="[^"]*(?:fill|stroke):\s*(#[0-9a-f]{6}|#[0-9a-f]{3}|black|white|red|green|blue|yellow|cyan|magenta|currentColor)[^"]*""#).unwrap();
-    let before_count = result.len();
-    result = style_regex.replace_all(&result, &format!(r#"style="fill: {}; stroke: {};""#, target_hex, target_hex)).to_string();
-    if result.len() != before_count {
-        changes_made += 1;
-        println!("Replaced CSS style colors");
-    }
-
-    println!("Total changes made: {}", changes_made);
-    if changes_made > 0 {
-        println!("Modified SVG preview: {}", &result[..std::cmp::min(200, result.len())]);
+    let texture_name = format!("svg_{}", path.file_name().unwrap_or_default().to_string_lossy());
+    let recolor_suffix = if settings.svg_recolor_enabled { "_recolored" } else { "" };
+    
+    Ok(egui_adapter::load_texture(ctx, format!("{}{}", texture_name, recolor_suffix), &image))
+}
+
+/// Limits that keep broken or malicious SVGs from hanging the viewer
+pub const MAX_SVG_ELEMENTS: usize = 200_000;
+pub const MAX_SVG_DEPTH: usize = 256;
+/// Largest width or height, in user units
+pub const MAX_SVG_DIMENSION: f32 = 1_000_000.0;
+pub const SVG_PARSE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+pub const SVG_RENDER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Check the element count and nesting depth before handing markup to usvg
+fn check_svg_limits(svg_content: &str) -> Result<(), String> {
+    let options = resvg::usvg::roxmltree::ParsingOptions {
+        allow_dtd: true,
+        nodes_limit: (MAX_SVG_ELEMENTS * 4) as u32,
+    };
+    let document = resvg::usvg::roxmltree::Document::parse_with_options(svg_content, options)
+        .map_err(|e| format!("Failed to parse SVG: {}", e))?;
+    // Nodes come in document order, so a parent's depth is known before its children's
+    let mut depths = vec![0usize; document.descendants().count()];
+    let mut elements = 0;
+    for node in document.descendants().filter(|node| node.is_element()) {
+        elements += 1;
+        if elements > MAX_SVG_ELEMENTS {
+            return Err(format!("SVG has more than {} elements", MAX_SVG_ELEMENTS));
+        }
+        let depth = node.parent().map_or(0, |parent| depths[parent.id().get_usize()]) + 1;
+        if depth > MAX_SVG_DEPTH {
+            return Err(format!("SVG nests elements more than {} levels deep", MAX_SVG_DEPTH));
+        }
+        depths[node.id().get_usize()] = depth;
     }
+    Ok(())
+}
 
-    result
+/// Ends the error of work that [`with_timeout`] gave up on
+const TIMEOUT_ERROR: &str = "; the file may be broken or malicious";
+
+/// Most threads [`with_timeout`] leaves running at once; more work is refused until they end
+const MAX_ABANDONED_THREADS: usize = 4;
+
+/// Threads [`with_timeout`] gave up on that are still running
+static ABANDONED_THREADS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// Run `work` on its own thread, giving up after `timeout`. A runaway
+/// thread is left to finish in the background, but the caller isn't blocked.
+fn with_timeout<T: Send + 'static>(what: &str, timeout: std::time::Duration, work: impl FnOnce() -> Result<T, String> + Send + 'static) -> Result<T, String> {
+    use std::sync::atomic::{AtomicU8, Ordering};
+    const RUNNING: u8 = 0;
+    const DONE: u8 = 1;
+    const ABANDONED: u8 = 2;
+
+    if ABANDONED_THREADS.load(Ordering::SeqCst) >= MAX_ABANDONED_THREADS {
+        return Err(format!("{} was refused while earlier files are still being processed{}", what, TIMEOUT_ERROR));
+    }
+    let state = std::sync::Arc::new(AtomicU8::new(RUNNING));
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let thread_state = std::sync::Arc::clone(&state);
+    std::thread::spawn(move || {
+        let _ = sender.send(work());
+        if thread_state.swap(DONE, Ordering::SeqCst) == ABANDONED {
+            ABANDONED_THREADS.fetch_sub(1, Ordering::SeqCst);
+        }
+    });
+    receiver.recv_timeout(timeout).unwrap_or_else(|_| {
+        ABANDONED_THREADS.fetch_add(1, Ordering::SeqCst);
+        // The work may have finished just now, leaving nothing behind
+        if state.compare_exchange(RUNNING, ABANDONED, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            ABANDONED_THREADS.fetch_sub(1, Ordering::SeqCst);
+        }
+        Err(format!("{} took longer than {} seconds{}", what, timeout.as_secs(), TIMEOUT_ERROR))
+    })
+}
+
+/// Whether `error` is from parsing or rendering that took too long, which
+/// trying the file again would only repeat, leaving another thread behind
+#[cfg(feature = "gui")]
+pub(crate) fn timed_out(error: &str) -> bool {
+    error.ends_with(TIMEOUT_ERROR)
+}
+
+/// The system's fonts, for text in SVGs, loaded on first use
+fn system_fonts() -> std::sync::Arc<resvg::usvg::fontdb::Database> {
+    static FONTS: std::sync::OnceLock<std::sync::Arc<resvg::usvg::fontdb::Database>> = std::sync::OnceLock::new();
+    std::sync::Arc::clone(FONTS.get_or_init(|| {
+        let mut fontdb = resvg::usvg::fontdb::Database::new();
+        fontdb.load_system_fonts();
+        std::sync::Arc::new(fontdb)
+    }))
+}
+
+/// Parse SVG markup with recoloring applied and system fonts available for text
+///
+/// External files referenced by `<image>` elements aren't loaded; embedded
+/// `data:` images still are.
+fn parse_svg(svg_content: &str, settings: &ImageLoadingSettings) -> Result<resvg::usvg::Tree, String> {
+    check_svg_limits(svg_content)?;
+    // Apply recoloring if enabled
+    let processed_svg = recolor_svg(svg_content, settings);
+
+    let tree = with_timeout("Parsing the SVG", SVG_PARSE_TIMEOUT, move || {
+        let options = resvg::usvg::Options {
+            fontdb: system_fonts(),
+            image_href_resolver: resvg::usvg::ImageHrefResolver {
+                resolve_data: resvg::usvg::ImageHrefResolver::default_data_resolver(),
+                resolve_string: Box::new(|_, _| None),
+            },
+            ..Default::default()
+        };
+
+        resvg::usvg::Tree::from_data(processed_svg.as_bytes(), &options)
+            .map_err(|e| format!("Failed to parse SVG: {}", e))
+    })?;
+    let size = tree.size();
+    if size.width() > MAX_SVG_DIMENSION || size.height() > MAX_SVG_DIMENSION {
+        return Err(format!(
+            "SVG is too large ({}x{}, the limit is {} per side)",
+            size.width(), size.height(), MAX_SVG_DIMENSION
+        ));
+    }
+    Ok(tree)
+}
+
+/// Render `tree` into a new `width` x `height` pixmap, giving up after
+/// `SVG_RENDER_TIMEOUT`
+fn render_svg_tree(tree: resvg::usvg::Tree, transform: resvg::tiny_skia::Transform, width: u32, height: u32) -> Result<resvg::tiny_skia::Pixmap, String> {
+    with_timeout("Rendering the SVG", SVG_RENDER_TIMEOUT, move || {
+        let mut pixmap = resvg::tiny_skia::Pixmap::new(width, height)
+            .ok_or("Failed to create pixmap")?;
+        resvg::render(&tree, transform, &mut pixmap.as_mut());
+        Ok(pixmap)
+    })
+}
+
+/// Output resolution and background for exporting an SVG as PNG
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SvgExportOptions {
+    pub width: u32,
+    pub height: u32,
+    /// Fill color behind the drawing (RGBA), or transparent if `None`
+    pub background: Option<[u8; 4]>,
+}
+
+/// Largest export dimension, to keep the pixmap allocation reasonable
+pub const MAX_SVG_EXPORT_SIZE: u32 = 16384;
+
+/// The markup of an SVG file, or of an SVG inside an archive
+pub fn read_svg_source(path: &Path) -> Result<String, String> {
+    match archive::split_path(path) {
+        Some((archive, entry)) => String::from_utf8(archive::read_entry(archive, &entry, None)?)
+            .map_err(|e| format!("Failed to read SVG file: {}", e)),
+        None => std::fs::read_to_string(path).map_err(|e| format!("Failed to read SVG file: {}", e)),
+    }
 }
 
-pub fn load_svg_image(path: &PathBuf, settings: &ImageLoadingSettings, ctx: &egui::Context, force_load: bool) -> Result<TextureHandle, String> {
+/// Width and height the SVG declares for itself
+pub fn svg_intrinsic_size(path: &Path, settings: &ImageLoadingSettings) -> Result<(f32, f32), String> {
+    let svg_content = read_svg_source(path)?;
+    let size = parse_svg(&svg_content, settings)?.size();
+    Ok((size.width(), size.height()))
+}
+
+/// Rasterize an SVG at exactly the requested size (stretching if the aspect
+/// ratio differs) and write it as a PNG to `output`, or to a numbered name
+/// next to it if that file exists; returns where it was written
+pub fn export_svg_as_png(path: &Path, settings: &ImageLoadingSettings, options: &SvgExportOptions, output: &std::path::Path) -> Result<PathBuf, String> {
+    if options.width == 0 || options.height == 0 || options.width > MAX_SVG_EXPORT_SIZE || options.height > MAX_SVG_EXPORT_SIZE {
+        return Err(format!(
+            "Export size must be between 1 and {} pixels per side (got {}x{})",
+            MAX_SVG_EXPORT_SIZE, options.width, options.height
+        ));
+    }
+
+    let svg_content = read_svg_source(path)?;
+    let tree = parse_svg(&svg_content, settings)?;
+
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(options.width, options.height)
+        .ok_or("Failed to create pixmap")?;
+    if let Some([r, g, b, a]) = options.background {
+        pixmap.fill(resvg::tiny_skia::Color::from_rgba8(r, g, b, a));
+    }
+
+    let size = tree.size();
+    let transform = resvg::tiny_skia::Transform::from_scale(
+        options.width as f32 / size.width(),
+        options.height as f32 / size.height(),
+    );
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    let output = target_in(output, output.parent().unwrap_or(Path::new("")), Path::exists)?;
+    pixmap.save_png(&output)
+        .map_err(|e| format!("Failed to write PNG: {}", e))?;
+    Ok(output)
+}
+
+/// Rasterize an SVG file (with recoloring applied) into CPU-side pixels
+#[cfg(feature = "gui")]
+pub fn decode_svg_image(path: &Path, settings: &ImageLoadingSettings, force_load: bool) -> Result<ColorImage, String> {
+    decode_svg(path, settings, force_load).map(|image| egui_adapter::to_color_image(&image))
+}
+
+/// Rasterize an SVG file (with recoloring applied) at its own size, scaled
+/// down if it is very large
+pub fn decode_svg(path: &Path, settings: &ImageLoadingSettings, force_load: bool) -> Result<DecodedImage, String> {
     // Check file locality status first to avoid triggering downloads (unless forced)
     if !force_load {
-        let file_info = FileInfo::new(path.clone());
+        let file_info = FileInfo::new(archive::backing_file(path).to_path_buf());
         if file_info.will_trigger_download() {
             return Err("Cannot load on-demand file - would trigger download".to_string());
         }
     }
     
-    let svg_content = std::fs::read_to_string(path)
-        .map_err(|e| format!("Failed to read SVG file: {}", e))?;
-
-    // Apply recoloring if enabled
-    let processed_svg = recolor_svg_simple(&svg_content, settings);
-    let svg_bytes = processed_svg.as_bytes();
-    
-    let mut fontdb = resvg::usvg::fontdb::Database::new();
-    fontdb.load_system_fonts();
-    
-    let options = resvg::usvg::Options {
-        fontdb: std::sync::Arc::new(fontdb),
-        ..Default::default()
-    };
-    
-    let tree = resvg::usvg::Tree::from_data(svg_bytes, &options)
-        .map_err(|e| format!("Failed to parse SVG: {}", e))?;
+    let svg_content = read_svg_source(path)?;
+    let tree = parse_svg(&svg_content, settings)?;
     
     let bbox = tree.size();
     let width = bbox.width() as u32;
@@ -177,87 +415,421 @@ pub fn load_svg_image(path: &PathBuf, settings: &ImageLoadingSettings, ctx: &egu
         (width, height)
     };
     
-    let mut pixmap = resvg::tiny_skia::Pixmap::new(scaled_width, scaled_height)
-        .ok_or("Failed to create pixmap")?;
-    
     let scale_x = scaled_width as f32 / width as f32;
     let scale_y = scaled_height as f32 / height as f32;
     let transform = resvg::tiny_skia::Transform::from_scale(scale_x, scale_y);
     
-    resvg::render(&tree, transform, &mut pixmap.as_mut());
-    
+    let pixmap = render_svg_tree(tree, transform, scaled_width, scaled_height)?;
     // Convert to RGBA
-    let rgba_data: Vec<u8> = pixmap.data()
+    let pixels = pixmap.data()
         .chunks_exact(4)
         .flat_map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]]) // BGRA to RGBA
         .collect();
-    
-    let color_image = ColorImage::from_rgba_unmultiplied(
-        [scaled_width as usize, scaled_height as usize],
-        &rgba_data,
+    Ok(DecodedImage { width: pixmap.width(), height: pixmap.height(), pixels })
+}
+
+fn pixmap_to_rgba(pixmap: &resvg::tiny_skia::Pixmap) -> image::RgbaImage {
+    // tiny-skia pixels are premultiplied
+    let pixels = pixmap.pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    image::RgbaImage::from_raw(pixmap.width(), pixmap.height(), pixels)
+        .expect("pixel buffer matches the dimensions")
+}
+
+/// Longest side of the recolor preview thumbnails in the settings window
+pub const SVG_PREVIEW_SIZE: u32 = 96;
+
+/// Rasterize SVG markup to fit within `max_size` pixels, recolored per `settings`
+pub fn render_svg_preview(svg_content: &str, settings: &ImageLoadingSettings, max_size: u32) -> Result<image::RgbaImage, String> {
+    let tree = parse_svg(svg_content, settings)?;
+    let size = tree.size();
+    let scale = max_size as f32 / size.width().max(size.height());
+    let width = ((size.width() * scale).round() as u32).max(1);
+    let height = ((size.height() * scale).round() as u32).max(1);
+    let pixmap = render_svg_tree(tree, resvg::tiny_skia::Transform::from_scale(scale, scale), width, height)?;
+    Ok(pixmap_to_rgba(&pixmap))
+}
+
+/// Rasterize the part of an SVG within `uv` (normalized to its intrinsic
+/// size) at `width` x `height` pixels, for showing it sharp when zoomed in
+pub fn render_svg_region(svg_content: &str, settings: &ImageLoadingSettings, uv: emath::Rect, [width, height]: [u32; 2]) -> Result<image::RgbaImage, String> {
+    let tree = parse_svg(svg_content, settings)?;
+    let size = tree.size();
+    let scale_x = width as f32 / (uv.width() * size.width());
+    let scale_y = height as f32 / (uv.height() * size.height());
+    let transform = resvg::tiny_skia::Transform::from_row(
+        scale_x, 0.0, 0.0, scale_y,
+        -uv.min.x * size.width() * scale_x,
+        -uv.min.y * size.height() * scale_y,
     );
+    let pixmap = render_svg_tree(tree, transform, width, height)?;
+    Ok(pixmap_to_rgba(&pixmap))
+}
+
+#[cfg(feature = "gui")]
+pub fn load_raster_image(path: &Path, settings: &ImageLoadingSettings, ctx: &egui::Context, force_load: bool) -> Result<TextureHandle, String> {
+    let image = decode_raster(path, settings, force_load)?;
     
-    let texture_name = format!("svg_{}", path.file_name().unwrap_or_default().to_string_lossy());
-    let recolor_suffix = if settings.svg_recolor_enabled { "_recolored" } else { "" };
+    let texture_name = format!("image_{}", path.file_name().unwrap_or_default().to_string_lossy());
     
-    Ok(ctx.load_texture(
-        format!("{}{}", texture_name, recolor_suffix),
-        color_image,
-        Default::default(),
-    ))
+    Ok(egui_adapter::load_texture(ctx, texture_name, &image))
 }
 
-pub fn load_raster_image(path: &PathBuf, settings: &ImageLoadingSettings, ctx: &egui::Context, force_load: bool) -> Result<TextureHandle, String> {
+/// Decode (and scale if needed) a raster image into CPU-side pixels
+#[cfg(feature = "gui")]
+pub fn decode_raster_image(path: &Path, settings: &ImageLoadingSettings, force_load: bool) -> Result<ColorImage, String> {
+    decode_raster(path, settings, force_load).map(|image| egui_adapter::to_color_image(&image))
+}
+
+/// Decode a raster image, an image inside an archive or a video's poster
+/// frame, scaled per `settings`
+pub fn decode_raster(path: &Path, settings: &ImageLoadingSettings, force_load: bool) -> Result<DecodedImage, String> {
     // Check file locality status first to avoid triggering downloads (unless forced)
     if !force_load {
-        let file_info = FileInfo::new(path.clone());
+        let file_info = FileInfo::new(archive::backing_file(path).to_path_buf());
         if file_info.will_trigger_download() {
             return Err("Cannot load on-demand file - would trigger download".to_string());
         }
     }
     
-    let img = ImageReader::open(path)
-        .map_err(|e| format!("Failed to open image: {}", e))?
-        .decode()
-        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    let img = match archive::split_path(path) {
+        None if video::is_video(path) => video::poster_frame(path)?,
+        // Images inside archives are decoded from memory, never extracted
+        Some((archive, entry)) => {
+            let bytes = archive::read_entry(archive, &entry, None)?;
+            match format_decoders::decode_bytes(Path::new(&entry), &bytes) {
+                Some(decoded) => image::DynamicImage::ImageRgba8(decoded?.into_rgba()),
+                None => ImageReader::new(std::io::Cursor::new(bytes))
+                    .with_guessed_format()
+                    .map_err(|e| format!("Failed to open image: {}", e))?
+                    .decode()
+                    .map_err(|e| format!("Failed to decode image: {}", e))?,
+            }
+        }
+        None => open_image(path)?,
+    };
     
     // Apply scaling if needed
     let scaled_img = scale_image_if_needed(img, settings)?;
-    
-    let size = [scaled_img.width() as _, scaled_img.height() as _];
-    let rgba = scaled_img.to_rgba8();
-    let pixels = rgba.as_flat_samples();
-    let color_image = ColorImage::from_rgba_unmultiplied(size, pixels.as_slice());
-    
-    let texture_name = format!("image_{}", path.file_name().unwrap_or_default().to_string_lossy());
-    
-    Ok(ctx.load_texture(
-        texture_name,
-        color_image,
-        Default::default(),
-    ))
+    Ok(DecodedImage::from_rgba(scaled_img.into_rgba8()))
+}
+
+/// Decode a raster file at full size, with a registered
+/// [`FormatDecoder`](crate::format_decoders::FormatDecoder) if one handles it
+pub fn open_image(path: &Path) -> Result<image::DynamicImage, String> {
+    if let Some(decoded) = format_decoders::decode_file(path) {
+        return Ok(image::DynamicImage::ImageRgba8(decoded?.into_rgba()));
+    }
+    ImageReader::open(path)
+        .map_err(|e| format!("Failed to open image: {}", e))?
+        .decode()
+        .map_err(|e| format!("Failed to decode image: {}", e))
+}
+
+/// Decode `input`, scale it down to fit `max_size` if given, and write it in
+/// the format named by the extension of `output`; returns the written size
+pub fn convert_image(input: &Path, output: &Path, max_size: Option<u32>) -> Result<(u32, u32), String> {
+    let settings = ImageLoadingSettings {
+        large_image_max_dimension: max_size.unwrap_or(u32::MAX),
+        max_file_size_mb: None,
+        ..Default::default()
+    }.auto_scale_large_images(true);
+    // Files asked for by name are wanted even if that downloads them
+    let mut rgba = decode(input, &settings, true)?.into_rgba();
+    // SVGs are rasterized at their own size, so they are scaled here
+    if let Some(max_size) = max_size && (rgba.width() > max_size || rgba.height() > max_size) {
+        rgba = image::DynamicImage::ImageRgba8(rgba).thumbnail(max_size, max_size).to_rgba8();
+    }
+    let format = image::ImageFormat::from_path(output)
+        .map_err(|_| format!("Unknown output format for {}", output.display()))?;
+    // Formats without transparency reject RGBA pixels
+    let result = if matches!(format, image::ImageFormat::Jpeg | image::ImageFormat::Pnm) {
+        image::DynamicImage::ImageRgba8(rgba.clone()).to_rgb8().save_with_format(output, format)
+    } else {
+        rgba.save_with_format(output, format)
+    };
+    result.map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+    Ok(rgba.dimensions())
+}
+
+/// Decode any supported image, rasterizing SVGs
+pub fn decode(path: &Path, settings: &ImageLoadingSettings, force_load: bool) -> Result<DecodedImage, String> {
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")) {
+        decode_svg(path, settings, force_load)
+    } else {
+        decode_raster(path, settings, force_load)
+    }
+}
+
+/// The image turned a quarter turn clockwise
+#[cfg(feature = "gui")]
+pub fn rotate_clockwise(image: &ColorImage) -> ColorImage {
+    let [width, height] = image.size;
+    let mut pixels = vec![egui::Color32::TRANSPARENT; image.pixels.len()];
+    for (i, pixel) in image.pixels.iter().enumerate() {
+        let (x, y) = (i % width, i / width);
+        pixels[x * height + (height - 1 - y)] = *pixel;
+    }
+    ColorImage { size: [height, width], pixels }
+}
+
+/// The part of the image inside `region`, which must lie within it
+#[cfg(feature = "gui")]
+pub fn crop(image: &ColorImage, region: &crate::color_stats::PixelRect) -> ColorImage {
+    let width = image.size[0];
+    let pixels = (region.min_y..region.max_y)
+        .flat_map(|y| image.pixels[y * width + region.min_x..y * width + region.max_x].iter().copied())
+        .collect();
+    ColorImage { size: [region.width(), region.height()], pixels }
 }
 
 pub fn estimate_image_render_time(path: &PathBuf, performance_profile: &crate::benchmark::PerformanceProfile) -> Option<f64> {
-    // For on-demand files, skip dimension detection to avoid triggering downloads
+    // For on-demand files, skip dimension detection to avoid triggering downloads,
+    // and for files on network shares to avoid waiting on the network
     let file_info = FileInfo::new(path.clone());
-    if file_info.will_trigger_download() {
+    if file_info.is_slow_to_read() {
         return None; // Cannot safely estimate without triggering download
     }
     
     // Try to get image dimensions without fully loading (safe for local files only)
-    if let Ok(reader) = ImageReader::open(path) {
-        if let Ok((width, height)) = reader.into_dimensions() {
-            let format = path.extension()
-                .and_then(|s| s.to_str())
-                .unwrap_or("unknown")
-                .to_lowercase();
-            
-            let characteristics = ImageCharacteristics::new(path, width, height, format);
-            let estimated_time = performance_profile.estimate_render_time(&characteristics);
-            
-            return Some(estimated_time);
-        }
+    if let Ok(reader) = ImageReader::open(path)
+        && let Ok((width, height)) = reader.into_dimensions() {
+        return Some(estimate_for_dimensions(path, width, height, performance_profile));
     }
     None
 }
+
+fn estimate_for_dimensions(path: &PathBuf, width: u32, height: u32, performance_profile: &crate::benchmark::PerformanceProfile) -> f64 {
+    let format = path.extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown")
+        .to_lowercase();
+    let characteristics = ImageCharacteristics::new(path, width, height, format);
+    performance_profile.estimate_render_time(&characteristics)
+}
+
+/// Dimensions read from a file's header and the render time estimated from
+/// them, kept in its [`FileInfo`] so the header isn't read again
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderEstimate {
+    pub width: u32,
+    pub height: u32,
+    /// Modification time of the file when its header was read
+    pub modified: Option<std::time::SystemTime>,
+    /// Benchmark run the estimate was made with
+    pub benchmarked: Option<std::time::Instant>,
+    pub estimated_ms: f64,
+}
+
+/// Like [`estimate_image_render_time`], but reuses the estimate cached in
+/// `file_info` until the file is modified or the benchmark is run again
+pub fn cached_render_time(file_info: &mut FileInfo, performance_profile: &crate::benchmark::PerformanceProfile) -> Option<f64> {
+    if file_info.is_slow_to_read() {
+        return None;
+    }
+    let modified = std::fs::metadata(&file_info.path).and_then(|m| m.modified()).ok();
+    let dimensions = match &file_info.render_estimate {
+        Some(cached) if cached.modified == modified => {
+            if cached.benchmarked == performance_profile.last_benchmark_time {
+                return Some(cached.estimated_ms);
+            }
+            (cached.width, cached.height)
+        }
+        _ => ImageReader::open(&file_info.path).ok()?.into_dimensions().ok()?,
+    };
+    let (width, height) = dimensions;
+    let estimated_ms = estimate_for_dimensions(&file_info.path, width, height, performance_profile);
+    file_info.render_estimate = Some(RenderEstimate {
+        width,
+        height,
+        modified,
+        benchmarked: performance_profile.last_benchmark_time,
+        estimated_ms,
+    });
+    Some(estimated_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::SvgColorMapping;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_cached_render_time() {
+        let dir = TempDir::new("render_estimate");
+        let path = dir.join("estimate.png");
+        image::RgbaImage::new(40, 30).save(&path).unwrap();
+        let mut file_info = FileInfo::new(path.clone());
+        let profile = crate::benchmark::PerformanceProfile::default();
+
+        let estimate = cached_render_time(&mut file_info, &profile);
+        let cached = file_info.render_estimate.clone().unwrap();
+        assert_eq!((cached.width, cached.height), (40, 30));
+        assert_eq!(estimate, Some(cached.estimated_ms));
+
+        // Served from the cache while the file is unchanged, even if it can't be read
+        file_info.render_estimate.as_mut().unwrap().width = 4000;
+        cached_render_time(&mut file_info, &profile);
+        assert_eq!(file_info.render_estimate.as_ref().unwrap().width, 4000);
+
+        // A different modification time means the header is read again
+        file_info.render_estimate.as_mut().unwrap().modified = Some(std::time::SystemTime::UNIX_EPOCH);
+        cached_render_time(&mut file_info, &profile);
+        assert_eq!(file_info.render_estimate.as_ref().unwrap().width, 40);
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_rotate_clockwise() {
+        use egui::Color32;
+        // 2x1: red, green
+        let image = ColorImage { size: [2, 1], pixels: vec![Color32::RED, Color32::GREEN] };
+        let rotated = rotate_clockwise(&image);
+        assert_eq!(rotated.size, [1, 2]);
+        assert_eq!(rotated.pixels, vec![Color32::RED, Color32::GREEN]);
+        let full_turn = (0..4).fold(image.clone(), |image, _| rotate_clockwise(&image));
+        assert_eq!(full_turn, image);
+
+        // 2x2: the top-left corner moves to the top-right
+        let image = ColorImage { size: [2, 2], pixels: vec![Color32::RED, Color32::GREEN, Color32::BLUE, Color32::WHITE] };
+        assert_eq!(rotate_clockwise(&image).pixels, vec![Color32::BLUE, Color32::RED, Color32::WHITE, Color32::GREEN]);
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_crop() {
+        use egui::Color32;
+        let image = ColorImage { size: [2, 2], pixels: vec![Color32::RED, Color32::GREEN, Color32::BLUE, Color32::WHITE] };
+        let region = crate::color_stats::PixelRect { min_x: 1, min_y: 0, max_x: 2, max_y: 2 };
+        assert_eq!(crop(&image, &region), ColorImage { size: [1, 2], pixels: vec![Color32::GREEN, Color32::WHITE] });
+    }
+
+    #[test]
+    fn test_export_svg_at_requested_size() {
+        let source = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/check.svg"));
+        let dir = TempDir::new("svg_export");
+        let output = dir.join("export.png");
+        let settings = ImageLoadingSettings::default();
+        let options = SvgExportOptions { width: 300, height: 150, background: Some([255, 0, 0, 255]) };
+
+        assert_eq!(export_svg_as_png(&source, &settings, &options, &output).unwrap(), output);
+        let exported = image::open(&output).unwrap().to_rgba8();
+        assert_eq!(exported.dimensions(), (300, 150));
+        assert_eq!(exported.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        let again = export_svg_as_png(&source, &settings, &options, &output).unwrap();
+        assert_eq!(again, dir.join("export (2).png"));
+        assert!(output.exists());
+
+        let too_big = SvgExportOptions { width: MAX_SVG_EXPORT_SIZE + 1, ..options };
+        assert!(export_svg_as_png(&source, &settings, &too_big, &output).is_err());
+    }
+
+    #[test]
+    fn test_recolor_svg_walks_styles_and_gradients() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg">
+<style>.a { fill: rgb(255, 0, 0); stroke:white } .b{color:Navy}</style>
+<linearGradient id="g"><stop offset="0" stop-color="#00ff00"/><stop style="stop-color: transparent"/></linearGradient>
+<rect class="a" fill="url(#g)" stroke="none"/>
+<path style="fill:#000;opacity:0.5" stroke="currentColor" fill-opacity="1"/>
+</svg>"##;
+        let mut settings = ImageLoadingSettings { svg_recolor_enabled: true, svg_target_color: [0x33, 0x66, 0x99], ..Default::default() };
+        let recolored = recolor_svg(svg, &settings);
+        assert!(recolored.contains(".a { fill: #336699; stroke:#336699 } .b{color:#336699}"));
+        assert!(recolored.contains(r##"stop-color="#336699""##));
+        assert!(recolored.contains("stop-color: transparent"));
+        assert!(recolored.contains(r##"fill="url(#g)" stroke="none""##));
+        assert!(recolored.contains(r##"style="fill:#336699;opacity:0.5" stroke="#336699""##));
+
+        settings.svg_preserved_colors = vec![[255, 255, 255]];
+        assert!(recolor_svg(svg, &settings).contains("stroke:white"));
+
+        settings.svg_recolor_current_color_only = true;
+        let recolored = recolor_svg(svg, &settings);
+        assert!(recolored.contains(r##"style="fill:#000;opacity:0.5" stroke="#336699""##));
+        assert!(recolored.contains("fill: rgb(255, 0, 0)"));
+    }
+
+    #[test]
+    fn test_color_mappings_and_preview() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20"><rect width="20" height="20" fill="red"/><rect x="20" width="20" height="20" fill="#0000ff80"/></svg>"##;
+        let settings = ImageLoadingSettings {
+            svg_recolor_enabled: true,
+            svg_target_color: [0, 255, 0],
+            svg_color_mappings: vec![SvgColorMapping { from: [255, 0, 0], to: [255, 255, 0] }],
+            ..Default::default()
+        };
+        let recolored = recolor_svg(svg, &settings);
+        assert!(recolored.contains(r##"fill="#ffff00""##));
+        // Unmapped colors use the target, keeping their alpha
+        assert!(recolored.contains(r##"fill="#00ff0080""##));
+
+        let preview = render_svg_preview(svg, &settings, SVG_PREVIEW_SIZE).unwrap();
+        assert_eq!(preview.dimensions(), (96, 48));
+        assert_eq!(preview.get_pixel(10, 24).0, [255, 255, 0, 255]);
+
+        // The middle half, magnified: red on the left, blue on the right
+        let uv = emath::Rect::from_min_max(emath::pos2(0.25, 0.0), emath::pos2(0.75, 1.0));
+        let region = render_svg_region(svg, &ImageLoadingSettings::default(), uv, [40, 40]).unwrap();
+        assert_eq!(region.dimensions(), (40, 40));
+        assert_eq!(region.get_pixel(5, 20).0, [255, 0, 0, 255]);
+        let [r, _, b, a] = region.get_pixel(35, 20).0;
+        assert_eq!((r, b, a), (0, 255, 128));
+    }
+
+    #[test]
+    fn test_svg_inside_archive() {
+        use std::io::Write;
+        let dir = TempDir::new("svg_in_archive");
+        let archive = dir.join("icons.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+        zip.start_file("icons/dot.svg", zip::write::SimpleFileOptions::default()).unwrap();
+        zip.write_all(br#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="2"><rect width="4" height="2" fill="red"/></svg>"#).unwrap();
+        zip.finish().unwrap();
+
+        let path = archive.join("icons/dot.svg");
+        let settings = ImageLoadingSettings::default();
+        assert!(read_svg_source(&path).unwrap().contains("<rect"));
+        assert_eq!(svg_intrinsic_size(&path, &settings).unwrap(), (4.0, 2.0));
+        let decoded = decode_svg(&path, &settings, false).unwrap();
+        assert_eq!(decoded.dimensions(), (4, 2));
+        assert_eq!(decoded.pixel(1, 1)[3], 255);
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_svg_safety_limits() {
+        let settings = ImageLoadingSettings::default();
+        let nested = format!(r#"<svg xmlns="http://www.w3.org/2000/svg">{}{}</svg>"#, "<g>".repeat(MAX_SVG_DEPTH), "</g>".repeat(MAX_SVG_DEPTH));
+        assert!(render_svg_preview(&nested, &settings, 16).unwrap_err().contains("levels deep"));
+        let huge = r#"<svg xmlns="http://www.w3.org/2000/svg" width="1e9" height="10"/>"#;
+        assert!(render_svg_preview(huge, &settings, 16).unwrap_err().contains("too large"));
+
+        // Files referenced by <image> are not loaded
+        let dir = TempDir::new("svg_external_image");
+        let png = dir.join("external.png");
+        image::RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255])).save(&png).unwrap();
+        let svg = format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="8" height="8"><image href="{}" width="8" height="8"/></svg>"#, png.display());
+        let rendered = render_svg_preview(&svg, &settings, 8).unwrap();
+        assert!(rendered.pixels().all(|pixel| pixel[3] == 0));
+
+        let slow = with_timeout("Waiting", std::time::Duration::from_millis(10), || {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            Ok(())
+        });
+        let error = slow.unwrap_err();
+        assert!(error.contains("took longer") && timed_out(&error));
+        // The thread left behind is no longer counted once it ends
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while ABANDONED_THREADS.load(std::sync::atomic::Ordering::SeqCst) > 0 && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(ABANDONED_THREADS.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert!(!timed_out("Failed to parse SVG: unexpected end of stream"));
+    }
+}
@@ -0,0 +1,421 @@
+//! Background job engine with idle-time gating
+//!
+//! Jobs run on a small pool of worker threads. Interactive jobs start right
+//! away; idle jobs only start while the user is idle and pause at their next
+//! checkpoint as soon as input resumes. A paused idle job doesn't count
+//! against the worker limit, and its worker runs interactive jobs meanwhile.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+use crate::progress::{OperationKind, ProgressReporter, ProgressSink};
+
+pub type JobId = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobPriority {
+    /// Started immediately, never paused
+    Interactive,
+    /// Heavy optional work that only runs while the user is idle
+    Idle,
+}
+
+#[derive(Debug, Clone)]
+pub enum JobEvent {
+    Started { id: JobId, name: String },
+    Finished { id: JobId, name: String, result: Result<String, String> },
+}
+
+/// Handle given to a running job for cooperative pausing and cancellation
+pub struct JobContext {
+    priority: JobPriority,
+    cancelled: Arc<AtomicBool>,
+    shared: Arc<EngineShared>,
+    progress: ProgressReporter,
+    /// For running interactive jobs while paused
+    events: Sender<JobEvent>,
+    sink: ProgressSink,
+}
+
+impl JobContext {
+    /// Report how many units of work are done, out of `total` if known
+    pub fn report_progress(&self, done: u64, total: Option<u64>) {
+        self.progress.progress(done, total);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst) || self.shared.shutdown.load(Ordering::SeqCst)
+    }
+
+    /// [`Self::is_cancelled`] for callbacks that have to own what they check
+    #[cfg(feature = "scripting")]
+    pub fn cancellation(&self) -> impl Fn() -> bool + Send + 'static {
+        let (cancelled, shared) = (Arc::clone(&self.cancelled), Arc::clone(&self.shared));
+        move || cancelled.load(Ordering::SeqCst) || shared.shutdown.load(Ordering::SeqCst)
+    }
+
+    /// Call between units of work. Blocks while an idle job is gated off and
+    /// returns an error once the job has been cancelled.
+    pub fn checkpoint(&self) -> Result<(), String> {
+        if self.priority == JobPriority::Idle {
+            let mut state = self.shared.state.lock().unwrap();
+            if !state.idle && !self.is_cancelled() {
+                state.paused += 1;
+                self.shared.wake.notify_all();
+                while !state.idle && !self.is_cancelled() {
+                    // Lend the worker to interactive jobs until the gate opens
+                    if let Some(job) = state.take_next(JobPriority::Interactive) {
+                        drop(state);
+                        run_job(&self.shared, &self.events, &self.sink, job);
+                        state = self.shared.state.lock().unwrap();
+                    } else {
+                        state = self.shared.wake.wait(state).unwrap();
+                    }
+                }
+                state.paused -= 1;
+            }
+        }
+        if self.is_cancelled() {
+            Err("Cancelled".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+type JobFn = Box<dyn FnOnce(&JobContext) -> Result<String, String> + Send>;
+
+struct QueuedJob {
+    id: JobId,
+    name: String,
+    priority: JobPriority,
+    cancelled: Arc<AtomicBool>,
+    run: JobFn,
+}
+
+#[derive(Default)]
+struct EngineState {
+    queue: VecDeque<QueuedJob>,
+    running: Vec<(JobId, Arc<AtomicBool>)>,
+    /// Running idle jobs waiting at a checkpoint for the gate to open
+    paused: usize,
+    /// Whether the user is currently idle (opens the gate for idle jobs)
+    idle: bool,
+    /// Most jobs run at once, at most the number of workers
+    worker_limit: usize,
+}
+
+impl EngineState {
+    /// Start the next queued job no lower than `lowest`, if the limit allows.
+    /// Interactive jobs always run; idle jobs only while the gate is open.
+    fn take_next(&mut self, lowest: JobPriority) -> Option<QueuedJob> {
+        if self.running.len() - self.paused >= self.worker_limit {
+            return None;
+        }
+        let index = self
+            .queue
+            .iter()
+            .position(|job| job.priority == JobPriority::Interactive)
+            .or_else(|| {
+                (self.idle && lowest == JobPriority::Idle)
+                    .then(|| self.queue.iter().position(|job| job.priority == JobPriority::Idle))
+                    .flatten()
+            })?;
+        let job = self.queue.remove(index)?;
+        self.running.push((job.id, Arc::clone(&job.cancelled)));
+        Some(job)
+    }
+}
+
+struct EngineShared {
+    state: Mutex<EngineState>,
+    wake: Condvar,
+    shutdown: AtomicBool,
+}
+
+pub struct JobEngine {
+    shared: Arc<EngineShared>,
+    events: Receiver<JobEvent>,
+    workers: Vec<JoinHandle<()>>,
+    next_id: AtomicU64,
+}
+
+impl JobEngine {
+    /// An engine whose progress goes nowhere; the app always shows it
+    #[cfg(test)]
+    pub fn new(worker_count: usize) -> Self {
+        Self::with_progress(worker_count, ProgressSink::disconnected())
+    }
+
+    /// Create an engine whose jobs report progress to `progress`
+    pub fn with_progress(worker_count: usize, progress: ProgressSink) -> Self {
+        let shared = Arc::new(EngineShared {
+            state: Mutex::new(EngineState { worker_limit: worker_count.max(1), ..Default::default() }),
+            wake: Condvar::new(),
+            shutdown: AtomicBool::new(false),
+        });
+        let (sender, events) = mpsc::channel();
+
+        let workers = (0..worker_count.max(1))
+            .filter_map(|i| {
+                let shared = Arc::clone(&shared);
+                let sender = sender.clone();
+                let progress = progress.clone();
+                std::thread::Builder::new()
+                    .name(format!("job-worker-{}", i))
+                    .spawn(move || job_worker(shared, sender, progress))
+                    .ok()
+            })
+            .collect();
+
+        Self {
+            shared,
+            events,
+            workers,
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn submit(
+        &self,
+        name: impl Into<String>,
+        priority: JobPriority,
+        run: impl FnOnce(&JobContext) -> Result<String, String> + Send + 'static,
+    ) -> JobId {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let mut state = self.shared.state.lock().unwrap();
+        state.queue.push_back(QueuedJob {
+            id,
+            name: name.into(),
+            priority,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            run: Box::new(run),
+        });
+        self.shared.wake.notify_all();
+        id
+    }
+
+    /// Open or close the gate for idle jobs. Closing it pauses running idle
+    /// jobs at their next checkpoint.
+    pub fn set_user_idle(&self, idle: bool) {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.idle != idle {
+            state.idle = idle;
+            self.shared.wake.notify_all();
+        }
+    }
+
+    /// Run at most `limit` jobs at once from now on, never more than the
+    /// engine has workers. Running jobs finish either way.
+    pub fn set_worker_limit(&self, limit: usize) {
+        let mut state = self.shared.state.lock().unwrap();
+        let limit = limit.clamp(1, self.workers.len().max(1));
+        if state.worker_limit != limit {
+            state.worker_limit = limit;
+            self.shared.wake.notify_all();
+        }
+    }
+
+    /// Cancel a queued or running job
+    pub fn cancel(&self, id: JobId) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.queue.retain(|job| job.id != id);
+        if let Some((_, cancelled)) = state.running.iter().find(|(job_id, _)| *job_id == id) {
+            cancelled.store(true, Ordering::SeqCst);
+        }
+        self.shared.wake.notify_all();
+    }
+
+    /// Jobs that are queued but not yet started, of the given priority
+    pub fn queued_count(&self, priority: JobPriority) -> usize {
+        let state = self.shared.state.lock().unwrap();
+        state.queue.iter().filter(|job| job.priority == priority).count()
+    }
+
+    pub fn is_busy(&self) -> bool {
+        let state = self.shared.state.lock().unwrap();
+        !state.queue.is_empty() || !state.running.is_empty()
+    }
+
+    /// Drain events produced by the workers since the last call
+    pub fn poll_events(&self) -> Vec<JobEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+impl Drop for JobEngine {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+        self.shared.wake.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn job_worker(shared: Arc<EngineShared>, sender: Sender<JobEvent>, progress: ProgressSink) {
+    loop {
+        let job = {
+            let mut state = shared.state.lock().unwrap();
+            loop {
+                if shared.shutdown.load(Ordering::SeqCst) {
+                    return;
+                }
+                if let Some(job) = state.take_next(JobPriority::Idle) {
+                    break job;
+                }
+                state = shared.wake.wait(state).unwrap();
+            }
+        };
+        if !run_job(&shared, &sender, &progress, job) {
+            return;
+        }
+    }
+}
+
+/// Run a job taken off the queue; false once nobody listens for events
+fn run_job(shared: &Arc<EngineShared>, sender: &Sender<JobEvent>, progress: &ProgressSink, job: QueuedJob) -> bool {
+    let _ = sender.send(JobEvent::Started { id: job.id, name: job.name.clone() });
+    let context = JobContext {
+        priority: job.priority,
+        cancelled: job.cancelled,
+        shared: Arc::clone(shared),
+        progress: progress.start(OperationKind::Job, job.name.clone()),
+        events: sender.clone(),
+        sink: progress.clone(),
+    };
+    let result = (job.run)(&context);
+    context.progress.complete(&result);
+
+    shared.state.lock().unwrap().running.retain(|(id, _)| *id != job.id);
+    // A worker held back by the limit can start the next job
+    shared.wake.notify_all();
+    sender.send(JobEvent::Finished { id: job.id, name: job.name, result }).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn wait_for_finish(engine: &JobEngine, timeout: Duration) -> Option<Result<String, String>> {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            for event in engine.poll_events() {
+                if let JobEvent::Finished { result, .. } = event {
+                    return Some(result);
+                }
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        None
+    }
+
+    #[test]
+    fn test_interactive_jobs_run_immediately() {
+        let engine = JobEngine::new(1);
+        engine.submit("interactive", JobPriority::Interactive, |_| Ok("done".to_string()));
+        assert_eq!(wait_for_finish(&engine, Duration::from_secs(5)), Some(Ok("done".to_string())));
+    }
+
+    #[test]
+    fn test_idle_jobs_wait_for_idle_gate() {
+        let engine = JobEngine::new(1);
+        engine.submit("idle", JobPriority::Idle, |ctx| {
+            ctx.checkpoint()?;
+            Ok("idle done".to_string())
+        });
+
+        assert_eq!(wait_for_finish(&engine, Duration::from_millis(100)), None);
+        assert_eq!(engine.queued_count(JobPriority::Idle), 1);
+
+        engine.set_user_idle(true);
+        assert_eq!(wait_for_finish(&engine, Duration::from_secs(5)), Some(Ok("idle done".to_string())));
+    }
+
+    #[test]
+    fn test_worker_limit_holds_jobs_back() {
+        let engine = JobEngine::new(2);
+        engine.set_worker_limit(1);
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        engine.submit("first", JobPriority::Interactive, move |_| {
+            let _ = release_rx.recv_timeout(Duration::from_secs(5));
+            Ok("first".to_string())
+        });
+        engine.submit("second", JobPriority::Interactive, |_| Ok("second".to_string()));
+
+        assert_eq!(wait_for_finish(&engine, Duration::from_millis(100)), None);
+        drop(release_tx);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut finished = Vec::new();
+        while finished.len() < 2 && Instant::now() < deadline {
+            finished.extend(engine.poll_events().into_iter().filter_map(|event| match event {
+                JobEvent::Finished { result, .. } => Some(result),
+                _ => None,
+            }));
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        // The second can report back before the first's worker does
+        finished.sort();
+        assert_eq!(finished, [Ok("first".to_string()), Ok("second".to_string())]);
+    }
+
+    #[test]
+    fn test_cancel_paused_idle_job() {
+        let engine = JobEngine::new(1);
+        engine.set_user_idle(true);
+        let (started_tx, started_rx) = mpsc::channel();
+        let id = engine.submit("paused", JobPriority::Idle, move |ctx| {
+            let _ = started_tx.send(());
+            std::thread::sleep(Duration::from_millis(50));
+            ctx.checkpoint()?;
+            Ok("should not finish".to_string())
+        });
+
+        started_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        // Input resumes while the job is running, so it pauses at its checkpoint
+        engine.set_user_idle(false);
+        engine.cancel(id);
+        assert_eq!(wait_for_finish(&engine, Duration::from_secs(5)), Some(Err("Cancelled".to_string())));
+    }
+
+    #[test]
+    fn test_paused_idle_job_frees_its_worker() {
+        let engine = JobEngine::new(1);
+        engine.set_user_idle(true);
+        let (started_tx, started_rx) = mpsc::channel();
+        engine.submit("paused", JobPriority::Idle, move |ctx| {
+            let _ = started_tx.send(());
+            std::thread::sleep(Duration::from_millis(50));
+            ctx.checkpoint()?;
+            Ok("idle".to_string())
+        });
+        started_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        engine.set_user_idle(false);
+
+        // The only worker is paused in the idle job, yet this still runs
+        engine.submit("interactive", JobPriority::Interactive, |_| Ok("interactive".to_string()));
+        assert_eq!(wait_for_finish(&engine, Duration::from_secs(5)), Some(Ok("interactive".to_string())));
+
+        engine.set_user_idle(true);
+        assert_eq!(wait_for_finish(&engine, Duration::from_secs(5)), Some(Ok("idle".to_string())));
+    }
+
+    #[test]
+    fn test_jobs_report_progress() {
+        let mut hub = crate::progress::ProgressHub::new();
+        let engine = JobEngine::with_progress(1, hub.sink());
+        engine.submit("counting", JobPriority::Interactive, |ctx| {
+            ctx.report_progress(1, Some(2));
+            Ok("counted".to_string())
+        });
+        assert_eq!(wait_for_finish(&engine, Duration::from_secs(5)), Some(Ok("counted".to_string())));
+
+        let events = hub.poll();
+        assert!(events.iter().any(|e| matches!(e, crate::progress::ProgressEvent::Progress { done: 1, total: Some(2), .. })));
+        assert!(matches!(events.last(), Some(crate::progress::ProgressEvent::Finished { summary, .. }) if summary == "counted"));
+    }
+}
@@ -0,0 +1,312 @@
+//! Keyboard shortcut registry
+//!
+//! All keyboard handling goes through this registry so the shortcut help
+//! overlay always lists exactly what the app responds to.
+
+use eframe::egui;
+use egui::{Key, KeyboardShortcut, Modifiers};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    PreviousImage,
+    NextImage,
+    FirstImage,
+    LastImage,
+    PageBackward,
+    PageForward,
+    ToggleInfoPanel,
+    ToggleSvgSource,
+    ToggleSoftProof,
+    ToggleSlideshow,
+    TogglePanoramaScroll,
+    ToggleShortcutHelp,
+    ToggleSearch,
+    Undo,
+    Redo,
+    /// Move the selected images into the second folder pane's folder
+    MoveToFolderPane,
+    ToggleCulling,
+    /// Show the culling summary; does nothing outside culling mode
+    FinishCulling,
+    CullKeep,
+    CullReject,
+    CullSkip,
+    /// Set the selected images' star rating; 0 clears it
+    SetRating(i8),
+    TogglePictureFrame,
+    /// Leave picture frame mode; does nothing outside it
+    ExitPictureFrame,
+}
+
+impl Action {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::PreviousImage => "Previous image",
+            Action::NextImage => "Next image",
+            Action::FirstImage => "First image",
+            Action::LastImage => "Last image",
+            Action::PageBackward => "Skip back several images",
+            Action::PageForward => "Skip ahead several images",
+            Action::ToggleInfoPanel => "Show/hide image info panel",
+            Action::ToggleSvgSource => "Show/hide SVG source panel",
+            Action::ToggleSoftProof => "Soft-proof: preview as printed",
+            Action::ToggleSlideshow => "Start/stop slideshow",
+            Action::TogglePanoramaScroll => "Start/stop panorama auto-scroll",
+            Action::ToggleShortcutHelp => "Show/hide this shortcut list",
+            Action::ToggleSearch => "Search previously visited folders",
+            Action::Undo => "Undo",
+            Action::Redo => "Redo",
+            Action::MoveToFolderPane => "Move selected images to the second folder",
+            Action::ToggleCulling => "Start/finish culling mode",
+            Action::FinishCulling => "Finish culling",
+            Action::CullKeep => "Keep image and show the next",
+            Action::CullReject => "Reject image and show the next",
+            Action::CullSkip => "Skip image for now",
+            Action::SetRating(0) => "Clear rating",
+            Action::SetRating(1) => "Rate 1 star",
+            Action::SetRating(2) => "Rate 2 stars",
+            Action::SetRating(3) => "Rate 3 stars",
+            Action::SetRating(4) => "Rate 4 stars",
+            Action::SetRating(_) => "Rate 5 stars",
+            Action::TogglePictureFrame => "Picture frame: compact, always on top",
+            Action::ExitPictureFrame => "Leave picture frame mode",
+        }
+    }
+
+    /// Section heading used to group shortcuts in the help overlay
+    pub fn category(&self) -> &'static str {
+        match self {
+            Action::PreviousImage | Action::NextImage | Action::FirstImage | Action::LastImage
+            | Action::PageBackward | Action::PageForward => "Navigation",
+            Action::ToggleInfoPanel | Action::ToggleSvgSource | Action::ToggleSoftProof
+            | Action::ToggleSlideshow | Action::TogglePanoramaScroll | Action::ToggleSearch
+            | Action::TogglePictureFrame | Action::ExitPictureFrame => "View",
+            Action::SetRating(_) => "Rating",
+            Action::ToggleCulling | Action::FinishCulling | Action::CullKeep | Action::CullReject | Action::CullSkip => "Culling",
+            Action::Undo | Action::Redo | Action::MoveToFolderPane => "Edit",
+            Action::ToggleShortcutHelp => "Help",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBinding {
+    pub action: Action,
+    pub shortcut: KeyboardShortcut,
+}
+
+impl KeyBinding {
+    pub fn new(action: Action, modifiers: Modifiers, key: Key) -> Self {
+        Self {
+            action,
+            shortcut: KeyboardShortcut::new(modifiers, key),
+        }
+    }
+
+    /// Human-readable shortcut, e.g. "Ctrl+Shift+I"
+    pub fn label(&self) -> String {
+        self.shortcut.format(&egui::ModifierNames::NAMES, cfg!(target_os = "macos"))
+    }
+}
+
+/// Mouse interactions, listed alongside the keyboard shortcuts in the help overlay
+pub const MOUSE_SHORTCUTS: &[(&str, &str)] = &[
+    ("Shift+Drag", "Select a region for color statistics or redaction"),
+    ("Drag between file lists", "Move images between the two folders"),
+    ("Back / Forward button", "Previous / next image"),
+    ("Right-drag right / left", "Previous / next image"),
+    ("Double-click", "Toggle fit to window / 100%"),
+    ("Pinch / Ctrl+Scroll", "Zoom"),
+    ("Two-finger drag", "Pan a zoomed image"),
+    ("Swipe left / right", "Next / previous image"),
+];
+
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    bindings: Vec<KeyBinding>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            bindings: vec![
+                KeyBinding::new(Action::PreviousImage, Modifiers::NONE, Key::ArrowUp),
+                KeyBinding::new(Action::NextImage, Modifiers::NONE, Key::ArrowDown),
+                KeyBinding::new(Action::PreviousImage, Modifiers::NONE, Key::ArrowLeft),
+                KeyBinding::new(Action::NextImage, Modifiers::NONE, Key::ArrowRight),
+                KeyBinding::new(Action::FirstImage, Modifiers::NONE, Key::Home),
+                KeyBinding::new(Action::LastImage, Modifiers::NONE, Key::End),
+                KeyBinding::new(Action::PageBackward, Modifiers::NONE, Key::PageUp),
+                KeyBinding::new(Action::PageForward, Modifiers::NONE, Key::PageDown),
+                KeyBinding::new(Action::ToggleInfoPanel, Modifiers::NONE, Key::I),
+                KeyBinding::new(Action::ToggleSvgSource, Modifiers::COMMAND, Key::U),
+                KeyBinding::new(Action::ToggleSoftProof, Modifiers::COMMAND, Key::Y),
+                KeyBinding::new(Action::ToggleSlideshow, Modifiers::NONE, Key::F5),
+                KeyBinding::new(Action::TogglePanoramaScroll, Modifiers::NONE, Key::P),
+                KeyBinding::new(Action::ToggleSearch, Modifiers::COMMAND, Key::F),
+                KeyBinding::new(Action::TogglePictureFrame, Modifiers::NONE, Key::T),
+                KeyBinding::new(Action::ExitPictureFrame, Modifiers::NONE, Key::Escape),
+                KeyBinding::new(Action::Undo, Modifiers::COMMAND, Key::Z),
+                KeyBinding::new(Action::Redo, Modifiers::COMMAND | Modifiers::SHIFT, Key::Z),
+                KeyBinding::new(Action::MoveToFolderPane, Modifiers::NONE, Key::M),
+                KeyBinding::new(Action::SetRating(1), Modifiers::NONE, Key::Num1),
+                KeyBinding::new(Action::SetRating(2), Modifiers::NONE, Key::Num2),
+                KeyBinding::new(Action::SetRating(3), Modifiers::NONE, Key::Num3),
+                KeyBinding::new(Action::SetRating(4), Modifiers::NONE, Key::Num4),
+                KeyBinding::new(Action::SetRating(5), Modifiers::NONE, Key::Num5),
+                KeyBinding::new(Action::SetRating(0), Modifiers::NONE, Key::Num0),
+                KeyBinding::new(Action::ToggleCulling, Modifiers::NONE, Key::F11),
+                KeyBinding::new(Action::FinishCulling, Modifiers::NONE, Key::Escape),
+                KeyBinding::new(Action::CullKeep, Modifiers::NONE, Key::K),
+                KeyBinding::new(Action::CullReject, Modifiers::NONE, Key::X),
+                KeyBinding::new(Action::CullSkip, Modifiers::NONE, Key::Space),
+                KeyBinding::new(Action::ToggleShortcutHelp, Modifiers::NONE, Key::F1),
+            ],
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn shortcuts_for(&self, action: Action) -> impl Iterator<Item = &KeyBinding> {
+        self.bindings.iter().filter(move |b| b.action == action)
+    }
+
+    /// Actions whose shortcut was pressed this frame. Nothing is reported while
+    /// a text field has keyboard focus.
+    pub fn triggered(&self, ctx: &egui::Context) -> Vec<Action> {
+        if ctx.wants_keyboard_input() {
+            return Vec::new();
+        }
+        ctx.input(|i| self.matching(|key| i.key_pressed(key), i.modifiers))
+    }
+
+    /// Actions bound to the pressed keys with `modifiers` held. Extra modifiers
+    /// are allowed, but when several bindings of a key match, only those
+    /// naming the most modifiers count, so Ctrl+Shift+Z isn't also Ctrl+Z.
+    fn matching(&self, pressed: impl Fn(Key) -> bool, modifiers: Modifiers) -> Vec<Action> {
+        let specificity = |m: Modifiers| [m.alt, m.shift, m.command || m.ctrl || m.mac_cmd].into_iter().filter(|&held| held).count();
+        let matched: Vec<&KeyBinding> = self.bindings
+            .iter()
+            .filter(|b| pressed(b.shortcut.logical_key) && modifiers.matches_logically(b.shortcut.modifiers))
+            .collect();
+        matched
+            .iter()
+            .filter(|b| {
+                matched.iter().all(|other| other.shortcut.logical_key != b.shortcut.logical_key
+                    || specificity(other.shortcut.modifiers) <= specificity(b.shortcut.modifiers))
+            })
+            .map(|b| b.action)
+            .collect()
+    }
+
+    /// Bindings grouped by action category, in registration order
+    pub fn grouped(&self) -> Vec<(&'static str, Vec<&KeyBinding>)> {
+        let mut groups: Vec<(&'static str, Vec<&KeyBinding>)> = Vec::new();
+        for binding in &self.bindings {
+            let category = binding.action.category();
+            match groups.iter_mut().find(|(name, _)| *name == category) {
+                Some((_, entries)) => entries.push(binding),
+                None => groups.push((category, vec![binding])),
+            }
+        }
+        groups
+    }
+}
+
+/// Index to select after a navigation action, or `None` if the selection
+/// doesn't change. With `wrap`, moving past either end continues from the
+/// other one.
+pub fn navigation_target(action: Action, current: Option<usize>, len: usize, page_size: usize, wrap: bool) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    let last = len - 1;
+    let page_size = page_size.max(1);
+    let target = match (action, current) {
+        (Action::FirstImage, _) => 0,
+        (Action::LastImage, _) => last,
+        (Action::PreviousImage | Action::PageBackward, None) => last,
+        (Action::NextImage | Action::PageForward, None) => 0,
+        (Action::PreviousImage, Some(0)) | (Action::PageBackward, Some(0)) if wrap => last,
+        (Action::NextImage, Some(i)) | (Action::PageForward, Some(i)) if wrap && i >= last => 0,
+        (Action::PreviousImage, Some(i)) => i.saturating_sub(1),
+        (Action::NextImage, Some(i)) => (i + 1).min(last),
+        (Action::PageBackward, Some(i)) => i.saturating_sub(page_size),
+        (Action::PageForward, Some(i)) => (i + page_size).min(last),
+        _ => return None,
+    };
+    (current != Some(target)).then_some(target)
+}
+
+/// The first index after `current` (before it unless `forward`) whose entry
+/// passes `accept`, continuing from the other end with `wrap`
+pub fn next_matching(current: usize, len: usize, forward: bool, wrap: bool, accept: impl Fn(usize) -> bool) -> Option<usize> {
+    (1..len)
+        .map_while(|step| match (forward, wrap) {
+            (true, false) => (current + step < len).then_some(current + step),
+            (false, false) => current.checked_sub(step),
+            (true, true) => Some((current + step) % len),
+            (false, true) => Some((current + len - step) % len),
+        })
+        .find(|&index| accept(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binding_labels() {
+        let binding = KeyBinding::new(Action::ToggleInfoPanel, Modifiers::NONE, Key::I);
+        assert_eq!(binding.label(), "I");
+        let binding = KeyBinding::new(Action::ToggleInfoPanel, Modifiers::SHIFT, Key::ArrowUp);
+        assert_eq!(binding.label(), "Shift+Up");
+    }
+
+    #[test]
+    fn test_grouped_keeps_every_binding() {
+        let bindings = KeyBindings::default();
+        let grouped = bindings.grouped();
+        let total: usize = grouped.iter().map(|(_, entries)| entries.len()).sum();
+        assert_eq!(total, bindings.bindings.len());
+        assert_eq!(grouped[0].0, "Navigation");
+        assert_eq!(bindings.shortcuts_for(Action::NextImage).count(), 2);
+    }
+
+    #[test]
+    fn test_most_specific_binding_wins() {
+        let bindings = KeyBindings::default();
+        let pressed = |key: Key| move |k: Key| k == key;
+        assert_eq!(bindings.matching(pressed(Key::Z), Modifiers::COMMAND), vec![Action::Undo]);
+        assert_eq!(bindings.matching(pressed(Key::Z), Modifiers::COMMAND | Modifiers::SHIFT), vec![Action::Redo]);
+        assert_eq!(bindings.matching(pressed(Key::Y), Modifiers::COMMAND), vec![Action::ToggleSoftProof]);
+        // Shift is needed for some keys on some layouts, so it doesn't stop a match
+        assert_eq!(bindings.matching(pressed(Key::I), Modifiers::SHIFT), vec![Action::ToggleInfoPanel]);
+    }
+
+    #[test]
+    fn test_navigation_target() {
+        use Action::*;
+        assert_eq!(navigation_target(NextImage, Some(3), 0, 10, false), None);
+        assert_eq!(navigation_target(NextImage, None, 5, 10, false), Some(0));
+        assert_eq!(navigation_target(PreviousImage, None, 5, 10, false), Some(4));
+        assert_eq!(navigation_target(NextImage, Some(4), 5, 10, false), None);
+        assert_eq!(navigation_target(NextImage, Some(4), 5, 10, true), Some(0));
+        assert_eq!(navigation_target(PreviousImage, Some(0), 5, 10, true), Some(4));
+        assert_eq!(navigation_target(FirstImage, Some(3), 5, 10, false), Some(0));
+        assert_eq!(navigation_target(LastImage, Some(4), 5, 10, false), None);
+        assert_eq!(navigation_target(PageForward, Some(1), 50, 10, false), Some(11));
+        assert_eq!(navigation_target(PageForward, Some(45), 50, 10, false), Some(49));
+        assert_eq!(navigation_target(PageForward, Some(49), 50, 10, true), Some(0));
+        assert_eq!(navigation_target(PageBackward, Some(5), 50, 10, false), Some(0));
+        assert_eq!(navigation_target(ToggleInfoPanel, Some(5), 50, 10, true), None);
+
+        let even = |index: usize| index.is_multiple_of(2);
+        assert_eq!(next_matching(2, 6, true, false, even), Some(4));
+        assert_eq!(next_matching(4, 6, true, false, even), None);
+        assert_eq!(next_matching(4, 6, true, true, even), Some(0));
+        assert_eq!(next_matching(0, 6, true, true, |index| index == 0), None);
+        assert_eq!(next_matching(3, 6, false, false, even), Some(2));
+        assert_eq!(next_matching(1, 6, false, true, |index| index == 5), Some(5));
+    }
+}
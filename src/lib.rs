@@ -1,18 +1,161 @@
 //! Image Preview Application Library
 //! 
 //! A high-performance image viewer with OneDrive integration and performance benchmarking.
+//!
+//! Use [`prelude`] when depending on this crate; it is the part of the API
+//! that follows semver.
+//!
+//! The viewer itself and everything egui-specific is behind the default `gui`
+//! feature; with `default-features = false` only the decoding, settings and
+//! cloud file core is built.
 
+pub mod prelude;
+#[cfg(feature = "gui")]
 pub mod app;
 pub mod benchmark;
 pub mod settings;
 pub mod image_processing;
+pub mod decoded_image;
+#[cfg(feature = "gui")]
+pub mod egui_adapter;
+pub mod format_decoders;
 pub mod onedrive;
 pub mod file_locality;
+#[cfg(feature = "gui")]
 pub mod icons;
+pub mod download_queue;
+#[cfg(feature = "gui")]
+pub(crate) mod color_stats;
+#[cfg(feature = "gui")]
+pub(crate) mod metadata;
+#[cfg(feature = "gui")]
+pub(crate) mod print_size;
+pub(crate) mod power;
+#[cfg(feature = "gui")]
+pub(crate) mod jobs;
+#[cfg(feature = "gui")]
+pub(crate) mod idle;
+#[cfg(feature = "gui")]
+pub(crate) mod keybindings;
+#[cfg(feature = "gui")]
+pub(crate) mod share;
+#[cfg(feature = "gui")]
+pub(crate) mod bandwidth;
+#[cfg(feature = "gui")]
+pub(crate) mod view_state;
+#[cfg(feature = "gui")]
+pub(crate) mod svg_source;
+pub(crate) mod backdrop;
+#[cfg(feature = "gui")]
+pub(crate) mod mouse_gestures;
+pub mod cli;
+pub mod progress;
+#[cfg(feature = "gui")]
+pub(crate) mod cache;
+#[cfg(feature = "gui")]
+pub(crate) mod favorites;
+pub(crate) mod diagnostics;
+#[cfg(feature = "gui")]
+pub(crate) mod file_selection;
+pub(crate) mod soft_proof;
+#[cfg(feature = "gui")]
+pub(crate) mod status_bar;
+pub(crate) mod theme;
+pub(crate) mod slideshow;
+pub(crate) mod preferences;
+pub(crate) mod fit_mode;
+#[cfg(feature = "gui")]
+pub(crate) mod panorama;
+#[cfg(feature = "gui")]
+pub(crate) mod deep_zoom;
+pub mod crash;
+#[cfg(feature = "gui")]
+pub(crate) mod metadata_providers;
+#[cfg(feature = "gui")]
+pub(crate) mod captions;
+#[cfg(feature = "gui")]
+pub(crate) mod sidecars;
+#[cfg(feature = "gui")]
+pub(crate) mod view_export;
+pub(crate) mod shell_integration;
+#[cfg(feature = "gui")]
+pub(crate) mod session;
+#[cfg(feature = "gui")]
+pub(crate) mod tuning;
+#[cfg(feature = "gui")]
+pub(crate) mod print;
+#[cfg(feature = "gui")]
+pub(crate) mod compare;
+pub(crate) mod duplicates;
+#[cfg(feature = "gui")]
+pub(crate) mod xmp_sidecar;
+#[cfg(feature = "gui")]
+pub(crate) mod search_index;
+pub(crate) mod archive;
+pub(crate) mod video;
+#[cfg(feature = "gui")]
+pub(crate) mod sharp_svg;
+pub(crate) mod toolbar;
+#[cfg(feature = "gui")]
+pub(crate) mod repaint;
+#[cfg(feature = "gui")]
+pub(crate) mod file_rows;
+#[cfg(feature = "gui")]
+pub(crate) mod frame_sequence;
+#[cfg(feature = "gui")]
+pub(crate) mod hot_folder;
+#[cfg(feature = "gui")]
+pub(crate) mod checksums;
+#[cfg(feature = "gui")]
+pub(crate) mod triage;
+pub(crate) mod sharpness;
+#[cfg(feature = "gui")]
+pub(crate) mod ocr;
+#[cfg(feature = "gui")]
+pub(crate) mod redaction;
+#[cfg(feature = "gui")]
+pub(crate) mod adjustment;
+#[cfg(feature = "gui")]
+pub(crate) mod annotation;
+#[cfg(feature = "gui")]
+pub(crate) mod history;
+#[cfg(feature = "gui")]
+pub(crate) mod edit_sidecar;
+#[cfg(feature = "gui")]
+pub(crate) mod external_editor;
+#[cfg(feature = "gui")]
+pub(crate) mod folder_pane;
+#[cfg(feature = "gui")]
+pub(crate) mod culling;
+#[cfg(feature = "gui")]
+pub(crate) mod stacks;
+#[cfg(feature = "gui")]
+pub(crate) mod pixel_grid;
+#[cfg(feature = "gui")]
+pub(crate) mod navigator;
+#[cfg(feature = "gui")]
+pub(crate) mod color_profile;
+#[cfg(feature = "gui")]
+pub(crate) mod preview_store;
+pub(crate) mod network_cost;
+#[cfg(feature = "gui")]
+pub(crate) mod picture_frame;
+#[cfg(feature = "gui")]
+pub(crate) mod prompt_policy;
+#[cfg(feature = "gui")]
+pub(crate) mod shell_thumbnail;
+#[cfg(feature = "gui")]
+pub(crate) mod toast;
+#[cfg(all(feature = "gui", feature = "scripting"))]
+pub(crate) mod scripting;
+#[cfg(test)]
+pub(crate) mod test_support;
 
 // Re-export commonly used types
+#[cfg(feature = "gui")]
 pub use app::ImageViewerApp;
 pub use settings::ImageLoadingSettings;
-pub use benchmark::{SystemPerformanceCategory, PerformanceProfile, BenchmarkResult};
+pub use benchmark::{SystemPerformanceCategory, PerformanceProfile, BenchmarkResult, HardwareFingerprint};
+#[allow(deprecated)]
 pub use onedrive::{OneDriveFileStatus, FileInfo as OneDriveFileInfo};
-pub use file_locality::{FileLocalityStatus, FileInfo};
+pub use file_locality::{CloudProvider, FileLocalityStatus, FileInfo};
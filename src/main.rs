@@ -4,6 +4,21 @@ use eframe::egui;
 use image_previewer::ImageViewerApp;
 
 fn main() -> Result<(), eframe::Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let launch_path = image_previewer::cli::launch_path(&args);
+    if !args.is_empty() && launch_path.is_none() {
+        // Release builds have no console of their own; reuse the terminal we were started from
+        #[cfg(windows)]
+        unsafe {
+            use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+            let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+        }
+        if let Some(code) = image_previewer::cli::run(&args) {
+            std::process::exit(code);
+        }
+    }
+
+    image_previewer::crash::install_panic_hook();
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([800.0, 600.0]),
         ..Default::default()
@@ -11,6 +26,6 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "Image PreViewer",
         options,
-        Box::new(|_cc| Ok(Box::<ImageViewerApp>::default())),
+        Box::new(|cc| Ok(Box::new(ImageViewerApp::new(cc, launch_path)))),
     )
 }
\ No newline at end of file
@@ -0,0 +1,261 @@
+//! Image metadata extraction (dimensions, format, resolution)
+
+use std::io::Read;
+use std::path::Path;
+
+use image::ImageReader;
+
+use crate::archive;
+use crate::file_locality::FileInfo;
+
+/// Only the start of the file is scanned for resolution metadata
+const HEADER_SCAN_BYTES: u64 = 128 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageMetadata {
+    pub width: u32,
+    pub height: u32,
+    pub format: String,
+    pub file_size: u64,
+    /// Horizontal and vertical resolution in dots per inch, if the file records one
+    pub dpi: Option<(f64, f64)>,
+}
+
+/// Read basic metadata for a local image without decoding the pixel data
+///
+/// Returns None for on-demand files so that inspecting metadata never triggers a download.
+pub fn read_image_metadata(path: &Path) -> Option<ImageMetadata> {
+    if FileInfo::new(archive::backing_file(path).to_path_buf()).will_trigger_download() {
+        return None;
+    }
+
+    let format = path.extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown")
+        .to_lowercase();
+    if let Some((archive, entry)) = archive::split_path(path) {
+        let bytes = archive::read_entry(archive, &entry, None).ok()?;
+        let (width, height) = ImageReader::new(std::io::Cursor::new(&bytes))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok())
+            .unwrap_or((0, 0));
+        return Some(ImageMetadata { width, height, format, file_size: bytes.len() as u64, dpi: parse_dpi(&bytes) });
+    }
+
+    let file_size = std::fs::metadata(path).ok()?.len();
+    let (width, height) = ImageReader::open(path)
+        .ok()
+        .and_then(|reader| reader.with_guessed_format().ok())
+        .and_then(|reader| reader.into_dimensions().ok())
+        .unwrap_or((0, 0));
+
+    Some(ImageMetadata {
+        width,
+        height,
+        format,
+        file_size,
+        dpi: parse_dpi(&read_header(path)),
+    })
+}
+
+/// The start of the file, where formats keep their metadata
+pub fn read_header(path: &Path) -> Vec<u8> {
+    if let Some((archive, entry)) = archive::split_path(path) {
+        return archive::read_entry(archive, &entry, Some(HEADER_SCAN_BYTES)).unwrap_or_default();
+    }
+    let mut header = Vec::new();
+    if let Ok(file) = std::fs::File::open(path) {
+        let _ = file.take(HEADER_SCAN_BYTES).read_to_end(&mut header);
+    }
+    header
+}
+
+/// Extract the recorded resolution from PNG, JPEG (EXIF or JFIF) or BMP header bytes
+pub fn parse_dpi(bytes: &[u8]) -> Option<(f64, f64)> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        parse_png_dpi(bytes)
+    } else if bytes.starts_with(&[0xFF, 0xD8]) {
+        parse_jpeg_dpi(bytes)
+    } else if bytes.starts_with(b"BM") {
+        parse_bmp_dpi(bytes)
+    } else {
+        None
+    }
+}
+
+fn be_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(bytes.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn be_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+const METERS_PER_INCH: f64 = 0.0254;
+
+fn parse_png_dpi(bytes: &[u8]) -> Option<(f64, f64)> {
+    let mut offset = 8;
+    while offset + 8 <= bytes.len() {
+        let length = be_u32(bytes, offset)? as usize;
+        let chunk_type = bytes.get(offset + 4..offset + 8)?;
+        let data = offset + 8;
+        match chunk_type {
+            b"pHYs" => {
+                let x = be_u32(bytes, data)? as f64;
+                let y = be_u32(bytes, data + 4)? as f64;
+                // Unit 1 is pixels per meter; unit 0 only describes aspect ratio
+                return (*bytes.get(data + 8)? == 1).then_some((x * METERS_PER_INCH, y * METERS_PER_INCH));
+            }
+            b"IDAT" | b"IEND" => return None,
+            _ => offset = data + length + 4,
+        }
+    }
+    None
+}
+
+fn parse_jpeg_dpi(bytes: &[u8]) -> Option<(f64, f64)> {
+    let mut jfif_dpi = None;
+    let mut offset = 2;
+    while offset + 4 <= bytes.len() {
+        if bytes[offset] != 0xFF {
+            return jfif_dpi;
+        }
+        let marker = bytes[offset + 1];
+        // Start of scan: no more metadata segments follow
+        if marker == 0xDA {
+            break;
+        }
+        let length = be_u16(bytes, offset + 2)? as usize;
+        let segment = bytes.get(offset + 4..(offset + 2 + length).min(bytes.len()))?;
+        match marker {
+            0xE0 if segment.starts_with(b"JFIF\0") => {
+                let units = *segment.get(7)?;
+                let x = be_u16(segment, 8)? as f64;
+                let y = be_u16(segment, 10)? as f64;
+                jfif_dpi = match units {
+                    1 => Some((x, y)),
+                    2 => Some((x * 2.54, y * 2.54)),
+                    _ => None,
+                };
+            }
+            0xE1 if segment.starts_with(b"Exif\0\0") => {
+                // EXIF takes precedence since cameras and editors keep it current
+                if let Some(dpi) = parse_exif_dpi(&segment[6..]) {
+                    return Some(dpi);
+                }
+            }
+            _ => {}
+        }
+        offset += 2 + length;
+    }
+    jfif_dpi
+}
+
+fn parse_exif_dpi(tiff: &[u8]) -> Option<(f64, f64)> {
+    let little_endian = match tiff.get(0..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let read_u16 = |offset: usize| -> Option<u16> {
+        let raw: [u8; 2] = tiff.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if little_endian { u16::from_le_bytes(raw) } else { u16::from_be_bytes(raw) })
+    };
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let raw: [u8; 4] = tiff.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if little_endian { u32::from_le_bytes(raw) } else { u32::from_be_bytes(raw) })
+    };
+    let read_rational = |offset: usize| -> Option<f64> {
+        let denominator = read_u32(offset + 4)?;
+        (denominator != 0).then(|| read_u32(offset).map(|n| n as f64 / denominator as f64))?
+    };
+
+    let ifd = read_u32(4)? as usize;
+    let entry_count = read_u16(ifd)? as usize;
+    let (mut x, mut y, mut unit) = (None, None, 2u16);
+    for i in 0..entry_count {
+        let entry = ifd + 2 + i * 12;
+        match read_u16(entry)? {
+            0x011A => x = read_rational(read_u32(entry + 8)? as usize),
+            0x011B => y = read_rational(read_u32(entry + 8)? as usize),
+            0x0128 => unit = read_u16(entry + 8)?,
+            _ => {}
+        }
+    }
+
+    let scale = match unit {
+        2 => 1.0,  // inches
+        3 => 2.54, // centimeters
+        _ => return None,
+    };
+    Some((x? * scale, y? * scale))
+}
+
+fn parse_bmp_dpi(bytes: &[u8]) -> Option<(f64, f64)> {
+    let x = i32::from_le_bytes(bytes.get(38..42)?.try_into().ok()?);
+    let y = i32::from_le_bytes(bytes.get(42..46)?.try_into().ok()?);
+    (x > 0 && y > 0).then_some((x as f64 * METERS_PER_INCH, y as f64 * METERS_PER_INCH))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_png_phys() {
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        // IHDR chunk (contents irrelevant for DPI parsing)
+        png.extend_from_slice(&13u32.to_be_bytes());
+        png.extend_from_slice(b"IHDR");
+        png.extend_from_slice(&[0u8; 13 + 4]);
+        // pHYs: 11811 pixels per meter ~= 300 DPI
+        png.extend_from_slice(&9u32.to_be_bytes());
+        png.extend_from_slice(b"pHYs");
+        png.extend_from_slice(&11811u32.to_be_bytes());
+        png.extend_from_slice(&11811u32.to_be_bytes());
+        png.push(1);
+        png.extend_from_slice(&[0u8; 4]);
+
+        let (x, y) = parse_dpi(&png).unwrap();
+        assert!((x - 300.0).abs() < 0.1 && (y - 300.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_parse_jfif_density() {
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        jpeg.extend_from_slice(b"JFIF\0");
+        jpeg.extend_from_slice(&[1, 1, 1]); // version 1.1, units = dots per inch
+        jpeg.extend_from_slice(&72u16.to_be_bytes());
+        jpeg.extend_from_slice(&72u16.to_be_bytes());
+        jpeg.extend_from_slice(&[0, 0]);
+        jpeg.extend_from_slice(&[0xFF, 0xDA]);
+
+        assert_eq!(parse_dpi(&jpeg), Some((72.0, 72.0)));
+    }
+
+    #[test]
+    fn test_parse_exif_resolution() {
+        // Little-endian TIFF header with XResolution, YResolution and ResolutionUnit
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        tiff.extend_from_slice(&3u16.to_le_bytes());
+        let rational_offset = 8 + 2 + 3 * 12 + 4;
+        for (tag, kind, value) in [(0x011Au16, 5u16, rational_offset), (0x011B, 5, rational_offset), (0x0128, 3, 2)] {
+            tiff.extend_from_slice(&tag.to_le_bytes());
+            tiff.extend_from_slice(&kind.to_le_bytes());
+            tiff.extend_from_slice(&1u32.to_le_bytes());
+            tiff.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff.extend_from_slice(&600u32.to_le_bytes());
+        tiff.extend_from_slice(&2u32.to_le_bytes());
+
+        assert_eq!(parse_exif_dpi(&tiff), Some((300.0, 300.0)));
+    }
+
+    #[test]
+    fn test_unknown_format_has_no_dpi() {
+        assert_eq!(parse_dpi(b"GIF89a"), None);
+    }
+}
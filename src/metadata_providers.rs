@@ -0,0 +1,430 @@
+//! Sections of the metadata panel, each contributed by a [`MetadataProvider`]
+//!
+//! The panel shows whatever sections the registered providers return, so a
+//! new metadata source only needs a provider registered in
+//! [`MetadataRegistry::default`].
+
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use regex::Regex;
+
+use crate::file_locality::FileInfo;
+
+/// A titled list of key-value rows in the metadata panel
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataSection {
+    pub title: String,
+    pub entries: Vec<(String, String)>,
+}
+
+impl MetadataSection {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self { title: title.into(), entries: Vec::new() }
+    }
+
+    pub fn push(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.entries.push((key.into(), value.into()));
+    }
+
+    /// The section, unless it has no rows
+    fn non_empty(self) -> Option<Self> {
+        (!self.entries.is_empty()).then_some(self)
+    }
+}
+
+/// What providers can read about the displayed image
+pub struct MetadataSource<'a> {
+    pub path: &'a Path,
+    pub file: &'a FileInfo,
+    /// Start of the file; empty for on-demand files so nothing is downloaded
+    pub header: &'a [u8],
+}
+
+pub trait MetadataProvider: Send + Sync {
+    /// Sections for the image, in display order; empty when the provider has nothing to show
+    fn sections(&self, source: &MetadataSource) -> Vec<MetadataSection>;
+}
+
+/// Providers queried for the metadata panel, in display order
+pub struct MetadataRegistry {
+    providers: Vec<Box<dyn MetadataProvider>>,
+}
+
+impl Default for MetadataRegistry {
+    fn default() -> Self {
+        let mut registry = Self { providers: Vec::new() };
+        registry.register(ExifProvider);
+        registry.register(XmpProvider);
+        registry.register(PngTextProvider);
+        registry.register(crate::captions::CaptionProvider);
+        registry.register(FileSystemProvider);
+        registry.register(CloudProvider);
+        registry
+    }
+}
+
+impl MetadataRegistry {
+    pub fn register(&mut self, provider: impl MetadataProvider + 'static) {
+        self.providers.push(Box::new(provider));
+    }
+
+    pub fn sections(&self, source: &MetadataSource) -> Vec<MetadataSection> {
+        self.providers.iter().flat_map(|provider| provider.sections(source)).collect()
+    }
+}
+
+/// Size, dates and permissions from the file system
+pub struct FileSystemProvider;
+
+impl MetadataProvider for FileSystemProvider {
+    fn sections(&self, source: &MetadataSource) -> Vec<MetadataSection> {
+        let mut section = MetadataSection::new("File");
+        section.push("Name", source.path.file_name().unwrap_or_default().to_string_lossy());
+        if let Some(folder) = source.path.parent() {
+            section.push("Folder", folder.display().to_string());
+        }
+        if let Ok(metadata) = std::fs::metadata(source.path) {
+            section.push("Size", format!("{} bytes", metadata.len()));
+            if let Some(modified) = metadata.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()) {
+                section.push("Modified", format_utc(modified.as_secs()));
+            }
+            if metadata.permissions().readonly() {
+                section.push("Read-only", "Yes");
+            }
+        }
+        vec![section]
+    }
+}
+
+/// Sync status and provider of files in cloud folders
+pub struct CloudProvider;
+
+impl MetadataProvider for CloudProvider {
+    fn sections(&self, source: &MetadataSource) -> Vec<MetadataSection> {
+        let Some(provider) = &source.file.provider else {
+            return Vec::new();
+        };
+        let mut section = MetadataSection::new("Cloud");
+        section.push("Provider", provider.name());
+        section.push("Status", source.file.locality_status.label());
+        if let Some(size) = source.file.estimated_download_size {
+            section.push("Download size", format!("{} bytes", size));
+        }
+        vec![section]
+    }
+}
+
+/// Camera and authoring tags from JPEG EXIF
+pub struct ExifProvider;
+
+/// IFD0 tags shown, with their labels
+const EXIF_TEXT_TAGS: [(u16, &str); 7] = [
+    (0x010F, "Camera make"),
+    (0x0110, "Camera model"),
+    (0x0131, "Software"),
+    (0x0132, "Date"),
+    (0x013B, "Artist"),
+    (0x8298, "Copyright"),
+    (0x010E, "Description"),
+];
+
+/// Pointer from IFD0 to the Exif IFD holding the camera settings
+const EXIF_IFD_POINTER: u16 = 0x8769;
+
+impl MetadataProvider for ExifProvider {
+    fn sections(&self, source: &MetadataSource) -> Vec<MetadataSection> {
+        let mut section = MetadataSection::new("EXIF");
+        for (label, value) in exif_fields(source.header) {
+            section.push(label, value);
+        }
+        section.non_empty().into_iter().collect()
+    }
+}
+
+/// Labelled EXIF fields of a JPEG header: IFD0 text tags, then the camera settings
+pub(crate) fn exif_fields(header: &[u8]) -> Vec<(&'static str, String)> {
+    let Some(tiff) = jpeg_app_segment(header, 0xE1, b"Exif\0\0") else {
+        return Vec::new();
+    };
+    let Some(ifd0) = read_ifd(tiff, None) else {
+        return Vec::new();
+    };
+    let mut fields = Vec::new();
+    for (tag, value) in &ifd0 {
+        if let (Some((_, label)), IfdValue::Text(text)) = (EXIF_TEXT_TAGS.iter().find(|(t, _)| t == tag), value) {
+            fields.push((*label, text.clone()));
+        }
+    }
+    let exif_ifd = ifd0.iter().find_map(|(tag, value)| match value {
+        IfdValue::Number(offset) if *tag == EXIF_IFD_POINTER => Some(*offset as usize),
+        _ => None,
+    });
+    for (tag, value) in exif_ifd.and_then(|offset| read_ifd(tiff, Some(offset))).unwrap_or_default() {
+        let field = match (tag, value) {
+            (0x9003, IfdValue::Text(date)) => ("Date taken", date),
+            (0x8827, IfdValue::Number(iso)) => ("ISO", iso.to_string()),
+            (0x829A, IfdValue::Ratio(n, d)) if n > 0 && d > 0 => {
+                ("Exposure", if n < d { format!("1/{} s", (d as f64 / n as f64).round()) } else { format!("{} s", n as f64 / d as f64) })
+            }
+            (0x829D, IfdValue::Ratio(n, d)) if d > 0 => ("Aperture", format!("f/{}", n as f64 / d as f64)),
+            (0x920A, IfdValue::Ratio(n, d)) if d > 0 => ("Focal length", format!("{} mm", n as f64 / d as f64)),
+            _ => continue,
+        };
+        fields.push(field);
+    }
+    fields
+}
+
+/// Adobe XMP packets, as embedded by editors and cataloguing tools
+pub struct XmpProvider;
+
+/// XMP properties shown, with their labels
+const XMP_PROPERTIES: [(&str, &str); 7] = [
+    ("dc:title", "Title"),
+    ("dc:description", "Description"),
+    ("dc:creator", "Creator"),
+    ("dc:subject", "Keywords"),
+    ("dc:rights", "Rights"),
+    ("xmp:Rating", "Rating"),
+    ("xmp:CreatorTool", "Created with"),
+];
+
+impl MetadataProvider for XmpProvider {
+    fn sections(&self, source: &MetadataSource) -> Vec<MetadataSection> {
+        let text = String::from_utf8_lossy(source.header);
+        let Some(packet) = text.find("<x:xmpmeta").and_then(|start| {
+            text[start..].find("</x:xmpmeta>").map(|end| &text[start..start + end])
+        }) else {
+            return Vec::new();
+        };
+        let mut section = MetadataSection::new("XMP");
+        for (property, label) in XMP_PROPERTIES {
+            if let Some(value) = xmp_property(packet, property) {
+                section.push(label, value);
+            }
+        }
+        section.non_empty().into_iter().collect()
+    }
+}
+
+/// Uncompressed tEXt and iTXt chunks of PNGs
+pub struct PngTextProvider;
+
+impl MetadataProvider for PngTextProvider {
+    fn sections(&self, source: &MetadataSource) -> Vec<MetadataSection> {
+        let mut section = MetadataSection::new("PNG Text");
+        for (keyword, text) in png_text_chunks(source.header) {
+            // XMP is shown by its own provider
+            if keyword != "XML:com.adobe.xmp" {
+                section.push(keyword, text);
+            }
+        }
+        section.non_empty().into_iter().collect()
+    }
+}
+
+/// Body of the first JPEG APPn segment with `marker` whose data starts with `prefix`
+fn jpeg_app_segment<'a>(bytes: &'a [u8], marker: u8, prefix: &[u8]) -> Option<&'a [u8]> {
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut offset = 2;
+    while offset + 4 <= bytes.len() && bytes[offset] == 0xFF && bytes[offset + 1] != 0xDA {
+        let length = u16::from_be_bytes([bytes[offset + 2], bytes[offset + 3]]) as usize;
+        let segment = bytes.get(offset + 4..(offset + 2 + length).min(bytes.len()))?;
+        if bytes[offset + 1] == marker && segment.starts_with(prefix) {
+            return Some(&segment[prefix.len()..]);
+        }
+        offset += 2 + length;
+    }
+    None
+}
+
+/// Value of a TIFF tag; only the first value of a list is kept
+#[derive(Debug, Clone, PartialEq)]
+enum IfdValue {
+    Text(String),
+    Number(u32),
+    Ratio(u32, u32),
+}
+
+/// Text, integer and rational tags of the IFD at `offset` of a TIFF/EXIF
+/// block, or of the first IFD
+fn read_ifd(tiff: &[u8], offset: Option<usize>) -> Option<Vec<(u16, IfdValue)>> {
+    let little_endian = match tiff.get(0..2) {
+        Some(b"II") => true,
+        Some(b"MM") => false,
+        _ => return None,
+    };
+    let read_u16 = |offset: usize| -> Option<u16> {
+        let raw: [u8; 2] = tiff.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if little_endian { u16::from_le_bytes(raw) } else { u16::from_be_bytes(raw) })
+    };
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let raw: [u8; 4] = tiff.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if little_endian { u32::from_le_bytes(raw) } else { u32::from_be_bytes(raw) })
+    };
+    let ifd = match offset {
+        Some(offset) => offset,
+        None => read_u32(4)? as usize,
+    };
+    let count = read_u16(ifd)? as usize;
+    let entries = (0..count)
+        .filter_map(|i| {
+            let entry = ifd + 2 + i * 12;
+            let (tag, kind, length) = (read_u16(entry)?, read_u16(entry + 2)?, read_u32(entry + 4)? as usize);
+            // Values over 4 bytes are stored at an offset
+            let value = match kind {
+                2 => {
+                    let start = if length > 4 { read_u32(entry + 8)? as usize } else { entry + 8 };
+                    let raw = tiff.get(start..start + length)?;
+                    let text = String::from_utf8_lossy(raw).trim_end_matches('\0').trim().to_string();
+                    if text.is_empty() {
+                        return None;
+                    }
+                    IfdValue::Text(text)
+                }
+                3 => IfdValue::Number(read_u16(entry + 8)? as u32),
+                4 => IfdValue::Number(read_u32(entry + 8)?),
+                5 => {
+                    let start = read_u32(entry + 8)? as usize;
+                    IfdValue::Ratio(read_u32(start)?, read_u32(start + 4)?)
+                }
+                _ => return None,
+            };
+            Some((tag, value))
+        })
+        .collect();
+    Some(entries)
+}
+
+/// Value of an XMP property, written as an attribute, a simple element or an rdf list
+pub(crate) fn xmp_property(packet: &str, property: &str) -> Option<String> {
+    let name = regex::escape(property);
+    let attribute = Regex::new(&format!(r#"{}\s*=\s*(?:"([^"]*)"|'([^']*)')"#, name)).ok()?;
+    if let Some(captures) = attribute.captures(packet) {
+        return captures.get(1).or_else(|| captures.get(2)).map(|value| value.as_str().to_string());
+    }
+    let element = Regex::new(&format!(r"(?s)<{0}(?:\s[^>]*)?>(.*?)</{0}>", name)).ok()?;
+    let body = element.captures(packet)?.get(1)?.as_str();
+    let item = Regex::new(r"(?s)<rdf:li[^>]*>(.*?)</rdf:li>").ok()?;
+    let items: Vec<&str> = item.captures_iter(body).filter_map(|c| c.get(1)).map(|m| m.as_str().trim()).collect();
+    let value = if items.is_empty() { body.trim().to_string() } else { items.join(", ") };
+    (!value.is_empty()).then_some(value)
+}
+
+/// Keyword and text of each uncompressed tEXt/iTXt chunk before the image data
+fn png_text_chunks(bytes: &[u8]) -> Vec<(String, String)> {
+    let mut chunks = Vec::new();
+    if !bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return chunks;
+    }
+    let mut offset = 8;
+    while let Some(length) = bytes.get(offset..offset + 4).map(|b| u32::from_be_bytes(b.try_into().unwrap()) as usize) {
+        let Some(kind) = bytes.get(offset + 4..offset + 8) else { break };
+        let Some(data) = bytes.get(offset + 8..offset + 8 + length) else { break };
+        let mut fields = data.splitn(2, |&b| b == 0);
+        let keyword = String::from_utf8_lossy(fields.next().unwrap_or_default()).to_string();
+        let rest = fields.next().unwrap_or_default();
+        match kind {
+            b"tEXt" => chunks.push((keyword, rest.iter().map(|&b| b as char).collect())),
+            // Compression flag, method, then language and translated keyword
+            b"iTXt" if rest.first() == Some(&0) => {
+                let text = rest.get(2..).unwrap_or_default().splitn(3, |&b| b == 0).nth(2).unwrap_or_default();
+                chunks.push((keyword, String::from_utf8_lossy(text).to_string()));
+            }
+            b"IDAT" | b"IEND" => break,
+            _ => {}
+        }
+        offset += 12 + length;
+    }
+    chunks
+}
+
+/// "YYYY-MM-DD HH:MM UTC" for seconds since the Unix epoch
+pub fn format_utc(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let minutes = seconds % 86_400 / 60;
+    // Civil date from day count (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn source_for<'a>(file: &'a FileInfo, header: &'a [u8]) -> MetadataSource<'a> {
+        MetadataSource { path: &file.path, file, header }
+    }
+
+    #[test]
+    fn test_exif_and_xmp_sections() {
+        // Big-endian TIFF with Make (inline), Model (at an offset) and an Exif IFD holding the ISO
+        let mut tiff = b"MM\0*".to_vec();
+        tiff.extend_from_slice(&8u32.to_be_bytes());
+        tiff.extend_from_slice(&3u16.to_be_bytes());
+        let model_offset = 8 + 2 + 3 * 12 + 4;
+        let exif_offset = model_offset + 8;
+        for (tag, kind, length, value) in [
+            (0x010Fu16, 2u16, 4u32, u32::from_be_bytes(*b"ACM\0")),
+            (0x0110, 2, 8, model_offset as u32),
+            (EXIF_IFD_POINTER, 4, 1, exif_offset as u32),
+        ] {
+            tiff.extend_from_slice(&tag.to_be_bytes());
+            tiff.extend_from_slice(&kind.to_be_bytes());
+            tiff.extend_from_slice(&length.to_be_bytes());
+            tiff.extend_from_slice(&value.to_be_bytes());
+        }
+        tiff.extend_from_slice(&0u32.to_be_bytes());
+        tiff.extend_from_slice(b"Model X\0");
+        tiff.extend_from_slice(&1u16.to_be_bytes());
+        tiff.extend_from_slice(&[0x88, 0x27, 0, 3, 0, 0, 0, 1, 0x0C, 0x80, 0, 0]);
+        tiff.extend_from_slice(&0u32.to_be_bytes());
+
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&((tiff.len() + 8) as u16).to_be_bytes());
+        jpeg.extend_from_slice(b"Exif\0\0");
+        jpeg.extend_from_slice(&tiff);
+        jpeg.extend_from_slice(br#"<x:xmpmeta><rdf:Description xmp:Rating="4"><dc:subject><rdf:Bag><rdf:li>harbour</rdf:li><rdf:li>dusk</rdf:li></rdf:Bag></dc:subject></rdf:Description></x:xmpmeta>"#);
+
+        let file = FileInfo { path: PathBuf::from("missing/photo.jpg"), locality_status: crate::file_locality::FileLocalityStatus::Local, estimated_download_size: None, provider: None, render_estimate: None };
+        let sections = MetadataRegistry::default().sections(&source_for(&file, &jpeg));
+        let titles: Vec<&str> = sections.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["EXIF", "XMP", "File"]);
+        assert_eq!(sections[0].entries, [
+            ("Camera make".to_string(), "ACM".to_string()),
+            ("Camera model".to_string(), "Model X".to_string()),
+            ("ISO".to_string(), "3200".to_string()),
+        ]);
+        assert_eq!(sections[1].entries, [("Keywords".to_string(), "harbour, dusk".to_string()), ("Rating".to_string(), "4".to_string())]);
+    }
+
+    #[test]
+    fn test_png_text_chunks() {
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (kind, data) in [(&b"tEXt"[..], &b"Author\0Ada"[..]), (b"iTXt", b"Comment\0\0\0en\0\0Bonjour"), (b"IDAT", b"")] {
+            png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            png.extend_from_slice(kind);
+            png.extend_from_slice(data);
+            png.extend_from_slice(&[0; 4]);
+        }
+        assert_eq!(png_text_chunks(&png), [("Author".to_string(), "Ada".to_string()), ("Comment".to_string(), "Bonjour".to_string())]);
+    }
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(format_utc(0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_utc(1_709_210_096), "2024-02-29 12:34 UTC");
+    }
+}
@@ -0,0 +1,96 @@
+//! Right-drag mouse gestures and touch swipes over the image preview
+
+use eframe::egui;
+use egui::{Pos2, Vec2};
+
+/// Horizontal distance a drag must cover to count as a gesture
+const MIN_GESTURE_DISTANCE: f32 = 80.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    SwipeLeft,
+    SwipeRight,
+}
+
+/// Classify a completed drag; mostly-vertical or short drags are ignored
+pub fn classify_gesture(delta: Vec2) -> Option<Gesture> {
+    if delta.x.abs() < MIN_GESTURE_DISTANCE || delta.y.abs() * 2.0 > delta.x.abs() {
+        return None;
+    }
+    Some(if delta.x < 0.0 { Gesture::SwipeLeft } else { Gesture::SwipeRight })
+}
+
+/// Tracks a drag from press to release
+#[derive(Debug)]
+pub struct GestureTracker {
+    button: egui::PointerButton,
+    /// Only drags made with a finger count, so mouse drags stay free for other uses
+    touch_only: bool,
+    start: Option<Pos2>,
+    completed: Option<Gesture>,
+}
+
+impl GestureTracker {
+    pub fn right_drag() -> Self {
+        Self { button: egui::PointerButton::Secondary, touch_only: false, start: None, completed: None }
+    }
+
+    pub fn touch_swipe() -> Self {
+        Self { button: egui::PointerButton::Primary, touch_only: true, start: None, completed: None }
+    }
+
+    /// Feed this frame's pointer input. Presses only start a gesture when
+    /// `over_target` is true, so drags elsewhere are left alone.
+    pub fn update(&mut self, ctx: &egui::Context, over_target: bool) {
+        let (pressed, released, position) = ctx.input(|i| (
+            i.pointer.button_pressed(self.button) && (!self.touch_only || i.any_touches()),
+            i.pointer.button_released(self.button),
+            i.pointer.interact_pos(),
+        ));
+        if pressed && over_target {
+            self.start = position;
+        }
+        if released && let (Some(start), Some(end)) = (self.start.take(), position) {
+            self.completed = classify_gesture(end - start);
+        }
+    }
+
+    /// Forget a drag in progress, e.g. when it turned into a pan
+    pub fn cancel(&mut self) {
+        self.start = None;
+    }
+
+    /// The gesture finished since the last call, if any
+    pub fn take(&mut self) -> Option<Gesture> {
+        self.completed.take()
+    }
+}
+
+/// Pan offset that keeps the point under `pivot` fixed while zooming by `zoom_delta`.
+/// `offset` is the image center's offset from the view center.
+pub fn zoom_about(offset: Vec2, view_center: Pos2, pivot: Pos2, zoom_delta: f32) -> Vec2 {
+    let from_image_center = pivot - (view_center + offset);
+    offset + from_image_center - from_image_center * zoom_delta
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_gesture() {
+        assert_eq!(classify_gesture(egui::vec2(-120.0, 10.0)), Some(Gesture::SwipeLeft));
+        assert_eq!(classify_gesture(egui::vec2(150.0, -40.0)), Some(Gesture::SwipeRight));
+        assert_eq!(classify_gesture(egui::vec2(40.0, 0.0)), None);
+        assert_eq!(classify_gesture(egui::vec2(100.0, 90.0)), None);
+    }
+
+    #[test]
+    fn test_zoom_about_keeps_pivot_fixed() {
+        let center = egui::pos2(100.0, 100.0);
+        // Zooming about the image center doesn't move it
+        assert_eq!(zoom_about(Vec2::ZERO, center, center, 2.0), Vec2::ZERO);
+        // A point 10px right of center stays put: the image shifts left by 10px
+        assert_eq!(zoom_about(Vec2::ZERO, center, egui::pos2(110.0, 100.0), 2.0), egui::vec2(-10.0, 0.0));
+    }
+}
@@ -0,0 +1,71 @@
+//! Minimap of an image larger than the view
+//!
+//! A small copy of the whole image sits in the bottom-right corner with a
+//! rectangle marking the part in view. Dragging the rectangle pans the view,
+//! and clicking elsewhere on the minimap jumps there.
+
+use eframe::egui::{self, Pos2, Rect, Vec2};
+
+/// Length of the minimap's longer side, in points
+pub const NAVIGATOR_SIZE: f32 = 160.0;
+
+/// Gap between the minimap and the edges of the view
+const MARGIN: f32 = 12.0;
+
+/// The shortest side a minimap is drawn with, so panoramas stay usable
+const MIN_SIDE: f32 = 24.0;
+
+/// Where the minimap of an `image` sized image goes in `area`
+pub fn navigator_rect(area: Rect, image: Vec2) -> Rect {
+    let scale = NAVIGATOR_SIZE / image.x.max(image.y).max(1.0);
+    let size = (image * scale).max(Vec2::splat(MIN_SIDE));
+    Rect::from_min_size(area.max - size - Vec2::splat(MARGIN), size)
+}
+
+/// The part of `image_rect` that `area` shows, drawn on the minimap at `navigator`
+pub fn viewport_rect(navigator: Rect, image_rect: Rect, area: Rect) -> Rect {
+    let to_navigator = |pos: Pos2| navigator.min + (pos - image_rect.min) / image_rect.size() * navigator.size();
+    Rect::from_min_max(to_navigator(area.min), to_navigator(area.max)).intersect(navigator)
+}
+
+/// How far the image must move for the view to center on `pointer` over the minimap
+pub fn pan_to(navigator: Rect, image_rect: Rect, area: Rect, pointer: Pos2) -> Vec2 {
+    let target = image_rect.min + (pointer - navigator.min) / navigator.size() * image_rect.size();
+    area.center() - target
+}
+
+/// How far the image moves when the viewport rectangle is dragged by `delta`
+pub fn pan_for_drag(navigator: Rect, image_rect: Rect, delta: Vec2) -> Vec2 {
+    -delta / navigator.size() * image_rect.size()
+}
+
+pub fn paint(painter: &egui::Painter, texture: egui::TextureId, navigator: Rect, viewport: Rect, visuals: &egui::Visuals) {
+    let uv = Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+    painter.rect_filled(navigator.expand(2.0), 3.0, egui::Color32::from_black_alpha(160));
+    painter.image(texture, navigator, uv, egui::Color32::WHITE);
+    painter.rect_filled(viewport, 0.0, egui::Color32::from_white_alpha(30));
+    painter.rect_stroke(viewport, 0.0, egui::Stroke::new(1.5_f32, visuals.selection.stroke.color), egui::StrokeKind::Middle);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viewport_maps_between_image_and_minimap() {
+        let area = Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(800.0, 600.0));
+        // An image four times the view's width, scrolled to its start
+        let image_rect = Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(3200.0, 600.0));
+        let navigator = navigator_rect(area, image_rect.size());
+        assert_eq!(navigator.size(), egui::vec2(160.0, 30.0));
+        assert_eq!(navigator.max, egui::pos2(788.0, 588.0));
+
+        let viewport = viewport_rect(navigator, image_rect, area);
+        assert_eq!(viewport.width(), 40.0);
+        assert_eq!(viewport.min, navigator.min);
+
+        // Clicking the minimap's middle centers the image
+        assert_eq!(pan_to(navigator, image_rect, area, navigator.center()), egui::vec2(-1200.0, 0.0));
+        assert_eq!(pan_for_drag(navigator, image_rect, egui::vec2(10.0, 0.0)), egui::vec2(-200.0, 0.0));
+    }
+}
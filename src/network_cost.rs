@@ -0,0 +1,111 @@
+//! Metered connection detection, so downloads of cloud files hold back
+//!
+//! On a metered connection every on-demand file asks before it's downloaded,
+//! whatever the saved warning policies say, and the slideshow stops
+//! downloading ahead. Windows reports the connection's cost; elsewhere it's
+//! unknown, and the setting can say what the connection is instead.
+
+#[cfg(feature = "gui")]
+use std::time::{Duration, Instant};
+
+/// How often the connection's cost is re-checked
+#[cfg(feature = "gui")]
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Whether to treat the connection as metered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeteredConnection {
+    /// As the system reports it
+    Detect,
+    Always,
+    Never,
+}
+
+impl MeteredConnection {
+    pub const ALL: [MeteredConnection; 3] = [MeteredConnection::Detect, MeteredConnection::Always, MeteredConnection::Never];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MeteredConnection::Detect => "Detect",
+            MeteredConnection::Always => "Always metered",
+            MeteredConnection::Never => "Never metered",
+        }
+    }
+
+    /// Whether the connection counts as metered, given what was `detected`
+    pub fn is_metered(&self, detected: Option<bool>) -> bool {
+        match self {
+            MeteredConnection::Detect => detected.unwrap_or(false),
+            MeteredConnection::Always => true,
+            MeteredConnection::Never => false,
+        }
+    }
+}
+
+/// Caches whether the connection is metered and refreshes it periodically
+#[cfg(feature = "gui")]
+#[derive(Debug)]
+pub struct NetworkCostMonitor {
+    detected: Option<bool>,
+    last_check: Instant,
+}
+
+#[cfg(feature = "gui")]
+impl Default for NetworkCostMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "gui")]
+impl NetworkCostMonitor {
+    pub fn new() -> Self {
+        Self {
+            detected: detect_metered(),
+            last_check: Instant::now(),
+        }
+    }
+
+    /// Whether the system reports the connection as metered, if it can tell
+    pub fn detected(&self) -> Option<bool> {
+        self.detected
+    }
+
+    /// Re-check the connection if the poll interval has elapsed
+    pub fn poll(&mut self) {
+        if self.last_check.elapsed() >= POLL_INTERVAL {
+            self.last_check = Instant::now();
+            self.detected = detect_metered();
+        }
+    }
+}
+
+/// Whether the internet connection costs by the byte, is roaming or is over
+/// its data limit, from the connection profile Windows keeps for it
+#[cfg(all(feature = "gui", windows))]
+pub fn detect_metered() -> Option<bool> {
+    use windows::Networking::Connectivity::{NetworkCostType, NetworkInformation};
+
+    let profile = NetworkInformation::GetInternetConnectionProfile().ok()?;
+    let cost = profile.GetConnectionCost().ok()?;
+    let charged = matches!(cost.NetworkCostType().ok()?, NetworkCostType::Fixed | NetworkCostType::Variable);
+    Some(charged || cost.Roaming().unwrap_or(false) || cost.OverDataLimit().unwrap_or(false))
+}
+
+#[cfg(all(feature = "gui", not(windows)))]
+pub fn detect_metered() -> Option<bool> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setting_overrides_detection() {
+        assert!(MeteredConnection::Detect.is_metered(Some(true)));
+        assert!(!MeteredConnection::Detect.is_metered(None));
+        assert!(MeteredConnection::Always.is_metered(Some(false)));
+        assert!(!MeteredConnection::Never.is_metered(Some(true)));
+    }
+}
@@ -0,0 +1,77 @@
+//! Copying text out of images
+//!
+//! With the `ocr` feature, text is recognized by running the `tesseract`
+//! executable on the displayed pixels, or on the selected region, so error
+//! messages in screenshots can be pasted elsewhere. Builds without the
+//! feature don't offer the action.
+
+#[cfg(any(feature = "ocr", test))]
+use eframe::egui::ColorImage;
+
+#[cfg(any(feature = "ocr", test))]
+use crate::color_stats::PixelRect;
+
+/// Arguments that make tesseract read an image from stdin and write plain text to stdout
+#[cfg(any(feature = "ocr", test))]
+fn tesseract_args() -> [&'static str; 4] {
+    // Page segmentation 3 finds the text blocks itself, as screenshots have no fixed layout
+    ["stdin", "stdout", "--psm", "3"]
+}
+
+/// The pixels of `image` within `region`, or all of them
+#[cfg(any(feature = "ocr", test))]
+fn to_rgba(image: &ColorImage, region: Option<PixelRect>) -> image::RgbaImage {
+    let [width, height] = image.size;
+    let region = region.unwrap_or(PixelRect { min_x: 0, min_y: 0, max_x: width, max_y: height });
+    image::RgbaImage::from_fn(region.width() as u32, region.height() as u32, |x, y| {
+        image::Rgba(image.pixels[(region.min_y + y as usize) * width + region.min_x + x as usize].to_srgba_unmultiplied())
+    })
+}
+
+/// Text recognized in `image`, or in `region` of it
+#[cfg(feature = "ocr")]
+pub fn recognize_text(image: &ColorImage, region: Option<PixelRect>) -> Result<String, String> {
+    use std::io::Write;
+
+    let mut png = Vec::new();
+    to_rgba(image, region)
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode the image for tesseract: {}", e))?;
+    let mut child = std::process::Command::new("tesseract")
+        .args(tesseract_args())
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run tesseract (is it installed?): {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(&png).map_err(|e| format!("Failed to send the image to tesseract: {}", e))?;
+    }
+    let output = child.wait_with_output().map_err(|e| format!("Failed to run tesseract: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if text.is_empty() {
+        return Err("No text found in the image".to_string());
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eframe::egui::Color32;
+
+    #[test]
+    fn test_region_is_cropped_for_tesseract() {
+        let mut image = ColorImage::new([4, 3], Color32::WHITE);
+        image.pixels[2 * 4 + 3] = Color32::RED;
+        let region = PixelRect { min_x: 2, min_y: 1, max_x: 4, max_y: 3 };
+        let cropped = to_rgba(&image, Some(region));
+        assert_eq!(cropped.dimensions(), (2, 2));
+        assert_eq!(cropped.get_pixel(1, 1).0, [255, 0, 0, 255]);
+        assert_eq!(to_rgba(&image, None).dimensions(), (4, 3));
+        assert_eq!(tesseract_args()[..2], ["stdin", "stdout"]);
+    }
+}
@@ -2,7 +2,11 @@
 
 use std::path::PathBuf;
 
-// OneDrive file status tracking
+#[cfg(feature = "graph")]
+pub mod graph;
+
+/// Download state of a file under a OneDrive sync root
+#[deprecated(since = "0.1.0", note = "use file_locality::FileLocalityStatus, which covers every cloud provider")]
 #[derive(Debug, Clone, PartialEq)]
 pub enum OneDriveFileStatus {
     /// File is fully downloaded and available locally
@@ -11,10 +15,11 @@ pub enum OneDriveFileStatus {
     OnlineOnly,
     /// File is partially downloaded
     PartiallyDownloaded,
-    /// Not a OneDrive file
+    /// Not under any cloud provider sync root
     NotOneDrive,
 }
 
+#[allow(deprecated)]
 impl OneDriveFileStatus {
     pub fn icon(&self) -> &'static str {
         match self {
@@ -28,13 +33,15 @@ impl OneDriveFileStatus {
     pub fn description(&self) -> &'static str {
         match self {
             OneDriveFileStatus::Local => "Local file (fully downloaded)",
-            OneDriveFileStatus::OnlineOnly => "OneDrive online-only file",
-            OneDriveFileStatus::PartiallyDownloaded => "OneDrive partially downloaded",
+            OneDriveFileStatus::OnlineOnly => "Cloud online-only file",
+            OneDriveFileStatus::PartiallyDownloaded => "Cloud file partially downloaded",
             OneDriveFileStatus::NotOneDrive => "Regular local file",
         }
     }
 }
 
+#[deprecated(since = "0.1.0", note = "use file_locality::FileInfo")]
+#[allow(deprecated)]
 #[derive(Debug, Clone)]
 pub struct FileInfo {
     pub path: PathBuf,
@@ -42,6 +49,7 @@ pub struct FileInfo {
     pub estimated_download_size: Option<u64>, // Size in bytes if it needs to be downloaded
 }
 
+#[allow(deprecated)]
 impl FileInfo {
     pub fn new(path: PathBuf) -> Self {
         let onedrive_status = get_onedrive_file_status(&path);
@@ -66,16 +74,17 @@ impl FileInfo {
 
 // Platform-specific OneDrive status detection
 #[cfg(windows)]
+#[deprecated(since = "0.1.0", note = "use file_locality::get_file_locality_status")]
+#[allow(deprecated)]
 pub fn get_onedrive_file_status(path: &std::path::Path) -> OneDriveFileStatus {
     use std::os::windows::fs::MetadataExt;
     
-    // Check if path is in OneDrive folder
-    let path_str = path.to_string_lossy().to_lowercase();
-    if !(path_str.contains("onedrive") || path_str.contains("sharepoint")) {
+    // Only files under a registered cloud sync root can be placeholders
+    let Some(provider) = sync_root_provider_name(path) else {
         return OneDriveFileStatus::NotOneDrive;
-    }
+    };
     
-    // For files in OneDrive paths, check file attributes
+    // For files in a sync root, check file attributes
     if let Ok(metadata) = std::fs::metadata(path) {
         let attributes = metadata.file_attributes();
         
@@ -86,7 +95,7 @@ pub fn get_onedrive_file_status(path: &std::path::Path) -> OneDriveFileStatus {
         
         // Debug output for troubleshooting
         #[cfg(debug_assertions)]
-        println!("OneDrive file check: {} - attributes: 0x{:08X}", path.display(), attributes);
+        println!("Cloud file check ({}): {} - attributes: 0x{:08X}", provider, path.display(), attributes);
         
         if (attributes & FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS) != 0 {
             #[cfg(debug_assertions)]
@@ -112,7 +121,61 @@ pub fn get_onedrive_file_status(path: &std::path::Path) -> OneDriveFileStatus {
     OneDriveFileStatus::Local
 }
 
+/// Name of the cloud provider (OneDrive, Dropbox, ...) whose sync root contains `path`
+///
+/// Uses the Cloud Files API, so renamed or localized sync root folders are
+/// recognized. Returns None if the path is not under a registered sync root.
+#[cfg(windows)]
+pub fn sync_root_provider_name(path: &std::path::Path) -> Option<String> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::CloudFilters::{
+        CF_SYNC_ROOT_INFO_PROVIDER, CF_SYNC_ROOT_PROVIDER_INFO, CfGetSyncRootInfoByPath,
+    };
+
+    let wide_path: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut info = CF_SYNC_ROOT_PROVIDER_INFO::default();
+    let mut returned_length = 0u32;
+
+    // Fails with ERROR_CLOUD_FILE_NOT_UNDER_SYNC_ROOT for ordinary files
+    unsafe {
+        CfGetSyncRootInfoByPath(
+            PCWSTR::from_raw(wide_path.as_ptr()),
+            CF_SYNC_ROOT_INFO_PROVIDER,
+            &mut info as *mut _ as *mut std::ffi::c_void,
+            std::mem::size_of::<CF_SYNC_ROOT_PROVIDER_INFO>() as u32,
+            Some(&mut returned_length),
+        )
+        .ok()?;
+    }
+
+    let name_length = info.ProviderName.iter().position(|&c| c == 0).unwrap_or(info.ProviderName.len());
+    Some(String::from_utf16_lossy(&info.ProviderName[..name_length]))
+}
+
+/// On macOS, the provider of the File Provider domain containing `path`. The
+/// system gives each registered domain its own folder under
+/// `~/Library/CloudStorage`, named "Provider-account", and keeps iCloud
+/// Drive in `~/Library/Mobile Documents`.
+#[cfg(target_os = "macos")]
+pub fn sync_root_provider_name(path: &std::path::Path) -> Option<String> {
+    let library = std::path::PathBuf::from(std::env::var_os("HOME")?).join("Library");
+    if path.starts_with(library.join("Mobile Documents")) {
+        return Some("iCloud Drive".to_string());
+    }
+    let domain = path.strip_prefix(library.join("CloudStorage")).ok()?.components().next()?;
+    let name = domain.as_os_str().to_string_lossy();
+    Some(name.split_once('-').map_or(&*name, |(provider, _)| provider).to_string())
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn sync_root_provider_name(_path: &std::path::Path) -> Option<String> {
+    None
+}
+
 #[cfg(not(windows))]
+#[deprecated(since = "0.1.0", note = "use file_locality::get_file_locality_status")]
+#[allow(deprecated)]
 pub fn get_onedrive_file_status(_path: &std::path::Path) -> OneDriveFileStatus {
     // On non-Windows platforms, assume all files are local
     OneDriveFileStatus::NotOneDrive
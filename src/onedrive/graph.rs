@@ -0,0 +1,514 @@
+//! Optional Microsoft Graph client for browsing OneDrive without hydrating files
+//!
+//! Authentication uses the OAuth 2.0 device code flow, which suits a desktop app
+//! without a redirect URI: the user opens the verification page in a browser,
+//! enters the displayed code, and the app polls until a token is issued.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+const AUTHORITY: &str = "https://login.microsoftonline.com/common/oauth2/v2.0";
+const GRAPH_ROOT: &str = "https://graph.microsoft.com/v1.0";
+const SCOPES: &str = "Files.Read offline_access";
+const MAX_THUMBNAIL_BYTES: u64 = 20 * 1024 * 1024;
+/// Added to the polling interval each time the token endpoint answers `slow_down`
+const SLOW_DOWN_STEP: Duration = Duration::from_secs(5);
+
+/// Server-side thumbnail sizes offered by Graph
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThumbnailSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl ThumbnailSize {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ThumbnailSize::Small => "small",
+            ThumbnailSize::Medium => "medium",
+            ThumbnailSize::Large => "large",
+        }
+    }
+
+    /// The smallest size whose longest side covers `pixels`
+    pub fn for_pixels(pixels: u32) -> Self {
+        // Graph's small and medium thumbnails are 96 and 176 pixels
+        match pixels {
+            0..=96 => ThumbnailSize::Small,
+            97..=176 => ThumbnailSize::Medium,
+            _ => ThumbnailSize::Large,
+        }
+    }
+}
+
+/// Instructions shown to the user while the device code login is pending
+#[derive(Debug, Clone)]
+pub struct DeviceCodePrompt {
+    pub user_code: String,
+    pub verification_uri: String,
+    pub message: String,
+    device_code: String,
+    interval: Duration,
+    expires_at: Instant,
+}
+
+#[derive(Debug, Clone)]
+struct AccessToken {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_at: Instant,
+}
+
+/// A file or folder returned by a Graph children listing
+#[derive(Debug, Clone)]
+pub struct DriveItem {
+    pub id: String,
+    pub name: String,
+    pub size: u64,
+    pub is_folder: bool,
+    pub mime_type: Option<String>,
+}
+
+impl DriveItem {
+    pub fn is_image(&self) -> bool {
+        self.mime_type.as_deref().is_some_and(|m| m.starts_with("image/"))
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            id: value.get("id")?.as_str()?.to_string(),
+            name: value.get("name")?.as_str()?.to_string(),
+            size: value.get("size").and_then(Value::as_u64).unwrap_or(0),
+            is_folder: value.get("folder").is_some(),
+            mime_type: value
+                .get("file")
+                .and_then(|f| f.get("mimeType"))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        })
+    }
+}
+
+/// Result of polling the token endpoint during device code login
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoginPoll {
+    Pending,
+    /// Still pending, and polling too often
+    SlowDown,
+    SignedIn,
+    Failed(String),
+}
+
+/// Cheaply cloneable Graph client; clones share the same sign-in state
+#[derive(Clone)]
+pub struct GraphClient {
+    client_id: String,
+    agent: ureq::Agent,
+    token: Arc<Mutex<Option<AccessToken>>>,
+}
+
+impl GraphClient {
+    /// Create a client for an Azure app registration that allows public client flows
+    pub fn new(client_id: impl Into<String>) -> Self {
+        let agent = ureq::Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(30)))
+            .http_status_as_error(false)
+            .build()
+            .into();
+        Self {
+            client_id: client_id.into(),
+            agent,
+            token: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn is_signed_in(&self) -> bool {
+        self.token.lock().unwrap().is_some()
+    }
+
+    pub fn sign_out(&self) {
+        *self.token.lock().unwrap() = None;
+    }
+
+    /// Begin device code login; show the returned prompt to the user
+    pub fn start_device_login(&self) -> Result<DeviceCodePrompt, String> {
+        let json = self.post_form(
+            &format!("{}/devicecode", AUTHORITY),
+            &[("client_id", self.client_id.as_str()), ("scope", SCOPES)],
+        )?;
+
+        let field = |name: &str| {
+            json.get(name)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| format!("Device code response missing '{}'", name))
+        };
+
+        Ok(DeviceCodePrompt {
+            user_code: field("user_code")?,
+            verification_uri: field("verification_uri")?,
+            message: field("message")?,
+            device_code: field("device_code")?,
+            interval: Duration::from_secs(json.get("interval").and_then(Value::as_u64).unwrap_or(5)),
+            expires_at: Instant::now()
+                + Duration::from_secs(json.get("expires_in").and_then(Value::as_u64).unwrap_or(900)),
+        })
+    }
+
+    /// Poll once for the token of a pending device login
+    pub fn poll_device_login(&self, prompt: &DeviceCodePrompt) -> LoginPoll {
+        if Instant::now() > prompt.expires_at {
+            return LoginPoll::Failed("Sign-in code expired".to_string());
+        }
+
+        let result = self.post_form(
+            &format!("{}/token", AUTHORITY),
+            &[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                ("client_id", self.client_id.as_str()),
+                ("device_code", prompt.device_code.as_str()),
+            ],
+        );
+
+        match result {
+            Ok(json) => match self.store_token(&json) {
+                Ok(()) => LoginPoll::SignedIn,
+                Err(e) => LoginPoll::Failed(e),
+            },
+            Err(e) if e.contains("authorization_pending") => LoginPoll::Pending,
+            Err(e) if e.contains("slow_down") => LoginPoll::SlowDown,
+            Err(e) => LoginPoll::Failed(e),
+        }
+    }
+
+    /// Block until the user completes the device login or it fails
+    pub fn wait_for_device_login(&self, prompt: &DeviceCodePrompt) -> Result<(), String> {
+        let mut interval = prompt.interval;
+        loop {
+            match self.poll_device_login(prompt) {
+                LoginPoll::Pending => std::thread::sleep(interval),
+                LoginPoll::SlowDown => {
+                    interval += SLOW_DOWN_STEP;
+                    std::thread::sleep(interval);
+                }
+                LoginPoll::SignedIn => return Ok(()),
+                LoginPoll::Failed(e) => return Err(e),
+            }
+        }
+    }
+
+    /// List the children of a drive folder given as a path relative to the drive root
+    pub fn list_folder(&self, folder_path: &str) -> Result<Vec<DriveItem>, String> {
+        let trimmed = folder_path.trim_matches('/');
+        let mut url = if trimmed.is_empty() {
+            format!("{}/me/drive/root/children", GRAPH_ROOT)
+        } else {
+            format!("{}/me/drive/root:/{}:/children", GRAPH_ROOT, encode_drive_path(trimmed))
+        };
+
+        let mut items = Vec::new();
+        loop {
+            let json = self.get_json(&url)?;
+            if let Some(values) = json.get("value").and_then(Value::as_array) {
+                items.extend(values.iter().filter_map(DriveItem::from_json));
+            }
+            match json.get("@odata.nextLink").and_then(Value::as_str) {
+                Some(next) => url = next.to_string(),
+                None => break,
+            }
+        }
+        Ok(items)
+    }
+
+    /// Fetch the server-generated thumbnail for a drive item by id
+    pub fn thumbnail_by_id(&self, item_id: &str, size: ThumbnailSize) -> Result<Vec<u8>, String> {
+        self.get_bytes(&format!(
+            "{}/me/drive/items/{}/thumbnails/0/{}/content",
+            GRAPH_ROOT,
+            item_id,
+            size.as_str()
+        ))
+    }
+
+    /// Fetch the server-generated thumbnail for a drive item by its path
+    pub fn thumbnail_by_path(&self, drive_path: &str, size: ThumbnailSize) -> Result<Vec<u8>, String> {
+        self.get_bytes(&format!(
+            "{}/me/drive/root:/{}:/thumbnails/0/{}/content",
+            GRAPH_ROOT,
+            encode_drive_path(drive_path.trim_matches('/')),
+            size.as_str()
+        ))
+    }
+
+    fn store_token(&self, json: &Value) -> Result<(), String> {
+        let access_token = json
+            .get("access_token")
+            .and_then(Value::as_str)
+            .ok_or("Token response missing 'access_token'")?
+            .to_string();
+        let expires_in = json.get("expires_in").and_then(Value::as_u64).unwrap_or(3600);
+        *self.token.lock().unwrap() = Some(AccessToken {
+            access_token,
+            refresh_token: json.get("refresh_token").and_then(Value::as_str).map(str::to_string),
+            // Refresh a minute early so in-flight requests don't race expiry
+            expires_at: Instant::now() + Duration::from_secs(expires_in.saturating_sub(60)),
+        });
+        Ok(())
+    }
+
+    fn access_token(&self) -> Result<String, String> {
+        let token = self.token.lock().unwrap().clone().ok_or("Not signed in to OneDrive")?;
+        if Instant::now() < token.expires_at {
+            return Ok(token.access_token);
+        }
+
+        let refresh_token = token.refresh_token.ok_or("OneDrive session expired, please sign in again")?;
+        let json = self.post_form(
+            &format!("{}/token", AUTHORITY),
+            &[
+                ("grant_type", "refresh_token"),
+                ("client_id", self.client_id.as_str()),
+                ("refresh_token", refresh_token.as_str()),
+                ("scope", SCOPES),
+            ],
+        )?;
+        self.store_token(&json)?;
+        self.access_token()
+    }
+
+    fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<Value, String> {
+        let mut response = self
+            .agent
+            .post(url)
+            .send_form(form.iter().copied())
+            .map_err(|e| format!("Request failed: {}", e))?;
+        let status = response.status().as_u16();
+        let json: Value = response
+            .body_mut()
+            .read_json()
+            .map_err(|e| format!("Invalid response: {}", e))?;
+        if status >= 400 {
+            let error = json.get("error").and_then(Value::as_str).unwrap_or("unknown_error");
+            return Err(format!("{} (HTTP {})", error, status));
+        }
+        Ok(json)
+    }
+
+    fn get_json(&self, url: &str) -> Result<Value, String> {
+        let mut response = self
+            .agent
+            .get(url)
+            .header("Authorization", format!("Bearer {}", self.access_token()?))
+            .call()
+            .map_err(|e| format!("Request failed: {}", e))?;
+        let status = response.status().as_u16();
+        if status >= 400 {
+            return Err(format!("Graph request failed (HTTP {})", status));
+        }
+        response
+            .body_mut()
+            .read_json()
+            .map_err(|e| format!("Invalid response: {}", e))
+    }
+
+    fn get_bytes(&self, url: &str) -> Result<Vec<u8>, String> {
+        let mut response = self
+            .agent
+            .get(url)
+            .header("Authorization", format!("Bearer {}", self.access_token()?))
+            .call()
+            .map_err(|e| format!("Request failed: {}", e))?;
+        let status = response.status().as_u16();
+        if status == 404 {
+            return Err("No thumbnail available for this item".to_string());
+        }
+        if status >= 400 {
+            return Err(format!("Graph request failed (HTTP {})", status));
+        }
+        response
+            .body_mut()
+            .with_config()
+            .limit(MAX_THUMBNAIL_BYTES)
+            .read_to_vec()
+            .map_err(|e| format!("Failed to read thumbnail: {}", e))
+    }
+}
+
+/// Results delivered by background Graph requests
+#[derive(Debug, Clone)]
+pub enum GraphTaskResult {
+    LoginPrompt(DeviceCodePrompt),
+    LoginFinished(Result<(), String>),
+    Listing {
+        folder: String,
+        result: Result<Vec<DriveItem>, String>,
+    },
+    Thumbnail {
+        key: String,
+        result: Result<Vec<u8>, String>,
+    },
+    /// Cloud thumbnail of a file in the local OneDrive folder
+    LocalThumbnail {
+        path: PathBuf,
+        result: Result<Vec<u8>, String>,
+    },
+}
+
+/// Runs Graph requests on background threads so the UI never blocks on the network
+pub struct GraphSession {
+    client: Option<GraphClient>,
+    sender: mpsc::Sender<GraphTaskResult>,
+    receiver: mpsc::Receiver<GraphTaskResult>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Default for GraphSession {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            client: None,
+            sender,
+            receiver,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+impl GraphSession {
+    pub fn is_signed_in(&self) -> bool {
+        self.client.as_ref().is_some_and(GraphClient::is_signed_in)
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.in_flight.load(Ordering::SeqCst) > 0
+    }
+
+    /// Start device code login; a `LoginPrompt` result follows, then `LoginFinished`
+    pub fn sign_in(&mut self, client_id: &str) {
+        let client = GraphClient::new(client_id);
+        self.client = Some(client.clone());
+        self.spawn(move |sender| {
+            let prompt = match client.start_device_login() {
+                Ok(prompt) => prompt,
+                Err(e) => return GraphTaskResult::LoginFinished(Err(e)),
+            };
+            let _ = sender.send(GraphTaskResult::LoginPrompt(prompt.clone()));
+            GraphTaskResult::LoginFinished(client.wait_for_device_login(&prompt))
+        });
+    }
+
+    pub fn sign_out(&mut self) {
+        if let Some(client) = self.client.take() {
+            client.sign_out();
+        }
+    }
+
+    pub fn list_folder(&self, folder: &str) {
+        let Some(client) = self.client.clone() else { return };
+        let folder = folder.to_string();
+        self.spawn(move |_| {
+            let result = client.list_folder(&folder);
+            GraphTaskResult::Listing { folder, result }
+        });
+    }
+
+    pub fn fetch_thumbnail_by_id(&self, key: &str, item_id: &str, size: ThumbnailSize) {
+        let Some(client) = self.client.clone() else { return };
+        let (key, item_id) = (key.to_string(), item_id.to_string());
+        self.spawn(move |_| GraphTaskResult::Thumbnail {
+            key,
+            result: client.thumbnail_by_id(&item_id, size),
+        });
+    }
+
+    /// Fetch the thumbnail of the local file `path`, at `drive_path` in the drive
+    pub fn fetch_local_thumbnail(&self, path: &Path, drive_path: &str, size: ThumbnailSize) {
+        let Some(client) = self.client.clone() else { return };
+        let (path, drive_path) = (path.to_path_buf(), drive_path.to_string());
+        self.spawn(move |_| GraphTaskResult::LocalThumbnail {
+            path,
+            result: client.thumbnail_by_path(&drive_path, size),
+        });
+    }
+
+    /// Drain finished background requests
+    pub fn poll(&self) -> Vec<GraphTaskResult> {
+        self.receiver.try_iter().collect()
+    }
+
+    fn spawn<F>(&self, task: F)
+    where
+        F: FnOnce(&mpsc::Sender<GraphTaskResult>) -> GraphTaskResult + Send + 'static,
+    {
+        let sender = self.sender.clone();
+        let in_flight = Arc::clone(&self.in_flight);
+        in_flight.fetch_add(1, Ordering::SeqCst);
+        std::thread::spawn(move || {
+            let result = task(&sender);
+            let _ = sender.send(result);
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+/// Map a local file inside a OneDrive sync folder to its path relative to the drive root
+///
+/// Uses the `OneDrive`, `OneDriveConsumer` and `OneDriveCommercial` environment
+/// variables that the Windows client sets for the signed-in user.
+pub fn drive_path_for_local_file(path: &Path) -> Option<String> {
+    let absolute = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    ["OneDrive", "OneDriveConsumer", "OneDriveCommercial"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .map(|root| std::fs::canonicalize(&root).unwrap_or_else(|_| root.into()))
+        .find_map(|root| {
+            let relative = absolute.strip_prefix(&root).ok()?;
+            let parts: Vec<String> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect();
+            Some(parts.join("/"))
+        })
+}
+
+/// Percent-encode a drive path for use inside a `root:/path:` segment
+fn encode_drive_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_drive_path() {
+        assert_eq!(encode_drive_path("Pictures/My Trip/img #1.jpg"), "Pictures/My%20Trip/img%20%231.jpg");
+        assert_eq!(encode_drive_path("café.png"), "caf%C3%A9.png");
+    }
+
+    #[test]
+    fn test_drive_item_from_json() {
+        let json: Value = serde_json::from_str(
+            r#"{"id":"ABC","name":"photo.jpg","size":1234,"file":{"mimeType":"image/jpeg"}}"#,
+        )
+        .unwrap();
+        let item = DriveItem::from_json(&json).unwrap();
+        assert_eq!(item.id, "ABC");
+        assert!(item.is_image());
+        assert!(!item.is_folder);
+    }
+}
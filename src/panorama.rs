@@ -0,0 +1,73 @@
+//! Automatic horizontal scrolling across wide panoramas
+//!
+//! The panorama is shown at fit-height zoom and the view pans from one end to
+//! the other, reversing direction at each end so long stitched images can be
+//! reviewed without dragging.
+
+/// Slowest and fastest scroll speeds, in screen points per second
+pub const MIN_SCROLL_SPEED: f32 = 10.0;
+pub const MAX_SCROLL_SPEED: f32 = 600.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PanoramaScroll {
+    active: bool,
+    /// 1.0 while the view moves right (towards the image's right edge), -1.0 while it moves left
+    direction: f32,
+}
+
+impl Default for PanoramaScroll {
+    fn default() -> Self {
+        Self { active: false, direction: 1.0 }
+    }
+}
+
+impl PanoramaScroll {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn start(&mut self) {
+        self.active = true;
+        self.direction = 1.0;
+    }
+
+    pub fn stop(&mut self) {
+        self.active = false;
+    }
+
+    /// Horizontal pan offset after `dt` seconds. The pan ranges from
+    /// `overflow` (left edge in view) to `-overflow` (right edge in view).
+    pub fn step(&mut self, pan_x: f32, overflow: f32, speed: f32, dt: f32) -> f32 {
+        if overflow <= 0.0 {
+            return 0.0;
+        }
+        let pan_x = pan_x - self.direction * speed * dt;
+        if pan_x <= -overflow {
+            self.direction = -1.0;
+            -overflow
+        } else if pan_x >= overflow {
+            self.direction = 1.0;
+            overflow
+        } else {
+            pan_x
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_reverses_at_the_ends() {
+        let mut scroll = PanoramaScroll::default();
+        scroll.start();
+        assert_eq!(scroll.step(100.0, 100.0, 50.0, 1.0), 50.0);
+        assert_eq!(scroll.step(-90.0, 100.0, 50.0, 1.0), -100.0);
+        // Heading back towards the left edge
+        assert_eq!(scroll.step(-100.0, 100.0, 50.0, 1.0), -50.0);
+        assert_eq!(scroll.step(95.0, 100.0, 50.0, 1.0), 100.0);
+        assert_eq!(scroll.step(100.0, 100.0, 50.0, 1.0), 50.0);
+        assert_eq!(scroll.step(30.0, 0.0, 50.0, 1.0), 0.0);
+    }
+}
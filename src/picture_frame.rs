@@ -0,0 +1,94 @@
+//! Compact always-on-top window showing only the image
+//!
+//! Picture frame mode hides the menus, toolbar, panels and window decorations
+//! and keeps the window above others, for keeping a reference image in view
+//! while working in another program. Without decorations, dragging the image
+//! moves the window and dragging its edges resizes it.
+
+use eframe::egui::{self, CursorIcon, Pos2, Rect, Vec2};
+use eframe::egui::viewport::ResizeDirection;
+
+/// Longer side of the window when the mode starts, in points
+pub const FRAME_SIZE: f32 = 480.0;
+
+/// How close to the window's edge the pointer resizes instead of moving it
+const EDGE_GRAB: f32 = 6.0;
+
+/// Smallest window side, so the frame can't be resized away
+const MIN_SIDE: f32 = 64.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PictureFrame {
+    /// Window size to go back to when the mode ends
+    pub restore_size: Option<Vec2>,
+}
+
+/// Window size showing an `image` sized image whole, its longer side [`FRAME_SIZE`]
+pub fn frame_size(image: Vec2) -> Vec2 {
+    let scale = FRAME_SIZE / image.x.max(image.y).max(1.0);
+    (image * scale).max(Vec2::splat(MIN_SIDE))
+}
+
+/// The edge or corner of `window` that `pointer` is on
+pub fn resize_direction(window: Rect, pointer: Pos2) -> Option<ResizeDirection> {
+    let west = pointer.x - window.left() < EDGE_GRAB;
+    let east = window.right() - pointer.x < EDGE_GRAB;
+    let north = pointer.y - window.top() < EDGE_GRAB;
+    let south = window.bottom() - pointer.y < EDGE_GRAB;
+    Some(match (north, south, west, east) {
+        (true, _, true, _) => ResizeDirection::NorthWest,
+        (true, _, _, true) => ResizeDirection::NorthEast,
+        (_, true, true, _) => ResizeDirection::SouthWest,
+        (_, true, _, true) => ResizeDirection::SouthEast,
+        (true, ..) => ResizeDirection::North,
+        (_, true, ..) => ResizeDirection::South,
+        (_, _, true, _) => ResizeDirection::West,
+        (_, _, _, true) => ResizeDirection::East,
+        _ => return None,
+    })
+}
+
+pub fn resize_cursor(direction: ResizeDirection) -> CursorIcon {
+    match direction {
+        ResizeDirection::North => CursorIcon::ResizeNorth,
+        ResizeDirection::South => CursorIcon::ResizeSouth,
+        ResizeDirection::East => CursorIcon::ResizeEast,
+        ResizeDirection::West => CursorIcon::ResizeWest,
+        ResizeDirection::NorthEast => CursorIcon::ResizeNorthEast,
+        ResizeDirection::SouthEast => CursorIcon::ResizeSouthEast,
+        ResizeDirection::NorthWest => CursorIcon::ResizeNorthWest,
+        ResizeDirection::SouthWest => CursorIcon::ResizeSouthWest,
+    }
+}
+
+/// Show the resize cursor near the window's edges and start resizing when
+/// one is pressed; returns whether the pointer is on an edge
+pub fn handle_edge_resize(ctx: &egui::Context) -> bool {
+    let window = ctx.screen_rect();
+    let (pointer, pressed) = ctx.input(|i| (i.pointer.hover_pos(), i.pointer.primary_pressed()));
+    let Some(direction) = pointer.and_then(|pointer| resize_direction(window, pointer)) else {
+        return false;
+    };
+    ctx.set_cursor_icon(resize_cursor(direction));
+    if pressed {
+        ctx.send_viewport_cmd(egui::ViewportCommand::BeginResize(direction));
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_size_and_edges() {
+        assert_eq!(frame_size(egui::vec2(4000.0, 3000.0)), egui::vec2(480.0, 360.0));
+        assert_eq!(frame_size(egui::vec2(100.0, 5000.0)), egui::vec2(64.0, 480.0));
+
+        let window = Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(400.0, 300.0));
+        assert_eq!(resize_direction(window, egui::pos2(200.0, 150.0)), None);
+        assert_eq!(resize_direction(window, egui::pos2(2.0, 150.0)), Some(ResizeDirection::West));
+        assert_eq!(resize_direction(window, egui::pos2(398.0, 298.0)), Some(ResizeDirection::SouthEast));
+        assert_eq!(resize_direction(window, egui::pos2(200.0, 1.0)), Some(ResizeDirection::North));
+    }
+}
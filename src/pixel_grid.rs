@@ -0,0 +1,125 @@
+//! Pixel grid, rulers and crosshair for inspecting pixel art and UI assets
+//!
+//! Once each image pixel covers [`GRID_MIN_SCALE`] screen pixels, lines are
+//! drawn between the pixels. Rulers along the top and left of the view count
+//! image pixels, and a crosshair follows the pointer with its coordinates.
+
+use eframe::egui::{self, Color32, Pos2, Rect, Shape, Stroke};
+
+/// Screen pixels per image pixel (800%) from which the grid is drawn
+pub const GRID_MIN_SCALE: f32 = 8.0;
+
+/// Thickness of the rulers, in points
+pub const RULER_SIZE: f32 = 18.0;
+
+/// Closest that labeled ruler ticks get, in points
+const MIN_LABEL_SPACING: f32 = 50.0;
+
+/// Lines between the pixels of an image drawn at `image_rect`, `scale` points
+/// per pixel, inside `clip`
+pub fn grid_shapes(image_rect: Rect, scale: f32, clip: Rect, stroke: Stroke) -> Vec<Shape> {
+    let visible = image_rect.intersect(clip);
+    if !visible.is_positive() || scale <= 0.0 {
+        return Vec::new();
+    }
+    let lines = |start: f32, from: f32, to: f32| {
+        let first = ((from - start) / scale).ceil() as i64;
+        let last = ((to - start) / scale).floor() as i64;
+        (first..=last).map(move |n| start + n as f32 * scale)
+    };
+    let vertical = lines(image_rect.left(), visible.left(), visible.right())
+        .map(|x| Shape::line_segment([egui::pos2(x, visible.top()), egui::pos2(x, visible.bottom())], stroke));
+    let horizontal = lines(image_rect.top(), visible.top(), visible.bottom())
+        .map(|y| Shape::line_segment([egui::pos2(visible.left(), y), egui::pos2(visible.right(), y)], stroke));
+    vertical.chain(horizontal).collect()
+}
+
+/// Image pixels between labeled ticks: 1, 2, 5, 10, 20, 50, … whichever
+/// first puts the labels far enough apart
+pub fn label_step(scale: f32) -> u32 {
+    let mut magnitude = 1;
+    loop {
+        for step in [magnitude, magnitude * 2, magnitude * 5] {
+            if step as f32 * scale >= MIN_LABEL_SPACING || step >= 1_000_000 {
+                return step;
+            }
+        }
+        magnitude *= 10;
+    }
+}
+
+/// Pixel numbers to label along a ruler from `from` to `to` (screen
+/// coordinates) for an image starting at `start`, with their positions
+pub fn ruler_labels(start: f32, scale: f32, from: f32, to: f32) -> Vec<(f32, u32)> {
+    if scale <= 0.0 {
+        return Vec::new();
+    }
+    let step = label_step(scale) as i64;
+    let first = (((from - start) / scale / step as f32).ceil() as i64).max(0);
+    let last = ((to - start) / scale / step as f32).floor() as i64;
+    (first..=last).map(|n| (start + (n * step) as f32 * scale, (n * step) as u32)).collect()
+}
+
+/// Rulers along the top and left of `area` for an image at `image_rect`
+pub fn paint_rulers(painter: &egui::Painter, area: Rect, image_rect: Rect, scale: f32, visuals: &egui::Visuals) {
+    let font = egui::FontId::monospace(10.0);
+    let text_color = visuals.text_color();
+    let stroke = Stroke::new(1.0_f32, visuals.weak_text_color());
+    let top = Rect::from_min_max(area.min, egui::pos2(area.right(), area.top() + RULER_SIZE));
+    let left = Rect::from_min_max(area.min, egui::pos2(area.left() + RULER_SIZE, area.bottom()));
+    painter.rect_filled(top, 0.0, visuals.panel_fill);
+    painter.rect_filled(left, 0.0, visuals.panel_fill);
+    for (x, pixel) in ruler_labels(image_rect.left(), scale, top.left() + RULER_SIZE, top.right()) {
+        painter.line_segment([egui::pos2(x, top.top()), egui::pos2(x, top.bottom())], stroke);
+        painter.text(egui::pos2(x + 2.0, top.top()), egui::Align2::LEFT_TOP, pixel.to_string(), font.clone(), text_color);
+    }
+    for (y, pixel) in ruler_labels(image_rect.top(), scale, left.top() + RULER_SIZE, left.bottom()) {
+        painter.line_segment([egui::pos2(left.left(), y), egui::pos2(left.right(), y)], stroke);
+        painter.text(egui::pos2(left.left() + 1.0, y + 2.0), egui::Align2::LEFT_TOP, pixel.to_string(), font.clone(), text_color);
+    }
+}
+
+/// The image pixel under `pointer`, if it's over the image
+pub fn pixel_at(pointer: Pos2, image_rect: Rect, scale: f32) -> Option<(u32, u32)> {
+    if !image_rect.contains(pointer) || scale <= 0.0 {
+        return None;
+    }
+    let offset = (pointer - image_rect.min) / scale;
+    Some((offset.x as u32, offset.y as u32))
+}
+
+/// Guides through `pointer` across `area`, with the pixel coordinates beside it
+pub fn paint_crosshair(painter: &egui::Painter, area: Rect, pointer: Pos2, image_rect: Rect, scale: f32) {
+    // Dark under light, so the guides show on any image
+    for (width, color) in [(3.0_f32, Color32::from_black_alpha(120)), (1.0_f32, Color32::from_white_alpha(200))] {
+        let stroke = Stroke::new(width, color);
+        painter.line_segment([egui::pos2(area.left(), pointer.y), egui::pos2(area.right(), pointer.y)], stroke);
+        painter.line_segment([egui::pos2(pointer.x, area.top()), egui::pos2(pointer.x, area.bottom())], stroke);
+    }
+    if let Some((x, y)) = pixel_at(pointer, image_rect, scale) {
+        let text = format!("{}, {}", x, y);
+        let position = pointer + egui::vec2(8.0, 8.0);
+        let galley = painter.layout_no_wrap(text, egui::FontId::monospace(11.0), Color32::WHITE);
+        painter.rect_filled(Rect::from_min_size(position, galley.size()).expand(2.0), 2.0, Color32::from_black_alpha(180));
+        painter.galley(position, galley, Color32::WHITE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_and_ruler_positions() {
+        let image = Rect::from_min_size(egui::pos2(-40.0, 0.0), egui::vec2(160.0, 80.0));
+        let clip = Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(100.0, 100.0));
+        let shapes = grid_shapes(image, 10.0, clip, Stroke::new(1.0_f32, Color32::GRAY));
+        // x = 0, 10, …, 100 and y = 0, 10, …, 80
+        assert_eq!(shapes.len(), 11 + 9);
+
+        assert_eq!([1.0, 8.0, 20.0, 60.0].map(label_step), [50, 10, 5, 1]);
+        assert_eq!(ruler_labels(-40.0, 10.0, 0.0, 100.0), vec![(10.0, 5), (60.0, 10)]);
+        assert_eq!(pixel_at(egui::pos2(5.0, 15.0), image, 10.0), Some((4, 1)));
+        assert_eq!(pixel_at(egui::pos2(5.0, 90.0), image, 10.0), None);
+    }
+}
@@ -0,0 +1,384 @@
+//! Power source detection and battery-aware background work limits
+
+#[cfg(feature = "gui")]
+use std::sync::OnceLock;
+#[cfg(feature = "gui")]
+use std::time::Instant;
+use std::time::{Duration, SystemTime};
+
+/// How often the power source is re-checked while the app is running
+#[cfg(feature = "gui")]
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Wall-clock gap between polls taken to mean the machine was asleep
+const SLEEP_GAP: Duration = Duration::from_secs(20);
+
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerSource {
+    Ac,
+    Battery,
+    /// Could not be determined (treated like AC power)
+    Unknown,
+}
+
+#[cfg(feature = "gui")]
+impl PowerSource {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            PowerSource::Ac => "🔌",
+            PowerSource::Battery => "🔋",
+            PowerSource::Unknown => "❓",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            PowerSource::Ac => "On AC power",
+            PowerSource::Battery => "On battery power",
+            PowerSource::Unknown => "Power source unknown",
+        }
+    }
+}
+
+/// Limits for background work such as prefetching, decoding and thumbnail generation
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackgroundWorkBudget {
+    /// Whether the slideshow downloads upcoming images ahead
+    pub prefetch_enabled: bool,
+    /// Background jobs run at once
+    pub decode_threads: usize,
+    /// Delay before saving a viewed image's preview, so quick scrolling doesn't waste work
+    pub thumbnail_delay: Duration,
+}
+
+#[cfg(feature = "gui")]
+impl BackgroundWorkBudget {
+    pub fn full() -> Self {
+        // Asked once; it reads cgroup limits on Linux, and this runs every frame
+        static CORES: OnceLock<usize> = OnceLock::new();
+        let cores = *CORES.get_or_init(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4));
+        Self {
+            prefetch_enabled: true,
+            decode_threads: cores.saturating_sub(1).max(1),
+            thumbnail_delay: Duration::ZERO,
+        }
+    }
+
+    pub fn battery_saver() -> Self {
+        Self {
+            prefetch_enabled: false,
+            decode_threads: 1,
+            thumbnail_delay: Duration::from_millis(750),
+        }
+    }
+
+    pub fn for_power_source(source: PowerSource, reduce_on_battery: bool) -> Self {
+        if reduce_on_battery && source == PowerSource::Battery {
+            Self::battery_saver()
+        } else {
+            Self::full()
+        }
+    }
+
+    /// The budget with at most `limit` decode threads
+    pub fn with_thread_limit(mut self, limit: Option<usize>) -> Self {
+        if let Some(limit) = limit {
+            self.decode_threads = self.decode_threads.min(limit.max(1));
+        }
+        self
+    }
+}
+
+/// Caches the detected power source and refreshes it periodically
+#[cfg(feature = "gui")]
+#[derive(Debug)]
+pub struct PowerMonitor {
+    source: PowerSource,
+    last_check: Instant,
+}
+
+#[cfg(feature = "gui")]
+impl Default for PowerMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "gui")]
+impl PowerMonitor {
+    pub fn new() -> Self {
+        Self {
+            source: detect_power_source(),
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn source(&self) -> PowerSource {
+        self.source
+    }
+
+    /// Re-detect the power source if the poll interval has elapsed.
+    /// Returns true if the power source changed.
+    pub fn poll(&mut self) -> bool {
+        if self.last_check.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.last_check = Instant::now();
+        let source = detect_power_source();
+        let changed = source != self.source;
+        self.source = source;
+        changed
+    }
+}
+
+/// Notices the machine waking from sleep or hibernation by the wall clock
+/// jumping between polls, for systems that don't report it through
+/// [`ResumeNotifications`]. Only meaningful while something polls several
+/// times a second, as the download worker does between reads.
+#[derive(Debug, Default)]
+pub struct SleepDetector {
+    last_poll: Option<SystemTime>,
+}
+
+impl SleepDetector {
+    /// Record a poll at `now`; returns how long the machine slept if it just woke
+    pub fn observe(&mut self, now: SystemTime) -> Option<Duration> {
+        let gap = self.last_poll.replace(now).and_then(|last| now.duration_since(last).ok());
+        gap.filter(|gap| *gap >= SLEEP_GAP)
+    }
+
+    /// Forget the last poll, for when nothing keeps the app polling
+    pub fn reset(&mut self) {
+        self.last_poll = None;
+    }
+}
+
+/// Suspend/resume notifications from the system, delivered for as long as
+/// this is kept. On resume the callback gets how long the machine slept.
+pub struct ResumeNotifications {
+    #[cfg(windows)]
+    handle: *mut std::ffi::c_void,
+    #[cfg(windows)]
+    subscription: *mut windows::Win32::System::Power::DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS,
+}
+
+// The handle and subscription are only touched again when unregistering
+#[cfg(windows)]
+unsafe impl Send for ResumeNotifications {}
+
+/// State the system hands back to [`power_changed`]
+#[cfg(windows)]
+struct ResumeContext {
+    suspended_at: std::sync::Mutex<Option<SystemTime>>,
+    on_resume: Box<dyn Fn(Duration) + Send + Sync>,
+}
+
+impl ResumeNotifications {
+    /// Register `on_resume`, or `None` where the system doesn't report
+    /// suspend and resume (fall back to a [`SleepDetector`])
+    #[cfg(windows)]
+    pub fn subscribe(on_resume: impl Fn(Duration) + Send + Sync + 'static) -> Option<Self> {
+        use windows::Win32::Foundation::{ERROR_SUCCESS, HANDLE};
+        use windows::Win32::System::Power::{DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS, PowerRegisterSuspendResumeNotification};
+        use windows::Win32::UI::WindowsAndMessaging::DEVICE_NOTIFY_CALLBACK;
+
+        let context = Box::into_raw(Box::new(ResumeContext {
+            suspended_at: std::sync::Mutex::new(None),
+            on_resume: Box::new(on_resume),
+        }));
+        let subscription = Box::into_raw(Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+            Callback: Some(power_changed),
+            Context: context.cast(),
+        }));
+        let mut handle = std::ptr::null_mut();
+        let result = unsafe { PowerRegisterSuspendResumeNotification(DEVICE_NOTIFY_CALLBACK, HANDLE(subscription.cast()), &mut handle) };
+        if result != ERROR_SUCCESS {
+            unsafe {
+                drop(Box::from_raw(subscription));
+                drop(Box::from_raw(context));
+            }
+            return None;
+        }
+        Some(Self { handle, subscription })
+    }
+
+    #[cfg(not(windows))]
+    pub fn subscribe(_on_resume: impl Fn(Duration) + Send + Sync + 'static) -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(windows)]
+impl Drop for ResumeNotifications {
+    fn drop(&mut self) {
+        use windows::Win32::System::Power::{HPOWERNOTIFY, PowerUnregisterSuspendResumeNotification};
+
+        // Unregistering waits for a running callback, so the context can go afterwards
+        unsafe {
+            let _ = PowerUnregisterSuspendResumeNotification(HPOWERNOTIFY(self.handle as isize));
+            let subscription = Box::from_raw(self.subscription);
+            drop(Box::from_raw(subscription.Context.cast::<ResumeContext>()));
+        }
+    }
+}
+
+/// Called by the system on a thread of its own for each power event
+#[cfg(windows)]
+unsafe extern "system" fn power_changed(context: *const std::ffi::c_void, kind: u32, _setting: *const std::ffi::c_void) -> u32 {
+    use windows::Win32::UI::WindowsAndMessaging::{PBT_APMRESUMEAUTOMATIC, PBT_APMSUSPEND};
+
+    let context = unsafe { &*context.cast::<ResumeContext>() };
+    match kind {
+        PBT_APMSUSPEND => *context.suspended_at.lock().unwrap() = Some(SystemTime::now()),
+        // Sent on every resume, whether or not a user is present
+        PBT_APMRESUMEAUTOMATIC => {
+            let slept = context.suspended_at.lock().unwrap().take()
+                .and_then(|at| SystemTime::now().duration_since(at).ok())
+                .unwrap_or_default();
+            (context.on_resume)(slept);
+        }
+        _ => {}
+    }
+    0
+}
+
+#[cfg(all(feature = "gui", windows))]
+pub fn detect_power_source() -> PowerSource {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status = SYSTEM_POWER_STATUS::default();
+    if unsafe { GetSystemPowerStatus(&mut status) }.is_err() {
+        return PowerSource::Unknown;
+    }
+    match status.ACLineStatus {
+        0 => PowerSource::Battery,
+        1 => PowerSource::Ac,
+        _ => PowerSource::Unknown,
+    }
+}
+
+#[cfg(all(feature = "gui", target_os = "macos"))]
+pub fn detect_power_source() -> PowerSource {
+    // First line of `pmset -g batt` reads "Now drawing from 'AC Power'" or "'Battery Power'"
+    match std::process::Command::new("pmset").args(["-g", "batt"]).output() {
+        Ok(output) => {
+            let text = String::from_utf8_lossy(&output.stdout);
+            if text.contains("'Battery Power'") {
+                PowerSource::Battery
+            } else if text.contains("'AC Power'") {
+                PowerSource::Ac
+            } else {
+                PowerSource::Unknown
+            }
+        }
+        Err(_) => PowerSource::Unknown,
+    }
+}
+
+#[cfg(all(feature = "gui", not(any(windows, target_os = "macos"))))]
+pub fn detect_power_source() -> PowerSource {
+    let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+        return PowerSource::Unknown;
+    };
+    let read = |dir: &std::path::Path, name: &str| {
+        std::fs::read_to_string(dir.join(name)).map(|s| s.trim().to_string()).unwrap_or_default()
+    };
+    let supplies: Vec<PowerSupply> = entries
+        .flatten()
+        .map(|entry| {
+            let dir = entry.path();
+            PowerSupply {
+                kind: read(&dir, "type"),
+                online: read(&dir, "online") == "1",
+                status: read(&dir, "status"),
+            }
+        })
+        .collect();
+    classify_power_supplies(&supplies)
+}
+
+/// A Linux `/sys/class/power_supply` entry
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone)]
+pub struct PowerSupply {
+    /// "Mains", "Battery", "USB", ...
+    pub kind: String,
+    pub online: bool,
+    /// Battery status such as "Charging" or "Discharging"
+    pub status: String,
+}
+
+#[cfg(feature = "gui")]
+pub fn classify_power_supplies(supplies: &[PowerSupply]) -> PowerSource {
+    let batteries: Vec<&PowerSupply> = supplies.iter().filter(|s| s.kind == "Battery").collect();
+    if batteries.is_empty() {
+        // Desktops without a battery are always on AC power
+        return if supplies.is_empty() { PowerSource::Unknown } else { PowerSource::Ac };
+    }
+    if supplies.iter().any(|s| s.kind != "Battery" && s.online) {
+        return PowerSource::Ac;
+    }
+    if batteries.iter().any(|b| b.status == "Discharging") {
+        PowerSource::Battery
+    } else if supplies.iter().any(|s| s.kind != "Battery") {
+        // Adapters reported but none online
+        PowerSource::Battery
+    } else {
+        PowerSource::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "gui")]
+    fn supply(kind: &str, online: bool, status: &str) -> PowerSupply {
+        PowerSupply {
+            kind: kind.to_string(),
+            online,
+            status: status.to_string(),
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_classify_power_supplies() {
+        assert_eq!(classify_power_supplies(&[]), PowerSource::Unknown);
+        assert_eq!(classify_power_supplies(&[supply("Mains", true, "")]), PowerSource::Ac);
+        assert_eq!(
+            classify_power_supplies(&[supply("Mains", true, ""), supply("Battery", false, "Charging")]),
+            PowerSource::Ac
+        );
+        assert_eq!(
+            classify_power_supplies(&[supply("Mains", false, ""), supply("Battery", false, "Full")]),
+            PowerSource::Battery
+        );
+        assert_eq!(classify_power_supplies(&[supply("Battery", false, "Discharging")]), PowerSource::Battery);
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_budget_only_reduced_on_battery_when_enabled() {
+        assert_eq!(BackgroundWorkBudget::for_power_source(PowerSource::Battery, true), BackgroundWorkBudget::battery_saver());
+        assert_eq!(BackgroundWorkBudget::for_power_source(PowerSource::Battery, false), BackgroundWorkBudget::full());
+        assert_eq!(BackgroundWorkBudget::for_power_source(PowerSource::Ac, true), BackgroundWorkBudget::full());
+        assert!(!BackgroundWorkBudget::battery_saver().prefetch_enabled);
+    }
+
+    #[test]
+    fn test_sleep_detected_from_wall_clock_gap() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let mut detector = SleepDetector::default();
+        assert_eq!(detector.observe(start), None);
+        assert_eq!(detector.observe(start + Duration::from_millis(200)), None);
+        let woke = start + Duration::from_secs(3600);
+        assert!(detector.observe(woke).is_some_and(|slept| slept >= Duration::from_secs(3599)));
+        // A clock set backwards isn't sleep
+        assert_eq!(detector.observe(start), None);
+        detector.reset();
+        assert_eq!(detector.observe(woke), None);
+    }
+}
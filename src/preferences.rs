@@ -0,0 +1,100 @@
+//! Small preferences kept between sessions
+//!
+//! Stored as `key = value` lines in `preferences.txt` inside the config
+//! directory. Unknown keys are kept so older and newer builds can share the file.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::settings::config_dir;
+
+const PREFERENCES_FILE: &str = "preferences.txt";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Preferences {
+    values: BTreeMap<String, String>,
+}
+
+impl Preferences {
+    /// Value stored under `key`, if present and parseable
+    pub fn get<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.values.get(key).and_then(|value| value.parse().ok())
+    }
+
+    pub fn set(&mut self, key: &str, value: impl Display) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.values.remove(key);
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let values = text
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .filter(|(key, _)| !key.is_empty())
+            .collect();
+        Self { values }
+    }
+
+    pub fn to_text(&self) -> String {
+        self.values.iter().map(|(key, value)| format!("{} = {}\n", key, value)).collect()
+    }
+
+    pub fn load_from(file: &Path) -> Result<Self, String> {
+        match std::fs::read_to_string(file) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read preferences: {}", e)),
+        }
+    }
+
+    pub fn save_to(&self, file: &Path) -> Result<(), String> {
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        std::fs::write(file, self.to_text()).map_err(|e| format!("Failed to save preferences: {}", e))
+    }
+
+    /// Preferences saved by earlier sessions, if any
+    pub fn load() -> Self {
+        default_file()
+            .and_then(|file| Self::load_from(&file).map_err(|e| eprintln!("Warning: {}", e)).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = default_file().ok_or("No config directory available")?;
+        self.save_to(&file)
+    }
+}
+
+fn default_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(PREFERENCES_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_parse_and_round_trip() {
+        let preferences = Preferences::parse("# comment\nui_scale = 1.5\nbroken line\nname=a = b\n");
+        assert_eq!(preferences.get::<f32>("ui_scale"), Some(1.5));
+        assert_eq!(preferences.get::<String>("name").as_deref(), Some("a = b"));
+        assert_eq!(preferences.get::<u32>("ui_scale"), None);
+
+        let dir = TempDir::new("preferences");
+        let file = dir.join(PREFERENCES_FILE);
+        let mut preferences = preferences;
+        preferences.set("ui_scale", 1.25);
+        preferences.save_to(&file).unwrap();
+        assert_eq!(Preferences::load_from(&file).unwrap().get::<f32>("ui_scale"), Some(1.25));
+    }
+}
@@ -0,0 +1,66 @@
+//! The stable public API
+//!
+//! Items re-exported here follow semver: removing or changing one is a
+//! breaking change and needs a major version bump. `tests/public_api.rs`
+//! compares this surface against a snapshot, so changes to it are always
+//! deliberate. The remaining public modules exist for the application binary
+//! and may change in any release.
+//!
+//! ```no_run
+//! use image_previewer::prelude::*;
+//!
+//! let settings = ImageLoadingSettings::default().max_file_size_mb(Some(64));
+//! let path = std::path::PathBuf::from("photo.jpg");
+//! if is_file_immediately_available(&path) {
+//!     let image = decode(&path, &settings, false).unwrap();
+//!     println!("{}x{}", image.width, image.height);
+//! }
+//! ```
+//!
+//! Decoding returns [`DecodedImage`] buffers and needs no egui context; the
+//! `egui_adapter` functions turn them into textures for egui programs. They,
+//! the texture-loading helpers and [`ImageViewerApp`] need the default `gui`
+//! feature; without it the crate builds without eframe and egui.
+
+// Settings and the types of their fields
+pub use crate::settings::{ColorPalette, DEFAULT_SUPPORTED_FORMATS, FilenameTruncationStyle, ImageLoadingSettings, ResizeFilter, SvgColorMapping};
+pub use crate::backdrop::PreviewBackground;
+pub use crate::fit_mode::{FitMode, OpeningZoom};
+pub use crate::network_cost::MeteredConnection;
+pub use crate::slideshow::SkipRules;
+pub use crate::soft_proof::SoftProofOptions;
+pub use crate::theme::{Theme, ThemePreset};
+pub use crate::toolbar::ToolbarButton;
+
+// Decoding
+pub use crate::decoded_image::DecodedImage;
+pub use crate::image_processing::{
+    MAX_SVG_EXPORT_SIZE, RenderEstimate, SvgExportOptions, decode, decode_raster, decode_svg, export_svg_as_png,
+    recolor_svg, scale_image_if_needed, should_skip_large_file, svg_intrinsic_size,
+};
+#[cfg(feature = "gui")]
+pub use crate::image_processing::{decode_raster_image, decode_svg_image, load_raster_image, load_svg_image};
+
+// Decoders for other formats
+pub use crate::format_decoders::{FormatDecoder, register_decoder, registered_extensions};
+
+// Showing decoded images with egui
+#[cfg(feature = "gui")]
+pub use crate::egui_adapter::{from_color_image, load_texture, to_color_image};
+
+// Cloud file locality
+pub use crate::file_locality::{
+    CloudProvider, FileInfo, FileLocalityStatus, detect_cloud_provider, get_file_locality_status,
+    is_file_immediately_available, will_file_access_trigger_download,
+};
+
+// Benchmarking
+pub use crate::benchmark::{
+    BenchmarkLimits, BenchmarkResult, HardwareFingerprint, ImageCharacteristics, PerformanceProfile,
+    SystemPerformanceCategory, get_performance_baseline, run_simple_cpu_benchmark,
+};
+#[cfg(feature = "gui")]
+pub use crate::benchmark::benchmark_image;
+
+#[cfg(feature = "gui")]
+pub use crate::app::ImageViewerApp;
@@ -0,0 +1,214 @@
+//! Small previews of viewed cloud files, kept between sessions
+//!
+//! Once a synced file is back to online-only, its pixels can't be read
+//! without downloading it again. The preview saved the last time it was
+//! viewed lets the placeholder show what the file looks like meanwhile.
+//!
+//! The [`PreviewStore`] keeps the folder under a byte limit, deleting the
+//! least recently used previews, and is listed in the Caches settings section.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use eframe::egui::ColorImage;
+use image::RgbaImage;
+
+use crate::cache::ManagedCache;
+use crate::settings::config_dir;
+
+const PREVIEWS_FOLDER: &str = "previews";
+
+/// Longer side of a saved preview, in pixels
+pub const PREVIEW_SIZE: u32 = 256;
+
+pub fn previews_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(PREVIEWS_FOLDER))
+}
+
+/// Where the preview of `path` is kept in `dir`, named by a hash of the path
+fn preview_file(dir: &Path, path: &Path) -> PathBuf {
+    // FNV-1a, which unlike the std hasher stays the same between builds
+    let hash = path.to_string_lossy().bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    dir.join(format!("{:016x}.png", hash))
+}
+
+/// Whether `dir` has no preview of `path`, or only one older than the file
+fn needs_preview(dir: &Path, path: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(&preview_file(dir, path)), modified(path)) {
+        (Some(saved), Some(changed)) => saved < changed,
+        (saved, _) => saved.is_none(),
+    }
+}
+
+/// `image` shrunk to at most [`PREVIEW_SIZE`] on its longer side
+pub fn shrink(image: &ColorImage) -> Result<RgbaImage, String> {
+    let [width, height] = image.size;
+    let rgba: Vec<u8> = image.pixels.iter().flat_map(|pixel| pixel.to_srgba_unmultiplied()).collect();
+    let full = RgbaImage::from_raw(width as u32, height as u32, rgba).ok_or("Image has no pixels")?;
+    let scale = (PREVIEW_SIZE as f32 / width.max(height) as f32).min(1.0);
+    Ok(image::imageops::thumbnail(
+        &full,
+        ((width as f32 * scale).round() as u32).max(1),
+        ((height as f32 * scale).round() as u32).max(1),
+    ))
+}
+
+/// The saved previews in one folder, kept under a byte limit
+pub struct PreviewStore {
+    dir: PathBuf,
+    limit_bytes: usize,
+    size_bytes: usize,
+    /// Size and last use of each preview file; ones from earlier sessions
+    /// were last used when they were saved
+    entries: HashMap<PathBuf, (usize, SystemTime)>,
+}
+
+impl PreviewStore {
+    /// The previews already in `dir`, trimmed to `limit_bytes`
+    pub fn open(dir: PathBuf, limit_bytes: usize) -> Self {
+        let entries: HashMap<PathBuf, (usize, SystemTime)> = std::fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "png"))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                Some((entry.path(), (metadata.len() as usize, metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH))))
+            })
+            .collect();
+        let size_bytes = entries.values().map(|(size, _)| size).sum();
+        let mut store = Self { dir, limit_bytes, size_bytes, entries };
+        store.evict_to(limit_bytes);
+        store
+    }
+
+    /// Whether there is no preview of `path`, or only one older than the file
+    pub fn needs_preview(&self, path: &Path) -> bool {
+        needs_preview(&self.dir, path)
+    }
+
+    /// Keep `preview`, made by [`shrink`] from the decoded `path`
+    pub fn save(&mut self, path: &Path, preview: &RgbaImage) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| format!("Failed to create {}: {}", self.dir.display(), e))?;
+        let output = preview_file(&self.dir, path);
+        preview.save(&output).map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+        let size = std::fs::metadata(&output).map_or(0, |metadata| metadata.len() as usize);
+        if let Some((old_size, _)) = self.entries.insert(output, (size, SystemTime::now())) {
+            self.size_bytes -= old_size;
+        }
+        self.size_bytes += size;
+        self.evict_to(self.limit_bytes);
+        Ok(())
+    }
+
+    /// The saved preview of `path`, if any, marking it as recently used
+    pub fn load(&mut self, path: &Path) -> Option<ColorImage> {
+        let file = preview_file(&self.dir, path);
+        let preview = image::open(&file).ok()?.to_rgba8();
+        if let Some((_, last_used)) = self.entries.get_mut(&file) {
+            *last_used = SystemTime::now();
+        }
+        Some(ColorImage::from_rgba_unmultiplied([preview.width() as usize, preview.height() as usize], preview.as_raw()))
+    }
+
+    fn remove(&mut self, file: &Path) {
+        if let Some((size, _)) = self.entries.remove(file) {
+            self.size_bytes -= size;
+            let _ = std::fs::remove_file(file);
+        }
+    }
+
+    fn evict_to(&mut self, limit: usize) {
+        while self.size_bytes > limit {
+            let Some(oldest) = self.entries.iter().min_by_key(|(_, (_, last_used))| *last_used).map(|(file, _)| file.clone()) else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+    }
+}
+
+impl ManagedCache for PreviewStore {
+    fn name(&self) -> &str {
+        "Saved cloud previews"
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn size_bytes(&self) -> usize {
+        self.size_bytes
+    }
+
+    fn limit_bytes(&self) -> usize {
+        self.limit_bytes
+    }
+
+    fn set_limit_bytes(&mut self, limit: usize) {
+        self.limit_bytes = limit;
+        self.evict_to(limit);
+    }
+
+    fn clear(&mut self) {
+        self.evict_to(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use eframe::egui::Color32;
+
+    #[test]
+    fn test_previews_are_saved_small_and_found_again() {
+        let dir = TempDir::new("preview_store");
+        let source = dir.join("photo.jpg");
+        std::fs::write(&source, b"not read").unwrap();
+        let mut store = PreviewStore::open(dir.join("previews"), 1024 * 1024);
+        assert!(store.needs_preview(&source));
+        assert_eq!(store.load(&source), None);
+
+        let image = ColorImage::new([1024, 512], Color32::from_rgb(200, 30, 30));
+        store.save(&source, &shrink(&image).unwrap()).unwrap();
+        let loaded = store.load(&source);
+        let needed = store.needs_preview(&source);
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.size, [256, 128]);
+        assert_eq!(loaded.pixels[0], Color32::from_rgb(200, 30, 30));
+        assert!(!needed);
+        assert_eq!(store.len(), 1);
+
+        // Reopening finds it again; clearing deletes it
+        let mut store = PreviewStore::open(dir.join("previews"), 1024 * 1024);
+        assert_eq!(store.len(), 1);
+        store.clear();
+        assert!(store.needs_preview(&source));
+    }
+
+    #[test]
+    fn test_least_recently_used_previews_are_deleted() {
+        let dir = TempDir::new("preview_store_limit");
+        let preview = shrink(&ColorImage::new([64, 64], Color32::WHITE)).unwrap();
+        let mut store = PreviewStore::open(dir.join("previews"), usize::MAX);
+        let [a, b, c] = ["a.jpg", "b.jpg", "c.jpg"].map(|name| dir.join(name));
+        store.save(&a, &preview).unwrap();
+        store.save(&b, &preview).unwrap();
+        let size = store.size_bytes() / 2;
+        // Loading "a" makes "b" the oldest
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert!(store.load(&a).is_some());
+
+        store.set_limit_bytes(2 * size);
+        store.save(&c, &preview).unwrap();
+        assert_eq!(store.len(), 2);
+        assert!(store.load(&b).is_none());
+        assert!(store.load(&a).is_some() && store.load(&c).is_some());
+    }
+}
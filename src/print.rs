@@ -0,0 +1,348 @@
+//! Printing the displayed image
+//!
+//! The page is laid out in inches, rasterized at [`PRINT_DPI`] with the image
+//! fitted to the printable area or at its actual size, and handed to the
+//! system's print spooler as a PNG recording that resolution: `lp` on Unix,
+//! and on Windows a PowerShell script that prints it on the chosen paper.
+
+use std::io::BufWriter;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use eframe::egui::{Color32, ColorImage, Rect, pos2, vec2};
+use image::RgbaImage;
+
+use crate::view_export::{ViewSnapshot, render_view};
+
+/// Resolution pages are rasterized at
+pub const PRINT_DPI: f32 = 300.0;
+/// Smallest printable width or height left by the margins, in inches
+const MIN_PRINTABLE: f32 = 1.0;
+const METERS_PER_INCH: f32 = 0.0254;
+
+/// Print files written so far, so concurrent prints get their own
+static PRINT_FILES: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Paper {
+    Letter,
+    Legal,
+    A4,
+    A5,
+    Photo4x6,
+}
+
+impl Paper {
+    pub const ALL: [Paper; 5] = [Paper::Letter, Paper::Legal, Paper::A4, Paper::A5, Paper::Photo4x6];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Paper::Letter => "Letter",
+            Paper::Legal => "Legal",
+            Paper::A4 => "A4",
+            Paper::A5 => "A5",
+            Paper::Photo4x6 => "4 x 6 in photo",
+        }
+    }
+
+    /// Portrait width and height in inches
+    pub fn size_inches(&self) -> (f32, f32) {
+        match self {
+            Paper::Letter => (8.5, 11.0),
+            Paper::Legal => (8.5, 14.0),
+            Paper::A4 => (210.0 / 25.4, 297.0 / 25.4),
+            Paper::A5 => (148.0 / 25.4, 210.0 / 25.4),
+            Paper::Photo4x6 => (4.0, 6.0),
+        }
+    }
+
+    /// Media name understood by CUPS
+    fn cups_media(&self) -> &'static str {
+        match self {
+            Paper::Letter => "Letter",
+            Paper::Legal => "Legal",
+            Paper::A4 => "A4",
+            Paper::A5 => "A5",
+            Paper::Photo4x6 => "na_index-4x6_4x6in",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PrintScaling {
+    /// As large as fits inside the margins, keeping the aspect ratio
+    FitToPage,
+    /// At `image_dpi` pixels per inch; cropped if it doesn't fit
+    ActualSize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageLayout {
+    pub paper: Paper,
+    pub landscape: bool,
+    pub scaling: PrintScaling,
+    /// Margin on every side, in inches
+    pub margin: f32,
+    /// Resolution used for actual size, usually the one recorded in the file
+    pub image_dpi: f32,
+}
+
+impl PageLayout {
+    /// A layout whose orientation matches the image
+    pub fn for_image(image_size: [usize; 2], image_dpi: Option<f32>) -> Self {
+        Self {
+            paper: Paper::Letter,
+            landscape: image_size[0] > image_size[1],
+            scaling: PrintScaling::FitToPage,
+            margin: 0.5,
+            image_dpi: image_dpi.unwrap_or(crate::print_size::RECOMMENDED_PRINT_DPI as f32),
+        }
+    }
+
+    /// Page width and height in inches
+    pub fn page_size(&self) -> (f32, f32) {
+        let (width, height) = self.paper.size_inches();
+        if self.landscape { (height, width) } else { (width, height) }
+    }
+
+    /// The page inside the margins, in inches from the top left corner
+    pub fn printable_rect(&self) -> Rect {
+        let (width, height) = self.page_size();
+        let largest_margin = ((width.min(height) - MIN_PRINTABLE) / 2.0).max(0.0);
+        let margin = self.margin.clamp(0.0, largest_margin);
+        Rect::from_min_max(pos2(margin, margin), pos2(width - margin, height - margin))
+    }
+
+    /// Where the whole image goes on the page, in inches; may extend past the printable area
+    pub fn image_rect(&self, image_size: [usize; 2]) -> Rect {
+        let printable = self.printable_rect();
+        let (width, height) = (image_size[0].max(1) as f32, image_size[1].max(1) as f32);
+        let size = match self.scaling {
+            PrintScaling::FitToPage => {
+                let scale = (printable.width() / width).min(printable.height() / height);
+                vec2(width * scale, height * scale)
+            }
+            PrintScaling::ActualSize => vec2(width, height) / self.image_dpi.max(1.0),
+        };
+        Rect::from_center_size(printable.center(), size)
+    }
+
+    /// Whether part of the image falls outside the margins
+    pub fn is_cropped(&self, image_size: [usize; 2]) -> bool {
+        !self.printable_rect().expand(0.001).contains_rect(self.image_rect(image_size))
+    }
+}
+
+/// Rasterize the page at `dpi`, white outside the image
+pub fn render_page(image: &ColorImage, layout: &PageLayout, dpi: f32) -> RgbaImage {
+    let (width, height) = layout.page_size();
+    let printable = layout.printable_rect();
+    let view = ViewSnapshot {
+        area: printable,
+        image_rect: layout.image_rect(image.size),
+        background: Color32::WHITE,
+        checkerboard: false,
+        selection: None,
+        pixels_per_point: dpi,
+    };
+    let content = render_view(image, &view, 1.0);
+    let mut page = RgbaImage::from_pixel((width * dpi).round() as u32, (height * dpi).round() as u32, image::Rgba([255; 4]));
+    let offset = ((printable.min.x * dpi).round() as i64, (printable.min.y * dpi).round() as i64);
+    image::imageops::overlay(&mut page, &content, offset.0, offset.1);
+    page
+}
+
+/// Names of the installed printers; empty if they can't be listed
+pub fn list_printers() -> Vec<String> {
+    #[cfg(windows)]
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-Command", "Get-Printer | Select-Object -ExpandProperty Name"])
+        .output();
+    #[cfg(not(windows))]
+    let output = Command::new("lpstat").arg("-e").output();
+
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Save `page` as a PNG that records it was rendered at `dpi`
+fn save_page(page: &RgbaImage, dpi: f32, output: &Path) -> Result<(), String> {
+    let file = std::fs::File::create(output).map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), page.width(), page.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let per_meter = (dpi / METERS_PER_INCH).round() as u32;
+    encoder.set_pixel_dims(Some(png::PixelDimensions { xppu: per_meter, yppu: per_meter, unit: png::Unit::Meter }));
+    encoder.write_header()
+        .and_then(|mut writer| writer.write_image_data(page.as_raw()))
+        .map_err(|e| format!("Failed to write {}: {}", output.display(), e))
+}
+
+/// Send a page laid out with `layout` and rendered at `dpi` to `printer`, or
+/// the default printer
+pub fn print_page(page: &RgbaImage, dpi: f32, layout: &PageLayout, printer: Option<&str>) -> Result<(), String> {
+    let number = PRINT_FILES.fetch_add(1, Ordering::Relaxed);
+    let file = std::env::temp_dir().join(format!("image_previewer_print_{}_{}.png", std::process::id(), number));
+    save_page(page, dpi, &file)?;
+    let result = spool(&file, layout, printer);
+    let _ = std::fs::remove_file(&file);
+    result
+}
+
+/// Prints `PRINT_FILE` across the whole of a sheet of `PRINT_PAPER_*`,
+/// turned to landscape for a landscape page
+#[cfg(windows)]
+const PRINT_SCRIPT: &str = r#"
+$ErrorActionPreference = 'Stop'
+Add-Type -AssemblyName System.Drawing
+$image = [System.Drawing.Image]::FromFile($env:PRINT_FILE)
+try {
+    $document = New-Object System.Drawing.Printing.PrintDocument
+    if ($env:PRINT_PRINTER) { $document.PrinterSettings.PrinterName = $env:PRINT_PRINTER }
+    if (-not $document.PrinterSettings.IsValid) { throw "No printer named $($document.PrinterSettings.PrinterName)" }
+    $width = [int]$env:PRINT_PAPER_WIDTH
+    $height = [int]$env:PRINT_PAPER_HEIGHT
+    $paper = $document.PrinterSettings.PaperSizes |
+        Where-Object { [Math]::Abs($_.Width - $width) -le 5 -and [Math]::Abs($_.Height - $height) -le 5 } |
+        Select-Object -First 1
+    if (-not $paper) { $paper = New-Object System.Drawing.Printing.PaperSize($env:PRINT_PAPER_NAME, $width, $height) }
+    $document.DefaultPageSettings.PaperSize = $paper
+    $document.DefaultPageSettings.Landscape = $image.Width -gt $image.Height
+    $document.DefaultPageSettings.Margins = New-Object System.Drawing.Printing.Margins(0, 0, 0, 0)
+    $document.add_PrintPage({
+        param($sender, $page)
+        # Drawing starts at the printer's hard margins, so the sheet's corner is above and left of it
+        $bounds = $page.PageBounds
+        $page.Graphics.DrawImage($image, -$page.PageSettings.HardMarginX, -$page.PageSettings.HardMarginY, $bounds.Width, $bounds.Height)
+    })
+    $document.Print()
+} finally {
+    $image.Dispose()
+}
+"#;
+
+#[cfg(windows)]
+fn spool(file: &Path, layout: &PageLayout, printer: Option<&str>) -> Result<(), String> {
+    // Paper sizes are in hundredths of an inch
+    let paper = layout.paper;
+    let (width, height) = paper.size_inches();
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", PRINT_SCRIPT])
+        .env("PRINT_FILE", file)
+        .env("PRINT_PRINTER", printer.unwrap_or_default())
+        .env("PRINT_PAPER_NAME", paper.label())
+        .env("PRINT_PAPER_WIDTH", ((width * 100.0).round() as u32).to_string())
+        .env("PRINT_PAPER_HEIGHT", ((height * 100.0).round() as u32).to_string());
+    run_spooler(command, "powershell")
+}
+
+#[cfg(not(windows))]
+fn spool(file: &Path, layout: &PageLayout, printer: Option<&str>) -> Result<(), String> {
+    let mut command = Command::new("lp");
+    if let Some(printer) = printer {
+        command.args(["-d", printer]);
+    }
+    for option in cups_options(layout) {
+        command.args(["-o", &option]);
+    }
+    command.arg(file);
+    run_spooler(command, "lp")
+}
+
+/// `lp` options for a page that already has the layout's margins drawn in.
+/// A fitted page may shrink into the printer's printable area; any other
+/// layout is printed at the page's recorded resolution across the whole sheet,
+/// so actual sizes and margins come out as chosen.
+#[cfg(not(windows))]
+fn cups_options(layout: &PageLayout) -> Vec<String> {
+    let mut options = vec![format!("media={}", layout.paper.cups_media())];
+    match layout.scaling {
+        PrintScaling::FitToPage => options.push("fit-to-page".to_string()),
+        PrintScaling::ActualSize => {
+            options.push("natural-scaling=100".to_string());
+            options.extend(["page-left=0", "page-right=0", "page-top=0", "page-bottom=0"].map(str::to_string));
+        }
+    }
+    options
+}
+
+fn run_spooler(mut command: Command, name: &str) -> Result<(), String> {
+    let output = command.output().map_err(|e| format!("Failed to run {}: {}", name, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!("{} failed: {}", name, String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_layout_fit_and_actual_size() {
+        let mut layout = PageLayout::for_image([3000, 2000], Some(200.0));
+        assert!(layout.landscape);
+        assert_eq!(layout.page_size(), (11.0, 8.5));
+
+        // Fit: limited by the 10 inch printable width
+        let rect = layout.image_rect([3000, 2000]);
+        assert!((rect.width() - 10.0).abs() < 1e-4 && (rect.height() - 10.0 / 1.5).abs() < 1e-4);
+        assert_eq!(rect.center(), pos2(5.5, 4.25));
+        assert!(!layout.is_cropped([3000, 2000]));
+
+        // 15 x 10 inches at 200 DPI doesn't fit
+        layout.scaling = PrintScaling::ActualSize;
+        assert_eq!(layout.image_rect([3000, 2000]).size(), vec2(15.0, 10.0));
+        assert!(layout.is_cropped([3000, 2000]));
+
+        // Margins never leave less than an inch to print on
+        layout.paper = Paper::Photo4x6;
+        layout.margin = 5.0;
+        assert_eq!(layout.printable_rect().height(), MIN_PRINTABLE);
+    }
+
+    #[test]
+    fn test_render_page() {
+        let image = ColorImage::new([4, 2], Color32::RED);
+        let layout = PageLayout { paper: Paper::Photo4x6, landscape: false, scaling: PrintScaling::FitToPage, margin: 0.5, image_dpi: 300.0 };
+        let page = render_page(&image, &layout, 10.0);
+        assert_eq!(page.dimensions(), (40, 60));
+        // Margin, then the 3 x 1.5 inch image centred on the page
+        assert_eq!(page.get_pixel(2, 30).0, [255, 255, 255, 255]);
+        assert_eq!(page.get_pixel(20, 30).0, [255, 0, 0, 255]);
+        assert_eq!(page.get_pixel(20, 20).0, [255, 255, 255, 255]);
+
+        let dir = TempDir::new("print_page");
+        let file = dir.join("page.png");
+        save_page(&page, 10.0, &file).unwrap();
+        let bytes = std::fs::read(&file).unwrap();
+        let (dpi, _) = crate::metadata::parse_dpi(&bytes).unwrap();
+        assert!((dpi - 10.0).abs() < 0.01);
+        assert_eq!(image::load_from_memory(&bytes).unwrap().to_rgba8(), page);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_cups_options_follow_scaling() {
+        let mut layout = PageLayout::for_image([3000, 2000], None);
+        assert_eq!(cups_options(&layout), ["media=Letter", "fit-to-page"]);
+
+        layout.scaling = PrintScaling::ActualSize;
+        layout.paper = Paper::A4;
+        let options = cups_options(&layout);
+        assert!(!options.iter().any(|option| option == "fit-to-page"));
+        assert!(options.iter().any(|option| option == "natural-scaling=100"));
+        assert!(options.iter().any(|option| option == "page-left=0"));
+        assert_eq!(options[0], "media=A4");
+    }
+}
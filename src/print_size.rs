@@ -0,0 +1,123 @@
+//! Print size / resolution calculations
+
+/// Resolution commonly considered the minimum for high-quality photo prints
+pub const RECOMMENDED_PRINT_DPI: f64 = 300.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PrintUnit {
+    Inches,
+    Centimeters,
+}
+
+impl PrintUnit {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PrintUnit::Inches => "in",
+            PrintUnit::Centimeters => "cm",
+        }
+    }
+
+    pub fn to_inches(self, value: f64) -> f64 {
+        match self {
+            PrintUnit::Inches => value,
+            PrintUnit::Centimeters => value / 2.54,
+        }
+    }
+
+    // Converts a length from inches into this unit, so it does take `self`
+    #[allow(clippy::wrong_self_convention)]
+    pub fn from_inches(self, inches: f64) -> f64 {
+        match self {
+            PrintUnit::Inches => inches,
+            PrintUnit::Centimeters => inches * 2.54,
+        }
+    }
+}
+
+/// Desired print dimensions for the print-size calculator
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrintTarget {
+    pub width: f64,
+    pub height: f64,
+    pub unit: PrintUnit,
+    pub dpi: f64,
+}
+
+impl Default for PrintTarget {
+    fn default() -> Self {
+        Self {
+            width: 10.0,
+            height: 8.0,
+            unit: PrintUnit::Inches,
+            dpi: RECOMMENDED_PRINT_DPI,
+        }
+    }
+}
+
+impl PrintTarget {
+    /// Switch units while keeping the same physical size
+    pub fn set_unit(&mut self, unit: PrintUnit) {
+        self.width = unit.from_inches(self.unit.to_inches(self.width));
+        self.height = unit.from_inches(self.unit.to_inches(self.height));
+        self.unit = unit;
+    }
+}
+
+/// Physical print size of an image at a given resolution, in the requested unit
+pub fn print_size(width_px: u32, height_px: u32, dpi: f64, unit: PrintUnit) -> (f64, f64) {
+    if dpi <= 0.0 {
+        return (0.0, 0.0);
+    }
+    (
+        unit.from_inches(width_px as f64 / dpi),
+        unit.from_inches(height_px as f64 / dpi),
+    )
+}
+
+/// Effective resolution when printing at the given size
+///
+/// Aspect ratio mismatches are handled as "fit inside": the image is scaled
+/// until one axis fills the print, and the other is left with a margin at
+/// the same resolution, which is the higher of the two axes' ratios.
+pub fn effective_dpi(width_px: u32, height_px: u32, print_width: f64, print_height: f64, unit: PrintUnit) -> f64 {
+    let width_in = unit.to_inches(print_width);
+    let height_in = unit.to_inches(print_height);
+    if width_in <= 0.0 || height_in <= 0.0 {
+        return 0.0;
+    }
+    (width_px as f64 / width_in).max(height_px as f64 / height_in)
+}
+
+/// Pixel dimensions needed to print at the given size and resolution
+pub fn required_pixels(print_width: f64, print_height: f64, dpi: f64, unit: PrintUnit) -> (u32, u32) {
+    (
+        (unit.to_inches(print_width) * dpi).ceil() as u32,
+        (unit.to_inches(print_height) * dpi).ceil() as u32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_print_size_in_inches_and_cm() {
+        assert_eq!(print_size(3000, 2400, 300.0, PrintUnit::Inches), (10.0, 8.0));
+        let (w, h) = print_size(3000, 2400, 300.0, PrintUnit::Centimeters);
+        assert!((w - 25.4).abs() < 1e-9 && (h - 20.32).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_effective_dpi_fits_inside() {
+        // 3000x2000 fitted inside 10x8 inches prints 10x6.67 inches at 300 DPI
+        assert_eq!(effective_dpi(3000, 2000, 10.0, 8.0, PrintUnit::Inches), 300.0);
+        assert_eq!(effective_dpi(2000, 3000, 10.0, 8.0, PrintUnit::Inches), 375.0);
+        assert_eq!(effective_dpi(3000, 2000, 0.0, 8.0, PrintUnit::Inches), 0.0);
+    }
+
+    #[test]
+    fn test_required_pixels() {
+        assert_eq!(required_pixels(10.0, 8.0, 300.0, PrintUnit::Inches), (3000, 2400));
+        assert_eq!(required_pixels(2.54, 2.54, 100.0, PrintUnit::Centimeters), (100, 100));
+    }
+}
@@ -0,0 +1,249 @@
+//! Progress events shared by every long-running operation
+//!
+//! Scanning, hydration, benchmarking, background jobs and exports all report
+//! through a [`ProgressReporter`] instead of keeping their own progress flags.
+//! The UI drains one [`ProgressHub`] to show what is running; library users
+//! can create their own hub or pass [`ProgressSink::disconnected`].
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+
+pub type OperationId = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationKind {
+    Scan,
+    Hydration,
+    Benchmark,
+    Job,
+    Export,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressEvent {
+    Started { id: OperationId, kind: OperationKind, name: String },
+    /// `total` is `None` while the amount of work is unknown; `bytes` is set
+    /// by operations that move data
+    Progress { id: OperationId, done: u64, total: Option<u64>, bytes: Option<u64> },
+    Finished { id: OperationId, summary: String },
+    Failed { id: OperationId, error: String },
+}
+
+impl ProgressEvent {
+    pub fn id(&self) -> OperationId {
+        match self {
+            ProgressEvent::Started { id, .. }
+            | ProgressEvent::Progress { id, .. }
+            | ProgressEvent::Finished { id, .. }
+            | ProgressEvent::Failed { id, .. } => *id,
+        }
+    }
+}
+
+/// Cloneable handle that worker threads use to start operations
+#[derive(Debug, Clone)]
+pub struct ProgressSink {
+    sender: Sender<ProgressEvent>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl ProgressSink {
+    /// A sink whose events are discarded, for callers that don't track progress
+    pub fn disconnected() -> Self {
+        let (sender, _) = mpsc::channel();
+        Self { sender, next_id: Arc::new(AtomicU64::new(1)) }
+    }
+
+    /// Announce a new operation
+    pub fn start(&self, kind: OperationKind, name: impl Into<String>) -> ProgressReporter {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let _ = self.sender.send(ProgressEvent::Started { id, kind, name: name.into() });
+        ProgressReporter { id, sender: self.sender.clone(), ended: false }
+    }
+}
+
+/// Reports on a single operation. Dropping it before `finish` or `fail`
+/// reports the operation as cancelled, so it never stays active forever.
+#[derive(Debug)]
+pub struct ProgressReporter {
+    id: OperationId,
+    sender: Sender<ProgressEvent>,
+    ended: bool,
+}
+
+impl ProgressReporter {
+    pub fn id(&self) -> OperationId {
+        self.id
+    }
+
+    pub fn progress(&self, done: u64, total: Option<u64>) {
+        self.send(ProgressEvent::Progress { id: self.id, done, total, bytes: None });
+    }
+
+    /// Progress of an operation that transfers data, measured in bytes
+    pub fn progress_bytes(&self, done: u64, total: Option<u64>) {
+        self.send(ProgressEvent::Progress { id: self.id, done, total, bytes: Some(done) });
+    }
+
+    pub fn finish(mut self, summary: impl Into<String>) {
+        self.ended = true;
+        self.send(ProgressEvent::Finished { id: self.id, summary: summary.into() });
+    }
+
+    pub fn fail(mut self, error: impl Into<String>) {
+        self.ended = true;
+        self.send(ProgressEvent::Failed { id: self.id, error: error.into() });
+    }
+
+    /// Finish or fail depending on the result
+    pub fn complete(self, result: &Result<String, String>) {
+        match result {
+            Ok(summary) => self.finish(summary.clone()),
+            Err(error) => self.fail(error.clone()),
+        }
+    }
+
+    fn send(&self, event: ProgressEvent) {
+        // Nobody listening is fine; progress is informational
+        let _ = self.sender.send(event);
+    }
+}
+
+impl Drop for ProgressReporter {
+    fn drop(&mut self) {
+        if !self.ended {
+            self.send(ProgressEvent::Failed { id: self.id, error: "Cancelled".to_string() });
+        }
+    }
+}
+
+/// Latest known state of an operation that hasn't ended yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationStatus {
+    pub id: OperationId,
+    pub kind: OperationKind,
+    pub name: String,
+    pub done: u64,
+    pub total: Option<u64>,
+    pub bytes: Option<u64>,
+}
+
+impl OperationStatus {
+    /// Completed fraction (0..1), if the total is known
+    pub fn fraction(&self) -> Option<f32> {
+        self.total
+            .filter(|&total| total > 0)
+            .map(|total| (self.done as f32 / total as f32).clamp(0.0, 1.0))
+    }
+}
+
+/// Receives the events of every operation started through its sink
+pub struct ProgressHub {
+    sink: ProgressSink,
+    receiver: Receiver<ProgressEvent>,
+    active: Vec<OperationStatus>,
+}
+
+impl Default for ProgressHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressHub {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sink: ProgressSink { sender, next_id: Arc::new(AtomicU64::new(1)) },
+            receiver,
+            active: Vec::new(),
+        }
+    }
+
+    pub fn sink(&self) -> ProgressSink {
+        self.sink.clone()
+    }
+
+    /// Drain events received since the last call and update the active list
+    pub fn poll(&mut self) -> Vec<ProgressEvent> {
+        let events: Vec<ProgressEvent> = self.receiver.try_iter().collect();
+        for event in &events {
+            match event {
+                ProgressEvent::Started { id, kind, name } => self.active.push(OperationStatus {
+                    id: *id,
+                    kind: *kind,
+                    name: name.clone(),
+                    done: 0,
+                    total: None,
+                    bytes: None,
+                }),
+                ProgressEvent::Progress { id, done, total, bytes } => {
+                    if let Some(status) = self.active.iter_mut().find(|s| s.id == *id) {
+                        status.done = *done;
+                        status.total = *total;
+                        status.bytes = *bytes;
+                    }
+                }
+                ProgressEvent::Finished { id, .. } | ProgressEvent::Failed { id, .. } => {
+                    self.active.retain(|s| s.id != *id);
+                }
+            }
+        }
+        events
+    }
+
+    /// Operations that have started but not yet finished, oldest first
+    pub fn active(&self) -> &[OperationStatus] {
+        &self.active
+    }
+
+    pub fn is_busy(&self) -> bool {
+        !self.active.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hub_tracks_operations() {
+        let mut hub = ProgressHub::new();
+        let sink = hub.sink();
+        let scan = sink.start(OperationKind::Scan, "Scanning");
+        let export = sink.start(OperationKind::Export, "Exporting");
+        scan.progress(3, Some(4));
+        hub.poll();
+
+        assert_eq!(hub.active().len(), 2);
+        assert_eq!(hub.active()[0].fraction(), Some(0.75));
+        assert_eq!(hub.active()[1].fraction(), None);
+
+        let scan_id = scan.id();
+        scan.finish("4 files");
+        let events = hub.poll();
+        assert_eq!(events, vec![ProgressEvent::Finished { id: scan_id, summary: "4 files".to_string() }]);
+        assert_eq!(hub.active().len(), 1);
+
+        export.fail("disk full");
+        hub.poll();
+        assert!(!hub.is_busy());
+    }
+
+    #[test]
+    fn test_dropped_reporter_reports_cancellation() {
+        let mut hub = ProgressHub::new();
+        let reporter = hub.sink().start(OperationKind::Hydration, "photo.jpg");
+        let id = reporter.id();
+        reporter.progress_bytes(1024, Some(4096));
+        drop(reporter);
+
+        let events = hub.poll();
+        assert_eq!(events.last(), Some(&ProgressEvent::Failed { id, error: "Cancelled".to_string() }));
+        assert!(!hub.is_busy());
+
+        // Reporting without a listener is harmless
+        ProgressSink::disconnected().start(OperationKind::Job, "ignored").finish("done");
+    }
+}
@@ -0,0 +1,198 @@
+//! When the download and slow-image warnings are skipped
+//!
+//! Confirming a warning can silence it for the rest of the session, for the
+//! image's folder or for good, and downloads up to a size can be allowed
+//! without asking. Everything but the session choice is kept in the
+//! preferences file and listed in the settings, where it can be undone.
+
+use std::path::{Path, PathBuf};
+
+use crate::preferences::Preferences;
+
+const PREFERENCE_PREFIX: &str = "prompt.";
+
+const MB: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prompt {
+    Download,
+    SlowImage,
+}
+
+impl Prompt {
+    pub const ALL: [Prompt; 2] = [Prompt::Download, Prompt::SlowImage];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Prompt::Download => "Download warning",
+            Prompt::SlowImage => "Slow image warning",
+        }
+    }
+
+    fn key(&self) -> &'static str {
+        match self {
+            Prompt::Download => "download",
+            Prompt::SlowImage => "slow_image",
+        }
+    }
+}
+
+/// How long confirming a warning keeps it away
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Silence {
+    #[default]
+    None,
+    Session,
+    Folder,
+    Always,
+}
+
+impl Silence {
+    pub const ALL: [Silence; 4] = [Silence::None, Silence::Session, Silence::Folder, Silence::Always];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Silence::None => "Ask again next time",
+            Silence::Session => "Don't warn again this session",
+            Silence::Folder => "Don't warn for this folder",
+            Silence::Always => "Never warn again",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PromptRules {
+    pub never: bool,
+    /// Folders whose images open without the warning, sorted
+    pub folders: Vec<PathBuf>,
+    /// Silenced until the viewer is closed; not saved
+    pub session: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PromptPolicies {
+    download: PromptRules,
+    slow_image: PromptRules,
+    /// Files up to this size are downloaded without asking
+    pub download_under_mb: Option<u32>,
+}
+
+impl PromptPolicies {
+    pub fn load(preferences: &Preferences) -> Self {
+        let mut policies = Self {
+            download_under_mb: preferences.get(&format!("{}download.under_mb", PREFERENCE_PREFIX)),
+            ..Self::default()
+        };
+        for prompt in Prompt::ALL {
+            let prefix = format!("{}{}.", PREFERENCE_PREFIX, prompt.key());
+            let rules = policies.rules_mut(prompt);
+            rules.never = preferences.get(&format!("{}never", prefix)).unwrap_or(false);
+            rules.folders = (1..)
+                .map_while(|number| preferences.get::<String>(&format!("{}folder.{}", prefix, number)))
+                .map(PathBuf::from)
+                .collect();
+        }
+        policies
+    }
+
+    pub fn save_to(&self, preferences: &mut Preferences) {
+        match self.download_under_mb {
+            Some(mb) => preferences.set(&format!("{}download.under_mb", PREFERENCE_PREFIX), mb),
+            None => preferences.remove(&format!("{}download.under_mb", PREFERENCE_PREFIX)),
+        }
+        for prompt in Prompt::ALL {
+            let prefix = format!("{}{}.", PREFERENCE_PREFIX, prompt.key());
+            let rules = self.rules(prompt);
+            preferences.set(&format!("{}never", prefix), rules.never);
+            let mut number = 1;
+            while preferences.get::<String>(&format!("{}folder.{}", prefix, number)).is_some() {
+                preferences.remove(&format!("{}folder.{}", prefix, number));
+                number += 1;
+            }
+            for (number, folder) in (1..).zip(&rules.folders) {
+                preferences.set(&format!("{}folder.{}", prefix, number), folder.display());
+            }
+        }
+    }
+
+    pub fn rules(&self, prompt: Prompt) -> &PromptRules {
+        match prompt {
+            Prompt::Download => &self.download,
+            Prompt::SlowImage => &self.slow_image,
+        }
+    }
+
+    pub fn rules_mut(&mut self, prompt: Prompt) -> &mut PromptRules {
+        match prompt {
+            Prompt::Download => &mut self.download,
+            Prompt::SlowImage => &mut self.slow_image,
+        }
+    }
+
+    /// Whether to warn before opening `path`; `size` is its download size
+    pub fn should_ask(&self, prompt: Prompt, path: &Path, size: Option<u64>) -> bool {
+        let rules = self.rules(prompt);
+        let in_folder = path.parent().is_some_and(|parent| rules.folders.iter().any(|folder| folder == parent));
+        let small = prompt == Prompt::Download
+            && self.download_under_mb.zip(size).is_some_and(|(mb, size)| size <= mb as u64 * MB);
+        !(rules.never || rules.session || in_folder || small)
+    }
+
+    /// Apply the choice made when the warning for `path` was confirmed;
+    /// returns whether anything that is saved changed
+    pub fn silence(&mut self, prompt: Prompt, silence: Silence, path: &Path) -> bool {
+        let rules = self.rules_mut(prompt);
+        match silence {
+            Silence::None => false,
+            Silence::Session => {
+                rules.session = true;
+                false
+            }
+            Silence::Folder => {
+                let Some(folder) = path.parent().map(Path::to_path_buf) else {
+                    return false;
+                };
+                if rules.folders.contains(&folder) {
+                    return false;
+                }
+                rules.folders.push(folder);
+                rules.folders.sort();
+                true
+            }
+            Silence::Always => {
+                rules.never = true;
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policies_silence_and_round_trip() {
+        let photo = Path::new("cloud/trip/photo.jpg");
+        let mut policies = PromptPolicies { download_under_mb: Some(5), ..PromptPolicies::default() };
+        assert!(!policies.should_ask(Prompt::Download, photo, Some(3 * MB)));
+        assert!(policies.should_ask(Prompt::Download, photo, Some(8 * MB)));
+        assert!(policies.should_ask(Prompt::SlowImage, photo, Some(3 * MB)));
+
+        assert!(policies.silence(Prompt::Download, Silence::Folder, photo));
+        assert!(!policies.silence(Prompt::Download, Silence::Folder, photo));
+        assert!(!policies.should_ask(Prompt::Download, photo, Some(8 * MB)));
+        assert!(policies.should_ask(Prompt::Download, Path::new("cloud/other/photo.jpg"), None));
+
+        assert!(!policies.silence(Prompt::SlowImage, Silence::Session, photo));
+        assert!(!policies.should_ask(Prompt::SlowImage, photo, None));
+
+        let mut preferences = Preferences::default();
+        policies.save_to(&mut preferences);
+        let loaded = PromptPolicies::load(&Preferences::parse(&preferences.to_text()));
+        // Session choices aren't saved
+        assert!(loaded.should_ask(Prompt::SlowImage, photo, None));
+        assert_eq!(loaded.rules(Prompt::Download), policies.rules(Prompt::Download));
+        assert_eq!(loaded.download_under_mb, Some(5));
+    }
+}
@@ -0,0 +1,180 @@
+//! Pixelating or blurring parts of an image before it is exported
+//!
+//! Redactions are edits in the undo history, replayed on top of the decoded
+//! image. Exports and prints use the displayed pixels and so never contain
+//! what was covered. Regions are kept in the pixels of the original file, as
+//! the decoded image may be scaled down, and the file may have been saved
+//! smaller since.
+
+use eframe::egui::{Color32, ColorImage};
+
+use crate::color_stats::PixelRect;
+
+/// Default pixel block size or blur radius
+pub const DEFAULT_STRENGTH: usize = 12;
+
+/// Blur radii below this leave text readable
+pub const MIN_STRENGTH: usize = 4;
+pub const MAX_STRENGTH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactionStyle {
+    Pixelate,
+    Blur,
+}
+
+impl RedactionStyle {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RedactionStyle::Pixelate => "Pixelate",
+            RedactionStyle::Blur => "Blur",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Redaction {
+    pub region: PixelRect,
+    pub style: RedactionStyle,
+    /// Block size for pixelating, radius for blurring
+    pub strength: usize,
+}
+
+impl Redaction {
+    /// A redaction of `selection`, in an image shown at `scale` times the
+    /// size of the original
+    pub fn of_selection(selection: PixelRect, style: RedactionStyle, strength: usize, scale: f64) -> Self {
+        let region = selection.scaled(1.0 / scale, [usize::MAX, usize::MAX]).unwrap_or(selection);
+        Self { region, style, strength }
+    }
+
+    /// Cover the region in `image`, which is shown at `scale` times the size
+    /// of the original; the part of the region outside the image is skipped
+    pub fn apply(&self, image: &mut ColorImage, scale: f64) {
+        let Some(region) = self.region.scaled(scale, image.size) else {
+            return;
+        };
+        let strength = self.strength.clamp(MIN_STRENGTH, MAX_STRENGTH);
+        match self.style {
+            RedactionStyle::Pixelate => pixelate(image, &region, strength),
+            // Three box blurs are close to a Gaussian
+            RedactionStyle::Blur => (0..3).for_each(|_| box_blur(image, &region, strength)),
+        }
+    }
+}
+
+/// Fill each `block`-sized square of the region with its average color
+fn pixelate(image: &mut ColorImage, region: &PixelRect, block: usize) {
+    let width = image.size[0];
+    for block_y in (region.min_y..region.max_y).step_by(block) {
+        for block_x in (region.min_x..region.max_x).step_by(block) {
+            let (end_x, end_y) = ((block_x + block).min(region.max_x), (block_y + block).min(region.max_y));
+            let mut sum = [0u64; 4];
+            for y in block_y..end_y {
+                for pixel in &image.pixels[y * width + block_x..y * width + end_x] {
+                    for (total, channel) in sum.iter_mut().zip(pixel.to_array()) {
+                        *total += channel as u64;
+                    }
+                }
+            }
+            let count = ((end_x - block_x) * (end_y - block_y)) as u64;
+            let [r, g, b, a] = sum.map(|total| (total / count) as u8);
+            let average = Color32::from_rgba_premultiplied(r, g, b, a);
+            for y in block_y..end_y {
+                image.pixels[y * width + block_x..y * width + end_x].fill(average);
+            }
+        }
+    }
+}
+
+/// Average each pixel with its neighbours within `radius` inside the region,
+/// horizontally and then vertically
+fn box_blur(image: &mut ColorImage, region: &PixelRect, radius: usize) {
+    let width = image.size[0];
+    let blur_line = |line: &mut [Color32]| {
+        let source = line.to_vec();
+        let mut sum = [0u64; 4];
+        let mut count = 0u64;
+        let add = |sum: &mut [u64; 4], pixel: Color32, sign: bool| {
+            for (total, channel) in sum.iter_mut().zip(pixel.to_array()) {
+                if sign { *total += channel as u64 } else { *total -= channel as u64 }
+            }
+        };
+        for pixel in source.iter().take(radius) {
+            add(&mut sum, *pixel, true);
+            count += 1;
+        }
+        for i in 0..source.len() {
+            if let Some(&entering) = source.get(i + radius) {
+                add(&mut sum, entering, true);
+                count += 1;
+            }
+            if i > radius {
+                add(&mut sum, source[i - radius - 1], false);
+                count -= 1;
+            }
+            let [r, g, b, a] = sum.map(|total| (total / count) as u8);
+            line[i] = Color32::from_rgba_premultiplied(r, g, b, a);
+        }
+    };
+    for y in region.min_y..region.max_y {
+        blur_line(&mut image.pixels[y * width + region.min_x..y * width + region.max_x]);
+    }
+    let mut column = Vec::with_capacity(region.height());
+    for x in region.min_x..region.max_x {
+        column.clear();
+        column.extend((region.min_y..region.max_y).map(|y| image.pixels[y * width + x]));
+        blur_line(&mut column);
+        for (y, pixel) in (region.min_y..region.max_y).zip(&column) {
+            image.pixels[y * width + x] = *pixel;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redactions_stay_in_their_region() {
+        let mut image = ColorImage::new([8, 8], Color32::WHITE);
+        for y in 0..8 {
+            image.pixels[y * 8 + 2] = Color32::BLACK;
+        }
+        let region = PixelRect { min_x: 0, min_y: 0, max_x: 4, max_y: 4 };
+
+        let mut pixelated = image.clone();
+        Redaction { region, style: RedactionStyle::Pixelate, strength: 4 }.apply(&mut pixelated, 1.0);
+        // The black line is averaged away inside the region and untouched outside
+        assert_eq!(pixelated.pixels[2], pixelated.pixels[3 * 8]);
+        assert_ne!(pixelated.pixels[2], Color32::BLACK);
+        assert_eq!(pixelated.pixels[5 * 8 + 2], Color32::BLACK);
+
+        let mut blurred = pixelated.clone();
+        Redaction { region: PixelRect { min_x: 0, min_y: 4, max_x: 8, max_y: 8 }, style: RedactionStyle::Blur, strength: 4 }.apply(&mut blurred, 1.0);
+        assert_ne!(blurred.pixels[5 * 8 + 2], Color32::BLACK);
+        assert_eq!(blurred.pixels[..32], pixelated.pixels[..32]);
+    }
+
+    #[test]
+    fn test_regions_follow_the_image_size() {
+        // Selected in an image shown at half size, kept in the original's pixels
+        let redaction = Redaction::of_selection(PixelRect { min_x: 1, min_y: 1, max_x: 3, max_y: 3 }, RedactionStyle::Blur, 4, 0.5);
+        assert_eq!(redaction.region, PixelRect { min_x: 2, min_y: 2, max_x: 6, max_y: 6 });
+
+        let mut image = ColorImage::new([8, 8], Color32::WHITE);
+        image.pixels[5 * 8 + 5] = Color32::BLACK;
+        redaction.apply(&mut image, 1.0);
+        assert_ne!(image.pixels[5 * 8 + 5], Color32::BLACK);
+
+        // A file saved smaller since, or decoded at a smaller size, doesn't panic
+        let beyond = Redaction { region: PixelRect { min_x: 4, min_y: 4, max_x: 40, max_y: 40 }, style: RedactionStyle::Pixelate, strength: 4 };
+        let mut small = ColorImage::new([6, 6], Color32::WHITE);
+        beyond.apply(&mut small, 1.0);
+        beyond.apply(&mut small, 0.25);
+        let outside = Redaction { region: PixelRect { min_x: 10, min_y: 10, max_x: 20, max_y: 20 }, style: RedactionStyle::Blur, strength: 4 };
+        let untouched = small.clone();
+        outside.apply(&mut small, 1.0);
+        assert_eq!(small, untouched);
+    }
+}
@@ -0,0 +1,109 @@
+//! Deciding when the next frame is drawn
+//!
+//! egui only repaints on input. Anything that changes on its own, such as an
+//! animation, a download or a decode running in the background, asks the
+//! [`RepaintScheduler`] for a frame instead of calling egui directly; at the
+//! end of the frame the earliest request is passed on to egui. On battery the
+//! frame rate can be capped so these don't keep the GPU busy.
+
+use std::time::Duration;
+
+use eframe::egui;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepaintReason {
+    /// Motion on screen, e.g. auto-scrolling or a view settling
+    Animation,
+    Download,
+    Decode,
+    /// Jobs, status refreshes and file watching
+    Background,
+    Slideshow,
+}
+
+impl RepaintReason {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RepaintReason::Animation => "animation",
+            RepaintReason::Download => "download",
+            RepaintReason::Decode => "decode",
+            RepaintReason::Background => "background work",
+            RepaintReason::Slideshow => "slideshow",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RepaintScheduler {
+    /// Earliest frame requested so far in this frame
+    next: Option<Duration>,
+    reasons: Vec<RepaintReason>,
+    /// Reasons behind the last frame that was scheduled
+    last_reasons: Vec<RepaintReason>,
+    /// Shortest time between frames; zero when uncapped
+    min_interval: Duration,
+}
+
+impl RepaintScheduler {
+    /// Ask for the next frame as soon as possible
+    pub fn request(&mut self, reason: RepaintReason) {
+        self.request_after(reason, Duration::ZERO);
+    }
+
+    /// Ask for a frame within `delay`
+    pub fn request_after(&mut self, reason: RepaintReason, delay: Duration) {
+        self.next = Some(self.next.map_or(delay, |next| next.min(delay)));
+        if !self.reasons.contains(&reason) {
+            self.reasons.push(reason);
+        }
+    }
+
+    /// Cap the frame rate at `fps`, or lift the cap with `None`
+    pub fn set_max_fps(&mut self, fps: Option<u32>) {
+        self.min_interval = fps.filter(|&fps| fps > 0).map_or(Duration::ZERO, |fps| Duration::from_secs(1) / fps);
+    }
+
+    /// When the requested frame is due, respecting the frame rate cap
+    pub fn next_frame(&self) -> Option<Duration> {
+        self.next.map(|next| next.max(self.min_interval))
+    }
+
+    /// Hand this frame's requests to egui and start collecting the next ones
+    pub fn flush(&mut self, ctx: &egui::Context) {
+        if let Some(delay) = self.next_frame() {
+            ctx.request_repaint_after(delay);
+        }
+        self.next = None;
+        self.last_reasons = std::mem::take(&mut self.reasons);
+    }
+
+    /// What the last scheduled frame was for; empty when idle
+    pub fn last_reasons(&self) -> &[RepaintReason] {
+        &self.last_reasons
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_earliest_request_wins_within_the_cap() {
+        let mut scheduler = RepaintScheduler::default();
+        assert_eq!(scheduler.next_frame(), None);
+        scheduler.request_after(RepaintReason::Download, Duration::from_millis(200));
+        scheduler.request_after(RepaintReason::Slideshow, Duration::from_secs(1));
+        assert_eq!(scheduler.next_frame(), Some(Duration::from_millis(200)));
+        scheduler.request(RepaintReason::Animation);
+        assert_eq!(scheduler.next_frame(), Some(Duration::ZERO));
+
+        scheduler.set_max_fps(Some(20));
+        assert_eq!(scheduler.next_frame(), Some(Duration::from_millis(50)));
+        scheduler.set_max_fps(None);
+        assert_eq!(scheduler.next_frame(), Some(Duration::ZERO));
+
+        scheduler.flush(&egui::Context::default());
+        assert_eq!(scheduler.next_frame(), None);
+        assert_eq!(scheduler.last_reasons(), [RepaintReason::Download, RepaintReason::Slideshow, RepaintReason::Animation]);
+    }
+}
@@ -0,0 +1,304 @@
+//! Rhai scripts for automating repetitive work
+//!
+//! With the `scripting` feature, every `*.rhai` file in the [`SCRIPTS_FOLDER`]
+//! of the config directory is loaded at startup. A script can define hooks
+//! the viewer calls, and add entries to the Scripts menu from its top level:
+//!
+//! ```text
+//! command("Export 5-star images at 2048px", "export_five_star");
+//!
+//! fn export_five_star() {
+//!     for file in files() {
+//!         if rating(file) == 5 { export(file, current_folder() + "/export", 2048); }
+//!     }
+//! }
+//!
+//! fn on_folder_opened(folder) { log(`${files().len()} images in ${folder}`); }
+//! fn on_image_loaded(path) { }
+//! ```
+//!
+//! Top-level code only runs when scripts load and may only call `command`.
+//! Functions run on the job engine against a snapshot of the folder; what
+//! they ask the viewer to show (`open_folder`, `show`, `next`, `previous`)
+//! is done once they return. Cancelling the job stops a script, and one that
+//! runs away is stopped after [`MAX_OPERATIONS`] steps or [`MAX_CALL_DEPTH`]
+//! nested calls. `export` never overwrites: an existing name gets a number.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope};
+
+use crate::duplicates::target_in;
+use crate::image_processing::convert_image;
+use crate::metadata::read_image_metadata;
+
+/// Subfolder of the config directory scripts are loaded from
+pub const SCRIPTS_FOLDER: &str = "scripts";
+
+/// Steps a script function may take before it's stopped
+pub const MAX_OPERATIONS: u64 = 50_000_000;
+
+/// Deepest a script may nest function calls
+pub const MAX_CALL_DEPTH: usize = 32;
+
+/// Steps the top level may take while a script loads, on the UI thread
+const MAX_LOAD_OPERATIONS: u64 = 100_000;
+
+pub const FOLDER_OPENED_HOOK: &str = "on_folder_opened";
+pub const IMAGE_LOADED_HOOK: &str = "on_image_loaded";
+
+/// Result of running a script function, sent back to the UI
+pub type ScriptUpdate = (String, ScriptOutcome);
+
+/// A Scripts menu entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptCommand {
+    pub label: String,
+    pub function: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Script {
+    /// File name without the extension
+    pub name: String,
+    pub commands: Vec<ScriptCommand>,
+    source: String,
+    /// Names of the functions with one parameter, for the hooks
+    hooks: Vec<String>,
+}
+
+impl Script {
+    /// Compile `source` and collect the commands its top level adds
+    pub fn load(name: &str, source: &str) -> Result<Self, String> {
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        let mut engine = limited_engine();
+        engine.set_max_operations(MAX_LOAD_OPERATIONS);
+        let registered = Rc::clone(&commands);
+        engine.register_fn("command", move |label: &str, function: &str| {
+            registered.borrow_mut().push(ScriptCommand { label: label.to_string(), function: function.to_string() });
+        });
+        let ast = engine.compile(source).map_err(|e| format!("{}: {}", name, e))?;
+        engine.run_ast(&ast).map_err(|e| format!("{}: {}", name, e))?;
+        let hooks = ast.iter_functions()
+            .filter(|function| function.params.len() == 1)
+            .map(|function| function.name.to_string())
+            .collect();
+        let commands = commands.borrow().clone();
+        Ok(Self { name: name.to_string(), commands, source: source.to_string(), hooks })
+    }
+
+    pub fn has_hook(&self, hook: &str) -> bool {
+        self.hooks.iter().any(|name| name == hook)
+    }
+
+    /// Call `function` with `args`, stopping once `cancelled` says so; errors
+    /// end up in the outcome
+    pub fn run(
+        &self,
+        function: &str,
+        args: Vec<String>,
+        context: &ScriptContext,
+        cancelled: impl Fn() -> bool + 'static,
+    ) -> ScriptOutcome {
+        let outcome = Rc::new(RefCell::new(ScriptOutcome::default()));
+        let mut engine = api_engine(context, &outcome);
+        engine.on_progress(move |_| cancelled().then_some(Dynamic::UNIT));
+        let result = engine.compile(&self.source).map_err(|e| e.to_string()).and_then(|ast| {
+            // The top level already ran when the script loaded
+            let options = CallFnOptions::new().eval_ast(false);
+            engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &ast, function, args)
+                .map_err(|e| e.to_string())
+        });
+        let mut outcome = outcome.take();
+        outcome.error = result.err().map(|e| format!("{} ({}): {}", self.name, function, e));
+        outcome
+    }
+}
+
+/// What a script sees of the viewer
+#[derive(Debug, Clone, Default)]
+pub struct ScriptContext {
+    pub folder: PathBuf,
+    /// Images in the folder, in list order
+    pub files: Vec<PathBuf>,
+    pub current_image: Option<PathBuf>,
+    /// Stars from the XMP sidecars; unrated images are missing
+    pub ratings: HashMap<PathBuf, i8>,
+    pub keywords: HashMap<PathBuf, Vec<String>>,
+}
+
+/// Something a script asked the viewer to do
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptAction {
+    OpenFolder(PathBuf),
+    Show(PathBuf),
+    Next,
+    Previous,
+}
+
+#[derive(Debug, Default)]
+pub struct ScriptOutcome {
+    pub actions: Vec<ScriptAction>,
+    /// Lines from `log` and `print`
+    pub log: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Where `export` writes `path`: into `folder`, keeping the name, as PNG for
+/// SVGs, numbered while `taken` says the name is in use. Creates `folder`.
+fn export_path(path: &Path, folder: &Path, taken: impl Fn(&Path) -> bool) -> Result<PathBuf, String> {
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")) {
+        target_in(&path.with_extension("png"), folder, taken)
+    } else {
+        target_in(path, folder, taken)
+    }
+}
+
+fn export(path: &str, folder: &str, max_size: i64) -> Result<String, Box<EvalAltResult>> {
+    let (path, folder) = (Path::new(path), Path::new(folder));
+    let output = export_path(path, folder, Path::exists)?;
+    let max_size = u32::try_from(max_size).ok().filter(|&size| size > 0);
+    convert_image(path, &output, max_size)?;
+    Ok(output.to_string_lossy().into_owned())
+}
+
+/// An engine that stops scripts running away
+fn limited_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_DEPTH);
+    engine
+}
+
+/// An engine with the viewer API, reading from `context` and recording into `outcome`
+fn api_engine(context: &ScriptContext, outcome: &Rc<RefCell<ScriptOutcome>>) -> Engine {
+    let mut engine = limited_engine();
+    engine.register_fn("command", |_: &str, _: &str| {});
+
+    let files: Array = context.files.iter().map(|path| Dynamic::from(path.to_string_lossy().into_owned())).collect();
+    engine.register_fn("files", move || files.clone());
+    let folder = context.folder.to_string_lossy().into_owned();
+    engine.register_fn("current_folder", move || folder.clone());
+    let current = context.current_image.as_ref().map(|path| path.to_string_lossy().into_owned()).unwrap_or_default();
+    engine.register_fn("current_image", move || current.clone());
+    let ratings = context.ratings.clone();
+    engine.register_fn("rating", move |path: &str| ratings.get(Path::new(path)).copied().unwrap_or(0) as i64);
+    let keywords = context.keywords.clone();
+    engine.register_fn("keywords", move |path: &str| -> Array {
+        keywords.get(Path::new(path)).into_iter().flatten().map(|keyword| Dynamic::from(keyword.clone())).collect()
+    });
+    // Never downloads on-demand files, which have no metadata
+    engine.register_fn("metadata", |path: &str| -> Dynamic {
+        let Some(metadata) = read_image_metadata(Path::new(path)) else {
+            return Dynamic::UNIT;
+        };
+        let mut map = Map::new();
+        map.insert("width".into(), (metadata.width as i64).into());
+        map.insert("height".into(), (metadata.height as i64).into());
+        map.insert("format".into(), metadata.format.into());
+        map.insert("file_size".into(), (metadata.file_size as i64).into());
+        map.into()
+    });
+    engine.register_fn("export", export);
+
+    let act = |action: fn(&str) -> ScriptAction| {
+        let outcome = Rc::clone(outcome);
+        move |path: &str| outcome.borrow_mut().actions.push(action(path))
+    };
+    engine.register_fn("open_folder", act(|path| ScriptAction::OpenFolder(PathBuf::from(path))));
+    engine.register_fn("show", act(|path| ScriptAction::Show(PathBuf::from(path))));
+    let next = Rc::clone(outcome);
+    engine.register_fn("next", move || next.borrow_mut().actions.push(ScriptAction::Next));
+    let previous = Rc::clone(outcome);
+    engine.register_fn("previous", move || previous.borrow_mut().actions.push(ScriptAction::Previous));
+
+    let log = Rc::clone(outcome);
+    engine.register_fn("log", move |text: &str| log.borrow_mut().log.push(text.to_string()));
+    let print = Rc::clone(outcome);
+    engine.on_print(move |text| print.borrow_mut().log.push(text.to_string()));
+    engine
+}
+
+/// Scripts in `dir`, sorted by name, and the errors of those that failed to load
+pub fn load_scripts(dir: &Path) -> (Vec<Script>, Vec<String>) {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default();
+    paths.retain(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("rhai")));
+    paths.sort();
+
+    let mut scripts = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        match std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", name, e)).and_then(|source| Script::load(&name, &source)) {
+            Ok(script) => scripts.push(script),
+            Err(e) => errors.push(e),
+        }
+    }
+    (scripts, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_commands_and_hooks_run_against_the_snapshot() {
+        let script = Script::load("culling", r#"
+            command("Next five-star image", "next_five_star");
+
+            fn next_five_star() {
+                for file in files() {
+                    if rating(file) == 5 { show(file); }
+                }
+                next();
+                print(current_image());
+            }
+
+            fn on_folder_opened(folder) { log(`${files().len()} in ${folder}`); }
+        "#).unwrap();
+        assert_eq!(script.commands, vec![ScriptCommand { label: "Next five-star image".to_string(), function: "next_five_star".to_string() }]);
+        assert!(script.has_hook(FOLDER_OPENED_HOOK) && !script.has_hook(IMAGE_LOADED_HOOK));
+
+        let context = ScriptContext {
+            folder: PathBuf::from("shots"),
+            files: vec![PathBuf::from("shots/a.jpg"), PathBuf::from("shots/b.jpg")],
+            current_image: Some(PathBuf::from("shots/a.jpg")),
+            ratings: HashMap::from([(PathBuf::from("shots/b.jpg"), 5)]),
+            keywords: HashMap::new(),
+        };
+        let outcome = script.run("next_five_star", Vec::new(), &context, || false);
+        assert_eq!(outcome.error, None);
+        assert_eq!(outcome.actions, vec![ScriptAction::Show(PathBuf::from("shots/b.jpg")), ScriptAction::Next]);
+        assert_eq!(outcome.log, vec!["shots/a.jpg"]);
+
+        let outcome = script.run(FOLDER_OPENED_HOOK, vec!["shots".to_string()], &context, || false);
+        assert_eq!(outcome.log, vec!["2 in shots"]);
+        assert!(script.run("missing", Vec::new(), &context, || false).error.is_some());
+        assert!(Script::load("broken", "command(").is_err());
+    }
+
+    #[test]
+    fn test_runaway_scripts_stop_and_exports_keep_earlier_files() {
+        let script = Script::load("runaway", r#"
+            fn spin() { loop { } }
+            fn recurse(depth) { recurse(depth + 1) }
+        "#).unwrap();
+        let context = ScriptContext::default();
+        let cancelled = script.run("spin", Vec::new(), &context, || true);
+        assert!(cancelled.error.is_some_and(|e| e.contains("terminated")));
+        assert!(script.run("spin", Vec::new(), &context, || false).error.is_some());
+        assert!(script.run("recurse", vec!["0".to_string()], &context, || false).error.is_some());
+        assert!(Script::load("endless", "loop { }").is_err());
+
+        let dir = TempDir::new("script_export");
+        let taken = |path: &Path| path.ends_with("a.png");
+        assert_eq!(export_path(Path::new("icons/a.svg"), &dir, taken).unwrap(), dir.join("a (2).png"));
+        assert_eq!(export_path(Path::new("shots/b.jpg"), &dir, taken).unwrap(), dir.join("b.jpg"));
+    }
+}
@@ -0,0 +1,480 @@
+//! Metadata search across previously visited folders
+//!
+//! Each visited folder is indexed in the background: file name, size,
+//! dimensions, format, EXIF camera fields, rating and tags. The records are
+//! kept in a SQLite database, `search_index.sqlite` in the config directory,
+//! and a file is only read again when its size or modification time changes.
+//! Saving rewrites only the folders indexed since the last save, in one
+//! transaction, so a crash never leaves a partial index behind. Queries run
+//! against the records in memory, so results appear as you type.
+//!
+//! A query is a list of terms that must all match:
+//!
+//! - `iso>1600`, `width>=4000`, `rating>=4`, `size<2` (MB), `mp>20`,
+//!   `f<2.8`, `focal>=85`, `year=2023`
+//! - `tag:harbour`, `camera:canon`, `model:x100`, `format:png`
+//! - `.nef` for the extension, `2023` for the year taken (or modified)
+//! - anything else is looked for in the path, tags and EXIF values
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use rusqlite::{Connection, params};
+
+use crate::file_locality::FileInfo;
+use crate::metadata::{read_header, read_image_metadata};
+use crate::metadata_providers::{exif_fields, format_utc};
+use crate::settings::config_dir;
+use crate::xmp_sidecar::read_sidecar_data;
+
+const INDEX_FILE: &str = "search_index.sqlite";
+
+/// A folder and fresh records of its images
+pub type FolderRecords = (PathBuf, Vec<IndexedImage>);
+
+/// What the index knows about one image
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct IndexedImage {
+    pub path: PathBuf,
+    /// Modification time in seconds since the Unix epoch
+    pub modified: u64,
+    pub file_size: u64,
+    /// 0 when unknown, e.g. for cloud placeholders, which are never read
+    pub width: u32,
+    pub height: u32,
+    pub format: String,
+    pub rating: i8,
+    pub keywords: Vec<String>,
+    /// EXIF fields by label, as shown in the metadata panel
+    pub exif: Vec<(String, String)>,
+}
+
+impl IndexedImage {
+    /// Record an image; `previous` is reused when the file hasn't changed
+    pub fn read(path: &Path, previous: Option<&IndexedImage>) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_secs());
+        let (rating, keywords) = read_sidecar_data(path);
+        if let Some(previous) = previous.filter(|p| p.modified == modified && p.file_size == metadata.len()) {
+            // The sidecar may have changed without the image changing
+            return Some(Self { rating: rating.stars, keywords, ..previous.clone() });
+        }
+        let mut image = Self {
+            path: path.to_path_buf(),
+            modified,
+            file_size: metadata.len(),
+            format: path.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default(),
+            rating: rating.stars,
+            keywords,
+            ..Default::default()
+        };
+        // Placeholders are indexed by name only, so indexing never downloads
+        if !FileInfo::new(path.to_path_buf()).will_trigger_download() {
+            if let Some(metadata) = read_image_metadata(path) {
+                image.width = metadata.width;
+                image.height = metadata.height;
+            }
+            image.exif = exif_fields(&read_header(path))
+                .into_iter()
+                .map(|(label, value)| (label.to_string(), value))
+                .collect();
+        }
+        Some(image)
+    }
+
+    fn exif_value(&self, label: &str) -> Option<&str> {
+        self.exif.iter().find(|(l, _)| l == label).map(|(_, value)| value.as_str())
+    }
+
+    /// Leading number of an EXIF value, e.g. 2.8 for "f/2.8"
+    fn exif_number(&self, label: &str) -> Option<f64> {
+        let value = self.exif_value(label)?;
+        let start = value.find(|c: char| c.is_ascii_digit())?;
+        let number: String = value[start..].chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+        number.parse().ok()
+    }
+
+    /// Year taken, or modified when the camera didn't record it
+    pub fn year(&self) -> Option<i32> {
+        let date = self.exif_value("Date taken").or_else(|| self.exif_value("Date")).map(str::to_string);
+        let date = date.unwrap_or_else(|| format_utc(self.modified));
+        date.get(..4)?.parse().ok()
+    }
+
+    fn number(&self, field: &str) -> Option<f64> {
+        Some(match field {
+            "iso" => self.exif_number("ISO")?,
+            "width" | "w" => self.width as f64,
+            "height" | "h" => self.height as f64,
+            "mp" | "megapixels" => self.width as f64 * self.height as f64 / 1_000_000.0,
+            "rating" | "stars" => self.rating as f64,
+            "size" => self.file_size as f64 / (1024.0 * 1024.0),
+            "year" => self.year()? as f64,
+            "f" | "aperture" => self.exif_number("Aperture")?,
+            "focal" => self.exif_number("Focal length")?,
+            _ => return None,
+        })
+    }
+
+    fn text(&self, field: &str) -> Option<Vec<&str>> {
+        Some(match field {
+            "tag" | "tags" | "keyword" => self.keywords.iter().map(String::as_str).collect(),
+            "camera" | "make" => self.exif_value("Camera make").into_iter().collect(),
+            "model" => self.exif_value("Camera model").into_iter().collect(),
+            "format" | "ext" => vec![self.format.as_str()],
+            "name" => self.path.file_name().and_then(|name| name.to_str()).into_iter().collect(),
+            _ => return None,
+        })
+    }
+
+    fn contains(&self, term: &str) -> bool {
+        let contains = |text: &str| text.to_lowercase().contains(term);
+        contains(&self.path.to_string_lossy())
+            || self.keywords.iter().any(|keyword| contains(keyword))
+            || self.exif.iter().any(|(_, value)| contains(value))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn holds(self, value: f64, target: f64) -> bool {
+        match self {
+            Comparison::Less => value < target,
+            Comparison::LessOrEqual => value <= target,
+            Comparison::Equal => (value - target).abs() < 1e-6,
+            Comparison::GreaterOrEqual => value >= target,
+            Comparison::Greater => value > target,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Number { field: String, comparison: Comparison, value: f64 },
+    Field { field: String, value: String },
+    Extension(String),
+    Year(i32),
+    Text(String),
+}
+
+impl Term {
+    fn parse(term: &str) -> Self {
+        let term = term.to_lowercase();
+        for (operator, comparison) in [
+            (">=", Comparison::GreaterOrEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">", Comparison::Greater),
+            ("<", Comparison::Less),
+            ("=", Comparison::Equal),
+            (":", Comparison::Equal),
+        ] {
+            let Some((field, value)) = term.split_once(operator) else {
+                continue;
+            };
+            if field.is_empty() || value.is_empty() {
+                break;
+            }
+            return match value.parse::<f64>() {
+                Ok(value) if IndexedImage::default().text(field).is_none() => {
+                    Term::Number { field: field.to_string(), comparison, value }
+                }
+                _ => Term::Field { field: field.to_string(), value: value.to_string() },
+            };
+        }
+        if let Some(extension) = term.strip_prefix('.').filter(|ext| !ext.is_empty()) {
+            return Term::Extension(extension.to_string());
+        }
+        match term.parse::<i32>() {
+            Ok(year) if term.len() == 4 && (1826..=2200).contains(&year) => Term::Year(year),
+            _ => Term::Text(term),
+        }
+    }
+
+    fn matches(&self, image: &IndexedImage) -> bool {
+        match self {
+            Term::Number { field, comparison, value } => {
+                image.number(field).is_some_and(|number| comparison.holds(number, *value))
+            }
+            Term::Field { field, value } => match image.text(field) {
+                Some(texts) => texts.iter().any(|text| text.to_lowercase().contains(value.as_str())),
+                // Not a field name, e.g. "12:30" in a file name
+                None => image.contains(&format!("{}:{}", field, value)),
+            },
+            Term::Extension(extension) => image.format == *extension,
+            Term::Year(year) => image.year() == Some(*year) || image.contains(&year.to_string()),
+            Term::Text(text) => image.contains(text),
+        }
+    }
+}
+
+/// Images of every indexed folder
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchIndex {
+    images: BTreeMap<PathBuf, IndexedImage>,
+    /// Folders replaced since the index was last saved
+    unsaved: BTreeSet<PathBuf>,
+}
+
+impl SearchIndex {
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    /// Records of the images directly inside `dir`
+    pub fn folder(&self, dir: &Path) -> HashMap<PathBuf, IndexedImage> {
+        self.images
+            .iter()
+            .filter(|(path, _)| path.parent() == Some(dir))
+            .map(|(path, image)| (path.clone(), image.clone()))
+            .collect()
+    }
+
+    /// Replace the records of `dir` with a fresh scan of it
+    pub fn replace_folder(&mut self, dir: &Path, images: Vec<IndexedImage>) {
+        self.images.retain(|path, _| path.parent() != Some(dir));
+        self.images.extend(images.into_iter().map(|image| (image.path.clone(), image)));
+        self.unsaved.insert(dir.to_path_buf());
+    }
+
+    /// The folders replaced since the last call, with their records, for
+    /// [`save_folders`]
+    pub fn take_unsaved(&mut self) -> Vec<FolderRecords> {
+        std::mem::take(&mut self.unsaved)
+            .into_iter()
+            .map(|dir| {
+                let images = self.folder(&dir).into_values().collect();
+                (dir, images)
+            })
+            .collect()
+    }
+
+    /// Images matching every term of `query`, best rated first
+    pub fn search(&self, query: &str) -> Vec<&IndexedImage> {
+        let terms: Vec<Term> = query.split_whitespace().map(Term::parse).collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+        let mut results: Vec<&IndexedImage> = self.images.values().filter(|image| terms.iter().all(|term| term.matches(image))).collect();
+        results.sort_by_key(|image| std::cmp::Reverse(image.rating));
+        results
+    }
+
+    pub fn load_from(file: &Path) -> Result<Self, String> {
+        if !file.exists() {
+            return Ok(Self::default());
+        }
+        let read = || -> rusqlite::Result<Self> {
+            let connection = open(file)?;
+            let mut statement = connection.prepare(
+                "SELECT path, modified, file_size, width, height, format, rating, keywords, exif FROM images",
+            )?;
+            let images = statement
+                .query_map([], |row| {
+                    let exif: String = row.get(8)?;
+                    Ok(IndexedImage {
+                        path: PathBuf::from(row.get::<_, String>(0)?),
+                        modified: row.get::<_, i64>(1)? as u64,
+                        file_size: row.get::<_, i64>(2)? as u64,
+                        width: row.get(3)?,
+                        height: row.get(4)?,
+                        format: row.get(5)?,
+                        rating: row.get(6)?,
+                        keywords: split_list(&row.get::<_, String>(7)?),
+                        exif: split_list(&exif)
+                            .into_iter()
+                            .filter_map(|pair| pair.split_once('=').map(|(label, value)| (label.to_string(), value.to_string())))
+                            .collect(),
+                    })
+                })?
+                .map(|image| image.map(|image| (image.path.clone(), image)))
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(Self { images, unsaved: BTreeSet::new() })
+        };
+        read().map_err(|e| format!("Failed to read the search index: {}", e))
+    }
+
+    /// The index saved by earlier sessions, if any
+    pub fn load() -> Self {
+        default_file()
+            .and_then(|file| Self::load_from(&file).map_err(|e| eprintln!("Warning: {}", e)).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Open the database in `file`, creating it and its table if needed
+fn open(file: &Path) -> rusqlite::Result<Connection> {
+    let connection = Connection::open(file)?;
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS images (
+            path TEXT PRIMARY KEY,
+            folder TEXT NOT NULL,
+            modified INTEGER NOT NULL,
+            file_size INTEGER NOT NULL,
+            width INTEGER NOT NULL,
+            height INTEGER NOT NULL,
+            format TEXT NOT NULL,
+            rating INTEGER NOT NULL,
+            keywords TEXT NOT NULL,
+            exif TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS images_by_folder ON images (folder);",
+    )?;
+    Ok(connection)
+}
+
+/// Replace the saved records of each folder in `folders`, all in one transaction
+pub fn save_folders_to(file: &Path, folders: &[FolderRecords]) -> Result<(), String> {
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let save = || -> rusqlite::Result<()> {
+        let mut connection = open(file)?;
+        let transaction = connection.transaction()?;
+        {
+            let mut remove = transaction.prepare("DELETE FROM images WHERE folder = ?1")?;
+            let mut insert = transaction.prepare(
+                "INSERT OR REPLACE INTO images (path, folder, modified, file_size, width, height, format, rating, keywords, exif)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )?;
+            for (dir, images) in folders {
+                let folder = dir.to_string_lossy();
+                remove.execute([&folder])?;
+                for image in images {
+                    let exif: Vec<String> = image.exif.iter().map(|(label, value)| format!("{}={}", label, value)).collect();
+                    insert.execute(params![
+                        image.path.to_string_lossy(),
+                        folder,
+                        image.modified as i64,
+                        image.file_size as i64,
+                        image.width,
+                        image.height,
+                        image.format,
+                        image.rating,
+                        join_list(&image.keywords),
+                        join_list(&exif),
+                    ])?;
+                }
+            }
+        }
+        transaction.commit()
+    };
+    save().map_err(|e| format!("Failed to save the search index: {}", e))
+}
+
+/// Save the records from [`SearchIndex::take_unsaved`] to the config directory
+pub fn save_folders(folders: &[FolderRecords]) -> Result<(), String> {
+    let file = default_file().ok_or("No config directory available")?;
+    save_folders_to(&file, folders)
+}
+
+fn default_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(INDEX_FILE))
+}
+
+/// List items are separated by `|`, which is escaped with a backslash
+fn join_list(items: &[String]) -> String {
+    items.iter().map(|item| item.replace('\\', "\\\\").replace('|', "\\p")).collect::<Vec<_>>().join("|")
+}
+
+fn split_list(text: &str) -> Vec<String> {
+    if text.is_empty() {
+        return Vec::new();
+    }
+    text.split('|').map(unescape).collect()
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('p') => out.push('|'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn photo(path: &str, iso: &str, date: &str, keywords: &[&str]) -> IndexedImage {
+        IndexedImage {
+            path: PathBuf::from(path),
+            width: 6000,
+            height: 4000,
+            format: path.rsplit('.').next().unwrap().to_string(),
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            exif: vec![("ISO".to_string(), iso.to_string()), ("Date taken".to_string(), date.to_string())],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_queries() {
+        let mut index = SearchIndex::default();
+        index.replace_folder(Path::new("/photos"), vec![
+            photo("/photos/night.nef", "3200", "2023:08:01 21:00:00", &["harbour"]),
+            photo("/photos/day.nef", "100", "2023:08:02 12:00:00", &["harbour", "boats"]),
+            photo("/photos/old.jpg", "3200", "2019:01:01 10:00:00", &[]),
+        ]);
+        let names = |query: &str| -> Vec<String> {
+            let mut names: Vec<String> = index.search(query).iter().map(|image| image.path.file_name().unwrap().to_string_lossy().into_owned()).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names("iso>1600 2023 .nef"), ["night.nef"]);
+        assert_eq!(names("tag:boats"), ["day.nef"]);
+        assert_eq!(names("harbour iso<=100"), ["day.nef"]);
+        assert_eq!(names("mp>20 year<2020"), ["old.jpg"]);
+        assert!(names("").is_empty());
+
+        // Rescanning a folder replaces its records
+        index.replace_folder(Path::new("/photos"), Vec::new());
+        assert_eq!(index.len(), 0);
+    }
+
+    #[test]
+    fn test_saved_folders_round_trip() {
+        let mut index = SearchIndex::default();
+        let mut image = photo("/photos/a\tb.jpg", "400", "2024:02:03 04:05:06", &["left|right", "back\\slash"]);
+        image.rating = -1;
+        index.replace_folder(Path::new("/photos"), vec![image, photo("/photos/c.jpg", "100", "", &[])]);
+        index.replace_folder(Path::new("/other"), vec![photo("/other/d.png", "800", "", &["x"])]);
+
+        let dir = TempDir::new("search_index");
+        let file = dir.join(INDEX_FILE);
+        save_folders_to(&file, &index.take_unsaved()).unwrap();
+        assert!(index.take_unsaved().is_empty());
+        assert_eq!(SearchIndex::load_from(&file).unwrap(), index);
+
+        // Only the rescanned folder is written again
+        index.replace_folder(Path::new("/photos"), vec![photo("/photos/e.jpg", "200", "", &[])]);
+        let unsaved = index.take_unsaved();
+        assert_eq!(unsaved.len(), 1);
+        save_folders_to(&file, &unsaved).unwrap();
+        let loaded = SearchIndex::load_from(&file).unwrap();
+        assert_eq!(loaded, index);
+        assert_eq!(loaded.len(), 2);
+
+        assert_eq!(SearchIndex::load_from(&dir.join("missing.sqlite")).unwrap(), SearchIndex::default());
+    }
+}
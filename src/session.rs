@@ -0,0 +1,208 @@
+//! Session recovery after an unclean exit
+//!
+//! While the viewer runs, the open folder, displayed image, multi-selection
+//! and list filter are written to `session_<pid>.txt` every few seconds. A
+//! clean exit removes the file, so finding one at startup means that session
+//! crashed or was killed, and it can be offered for restoring. Each running
+//! viewer holds a lock on its `session_<pid>.lock`, so one instance never
+//! takes the session of another that is still open.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::preferences::Preferences;
+use crate::settings::config_dir;
+
+/// Start of the names of each session's file and lock
+const SESSION_PREFIX: &str = "session_";
+
+/// How often the session is saved while it changes
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionState {
+    pub folder: PathBuf,
+    pub displayed: Option<PathBuf>,
+    /// Images selected for batch operations
+    pub selection: Vec<PathBuf>,
+    pub filter: String,
+}
+
+impl SessionState {
+    fn to_preferences(&self) -> Preferences {
+        let mut preferences = Preferences::default();
+        preferences.set("folder", self.folder.display());
+        if let Some(displayed) = &self.displayed {
+            preferences.set("displayed", displayed.display());
+        }
+        for (index, path) in self.selection.iter().enumerate() {
+            preferences.set(&format!("selection.{}", index), path.display());
+        }
+        if !self.filter.is_empty() {
+            preferences.set("filter", &self.filter);
+        }
+        preferences
+    }
+
+    fn from_preferences(preferences: &Preferences) -> Option<Self> {
+        let path = |key: &str| preferences.get::<String>(key).map(PathBuf::from);
+        Some(Self {
+            folder: path("folder")?,
+            displayed: path("displayed"),
+            selection: (0..).map_while(|index| path(&format!("selection.{}", index))).collect(),
+            filter: preferences.get("filter").unwrap_or_default(),
+        })
+    }
+}
+
+/// Saves the running session, at most every [`SAVE_INTERVAL`]
+#[derive(Debug)]
+pub struct SessionRecorder {
+    dir: Option<PathBuf>,
+    id: u32,
+    /// Held while the session runs
+    lock: Option<std::fs::File>,
+    last_save: Option<Instant>,
+    saved: Option<SessionState>,
+}
+
+impl Default for SessionRecorder {
+    fn default() -> Self {
+        Self::in_dir(config_dir(), std::process::id())
+    }
+}
+
+fn session_file(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}{}.txt", SESSION_PREFIX, id))
+}
+
+fn lock_file(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}{}.lock", SESSION_PREFIX, id))
+}
+
+impl SessionRecorder {
+    /// Recorder for the session `id`, keeping its file in `dir`
+    pub fn in_dir(dir: Option<PathBuf>, id: u32) -> Self {
+        let lock = dir.as_deref().and_then(|dir| {
+            std::fs::create_dir_all(dir).ok()?;
+            let file = std::fs::File::create(lock_file(dir, &id.to_string())).ok()?;
+            file.try_lock().ok()?;
+            Some(file)
+        });
+        Self { dir, id, lock, last_save: None, saved: None }
+    }
+
+    fn file(&self) -> Option<PathBuf> {
+        self.dir.as_deref().map(|dir| session_file(dir, &self.id.to_string()))
+    }
+
+    /// Whether it is time to check for changes
+    pub fn is_due(&self) -> bool {
+        self.last_save.is_none_or(|saved| saved.elapsed() >= SAVE_INTERVAL)
+    }
+
+    /// Save `state` if it changed since the last save
+    pub fn record(&mut self, state: SessionState) -> Result<(), String> {
+        self.last_save = Some(Instant::now());
+        if self.saved.as_ref() == Some(&state) {
+            return Ok(());
+        }
+        if let Some(file) = self.file() {
+            state.to_preferences().save_to(&file).map_err(|e| format!("Failed to save session: {}", e))?;
+        }
+        self.saved = Some(state);
+        Ok(())
+    }
+
+    /// Forget the session on a clean exit
+    pub fn finish(&mut self) {
+        if let Some(file) = self.file() {
+            let _ = std::fs::remove_file(file);
+        }
+        if let (Some(dir), Some(lock)) = (&self.dir, self.lock.take()) {
+            drop(lock);
+            let _ = std::fs::remove_file(lock_file(dir, &self.id.to_string()));
+        }
+    }
+
+    /// The latest session left behind by an unclean exit, removed once read.
+    /// Sessions whose viewer is still running are left alone.
+    pub fn take_unclean(&self) -> Option<SessionState> {
+        let dir = self.dir.as_deref()?;
+        let own = self.id.to_string();
+        let mut ended: Vec<(std::time::SystemTime, String)> = std::fs::read_dir(dir)
+            .ok()?
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let id = name.strip_prefix(SESSION_PREFIX)?.strip_suffix(".txt")?.to_string();
+                let modified = entry.metadata().and_then(|metadata| metadata.modified()).ok()?;
+                (id != own).then_some((modified, id))
+            })
+            .collect();
+        ended.sort();
+        while let Some((_, id)) = ended.pop() {
+            let lock = lock_file(dir, &id);
+            // A session that is still running holds its lock
+            let held = std::fs::File::open(&lock).is_ok_and(|file| file.try_lock().is_err());
+            if held {
+                continue;
+            }
+            let file = session_file(dir, &id);
+            let state = read_session(&file);
+            let _ = std::fs::remove_file(&file);
+            let _ = std::fs::remove_file(&lock);
+            if state.is_some() {
+                return state;
+            }
+        }
+        None
+    }
+}
+
+fn read_session(file: &Path) -> Option<SessionState> {
+    if !file.exists() {
+        return None;
+    }
+    SessionState::from_preferences(&Preferences::load_from(file).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_unclean_session_round_trip() {
+        let dir = TempDir::new("session");
+        let in_dir = |id| SessionRecorder::in_dir(Some(dir.to_path_buf()), id);
+        let state = SessionState {
+            folder: PathBuf::from("/photos/trip"),
+            displayed: Some(PathBuf::from("/photos/trip/b.jpg")),
+            selection: vec![PathBuf::from("/photos/trip/a.jpg"), PathBuf::from("/photos/trip/b.jpg")],
+            filter: "harbour".to_string(),
+        };
+
+        let mut recorder = in_dir(1);
+        assert!(recorder.is_due());
+        recorder.record(state.clone()).unwrap();
+        assert!(!recorder.is_due());
+
+        // Another instance leaves a running session alone
+        let mut other = in_dir(2);
+        assert_eq!(other.take_unclean(), None);
+        other.record(SessionState::default()).unwrap();
+        other.finish();
+        assert!(dir.join("session_1.txt").exists(), "one instance's exit keeps the other's session");
+
+        // A crash leaves the file behind; it is offered once
+        drop(recorder);
+        let mut next_launch = in_dir(3);
+        assert_eq!(next_launch.take_unclean(), Some(state.clone()));
+        assert_eq!(next_launch.take_unclean(), None);
+
+        next_launch.record(state).unwrap();
+        next_launch.finish();
+        assert_eq!(in_dir(4).take_unclean(), None);
+    }
+}
@@ -2,6 +2,18 @@
 
 use sysinfo::System;
 
+use crate::backdrop::PreviewBackground;
+use crate::cli::parse_hex_color;
+use crate::preferences::Preferences;
+use crate::network_cost::MeteredConnection;
+use crate::soft_proof::SoftProofOptions;
+use crate::theme::Theme;
+use crate::slideshow::SkipRules;
+use crate::fit_mode::{FitMode, OpeningZoom};
+use crate::toolbar::ToolbarButton;
+use crate::sharpness::DEFAULT_BLUR_THRESHOLD;
+use crate::format_decoders::registered_extensions;
+
 pub const DEFAULT_SUPPORTED_FORMATS: &[&str] = &["png", "jpg", "jpeg", "svg", "bmp", "gif"];
 
 #[derive(Debug, Clone, PartialEq)]
@@ -14,21 +26,187 @@ pub enum FilenameTruncationStyle {
     FadeEnd,
 }
 
+/// Filter used to scale down images over the large-image limit, from fastest to best
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeFilter {
+    Nearest,
+    Bilinear,
+    CatmullRom,
+    Lanczos3,
+}
+
+impl ResizeFilter {
+    pub const ALL: [ResizeFilter; 4] = [ResizeFilter::Nearest, ResizeFilter::Bilinear, ResizeFilter::CatmullRom, ResizeFilter::Lanczos3];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ResizeFilter::Nearest => "Fastest (nearest neighbor)",
+            ResizeFilter::Bilinear => "Fast (bilinear)",
+            ResizeFilter::CatmullRom => "Good (Catmull-Rom)",
+            ResizeFilter::Lanczos3 => "Best (Lanczos)",
+        }
+    }
+
+    pub fn filter_type(&self) -> image::imageops::FilterType {
+        match self {
+            ResizeFilter::Nearest => image::imageops::FilterType::Nearest,
+            ResizeFilter::Bilinear => image::imageops::FilterType::Triangle,
+            ResizeFilter::CatmullRom => image::imageops::FilterType::CatmullRom,
+            ResizeFilter::Lanczos3 => image::imageops::FilterType::Lanczos3,
+        }
+    }
+}
+
+/// Range of the large-image limit, in pixels along the longest side
+pub const MIN_LARGE_IMAGE_DIMENSION: u32 = 2048;
+pub const MAX_LARGE_IMAGE_DIMENSION: u32 = 32768;
+
+/// Range of the interface scale setting (1.0 is 100%)
+pub const MIN_UI_SCALE: f32 = 0.75;
+pub const MAX_UI_SCALE: f32 = 2.0;
+
+/// Number of recently used SVG recolor colors that are remembered
+pub const MAX_RECENT_COLORS: usize = 8;
+
+/// Preference keys for the recolor colors kept between sessions
+const RECENT_COLORS_KEY: &str = "svg_recent_colors";
+const SELECTED_PALETTE_KEY: &str = "svg_selected_palette";
+const PALETTE_PREFIX: &str = "svg_palette.";
+
+/// A named set of colors offered as SVG recolor targets
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorPalette {
+    pub name: String,
+    pub colors: Vec<[u8; 3]>,
+}
+
+impl ColorPalette {
+    pub fn new(name: &str, colors: &[[u8; 3]]) -> Self {
+        Self {
+            name: name.to_string(),
+            colors: colors.to_vec(),
+        }
+    }
+
+    /// "Name|#rrggbb #rrggbb", as kept in the preferences
+    fn to_preference(&self) -> String {
+        format!("{}|{}", self.name, colors_to_preference(&self.colors))
+    }
+
+    fn from_preference(value: &str) -> Option<Self> {
+        let (name, colors) = value.split_once('|')?;
+        let name = name.trim();
+        (!name.is_empty()).then(|| Self {
+            name: name.to_string(),
+            colors: colors_from_preference(colors),
+        })
+    }
+}
+
+fn colors_to_preference(colors: &[[u8; 3]]) -> String {
+    colors.iter().map(|[r, g, b]| format!("#{:02x}{:02x}{:02x}", r, g, b)).collect::<Vec<_>>().join(" ")
+}
+
+fn colors_from_preference(value: &str) -> Vec<[u8; 3]> {
+    value.split_whitespace().filter_map(|color| parse_hex_color(color).ok()).collect()
+}
+
+/// Replaces one source color with its own target when recoloring SVGs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SvgColorMapping {
+    pub from: [u8; 3],
+    pub to: [u8; 3],
+}
+
+/// Palette presets available out of the box
+pub fn default_palettes() -> Vec<ColorPalette> {
+    vec![
+        ColorPalette::new("Grayscale", &[
+            [0, 0, 0], [64, 64, 64], [128, 128, 128], [192, 192, 192], [255, 255, 255],
+        ]),
+        ColorPalette::new("Basic", &[
+            [220, 53, 69], [253, 126, 20], [255, 193, 7], [40, 167, 69], [23, 162, 184], [0, 123, 255], [111, 66, 193],
+        ]),
+        ColorPalette::new("Status", &[
+            [46, 204, 113], [241, 196, 15], [231, 76, 60], [52, 152, 219],
+        ]),
+    ]
+}
+
 #[derive(Debug, Clone)]
 pub struct ImageLoadingSettings {
     pub skip_large_images: bool,
     pub auto_scale_large_images: bool,
-    pub auto_scale_to_fit: bool, // Scale images to fit within the display frame
+    pub photo_zoom: OpeningZoom, // Size photos open at, unless changed for the image
+    pub icon_zoom: OpeningZoom, // Size icons and SVGs open at
+    pub fit_mode: FitMode, // How images are fitted when opened at OpeningZoom::Fit
+    pub snap_zoom: bool, // Zoom buttons step through fit_mode::ZOOM_STEPS
+    pub panorama_scroll_speed: f32, // Points per second for panorama auto-scroll
     pub max_file_size_mb: Option<u32>, // None means no limit
     pub supported_formats: Vec<String>,
     pub svg_recolor_enabled: bool,
     pub svg_target_color: [u8; 3], // RGB values
+    pub recent_svg_colors: Vec<[u8; 3]>, // Most recent first, at most MAX_RECENT_COLORS
+    pub svg_palettes: Vec<ColorPalette>,
+    pub selected_palette: usize, // Index into svg_palettes shown in the SVG options
+    pub svg_preserved_colors: Vec<[u8; 3]>, // Colors left alone by recoloring
+    pub svg_color_mappings: Vec<SvgColorMapping>, // Checked before the target color; applied even in currentColor-only mode
+    pub svg_recolor_current_color_only: bool, // Only replace currentColor, keeping explicit colors
     pub debug_file_locality_detection: bool, // Show debug info for file locality detection
+    pub capture_error_reports: bool, // Save a diagnostics report when an image fails to decode
     // Filename display settings
     pub truncate_long_filenames: bool,
     pub max_filename_length: usize,
     pub truncation_style: FilenameTruncationStyle,
     pub ellipsis_char: String, // Customizable ellipsis character
+    pub reduce_work_on_battery: bool, // Limit prefetching, decode threads and thumbnails on battery
+    pub battery_max_fps: Option<u32>, // Frame rate cap on battery, None for uncapped
+    pub idle_delay_secs: u32, // Seconds without input before idle-only background jobs run
+    // Share targets (empty disables the target)
+    pub share_folder: String,
+    pub share_command: String, // `{path}` is replaced with the image path
+    // Icons
+    pub icon_directory: String, // SVG files overriding the built-in icons by name; empty uses the built-ins
+    pub icon_hot_reload: bool, // Reload the icon directory when its files change
+    // Toolbar
+    pub show_toolbar: bool,
+    pub hidden_toolbar_buttons: Vec<ToolbarButton>, // Buttons taken off the toolbar
+    pub link_speed_mbps: Option<f64>, // Used for download estimates until real downloads have been measured
+    pub metered_connection: MeteredConnection, // On a metered connection every download asks first
+    pub stack_images: bool, // RAW+JPEG pairs and bursts collapse into one row of the file list
+    // Keyboard navigation
+    pub wrap_navigation: bool, // Moving past the last image continues from the first, and vice versa
+    pub skip_on_demand_navigation: bool, // Previous/next image steps over files that would need downloading
+    pub page_jump_size: usize, // Images skipped by PageUp/PageDown
+    // Mouse navigation
+    pub mouse_button_navigation: bool, // Back/forward buttons select the previous/next image
+    pub gesture_navigation: bool, // Right-drag left/right over the image selects the next/previous image
+    pub double_click_zoom: bool, // Double-click toggles fit-to-window and 100%
+    pub touch_gestures: bool, // Pinch zoom, two-finger pan and swipe navigation
+    pub show_navigator: bool, // Minimap in the corner when the image is larger than the view
+    // Pixel inspection
+    pub pixel_grid: bool, // Lines between the pixels once zoomed past 800%
+    pub pixel_rulers: bool, // Image pixel rulers along the top and left of the view
+    pub pixel_crosshair: bool, // Guides through the pointer with its pixel coordinates
+    pub preview_background: PreviewBackground, // Auto picks a contrasting backdrop for low-contrast transparent images
+    pub soft_proof_enabled: bool, // Show the image as it would print
+    pub soft_proof: SoftProofOptions,
+    pub theme: Theme,
+    pub ui_scale: f32, // Size of text and widgets; saved in the preferences file
+    pub show_status_text: bool, // Show text next to status icons, not only in tooltips
+    pub slideshow_interval_secs: u32, // How long each slide is shown
+    pub sequence_fps: u32, // Playback rate of numbered frame sequences
+    pub slideshow_skip: SkipRules,
+    pub slideshow_read_ahead: usize, // On-demand slides downloaded ahead of time when they aren't skipped
+    pub slideshow_download_limit_mb: Option<u32>, // Download budget per slideshow session, None for unlimited
+    // Performance tuning, suggested after the first benchmark
+    pub thumbnail_size: u32, // Longest side of cloud thumbnails requested from the server
+    pub max_decode_threads: Option<usize>, // None uses every core but one; applied at startup
+    pub large_image_max_dimension: u32, // Images larger than this are scaled down or skipped
+    pub resize_filter: ResizeFilter, // Quality of scaling down large images
+    pub animations: bool, // Animate collapsing headers, scroll areas and other widgets
+    pub detect_blur: bool, // Measure sharpness of local images while idle; off by default since it decodes every image
+    pub blur_threshold: f32, // Sharpness below which an image is flagged as possibly blurry
 }
 
 impl Default for ImageLoadingSettings {
@@ -36,19 +214,72 @@ impl Default for ImageLoadingSettings {
         Self {
             skip_large_images: false,
             auto_scale_large_images: true,
-            auto_scale_to_fit: true, // Enabled by default
+            photo_zoom: OpeningZoom::Fit,
+            icon_zoom: OpeningZoom::IntegerMultiple,
+            fit_mode: FitMode::Auto,
+            snap_zoom: true,
+            panorama_scroll_speed: 80.0,
             max_file_size_mb: None, // Use dynamic calculation by default
             supported_formats: DEFAULT_SUPPORTED_FORMATS
                 .iter()
                 .map(|s| s.to_string())
+                .chain(registered_extensions())
                 .collect(),
             svg_recolor_enabled: false,
             svg_target_color: [128, 128, 128], // Default gray
+            recent_svg_colors: Vec::new(),
+            svg_palettes: default_palettes(),
+            selected_palette: 0,
+            svg_preserved_colors: Vec::new(),
+            svg_color_mappings: Vec::new(),
+            svg_recolor_current_color_only: false,
             debug_file_locality_detection: false, // Disabled by default
+            capture_error_reports: false,
             truncate_long_filenames: true, // Enabled by default
             max_filename_length: 25, // Default max length
             truncation_style: FilenameTruncationStyle::Ellipsis, // Default truncation style
             ellipsis_char: "…".to_string(), // Default ellipsis character
+            reduce_work_on_battery: true, // Enabled by default
+            battery_max_fps: Some(30),
+            idle_delay_secs: 60, // One minute without input
+            share_folder: String::new(),
+            share_command: String::new(),
+            icon_directory: String::new(),
+            icon_hot_reload: false,
+            show_toolbar: true,
+            hidden_toolbar_buttons: Vec::new(),
+            link_speed_mbps: None, // Unknown until configured or measured
+            metered_connection: MeteredConnection::Detect,
+            stack_images: true,
+            wrap_navigation: false,
+            skip_on_demand_navigation: false,
+            page_jump_size: 10,
+            mouse_button_navigation: true,
+            gesture_navigation: true,
+            double_click_zoom: true,
+            touch_gestures: true,
+            show_navigator: true,
+            pixel_grid: true,
+            pixel_rulers: false,
+            pixel_crosshair: false,
+            preview_background: PreviewBackground::Auto,
+            soft_proof_enabled: false,
+            soft_proof: SoftProofOptions::default(),
+            theme: Theme::default(),
+            ui_scale: 1.0,
+            show_status_text: false,
+            slideshow_interval_secs: 5,
+            sequence_fps: 24,
+            slideshow_skip: SkipRules::default(),
+            slideshow_read_ahead: 3,
+            slideshow_download_limit_mb: Some(500),
+            thumbnail_size: 800,
+            max_decode_threads: None,
+            large_image_max_dimension: 8192,
+            resize_filter: ResizeFilter::Lanczos3,
+            animations: true,
+            detect_blur: false,
+            blur_threshold: DEFAULT_BLUR_THRESHOLD,
         }
     }
 }
@@ -75,11 +306,60 @@ impl ImageLoadingSettings {
         self
     }
 
-    pub fn auto_scale_to_fit(mut self, auto_scale: bool) -> Self {
-        self.auto_scale_to_fit = auto_scale;
+    pub fn photo_zoom(mut self, zoom: OpeningZoom) -> Self {
+        self.photo_zoom = zoom;
+        self
+    }
+
+    pub fn icon_zoom(mut self, zoom: OpeningZoom) -> Self {
+        self.icon_zoom = zoom;
         self
     }
 
+    /// Record a recolor target as recently used (most recent first, no duplicates)
+    pub fn remember_svg_color(&mut self, color: [u8; 3]) {
+        self.recent_svg_colors.retain(|c| *c != color);
+        self.recent_svg_colors.insert(0, color);
+        self.recent_svg_colors.truncate(MAX_RECENT_COLORS);
+    }
+
+    /// Make `color` the recolor target and remember it
+    pub fn set_svg_target_color(&mut self, color: [u8; 3]) {
+        self.svg_target_color = color;
+        self.remember_svg_color(color);
+    }
+
+    /// Apply the recent recolor targets and palettes saved by an earlier session
+    pub fn load_svg_colors(&mut self, preferences: &Preferences) {
+        if let Some(colors) = preferences.get::<String>(RECENT_COLORS_KEY) {
+            self.recent_svg_colors = colors_from_preference(&colors);
+            self.recent_svg_colors.truncate(MAX_RECENT_COLORS);
+        }
+        let palettes: Vec<ColorPalette> = (1..)
+            .map_while(|number| preferences.get::<String>(&format!("{}{}", PALETTE_PREFIX, number)))
+            .filter_map(|value| ColorPalette::from_preference(&value))
+            .collect();
+        if !palettes.is_empty() {
+            self.svg_palettes = palettes;
+        }
+        if let Some(selected) = preferences.get::<usize>(SELECTED_PALETTE_KEY) {
+            self.selected_palette = selected.min(self.svg_palettes.len().saturating_sub(1));
+        }
+    }
+
+    pub fn save_svg_colors(&self, preferences: &mut Preferences) {
+        preferences.set(RECENT_COLORS_KEY, colors_to_preference(&self.recent_svg_colors));
+        let mut number = 1;
+        while preferences.get::<String>(&format!("{}{}", PALETTE_PREFIX, number)).is_some() {
+            preferences.remove(&format!("{}{}", PALETTE_PREFIX, number));
+            number += 1;
+        }
+        for (number, palette) in (1..).zip(&self.svg_palettes) {
+            preferences.set(&format!("{}{}", PALETTE_PREFIX, number), palette.to_preference());
+        }
+        preferences.set(SELECTED_PALETTE_KEY, self.selected_palette);
+    }
+
     pub fn get_supported_extensions(&self) -> &[String] {
         &self.supported_formats
     }
@@ -178,7 +458,7 @@ fn truncate_filename_with_ellipsis(filename: &str, max_length: usize, ellipsis_c
     }
 
     // Split available space between start and end, favoring the start slightly
-    let start_chars = (available_for_name + 1) / 2;
+    let start_chars = available_for_name.div_ceil(2);
     let end_chars = available_for_name - start_chars;
 
     if name_part.len() <= available_for_name {
@@ -196,10 +476,62 @@ fn truncate_filename_with_ellipsis(filename: &str, max_length: usize, ellipsis_c
     }
 }
 
+/// Per-user directory for files the app keeps between sessions
+pub fn config_dir() -> Option<std::path::PathBuf> {
+    #[cfg(windows)]
+    let base = std::env::var_os("APPDATA").map(std::path::PathBuf::from);
+    #[cfg(not(windows))]
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".config")));
+    base.map(|dir| dir.join("image_previewer"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_recent_svg_colors() {
+        let mut settings = ImageLoadingSettings::default();
+        for i in 0..10u8 {
+            settings.remember_svg_color([i, i, i]);
+        }
+        settings.set_svg_target_color([5, 5, 5]);
+
+        assert_eq!(settings.svg_target_color, [5, 5, 5]);
+        assert_eq!(settings.recent_svg_colors.len(), MAX_RECENT_COLORS);
+        assert_eq!(settings.recent_svg_colors[0], [5, 5, 5]);
+        assert_eq!(settings.recent_svg_colors.iter().filter(|c| **c == [5, 5, 5]).count(), 1);
+        assert!(!settings.recent_svg_colors.contains(&[0, 0, 0]));
+    }
+
+    #[test]
+    fn test_svg_colors_round_trip() {
+        let mut settings = ImageLoadingSettings::default();
+        settings.remember_svg_color([1, 2, 3]);
+        settings.remember_svg_color([255, 128, 0]);
+        settings.svg_palettes = vec![ColorPalette::new("Brand", &[[0, 51, 102], [255, 255, 255]])];
+        settings.selected_palette = 0;
+        // The longer default list saved earlier must not leave palettes behind
+        let mut preferences = Preferences::default();
+        ImageLoadingSettings::default().save_svg_colors(&mut preferences);
+        settings.save_svg_colors(&mut preferences);
+        assert_eq!(preferences.get::<String>("svg_palette.2"), None);
+
+        let mut loaded = ImageLoadingSettings::default();
+        loaded.load_svg_colors(&Preferences::parse(&preferences.to_text()));
+        assert_eq!(loaded.recent_svg_colors, vec![[255, 128, 0], [1, 2, 3]]);
+        assert_eq!(loaded.svg_palettes, settings.svg_palettes);
+        assert_eq!(loaded.selected_palette, 0);
+
+        // Nothing saved keeps the defaults
+        let mut fresh = ImageLoadingSettings::default();
+        fresh.load_svg_colors(&Preferences::default());
+        assert_eq!(fresh.svg_palettes, default_palettes());
+    }
+
     #[test]
     fn test_filename_truncation_no_truncation_needed() {
         let settings = ImageLoadingSettings {
@@ -280,11 +612,13 @@ mod tests {
 
     #[test]
     fn test_custom_ellipsis_character() {
-        let mut settings = ImageLoadingSettings::default();
-        settings.truncate_long_filenames = true;
-        settings.max_filename_length = 20;
-        settings.truncation_style = FilenameTruncationStyle::Ellipsis;
-        settings.ellipsis_char = "...".to_string();
+        let settings = ImageLoadingSettings {
+            truncate_long_filenames: true,
+            max_filename_length: 20,
+            truncation_style: FilenameTruncationStyle::Ellipsis,
+            ellipsis_char: "...".to_string(),
+            ..Default::default()
+        };
 
         let long_filename = "very_long_filename_example.jpg";
         let result = settings.truncate_filename(long_filename);
@@ -323,8 +657,10 @@ mod tests {
 
     #[test]
     fn test_effective_max_file_size_manual_override() {
-        let mut settings = ImageLoadingSettings::default();
-        settings.max_file_size_mb = Some(200);
+        let settings = ImageLoadingSettings {
+            max_file_size_mb: Some(200),
+            ..Default::default()
+        };
         
         let effective = settings.get_effective_max_file_size_mb();
         assert_eq!(effective, Some(200));
@@ -0,0 +1,250 @@
+//! Sharing the current image with external targets
+//!
+//! Each destination implements [`ShareTarget`]; the Share menu lists whatever
+//! [`share_targets`] returns, so new integrations don't need UI changes.
+//! Targets that pass on a file share a flattened PNG of the displayed pixels
+//! while the image has edits, such as redactions, that its file doesn't.
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use egui::ColorImage;
+
+use crate::color_profile::{self, ColorProfile};
+use crate::duplicates::target_in;
+use crate::egui_adapter;
+use crate::settings::ImageLoadingSettings;
+
+/// Folder in the temp directory for flattened copies handed to the upload command
+const SHARE_TEMP_FOLDER: &str = "image_previewer_share";
+
+/// What is being shared: the file on disk and, if loaded, its decoded pixels
+pub struct ShareRequest<'a> {
+    pub path: &'a Path,
+    pub image: Option<&'a ColorImage>,
+    /// Whether `image` has edits the file doesn't, so the file can't be shared as is
+    pub edited: bool,
+}
+
+impl ShareRequest<'_> {
+    /// The file to pass on: the original, or a flattened copy written into
+    /// `folder` while the image has edits
+    fn file_in(&self, folder: &Path) -> Result<PathBuf, String> {
+        if !self.edited {
+            return Ok(self.path.to_path_buf());
+        }
+        let image = self.image.ok_or("Load the image first")?;
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        let output = target_in(Path::new(&format!("{}_edited.png", stem)), folder, Path::exists)?;
+        let pixels = egui_adapter::from_color_image(image).into_rgba();
+        color_profile::save_png(&pixels, ColorProfile::read(self.path).as_ref(), &output)?;
+        Ok(output)
+    }
+}
+
+pub trait ShareTarget {
+    /// Label shown in the Share menu
+    fn name(&self) -> String;
+
+    /// Why the target can't be used right now, if it can't (shown as a tooltip)
+    fn unavailable_reason(&self, _request: &ShareRequest) -> Option<String> {
+        None
+    }
+
+    /// Share the image, returning a short status message
+    fn share(&self, request: &ShareRequest, ctx: &egui::Context) -> Result<String, String>;
+}
+
+/// Copies the decoded image to the system clipboard (as PNG where supported),
+/// converted to sRGB since the clipboard carries no color profile
+pub struct ClipboardTarget;
+
+impl ShareTarget for ClipboardTarget {
+    fn name(&self) -> String {
+        "Copy Image to Clipboard".to_string()
+    }
+
+    fn unavailable_reason(&self, request: &ShareRequest) -> Option<String> {
+        request.image.is_none().then(|| "Load the image first".to_string())
+    }
+
+    fn share(&self, request: &ShareRequest, ctx: &egui::Context) -> Result<String, String> {
+        let image = request.image.ok_or("No image loaded")?;
+        let profile = ColorProfile::read(request.path);
+        let message = format!("Copied {}x{} image to clipboard", image.size[0], image.size[1]);
+        match profile.as_ref().map(|profile| profile.to_srgb(image)) {
+            Some(Some(converted)) => {
+                ctx.copy_image(converted);
+                Ok(format!("{}, converted to sRGB", message))
+            }
+            Some(None) => {
+                ctx.copy_image(image.clone());
+                Ok(format!("{}; its color profile couldn't be converted, so colors may shift", message))
+            }
+            None => {
+                ctx.copy_image(image.clone());
+                Ok(message)
+            }
+        }
+    }
+}
+
+/// Copies the original file into a configured folder
+pub struct FolderTarget {
+    pub folder: PathBuf,
+}
+
+impl ShareTarget for FolderTarget {
+    fn name(&self) -> String {
+        format!("Save to {}", self.folder.display())
+    }
+
+    fn unavailable_reason(&self, request: &ShareRequest) -> Option<String> {
+        (request.edited && request.image.is_none()).then(|| "Load the image first".to_string())
+    }
+
+    fn share(&self, request: &ShareRequest, _ctx: &egui::Context) -> Result<String, String> {
+        let destination = if request.edited {
+            request.file_in(&self.folder)?
+        } else {
+            copy_to_folder(request.path, &self.folder)?
+        };
+        Ok(format!("Saved to {}", destination.display()))
+    }
+}
+
+/// Runs a user-defined command with `{path}` replaced by the image path
+pub struct CommandTarget {
+    pub template: String,
+}
+
+impl ShareTarget for CommandTarget {
+    fn name(&self) -> String {
+        "Run Upload Command".to_string()
+    }
+
+    fn unavailable_reason(&self, request: &ShareRequest) -> Option<String> {
+        (request.edited && request.image.is_none()).then(|| "Load the image first".to_string())
+    }
+
+    fn share(&self, request: &ShareRequest, _ctx: &egui::Context) -> Result<String, String> {
+        // Left for the command, which may still be reading it after this returns
+        let file = request.file_in(&std::env::temp_dir().join(SHARE_TEMP_FOLDER))?;
+        let command_line = expand_command_template(&self.template, &file);
+
+        // Uploads can take a while, so the command runs detached from the UI
+        spawn_shell(&command_line)
+            .map_err(|e| format!("Failed to run upload command: {}", e))?;
+        Ok(format!("Started: {}", command_line))
+    }
+}
+
+/// All share targets enabled by the current settings
+pub fn share_targets(settings: &ImageLoadingSettings) -> Vec<Box<dyn ShareTarget>> {
+    let mut targets: Vec<Box<dyn ShareTarget>> = vec![Box::new(ClipboardTarget)];
+    if !settings.share_folder.trim().is_empty() {
+        targets.push(Box::new(FolderTarget { folder: PathBuf::from(settings.share_folder.trim()) }));
+    }
+    if !settings.share_command.trim().is_empty() {
+        targets.push(Box::new(CommandTarget { template: settings.share_command.clone() }));
+    }
+    targets
+}
+
+/// Copy a file into `folder`, adding a numeric suffix instead of overwriting
+pub fn copy_to_folder(path: &Path, folder: &Path) -> Result<PathBuf, String> {
+    let destination = target_in(path, folder, Path::exists)?;
+    std::fs::copy(path, &destination)
+        .map_err(|e| format!("Failed to copy file: {}", e))?;
+    Ok(destination)
+}
+
+/// Substitute `{path}` in a command template with the shell-quoted path
+pub fn expand_command_template(template: &str, path: &Path) -> String {
+    template.replace("{path}", &shell_quote(&path.to_string_lossy()))
+}
+
+/// Start `command_line` in the platform shell without waiting for it; the
+/// exited process is reaped on a thread of its own
+pub fn spawn_shell(command_line: &str) -> std::io::Result<()> {
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", command_line]);
+        command
+    };
+    #[cfg(not(windows))]
+    let mut command = {
+        let mut command = std::process::Command::new("sh");
+        command.args(["-c", command_line]);
+        command
+    };
+    let mut child = command.spawn()?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(windows)]
+fn shell_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+#[cfg(not(windows))]
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_expand_command_template_quotes_path() {
+        let command = expand_command_template("upload --file {path}", Path::new("/tmp/it's here.png"));
+        assert_eq!(command, "upload --file '/tmp/it'\\''s here.png'");
+    }
+
+    #[test]
+    fn test_copy_to_folder_does_not_overwrite() {
+        let root = TempDir::new("share");
+        let source = root.join("photo.png");
+        std::fs::write(&source, b"png").unwrap();
+        let folder = root.join("shared");
+
+        let first = copy_to_folder(&source, &folder).unwrap();
+        let second = copy_to_folder(&source, &folder).unwrap();
+
+        assert_eq!(first, folder.join("photo.png"));
+        assert_eq!(second, folder.join("photo (2).png"));
+    }
+
+    #[test]
+    fn test_edited_images_are_shared_flattened() {
+        let root = TempDir::new("share_edited");
+        let source = root.join("photo.png");
+        std::fs::write(&source, b"unredacted").unwrap();
+        let folder = root.join("shared");
+        let image = ColorImage::new([3, 2], egui::Color32::BLACK);
+        let request = ShareRequest { path: &source, image: Some(&image), edited: true };
+
+        let target = FolderTarget { folder: folder.clone() };
+        target.share(&request, &egui::Context::default()).unwrap();
+        let shared = image::open(folder.join("photo_edited.png")).unwrap();
+        assert_eq!((shared.width(), shared.height()), (3, 2));
+        assert!(!folder.join("photo.png").exists());
+        assert!(target.unavailable_reason(&ShareRequest { image: None, ..request }).is_some());
+    }
+
+    #[test]
+    fn test_share_targets_follow_settings() {
+        let mut settings = ImageLoadingSettings::default();
+        assert_eq!(share_targets(&settings).len(), 1);
+        settings.share_folder = "/tmp/share".to_string();
+        settings.share_command = "echo {path}".to_string();
+        let names: Vec<String> = share_targets(&settings).iter().map(|t| t.name()).collect();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[2], "Run Upload Command");
+    }
+}
@@ -0,0 +1,171 @@
+//! Crisp SVGs when zoomed in
+//!
+//! An SVG is decoded once at its intrinsic size, which blurs when magnified.
+//! Once zooming and panning settle, the visible part of the drawing is
+//! rasterized again at screen resolution and drawn over the texture.
+
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+use eframe::egui::{self, ColorImage, Rect, TextureHandle, TextureId, Vec2};
+
+use crate::image_processing::MAX_SVG_EXPORT_SIZE;
+
+/// How long the view must stay still before it is re-rendered
+pub const SETTLE_DELAY: Duration = Duration::from_millis(250);
+
+/// Magnification the texture is left to handle on its own
+const MIN_MAGNIFICATION: f32 = 1.05;
+
+/// Part of an image in normalized image coordinates, with the size in
+/// physical pixels to render it at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub uv: Rect,
+    pub size: [u32; 2],
+}
+
+impl Region {
+    fn same_as(&self, other: &Region) -> bool {
+        self.size == other.size
+            && (self.uv.min - other.uv.min).length() < 1e-4
+            && (self.uv.max - other.uv.max).length() < 1e-4
+    }
+}
+
+/// The part of an image drawn at `image_rect` that is visible in `area`, or
+/// `None` if the texture isn't magnified enough to look blurry
+pub fn visible_region(image_rect: Rect, area: Rect, texture_size: Vec2, pixels_per_point: f32) -> Option<Region> {
+    if image_rect.width() * pixels_per_point < texture_size.x * MIN_MAGNIFICATION {
+        return None;
+    }
+    let visible = image_rect.intersect(area);
+    if !visible.is_positive() {
+        return None;
+    }
+    let to_uv = |point: egui::Pos2| ((point - image_rect.min) / image_rect.size()).to_pos2();
+    let pixels = |points: f32| ((points * pixels_per_point).round() as u32).clamp(1, MAX_SVG_EXPORT_SIZE);
+    Some(Region {
+        uv: Rect::from_min_max(to_uv(visible.min), to_uv(visible.max)),
+        size: [pixels(visible.width()), pixels(visible.height())],
+    })
+}
+
+/// Re-rendering state for the displayed SVG
+#[derive(Default)]
+pub struct SharpSvg {
+    /// Texture the renders belong to
+    base: Option<TextureId>,
+    rendered: Option<(Region, TextureHandle)>,
+    /// Region on screen and when it was first seen there
+    wanted: Option<(Region, Instant)>,
+    /// Last region handed out for rendering, so a failed render isn't retried
+    requested: Option<Region>,
+    rendering: Option<Receiver<(Region, Result<ColorImage, String>)>>,
+}
+
+impl SharpSvg {
+    /// Start over when a different texture is displayed
+    pub fn track(&mut self, texture: TextureId) {
+        if self.base != Some(texture) {
+            *self = Self { base: Some(texture), ..Default::default() };
+        }
+    }
+
+    /// Track the visible region; returns it once it has stayed put for
+    /// `SETTLE_DELAY` and needs rendering
+    pub fn request(&mut self, region: Option<Region>, now: Instant) -> Option<Region> {
+        let Some(region) = region else {
+            // Back at (or below) the texture's resolution; free the render
+            self.rendered = None;
+            self.wanted = None;
+            self.requested = None;
+            return None;
+        };
+        if self.requested.is_some_and(|requested| requested.same_as(&region)) {
+            self.wanted = None;
+            return None;
+        }
+        match self.wanted {
+            Some((wanted, since)) if wanted.same_as(&region) => {
+                (self.rendering.is_none() && now.duration_since(since) >= SETTLE_DELAY).then(|| {
+                    self.wanted = None;
+                    self.requested = Some(region);
+                    region
+                })
+            }
+            _ => {
+                self.wanted = Some((region, now));
+                None
+            }
+        }
+    }
+
+    /// Whether a region is waiting for the view to settle
+    pub fn is_waiting(&self) -> bool {
+        self.wanted.is_some()
+    }
+
+    pub fn start(&mut self, receiver: Receiver<(Region, Result<ColorImage, String>)>) {
+        self.rendering = Some(receiver);
+    }
+
+    /// Upload a finished render
+    pub fn receive(&mut self, ctx: &egui::Context) -> Result<(), String> {
+        let Some(Ok((region, result))) = self.rendering.as_ref().map(Receiver::try_recv) else {
+            return Ok(());
+        };
+        self.rendering = None;
+        let texture = ctx.load_texture("sharp_svg", result?, Default::default());
+        self.rendered = Some((region, texture));
+        Ok(())
+    }
+
+    /// The render and where it goes for an image drawn at `image_rect`, and
+    /// whether it covers all of `visible` so the texture needn't be drawn
+    pub fn overlay(&self, image_rect: Rect, visible: Option<&Region>) -> Option<(&TextureHandle, Rect, bool)> {
+        let (region, texture) = self.rendered.as_ref()?;
+        let to_screen = |uv: egui::Pos2| image_rect.min + uv.to_vec2() * image_rect.size();
+        let covers = visible.is_some_and(|visible| region.uv.expand(1e-4).contains_rect(visible.uv));
+        Some((texture, Rect::from_min_max(to_screen(region.uv.min), to_screen(region.uv.max)), covers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{pos2, vec2};
+
+    #[test]
+    fn test_visible_region() {
+        let area = Rect::from_min_size(pos2(0.0, 0.0), vec2(400.0, 300.0));
+        // A 100x100 texture shown at 800x800 points, centered
+        let image_rect = Rect::from_center_size(area.center(), vec2(800.0, 800.0));
+        let region = visible_region(image_rect, area, vec2(100.0, 100.0), 2.0).unwrap();
+        assert_eq!(region.size, [800, 600]);
+        assert_eq!(region.uv, Rect::from_min_max(pos2(0.25, 0.3125), pos2(0.75, 0.6875)));
+
+        // Not magnified
+        assert!(visible_region(Rect::from_min_size(pos2(0.0, 0.0), vec2(100.0, 100.0)), area, vec2(100.0, 100.0), 1.0).is_none());
+        // Panned out of view
+        assert!(visible_region(image_rect.translate(vec2(2000.0, 0.0)), area, vec2(100.0, 100.0), 1.0).is_none());
+    }
+
+    #[test]
+    fn test_request_waits_for_the_view_to_settle() {
+        let region = |x: f32| Region { uv: Rect::from_min_max(pos2(x, 0.0), pos2(x + 0.5, 0.5)), size: [100, 100] };
+        let start = Instant::now();
+        let mut sharp = SharpSvg::default();
+        assert_eq!(sharp.request(Some(region(0.0)), start), None);
+        assert!(sharp.is_waiting());
+        // Still moving: the clock restarts
+        assert_eq!(sharp.request(Some(region(0.1)), start + SETTLE_DELAY), None);
+        assert_eq!(sharp.request(Some(region(0.1)), start + SETTLE_DELAY * 2), Some(region(0.1)));
+        assert!(!sharp.is_waiting());
+        // Requested once, even if the render never arrives
+        assert_eq!(sharp.request(Some(region(0.1)), start + SETTLE_DELAY * 4), None);
+        assert!(!sharp.is_waiting());
+        assert_eq!(sharp.request(None, start + SETTLE_DELAY * 5), None);
+        assert!(!sharp.is_waiting());
+    }
+}
@@ -0,0 +1,74 @@
+//! Spotting possibly blurry shots
+//!
+//! Sharpness is the variance of the Laplacian of a downscaled grayscale copy:
+//! crisp edges give strong, varied responses, while blur and missed focus
+//! flatten them. The score depends on content, so a low one only means the
+//! image is worth a second look.
+
+#[cfg(feature = "gui")]
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "gui")]
+use image::{DynamicImage, GrayImage};
+
+#[cfg(feature = "gui")]
+use crate::image_processing::open_image;
+
+/// Longest side of the copy the score is computed on
+#[cfg(feature = "gui")]
+pub const ANALYSIS_SIZE: u32 = 512;
+
+/// Scores below this are flagged as possibly blurry
+pub const DEFAULT_BLUR_THRESHOLD: f32 = 100.0;
+
+/// An image and its sharpness score
+#[cfg(feature = "gui")]
+pub type SharpnessUpdate = (PathBuf, f32);
+
+/// Variance of the 4-neighbour Laplacian over the interior pixels
+#[cfg(feature = "gui")]
+pub fn laplacian_variance(image: &GrayImage) -> f32 {
+    let (width, height) = image.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let pixel = |x: u32, y: u32| image.get_pixel(x, y)[0] as f64;
+    let mut sum = 0.0;
+    let mut sum_squares = 0.0;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let laplacian = pixel(x - 1, y) + pixel(x + 1, y) + pixel(x, y - 1) + pixel(x, y + 1) - 4.0 * pixel(x, y);
+            sum += laplacian;
+            sum_squares += laplacian * laplacian;
+        }
+    }
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+    (sum_squares / count - mean * mean) as f32
+}
+
+/// Sharpness score of an image; higher is sharper
+#[cfg(feature = "gui")]
+pub fn sharpness(image: &DynamicImage) -> f32 {
+    laplacian_variance(&image.thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE).to_luma8())
+}
+
+#[cfg(feature = "gui")]
+pub fn measure_file(path: &Path) -> Result<f32, String> {
+    let image = open_image(path).map_err(|e| format!("Failed to decode {}: {}", path.display(), e))?;
+    Ok(sharpness(&image))
+}
+
+#[cfg(all(test, feature = "gui"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edges_score_higher_than_smooth_gradients() {
+        let checkerboard = GrayImage::from_fn(64, 64, |x, y| image::Luma([if (x / 4 + y / 4) % 2 == 0 { 0 } else { 255 }]));
+        let gradient = GrayImage::from_fn(64, 64, |x, _| image::Luma([(x * 4) as u8]));
+        assert!(laplacian_variance(&checkerboard) > DEFAULT_BLUR_THRESHOLD);
+        assert!(laplacian_variance(&gradient) < DEFAULT_BLUR_THRESHOLD);
+        assert_eq!(laplacian_variance(&GrayImage::new(2, 2)), 0.0);
+    }
+}
@@ -0,0 +1,202 @@
+//! Windows Explorer integration without an installer
+//!
+//! Registering writes per-user registry entries (under `HKCU`, so no
+//! administrator rights are needed) that add the viewer to "Open with" for
+//! the supported formats, list it in Default Apps, and add "Send to Image
+//! PreViewer" to the context menu of images and folders. Explorer then
+//! starts the viewer with the file or folder as its argument.
+//!
+//! The entries are plain data, so they can be checked on any platform; on
+//! Windows they are written with the registry API.
+
+use std::path::Path;
+
+pub const APP_NAME: &str = "Image PreViewer";
+const PROG_ID: &str = "ImagePreViewer.Image";
+const CAPABILITIES_KEY: &str = r"Software\ImagePreViewer\Capabilities";
+const CLASSES: &str = r"HKCU\Software\Classes";
+const SEND_TO_VERB: &str = "ImagePreViewer.SendTo";
+
+/// One string value; `name` None is the key's default value
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegistryValue {
+    pub key: String,
+    pub name: Option<String>,
+    pub data: String,
+}
+
+impl RegistryValue {
+    fn new(key: impl Into<String>, name: Option<&str>, data: impl Into<String>) -> Self {
+        Self { key: key.into(), name: name.map(str::to_string), data: data.into() }
+    }
+}
+
+/// Something registration created: a whole key, or one value in a shared key
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistryRemoval {
+    Key(String),
+    Value { key: String, name: String },
+}
+
+fn executable_name(exe: &Path) -> String {
+    exe.file_name().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// Registry values that register `exe` for `extensions` (lowercase, without the dot)
+pub fn registration_entries(exe: &Path, extensions: &[String]) -> Vec<RegistryValue> {
+    let exe_path = exe.display().to_string();
+    let open_command = format!("\"{}\" \"%1\"", exe_path);
+    let application = format!(r"{}\Applications\{}", CLASSES, executable_name(exe));
+    let mut entries = vec![
+        RegistryValue::new(format!(r"{}\{}", CLASSES, PROG_ID), None, format!("{} image", APP_NAME)),
+        RegistryValue::new(format!(r"{}\{}\DefaultIcon", CLASSES, PROG_ID), None, format!("\"{}\",0", exe_path)),
+        RegistryValue::new(format!(r"{}\{}\shell\open\command", CLASSES, PROG_ID), None, open_command.clone()),
+        RegistryValue::new(&application, Some("FriendlyAppName"), APP_NAME),
+        RegistryValue::new(format!(r"{}\shell\open\command", application), None, open_command.clone()),
+        RegistryValue::new(format!(r"HKCU\{}", CAPABILITIES_KEY), Some("ApplicationName"), APP_NAME),
+        RegistryValue::new(format!(r"HKCU\{}", CAPABILITIES_KEY), Some("ApplicationDescription"), "Fast previews of local and cloud images"),
+        RegistryValue::new(r"HKCU\Software\RegisteredApplications", Some(APP_NAME), CAPABILITIES_KEY),
+    ];
+    for ext in extensions {
+        entries.push(RegistryValue::new(format!(r"{}\.{}\OpenWithProgids", CLASSES, ext), Some(PROG_ID), ""));
+        entries.push(RegistryValue::new(format!(r"{}\SupportedTypes", application), Some(&format!(".{}", ext)), ""));
+        entries.push(RegistryValue::new(format!(r"HKCU\{}\FileAssociations", CAPABILITIES_KEY), Some(&format!(".{}", ext)), PROG_ID));
+        entries.extend(send_to_entries(&format!(r"{}\SystemFileAssociations\.{}", CLASSES, ext), &open_command));
+    }
+    entries.extend(send_to_entries(&format!(r"{}\Directory", CLASSES), &open_command));
+    entries
+}
+
+fn send_to_entries(class: &str, command: &str) -> [RegistryValue; 2] {
+    let verb = format!(r"{}\shell\{}", class, SEND_TO_VERB);
+    [
+        RegistryValue::new(&verb, Some("MUIVerb"), format!("Send to {}", APP_NAME)),
+        RegistryValue::new(format!(r"{}\command", verb), None, command),
+    ]
+}
+
+/// What `registration_entries` created, for unregistering
+pub fn registration_removals(exe: &Path, extensions: &[String]) -> Vec<RegistryRemoval> {
+    let mut removals = vec![
+        RegistryRemoval::Key(format!(r"{}\{}", CLASSES, PROG_ID)),
+        RegistryRemoval::Key(format!(r"{}\Applications\{}", CLASSES, executable_name(exe))),
+        RegistryRemoval::Key(r"HKCU\Software\ImagePreViewer".to_string()),
+        RegistryRemoval::Value { key: r"HKCU\Software\RegisteredApplications".to_string(), name: APP_NAME.to_string() },
+        RegistryRemoval::Key(format!(r"{}\Directory\shell\{}", CLASSES, SEND_TO_VERB)),
+    ];
+    for ext in extensions {
+        removals.push(RegistryRemoval::Value { key: format!(r"{}\.{}\OpenWithProgids", CLASSES, ext), name: PROG_ID.to_string() });
+        removals.push(RegistryRemoval::Key(format!(r"{}\SystemFileAssociations\.{}\shell\{}", CLASSES, ext, SEND_TO_VERB)));
+    }
+    removals
+}
+
+/// Register the running executable with Explorer
+pub fn register(extensions: &[String]) -> Result<usize, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate the executable: {}", e))?;
+    for entry in registration_entries(&exe, extensions) {
+        write_value(&entry)?;
+    }
+    Ok(extensions.len())
+}
+
+/// Remove everything `register` wrote; entries that are already gone are skipped
+pub fn unregister(extensions: &[String]) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate the executable: {}", e))?;
+    for removal in registration_removals(&exe, extensions) {
+        remove(&removal)?;
+    }
+    Ok(())
+}
+
+/// The part of `key` below `HKCU`
+#[cfg(windows)]
+fn user_subkey(key: &str) -> Result<windows::core::HSTRING, String> {
+    key.strip_prefix(r"HKCU").map(windows::core::HSTRING::from).ok_or_else(|| format!("{} is not a per-user key", key))
+}
+
+#[cfg(windows)]
+fn write_value(entry: &RegistryValue) -> Result<(), String> {
+    use windows::Win32::System::Registry::{
+        HKEY, HKEY_CURRENT_USER, KEY_SET_VALUE, REG_OPTION_NON_VOLATILE, REG_SZ, RegCloseKey, RegCreateKeyExW, RegSetValueExW,
+    };
+    use windows::core::{HSTRING, PCWSTR};
+
+    let subkey = user_subkey(&entry.key)?;
+    let mut key = HKEY::default();
+    unsafe { RegCreateKeyExW(HKEY_CURRENT_USER, &subkey, None, PCWSTR::null(), REG_OPTION_NON_VOLATILE, KEY_SET_VALUE, None, &mut key, None) }
+        .ok()
+        .map_err(|e| format!("Failed to create {}: {}", entry.key, e))?;
+    // REG_SZ data is UTF-16 with its terminating null; an empty name is the default value
+    let data: Vec<u8> = entry.data.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect();
+    let name = HSTRING::from(entry.name.as_deref().unwrap_or(""));
+    let written = unsafe { RegSetValueExW(key, &name, None, REG_SZ, Some(&data)) };
+    unsafe {
+        let _ = RegCloseKey(key);
+    }
+    written.ok().map_err(|e| format!("Failed to write {}: {}", entry.key, e))
+}
+
+/// Delete a key or value; one that is already gone counts as removed
+#[cfg(windows)]
+fn remove(removal: &RegistryRemoval) -> Result<(), String> {
+    use windows::Win32::Foundation::ERROR_FILE_NOT_FOUND;
+    use windows::Win32::System::Registry::{HKEY_CURRENT_USER, RegDeleteKeyValueW, RegDeleteTreeW};
+    use windows::core::HSTRING;
+
+    let (key, result) = match removal {
+        RegistryRemoval::Key(key) => (key, unsafe { RegDeleteTreeW(HKEY_CURRENT_USER, &user_subkey(key)?) }),
+        RegistryRemoval::Value { key, name } => {
+            (key, unsafe { RegDeleteKeyValueW(HKEY_CURRENT_USER, &user_subkey(key)?, &HSTRING::from(name.as_str())) })
+        }
+    };
+    if result == ERROR_FILE_NOT_FOUND {
+        return Ok(());
+    }
+    result.ok().map_err(|e| format!("Failed to remove {}: {}", key, e))
+}
+
+#[cfg(not(windows))]
+fn write_value(_entry: &RegistryValue) -> Result<(), String> {
+    Err("Registering as an image viewer is only supported on Windows".to_string())
+}
+
+#[cfg(not(windows))]
+fn remove(_removal: &RegistryRemoval) -> Result<(), String> {
+    Err("Registering as an image viewer is only supported on Windows".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registration_entries() {
+        let exe = Path::new(r"C:\Tools\image_previewer.exe");
+        let extensions = vec!["png".to_string(), "jpg".to_string()];
+        let entries = registration_entries(exe, &extensions);
+
+        let command = RegistryValue::new(
+            r"HKCU\Software\Classes\ImagePreViewer.Image\shell\open\command",
+            None,
+            r#""C:\Tools\image_previewer.exe" "%1""#,
+        );
+        assert!(entries.contains(&command));
+        assert!(entries.contains(&RegistryValue::new(r"HKCU\Software\Classes\.jpg\OpenWithProgids", Some(PROG_ID), "")));
+        assert!(entries.contains(&RegistryValue::new(
+            r"HKCU\Software\Classes\Directory\shell\ImagePreViewer.SendTo",
+            Some("MUIVerb"),
+            "Send to Image PreViewer",
+        )));
+        assert_eq!(entries.iter().filter(|e| e.key.ends_with(r"\FileAssociations")).count(), 2);
+
+        // Every key written is covered by a removal
+        let removals = registration_removals(exe, &extensions);
+        for entry in &entries {
+            assert!(removals.iter().any(|removal| match removal {
+                RegistryRemoval::Key(key) => entry.key.starts_with(key.as_str()),
+                RegistryRemoval::Value { key, name } => entry.key == *key && entry.name.as_ref() == Some(name),
+            }), "{:?} is never removed", entry);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Thumbnails Windows already has for a file, read without downloading it
+//!
+//! Explorer keeps the thumbnails it has shown, including those of cloud files
+//! that have since gone back to online-only. Asking the shell for a cached
+//! thumbnail only never opens the file, so it can't trigger a download. Other
+//! platforms have no such cache to ask.
+
+use std::path::Path;
+
+use eframe::egui::ColorImage;
+
+/// The shell's cached thumbnail of `path`, at most `size` pixels on a side
+#[cfg(windows)]
+pub fn cached_thumbnail(path: &Path, size: u32) -> Option<ColorImage> {
+    use windows::Win32::Foundation::SIZE;
+    use windows::Win32::Graphics::Gdi::{
+        BI_RGB, BITMAP, BITMAPINFO, BITMAPINFOHEADER, CreateCompatibleDC, DIB_RGB_COLORS, DeleteDC, DeleteObject,
+        GetDIBits, GetObjectW, HGDIOBJ,
+    };
+    use windows::Win32::System::Com::{COINIT_APARTMENTTHREADED, CoInitializeEx, CoUninitialize};
+    use windows::Win32::UI::Shell::{IShellItemImageFactory, SHCreateItemFromParsingName, SIIGBF_INCACHEONLY, SIIGBF_THUMBNAILONLY};
+    use windows::core::HSTRING;
+
+    // Already initialized on the UI thread, in which case this only counts up
+    let initialized = unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED) }.is_ok();
+    let image = (|| unsafe {
+        let factory: IShellItemImageFactory = SHCreateItemFromParsingName(&HSTRING::from(path.as_os_str()), None).ok()?;
+        let side = size as i32;
+        let bitmap = factory.GetImage(SIZE { cx: side, cy: side }, SIIGBF_THUMBNAILONLY | SIIGBF_INCACHEONLY).ok()?;
+        let object = HGDIOBJ(bitmap.0);
+        let mut info = BITMAP::default();
+        let read = GetObjectW(object, std::mem::size_of::<BITMAP>() as i32, Some(&mut info as *mut BITMAP as *mut _));
+        let (width, height) = (info.bmWidth, info.bmHeight.abs());
+        // A negative height asks for rows from the top down
+        let mut header = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width,
+                biHeight: -height,
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut bgra = vec![0u8; width.max(0) as usize * height as usize * 4];
+        let dc = CreateCompatibleDC(None);
+        let lines = GetDIBits(dc, bitmap, 0, height as u32, Some(bgra.as_mut_ptr() as *mut _), &mut header, DIB_RGB_COLORS);
+        let _ = DeleteDC(dc);
+        let _ = DeleteObject(object);
+        (read != 0 && height > 0 && lines == height).then(|| from_bgra(width as usize, height as usize, &bgra))
+    })();
+    if initialized {
+        unsafe { CoUninitialize() };
+    }
+    image
+}
+
+#[cfg(not(windows))]
+pub fn cached_thumbnail(_path: &Path, _size: u32) -> Option<ColorImage> {
+    None
+}
+
+/// A shell bitmap's premultiplied BGRA rows as an image. Thumbnails without
+/// transparency come with every alpha at zero, so those are made opaque.
+#[cfg(any(windows, test))]
+pub fn from_bgra(width: usize, height: usize, bgra: &[u8]) -> ColorImage {
+    use eframe::egui::Color32;
+
+    let opaque = bgra.chunks_exact(4).all(|pixel| pixel[3] == 0);
+    let pixels = bgra
+        .chunks_exact(4)
+        .map(|pixel| match opaque {
+            true => Color32::from_rgb(pixel[2], pixel[1], pixel[0]),
+            false => Color32::from_rgba_premultiplied(pixel[2], pixel[1], pixel[0], pixel[3]),
+        })
+        .collect();
+    ColorImage { size: [width, height], pixels }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eframe::egui::Color32;
+
+    #[test]
+    fn test_bgra_rows_become_pixels() {
+        let opaque = from_bgra(2, 1, &[10, 20, 30, 0, 0, 0, 255, 0]);
+        assert_eq!(opaque.pixels, vec![Color32::from_rgb(30, 20, 10), Color32::from_rgb(255, 0, 0)]);
+
+        let transparent = from_bgra(2, 1, &[0, 0, 128, 128, 0, 0, 0, 0]);
+        assert_eq!(transparent.pixels[0], Color32::from_rgba_premultiplied(128, 0, 0, 128));
+        assert_eq!(transparent.pixels[1], Color32::TRANSPARENT);
+    }
+}
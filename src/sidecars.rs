@@ -0,0 +1,58 @@
+//! Finding the files tools keep next to an image under its name
+//!
+//! A sidecar is named after the image's stem (`photo.xmp`) or, as darktable
+//! and some taggers do, after its full name (`photo.jpg.xmp`).
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::file_locality::FileInfo;
+
+/// Names a sidecar of `image` with `extension` can have, in order of preference
+pub fn sidecar_names(image: &Path, extension: &str) -> [String; 2] {
+    let name = image.file_name().unwrap_or_default().to_string_lossy();
+    let stem = image.file_stem().unwrap_or_default().to_string_lossy();
+    [format!("{}.{}", stem, extension), format!("{}.{}", name, extension)]
+}
+
+/// The first sidecar of each of `images` that `read` accepts, trying the
+/// names for each of `extensions` in turn
+pub fn scan<T>(
+    dir: &Path,
+    images: &[FileInfo],
+    extensions: &[&str],
+    read: impl Fn(&Path) -> Option<T>,
+) -> Vec<(PathBuf, T)> {
+    // One directory listing instead of probing every candidate name
+    let names: HashSet<String> = std::fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|entry| entry.file_name().to_string_lossy().into_owned()).collect())
+        .unwrap_or_default();
+    images
+        .iter()
+        .filter_map(|image| {
+            let value = extensions
+                .iter()
+                .flat_map(|extension| sidecar_names(&image.path, extension))
+                .filter(|name| names.contains(name))
+                .find_map(|name| read(&dir.join(name)))?;
+            Some((image.path.clone(), value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_scan_prefers_extension_order_then_stem() {
+        let dir = TempDir::new("sidecars");
+        for name in ["a.jpg", "a.jpg.json", "a.txt", "b.png", "b.txt", "b.png.txt", "c.gif"] {
+            std::fs::write(dir.join(name), name).unwrap();
+        }
+        let images: Vec<FileInfo> = ["a.jpg", "b.png", "c.gif"].iter().map(|name| FileInfo::new(dir.join(name))).collect();
+        let found = scan(&dir, &images, &["json", "txt"], |path| std::fs::read_to_string(path).ok());
+        assert_eq!(found, [(dir.join("a.jpg"), "a.jpg.json".to_string()), (dir.join("b.png"), "b.txt".to_string())]);
+    }
+}
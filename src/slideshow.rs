@@ -0,0 +1,352 @@
+//! Slideshow playback and the rules for skipping slides while advancing
+//!
+//! When a slideshow advances, each candidate image is checked against the
+//! [`SkipRules`]; images that match a rule are passed over and recorded in
+//! the skip log so it is clear why they never appeared.
+//!
+//! When on-demand files aren't skipped, [`ReadAhead`] decides which of the
+//! upcoming slides to download in advance, within a per-session budget.
+
+#[cfg(feature = "gui")]
+use std::collections::HashSet;
+#[cfg(feature = "gui")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "gui")]
+use std::time::{Duration, Instant};
+
+/// Skipped slides kept in the log
+#[cfg(feature = "gui")]
+pub const MAX_SKIP_LOG: usize = 200;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkipRules {
+    /// Don't download cloud placeholders just to show them
+    pub skip_on_demand: bool,
+    /// Skip images larger than this many megapixels
+    pub max_megapixels: Option<f64>,
+    /// Lowercase extensions that are never shown
+    pub skip_formats: Vec<String>,
+    /// Skip images that failed to decode, in an integrity scan or when shown
+    pub skip_failed_integrity: bool,
+}
+
+impl Default for SkipRules {
+    fn default() -> Self {
+        Self {
+            skip_on_demand: true,
+            max_megapixels: None,
+            skip_formats: Vec::new(),
+            skip_failed_integrity: true,
+        }
+    }
+}
+
+/// What the skip rules know about a candidate slide
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlideFacts {
+    pub path: PathBuf,
+    pub on_demand: bool,
+    /// None when the size can't be read without downloading the file
+    pub megapixels: Option<f64>,
+    pub failed_integrity: bool,
+    /// An on-demand file that the session's download budget can't cover
+    pub over_download_limit: bool,
+}
+
+#[cfg(feature = "gui")]
+impl SlideFacts {
+    fn format(&self) -> String {
+        self.path.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    OnDemand,
+    TooLarge { megapixels: f64, limit: f64 },
+    Format(String),
+    FailedIntegrity,
+    DownloadLimit,
+}
+
+#[cfg(feature = "gui")]
+impl SkipReason {
+    pub fn description(&self) -> String {
+        match self {
+            SkipReason::OnDemand => "not downloaded".to_string(),
+            SkipReason::TooLarge { megapixels, limit } => format!("{:.1} MP exceeds {:.1} MP", megapixels, limit),
+            SkipReason::Format(format) => format!("{} files are skipped", format.to_uppercase()),
+            SkipReason::FailedIntegrity => "failed to decode".to_string(),
+            SkipReason::DownloadLimit => "session download limit reached".to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+impl SkipRules {
+    /// The first rule that excludes this slide, if any
+    pub(crate) fn check(&self, facts: &SlideFacts) -> Option<SkipReason> {
+        if self.skip_on_demand && facts.on_demand {
+            return Some(SkipReason::OnDemand);
+        }
+        if facts.on_demand && facts.over_download_limit {
+            return Some(SkipReason::DownloadLimit);
+        }
+        if self.skip_failed_integrity && facts.failed_integrity {
+            return Some(SkipReason::FailedIntegrity);
+        }
+        let format = facts.format();
+        if self.skip_formats.iter().any(|skipped| skipped.eq_ignore_ascii_case(&format)) {
+            return Some(SkipReason::Format(format));
+        }
+        if let (Some(limit), Some(megapixels)) = (self.max_megapixels, facts.megapixels)
+            && megapixels > limit {
+            return Some(SkipReason::TooLarge { megapixels, limit });
+        }
+        None
+    }
+}
+
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedSlide {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+/// Advances the slideshow on a timer, applying the skip rules
+#[cfg(feature = "gui")]
+#[derive(Debug, Default)]
+pub struct SlideshowController {
+    running: bool,
+    last_advance: Option<Instant>,
+    /// When the next slide came due while still downloading
+    waiting_since: Option<Instant>,
+    skip_log: Vec<SkippedSlide>,
+}
+
+#[cfg(feature = "gui")]
+impl SlideshowController {
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn start(&mut self) {
+        self.running = true;
+        self.last_advance = Some(Instant::now());
+    }
+
+    pub fn stop(&mut self) {
+        self.running = false;
+        self.last_advance = None;
+        self.waiting_since = None;
+    }
+
+    pub fn is_waiting(&self) -> bool {
+        self.waiting_since.is_some()
+    }
+
+    /// Hold the current slide while the next one finishes downloading
+    pub fn set_waiting(&mut self, waiting: bool) {
+        if !waiting {
+            self.waiting_since = None;
+        } else if self.waiting_since.is_none() {
+            self.waiting_since = Some(Instant::now());
+        }
+    }
+
+    /// How long the current slide has been held for a download
+    pub fn waited(&self) -> Duration {
+        self.waiting_since.map_or(Duration::ZERO, |since| since.elapsed())
+    }
+
+    /// Whether the current slide has been shown for `interval`
+    pub fn is_due(&self, interval: Duration) -> bool {
+        self.running && self.last_advance.is_none_or(|last| last.elapsed() >= interval)
+    }
+
+    /// Time until the next slide is due
+    pub fn time_remaining(&self, interval: Duration) -> Duration {
+        self.last_advance.map_or(Duration::ZERO, |last| interval.saturating_sub(last.elapsed()))
+    }
+
+    /// Index of the next slide after `current` that passes the rules, wrapping
+    /// around the list. Skipped slides are logged. Returns None when every
+    /// slide is skipped.
+    pub fn advance(
+        &mut self,
+        current: Option<usize>,
+        len: usize,
+        rules: &SkipRules,
+        mut facts: impl FnMut(usize) -> SlideFacts,
+    ) -> Option<usize> {
+        self.last_advance = Some(Instant::now());
+        self.waiting_since = None;
+        let start = current.map_or(0, |i| i + 1);
+        for offset in 0..len {
+            let index = (start + offset) % len;
+            let candidate = facts(index);
+            match rules.check(&candidate) {
+                Some(reason) => self.log_skip(candidate.path, reason),
+                None => return Some(index),
+            }
+        }
+        None
+    }
+
+    fn log_skip(&mut self, path: PathBuf, reason: SkipReason) {
+        if self.skip_log.len() >= MAX_SKIP_LOG {
+            self.skip_log.remove(0);
+        }
+        self.skip_log.push(SkippedSlide { path, reason });
+    }
+
+    /// The next `count` slides after `current` that pass the rules, in playback order
+    pub fn upcoming(
+        current: Option<usize>,
+        len: usize,
+        count: usize,
+        rules: &SkipRules,
+        mut facts: impl FnMut(usize) -> SlideFacts,
+    ) -> Vec<usize> {
+        let start = current.map_or(0, |i| i + 1);
+        (0..len)
+            .map(|offset| (start + offset) % len)
+            .filter(|&index| Some(index) != current && rules.check(&facts(index)).is_none())
+            .take(count)
+            .collect()
+    }
+
+    /// Skipped slides, oldest first
+    pub fn skip_log(&self) -> &[SkippedSlide] {
+        &self.skip_log
+    }
+
+    pub fn clear_skip_log(&mut self) {
+        self.skip_log.clear();
+    }
+}
+
+/// Download budget for reading ahead during one slideshow session
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone, Default)]
+pub struct ReadAhead {
+    limit_bytes: Option<u64>,
+    used_bytes: u64,
+    requested: HashSet<PathBuf>,
+}
+
+#[cfg(feature = "gui")]
+impl ReadAhead {
+    /// Start a session; None means downloads are unlimited
+    pub fn new(limit_bytes: Option<u64>) -> Self {
+        Self { limit_bytes, ..Self::default() }
+    }
+
+    pub fn used_bytes(&self) -> u64 {
+        self.used_bytes
+    }
+
+    pub fn limit_bytes(&self) -> Option<u64> {
+        self.limit_bytes
+    }
+
+    pub fn was_requested(&self, path: &Path) -> bool {
+        self.requested.contains(path)
+    }
+
+    /// Whether a file of `size` bytes can still be downloaded this session
+    pub fn can_fit(&self, path: &Path, size: u64) -> bool {
+        self.was_requested(path) || self.limit_bytes.is_none_or(|limit| self.used_bytes + size <= limit)
+    }
+
+    /// Count a download against the budget. Returns false if it was already
+    /// requested or doesn't fit.
+    pub fn admit(&mut self, path: &Path, size: u64) -> bool {
+        if self.was_requested(path) || !self.can_fit(path, size) {
+            return false;
+        }
+        self.used_bytes += size;
+        self.requested.insert(path.to_path_buf());
+        true
+    }
+}
+
+#[cfg(all(test, feature = "gui"))]
+mod tests {
+    use super::*;
+
+    fn facts(name: &str) -> SlideFacts {
+        SlideFacts {
+            path: PathBuf::from(name),
+            on_demand: false,
+            megapixels: Some(2.0),
+            failed_integrity: false,
+            over_download_limit: false,
+        }
+    }
+
+    #[test]
+    fn test_rules() {
+        let rules = SkipRules { max_megapixels: Some(12.0), skip_formats: vec!["gif".to_string()], ..SkipRules::default() };
+        assert_eq!(rules.check(&facts("a.jpg")), None);
+        assert_eq!(rules.check(&facts("a.GIF")), Some(SkipReason::Format("gif".to_string())));
+        assert_eq!(rules.check(&SlideFacts { on_demand: true, ..facts("a.jpg") }), Some(SkipReason::OnDemand));
+        assert_eq!(rules.check(&SlideFacts { failed_integrity: true, ..facts("a.jpg") }), Some(SkipReason::FailedIntegrity));
+        assert_eq!(
+            rules.check(&SlideFacts { megapixels: Some(24.0), ..facts("a.jpg") }),
+            Some(SkipReason::TooLarge { megapixels: 24.0, limit: 12.0 })
+        );
+        // Unknown size doesn't count against the limit
+        assert_eq!(rules.check(&SlideFacts { megapixels: None, ..facts("a.jpg") }), None);
+    }
+
+    #[test]
+    fn test_advance_skips_and_wraps() {
+        let names = ["a.jpg", "b.gif", "c.png", "d.gif"];
+        let rules = SkipRules { skip_formats: vec!["gif".to_string()], ..SkipRules::default() };
+        let mut controller = SlideshowController::default();
+
+        assert_eq!(controller.advance(Some(0), names.len(), &rules, |i| facts(names[i])), Some(2));
+        assert_eq!(controller.advance(Some(2), names.len(), &rules, |i| facts(names[i])), Some(0));
+        assert_eq!(controller.skip_log().len(), 2);
+        assert_eq!(controller.skip_log()[1].path, PathBuf::from("d.gif"));
+
+        let only_gifs = ["x.gif", "y.gif"];
+        assert_eq!(controller.advance(None, only_gifs.len(), &rules, |i| facts(only_gifs[i])), None);
+
+        let upcoming = SlideshowController::upcoming(Some(2), names.len(), 3, &rules, |i| facts(names[i]));
+        assert_eq!(upcoming, [0]);
+    }
+
+    #[test]
+    fn test_waiting_keeps_its_start() {
+        let mut controller = SlideshowController::default();
+        assert_eq!(controller.waited(), Duration::ZERO);
+        controller.set_waiting(true);
+        let since = controller.waiting_since;
+        controller.set_waiting(true);
+        assert!(controller.is_waiting());
+        assert_eq!(controller.waiting_since, since);
+        controller.advance(None, 1, &SkipRules::default(), |_| facts("a.jpg"));
+        assert!(!controller.is_waiting());
+    }
+
+    #[test]
+    fn test_read_ahead_budget() {
+        let mut read_ahead = ReadAhead::new(Some(1000));
+        assert!(read_ahead.admit(Path::new("a.jpg"), 600));
+        assert!(!read_ahead.admit(Path::new("a.jpg"), 600));
+        assert!(!read_ahead.can_fit(Path::new("b.jpg"), 500));
+        assert!(read_ahead.can_fit(Path::new("a.jpg"), 600));
+        assert!(read_ahead.admit(Path::new("c.jpg"), 400));
+        assert_eq!(read_ahead.used_bytes(), 1000);
+
+        let over = SlideFacts { on_demand: true, over_download_limit: true, ..facts("b.jpg") };
+        let rules = SkipRules { skip_on_demand: false, ..SkipRules::default() };
+        assert_eq!(rules.check(&over), Some(SkipReason::DownloadLimit));
+        assert!(ReadAhead::new(None).can_fit(Path::new("huge.jpg"), u64::MAX / 2));
+    }
+}
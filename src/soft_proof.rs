@@ -0,0 +1,249 @@
+//! Soft-proofing: preview how an image will look when printed
+//!
+//! Output conditions are described by their paper white, the darkest black
+//! the process can print and the chroma it can reproduce. Colors are moved
+//! through CIELAB: chroma is compressed into the printable range, lightness
+//! is mapped onto the paper's range (scaled with black-point compensation,
+//! clipped without it) and the result is shown either relative to the
+//! display white or on the simulated paper white.
+
+#[cfg(feature = "gui")]
+use eframe::egui::{Color32, ColorImage};
+
+/// A printing condition to simulate
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputProfile {
+    pub name: &'static str,
+    /// CIELAB color of the unprinted paper
+    pub paper_white: [f32; 3],
+    /// Lightness of the darkest printable black
+    pub black_lightness: f32,
+    /// Highest chroma the inks reproduce
+    pub max_chroma: f32,
+}
+
+#[cfg(feature = "gui")]
+pub const OUTPUT_PROFILES: &[OutputProfile] = &[
+    OutputProfile { name: "Generic coated paper", paper_white: [95.0, 0.0, -2.0], black_lightness: 12.0, max_chroma: 90.0 },
+    OutputProfile { name: "Generic uncoated paper", paper_white: [94.0, 0.0, -1.0], black_lightness: 28.0, max_chroma: 65.0 },
+    OutputProfile { name: "Newsprint", paper_white: [84.0, 0.0, 5.0], black_lightness: 35.0, max_chroma: 50.0 },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SoftProofOptions {
+    /// Index into [`OUTPUT_PROFILES`]
+    pub profile: usize,
+    pub black_point_compensation: bool,
+    pub simulate_paper_white: bool,
+}
+
+impl Default for SoftProofOptions {
+    fn default() -> Self {
+        Self { profile: 0, black_point_compensation: true, simulate_paper_white: true }
+    }
+}
+
+#[cfg(feature = "gui")]
+impl SoftProofOptions {
+    pub(crate) fn output_profile(&self) -> &'static OutputProfile {
+        &OUTPUT_PROFILES[self.profile.min(OUTPUT_PROFILES.len() - 1)]
+    }
+}
+
+// D65 reference white, as used by sRGB
+#[cfg(feature = "gui")]
+const WHITE: [f32; 3] = [0.950_47, 1.0, 1.088_83];
+
+#[cfg(feature = "gui")]
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.040_45 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+#[cfg(feature = "gui")]
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.003_130_8 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+#[cfg(feature = "gui")]
+pub fn srgb_to_lab(rgb: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(srgb_to_linear);
+    let xyz = [
+        0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b,
+        0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b,
+        0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b,
+    ];
+    let f = |t: f32| if t > 216.0 / 24_389.0 { t.cbrt() } else { (24_389.0 / 27.0 * t + 16.0) / 116.0 };
+    let [fx, fy, fz] = [f(xyz[0] / WHITE[0]), f(xyz[1] / WHITE[1]), f(xyz[2] / WHITE[2])];
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+#[cfg(feature = "gui")]
+pub fn lab_to_srgb(lab: [f32; 3]) -> [f32; 3] {
+    let fy = (lab[0] + 16.0) / 116.0;
+    let fx = fy + lab[1] / 500.0;
+    let fz = fy - lab[2] / 200.0;
+    let inverse = |t: f32| if t.powi(3) > 216.0 / 24_389.0 { t.powi(3) } else { (116.0 * t - 16.0) * 27.0 / 24_389.0 };
+    let [x, y, z] = [inverse(fx) * WHITE[0], inverse(fy) * WHITE[1], inverse(fz) * WHITE[2]];
+    let linear = [
+        3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z,
+        -0.969_266 * x + 1.876_010_8 * y + 0.041_556 * z,
+        0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z,
+    ];
+    linear.map(|c| linear_to_srgb(c.clamp(0.0, 1.0)))
+}
+
+/// Simulated appearance of a CIELAB color printed under `options`
+#[cfg(feature = "gui")]
+pub fn proof_lab(lab: [f32; 3], options: &SoftProofOptions) -> [f32; 3] {
+    let profile = options.output_profile();
+    let [lightness, a, b] = lab;
+    let paper = profile.paper_white;
+
+    // Compress chroma smoothly above a knee instead of clipping, keeping hue
+    let chroma = a.hypot(b);
+    let knee = profile.max_chroma * 0.8;
+    let scale = if chroma > knee {
+        let range = profile.max_chroma - knee;
+        (knee + range * ((chroma - knee) / range).tanh()) / chroma
+    } else {
+        1.0
+    };
+    let (a, b) = (a * scale, b * scale);
+
+    let printed = if options.black_point_compensation {
+        profile.black_lightness + lightness * (paper[0] - profile.black_lightness) / 100.0
+    } else {
+        (lightness * paper[0] / 100.0).max(profile.black_lightness)
+    };
+
+    if options.simulate_paper_white {
+        // Unprinted areas take on the paper's tint, fading out towards black
+        let tint = printed / paper[0];
+        [printed, a + paper[1] * tint, b + paper[2] * tint]
+    } else {
+        [printed * 100.0 / paper[0], a, b]
+    }
+}
+
+/// Lookup table size per channel; colors in between are interpolated
+#[cfg(feature = "gui")]
+const LUT_SIZE: usize = 33;
+
+/// Precomputed soft-proof transform for sRGB images
+#[cfg(feature = "gui")]
+pub struct ProofTransform {
+    lut: Vec<[f32; 3]>,
+}
+
+#[cfg(feature = "gui")]
+impl ProofTransform {
+    pub fn new(options: &SoftProofOptions) -> Self {
+        let step = 1.0 / (LUT_SIZE - 1) as f32;
+        let mut lut = Vec::with_capacity(LUT_SIZE.pow(3));
+        for r in 0..LUT_SIZE {
+            for g in 0..LUT_SIZE {
+                for b in 0..LUT_SIZE {
+                    let rgb = [r as f32 * step, g as f32 * step, b as f32 * step];
+                    lut.push(lab_to_srgb(proof_lab(srgb_to_lab(rgb), options)));
+                }
+            }
+        }
+        Self { lut }
+    }
+
+    fn entry(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.lut[(r * LUT_SIZE + g) * LUT_SIZE + b]
+    }
+
+    /// Trilinear lookup of one sRGB color
+    pub fn apply_rgb(&self, rgb: [u8; 3]) -> [u8; 3] {
+        let scaled = rgb.map(|c| c as f32 / 255.0 * (LUT_SIZE - 1) as f32);
+        let lower = scaled.map(|c| (c.floor() as usize).min(LUT_SIZE - 2));
+        let t = [0, 1, 2].map(|i| scaled[i] - lower[i] as f32);
+
+        let mut out = [0.0; 3];
+        for corner in 0..8 {
+            let (dr, dg, db) = (corner >> 2 & 1, corner >> 1 & 1, corner & 1);
+            let weight = [dr, dg, db]
+                .iter()
+                .zip(t)
+                .map(|(&d, t)| if d == 1 { t } else { 1.0 - t })
+                .product::<f32>();
+            let value = self.entry(lower[0] + dr, lower[1] + dg, lower[2] + db);
+            for channel in 0..3 {
+                out[channel] += value[channel] * weight;
+            }
+        }
+        out.map(|c| (c * 255.0).round().clamp(0.0, 255.0) as u8)
+    }
+
+    pub fn apply(&self, image: &ColorImage) -> ColorImage {
+        let pixels = image.pixels
+            .iter()
+            .map(|pixel| {
+                let [r, g, b, a] = pixel.to_srgba_unmultiplied();
+                let [r, g, b] = self.apply_rgb([r, g, b]);
+                Color32::from_rgba_unmultiplied(r, g, b, a)
+            })
+            .collect();
+        ColorImage { size: image.size, pixels }
+    }
+}
+
+#[cfg(all(test, feature = "gui"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lab_round_trip() {
+        for rgb in [[0.0, 0.0, 0.0], [1.0, 1.0, 1.0], [0.2, 0.4, 0.8], [1.0, 0.0, 0.0]] {
+            let back = lab_to_srgb(srgb_to_lab(rgb));
+            for channel in 0..3 {
+                assert!((back[channel] - rgb[channel]).abs() < 1e-3, "{:?} -> {:?}", rgb, back);
+            }
+        }
+        let white = srgb_to_lab([1.0, 1.0, 1.0]);
+        assert!((white[0] - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_proof_maps_white_and_black_to_paper() {
+        let simulate = SoftProofOptions::default();
+        let profile = simulate.output_profile();
+        let white = proof_lab([100.0, 0.0, 0.0], &simulate);
+        assert!((white[0] - profile.paper_white[0]).abs() < 1e-3);
+        assert!((white[2] - profile.paper_white[2]).abs() < 1e-3);
+        assert!((proof_lab([0.0, 0.0, 0.0], &simulate)[0] - profile.black_lightness).abs() < 1e-3);
+
+        // Relative to the display, paper white shows as white
+        let relative = SoftProofOptions { simulate_paper_white: false, ..simulate };
+        assert!((proof_lab([100.0, 0.0, 0.0], &relative)[0] - 100.0).abs() < 1e-3);
+
+        // Without black-point compensation, shadow detail is clipped
+        let clipped = SoftProofOptions { black_point_compensation: false, ..simulate };
+        assert_eq!(proof_lab([5.0, 0.0, 0.0], &clipped)[0], proof_lab([10.0, 0.0, 0.0], &clipped)[0]);
+
+        // Saturated colors are pulled into the printable range
+        let red = proof_lab([54.0, 80.0, 67.0], &SoftProofOptions { profile: 2, ..simulate });
+        assert!(red[1].hypot(red[2]) <= OUTPUT_PROFILES[2].max_chroma + 5.0);
+    }
+
+    #[test]
+    fn test_transform_matches_direct_conversion() {
+        let options = SoftProofOptions::default();
+        let transform = ProofTransform::new(&options);
+        let direct = lab_to_srgb(proof_lab(srgb_to_lab([1.0, 1.0, 1.0]), &options)).map(|c| (c * 255.0).round() as u8);
+        assert_eq!(transform.apply_rgb([255, 255, 255]), direct);
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_transform_keeps_alpha() {
+        let transform = ProofTransform::new(&SoftProofOptions::default());
+        let image = ColorImage::new([2, 1], Color32::WHITE);
+        let proofed = transform.apply(&image);
+        assert_ne!(proofed.pixels[0], Color32::WHITE);
+        assert_eq!(proofed.pixels[0].a(), 255);
+    }
+}
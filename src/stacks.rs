@@ -0,0 +1,173 @@
+//! Stacks of images that belong together
+//!
+//! A camera shooting RAW+JPEG writes two files with the same name, and a
+//! burst writes several shots moments apart. The file list collapses each
+//! into one row for its first image, with a badge that expands the stack.
+//! RAW files can't be shown, so they are never listed; one that pairs with
+//! an image is kept as that image's companion and goes along when it is
+//! moved or deleted. Bursts are found from modification times, which cameras and most
+//! copy tools keep, so nothing is read from on-demand files.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::file_locality::FileInfo;
+
+pub const RAW_EXTENSIONS: [&str; 11] = ["cr2", "cr3", "nef", "nrw", "arw", "dng", "orf", "rw2", "raf", "pef", "srw"];
+
+/// Shots at most this far apart belong to the same burst
+pub const BURST_GAP: Duration = Duration::from_secs(1);
+
+/// Fewer consecutive shots than this are not a burst
+pub const MIN_BURST: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StackKind {
+    RawPair,
+    Burst,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stack {
+    pub kind: StackKind,
+    /// Indices into the folder's files; the first one stands for the stack
+    pub members: Vec<usize>,
+    /// RAW files of the members, which aren't listed
+    pub raw: Vec<PathBuf>,
+}
+
+impl Stack {
+    /// Files in the stack, RAW files included
+    pub fn file_count(&self) -> usize {
+        self.members.len() + self.raw.len()
+    }
+
+    pub fn description(&self) -> String {
+        match self.kind {
+            StackKind::RawPair => format!("RAW+JPEG: {} files of one shot", self.file_count()),
+            StackKind::Burst => format!("Burst: {} files taken moments apart", self.file_count()),
+        }
+    }
+}
+
+pub fn is_raw(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| RAW_EXTENSIONS.iter().any(|raw| ext.eq_ignore_ascii_case(raw)))
+}
+
+fn stem_key(path: &Path) -> (Option<&Path>, String) {
+    (path.parent(), path.file_stem().unwrap_or_default().to_string_lossy().to_lowercase())
+}
+
+/// RAW files in `dir`
+pub fn raw_files(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|entry| entry.path()).filter(|path| is_raw(path)).collect())
+        .unwrap_or_default()
+}
+
+/// The RAW files among `raw` with an image of the same name in `files`,
+/// keyed by that image. RAW files without one are dropped.
+pub fn raw_companions(files: &[FileInfo], raw: Vec<PathBuf>) -> HashMap<PathBuf, Vec<PathBuf>> {
+    pair_raw_files(files, raw).0
+}
+
+/// Like [`raw_companions`], also returning the RAW files without an image
+pub fn pair_raw_files(files: &[FileInfo], raw: Vec<PathBuf>) -> (HashMap<PathBuf, Vec<PathBuf>>, Vec<PathBuf>) {
+    let shown: HashMap<(Option<&Path>, String), &Path> = files.iter()
+        .map(|file| (stem_key(&file.path), file.path.as_path()))
+        .collect();
+    let mut companions: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    let mut unpaired = Vec::new();
+    for path in raw {
+        match shown.get(&stem_key(&path)) {
+            Some(image) => companions.entry(image.to_path_buf()).or_default().push(path),
+            None => unpaired.push(path),
+        }
+    }
+    for raw in companions.values_mut() {
+        raw.sort();
+    }
+    (companions, unpaired)
+}
+
+/// The stacks among `files`, in list order, with the RAW `companions` of each image
+pub fn find_stacks(files: &[FileInfo], companions: &HashMap<PathBuf, Vec<PathBuf>>) -> Vec<Stack> {
+    let paths: Vec<&Path> = files.iter().map(|file| file.path.as_path()).collect();
+    let times: Vec<Option<SystemTime>> = paths.iter()
+        .map(|path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+        .collect();
+    stacks_from(&paths, &times, companions)
+}
+
+/// Stacks of `paths`, sorted by name, taken at `times`
+fn stacks_from(paths: &[&Path], times: &[Option<SystemTime>], companions: &HashMap<PathBuf, Vec<PathBuf>>) -> Vec<Stack> {
+    // Files of one shot, the image that can be shown first
+    let mut shots: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    let mut by_stem: BTreeMap<(Option<&Path>, String), usize> = BTreeMap::new();
+    for (index, path) in paths.iter().enumerate() {
+        match by_stem.get(&stem_key(path)) {
+            Some(&first) => shots.entry(first).or_default().push(index),
+            None => {
+                by_stem.insert(stem_key(path), index);
+                shots.insert(index, vec![index]);
+            }
+        }
+    }
+
+    let close = |a: usize, b: usize| match (times[a], times[b]) {
+        (Some(a), Some(b)) => a.duration_since(b).or_else(|_| b.duration_since(a)).is_ok_and(|gap| gap <= BURST_GAP),
+        _ => false,
+    };
+    let shots: Vec<Vec<usize>> = shots.into_values().collect();
+    let stack = |kind, members: Vec<usize>| {
+        let raw = members.iter().flat_map(|&index| companions.get(paths[index])).flatten().cloned().collect();
+        Stack { kind, members, raw }
+    };
+    let mut stacks = Vec::new();
+    let mut start = 0;
+    while start < shots.len() {
+        let mut end = start + 1;
+        while end < shots.len() && close(shots[end - 1][0], shots[end][0]) {
+            end += 1;
+        }
+        if end - start >= MIN_BURST {
+            stacks.push(stack(StackKind::Burst, shots[start..end].concat()));
+        } else {
+            stacks.extend(shots[start..end].iter()
+                .map(|members| stack(StackKind::RawPair, members.clone()))
+                .filter(|stack| stack.file_count() > 1));
+        }
+        start = end;
+    }
+    stacks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairs_and_bursts_are_stacked() {
+        let shoot = |name: &str| Path::new("shoot").join(name);
+        let files: Vec<FileInfo> = ["a.jpg", "b.jpg", "d.jpg", "e.jpg", "f.jpg"]
+            .iter().map(|name| FileInfo::new(shoot(name))).collect();
+        let raw = ["a.cr2", "c.nef", "d.nef", "x.dng"].iter().map(|name| shoot(name)).collect();
+        let (companions, unpaired) = pair_raw_files(&files, raw);
+        assert_eq!(companions, HashMap::from([
+            (shoot("a.jpg"), vec![shoot("a.cr2")]),
+            (shoot("d.jpg"), vec![shoot("d.nef")]),
+        ]));
+        assert_eq!(unpaired, vec![shoot("c.nef"), shoot("x.dng")]);
+
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let at = |millis: u64| Some(start + Duration::from_millis(millis));
+        // a stands alone; d, e and f are a burst
+        let times = [at(0), at(60_000), at(120_000), at(120_400), at(121_200)];
+        let refs: Vec<&Path> = files.iter().map(|file| file.path.as_path()).collect();
+        assert_eq!(stacks_from(&refs, &times, &companions), vec![
+            Stack { kind: StackKind::RawPair, members: vec![0], raw: vec![shoot("a.cr2")] },
+            Stack { kind: StackKind::Burst, members: vec![2, 3, 4], raw: vec![shoot("d.nef")] },
+        ]);
+    }
+}
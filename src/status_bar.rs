@@ -0,0 +1,141 @@
+//! Image and system details shown in the bottom status bar
+
+use std::time::{Duration, Instant};
+
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+use crate::file_locality::FileLocalityStatus;
+
+/// How often process memory usage is re-read
+const MEMORY_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What the status bar shows about the displayed image and the app
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatusInfo {
+    pub dimensions: Option<(u32, u32)>,
+    pub zoom_percent: Option<f32>,
+    pub file_size: Option<u64>,
+    pub format: Option<String>,
+    pub locality: Option<FileLocalityStatus>,
+    /// 1-based position of the selected image and the number of images
+    pub position: Option<(usize, usize)>,
+    pub memory_bytes: Option<u64>,
+}
+
+impl StatusInfo {
+    /// Segments describing the image, in display order
+    pub fn image_segments(&self) -> Vec<String> {
+        let mut segments = Vec::new();
+        if let Some((index, total)) = self.position {
+            segments.push(format!("{}/{}", index, total));
+        }
+        if let Some((width, height)) = self.dimensions {
+            segments.push(format!("{} × {}", width, height));
+        }
+        if let Some(zoom) = self.zoom_percent {
+            segments.push(format!("{:.0}%", zoom));
+        }
+        if let Some(size) = self.file_size {
+            segments.push(format_size(size));
+        }
+        if let Some(format) = &self.format {
+            segments.push(format.to_uppercase());
+        }
+        if let Some(locality) = &self.locality {
+            segments.push(format!("{} {}", locality.icon(), locality.label()));
+        }
+        segments
+    }
+}
+
+/// Human-readable byte count, e.g. "2.4 MB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Tracks this process's memory use, refreshing at most every couple of seconds
+pub struct MemoryMonitor {
+    system: System,
+    pid: Option<Pid>,
+    last_refresh: Option<Instant>,
+    memory_bytes: Option<u64>,
+}
+
+impl Default for MemoryMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryMonitor {
+    pub fn new() -> Self {
+        Self {
+            system: System::new(),
+            pid: sysinfo::get_current_pid().ok(),
+            last_refresh: None,
+            memory_bytes: None,
+        }
+    }
+
+    /// Re-read memory usage if the refresh interval has passed
+    pub fn poll(&mut self) {
+        if self.last_refresh.is_some_and(|last| last.elapsed() < MEMORY_REFRESH_INTERVAL) {
+            return;
+        }
+        self.last_refresh = Some(Instant::now());
+        let Some(pid) = self.pid else { return };
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            true,
+            ProcessRefreshKind::nothing().with_memory(),
+        );
+        self.memory_bytes = self.system.process(pid).map(|process| process.memory());
+    }
+
+    /// Resident memory in bytes, once it has been read
+    pub fn memory_bytes(&self) -> Option<u64> {
+        self.memory_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(2048), "2.0 KB");
+        assert_eq!(format_size(5 * 1024 * 1024 + 300 * 1024), "5.3 MB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn test_image_segments() {
+        let info = StatusInfo {
+            dimensions: Some((1920, 1080)),
+            zoom_percent: Some(50.0),
+            file_size: Some(2 * 1024 * 1024),
+            format: Some("png".to_string()),
+            locality: Some(FileLocalityStatus::Hydrating { percent: 40 }),
+            position: Some((14, 230)),
+            memory_bytes: None,
+        };
+        assert_eq!(info.image_segments(), ["14/230", "1920 × 1080", "50%", "2.0 MB", "PNG", "⬇️ Downloading 40%"]);
+        assert!(StatusInfo::default().image_segments().is_empty());
+
+        let mut monitor = MemoryMonitor::new();
+        monitor.poll();
+        assert!(monitor.memory_bytes().is_some_and(|bytes| bytes > 0));
+    }
+}
@@ -0,0 +1,182 @@
+//! Read-only SVG source view with lightweight XML syntax highlighting
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Range;
+
+use eframe::egui;
+use egui::text::{LayoutJob, TextFormat};
+
+/// Source text of the displayed SVG, before and after recoloring
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgSource {
+    pub original: String,
+    /// Only present when recoloring was applied to this file
+    pub recolored: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XmlTokenKind {
+    /// Tag delimiters and element names, e.g. `<path`, `/>`
+    Tag,
+    AttributeName,
+    AttributeValue,
+    Comment,
+    /// Character data and whitespace between tokens
+    Text,
+}
+
+/// Split XML into highlighted spans. Malformed input never fails; unterminated
+/// constructs run to the end of the text.
+pub fn tokenize_xml(text: &str) -> Vec<(XmlTokenKind, Range<usize>)> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    let find = |from: usize, needle: &str| text[from..].find(needle).map_or(text.len(), |i| from + i);
+
+    while pos < bytes.len() {
+        if bytes[pos..].starts_with(b"<!--") {
+            let end = (find(pos + 4, "-->") + 3).min(text.len());
+            tokens.push((XmlTokenKind::Comment, pos..end));
+            pos = end;
+        } else if bytes[pos] == b'<' {
+            // Tag opener and element name
+            let mut end = pos + 1;
+            while end < bytes.len() && !bytes[end].is_ascii_whitespace() && bytes[end] != b'>' && !bytes[end..].starts_with(b"/>") {
+                end += 1;
+            }
+            tokens.push((XmlTokenKind::Tag, pos..end));
+            pos = end;
+
+            // Attributes until the tag closes
+            while pos < bytes.len() {
+                let b = bytes[pos];
+                if b == b'>' {
+                    tokens.push((XmlTokenKind::Tag, pos..pos + 1));
+                    pos += 1;
+                    break;
+                } else if bytes[pos..].starts_with(b"/>") || bytes[pos..].starts_with(b"?>") {
+                    tokens.push((XmlTokenKind::Tag, pos..pos + 2));
+                    pos += 2;
+                    break;
+                } else if b == b'"' || b == b'\'' {
+                    let close = text[pos + 1..].find(b as char).map_or(text.len(), |i| pos + 1 + i + 1);
+                    tokens.push((XmlTokenKind::AttributeValue, pos..close));
+                    pos = close;
+                } else if b.is_ascii_whitespace() || b == b'=' {
+                    let start = pos;
+                    while pos < bytes.len() && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'=') {
+                        pos += 1;
+                    }
+                    tokens.push((XmlTokenKind::Text, start..pos));
+                } else {
+                    let start = pos;
+                    while pos < bytes.len() && !matches!(bytes[pos], b'=' | b'>' | b'"' | b'\'' | b'/' | b'?') && !bytes[pos].is_ascii_whitespace() {
+                        pos += 1;
+                    }
+                    // A stray '/' or '?' that doesn't close the tag
+                    if pos == start {
+                        pos += 1;
+                    }
+                    tokens.push((XmlTokenKind::AttributeName, start..pos));
+                }
+            }
+        } else {
+            let end = find(pos, "<");
+            tokens.push((XmlTokenKind::Text, pos..end));
+            pos = end;
+        }
+    }
+    tokens
+}
+
+fn token_color(kind: XmlTokenKind, dark_mode: bool) -> egui::Color32 {
+    match (kind, dark_mode) {
+        (XmlTokenKind::Tag, true) => egui::Color32::from_rgb(86, 156, 214),
+        (XmlTokenKind::Tag, false) => egui::Color32::from_rgb(0, 0, 170),
+        (XmlTokenKind::AttributeName, true) => egui::Color32::from_rgb(156, 220, 254),
+        (XmlTokenKind::AttributeName, false) => egui::Color32::from_rgb(170, 0, 0),
+        (XmlTokenKind::AttributeValue, true) => egui::Color32::from_rgb(206, 145, 120),
+        (XmlTokenKind::AttributeValue, false) => egui::Color32::from_rgb(0, 120, 0),
+        (XmlTokenKind::Comment, _) => egui::Color32::GRAY,
+        (XmlTokenKind::Text, true) => egui::Color32::LIGHT_GRAY,
+        (XmlTokenKind::Text, false) => egui::Color32::DARK_GRAY,
+    }
+}
+
+/// Build a monospace layout job with XML syntax colors
+pub fn highlight_xml(text: &str, font_id: egui::FontId, dark_mode: bool) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    for (kind, range) in tokenize_xml(text) {
+        job.append(&text[range], 0.0, TextFormat::simple(font_id.clone(), token_color(kind, dark_mode)));
+    }
+    job
+}
+
+/// The last highlighted source, so the panel doesn't re-tokenize it every frame
+#[derive(Default)]
+pub struct XmlHighlighter {
+    key: Option<(u64, egui::FontId, bool)>,
+    job: LayoutJob,
+}
+
+impl XmlHighlighter {
+    /// [`highlight_xml`] for `text`, reused while the text, font and mode are unchanged
+    pub fn layout_job(&mut self, text: &str, font_id: egui::FontId, dark_mode: bool) -> LayoutJob {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        let key = (hasher.finish(), font_id, dark_mode);
+        if self.key.as_ref() != Some(&key) {
+            self.job = highlight_xml(text, key.1.clone(), dark_mode);
+            self.key = Some(key);
+        }
+        self.job.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(text: &str) -> Vec<(XmlTokenKind, &str)> {
+        tokenize_xml(text).into_iter().map(|(kind, range)| (kind, &text[range])).collect()
+    }
+
+    #[test]
+    fn test_tokenize_element_with_attributes() {
+        use XmlTokenKind::*;
+        assert_eq!(spans(r#"<path id="a" class='b'/>"#), vec![
+            (Tag, "<path"), (Text, " "), (AttributeName, "id"), (Text, "="), (AttributeValue, "\"a\""),
+            (Text, " "), (AttributeName, "class"), (Text, "="), (AttributeValue, "'b'"), (Tag, "/>"),
+        ]);
+        assert_eq!(spans("<!-- hi --><g>x</g>"), vec![
+            (Comment, "<!-- hi -->"), (Tag, "<g"), (Tag, ">"), (Text, "x"), (Tag, "</g"), (Tag, ">"),
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_covers_malformed_input() {
+        for text in ["<svg a=\"unterminated", "<!-- open", "plain < text", "<a / b>", ""] {
+            let covered: usize = tokenize_xml(text).iter().map(|(_, range)| range.len()).sum();
+            assert_eq!(covered, text.len(), "tokens must cover {:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_tokenize_non_ascii_names() {
+        use XmlTokenKind::*;
+        assert_eq!(spans("<é>"), vec![(Tag, "<é"), (Tag, ">")]);
+        assert_eq!(spans(r#"<图形 名="值"/>文本"#), vec![
+            (Tag, "<图形"), (Text, " "), (AttributeName, "名"), (Text, "="), (AttributeValue, r#""值""#), (Tag, "/>"), (Text, "文本"),
+        ]);
+    }
+
+    #[test]
+    fn test_highlighter_follows_text_and_mode() {
+        let font = egui::FontId::monospace(12.0);
+        let mut highlighter = XmlHighlighter::default();
+        assert_eq!(highlighter.layout_job("<a/>", font.clone(), true), highlight_xml("<a/>", font.clone(), true));
+        assert_eq!(highlighter.layout_job("<a/>", font.clone(), false), highlight_xml("<a/>", font.clone(), false));
+        assert_eq!(highlighter.layout_job("<b/>", font.clone(), false).text, "<b/>");
+    }
+}
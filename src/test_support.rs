@@ -0,0 +1,40 @@
+//! Helpers shared by the unit tests
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// An empty directory of its own for one test, removed when dropped, so
+/// tests running in parallel or after a failed run never see each other's files
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// A new directory whose name starts with `name`
+    pub(crate) fn new(name: &str) -> Self {
+        static CREATED: AtomicU64 = AtomicU64::new(0);
+        let number = CREATED.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("image_previewer_{}_{}_{}", name, std::process::id(), number));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("test directory can be created");
+        Self(path)
+    }
+}
+
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
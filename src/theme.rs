@@ -0,0 +1,138 @@
+//! Dark, light and high-contrast UI themes with a custom accent color
+//!
+//! The theme also owns the color shown behind previews, which used to be a
+//! fixed gray.
+
+use ecolor::Color32;
+#[cfg(feature = "gui")]
+use eframe::egui::{self, Stroke, Visuals};
+
+use crate::backdrop::DEFAULT_BACKDROP;
+#[cfg(feature = "gui")]
+use crate::backdrop::relative_luminance;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemePreset {
+    Dark,
+    Light,
+    HighContrast,
+}
+
+impl ThemePreset {
+    pub const ALL: [ThemePreset; 3] = [ThemePreset::Dark, ThemePreset::Light, ThemePreset::HighContrast];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ThemePreset::Dark => "Dark",
+            ThemePreset::Light => "Light",
+            ThemePreset::HighContrast => "High Contrast",
+        }
+    }
+
+    /// Accent the preset uses unless a custom one is chosen
+    pub fn default_accent(&self) -> [u8; 3] {
+        match self {
+            ThemePreset::Dark => [0, 92, 128],
+            ThemePreset::Light => [144, 209, 255],
+            ThemePreset::HighContrast => [255, 210, 0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub preset: ThemePreset,
+    /// Custom accent for selections and links; None uses the preset's
+    pub accent: Option<[u8; 3]>,
+    /// Color behind previews
+    pub image_background: [u8; 3],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let [r, g, b, _] = DEFAULT_BACKDROP.to_array();
+        Self { preset: ThemePreset::Dark, accent: None, image_background: [r, g, b] }
+    }
+}
+
+impl Theme {
+    pub fn accent(&self) -> Color32 {
+        let [r, g, b] = self.accent.unwrap_or_else(|| self.preset.default_accent());
+        Color32::from_rgb(r, g, b)
+    }
+
+    pub fn image_background(&self) -> Color32 {
+        let [r, g, b] = self.image_background;
+        Color32::from_rgb(r, g, b)
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn visuals(&self) -> Visuals {
+        let mut visuals = match self.preset {
+            ThemePreset::Dark => Visuals::dark(),
+            ThemePreset::Light => Visuals::light(),
+            ThemePreset::HighContrast => high_contrast_visuals(),
+        };
+        let accent = self.accent();
+        // Text on the accent must stay readable, so the stroke follows its lightness
+        let on_accent = if relative_luminance(accent) > 0.4 { Color32::BLACK } else { Color32::WHITE };
+        visuals.selection.bg_fill = accent;
+        visuals.selection.stroke = Stroke::new(1.0_f32, on_accent);
+        let text_extreme = if visuals.dark_mode { Color32::WHITE } else { Color32::BLACK };
+        visuals.hyperlink_color = accent.lerp_to_gamma(text_extreme, 0.4);
+        visuals.text_cursor.stroke.color = accent;
+        visuals
+    }
+
+    /// Apply the theme to egui
+    #[cfg(feature = "gui")]
+    pub fn apply(&self, ctx: &egui::Context) {
+        ctx.set_visuals(self.visuals());
+    }
+}
+
+#[cfg(feature = "gui")]
+fn high_contrast_visuals() -> Visuals {
+    let mut visuals = Visuals::dark();
+    visuals.override_text_color = Some(Color32::WHITE);
+    visuals.panel_fill = Color32::BLACK;
+    visuals.window_fill = Color32::BLACK;
+    visuals.extreme_bg_color = Color32::BLACK;
+    visuals.faint_bg_color = Color32::from_gray(24);
+    visuals.window_stroke = Stroke::new(1.5_f32, Color32::WHITE);
+    for widget in [
+        &mut visuals.widgets.noninteractive,
+        &mut visuals.widgets.inactive,
+        &mut visuals.widgets.hovered,
+        &mut visuals.widgets.active,
+        &mut visuals.widgets.open,
+    ] {
+        widget.bg_stroke = Stroke::new(1.0_f32, Color32::WHITE);
+        widget.fg_stroke.color = Color32::WHITE;
+    }
+    visuals.widgets.inactive.bg_fill = Color32::BLACK;
+    visuals.widgets.inactive.weak_bg_fill = Color32::BLACK;
+    visuals.widgets.hovered.bg_stroke = Stroke::new(2.0_f32, Color32::WHITE);
+    visuals
+}
+
+#[cfg(all(test, feature = "gui"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_and_accent() {
+        let theme = Theme::default();
+        assert_eq!(theme.image_background(), DEFAULT_BACKDROP);
+        assert!(theme.visuals().dark_mode);
+
+        let light = Theme { preset: ThemePreset::Light, accent: Some([200, 40, 40]), ..theme };
+        let visuals = light.visuals();
+        assert!(!visuals.dark_mode);
+        assert_eq!(visuals.selection.bg_fill, Color32::from_rgb(200, 40, 40));
+
+        let high_contrast = Theme { preset: ThemePreset::HighContrast, ..theme };
+        assert_eq!(high_contrast.visuals().override_text_color, Some(Color32::WHITE));
+        assert_eq!(high_contrast.accent(), Color32::from_rgb(255, 210, 0));
+    }
+}
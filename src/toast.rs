@@ -0,0 +1,62 @@
+//! Brief notes shown over the view that fade out on their own
+//!
+//! For things worth mentioning but not worth a click, such as navigation
+//! stepping over files. A new toast replaces the one showing.
+
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+
+/// How long a toast stays fully visible
+const SHOW_FOR: Duration = Duration::from_millis(2500);
+
+/// How long it then takes to fade out
+const FADE_FOR: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub text: String,
+    shown_at: Instant,
+}
+
+impl Toast {
+    pub fn new(text: impl Into<String>, now: Instant) -> Self {
+        Self { text: text.into(), shown_at: now }
+    }
+
+    /// How opaque the toast is at `now`, or `None` once it has faded out
+    pub fn opacity(&self, now: Instant) -> Option<f32> {
+        let fading = now.saturating_duration_since(self.shown_at).checked_sub(SHOW_FOR).unwrap_or_default();
+        (fading < FADE_FOR).then(|| 1.0 - fading.as_secs_f32() / FADE_FOR.as_secs_f32())
+    }
+
+    /// Draw the toast near the bottom of the window
+    pub fn show(&self, ctx: &egui::Context, opacity: f32) {
+        egui::Area::new(egui::Id::new("toast"))
+            .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -48.0))
+            .interactable(false)
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                ui.set_opacity(opacity);
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(&self.text);
+                });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toast_fades_out() {
+        let start = Instant::now();
+        let toast = Toast::new("Skipped 2 files", start);
+        assert_eq!(toast.opacity(start), Some(1.0));
+        assert_eq!(toast.opacity(start + SHOW_FOR), Some(1.0));
+        let halfway = toast.opacity(start + SHOW_FOR + FADE_FOR / 2).unwrap();
+        assert!((halfway - 0.5).abs() < 0.01);
+        assert_eq!(toast.opacity(start + SHOW_FOR + FADE_FOR), None);
+    }
+}
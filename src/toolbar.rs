@@ -0,0 +1,83 @@
+//! Buttons on the toolbar under the menu bar
+//!
+//! Every button can be hidden in the settings; the order is fixed.
+
+#[cfg(feature = "gui")]
+use crate::icons::Icons;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ToolbarButton {
+    OpenFolder,
+    Refresh,
+    Rotate,
+    ZoomIn,
+    ZoomOut,
+    Fit,
+    Slideshow,
+    Delete,
+    Settings,
+}
+
+impl ToolbarButton {
+    pub const ALL: [ToolbarButton; 9] = [
+        ToolbarButton::OpenFolder,
+        ToolbarButton::Refresh,
+        ToolbarButton::Rotate,
+        ToolbarButton::ZoomIn,
+        ToolbarButton::ZoomOut,
+        ToolbarButton::Fit,
+        ToolbarButton::Slideshow,
+        ToolbarButton::Delete,
+        ToolbarButton::Settings,
+    ];
+
+    #[cfg(feature = "gui")]
+    pub fn icon(&self) -> &'static str {
+        match self {
+            ToolbarButton::OpenFolder => Icons::FOLDER,
+            ToolbarButton::Refresh => Icons::REFRESH,
+            ToolbarButton::Rotate => Icons::ROTATE_CLOCKWISE,
+            ToolbarButton::ZoomIn => Icons::ZOOM_IN,
+            ToolbarButton::ZoomOut => Icons::ZOOM_OUT,
+            ToolbarButton::Fit => Icons::MAXIMIZE,
+            ToolbarButton::Slideshow => Icons::PLAYER_PLAY,
+            ToolbarButton::Delete => Icons::TRASH,
+            ToolbarButton::Settings => Icons::SETTINGS,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ToolbarButton::OpenFolder => "Open Folder",
+            ToolbarButton::Refresh => "Refresh",
+            ToolbarButton::Rotate => "Rotate Clockwise",
+            ToolbarButton::ZoomIn => "Zoom In",
+            ToolbarButton::ZoomOut => "Zoom Out",
+            ToolbarButton::Fit => "Fit to Window",
+            ToolbarButton::Slideshow => "Slideshow",
+            ToolbarButton::Delete => "Delete Image",
+            ToolbarButton::Settings => "Settings",
+        }
+    }
+
+    /// Whether the button is shown with `hidden` buttons taken off the toolbar
+    pub fn is_visible(&self, hidden: &[ToolbarButton]) -> bool {
+        !hidden.contains(self)
+    }
+}
+
+#[cfg(all(test, feature = "gui"))]
+mod tests {
+    use super::*;
+    use crate::icons::SvgIcons;
+
+    #[test]
+    fn test_toolbar_icons_are_embedded() {
+        let available = SvgIcons::get_available_icons();
+        for button in ToolbarButton::ALL {
+            assert!(available.contains(&button.icon()), "No icon for {:?}", button);
+        }
+        assert!(!ToolbarButton::Delete.is_visible(&[ToolbarButton::Delete]));
+        assert!(ToolbarButton::Rotate.is_visible(&[ToolbarButton::Delete]));
+    }
+}
@@ -0,0 +1,81 @@
+//! Finding images that can't be read
+//!
+//! A quick check reads only the header, which catches files that aren't
+//! images at all or were cut off before their dimensions. Truncated or
+//! damaged pixel data only shows up in a full decode, which is much slower.
+//! Unreadable files can be moved aside into a [`QUARANTINE_FOLDER`].
+
+use std::path::{Path, PathBuf};
+
+use image::ImageReader;
+
+use crate::format_decoders;
+use crate::image_processing::{decode_svg, read_svg_source};
+use crate::settings::ImageLoadingSettings;
+
+/// Subfolder unreadable images are moved into
+pub const QUARANTINE_FOLDER: &str = "quarantine";
+
+/// A file that failed to read, with the decoder's error
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnreadableImage {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+fn is_svg(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+}
+
+/// Read the image's header, and with `full_decode` all of its pixels
+pub fn check_image(path: &Path, full_decode: bool, settings: &ImageLoadingSettings) -> Result<(), String> {
+    if is_svg(path) {
+        if full_decode {
+            return decode_svg(path, settings, false).map(|_| ());
+        }
+        let text = read_svg_source(path)?;
+        return resvg::usvg::Tree::from_str(&text, &resvg::usvg::Options::default())
+            .map(|_| ())
+            .map_err(|e| format!("Failed to parse SVG: {}", e));
+    }
+    // Registered decoders can't read just the header, so their files are always decoded
+    if let Some(decoded) = format_decoders::decode_file(path) {
+        return decoded.map(|_| ());
+    }
+    let reader = ImageReader::open(path)
+        .map_err(|e| format!("Failed to open image: {}", e))?
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to open image: {}", e))?;
+    if full_decode {
+        reader.decode().map(|_| ()).map_err(|e| format!("Failed to decode image: {}", e))
+    } else {
+        reader.into_dimensions().map(|_| ()).map_err(|e| format!("Failed to read header: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_truncated_image_fails_only_the_full_decode() {
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 64, |x, y| image::Rgb([x as u8 * 4, y as u8 * 4, 0])))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let dir = TempDir::new("triage");
+        let truncated = dir.join("truncated.png");
+        std::fs::write(&truncated, &png[..png.len() / 2]).unwrap();
+        let garbage = dir.join("garbage.jpg");
+        std::fs::write(&garbage, "not an image").unwrap();
+        let settings = ImageLoadingSettings::default();
+
+        let header = check_image(&truncated, false, &settings);
+        let full = check_image(&truncated, true, &settings);
+        let garbage_header = check_image(&garbage, false, &settings);
+        assert_eq!(header, Ok(()));
+        assert!(full.is_err());
+        assert!(garbage_header.is_err());
+    }
+}
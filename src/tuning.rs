@@ -0,0 +1,186 @@
+//! Settings suggested from the benchmark's performance category
+//!
+//! After the first benchmark, the viewer offers values suited to the machine:
+//! slower systems get smaller cloud thumbnails, less read-ahead, fewer decode
+//! threads, lower large-image limits and no UI animations. Accepted values are
+//! kept in the preferences file and applied at every launch.
+//!
+//! Once the benchmark has decoded images from the user's folder, the
+//! large-image limit is taken from the largest of them instead of the table.
+
+use crate::benchmark::SystemPerformanceCategory;
+use crate::preferences::Preferences;
+use crate::settings::{ImageLoadingSettings, MAX_LARGE_IMAGE_DIMENSION, MIN_LARGE_IMAGE_DIMENSION};
+
+/// Set once the suggestion has been accepted or declined, so it is only offered once
+const OFFERED_KEY: &str = "tuning_offered";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TunedDefaults {
+    pub thumbnail_size: u32,
+    pub read_ahead: usize,
+    /// None uses every core but one
+    pub max_decode_threads: Option<usize>,
+    pub large_image_max_dimension: u32,
+    /// None keeps the limit calculated from available memory
+    pub max_file_size_mb: Option<u32>,
+    pub animations: bool,
+}
+
+/// A setting the suggestion would change, for the confirmation dialog
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuningChange {
+    pub setting: &'static str,
+    pub current: String,
+    pub suggested: String,
+}
+
+/// Large-image limit for a machine that decoded images of `megapixels`: the
+/// side of a square that size, rounded up to a multiple of 1024
+pub fn dimension_for_megapixels(megapixels: f64) -> Option<u32> {
+    if megapixels <= 0.0 {
+        return None;
+    }
+    let side = (megapixels * 1_000_000.0).sqrt().ceil() as u32;
+    Some(side.div_ceil(1024).saturating_mul(1024).clamp(MIN_LARGE_IMAGE_DIMENSION, MAX_LARGE_IMAGE_DIMENSION))
+}
+
+fn threads_label(threads: Option<usize>) -> String {
+    threads.map_or("Automatic".to_string(), |n| n.to_string())
+}
+
+fn file_size_label(size: Option<u32>) -> String {
+    size.map_or("Automatic".to_string(), |mb| format!("{} MB", mb))
+}
+
+impl TunedDefaults {
+    pub fn for_category(category: SystemPerformanceCategory) -> Self {
+        use SystemPerformanceCategory::*;
+        let (thumbnail_size, read_ahead, max_decode_threads, large_image_max_dimension, max_file_size_mb, animations) = match category {
+            LowPower => (96, 1, Some(1), 4096, Some(25), false),
+            Moderate => (176, 2, Some(2), 6144, Some(50), true),
+            Good => (800, 3, None, 8192, None, true),
+            High => (800, 5, None, 12288, None, true),
+            Excellent => (800, 8, None, 16384, None, true),
+        };
+        Self { thumbnail_size, read_ahead, max_decode_threads, large_image_max_dimension, max_file_size_mb, animations }
+    }
+
+    /// Suggestion for `category`, with the large-image limit derived from the
+    /// benchmark's `max_successful_megapixels` when it decoded anything
+    pub fn for_benchmark(category: SystemPerformanceCategory, max_successful_megapixels: f64) -> Self {
+        let mut tuned = Self::for_category(category);
+        if let Some(dimension) = dimension_for_megapixels(max_successful_megapixels) {
+            tuned.large_image_max_dimension = dimension;
+        }
+        tuned
+    }
+
+    /// Settings that differ from the suggestion
+    pub fn changes(&self, settings: &ImageLoadingSettings) -> Vec<TuningChange> {
+        let mut changes = Vec::new();
+        let mut compare = |setting, current: String, suggested: String| {
+            if current != suggested {
+                changes.push(TuningChange { setting, current, suggested });
+            }
+        };
+        compare("Cloud thumbnail size", format!("{} px", settings.thumbnail_size), format!("{} px", self.thumbnail_size));
+        compare("Slideshow read-ahead", settings.slideshow_read_ahead.to_string(), self.read_ahead.to_string());
+        compare("Decode threads", threads_label(settings.max_decode_threads), threads_label(self.max_decode_threads));
+        compare("Large image limit", format!("{} px", settings.large_image_max_dimension), format!("{} px", self.large_image_max_dimension));
+        compare("Max file size", file_size_label(settings.max_file_size_mb), file_size_label(self.max_file_size_mb));
+        compare("Animations", settings.animations.to_string(), self.animations.to_string());
+        changes
+    }
+
+    pub fn apply(&self, settings: &mut ImageLoadingSettings) {
+        settings.thumbnail_size = self.thumbnail_size;
+        settings.slideshow_read_ahead = self.read_ahead;
+        settings.max_decode_threads = self.max_decode_threads;
+        settings.large_image_max_dimension = self.large_image_max_dimension;
+        settings.max_file_size_mb = self.max_file_size_mb;
+        settings.animations = self.animations;
+    }
+
+    pub fn save_to(&self, preferences: &mut Preferences) {
+        preferences.set("thumbnail_size", self.thumbnail_size);
+        preferences.set("slideshow_read_ahead", self.read_ahead);
+        match self.max_decode_threads {
+            Some(threads) => preferences.set("max_decode_threads", threads),
+            None => preferences.remove("max_decode_threads"),
+        }
+        preferences.set("large_image_max_dimension", self.large_image_max_dimension);
+        match self.max_file_size_mb {
+            Some(mb) => preferences.set("max_file_size_mb", mb),
+            None => preferences.remove("max_file_size_mb"),
+        }
+        preferences.set("animations", self.animations);
+    }
+}
+
+/// Apply tuned values saved by an earlier session
+pub fn load_into(preferences: &Preferences, settings: &mut ImageLoadingSettings) {
+    if let Some(size) = preferences.get("thumbnail_size") {
+        settings.thumbnail_size = size;
+    }
+    if let Some(read_ahead) = preferences.get("slideshow_read_ahead") {
+        settings.slideshow_read_ahead = read_ahead;
+    }
+    if let Some(threads) = preferences.get::<usize>("max_decode_threads") {
+        settings.max_decode_threads = Some(threads.max(1));
+    }
+    if let Some(dimension) = preferences.get("large_image_max_dimension") {
+        settings.large_image_max_dimension = dimension;
+    }
+    if let Some(mb) = preferences.get("max_file_size_mb") {
+        settings.max_file_size_mb = Some(mb);
+    }
+    if let Some(animations) = preferences.get("animations") {
+        settings.animations = animations;
+    }
+}
+
+pub fn was_offered(preferences: &Preferences) -> bool {
+    preferences.get(OFFERED_KEY).unwrap_or(false)
+}
+
+pub fn mark_offered(preferences: &mut Preferences) {
+    preferences.set(OFFERED_KEY, true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggestion_round_trip() {
+        let tuned = TunedDefaults::for_category(SystemPerformanceCategory::LowPower);
+        let mut settings = ImageLoadingSettings::default();
+        let changes = tuned.changes(&settings);
+        assert!(changes.iter().any(|c| c.setting == "Decode threads" && c.current == "Automatic" && c.suggested == "1"));
+        assert!(changes.iter().any(|c| c.setting == "Animations" && c.suggested == "false"));
+
+        let mut preferences = Preferences::default();
+        tuned.save_to(&mut preferences);
+        mark_offered(&mut preferences);
+        let preferences = Preferences::parse(&preferences.to_text());
+        load_into(&preferences, &mut settings);
+        assert!(tuned.changes(&settings).is_empty());
+        assert!(was_offered(&preferences));
+
+        // The defaults already suit a fast machine
+        let good = TunedDefaults::for_category(SystemPerformanceCategory::Good);
+        assert!(good.changes(&ImageLoadingSettings::default()).is_empty());
+    }
+
+    #[test]
+    fn test_large_image_limit_follows_benchmark() {
+        assert_eq!(dimension_for_megapixels(0.0), None);
+        assert_eq!(dimension_for_megapixels(1.0), Some(MIN_LARGE_IMAGE_DIMENSION));
+        assert_eq!(dimension_for_megapixels(24.0), Some(5120));
+        assert_eq!(dimension_for_megapixels(64.0), Some(8192));
+        let tuned = TunedDefaults::for_benchmark(SystemPerformanceCategory::Excellent, 24.0);
+        assert_eq!(tuned.large_image_max_dimension, 5120);
+        assert_eq!(TunedDefaults::for_benchmark(SystemPerformanceCategory::High, 0.0), TunedDefaults::for_category(SystemPerformanceCategory::High));
+    }
+}
@@ -0,0 +1,61 @@
+//! Poster frames for video files
+//!
+//! Videos are listed alongside the images so mixed media folders have no
+//! gaps. With the `ffmpeg` feature, the first keyframe is grabbed by running
+//! the `ffmpeg` executable and shown like an image; without it, or when
+//! ffmpeg isn't installed, the viewer shows a placeholder that opens the
+//! video in the system player.
+
+use std::path::Path;
+
+/// Extensions listed as videos
+pub const VIDEO_EXTENSIONS: [&str; 9] = ["mp4", "m4v", "mov", "mkv", "webm", "avi", "wmv", "mpg", "mpeg"];
+
+pub fn is_video(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| VIDEO_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+}
+
+/// Arguments that make ffmpeg write the first keyframe of `path` as a PNG to stdout
+#[cfg(any(feature = "ffmpeg", test))]
+fn poster_frame_args(path: &Path) -> Vec<std::ffi::OsString> {
+    let mut args: Vec<std::ffi::OsString> = ["-v", "error", "-nostdin", "-skip_frame", "nokey", "-i"].map(Into::into).to_vec();
+    args.push(path.as_os_str().to_owned());
+    args.extend(["-frames:v", "1", "-f", "image2pipe", "-vcodec", "png", "-"].map(Into::into));
+    args
+}
+
+/// The first keyframe of a video
+#[cfg(feature = "ffmpeg")]
+pub fn poster_frame(path: &Path) -> Result<image::DynamicImage, String> {
+    let output = std::process::Command::new("ffmpeg")
+        .args(poster_frame_args(path))
+        .output()
+        .map_err(|e| format!("Failed to run ffmpeg (is it installed?): {}", e))?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(format!("ffmpeg found no frame: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    image::load_from_memory_with_format(&output.stdout, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to decode the poster frame: {}", e))
+}
+
+#[cfg(not(feature = "ffmpeg"))]
+pub fn poster_frame(_path: &Path) -> Result<image::DynamicImage, String> {
+    Err("Video previews need a build with the ffmpeg feature; open it in the external player instead".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_video_detection_and_ffmpeg_args() {
+        assert!(is_video(Path::new("clips/holiday.MOV")));
+        assert!(!is_video(Path::new("clips/holiday.jpg")));
+
+        let args: Vec<String> = poster_frame_args(Path::new("a b.mp4")).iter().map(|arg| arg.to_string_lossy().into_owned()).collect();
+        // Only keyframes are decoded, and only the first is kept
+        assert_eq!(args[..6], ["-v", "error", "-nostdin", "-skip_frame", "nokey", "-i"]);
+        assert_eq!(args[6], "a b.mp4");
+        assert_eq!(args.last().map(String::as_str), Some("-"));
+    }
+}
@@ -0,0 +1,205 @@
+//! Export of the image view as it appears on screen
+//!
+//! The view is composited on the CPU from the decoded image rather than read
+//! back from the GPU, so it can be rendered at a multiple of the screen's
+//! resolution. The zoom, pan, backdrop and region selection are reproduced.
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{Color32, ColorImage, Rect};
+use image::RgbaImage;
+
+use crate::color_stats::PixelRect;
+use crate::duplicates::target_in;
+
+/// Side of a checkerboard square, in points, as drawn on screen
+const CHECKER_SIZE: f32 = 8.0;
+/// Width of the selection outline, in points
+const SELECTION_STROKE: f32 = 1.5;
+/// Most samples per axis when averaging a shrunken image
+const MAX_SAMPLES: usize = 8;
+
+/// Where the image was drawn in the last frame, and what surrounded it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewSnapshot {
+    /// The image area of the window, in points
+    pub area: Rect,
+    /// Where the whole image was drawn, in points; may extend past `area`
+    pub image_rect: Rect,
+    pub background: Color32,
+    /// A checkerboard was drawn under the image
+    pub checkerboard: bool,
+    pub selection: Option<PixelRect>,
+    pub pixels_per_point: f32,
+}
+
+impl ViewSnapshot {
+    /// Output size in pixels at `factor` times the screen's resolution
+    pub fn output_size(&self, factor: f32) -> (u32, u32) {
+        let scale = self.pixels_per_point * factor;
+        ((self.area.width() * scale).round().max(1.0) as u32, (self.area.height() * scale).round().max(1.0) as u32)
+    }
+}
+
+/// Composite the view of `image` at `factor` times the screen's resolution
+pub fn render_view(image: &ColorImage, view: &ViewSnapshot, factor: f32) -> RgbaImage {
+    let (width, height) = view.output_size(factor);
+    let points_per_pixel = 1.0 / (view.pixels_per_point * factor);
+    let [image_width, image_height] = image.size;
+    let image_pixels_per_point = image_width as f32 / view.image_rect.width().max(f32::EPSILON);
+    let samples = ((image_pixels_per_point * points_per_pixel).ceil() as usize).clamp(1, MAX_SAMPLES);
+
+    let selection_rect = view.selection.map(|selection| {
+        let to_point = |x: usize, y: usize| {
+            view.image_rect.min + eframe::egui::vec2(
+                x as f32 / image_width as f32 * view.image_rect.width(),
+                y as f32 / image_height as f32 * view.image_rect.height(),
+            )
+        };
+        Rect::from_min_max(to_point(selection.min_x, selection.min_y), to_point(selection.max_x, selection.max_y))
+    });
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let point = view.area.min + eframe::egui::vec2((x as f32 + 0.5) * points_per_pixel, (y as f32 + 0.5) * points_per_pixel);
+        let mut color = view.background;
+        if view.image_rect.contains(point) {
+            if view.checkerboard {
+                let offset = point - view.image_rect.min;
+                let dark = ((offset.x / CHECKER_SIZE) as usize + (offset.y / CHECKER_SIZE) as usize) % 2 == 1;
+                color = Color32::from_gray(if dark { 153 } else { 204 });
+            }
+            // Average a grid of samples across the output pixel's footprint
+            let (mut sum, step) = ([0.0_f32; 4], points_per_pixel / samples as f32);
+            for sy in 0..samples {
+                for sx in 0..samples {
+                    let sample = point + eframe::egui::vec2(
+                        (sx as f32 + 0.5) * step - points_per_pixel / 2.0,
+                        (sy as f32 + 0.5) * step - points_per_pixel / 2.0,
+                    );
+                    let u = (sample.x - view.image_rect.min.x) / view.image_rect.width() * image_width as f32;
+                    let v = (sample.y - view.image_rect.min.y) / view.image_rect.height() * image_height as f32;
+                    let texel = bilinear(image, u, v);
+                    for (total, channel) in sum.iter_mut().zip(texel) {
+                        *total += channel;
+                    }
+                }
+            }
+            let count = (samples * samples) as f32;
+            let [r, g, b, a] = sum.map(|total| total / count);
+            color = over([r, g, b, a], color);
+        }
+        if let Some(rect) = selection_rect && on_outline(rect, point, SELECTION_STROKE) {
+            color = Color32::YELLOW;
+        }
+        image::Rgba(color.to_srgba_unmultiplied())
+    })
+}
+
+/// Premultiplied color at fractional pixel coordinates, clamped to the edges;
+/// transparent for an image without pixels
+fn bilinear(image: &ColorImage, u: f32, v: f32) -> [f32; 4] {
+    let [width, height] = image.size;
+    if width == 0 || height == 0 {
+        return [0.0; 4];
+    }
+    let (x, y) = ((u - 0.5).clamp(0.0, (width - 1) as f32), (v - 0.5).clamp(0.0, (height - 1) as f32));
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+    let texel = |x: usize, y: usize| image.pixels[y * width + x].to_array().map(f32::from);
+    let mut out = [0.0; 4];
+    for (i, channel) in out.iter_mut().enumerate() {
+        let top = texel(x0, y0)[i] * (1.0 - fx) + texel(x1, y0)[i] * fx;
+        let bottom = texel(x0, y1)[i] * (1.0 - fx) + texel(x1, y1)[i] * fx;
+        *channel = top * (1.0 - fy) + bottom * fy;
+    }
+    out
+}
+
+/// Premultiplied `source` drawn over `destination`
+fn over(source: [f32; 4], destination: Color32) -> Color32 {
+    let coverage = 1.0 - source[3] / 255.0;
+    let [r, g, b, a] = destination.to_array().map(|channel| channel as f32 * coverage);
+    let channel = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+    Color32::from_rgba_premultiplied(channel(source[0] + r), channel(source[1] + g), channel(source[2] + b), channel(source[3] + a))
+}
+
+/// Whether `point` is within `width` outside the edge of `rect`, like egui's outside stroke
+fn on_outline(rect: Rect, point: eframe::egui::Pos2, width: f32) -> bool {
+    rect.expand(width).contains(point) && !rect.contains(point)
+}
+
+/// Render the view and save it as a PNG to `output`, or to a numbered name
+/// next to it if that file exists; returns where it went and its size
+pub fn export_view(image: &ColorImage, view: &ViewSnapshot, factor: f32, output: &Path) -> Result<(PathBuf, (u32, u32)), String> {
+    let rendered = render_view(image, view, factor);
+    let size = rendered.dimensions();
+    let output = target_in(output, output.parent().unwrap_or(Path::new("")), Path::exists)?;
+    rendered.save_with_format(&output, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to save {}: {}", output.display(), e))?;
+    Ok((output, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use eframe::egui::pos2;
+
+    fn view(image_rect: Rect) -> ViewSnapshot {
+        ViewSnapshot {
+            area: Rect::from_min_max(pos2(100.0, 50.0), pos2(110.0, 54.0)),
+            image_rect,
+            background: Color32::from_rgb(10, 20, 30),
+            checkerboard: false,
+            selection: None,
+            pixels_per_point: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_render_places_image_in_view() {
+        let mut image = ColorImage::new([2, 1], Color32::RED);
+        image.pixels[1] = Color32::BLUE;
+        // The image covers the left half of the view, each pixel 2.5 points wide
+        let view = view(Rect::from_min_max(pos2(100.0, 50.0), pos2(105.0, 54.0)));
+
+        let rendered = render_view(&image, &view, 1.0);
+        assert_eq!(rendered.dimensions(), (10, 4));
+        assert_eq!(rendered.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(rendered.get_pixel(4, 3).0, [0, 0, 255, 255]);
+        assert_eq!(rendered.get_pixel(7, 1).0, [10, 20, 30, 255]);
+
+        assert_eq!(render_view(&image, &view, 2.0).dimensions(), (20, 8));
+
+        let empty = ColorImage::new([0, 0], Color32::TRANSPARENT);
+        assert_eq!(render_view(&empty, &view, 1.0).get_pixel(0, 0).0, [10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn test_transparency_and_selection() {
+        let image = ColorImage::new([4, 4], Color32::TRANSPARENT);
+        let mut view = view(Rect::from_min_max(pos2(100.0, 50.0), pos2(104.0, 54.0)));
+        view.checkerboard = true;
+        view.selection = Some(PixelRect { min_x: 0, min_y: 0, max_x: 1, max_y: 1 });
+
+        let rendered = render_view(&image, &view, 1.0);
+        assert_eq!(rendered.get_pixel(3, 3).0, [204, 204, 204, 255]);
+        // Just outside the selection's right edge
+        assert_eq!(rendered.get_pixel(1, 0).0, [255, 255, 0, 255]);
+        assert_eq!(rendered.get_pixel(8, 0).0, [10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn test_export_keeps_existing_files() {
+        let dir = TempDir::new("view_export");
+        let output = dir.join("view.png");
+        let image = ColorImage::new([2, 2], Color32::RED);
+        let view = view(Rect::from_min_max(pos2(100.0, 50.0), pos2(104.0, 54.0)));
+        let first = export_view(&image, &view, 1.0, &output);
+        let second = export_view(&image, &view, 1.0, &output);
+
+        assert_eq!(first, Ok((output.clone(), (10, 4))));
+        assert_eq!(second, Ok((dir.join("view (2).png"), (10, 4))));
+    }
+}
@@ -0,0 +1,54 @@
+//! Per-image view state remembered while browsing
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::fit_mode::OpeningZoom;
+
+/// View options that apply to a single image only
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImageViewState {
+    /// Show this SVG in its original colors even when recoloring is enabled
+    pub disable_recolor: bool,
+    /// Size to show this image at instead of the default for its type
+    pub zoom: Option<OpeningZoom>,
+}
+
+#[derive(Debug, Default)]
+pub struct ViewStates {
+    states: HashMap<PathBuf, ImageViewState>,
+}
+
+impl ViewStates {
+    /// State for `path`, or the defaults if nothing was changed for it
+    pub fn get(&self, path: &Path) -> ImageViewState {
+        self.states.get(path).cloned().unwrap_or_default()
+    }
+
+    pub fn get_mut(&mut self, path: &Path) -> &mut ImageViewState {
+        self.states.entry(path.to_path_buf()).or_default()
+    }
+
+    /// Whether SVG recoloring applies to `path` given the global setting
+    pub fn recolor_active(&self, path: &Path, recolor_enabled: bool) -> bool {
+        recolor_enabled && !self.get(path).disable_recolor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recolor_override_is_per_file() {
+        let mut states = ViewStates::default();
+        let icon = Path::new("icon.svg");
+        let other = Path::new("other.svg");
+        assert!(states.recolor_active(icon, true));
+
+        states.get_mut(icon).disable_recolor = true;
+        assert!(!states.recolor_active(icon, true));
+        assert!(states.recolor_active(other, true));
+        assert!(!states.recolor_active(other, false));
+    }
+}
@@ -0,0 +1,414 @@
+//! Star ratings, color labels and keywords in XMP sidecars
+//!
+//! They live in `photo.xmp` next to the image, the sidecar Lightroom, Bridge
+//! and darktable also read (darktable's `photo.jpg.xmp` is used when it is
+//! the one present). Writing only touches `xmp:Rating`, `xmp:Label` and
+//! `dc:subject`, so anything else another tool stored in the sidecar is kept.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use eframe::egui::Color32;
+use regex::Regex;
+
+use crate::file_locality::FileInfo;
+use crate::metadata_providers::xmp_property;
+use crate::sidecars;
+
+/// Highest star rating
+pub const MAX_RATING: i8 = 5;
+/// The rating Lightroom and Bridge give rejected images
+pub const REJECTED: i8 = -1;
+/// Sidecars larger than this aren't read
+const MAX_SIDECAR_BYTES: u64 = 1024 * 1024;
+const XMP_NAMESPACE: &str = "http://ns.adobe.com/xap/1.0/";
+const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+
+/// Color labels, named as Lightroom and Bridge write them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorLabel {
+    Red,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl ColorLabel {
+    pub const ALL: [ColorLabel; 5] = [ColorLabel::Red, ColorLabel::Yellow, ColorLabel::Green, ColorLabel::Blue, ColorLabel::Purple];
+
+    /// Value of `xmp:Label`
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorLabel::Red => "Red",
+            ColorLabel::Yellow => "Yellow",
+            ColorLabel::Green => "Green",
+            ColorLabel::Blue => "Blue",
+            ColorLabel::Purple => "Purple",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|label| label.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn color(&self) -> Color32 {
+        match self {
+            ColorLabel::Red => Color32::from_rgb(220, 60, 60),
+            ColorLabel::Yellow => Color32::from_rgb(230, 200, 40),
+            ColorLabel::Green => Color32::from_rgb(70, 180, 80),
+            ColorLabel::Blue => Color32::from_rgb(60, 120, 220),
+            ColorLabel::Purple => Color32::from_rgb(160, 80, 200),
+        }
+    }
+}
+
+/// Rating and label of one image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ImageRating {
+    /// 0 (unrated) to [`MAX_RATING`] stars, or [`REJECTED`]
+    pub stars: i8,
+    pub label: Option<ColorLabel>,
+}
+
+impl ImageRating {
+    pub fn is_empty(&self) -> bool {
+        self.stars == 0 && self.label.is_none()
+    }
+
+    /// Rating and label found in an XMP packet
+    pub fn from_xmp(packet: &str) -> Self {
+        Self {
+            stars: xmp_property(packet, "xmp:Rating")
+                .and_then(|value| value.trim().parse::<i8>().ok())
+                .map_or(0, |stars| stars.clamp(REJECTED, MAX_RATING)),
+            label: xmp_property(packet, "xmp:Label").and_then(|name| ColorLabel::from_name(&name)),
+        }
+    }
+}
+
+/// Filled and empty stars, e.g. "★★★☆☆"
+pub fn stars_text(stars: i8) -> String {
+    if stars == REJECTED {
+        return "Rejected".to_string();
+    }
+    let stars = stars.clamp(0, MAX_RATING) as usize;
+    format!("{}{}", "★".repeat(stars), "☆".repeat(MAX_RATING as usize - stars))
+}
+
+/// Keywords listed in an XMP packet's `dc:subject`
+pub fn keywords_from_xmp(packet: &str) -> Vec<String> {
+    let subject = Regex::new(r"(?s)<dc:subject(?:\s[^>]*)?>(.*?)</dc:subject>").expect("valid regex");
+    let item = Regex::new(r"(?s)<rdf:li[^>]*>(.*?)</rdf:li>").expect("valid regex");
+    let Some(body) = subject.captures(packet).and_then(|captures| captures.get(1)) else {
+        return Vec::new();
+    };
+    let mut keywords: Vec<String> = Vec::new();
+    for keyword in item.captures_iter(body.as_str()).filter_map(|captures| normalize_keyword(&unescape_xml(&captures[1]))) {
+        if !keywords.iter().any(|known| known.eq_ignore_ascii_case(&keyword)) {
+            keywords.push(keyword);
+        }
+    }
+    keywords
+}
+
+/// A keyword with its whitespace tidied, or `None` if it is blank
+pub fn normalize_keyword(keyword: &str) -> Option<String> {
+    let keyword = keyword.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!keyword.is_empty()).then_some(keyword)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+/// Sidecar names checked for an image, in order of preference
+pub fn sidecar_names(image: &Path) -> [String; 2] {
+    sidecars::sidecar_names(image, "xmp")
+}
+
+/// The image's existing sidecar, or where a new one goes
+pub fn sidecar_path(image: &Path) -> PathBuf {
+    let dir = image.parent().unwrap_or(Path::new(""));
+    let candidates = sidecar_names(image).map(|name| dir.join(name));
+    candidates.iter().find(|path| path.is_file()).unwrap_or(&candidates[0]).clone()
+}
+
+fn read_sidecar(path: &Path) -> Option<String> {
+    let metadata = std::fs::metadata(path).ok()?;
+    // Cloud placeholders are never downloaded just to be read
+    if metadata.len() > MAX_SIDECAR_BYTES || FileInfo::new(path.to_path_buf()).will_trigger_download() {
+        return None;
+    }
+    std::fs::read_to_string(path).ok()
+}
+
+/// Set an XMP property as an attribute of the first `rdf:Description`, or remove it
+fn set_property(packet: &str, property: &str, value: Option<&str>) -> String {
+    let name = regex::escape(property);
+    let attribute = Regex::new(&format!(r#"\s{}\s*=\s*(?:"[^"]*"|'[^']*')"#, name)).expect("valid regex");
+    let element = Regex::new(&format!(r"(?s)\s*<{0}(?:\s[^>]*)?>.*?</{0}>", name)).expect("valid regex");
+    let mut packet = element.replace_all(packet, "").into_owned();
+    let replacement = value.map(|value| format!(r#" {}="{}""#, property, value)).unwrap_or_default();
+    if attribute.is_match(&packet) {
+        return attribute.replace(&packet, regex::NoExpand(&replacement)).into_owned();
+    }
+    if let Some(start) = packet.find("<rdf:Description") {
+        let end = start + "<rdf:Description".len();
+        packet.insert_str(end, &replacement);
+    }
+    packet
+}
+
+/// `packet`, or a new empty one, with the namespace of `prefix` declared
+fn base_packet(packet: Option<&str>, prefix: &str, namespace: &str) -> String {
+    let packet = match packet {
+        Some(packet) if packet.contains("<rdf:Description") => packet.to_string(),
+        _ => "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n  \
+              <rdf:Description rdf:about=\"\">\n  </rdf:Description>\n </rdf:RDF>\n</x:xmpmeta>\n"
+            .to_string(),
+    };
+    let declaration = format!("xmlns:{}", prefix);
+    if packet.contains(&format!("{}=", declaration)) {
+        packet
+    } else {
+        set_property(&packet, &declaration, Some(namespace))
+    }
+}
+
+/// `packet` with the rating and label replaced; a new packet if there is none
+pub fn update_packet(packet: Option<&str>, rating: &ImageRating) -> String {
+    let packet = base_packet(packet, "xmp", XMP_NAMESPACE);
+    let stars = (rating.stars != 0).then(|| rating.stars.clamp(REJECTED, MAX_RATING).to_string());
+    let packet = set_property(&packet, "xmp:Rating", stars.as_deref());
+    set_property(&packet, "xmp:Label", rating.label.map(|label| label.name()))
+}
+
+/// `packet` with `dc:subject` listing `keywords`; a new packet if there is none
+pub fn update_keywords(packet: Option<&str>, keywords: &[String]) -> String {
+    let mut packet = set_property(&base_packet(packet, "dc", DC_NAMESPACE), "dc:subject", None);
+    if keywords.is_empty() {
+        return packet;
+    }
+    let items: String = keywords.iter().map(|keyword| format!("     <rdf:li>{}</rdf:li>\n", escape_xml(keyword))).collect();
+    let subject = format!("\n   <dc:subject>\n    <rdf:Bag>\n{}    </rdf:Bag>\n   </dc:subject>", items);
+    let Some(start) = packet.find("<rdf:Description") else {
+        return packet;
+    };
+    let Some(end) = packet[start..].find('>').map(|offset| start + offset) else {
+        return packet;
+    };
+    if packet[..end].ends_with('/') {
+        // Open up a self-closing description to hold the element
+        packet.replace_range(end - 1..=end, &format!(">{}\n  </rdf:Description>", subject));
+    } else {
+        packet.insert_str(end + 1, &subject);
+    }
+    packet
+}
+
+/// Rewrite the image's sidecar with `update`. With `nothing_to_save`, no
+/// sidecar is created where there is none.
+fn save_sidecar(image: &Path, nothing_to_save: bool, update: impl FnOnce(Option<&str>) -> String) -> Result<(), String> {
+    let path = sidecar_path(image);
+    let existing = if path.is_file() {
+        Some(std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?)
+    } else {
+        None
+    };
+    if existing.is_none() && nothing_to_save {
+        return Ok(());
+    }
+    // Written next to the sidecar and renamed over it, so a crash or a full
+    // disk never leaves another tool's metadata half written
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    std::fs::write(&temp, update(existing.as_deref()))
+        .and_then(|_| std::fs::rename(&temp, &path))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&temp);
+            format!("Failed to write {}: {}", path.display(), e)
+        })
+}
+
+/// Rating and keywords from the image's sidecar, empty if it has none
+pub fn read_sidecar_data(image: &Path) -> (ImageRating, Vec<String>) {
+    let packet = read_sidecar(&sidecar_path(image)).unwrap_or_default();
+    (ImageRating::from_xmp(&packet), keywords_from_xmp(&packet))
+}
+
+/// Save the rating in the image's sidecar
+pub fn write_rating(image: &Path, rating: &ImageRating) -> Result<(), String> {
+    save_sidecar(image, rating.is_empty(), |packet| update_packet(packet, rating))
+}
+
+/// Save the keywords in the image's sidecar
+pub fn write_keywords(image: &Path, keywords: &[String]) -> Result<(), String> {
+    save_sidecar(image, keywords.is_empty(), |packet| update_keywords(packet, keywords))
+}
+
+/// Ratings and keywords of a folder's images, for the image list
+#[derive(Debug, Clone, Default)]
+pub struct SidecarIndex {
+    ratings: HashMap<PathBuf, ImageRating>,
+    keywords: HashMap<PathBuf, Vec<String>>,
+}
+
+impl SidecarIndex {
+    /// Read the sidecars in `dir` that belong to `images`
+    pub fn scan(dir: &Path, images: &[FileInfo]) -> Self {
+        let mut index = Self::default();
+        for (image, packet) in sidecars::scan(dir, images, &["xmp"], read_sidecar) {
+            index.set_rating(&image, ImageRating::from_xmp(&packet));
+            index.set_keywords(&image, keywords_from_xmp(&packet));
+        }
+        index
+    }
+
+    /// Re-read one image's sidecar, which another tool may have changed
+    pub fn refresh(&mut self, image: &Path) {
+        let (rating, keywords) = read_sidecar_data(image);
+        self.set_rating(image, rating);
+        self.set_keywords(image, keywords);
+    }
+
+    pub fn rating(&self, image: &Path) -> ImageRating {
+        self.ratings.get(image).copied().unwrap_or_default()
+    }
+
+    pub fn set_rating(&mut self, image: &Path, rating: ImageRating) {
+        if rating.is_empty() {
+            self.ratings.remove(image);
+        } else {
+            self.ratings.insert(image.to_path_buf(), rating);
+        }
+    }
+
+    pub fn keywords(&self, image: &Path) -> &[String] {
+        self.keywords.get(image).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn set_keywords(&mut self, image: &Path, keywords: Vec<String>) {
+        if keywords.is_empty() {
+            self.keywords.remove(image);
+        } else {
+            self.keywords.insert(image.to_path_buf(), keywords);
+        }
+    }
+
+    /// Whether the image has every one of `tags`, ignoring case
+    pub fn has_keywords(&self, image: &Path, tags: &[String]) -> bool {
+        let keywords = self.keywords(image);
+        tags.iter().all(|tag| keywords.iter().any(|keyword| keyword.eq_ignore_ascii_case(tag)))
+    }
+
+    /// Every keyword in the folder with its number of images, most used first
+    pub fn known_keywords(&self) -> Vec<(String, usize)> {
+        let mut counts: HashMap<String, (String, usize)> = HashMap::new();
+        for keyword in self.keywords.values().flatten() {
+            counts.entry(keyword.to_lowercase()).or_insert_with(|| (keyword.clone(), 0)).1 += 1;
+        }
+        let mut keywords: Vec<(String, usize)> = counts.into_values().collect();
+        keywords.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.to_lowercase().cmp(&b.0.to_lowercase())));
+        keywords
+    }
+
+    /// Known keywords containing `text`, those starting with it first, skipping `exclude`
+    pub fn suggest(&self, text: &str, exclude: &[String], limit: usize) -> Vec<String> {
+        let text = text.trim().to_lowercase();
+        let mut matches: Vec<(bool, String)> = self
+            .known_keywords()
+            .into_iter()
+            .map(|(keyword, _)| keyword)
+            .filter(|keyword| !exclude.iter().any(|excluded| excluded.eq_ignore_ascii_case(keyword)))
+            .filter_map(|keyword| {
+                let lower = keyword.to_lowercase();
+                lower.contains(&text).then(|| (!lower.starts_with(&text), keyword))
+            })
+            .collect();
+        // Stable, so usage order is kept within each group
+        matches.sort_by_key(|(later, _)| *later);
+        matches.into_iter().take(limit).map(|(_, keyword)| keyword).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_update_packet_keeps_other_properties() {
+        let rating = ImageRating { stars: 4, label: Some(ColorLabel::Green) };
+        let packet = update_packet(None, &rating);
+        assert_eq!(ImageRating::from_xmp(&packet), rating);
+
+        // Element-form values are replaced, unrelated properties kept
+        let lightroom = r#"<x:xmpmeta><rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:Label="Red" crs:Exposure2012="+0.50"><xmp:Rating>2</xmp:Rating></rdf:Description></x:xmpmeta>"#;
+        let updated = update_packet(Some(lightroom), &ImageRating { stars: 5, label: None });
+        assert_eq!(ImageRating::from_xmp(&updated), ImageRating { stars: 5, label: None });
+        assert!(updated.contains(r#"crs:Exposure2012="+0.50""#));
+        assert!(!updated.contains("<xmp:Rating>"));
+
+        // A reject survives changing the label
+        let rejected = r#"<rdf:Description xmp:Rating="-1"/>"#;
+        assert_eq!(ImageRating::from_xmp(rejected).stars, REJECTED);
+        let labelled = update_packet(Some(rejected), &ImageRating { label: Some(ColorLabel::Blue), ..ImageRating::from_xmp(rejected) });
+        assert_eq!(ImageRating::from_xmp(&labelled), ImageRating { stars: REJECTED, label: Some(ColorLabel::Blue) });
+        assert_eq!(stars_text(REJECTED), "Rejected");
+
+        // Single-quoted attributes are valid XML and replaced, not duplicated
+        let quoted = update_packet(Some("<rdf:Description rdf:about='' xmp:Rating='1' xmp:Label='Red'/>"), &rating);
+        assert_eq!(ImageRating::from_xmp(&quoted), rating);
+        assert_eq!(quoted.matches("xmp:Rating").count(), 1);
+        assert_eq!(quoted.matches("xmp:Label").count(), 1);
+        assert_eq!(stars_text(2), "★★☆☆☆");
+    }
+
+    #[test]
+    fn test_write_and_scan() {
+        let dir = TempDir::new("xmp_sidecar");
+        let image = dir.join("photo.jpg");
+        std::fs::write(&image, b"").unwrap();
+
+        // Clearing an unrated image leaves no sidecar behind
+        write_rating(&image, &ImageRating::default()).unwrap();
+        assert!(!dir.join("photo.xmp").exists());
+
+        let rating = ImageRating { stars: 3, label: Some(ColorLabel::Blue) };
+        write_rating(&image, &rating).unwrap();
+        let keywords = vec!["harbour".to_string(), "R&D".to_string()];
+        write_keywords(&image, &keywords).unwrap();
+        let leftovers = std::fs::read_dir(&dir).unwrap().filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().ends_with(".tmp")).count();
+        assert_eq!(leftovers, 0);
+        let mut index = SidecarIndex::scan(&dir, &[FileInfo::new(image.clone())]);
+        assert_eq!(index.rating(&image), rating);
+        assert_eq!(index.keywords(&image), keywords);
+
+        write_keywords(&image, &[]).unwrap();
+        index.refresh(&image);
+        assert!(index.keywords(&image).is_empty());
+        assert_eq!(index.rating(&image), rating);
+    }
+
+    #[test]
+    fn test_keywords_in_packets() {
+        // A self-closing description is opened up to hold the keywords
+        let packet = update_keywords(Some(r#"<x:xmpmeta><rdf:Description rdf:about="" xmp:Rating="2"/></x:xmpmeta>"#), &["dusk".to_string()]);
+        assert_eq!(keywords_from_xmp(&packet), ["dusk"]);
+        assert_eq!(ImageRating::from_xmp(&packet).stars, 2);
+        assert!(packet.contains(r#"xmlns:dc="http://purl.org/dc/elements/1.1/""#));
+
+        let mut index = SidecarIndex::default();
+        index.set_keywords(Path::new("a.jpg"), vec!["Harbour".to_string(), "boats".to_string()]);
+        index.set_keywords(Path::new("b.jpg"), vec!["Harbour".to_string(), "Sunset".to_string()]);
+        assert_eq!(index.known_keywords()[0], ("Harbour".to_string(), 2));
+        assert_eq!(index.suggest("s", &[], 5), ["Sunset", "boats"]);
+        assert_eq!(index.suggest("", &["harbour".to_string()], 5), ["boats", "Sunset"]);
+        assert!(index.has_keywords(Path::new("b.jpg"), &["HARBOUR".to_string()]));
+    }
+}
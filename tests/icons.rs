@@ -0,0 +1,76 @@
+//! Icon loading and fallback behavior against a headless egui context
+
+use eframe::egui;
+use image_previewer::icons::{Icons, IconRenderer, SvgIcons};
+
+/// Run `add_contents` inside a central panel of a headless frame
+fn with_ui(ctx: &egui::Context, mut add_contents: impl FnMut(&mut egui::Ui)) {
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| add_contents(ui));
+    });
+}
+
+#[test]
+fn all_embedded_icons_render() {
+    let ctx = egui::Context::default();
+    for name in SvgIcons::get_available_icons() {
+        let texture = SvgIcons::load_icon(&ctx, name, 16.0, egui::Color32::WHITE)
+            .unwrap_or_else(|| panic!("icon '{}' failed to render", name));
+        assert_eq!(texture.size(), [16, 16]);
+    }
+}
+
+#[test]
+fn invalid_sizes_fall_back_to_default_size() {
+    let ctx = egui::Context::default();
+    for size in [0.0, -8.0, 4096.0] {
+        let texture = SvgIcons::load_icon(&ctx, Icons::CHECK, size, egui::Color32::WHITE)
+            .unwrap_or_else(|| panic!("size {} should fall back instead of failing", size));
+        assert_eq!(texture.size(), [16, 16]);
+    }
+}
+
+#[test]
+fn unknown_names_are_not_found() {
+    let ctx = egui::Context::default();
+    // Includes a typo of an existing icon
+    for name in ["nonexistent-icon", "chekc", ""] {
+        assert!(SvgIcons::load_icon(&ctx, name, 16.0, egui::Color32::WHITE).is_none());
+    }
+
+    let mut renderer = IconRenderer::new();
+    assert!(renderer.get_icon(&ctx, "chekc", 16.0, egui::Color32::WHITE).is_none());
+    assert_eq!(renderer.stats().failed, ["chekc"]);
+    // A repeated request fails from the remembered failure without another render attempt
+    assert!(renderer.get_icon(&ctx, "chekc", 16.0, egui::Color32::WHITE).is_none());
+    let stats = renderer.stats();
+    assert_eq!(stats.failed, ["chekc"]);
+    assert_eq!(stats.counters.misses, 1);
+}
+
+#[test]
+fn renderer_caches_textures() {
+    let ctx = egui::Context::default();
+    let mut renderer = IconRenderer::new();
+    let first = renderer.get_icon(&ctx, Icons::CLOUD, 16.0, egui::Color32::LIGHT_BLUE).map(|t| t.id());
+    let second = renderer.get_icon(&ctx, Icons::CLOUD, 16.0, egui::Color32::LIGHT_BLUE).map(|t| t.id());
+    assert!(first.is_some());
+    assert_eq!(first, second);
+}
+
+#[test]
+fn labels_and_buttons_fall_back_to_text() {
+    let ctx = egui::Context::default();
+    let mut renderer = IconRenderer::new();
+    with_ui(&ctx, |ui| {
+        let ctx = ui.ctx().clone();
+        let known = renderer.icon_label(ui, &ctx, Icons::HELP, 16.0, egui::Color32::GRAY);
+        let unknown = renderer.icon_label(ui, &ctx, "not-an-icon", 16.0, egui::Color32::GRAY);
+        let button = renderer.icon_button(ui, &ctx, "not-an-icon", 16.0, egui::Color32::GRAY, "tooltip");
+
+        assert_eq!(known.rect.size(), egui::vec2(16.0, 16.0));
+        // Unknown icons still take up space as a text placeholder
+        assert!(unknown.rect.width() > 0.0);
+        assert!(button.rect.width() > 0.0);
+    });
+}
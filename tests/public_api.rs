@@ -0,0 +1,373 @@
+//! Public API snapshot
+//!
+//! Documents the crate with rustdoc's JSON output and lists the crate root's
+//! public modules and re-exports, then every item the prelude re-exports:
+//! signatures, public fields, variants, public methods and trait impls. The
+//! list is compared with `tests/public_api.txt`. After a deliberate API
+//! change, regenerate the snapshot and review the diff:
+//!
+//! ```text
+//! UPDATE_PUBLIC_API=1 cargo test --test public_api
+//! ```
+//!
+//! rustdoc's JSON output needs the nightly toolchain the repo already pins.
+//! It is written under `target/public_api`, apart from the test build.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::Value;
+
+use image_previewer::prelude::*;
+
+const SNAPSHOT: &str = "tests/public_api.txt";
+
+/// The crate's rustdoc JSON
+fn rustdoc_json() -> Value {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let target = root.join("target").join("public_api");
+    let output = Command::new(env!("CARGO"))
+        .current_dir(root)
+        .args(["rustdoc", "--lib", "--quiet", "--target-dir"])
+        .arg(&target)
+        .args(["--", "-Z", "unstable-options", "--output-format", "json"])
+        .output()
+        .expect("cargo runs");
+    assert!(output.status.success(), "rustdoc failed:\n{}", String::from_utf8_lossy(&output.stderr));
+    let json = std::fs::read_to_string(target.join("doc").join("image_previewer.json")).expect("rustdoc wrote JSON");
+    serde_json::from_str(&json).expect("valid JSON")
+}
+
+struct Api<'a> {
+    index: &'a Value,
+}
+
+impl Api<'_> {
+    fn item(&self, id: &Value) -> &Value {
+        &self.index[id.to_string()]
+    }
+
+    fn is_public(item: &Value) -> bool {
+        item["visibility"] == "public"
+    }
+
+    fn name(item: &Value) -> &str {
+        item["name"].as_str().unwrap_or_default()
+    }
+
+    fn ids(value: &Value) -> impl Iterator<Item = &Value> {
+        value.as_array().into_iter().flatten()
+    }
+
+    fn join(&self, values: &Value, separator: &str, render: impl Fn(&Value) -> String) -> String {
+        Self::ids(values).map(render).collect::<Vec<_>>().join(separator)
+    }
+
+    fn ty(&self, ty: &Value) -> String {
+        let (kind, inner) = ty.as_object().and_then(|object| object.iter().next()).expect("a type");
+        match kind.as_str() {
+            "resolved_path" => self.path(inner),
+            "generic" | "primitive" => inner.as_str().unwrap_or_default().to_string(),
+            "tuple" => format!("({})", self.join(inner, ", ", |ty| self.ty(ty))),
+            "slice" => format!("[{}]", self.ty(inner)),
+            "array" => format!("[{}; {}]", self.ty(&inner["type"]), inner["len"].as_str().unwrap_or_default()),
+            "borrowed_ref" => {
+                let lifetime = inner["lifetime"].as_str().map(|lifetime| format!("{} ", lifetime)).unwrap_or_default();
+                let mutable = if inner["is_mutable"] == true { "mut " } else { "" };
+                format!("&{}{}{}", lifetime, mutable, self.ty(&inner["type"]))
+            }
+            "raw_pointer" => {
+                let mutable = if inner["is_mutable"] == true { "mut" } else { "const" };
+                format!("*{} {}", mutable, self.ty(&inner["type"]))
+            }
+            "impl_trait" => format!("impl {}", self.bounds(inner)),
+            "dyn_trait" => {
+                let traits = self.join(&inner["traits"], " + ", |poly| self.path(&poly["trait"]));
+                match inner["lifetime"].as_str() {
+                    Some(lifetime) => format!("dyn {} + {}", traits, lifetime),
+                    None => format!("dyn {}", traits),
+                }
+            }
+            "function_pointer" => format!("fn({}){}", self.inputs(&inner["sig"]), self.output(&inner["sig"])),
+            "qualified_path" => format!("<{} as {}>::{}", self.ty(&inner["self_type"]), self.path(&inner["trait"]), inner["name"].as_str().unwrap_or_default()),
+            "infer" => "_".to_string(),
+            other => other.to_string(),
+        }
+    }
+
+    fn path(&self, path: &Value) -> String {
+        let name = path["path"].as_str().unwrap_or_default();
+        let args = &path["args"];
+        if let Some(angle) = args.get("angle_bracketed") {
+            let mut parts: Vec<String> = Self::ids(&angle["args"]).map(|arg| self.generic_arg(arg)).collect();
+            parts.extend(Self::ids(&angle["constraints"]).map(|constraint| {
+                format!("{} = {}", constraint["name"].as_str().unwrap_or_default(), self.ty(&constraint["binding"]["equality"]["type"]))
+            }));
+            if !parts.is_empty() {
+                return format!("{}<{}>", name, parts.join(", "));
+            }
+        } else if let Some(sugar) = args.get("parenthesized") {
+            let output = sugar["output"].is_null().then(String::new).unwrap_or_else(|| format!(" -> {}", self.ty(&sugar["output"])));
+            return format!("{}({}){}", name, self.join(&sugar["inputs"], ", ", |ty| self.ty(ty)), output);
+        }
+        name.to_string()
+    }
+
+    fn generic_arg(&self, arg: &Value) -> String {
+        if let Some(ty) = arg.get("type") {
+            self.ty(ty)
+        } else if let Some(lifetime) = arg.get("lifetime") {
+            lifetime.as_str().unwrap_or_default().to_string()
+        } else if let Some(constant) = arg.get("const") {
+            constant["expr"].as_str().unwrap_or_default().to_string()
+        } else {
+            "_".to_string()
+        }
+    }
+
+    fn bounds(&self, bounds: &Value) -> String {
+        self.join(bounds, " + ", |bound| {
+            if let Some(trait_bound) = bound.get("trait_bound") {
+                let maybe = if trait_bound["modifier"] == "maybe" { "?" } else { "" };
+                format!("{}{}", maybe, self.path(&trait_bound["trait"]))
+            } else {
+                bound["outlives"].as_str().unwrap_or_default().to_string()
+            }
+        })
+    }
+
+    /// `<..>` of the declared generic parameters, leaving out `impl Trait` arguments
+    fn generics(&self, generics: &Value) -> String {
+        let params: Vec<String> = Self::ids(&generics["params"])
+            .filter(|param| param["kind"]["type"]["is_synthetic"] != true)
+            .map(|param| {
+                let name = Self::name(param);
+                let kind = &param["kind"];
+                if let Some(ty) = kind.get("type") {
+                    let bounds = self.bounds(&ty["bounds"]);
+                    if bounds.is_empty() { name.to_string() } else { format!("{}: {}", name, bounds) }
+                } else if let Some(constant) = kind.get("const") {
+                    format!("const {}: {}", name, self.ty(&constant["type"]))
+                } else {
+                    name.to_string()
+                }
+            })
+            .collect();
+        if params.is_empty() { String::new() } else { format!("<{}>", params.join(", ")) }
+    }
+
+    fn where_clause(&self, generics: &Value) -> String {
+        let predicates: Vec<String> = Self::ids(&generics["where_predicates"])
+            .filter_map(|predicate| predicate.get("bound_predicate"))
+            .map(|bound| format!("{}: {}", self.ty(&bound["type"]), self.bounds(&bound["bounds"])))
+            .collect();
+        if predicates.is_empty() { String::new() } else { format!(" where {}", predicates.join(", ")) }
+    }
+
+    fn inputs(&self, sig: &Value) -> String {
+        self.join(&sig["inputs"], ", ", |input| {
+            let name = input[0].as_str().unwrap_or_default();
+            let ty = self.ty(&input[1]);
+            match (name, ty.as_str()) {
+                ("self", "Self") => "self".to_string(),
+                ("self", "&Self") => "&self".to_string(),
+                ("self", "&mut Self") => "&mut self".to_string(),
+                _ => format!("{}: {}", name, ty),
+            }
+        })
+    }
+
+    fn output(&self, sig: &Value) -> String {
+        if sig["output"].is_null() { String::new() } else { format!(" -> {}", self.ty(&sig["output"])) }
+    }
+
+    fn function(&self, item: &Value, visibility: &str) -> String {
+        let function = &item["inner"]["function"];
+        let header = &function["header"];
+        let qualifiers: String = [("is_const", "const "), ("is_async", "async "), ("is_unsafe", "unsafe ")]
+            .iter()
+            .filter(|(flag, _)| header[*flag] == true)
+            .map(|(_, keyword)| *keyword)
+            .collect();
+        format!(
+            "{}{}fn {}{}({}){}{}",
+            visibility,
+            qualifiers,
+            Self::name(item),
+            self.generics(&function["generics"]),
+            self.inputs(&function["sig"]),
+            self.output(&function["sig"]),
+            self.where_clause(&function["generics"]),
+        )
+    }
+
+    fn fields(&self, ids: &Value, public_only: bool) -> Vec<String> {
+        Self::ids(ids)
+            .filter(|id| !id.is_null())
+            .map(|id| self.item(id))
+            .filter(|field| !public_only || Self::is_public(field))
+            .map(|field| (Self::name(field), self.ty(&field["inner"]["struct_field"])))
+            .map(|(name, ty)| if name.parse::<usize>().is_ok() { ty } else { format!("{}: {}", name, ty) })
+            .collect()
+    }
+
+    /// Public methods and constants of inherent impls, and the traits implemented
+    fn impls(&self, ids: &Value, entries: &mut BTreeSet<String>) {
+        for implementation in Self::ids(ids).map(|id| &self.item(id)["inner"]["impl"]) {
+            if implementation["is_synthetic"] == true || !implementation["blanket_impl"].is_null() {
+                continue;
+            }
+            if !implementation["trait"].is_null() {
+                // The marker `derive(PartialEq)` adds for the compiler's own use
+                if implementation["trait"]["path"] == "StructuralPartialEq" {
+                    continue;
+                }
+                entries.insert(format!(
+                    "  impl{} {} for {}",
+                    self.generics(&implementation["generics"]),
+                    self.path(&implementation["trait"]),
+                    self.ty(&implementation["for"]),
+                ));
+                continue;
+            }
+            for member in Self::ids(&implementation["items"]).map(|id| self.item(id)).filter(|member| Self::is_public(member)) {
+                let inner = &member["inner"];
+                if inner.get("function").is_some() {
+                    entries.insert(format!("  {}", self.function(member, "pub ")));
+                } else if let Some(constant) = inner.get("assoc_const") {
+                    entries.insert(format!("  pub const {}: {}", Self::name(member), self.ty(&constant["type"])));
+                }
+            }
+        }
+    }
+
+    fn describe(&self, item: &Value, name: &str) -> Vec<String> {
+        let (kind, inner) = item["inner"].as_object().and_then(|object| object.iter().next()).expect("an item");
+        let mut entries = BTreeSet::new();
+        let header = match kind.as_str() {
+            "function" => self.function(item, "pub "),
+            "constant" => format!("pub const {}: {}", name, self.ty(&inner["type"])),
+            "static" => format!("pub static {}: {}", name, self.ty(&inner["type"])),
+            "type_alias" => format!("pub type {}{} = {}", name, self.generics(&inner["generics"]), self.ty(&inner["type"])),
+            "struct" => {
+                let generics = self.generics(&inner["generics"]);
+                self.impls(&inner["impls"], &mut entries);
+                let struct_kind = &inner["kind"];
+                if let Some(plain) = struct_kind.get("plain") {
+                    entries.extend(self.fields(&plain["fields"], true).into_iter().map(|field| format!("  pub {}", field)));
+                    format!("pub struct {}{}", name, generics)
+                } else if let Some(tuple) = struct_kind.get("tuple") {
+                    format!("pub struct {}{}({})", name, generics, self.fields(tuple, true).join(", "))
+                } else {
+                    format!("pub struct {}{}", name, generics)
+                }
+            }
+            "enum" => {
+                self.impls(&inner["impls"], &mut entries);
+                for variant in Self::ids(&inner["variants"]).map(|id| self.item(id)) {
+                    let variant_kind = &variant["inner"]["variant"]["kind"];
+                    let fields = if let Some(tuple) = variant_kind.get("tuple") {
+                        format!("({})", self.fields(tuple, false).join(", "))
+                    } else if let Some(fields) = variant_kind.get("struct") {
+                        format!(" {{ {} }}", self.fields(&fields["fields"], false).join(", "))
+                    } else {
+                        String::new()
+                    };
+                    entries.insert(format!("  {}{}", Self::name(variant), fields));
+                }
+                format!("pub enum {}{}", name, self.generics(&inner["generics"]))
+            }
+            "trait" => {
+                for member in Self::ids(&inner["items"]).map(|id| self.item(id)) {
+                    let member_inner = &member["inner"];
+                    if member_inner.get("function").is_some() {
+                        entries.insert(format!("  {}", self.function(member, "")));
+                    } else if let Some(constant) = member_inner.get("assoc_const") {
+                        entries.insert(format!("  const {}: {}", Self::name(member), self.ty(&constant["type"])));
+                    } else if member_inner.get("assoc_type").is_some() {
+                        entries.insert(format!("  type {}", Self::name(member)));
+                    }
+                }
+                let bounds = self.bounds(&inner["bounds"]);
+                let bounds = if bounds.is_empty() { bounds } else { format!(": {}", bounds) };
+                format!("pub trait {}{}{}", name, self.generics(&inner["generics"]), bounds)
+            }
+            other => panic!("the prelude re-exports a {}, which the snapshot doesn't describe yet", other),
+        };
+        std::iter::once(header).chain(entries).collect()
+    }
+
+    fn module(&self, item: &Value) -> impl Iterator<Item = &Value> {
+        Self::ids(&item["inner"]["module"]["items"]).map(|id| self.item(id)).filter(|item| Self::is_public(item))
+    }
+}
+
+fn current_api() -> String {
+    let json = rustdoc_json();
+    let api = Api { index: &json["index"] };
+    let root = api.item(&json["root"]);
+
+    let mut out = String::from("# Crate root\n");
+    let mut prelude = None;
+    for item in api.module(root) {
+        if item["inner"].get("module").is_some() {
+            out.push_str(&format!("pub mod {}\n", Api::name(item)));
+            if Api::name(item) == "prelude" {
+                prelude = Some(item);
+            }
+        } else if let Some(re_export) = item["inner"].get("use") {
+            out.push_str(&format!("pub use {}\n", re_export["source"].as_str().unwrap_or_default()));
+        }
+    }
+    for re_export in api.module(prelude.expect("a prelude module")).filter_map(|item| item["inner"].get("use")) {
+        let name = re_export["name"].as_str().unwrap_or_default();
+        let source = re_export["source"].as_str().unwrap_or_default();
+        let module = source.trim_start_matches("crate::").rsplit_once("::").map(|(module, _)| module).unwrap_or_default();
+        out.push_str(&format!("\n# prelude::{} (from {})\n", name, module));
+        for entry in api.describe(api.item(&re_export["id"]), name) {
+            out.push_str(&entry);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[test]
+fn public_api_matches_snapshot() {
+    let current = current_api();
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(SNAPSHOT);
+    if std::env::var_os("UPDATE_PUBLIC_API").is_some() {
+        std::fs::write(&path, &current).unwrap();
+        return;
+    }
+    let snapshot = std::fs::read_to_string(&path).unwrap_or_default();
+    if snapshot != current {
+        let removed: Vec<&str> = snapshot.lines().filter(|line| !current.lines().any(|l| l == *line)).collect();
+        let added: Vec<&str> = current.lines().filter(|line| !snapshot.lines().any(|l| l == *line)).collect();
+        panic!(
+            "the public API changed.\nRemoved:\n{}\nAdded:\n{}\n\
+             If this is deliberate, run `UPDATE_PUBLIC_API=1 cargo test --test public_api` and commit {}",
+            removed.join("\n"),
+            added.join("\n"),
+            SNAPSHOT,
+        );
+    }
+}
+
+#[test]
+fn prelude_covers_typical_use() {
+    let settings = ImageLoadingSettings::default()
+        .skip_large_images(false)
+        .auto_scale_large_images(true)
+        .max_file_size_mb(Some(64))
+        .photo_zoom(OpeningZoom::Fit);
+    assert_eq!(settings.fit_mode, FitMode::Auto);
+    assert_eq!(settings.icon_zoom, OpeningZoom::IntegerMultiple);
+    assert_eq!(settings.max_file_size_mb, Some(64));
+
+    let missing = std::path::PathBuf::from("missing-image.png");
+    assert!(!matches!(get_file_locality_status(&missing), FileLocalityStatus::OnDemand));
+    assert!(decode_raster_image(&missing, &settings, false).is_err());
+    assert_eq!(SystemPerformanceCategory::from_score(0), SystemPerformanceCategory::LowPower);
+}